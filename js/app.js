@@ -13,6 +13,11 @@ let operation = null;
 let waitingForOperand = false;
 let unifiedHistory = [];
 
+// Render a CalcErrorJs (or anything else thrown) for the display
+function errorText(error) {
+    return 'Error: ' + (error && error.message !== undefined ? error.message : error);
+}
+
 // Initialize WASM
 async function initWasm() {
     await init();
@@ -93,7 +98,7 @@ window.setOperation = function(op) {
             
             currentInput = result.toString();
         } catch (error) {
-            currentInput = 'Error: ' + error;
+            currentInput = errorText(error);
         }
         updateDisplay();
         return;
@@ -157,7 +162,7 @@ window.calculate = function() {
         operation = null;
         waitingForOperand = true;
     } catch (error) {
-        currentInput = 'Error: ' + error;
+        currentInput = errorText(error);
     }
     
     updateDisplay();
@@ -220,7 +225,7 @@ window.calculateFactorial = function() {
             
             currentInput = result.toString();
         } catch (error) {
-            currentInput = 'Error: ' + error;
+            currentInput = errorText(error);
         }
     }
     updateDisplay();
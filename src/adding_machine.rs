@@ -0,0 +1,267 @@
+//! Ten-key adding-machine mode.
+//!
+//! Amounts are entered with `+` or `-` and accumulate into a running
+//! subtotal. `subtotal()` reads it without ending the run (the `S` key);
+//! `total(clear)` finalizes it (the `T` key), starting a new run when asked.
+//! Entries go through an inner [`Calculator`], so they land in its history
+//! and print with [`Calculator::format_tape`].
+//!
+//! Like a business calculator, results are shown through the rounding
+//! switch (F/CUT/5/4) at the selected number of decimals (0-4). Add mode
+//! fixes two decimals and reads whole-number entries as cents, so `1234 +`
+//! enters 12.34. Rounding applies to what is returned; the subtotal itself
+//! is kept unrounded.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::rounding::{round_to, RoundingMode};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+const MAX_DECIMALS: u32 = 4;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone)]
+pub struct AddingMachine {
+    calc: Calculator,
+    // Amounts entered since the last clearing total.
+    items: u32,
+    rounding: RoundingMode,
+    decimals: u32,
+    add_mode: bool,
+}
+
+impl Default for AddingMachine {
+    fn default() -> Self {
+        AddingMachine {
+            calc: Calculator::new(),
+            items: 0,
+            rounding: RoundingMode::Float,
+            decimals: 2,
+            add_mode: false,
+        }
+    }
+}
+
+impl AddingMachine {
+    fn display(&self, value: f64) -> f64 {
+        let places = if self.add_mode { 2 } else { self.decimals };
+        round_to(value, places, self.rounding)
+    }
+
+    fn entry(&self, amount: f64) -> f64 {
+        if self.add_mode && amount.fract() == 0.0 {
+            amount / 100.0
+        } else {
+            amount
+        }
+    }
+
+    pub fn set_decimals(&mut self, places: u32) -> Result<(), CalcError> {
+        if places > MAX_DECIMALS {
+            return Err(CalcError::InvalidArgument(format!(
+                "Decimal places must be 0-{}, got {}",
+                MAX_DECIMALS, places
+            )));
+        }
+        self.decimals = places;
+        Ok(())
+    }
+
+    /// Subtotal divided by item count (the AVG key). Minus entries count as
+    /// items, as on desktop printing calculators.
+    pub fn item_average(&self) -> Result<f64, CalcError> {
+        if self.items == 0 {
+            return Err(CalcError::InvalidArgument("No items entered".to_string()));
+        }
+        Ok(self.display(self.calc.get_value() / self.items as f64))
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl AddingMachine {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> AddingMachine {
+        AddingMachine::default()
+    }
+
+    /// Adds `amount` to the subtotal and returns the new subtotal.
+    pub fn plus(&mut self, amount: f64) -> f64 {
+        self.items += 1;
+        let subtotal = self.calc.add(self.entry(amount));
+        self.display(subtotal)
+    }
+
+    /// Subtracts `amount` from the subtotal and returns the new subtotal.
+    pub fn minus(&mut self, amount: f64) -> f64 {
+        self.items += 1;
+        let subtotal = self.calc.subtract(self.entry(amount));
+        self.display(subtotal)
+    }
+
+    /// The running subtotal; does not end the run.
+    pub fn subtotal(&self) -> f64 {
+        self.display(self.calc.get_value())
+    }
+
+    /// Finalizes the run and returns its total. With `clear`, the subtotal
+    /// and item count reset so the next entry starts a new run.
+    pub fn total(&mut self, clear: bool) -> f64 {
+        let total = self.display(self.calc.get_value());
+        if clear {
+            self.calc.clear();
+            self.items = 0;
+        }
+        total
+    }
+
+    /// Amounts entered since the last clearing total (the COUNT key).
+    pub fn item_count(&self) -> u32 {
+        self.items
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = item_average)]
+    pub fn item_average_js(&self) -> Result<f64, CalcErrorJs> {
+        self.item_average().map_err(CalcErrorJs::from)
+    }
+
+    pub fn set_rounding(&mut self, mode: RoundingMode) {
+        self.rounding = mode;
+    }
+
+    pub fn rounding(&self) -> RoundingMode {
+        self.rounding
+    }
+
+    /// Decimal selector, 0-4. Ignored while add mode is on.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_decimals)]
+    pub fn set_decimals_js(&mut self, places: u32) -> Result<(), CalcErrorJs> {
+        self.set_decimals(places)
+            .map_err(|e| CalcErrorJs::new(e, Some(places as f64)))
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    pub fn set_add_mode(&mut self, on: bool) {
+        self.add_mode = on;
+    }
+
+    pub fn add_mode(&self) -> bool {
+        self.add_mode
+    }
+
+    /// The underlying calculator, for its history and tape.
+    pub fn calculator(&self) -> Calculator {
+        self.calc.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Operation;
+
+    #[test]
+    fn test_running_subtotal_and_total() {
+        let mut machine = AddingMachine::new();
+        assert_eq!(machine.plus(10.0), 10.0);
+        assert_eq!(machine.plus(5.5), 15.5);
+        assert_eq!(machine.minus(3.0), 12.5);
+        assert_eq!(machine.subtotal(), 12.5);
+        assert_eq!(machine.item_count(), 3);
+        assert_eq!(machine.total(false), 12.5);
+        assert_eq!(machine.plus(1.0), 13.5);
+        assert_eq!(machine.item_count(), 4);
+    }
+
+    #[test]
+    fn test_clearing_total_starts_a_new_run() {
+        let mut machine = AddingMachine::new();
+        machine.plus(7.0);
+        machine.plus(8.0);
+        assert_eq!(machine.total(true), 15.0);
+        assert_eq!(machine.subtotal(), 0.0);
+        assert_eq!(machine.item_count(), 0);
+        assert_eq!(machine.plus(2.0), 2.0);
+    }
+
+    #[test]
+    fn test_item_average() {
+        let mut machine = AddingMachine::new();
+        assert!(matches!(
+            machine.item_average(),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        machine.plus(10.0);
+        machine.plus(20.0);
+        machine.minus(3.0);
+        assert_eq!(machine.item_count(), 3);
+        assert_eq!(machine.item_average(), Ok(9.0));
+        machine.total(false);
+        assert_eq!(machine.item_average(), Ok(9.0));
+        machine.total(true);
+        assert!(machine.item_average().is_err());
+    }
+
+    #[test]
+    fn test_rounding_switch() {
+        let mut machine = AddingMachine::new();
+        machine.plus(10.0);
+        machine.minus(0.005);
+        assert_eq!(machine.subtotal(), 9.995);
+        machine.set_rounding(RoundingMode::Cut);
+        assert_eq!(machine.subtotal(), 9.99);
+        machine.set_rounding(RoundingMode::HalfUp);
+        assert_eq!(machine.subtotal(), 10.0);
+        machine.set_decimals(3).unwrap();
+        assert_eq!(machine.total(false), 9.995);
+        assert!(machine.set_decimals(5).is_err());
+        assert_eq!(machine.decimals(), 3);
+    }
+
+    #[test]
+    fn test_rounding_applies_to_average() {
+        let mut machine = AddingMachine::new();
+        machine.set_rounding(RoundingMode::HalfUp);
+        machine.set_decimals(0).unwrap();
+        machine.plus(1.0);
+        machine.plus(2.0);
+        assert_eq!(machine.item_average(), Ok(2.0));
+        machine.set_rounding(RoundingMode::Cut);
+        assert_eq!(machine.item_average(), Ok(1.0));
+    }
+
+    #[test]
+    fn test_add_mode_enters_cents() {
+        let mut machine = AddingMachine::new();
+        machine.set_add_mode(true);
+        machine.set_rounding(RoundingMode::HalfUp);
+        machine.set_decimals(0).unwrap();
+        assert_eq!(machine.plus(1234.0), 12.34);
+        assert_eq!(machine.plus(0.5), 12.84);
+        assert_eq!(machine.minus(4.0), 12.8);
+        machine.set_add_mode(false);
+        assert_eq!(machine.subtotal(), 13.0);
+    }
+
+    #[test]
+    fn test_entries_are_recorded_in_history() {
+        let mut machine = AddingMachine::new();
+        machine.plus(4.0);
+        machine.minus(1.0);
+        machine.total(true);
+        let history = machine.calculator().history_range(0, 10);
+        let ops: Vec<Operation> = history.iter().map(|h| h.operation.clone()).collect();
+        assert_eq!(
+            ops,
+            vec![Operation::Add, Operation::Subtract, Operation::Clear]
+        );
+        assert_eq!(machine.calculator().check_invariants(), Ok(()));
+    }
+}
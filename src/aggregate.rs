@@ -0,0 +1,132 @@
+//! Whole-array aggregates in one call.
+//!
+//! Summing a 100k-element dataset through `Calculator::add` crosses the
+//! WASM boundary and records a history entry per element. These functions
+//! take the data as one `Float64Array`, copied in once, and loop inside
+//! WASM instead.
+//!
+//! Sums are compensated (Neumaier), so adding many values of mixed
+//! magnitude loses no more precision than adding a few. NaN anywhere makes
+//! the result NaN, as it would with repeated `add` calls.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+// A running sum that carries the low-order bits plain `+=` drops.
+#[derive(Default)]
+struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    fn add(&mut self, value: f64) -> f64 {
+        let t = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - t) + value;
+        } else {
+            self.compensation += (value - t) + self.sum;
+        }
+        self.sum = t;
+        self.total()
+    }
+
+    fn total(&self) -> f64 {
+        // An infinite or NaN sum makes the compensation NaN; report the
+        // sum as is.
+        if self.sum.is_finite() {
+            self.sum + self.compensation
+        } else {
+            self.sum
+        }
+    }
+}
+
+/// The sum of `values`; 0 for an empty array.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn sum_array(values: &[f64]) -> f64 {
+    let mut sum = CompensatedSum::default();
+    for &v in values {
+        sum.add(v);
+    }
+    sum.total()
+}
+
+/// The product of `values`; 1 for an empty array.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn product_array(values: &[f64]) -> f64 {
+    values.iter().product()
+}
+
+/// The running sums of `values`: element `i` is the sum of the first
+/// `i + 1` values.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn cumulative_sum_array(values: &[f64]) -> Vec<f64> {
+    let mut sum = CompensatedSum::default();
+    values.iter().map(|&v| sum.add(v)).collect()
+}
+
+/// The smallest and largest of `values`, or NaN for both if any value is
+/// NaN.
+pub fn min_max_array(values: &[f64]) -> Result<(f64, f64), CalcError> {
+    let Some(&first) = values.first() else {
+        return Err(CalcError::InvalidArgument("No data".to_string()));
+    };
+    let mut range = (first, first);
+    for &v in values {
+        if v.is_nan() {
+            return Ok((f64::NAN, f64::NAN));
+        }
+        range = (range.0.min(v), range.1.max(v));
+    }
+    Ok(range)
+}
+
+/// `[min, max]` as a `Float64Array`; see [`min_max_array`].
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = min_max_array)]
+pub fn min_max_array_js(values: &[f64]) -> Result<Vec<f64>, CalcErrorJs> {
+    let (min, max) = min_max_array(values).map_err(CalcErrorJs::from)?;
+    Ok(vec![min, max])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sum_and_product() {
+        assert_eq!(sum_array(&[]), 0.0);
+        assert_eq!(product_array(&[]), 1.0);
+        assert_eq!(sum_array(&[1.5, 2.5, -1.0]), 3.0);
+        assert_eq!(product_array(&[1.5, 2.0, -4.0]), -12.0);
+        assert_eq!(sum_array(&[1.0, 1e100, 1.0, -1e100]), 2.0);
+        assert_eq!(sum_array(&vec![0.1; 100_000]), 10_000.0);
+        assert_eq!(sum_array(&[1.0, f64::INFINITY]), f64::INFINITY);
+        assert!(sum_array(&[1.0, f64::NAN]).is_nan());
+    }
+
+    #[test]
+    fn test_cumulative_sum() {
+        assert!(cumulative_sum_array(&[]).is_empty());
+        assert_eq!(
+            cumulative_sum_array(&[0.1, 0.2, 0.3]),
+            vec![0.1, 0.30000000000000004, 0.6]
+        );
+        let sums = cumulative_sum_array(&vec![0.1; 100_000]);
+        assert_eq!(sums[99_999], sum_array(&vec![0.1; 100_000]));
+    }
+
+    #[test]
+    fn test_min_max() {
+        assert_eq!(min_max_array(&[3.0, -1.0, 7.5, 0.0]), Ok((-1.0, 7.5)));
+        assert_eq!(min_max_array(&[2.0]), Ok((2.0, 2.0)));
+        assert!(min_max_array(&[]).is_err());
+        let (min, max) = min_max_array(&[1.0, f64::NAN]).unwrap();
+        assert!(min.is_nan() && max.is_nan());
+    }
+}
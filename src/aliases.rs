@@ -0,0 +1,340 @@
+//! Alternative spellings for operators and functions.
+//!
+//! Text pasted from other apps uses other symbols: `×` and `÷`, `**` for
+//! powers, `mod` for remainders, `mean` for `avg`. An [`Aliases`] table maps
+//! such spellings to the canonical ones, and [`Calculator::evaluate`]
+//! rewrites its input through the calculator's table before parsing, so
+//! `7 × 6 ÷ 2` evaluates without preprocessing.
+//!
+//! Word aliases (`mod`, `x`) only match whole words. A word operator alias
+//! also only matches where an operator can go, after an operand, so `mod`
+//! can be both an infix alias for `%` and the built-in `mod(a, b)`. Syntax
+//! error positions still point into the original text.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{CONSTANTS, FUNCTIONS};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// The operators an alias can stand for.
+pub const CANONICAL_OPERATORS: &[char] = &['+', '-', '*', '/', '%', '^'];
+
+/// The spellings [`Aliases::add_common`] registers: `×`, `·`, `÷`, `−`
+/// (the Unicode minus sign), `**` and `mod`.
+pub const COMMON_OPERATOR_ALIASES: &[(&str, char)] = &[
+    ("×", '*'),
+    ("·", '*'),
+    ("÷", '/'),
+    ("−", '-'),
+    ("**", '^'),
+    ("mod", '%'),
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aliases {
+    operators: BTreeMap<String, char>,
+    functions: BTreeMap<String, String>,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(is_word)
+}
+
+impl Aliases {
+    /// Makes `alias` stand for `operator`, one of `+ - * / % ^`. An alias
+    /// may not contain whitespace, digits or the characters `( ) , . ;`.
+    pub fn add_operator(&mut self, alias: &str, operator: &str) -> Result<(), CalcError> {
+        let canonical = match operator.trim().chars().collect::<Vec<_>>()[..] {
+            [c] if CANONICAL_OPERATORS.contains(&c) => c,
+            _ => {
+                return Err(invalid(format!(
+                    "Unknown operator '{}'; expected one of + - * / % ^",
+                    operator
+                )))
+            }
+        };
+        if alias.is_empty()
+            || alias
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_ascii_digit() || "(),.;".contains(c))
+        {
+            return Err(invalid(format!("Invalid operator alias '{}'", alias)));
+        }
+        if alias.chars().any(is_word) && !is_identifier(alias) {
+            return Err(invalid(format!(
+                "Operator alias '{}' must be a word or symbols only",
+                alias
+            )));
+        }
+        self.functions.remove(alias);
+        self.operators.insert(alias.to_string(), canonical);
+        Ok(())
+    }
+
+    /// Makes the word `alias` stand for the built-in `function`. Built-in
+    /// function and constant names cannot be redefined.
+    pub fn add_function(&mut self, alias: &str, function: &str) -> Result<(), CalcError> {
+        if !FUNCTIONS.iter().any(|(f, _, _)| *f == function) {
+            return Err(crate::expr::unknown_identifier(
+                function,
+                FUNCTIONS.iter().map(|(f, _, _)| *f),
+            ));
+        }
+        if !is_identifier(alias) {
+            return Err(invalid(format!("Invalid function alias '{}'", alias)));
+        }
+        let builtin = FUNCTIONS.iter().map(|(f, _, _)| *f);
+        if builtin
+            .chain(CONSTANTS.iter().map(|(c, _)| *c))
+            .any(|name| name == alias)
+        {
+            return Err(invalid(format!("'{}' is already a built-in name", alias)));
+        }
+        self.operators.remove(alias);
+        self.functions
+            .insert(alias.to_string(), function.to_string());
+        Ok(())
+    }
+
+    /// Registers [`COMMON_OPERATOR_ALIASES`].
+    pub fn add_common(&mut self) {
+        for (alias, operator) in COMMON_OPERATOR_ALIASES {
+            self.operators.insert(alias.to_string(), *operator);
+        }
+    }
+
+    /// Removes `alias`, returning whether it was registered.
+    pub fn remove(&mut self, alias: &str) -> bool {
+        self.operators.remove(alias).is_some() | self.functions.remove(alias).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.operators.clear();
+        self.functions.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty() && self.functions.is_empty()
+    }
+
+    /// `(alias, canonical)` pairs, operators first.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let operators = self
+            .operators
+            .iter()
+            .map(|(a, op)| (a.clone(), op.to_string()));
+        let functions = self.functions.iter().map(|(a, f)| (a.clone(), f.clone()));
+        operators.chain(functions).collect()
+    }
+
+    /// Rewrites `src` into canonical spellings. The second value maps each
+    /// character of the result to the character of `src` it came from.
+    pub fn rewrite(&self, src: &str) -> (String, Vec<usize>) {
+        let chars: Vec<char> = src.chars().collect();
+        let mut out = String::new();
+        let mut origins = Vec::new();
+        let mut i = 0;
+        // Whether the last non-space character ended an operand.
+        let mut after_operand = false;
+        while i < chars.len() {
+            let c = chars[i];
+            if let Some((length, replacement)) = self.alias_at(&chars, i, after_operand) {
+                out.push_str(&replacement);
+                origins.extend(std::iter::repeat_n(i, replacement.chars().count()));
+                after_operand = false;
+                i += length;
+                continue;
+            }
+            out.push(c);
+            origins.push(i);
+            if !c.is_whitespace() {
+                after_operand = is_word(c) || matches!(c, '.' | ')' | '!');
+            }
+            i += 1;
+        }
+        (out, origins)
+    }
+
+    // The longest alias starting at `chars[i]`, as its length and the text
+    // it stands for.
+    fn alias_at(&self, chars: &[char], i: usize, after_operand: bool) -> Option<(usize, String)> {
+        let matches = |alias: &str| {
+            let alias: Vec<char> = alias.chars().collect();
+            let end = i + alias.len();
+            if chars.get(i..end) != Some(&alias[..]) {
+                return false;
+            }
+            // Words only match whole words.
+            let word = is_word(alias[0]);
+            !(word
+                && (i > 0 && is_word(chars[i - 1]) || chars.get(end).is_some_and(|c| is_word(*c))))
+        };
+        let operator = self
+            .operators
+            .iter()
+            .filter(|(alias, _)| after_operand || !alias.starts_with(is_word))
+            .filter(|(alias, _)| matches(alias))
+            .map(|(alias, op)| (alias.chars().count(), op.to_string()));
+        let function = self
+            .functions
+            .iter()
+            .filter(|(alias, _)| matches(alias))
+            .map(|(alias, f)| (alias.chars().count(), f.clone()));
+        operator.chain(function).max_by_key(|(length, _)| *length)
+    }
+}
+
+impl Calculator {
+    pub fn add_operator_alias(&mut self, alias: &str, operator: &str) -> Result<(), CalcError> {
+        self.aliases.add_operator(alias, operator)
+    }
+
+    pub fn add_function_alias(&mut self, alias: &str, function: &str) -> Result<(), CalcError> {
+        self.aliases.add_function(alias, function)
+    }
+
+    /// `src` in canonical spellings, with syntax errors in the rewritten
+    /// text moved back to where they are in `src`.
+    pub(crate) fn unalias<T>(
+        &self,
+        src: &str,
+        then: impl FnOnce(&str) -> Result<T, CalcError>,
+    ) -> Result<T, CalcError> {
+        if self.aliases.is_empty() {
+            return then(src);
+        }
+        let (rewritten, origins) = self.aliases.rewrite(src);
+        then(&rewritten).map_err(|e| match e {
+            CalcError::Syntax { message, position } => CalcError::Syntax {
+                message,
+                position: origins
+                    .get(position)
+                    .copied()
+                    .unwrap_or(src.chars().count()),
+            },
+            e => e,
+        })
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add_operator_alias)]
+    pub fn add_operator_alias_js(
+        &mut self,
+        alias: &str,
+        operator: &str,
+    ) -> Result<(), CalcErrorJs> {
+        self.add_operator_alias(alias, operator)
+            .map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add_function_alias)]
+    pub fn add_function_alias_js(
+        &mut self,
+        alias: &str,
+        function: &str,
+    ) -> Result<(), CalcErrorJs> {
+        self.add_function_alias(alias, function)
+            .map_err(CalcErrorJs::from)
+    }
+
+    /// Registers `×`, `·`, `÷`, `−`, `**` and `mod`.
+    pub fn add_common_aliases(&mut self) {
+        self.aliases.add_common();
+    }
+
+    /// Removes an operator or function alias, returning whether it existed.
+    pub fn remove_alias(&mut self, alias: &str) -> bool {
+        self.aliases.remove(alias)
+    }
+
+    pub fn clear_aliases(&mut self) {
+        self.aliases.clear();
+    }
+
+    /// The registered aliases as `alias=canonical` strings, operators first.
+    pub fn aliases(&self) -> Vec<String> {
+        self.aliases
+            .entries()
+            .into_iter()
+            .map(|(alias, canonical)| format!("{}={}", alias, canonical))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_aliases() {
+        let mut calc = Calculator::new();
+        calc.add_common_aliases();
+        assert_eq!(calc.evaluate("7 × 6 ÷ 2"), Ok(21.0));
+        assert_eq!(calc.evaluate("2 ** 3 ** 2"), Ok(512.0));
+        assert_eq!(calc.evaluate("−3 · 2"), Ok(-6.0));
+        // `mod` is infix after an operand and the function otherwise.
+        assert_eq!(calc.evaluate("17 mod 5"), Ok(2.0));
+        assert_eq!(calc.evaluate("mod(17, 5) + (9)mod 4"), Ok(3.0));
+        calc.add_operator_alias("x", "*").unwrap();
+        calc.set_variable("x", 4.0).unwrap();
+        assert_eq!(calc.evaluate("x x 2"), Ok(8.0));
+        assert!(calc.add_operator_alias("plus", "add").is_err());
+        assert!(calc.add_operator_alias("2x", "*").is_err());
+        assert!(calc.add_operator_alias("a+", "+").is_err());
+        assert!(calc.remove_alias("x"));
+        assert!(!calc.remove_alias("x"));
+    }
+
+    #[test]
+    fn test_function_aliases() {
+        let mut calc = Calculator::new();
+        calc.add_function_alias("mean", "avg").unwrap();
+        calc.add_function_alias("root", "sqrt").unwrap();
+        assert_eq!(calc.evaluate("mean(1, 2, 6) + root(16)"), Ok(7.0));
+        // Only whole words are rewritten.
+        assert!(matches!(
+            calc.evaluate("meant(1)"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(calc.add_function_alias("sum", "avg").is_err());
+        assert!(calc.add_function_alias("pi", "avg").is_err());
+        assert!(calc.add_function_alias("average", "median").is_err());
+        assert_eq!(calc.aliases(), ["mean=avg", "root=sqrt"]);
+        calc.clear_aliases();
+        assert!(calc.aliases().is_empty());
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let mut calc = Calculator::new();
+        calc.add_common_aliases();
+        // `2 ** ** 3` in the original text: the second `**` is at 5.
+        assert!(matches!(
+            calc.evaluate("2 ** ** 3"),
+            Err(CalcError::Syntax { position: 5, .. })
+        ));
+        let (rewritten, origins) = calc.aliases.rewrite("a×b");
+        assert_eq!(rewritten, "a*b");
+        assert_eq!(origins, [0, 1, 2]);
+    }
+}
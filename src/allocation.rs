@@ -0,0 +1,145 @@
+//! Keeping basic operations free of allocation.
+//!
+//! In a long session every keystroke that allocates adds to the garbage
+//! the page eventually pauses to collect. Basic operations (`add`,
+//! `divide`, `sqrt`, `set_value`, `clear`, ...) allocate only to record
+//! history, and there are two ways to stop that:
+//!
+//! - [`Calculator::set_history_enabled`]`(false)` stops recording entries
+//!   altogether, keeping those already recorded. Session totals and
+//!   history listeners only see recorded entries, so they pause too;
+//!   change listeners, which allocate nothing, carry on.
+//! - [`Calculator::reserve_history`] makes room for entries up front. With
+//!   a history limit, a full history evicts its oldest entry into the slot
+//!   the new one takes, so reserving the limit is enough for the rest of
+//!   the session.
+//!
+//! Either way an operation still allocates to name its author, sync it to
+//! other replicas, time it or record it into a macro, when those are on.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::Calculator;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Starts or stops recording operations in the history.
+    pub fn set_history_enabled(&mut self, enabled: bool) {
+        self.history_enabled = enabled;
+    }
+
+    pub fn is_history_enabled(&self) -> bool {
+        self.history_enabled
+    }
+
+    /// Makes room for at least `additional` more entries, capped by the
+    /// history limit if there is one.
+    pub fn reserve_history(&mut self, additional: usize) {
+        let room = match self.history_limit {
+            Some(limit) => additional.min(limit.saturating_sub(self.history.len())),
+            None => additional,
+        };
+        self.history.reserve(room);
+    }
+
+    /// How many entries the history holds before it next allocates.
+    pub fn history_capacity(&self) -> usize {
+        self.history.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    use super::*;
+
+    // Counts this thread's allocations while `allocations` runs, so tests
+    // on other threads don't interfere.
+    struct Counting;
+
+    thread_local! {
+        static COUNT: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    fn count() {
+        let _ = COUNT.try_with(|c| c.set(c.get().map(|n| n + 1)));
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count();
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count();
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    fn allocations(run: impl FnOnce()) -> usize {
+        COUNT.with(|c| c.set(Some(0)));
+        run();
+        COUNT.with(|c| c.take()).unwrap_or(0)
+    }
+
+    fn keystrokes(calc: &mut Calculator) {
+        for i in 0..1000 {
+            calc.set_value(i as f64);
+            calc.add(2.0);
+            calc.multiply(3.0);
+            calc.subtract(1.0);
+            calc.divide(4.0).unwrap();
+            calc.power(2.0);
+            calc.sqrt().unwrap();
+            calc.clear();
+        }
+    }
+
+    #[test]
+    fn test_disabled_history_does_not_allocate() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.set_history_enabled(false);
+        assert!(!calc.is_history_enabled());
+        assert_eq!(allocations(|| keystrokes(&mut calc)), 0);
+        assert_eq!(calc.history_count(), 1);
+        assert_eq!(calc.session_summary().total_operations, 1);
+
+        let changes = std::rc::Rc::new(Cell::new(0));
+        let sink = changes.clone();
+        calc.on_change(move |_, _| sink.set(sink.get() + 1));
+        assert_eq!(allocations(|| keystrokes(&mut calc)), 0);
+        assert_eq!(changes.get(), 8000);
+        calc.clear_listeners();
+
+        calc.set_history_enabled(true);
+        calc.add(1.0);
+        assert_eq!(calc.history_count(), 2);
+        // An unbounded history grows as it goes.
+        assert!(allocations(|| keystrokes(&mut calc)) > 0);
+    }
+
+    #[test]
+    fn test_full_history_reuses_its_slots() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(64);
+        calc.reserve_history(1000);
+        assert!(calc.history_capacity() >= 64);
+        // The first of each operation names its session counter.
+        keystrokes(&mut calc);
+        assert_eq!(allocations(|| keystrokes(&mut calc)), 0);
+        assert_eq!(calc.history_count(), 64);
+        assert!(calc.check_invariants().is_ok());
+    }
+}
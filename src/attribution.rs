@@ -0,0 +1,114 @@
+//! Who performed each operation, for shared tapes.
+//!
+//! [`Calculator::set_author`] names the person at the keys, and every entry
+//! recorded from then on carries that name in its `author` field until the
+//! author changes or is cleared. Merged and synced tapes keep each entry's
+//! author, so a combined tape still shows who did what:
+//!
+//! ```json
+//! { "operation": "Add", "operand2": 15, "author": "alice", ... }
+//! ```
+//!
+//! Entries recorded without an author have no `author` field. Replaying or
+//! editing the history keeps the authors of the entries it recomputes.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// The longest author name, in characters.
+pub const MAX_AUTHOR_CHARS: usize = 64;
+
+impl Calculator {
+    /// Attributes new entries to `name`; an empty name stops attributing
+    /// them.
+    pub fn set_author(&mut self, name: &str) -> Result<(), CalcError> {
+        let name = name.trim();
+        if name.chars().count() > MAX_AUTHOR_CHARS || name.chars().any(char::is_control) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Author must be at most {} printable characters",
+                MAX_AUTHOR_CHARS
+            )));
+        }
+        self.author = (!name.is_empty()).then(|| name.to_string());
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_author)]
+    pub fn set_author_js(&mut self, name: &str) -> Result<(), CalcErrorJs> {
+        self.set_author(name).map_err(CalcErrorJs::from)
+    }
+
+    pub fn author(&self) -> Option<String> {
+        self.author.clone()
+    }
+
+    /// The author of every retained entry, oldest first, matching
+    /// [`Calculator::history_series`]; empty where no author was set.
+    pub fn history_authors(&self) -> Vec<String> {
+        self.history
+            .iter()
+            .map(|entry| entry.author.clone().unwrap_or_default())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::merge_histories;
+
+    #[test]
+    fn test_entries_carry_author() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.set_author(" alice ").unwrap();
+        assert_eq!(calc.author().as_deref(), Some("alice"));
+        calc.add(2.0);
+        calc.set_author("").unwrap();
+        calc.add(3.0);
+        assert_eq!(calc.history_authors(), ["", "alice", ""]);
+
+        let json = serde_json::to_value(&calc.history).unwrap();
+        assert_eq!(json[1]["author"], "alice");
+        assert!(json[0].get("author").is_none());
+        assert!(calc
+            .canonical_state_string()
+            .contains("  1: Add 1.0 2.0 -> 3.0 by alice\n"));
+
+        calc.replay_history(10.0).unwrap();
+        assert_eq!(calc.history_authors(), ["", "alice", ""]);
+        assert!(calc.set_author(&"x".repeat(65)).is_err());
+        assert!(calc.set_author("a\nb").is_err());
+    }
+
+    #[test]
+    fn test_authors_survive_sync_and_merge() {
+        let mut alice = Calculator::new();
+        alice.set_replica_id("a").unwrap();
+        alice.set_author("alice").unwrap();
+        alice.add(5.0);
+        let mut bob = Calculator::new();
+        bob.set_replica_id("b").unwrap();
+        bob.set_author("bob").unwrap();
+        bob.apply_delta(&alice.state_delta_since(&bob.version_vector()).unwrap())
+            .unwrap();
+        bob.add(1.0);
+        assert_eq!(bob.history_authors(), ["alice", "bob"]);
+
+        // The same step by two people at the same time is two steps.
+        let mut other = alice.clone();
+        other.set_author("carol").unwrap();
+        other.history.back_mut().unwrap().author = Some("carol".to_string());
+        let merged = merge_histories(&alice, &other).unwrap();
+        assert_eq!(merged.history_authors(), ["alice", "carol"]);
+        assert_eq!(merged.get_value(), 10.0);
+    }
+}
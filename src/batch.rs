@@ -0,0 +1,300 @@
+//! Many operations in one call.
+//!
+//! Replaying a long keystroke sequence one method call at a time pays the
+//! wasm-bindgen boundary cost per key. [`Calculator::apply_batch`] takes the
+//! whole sequence as one array instead.
+
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::keypad::Keypad;
+use crate::quota::RunUsage;
+use crate::summary::SessionStats;
+use crate::sync::SyncMark;
+use crate::tape::parse_operation;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{now_ms, CalcError, CalculationHistory, Calculator, Operation};
+
+/// One `{ op, operand }` item of a batch. `op` is an operation name or
+/// symbol as accepted by tape import (`"add"`, `"+"`, `"memory_store"`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BatchStep {
+    pub op: String,
+    #[serde(default)]
+    pub operand: Option<f64>,
+}
+
+// What a run can change, saved as the outermost run starts so a failed
+// run can be undone without copying the calculator.
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+    value: f64,
+    memory: f64,
+    keypad: Keypad,
+    history_len: usize,
+    history_evicted: usize,
+    // Entries from before the run that it evicted, oldest first. Only
+    // these need keeping, so they are bounded by the history limit.
+    evicted: VecDeque<CalculationHistory>,
+    session: SessionStats,
+    recording_len: Option<usize>,
+    sync: SyncMark,
+}
+
+impl Checkpoint {
+    fn new(calc: &Calculator) -> Checkpoint {
+        Checkpoint {
+            value: calc.current_value,
+            memory: calc.memory,
+            keypad: calc.keypad.clone(),
+            history_len: calc.history.len(),
+            history_evicted: calc.history_evicted,
+            evicted: VecDeque::new(),
+            session: calc.session.clone(),
+            recording_len: calc.recording.as_ref().map(|(_, steps)| steps.len()),
+            sync: calc.sync.mark(),
+        }
+    }
+
+    // Called with the number of each entry the run evicts.
+    pub(crate) fn keep_evicted(&mut self, index: usize, entry: CalculationHistory) {
+        if index < self.history_evicted + self.history_len {
+            self.evicted.push_back(entry);
+        }
+    }
+}
+
+impl Calculator {
+    /// Applies `steps` in order through [`Calculator::apply`] and returns
+    /// the current value after each one.
+    ///
+    /// All-or-nothing, like [`Calculator::import_tape`]: if any step fails,
+    /// what the earlier ones changed is put back. Listeners and the log
+    /// sink hear nothing while the run is in progress; they hear about the
+    /// new entries once every step applied.
+    pub(crate) fn apply_all(
+        &mut self,
+        steps: impl IntoIterator<Item = (Operation, Option<f64>)>,
+    ) -> Result<Vec<f64>, CalcError> {
+        // Macros called by the run run nested here, sharing its usage and
+        // its checkpoint; only the outermost run commits or rolls back.
+        let outermost = self.run_usage.is_none();
+        let next_index = self.history_next_index();
+        if outermost {
+            self.run_usage = Some(RunUsage::start());
+            self.checkpoint = Some(Checkpoint::new(self));
+        }
+        let mut values = Vec::new();
+        let mut failure = None;
+        for (operation, operand) in steps {
+            if let Err(e) = self.charge_run() {
+                failure = Some((e, None));
+                break;
+            }
+            match self.apply(&operation, operand) {
+                Ok(value) => values.push(value),
+                Err(e) => {
+                    failure = Some((e, operand));
+                    break;
+                }
+            }
+        }
+        if !outermost {
+            return match failure {
+                Some((e, _)) => Err(e),
+                None => Ok(values),
+            };
+        }
+        self.run_usage = None;
+        let checkpoint = self.checkpoint.take();
+        if let Some((e, operand)) = failure {
+            if let Some(checkpoint) = checkpoint {
+                self.roll_back(checkpoint);
+            }
+            return Err(self.fail(e, operand));
+        }
+        self.expire_history_at(now_ms());
+        // Steps record at most one entry each (none when dedupe, incognito
+        // or a disabled history leaves it out), and the limit may already
+        // have evicted the oldest of them.
+        let appended = self.history_next_index().saturating_sub(next_index);
+        let start = self.history.len().saturating_sub(appended);
+        for entry in self.history.range(start..) {
+            self.notify_recorded(entry);
+        }
+        // Unrecorded steps still changed the state.
+        if appended < values.len() {
+            self.notify_changed();
+        }
+        Ok(values)
+    }
+
+    fn roll_back(&mut self, checkpoint: Checkpoint) {
+        self.current_value = checkpoint.value;
+        self.memory = checkpoint.memory;
+        self.keypad = checkpoint.keypad;
+        // What is left of the old entries is at the front; the run's own
+        // follow them.
+        let kept = checkpoint.history_len - checkpoint.evicted.len();
+        self.history.truncate(kept);
+        for entry in checkpoint.evicted.into_iter().rev() {
+            self.history.push_front(entry);
+        }
+        self.history_evicted = checkpoint.history_evicted;
+        self.session = checkpoint.session;
+        if let (Some((_, steps)), Some(len)) = (&mut self.recording, checkpoint.recording_len) {
+            steps.truncate(len);
+        }
+        self.sync.rewind(checkpoint.sync);
+    }
+
+    /// Parses and applies a batch, returning the value after every step.
+    /// An unknown `op` fails before anything is applied.
+    pub fn apply_batch(&mut self, steps: &[BatchStep]) -> Result<Vec<f64>, CalcError> {
+        let mut operations = Vec::with_capacity(steps.len());
+        for (i, step) in steps.iter().enumerate() {
+            let operation = parse_operation(&step.op).ok_or_else(|| {
+                let err = CalcError::InvalidArgument(format!(
+                    "Step {}: unknown operation '{}'",
+                    i, step.op
+                ));
+                self.fail(err, step.operand)
+            })?;
+            operations.push((operation, step.operand));
+        }
+        self.apply_all(operations)
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn batch_steps(ops: JsValue) -> Result<Vec<BatchStep>, CalcErrorJs> {
+    serde_wasm_bindgen::from_value(ops)
+        .map_err(|e| CalcErrorJs::from(CalcError::InvalidArgument(format!("Invalid batch: {}", e))))
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Applies an array of `{ op, operand }` items in one call and returns
+    /// the final value. Nothing is applied if any item fails.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = apply_batch)]
+    pub fn apply_batch_js(&mut self, ops: JsValue) -> Result<f64, CalcErrorJs> {
+        let steps = batch_steps(ops)?;
+        self.apply_batch(&steps).map_err(CalcErrorJs::from)?;
+        Ok(self.current_value)
+    }
+
+    /// Like `apply_batch`, returning the value after each item.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = apply_batch_trace)]
+    pub fn apply_batch_trace_js(&mut self, ops: JsValue) -> Result<Vec<f64>, CalcErrorJs> {
+        let steps = batch_steps(ops)?;
+        self.apply_batch(&steps).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn step(op: &str, operand: Option<f64>) -> BatchStep {
+        BatchStep {
+            op: op.to_string(),
+            operand,
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_direct_calls() {
+        let mut calc = Calculator::new();
+        let values = calc
+            .apply_batch(&[
+                step("set_value", Some(10.0)),
+                step("+", Some(5.0)),
+                step("Multiply", Some(2.0)),
+                step("ms", None),
+                step("sqrt", None),
+            ])
+            .unwrap();
+        assert_eq!(values, vec![10.0, 15.0, 30.0, 30.0, 30f64.sqrt()]);
+        assert_eq!(calc.get_memory(), 30.0);
+        assert_eq!(calc.history_count(), 5);
+        assert_eq!(calc.check_invariants(), Ok(()));
+        assert_eq!(calc.apply_batch(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_batch_is_all_or_nothing() {
+        let mut calc = Calculator::new();
+        calc.set_value(4.0);
+        let before = calc.canonical_state_string();
+        assert_eq!(
+            calc.apply_batch(&[step("add", Some(1.0)), step("/", Some(0.0))]),
+            Err(CalcError::DivisionByZero)
+        );
+        assert!(matches!(
+            calc.apply_batch(&[step("add", Some(1.0)), step("frobnicate", None)]),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(calc.apply_batch(&[step("add", None)]).is_err());
+        assert_eq!(calc.canonical_state_string(), before);
+    }
+
+    #[test]
+    fn test_failed_batch_puts_back_what_it_changed() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(2);
+        calc.set_replica_id("a").unwrap();
+        calc.add(1.0);
+        calc.record_macro("m").unwrap();
+        calc.add(2.0);
+        let next_index = calc.history_next_index();
+        let summary = calc.session_summary();
+        let versions = calc.version_vector();
+        let steps = [
+            step("add", Some(1.0)),
+            step("ms", None),
+            step("add", Some(1.0)),
+            step("/", Some(0.0)),
+        ];
+        assert_eq!(calc.apply_batch(&steps), Err(CalcError::DivisionByZero));
+        assert_eq!(calc.get_value(), 3.0);
+        assert_eq!(calc.get_memory(), 0.0);
+        assert_eq!(calc.history_series(), [1.0, 3.0]);
+        assert_eq!(calc.history_next_index(), next_index);
+        assert_eq!(calc.version_vector(), versions);
+        assert_eq!(calc.error_log_count(), 1);
+        let after = calc.session_summary();
+        assert_eq!(after.operations, summary.operations);
+        assert_eq!(after.error_count, summary.error_count + 1);
+        assert_eq!(calc.finish_macro(), Ok(1));
+    }
+
+    #[test]
+    fn test_batch_notifies_only_new_entries() {
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.add(2.0);
+        let sink = heard.clone();
+        calc.on_history(move |entry| sink.borrow_mut().push(entry.result));
+        calc.set_history_enabled(false);
+        calc.apply_batch(&[step("add", Some(3.0)), step("add", Some(4.0))])
+            .unwrap();
+        assert!(heard.borrow().is_empty());
+
+        calc.set_history_enabled(true);
+        calc.set_history_limit(3);
+        calc.apply_batch(&[step("add", Some(1.0)), step("add", Some(1.0))])
+            .unwrap();
+        assert_eq!(*heard.borrow(), [11.0, 12.0]);
+        assert_eq!(calc.history_series(), [3.0, 11.0, 12.0]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Tip, split and sales-tax breakdowns.
+//!
+//! Amounts are worked in whole cents (see [`to_cents`]), and percentages
+//! are rounded half away from zero to the cent, so every breakdown adds up
+//! exactly: a split's shares always sum to the total.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::currency::to_cents;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TipBreakdown {
+    pub subtotal: f64,
+    pub tip: f64,
+    pub total: f64,
+    /// The tip and total divided evenly, to the nearest cent.
+    pub tip_per_person: f64,
+    pub total_per_person: f64,
+    /// What each person pays. When the total does not divide evenly the
+    /// first shares are a cent larger.
+    pub shares: Vec<f64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TaxBreakdown {
+    /// The amount before tax.
+    pub subtotal: f64,
+    pub tax: f64,
+    /// The amount with tax.
+    pub total: f64,
+}
+
+fn check_amount(amount: f64) -> Result<i64, CalcError> {
+    let cents = to_cents(amount)?;
+    if cents < 0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "Amount must not be negative, got {}",
+            amount
+        )));
+    }
+    Ok(cents)
+}
+
+fn check_percent(percent: f64) -> Result<f64, CalcError> {
+    if !percent.is_finite() || percent < 0.0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "Percentage must be zero or more, got {}",
+            percent
+        )));
+    }
+    Ok(percent / 100.0)
+}
+
+fn dollars(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// Adds `tip_percent`% to `bill` and splits the total `split_count` ways.
+pub fn tip_breakdown(
+    bill: f64,
+    tip_percent: f64,
+    split_count: u32,
+) -> Result<TipBreakdown, CalcError> {
+    let subtotal = check_amount(bill)?;
+    let rate = check_percent(tip_percent)?;
+    if split_count == 0 {
+        return Err(CalcError::InvalidArgument(
+            "Split count must be at least 1".to_string(),
+        ));
+    }
+    let tip = (subtotal as f64 * rate).round() as i64;
+    let total = subtotal + tip;
+    let people = split_count as i64;
+    let (base, extra) = (total / people, total % people);
+    let shares = (0..people)
+        .map(|i| dollars(base + i64::from(i < extra)))
+        .collect();
+    let per_person = |cents: i64| dollars((cents as f64 / people as f64).round() as i64);
+    Ok(TipBreakdown {
+        subtotal: dollars(subtotal),
+        tip: dollars(tip),
+        total: dollars(total),
+        tip_per_person: per_person(tip),
+        total_per_person: per_person(total),
+        shares,
+    })
+}
+
+/// Sales tax at `tax_percent`%. With `inclusive`, `amount` already
+/// includes the tax and is split into subtotal and tax; otherwise the tax
+/// is added to it.
+pub fn tax_breakdown(
+    amount: f64,
+    tax_percent: f64,
+    inclusive: bool,
+) -> Result<TaxBreakdown, CalcError> {
+    let cents = check_amount(amount)?;
+    let rate = check_percent(tax_percent)?;
+    let (subtotal, total) = if inclusive {
+        ((cents as f64 / (1.0 + rate)).round() as i64, cents)
+    } else {
+        (cents, cents + (cents as f64 * rate).round() as i64)
+    };
+    Ok(TaxBreakdown {
+        subtotal: dollars(subtotal),
+        tax: dollars(total - subtotal),
+        total: dollars(total),
+    })
+}
+
+/// `{ subtotal, tip, total, tip_per_person, total_per_person, shares }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = tip_breakdown)]
+pub fn tip_breakdown_js(
+    bill: f64,
+    tip_percent: f64,
+    split_count: u32,
+) -> Result<JsValue, CalcErrorJs> {
+    let breakdown = tip_breakdown(bill, tip_percent, split_count)
+        .map_err(|e| CalcErrorJs::new(e, Some(bill)))?;
+    Ok(serde_wasm_bindgen::to_value(&breakdown).unwrap_or(JsValue::NULL))
+}
+
+/// `{ subtotal, tax, total }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = tax_breakdown)]
+pub fn tax_breakdown_js(
+    amount: f64,
+    tax_percent: f64,
+    inclusive: bool,
+) -> Result<JsValue, CalcErrorJs> {
+    let breakdown = tax_breakdown(amount, tax_percent, inclusive)
+        .map_err(|e| CalcErrorJs::new(e, Some(amount)))?;
+    Ok(serde_wasm_bindgen::to_value(&breakdown).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tip_breakdown() {
+        let tip = tip_breakdown(100.0, 18.0, 3).unwrap();
+        assert_eq!(tip.tip, 18.0);
+        assert_eq!(tip.total, 118.0);
+        assert_eq!(tip.tip_per_person, 6.0);
+        assert_eq!(tip.total_per_person, 39.33);
+        assert_eq!(tip.shares, vec![39.34, 39.33, 39.33]);
+        let cents: i64 = tip.shares.iter().map(|s| to_cents(*s).unwrap()).sum();
+        assert_eq!(cents, 11800);
+
+        let odd = tip_breakdown(10.05, 15.0, 1).unwrap();
+        assert_eq!((odd.tip, odd.total), (1.51, 11.56));
+        assert_eq!(odd.shares, vec![11.56]);
+    }
+
+    #[test]
+    fn test_tax_breakdown() {
+        assert_eq!(
+            tax_breakdown(50.0, 8.25, false),
+            Ok(TaxBreakdown {
+                subtotal: 50.0,
+                tax: 4.13,
+                total: 54.13
+            })
+        );
+        assert_eq!(
+            tax_breakdown(119.0, 19.0, true),
+            Ok(TaxBreakdown {
+                subtotal: 100.0,
+                tax: 19.0,
+                total: 119.0
+            })
+        );
+        assert_eq!(tax_breakdown(10.0, 0.0, true).unwrap().tax, 0.0);
+    }
+
+    #[test]
+    fn test_breakdown_errors() {
+        assert!(tip_breakdown(10.0, 15.0, 0).is_err());
+        assert!(tip_breakdown(-10.0, 15.0, 2).is_err());
+        assert!(tip_breakdown(10.001, 15.0, 2).is_err());
+        assert!(tax_breakdown(10.0, -5.0, false).is_err());
+        assert!(tax_breakdown(10.0, f64::NAN, true).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! Terminal front-end: `wasm-calc` starts a REPL on stdin, and
+//! `wasm-calc LINE...` runs each argument as a line and exits.
+//! Exits with status 1 if any line failed.
+
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+use wasm_calc::repl::{execute, run, Outcome};
+use wasm_calc::Calculator;
+
+fn main() -> ExitCode {
+    let mut calc = Calculator::new();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let failures = if args.is_empty() {
+        let stdin = io::stdin();
+        let prompt = stdin.is_terminal().then_some("> ");
+        match run(&mut calc, stdin.lock(), io::stdout(), prompt) {
+            Ok(failures) => failures,
+            Err(e) => {
+                eprintln!("wasm-calc: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        let mut failures = 0;
+        for line in &args {
+            match execute(&mut calc, line) {
+                Ok(Outcome::Output(text)) => println!("{}", text),
+                Ok(Outcome::Silent) => {}
+                Ok(Outcome::Quit) => break,
+                Err(e) => {
+                    failures += 1;
+                    eprintln!("error: {}", e);
+                }
+            }
+        }
+        failures
+    };
+
+    if failures == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
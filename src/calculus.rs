@@ -0,0 +1,333 @@
+//! Numeric derivatives and integrals of expressions in `x`.
+//!
+//! [`derivative_at`] uses Ridders' method: central differences at shrinking
+//! steps, extrapolated to a zero step, which is typically good to 10 or
+//! more significant digits. [`integrate`] uses adaptive Simpson
+//! quadrature, halving each subinterval until its estimate settles.
+//!
+//! The calculator versions also accept the name of a user function (see
+//! [`Calculator::define_function`]).
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, Dialect};
+use crate::functions::UserFunction;
+use crate::locale::localize_input;
+use crate::solve::Function;
+#[cfg(feature = "wasm")]
+use crate::{timing, CalcErrorJs};
+use crate::{CalcError, Calculator};
+
+// Columns of the Ridders extrapolation table, and how much the step
+// shrinks between them.
+const RIDDERS_STEPS: usize = 10;
+const RIDDERS_SHRINK: f64 = 1.4;
+
+// How deep `integrate` will subdivide: 2^-40 of the interval at the finest.
+const MAX_DEPTH: u32 = 40;
+
+/// Relative tolerance `integrate` aims for.
+const TOLERANCE: f64 = 1e-10;
+
+fn invalid(message: String) -> CalcError {
+    CalcError::InvalidArgument(message)
+}
+
+// One Ridders extrapolation from step `h`; `None` if `f` is not finite
+// somewhere within `h` of `x`.
+fn ridders(f: &mut Function, x: f64, mut h: f64) -> Option<f64> {
+    let mut central = |h: f64| Some((f.at(x + h).ok()? - f.at(x - h).ok()?) / (2.0 * h));
+    let mut table = [[0.0; RIDDERS_STEPS]; RIDDERS_STEPS];
+    table[0][0] = central(h)?;
+    let (mut best, mut error) = (table[0][0], f64::INFINITY);
+    for i in 1..RIDDERS_STEPS {
+        h /= RIDDERS_SHRINK;
+        table[0][i] = central(h)?;
+        let mut factor = RIDDERS_SHRINK * RIDDERS_SHRINK;
+        for j in 1..=i {
+            table[j][i] = (table[j - 1][i] * factor - table[j - 1][i - 1]) / (factor - 1.0);
+            factor *= RIDDERS_SHRINK * RIDDERS_SHRINK;
+            let change = (table[j][i] - table[j - 1][i])
+                .abs()
+                .max((table[j][i] - table[j - 1][i - 1]).abs());
+            if change <= error {
+                (best, error) = (table[j][i], change);
+            }
+        }
+        // Higher orders have started to lose precision to rounding.
+        if (table[i][i] - table[i - 1][i - 1]).abs() >= 2.0 * error {
+            break;
+        }
+    }
+    Some(best)
+}
+
+fn derivative(mut f: Function, x: f64) -> Result<f64, CalcError> {
+    if !x.is_finite() {
+        return Err(invalid(format!("x must be finite, got {}", x)));
+    }
+    f.at(x)?;
+    // Near a singularity the first step can reach past it, so try smaller
+    // ones before giving up.
+    let mut h = 0.1 * x.abs().max(1.0);
+    for _ in 0..8 {
+        if let Some(slope) = ridders(&mut f, x, h) {
+            return Ok(slope);
+        }
+        h /= 16.0;
+    }
+    Err(invalid(format!(
+        "The expression is not finite close to x = {}",
+        x
+    )))
+}
+
+// Simpson's rule on [a, b], returning the midpoint and its value too.
+fn simpson(
+    f: &mut Function,
+    a: f64,
+    fa: f64,
+    b: f64,
+    fb: f64,
+) -> Result<(f64, f64, f64), CalcError> {
+    let m = a + (b - a) / 2.0;
+    let fm = f.at(m)?;
+    Ok((m, fm, (b - a) / 6.0 * (fa + 4.0 * fm + fb)))
+}
+
+struct Interval {
+    a: f64,
+    fa: f64,
+    b: f64,
+    fb: f64,
+    m: f64,
+    fm: f64,
+    estimate: f64,
+}
+
+fn adaptive(f: &mut Function, i: Interval, tolerance: f64, depth: u32) -> Result<f64, CalcError> {
+    let (lm, flm, left) = simpson(f, i.a, i.fa, i.m, i.fm)?;
+    let (rm, frm, right) = simpson(f, i.m, i.fm, i.b, i.fb)?;
+    let delta = left + right - i.estimate;
+    // Past the depth limit (a jump, say) the estimate is as good as it gets.
+    if depth == 0 || delta.abs() <= 15.0 * tolerance || lm <= i.a || rm >= i.b {
+        return Ok(left + right + delta / 15.0);
+    }
+    let halves = [
+        Interval {
+            a: i.a,
+            fa: i.fa,
+            b: i.m,
+            fb: i.fm,
+            m: lm,
+            fm: flm,
+            estimate: left,
+        },
+        Interval {
+            a: i.m,
+            fa: i.fm,
+            b: i.b,
+            fb: i.fb,
+            m: rm,
+            fm: frm,
+            estimate: right,
+        },
+    ];
+    let mut total = 0.0;
+    for half in halves {
+        total += adaptive(f, half, tolerance / 2.0, depth - 1)?;
+    }
+    Ok(total)
+}
+
+fn integral(mut f: Function, a: f64, b: f64) -> Result<f64, CalcError> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(invalid(format!(
+            "Bounds must be finite, got {} and {}",
+            a, b
+        )));
+    }
+    if a == b {
+        return Ok(0.0);
+    }
+    if a > b {
+        return integral(f, b, a).map(|v| -v);
+    }
+    let (fa, fb) = (f.at(a)?, f.at(b)?);
+    let (m, fm, estimate) = simpson(&mut f, a, fa, b, fb)?;
+    let tolerance = TOLERANCE * estimate.abs().max(1.0);
+    let whole = Interval {
+        a,
+        fa,
+        b,
+        fb,
+        m,
+        fm,
+        estimate,
+    };
+    adaptive(&mut f, whole, tolerance, MAX_DEPTH)
+}
+
+/// The derivative of `expression` (in `x`) at `x`. The expression must be
+/// finite at and around `x`.
+pub fn derivative_at(expression: &str, x: f64) -> Result<f64, CalcError> {
+    derivative(Function::parse(expression)?, x)
+}
+
+/// The definite integral of `expression` (in `x`) from `a` to `b`; negative
+/// when `b < a`. The expression must be finite over the whole interval, so
+/// improper integrals such as `1/sqrt(x)` from 0 are refused.
+pub fn integrate(expression: &str, a: f64, b: f64) -> Result<f64, CalcError> {
+    integral(Function::parse(expression)?, a, b)
+}
+
+impl Calculator {
+    // A user function named `expression`, or else the expression parsed in
+    // the input locale, with `ans` and `mem` bound as in `evaluate`.
+    fn function_of_x(&self, expression: &str) -> Result<Function, CalcError> {
+        self.permit_name("evaluate")?;
+        let expr = match self.functions.get(expression.trim()) {
+            Some(UserFunction::Expression { expr, .. }) => Ok(expr.clone()),
+            Some(UserFunction::Macro(_)) => Err(invalid(format!(
+                "'{}' is a macro, not a function of x",
+                expression.trim()
+            ))),
+            None => localize_input(expression, self.decimal_comma())
+                .and_then(|src| parse(&src, Dialect::Standard)),
+        }
+        .map_err(|e| self.fail(e, None))?;
+        let mut scope = BTreeMap::new();
+        scope.insert("ans".to_string(), self.current_value);
+        scope.insert("mem".to_string(), self.memory);
+        Ok(Function::new(expr, scope))
+    }
+
+    /// Like [`derivative_at`], where `expression` may also name a user
+    /// function. Leaves the current value and history alone.
+    pub fn derivative_at(&self, expression: &str, x: f64) -> Result<f64, CalcError> {
+        let f = self.function_of_x(expression)?;
+        derivative(f, x).map_err(|e| self.fail(e, Some(x)))
+    }
+
+    /// Like [`integrate`], where `expression` may also name a user
+    /// function. Leaves the current value and history alone.
+    pub fn integrate(&self, expression: &str, a: f64, b: f64) -> Result<f64, CalcError> {
+        let f = self.function_of_x(expression)?;
+        integral(f, a, b).map_err(|e| self.fail(e, None))
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = derivative_at)]
+    pub fn derivative_at_js(&self, expression: &str, x: f64) -> Result<f64, CalcErrorJs> {
+        timing::time("derivative_at", || self.derivative_at(expression, x))
+            .map_err(|e| CalcErrorJs::new(e, Some(x)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = integrate)]
+    pub fn integrate_js(&self, expression: &str, a: f64, b: f64) -> Result<f64, CalcErrorJs> {
+        timing::time("integrate", || self.integrate(expression, a, b)).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = derivative_at)]
+pub fn derivative_at_js(expression: &str, x: f64) -> Result<f64, CalcErrorJs> {
+    timing::time("derivative_at", || derivative_at(expression, x))
+        .map_err(|e| CalcErrorJs::new(e, Some(x)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = integrate)]
+pub fn integrate_js(expression: &str, a: f64, b: f64) -> Result<f64, CalcErrorJs> {
+    timing::time("integrate", || integrate(expression, a, b)).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+        (actual - expected).abs() <= tolerance * expected.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_derivative_at() {
+        assert!(close(derivative_at("x^3", 2.0).unwrap(), 12.0, 1e-10));
+        assert!(close(
+            derivative_at("sin(x)", 1.0).unwrap(),
+            1f64.cos(),
+            1e-10
+        ));
+        assert!(close(
+            derivative_at("exp(x)", 10.0).unwrap(),
+            10f64.exp(),
+            1e-10
+        ));
+        // The first step would reach x <= 0, where ln is undefined.
+        assert!(close(derivative_at("ln(x)", 0.01).unwrap(), 100.0, 1e-8));
+        assert!(derivative_at("ln(x)", 0.0).is_err());
+        assert!(derivative_at("x", f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_integrate() {
+        assert!(close(integrate("x^2", 0.0, 3.0).unwrap(), 9.0, 1e-12));
+        assert!(close(integrate("sin(x)", 0.0, pi()).unwrap(), 2.0, 1e-10));
+        assert!(close(
+            integrate("exp(-x^2)", -5.0, 5.0).unwrap(),
+            pi().sqrt(),
+            1e-10
+        ));
+        assert!(close(integrate("x", 2.0, 0.0).unwrap(), -2.0, 1e-12));
+        assert!(close(
+            integrate("abs(x - 0.3)", 0.0, 1.0).unwrap(),
+            0.29,
+            1e-10
+        ));
+        assert_eq!(integrate("x", 1.0, 1.0), Ok(0.0));
+        assert!(integrate("1 / sqrt(x)", 0.0, 1.0).is_err());
+        assert!(integrate("x", 0.0, f64::INFINITY).is_err());
+        assert!(matches!(
+            integrate("y", 0.0, 1.0),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+    }
+
+    fn pi() -> f64 {
+        std::f64::consts::PI
+    }
+
+    #[test]
+    fn test_calculator_calculus() {
+        let mut calc = Calculator::new();
+        calc.define_function("square", "x * x").unwrap();
+        assert!(close(
+            calc.derivative_at("square", 3.0).unwrap(),
+            6.0,
+            1e-10
+        ));
+        assert!(close(
+            calc.integrate("square", 0.0, 1.0).unwrap(),
+            1.0 / 3.0,
+            1e-12
+        ));
+        calc.set_value(2.0);
+        assert!(close(
+            calc.integrate("ans * x", 0.0, 1.0).unwrap(),
+            1.0,
+            1e-12
+        ));
+        assert_eq!(calc.current_value, 2.0);
+        calc.record_macro("m").unwrap();
+        calc.finish_macro().unwrap();
+        assert!(calc.derivative_at("m", 1.0).is_err());
+        assert_eq!(calc.session_summary().error_count, 1);
+    }
+}
@@ -0,0 +1,621 @@
+//! Permutations, combinations and binomial probabilities.
+//!
+//! Counts are computed exactly in `u128` by multiplying and dividing term by
+//! term, never through factorials, so `combinations(100, 50)` works although
+//! `100!` is far past any integer type. Past `u128` the Rust functions
+//! report [`CalcError::TooLarge`]; the
+//! JS wrappers return JS numbers anyway, so they fall back to an `f64`
+//! product and only throw `OVERFLOW` once that is infinite.
+//!
+//! Lottery odds and hypergeometric probabilities are ratios of counts that
+//! leave `u128` for modest inputs (`C(200, 100)` alone is about 9 * 10^58),
+//! so they count exactly in an arbitrary-precision integer and round only
+//! the final ratio to `f64`.
+
+use std::f64::consts::PI;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::{whole_number, CalcErrorJs};
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Ordered selections of `r` from `n`: `n! / (n - r)!`, 0 when `r > n`.
+pub fn permutations(n: u64, r: u64) -> Result<u128, CalcError> {
+    if r > n {
+        return Ok(0);
+    }
+    (n - r + 1..=n).try_fold(1u128, |acc, k| {
+        acc.checked_mul(k as u128).ok_or(CalcError::TooLarge {
+            operation: "permutations",
+        })
+    })
+}
+
+/// Unordered selections of `r` from `n`: `n! / (r! (n - r)!)`, 0 when
+/// `r > n`.
+pub fn combinations(n: u64, r: u64) -> Result<u128, CalcError> {
+    if r > n {
+        return Ok(0);
+    }
+    let r = r.min(n - r) as u128;
+    let n = n as u128;
+    // After step i the value is C(n - r + i, i); dividing out the common
+    // factor first keeps every intermediate no larger than the result.
+    let mut value = 1u128;
+    for i in 1..=r {
+        let g = gcd(value, i);
+        let term = (n - r + i) / (i / g);
+        value = (value / g).checked_mul(term).ok_or(CalcError::TooLarge {
+            operation: "combinations",
+        })?;
+    }
+    Ok(value)
+}
+
+// ln(n!) - ln(sqrt(2 pi n) (n / e)^n), the error of Stirling's formula.
+fn stirling_error(n: f64) -> f64 {
+    if n <= 15.0 {
+        let ln_factorial: f64 = (2..=n as u64).map(|k| (k as f64).ln()).sum();
+        return ln_factorial - (n + 0.5) * n.ln() + n - 0.5 * (2.0 * PI).ln();
+    }
+    let nn = n * n;
+    (1.0 / 12.0
+        - (1.0 / 360.0 - (1.0 / 1260.0 - (1.0 / 1680.0 - 1.0 / (1188.0 * nn)) / nn) / nn) / nn)
+        / n
+}
+
+// `x ln(x / m) + m - x`, accurate when x is close to m.
+fn deviance(x: f64, m: f64) -> f64 {
+    if (x - m).abs() < 0.1 * (x + m) {
+        let mut v = (x - m) / (x + m);
+        let mut sum = (x - m) * v;
+        let mut term = 2.0 * x * v;
+        v *= v;
+        for j in 1.. {
+            term *= v;
+            let next = sum + term / (2 * j + 1) as f64;
+            if next == sum {
+                break;
+            }
+            sum = next;
+        }
+        return sum;
+    }
+    x * (x / m).ln() + m - x
+}
+
+/// The probability of exactly `k` successes in `n` trials with success
+/// probability `p`.
+///
+/// Uses Loader's saddle-point expansion rather than `C(n, k) p^k q^(n-k)`,
+/// whose factors overflow and underflow for large `n` even when the
+/// probability itself is an ordinary number.
+pub fn binomial_probability(n: u64, k: u64, p: f64) -> Result<f64, CalcError> {
+    if !(0.0..=1.0).contains(&p) {
+        return Err(CalcError::InvalidArgument(format!(
+            "Probability must be between 0 and 1, got {}",
+            p
+        )));
+    }
+    if k > n {
+        return Ok(0.0);
+    }
+    let q = 1.0 - p;
+    let (n, k) = (n as f64, k as f64);
+    if p == 0.0 || q == 0.0 {
+        let certain = if p == 0.0 { 0.0 } else { n };
+        return Ok(if k == certain { 1.0 } else { 0.0 });
+    }
+    if k == 0.0 {
+        return Ok((n * q.ln()).exp());
+    }
+    if k == n {
+        return Ok((n * p.ln()).exp());
+    }
+    let exponent = stirling_error(n)
+        - stirling_error(k)
+        - stirling_error(n - k)
+        - deviance(k, n * p)
+        - deviance(n - k, n * q);
+    let scale = 2.0 * PI * k * (1.0 - k / n);
+    Ok(exponent.exp() / scale.sqrt())
+}
+
+/// Populations above this are refused by the hypergeometric functions,
+/// whose exact counts grow with the population's size in bits.
+pub const MAX_HYPERGEOMETRIC_POPULATION: u64 = 100_000;
+
+// An unbounded natural number in little-endian 64-bit limbs, with no
+// trailing zero limbs. Only what exact binomial sums need.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BigNat(Vec<u64>);
+
+impl BigNat {
+    fn one() -> BigNat {
+        BigNat(vec![1])
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn bits(&self) -> u64 {
+        match self.0.last() {
+            Some(top) => self.0.len() as u64 * 64 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    fn mul_small(&mut self, factor: u64) {
+        let mut carry = 0u128;
+        for limb in &mut self.0 {
+            let product = *limb as u128 * factor as u128 + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        if carry != 0 {
+            self.0.push(carry as u64);
+        }
+        self.trim();
+    }
+
+    // Divides by `divisor`, which must divide `self` exactly.
+    fn div_exact(&mut self, divisor: u64) {
+        let mut remainder = 0u128;
+        for limb in self.0.iter_mut().rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *limb = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        debug_assert_eq!(remainder, 0);
+        self.trim();
+    }
+
+    fn add(&mut self, other: &BigNat) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        let mut carry = false;
+        for (i, limb) in self.0.iter_mut().enumerate() {
+            let (sum, c1) = limb.overflowing_add(other.0.get(i).copied().unwrap_or(0));
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        if carry {
+            self.0.push(1);
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    // `(m, e)` with `self` about `m * 2^e` and `m` the top 64 bits.
+    fn top_bits(&self) -> (u64, i64) {
+        let bits = self.bits();
+        if bits <= 64 {
+            return (self.0.first().copied().unwrap_or(0), 0);
+        }
+        let shift = bits - 64;
+        let (limb, offset) = ((shift / 64) as usize, shift % 64);
+        let mut top = self.0[limb] >> offset;
+        if offset != 0 {
+            top |= self.0[limb + 1] << (64 - offset);
+        }
+        (top, shift as i64)
+    }
+
+    // `self / other` to within a few units in the last place.
+    fn ratio(&self, other: &BigNat) -> f64 {
+        let (m1, e1) = self.top_bits();
+        let (m2, e2) = other.top_bits();
+        let exponent = (e1 - e2).clamp(-4000, 4000) as i32;
+        // Two steps, so that 2^exponent alone cannot overflow or underflow.
+        let half = 2f64.powi(exponent / 2);
+        m1 as f64 / m2 as f64 * half * half * 2f64.powi(exponent % 2)
+    }
+}
+
+// C(n, r) exactly, or `None` once it passes `limit_bits`.
+fn big_combinations(n: u64, r: u64, limit_bits: u64) -> Option<BigNat> {
+    let r = r.min(n - r);
+    let mut value = BigNat::one();
+    // As in `combinations`, the value after step i is C(n - r + i, i),
+    // which only grows towards the result.
+    for i in 1..=r {
+        value.mul_small(n - r + i);
+        value.div_exact(i);
+        if value.bits() > limit_bits {
+            return None;
+        }
+    }
+    Some(value)
+}
+
+/// The `N` of "1 in `N`": how many tickets of `picks` numbers from `pool`
+/// there are, one of which wins.
+///
+/// Counted exactly and rounded to the nearest `f64`, so it is
+/// [`CalcError::TooLarge`] only past about 1.8 * 10^308.
+pub fn odds_of_combination(pool: u64, picks: u64) -> Result<f64, CalcError> {
+    if picks > pool {
+        return Err(CalcError::InvalidArgument(format!(
+            "Cannot pick {} numbers from a pool of {}",
+            picks, pool
+        )));
+    }
+    let too_large = CalcError::TooLarge {
+        operation: "odds_of_combination",
+    };
+    let count = big_combinations(pool, picks, 1025).ok_or_else(|| too_large.clone())?;
+    let value = count.ratio(&BigNat::one());
+    if value.is_infinite() {
+        return Err(too_large);
+    }
+    Ok(value)
+}
+
+fn check_hypergeometric(population: u64, successes: u64, draws: u64) -> Result<(), CalcError> {
+    if population > MAX_HYPERGEOMETRIC_POPULATION {
+        return Err(CalcError::InvalidArgument(format!(
+            "Population must be at most {}, got {}",
+            MAX_HYPERGEOMETRIC_POPULATION, population
+        )));
+    }
+    if successes > population || draws > population {
+        return Err(CalcError::InvalidArgument(format!(
+            "Successes and draws must not exceed the population of {}, got {} and {}",
+            population, successes, draws
+        )));
+    }
+    Ok(())
+}
+
+// The probability that `draws` from `population` with `successes` include
+// from `from` to `to` successes.
+fn hypergeometric_between(population: u64, successes: u64, draws: u64, from: u64, to: u64) -> f64 {
+    let failures = population - successes;
+    let low = draws.saturating_sub(failures).max(from);
+    let high = draws.min(successes).min(to);
+    if low > high {
+        return 0.0;
+    }
+    // Ways T(i) = C(K, i) C(N - K, n - i) of drawing i successes. The least
+    // possible i has one factor equal to 1; from there
+    // T(i + 1) = T(i) (K - i) (n - i) / ((i + 1) (N - K - n + i + 1)) is an
+    // integer, so each step divides exactly.
+    let start = draws.saturating_sub(failures);
+    let mut ways = if start == 0 {
+        big_combinations(failures, draws, u64::MAX)
+    } else {
+        big_combinations(successes, start, u64::MAX)
+    }
+    .expect("no bit limit");
+    let mut total = BigNat(Vec::new());
+    for i in start..=high {
+        if i >= low {
+            total.add(&ways);
+        }
+        if i < high {
+            ways.mul_small(successes - i);
+            ways.mul_small(draws - i);
+            ways.div_exact(i + 1);
+            ways.div_exact(failures + i + 1 - draws);
+        }
+    }
+    let all = big_combinations(population, draws, u64::MAX).expect("no bit limit");
+    if total.is_zero() {
+        0.0
+    } else {
+        total.ratio(&all).min(1.0)
+    }
+}
+
+/// The probability that `draws` taken without replacement from
+/// `population` items, `successes` of them successes, include exactly `k`
+/// successes.
+pub fn hypergeometric_probability(
+    population: u64,
+    successes: u64,
+    draws: u64,
+    k: u64,
+) -> Result<f64, CalcError> {
+    check_hypergeometric(population, successes, draws)?;
+    Ok(hypergeometric_between(population, successes, draws, k, k))
+}
+
+/// Like [`hypergeometric_probability`], for at least `k` successes.
+pub fn hypergeometric_at_least(
+    population: u64,
+    successes: u64,
+    draws: u64,
+    k: u64,
+) -> Result<f64, CalcError> {
+    check_hypergeometric(population, successes, draws)?;
+    Ok(hypergeometric_between(
+        population,
+        successes,
+        draws,
+        k,
+        u64::MAX,
+    ))
+}
+
+// Runs an exact count, falling back to `approximate` past `u128`.
+#[cfg(feature = "wasm")]
+fn count_js(
+    exact: Result<u128, CalcError>,
+    approximate: impl FnOnce() -> f64,
+    operand: f64,
+) -> Result<f64, CalcErrorJs> {
+    match exact {
+        Ok(value) => Ok(value as f64),
+        Err(too_large @ CalcError::TooLarge { .. }) => {
+            let value = approximate();
+            if value.is_finite() {
+                Ok(value)
+            } else {
+                Err(CalcErrorJs::new(too_large, Some(operand)))
+            }
+        }
+        Err(e) => Err(CalcErrorJs::new(e, Some(operand))),
+    }
+}
+
+/// Exact up to 2^53; beyond that the nearest JS number.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = permutations)]
+pub fn permutations_js(n: f64, r: f64) -> Result<f64, CalcErrorJs> {
+    let (n_int, r_int) = (whole_number(n)?, whole_number(r)?);
+    let approximate = || {
+        let mut value = 1.0;
+        for k in (n_int - r_int + 1..=n_int).rev() {
+            value *= k as f64;
+            if value.is_infinite() {
+                break;
+            }
+        }
+        value
+    };
+    count_js(permutations(n_int, r_int), approximate, r)
+}
+
+/// Exact up to 2^53; beyond that the nearest JS number.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = combinations)]
+pub fn combinations_js(n: f64, r: f64) -> Result<f64, CalcErrorJs> {
+    let (n_int, r_int) = (whole_number(n)?, whole_number(r)?);
+    let approximate = || {
+        let r = r_int.min(n_int - r_int);
+        let mut value = 1.0;
+        // Every factor exceeds 1, so once infinite it stays infinite.
+        for i in 1..=r {
+            value *= (n_int - r + i) as f64 / i as f64;
+            if value.is_infinite() {
+                break;
+            }
+        }
+        value
+    };
+    count_js(combinations(n_int, r_int), approximate, r)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = binomial_probability)]
+pub fn binomial_probability_js(n: f64, k: f64, p: f64) -> Result<f64, CalcErrorJs> {
+    binomial_probability(whole_number(n)?, whole_number(k)?, p)
+        .map_err(|e| CalcErrorJs::new(e, Some(p)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = odds_of_combination)]
+pub fn odds_of_combination_js(pool: f64, picks: f64) -> Result<f64, CalcErrorJs> {
+    odds_of_combination(whole_number(pool)?, whole_number(picks)?)
+        .map_err(|e| CalcErrorJs::new(e, Some(picks)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = hypergeometric_probability)]
+pub fn hypergeometric_probability_js(
+    population: f64,
+    successes: f64,
+    draws: f64,
+    k: f64,
+) -> Result<f64, CalcErrorJs> {
+    hypergeometric_probability(
+        whole_number(population)?,
+        whole_number(successes)?,
+        whole_number(draws)?,
+        whole_number(k)?,
+    )
+    .map_err(|e| CalcErrorJs::new(e, Some(population)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = hypergeometric_at_least)]
+pub fn hypergeometric_at_least_js(
+    population: f64,
+    successes: f64,
+    draws: f64,
+    k: f64,
+) -> Result<f64, CalcErrorJs> {
+    hypergeometric_at_least(
+        whole_number(population)?,
+        whole_number(successes)?,
+        whole_number(draws)?,
+        whole_number(k)?,
+    )
+    .map_err(|e| CalcErrorJs::new(e, Some(population)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * b.abs().max(1e-300)
+    }
+
+    #[test]
+    fn test_permutations() {
+        assert_eq!(permutations(5, 2), Ok(20));
+        assert_eq!(permutations(5, 0), Ok(1));
+        assert_eq!(permutations(5, 6), Ok(0));
+        assert_eq!(
+            permutations(34, 34),
+            Ok(295_232_799_039_604_140_847_618_609_643_520_000_000)
+        );
+        assert_eq!(
+            permutations(35, 35),
+            Err(CalcError::TooLarge {
+                operation: "permutations"
+            })
+        );
+        #[cfg(feature = "wasm")]
+        assert!(close(
+            permutations_js(100.0, 50.0).unwrap(),
+            3.068_518_756_254_966e93
+        ));
+        #[cfg(feature = "wasm")]
+        assert_eq!(
+            permutations_js(200.0, 200.0).map_err(|e| e.code()),
+            Err("OVERFLOW".to_string())
+        );
+    }
+
+    #[test]
+    fn test_combinations() {
+        assert_eq!(combinations(5, 2), Ok(10));
+        assert_eq!(combinations(52, 5), Ok(2_598_960));
+        assert_eq!(combinations(3, 4), Ok(0));
+        assert_eq!(
+            combinations(100, 50),
+            Ok(100_891_344_545_564_193_334_812_497_256)
+        );
+        assert_eq!(combinations(u64::MAX, u64::MAX - 1), Ok(u64::MAX as u128));
+        assert_eq!(
+            combinations(1000, 500),
+            Err(CalcError::TooLarge {
+                operation: "combinations"
+            })
+        );
+        #[cfg(feature = "wasm")]
+        assert!(close(
+            combinations_js(1000.0, 500.0).unwrap(),
+            2.702_882_409_454_366_5e299
+        ));
+        #[cfg(feature = "wasm")]
+        assert_eq!(
+            combinations_js(2000.0, 1000.0).map_err(|e| e.code()),
+            Err("OVERFLOW".to_string())
+        );
+        #[cfg(feature = "wasm")]
+        assert_eq!(
+            combinations_js(5.0, 2.5).map_err(|e| e.code()),
+            Err("INVALID_ARGUMENT".to_string())
+        );
+    }
+
+    #[test]
+    fn test_binomial_probability() {
+        for (n, k, p) in [
+            (10, 3, 0.5_f64),
+            (20, 0, 0.3),
+            (20, 20, 0.3),
+            (30, 12, 0.37),
+            (50, 1, 0.01),
+        ] {
+            let direct = combinations(n, k).unwrap() as f64
+                * p.powi(k as i32)
+                * (1.0 - p).powi((n - k) as i32);
+            let value = binomial_probability(n, k, p).unwrap();
+            assert!(
+                close(value, direct),
+                "{} {} {}: {} vs {}",
+                n,
+                k,
+                p,
+                value,
+                direct
+            );
+        }
+        assert_eq!(binomial_probability(10, 11, 0.5), Ok(0.0));
+        assert_eq!(binomial_probability(10, 0, 0.0), Ok(1.0));
+        assert_eq!(binomial_probability(10, 10, 1.0), Ok(1.0));
+        assert_eq!(binomial_probability(10, 9, 1.0), Ok(0.0));
+        assert!(binomial_probability(10, 5, 1.5).is_err());
+        // C(10^6, 5 * 10^5) alone is about 10^301029.
+        let middle = binomial_probability(1_000_000, 500_000, 0.5).unwrap();
+        assert!((middle - (2.0 / (PI * 1e6)).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_odds_of_combination() {
+        assert_eq!(odds_of_combination(49, 6), Ok(13_983_816.0));
+        // Powerball: five from 69, times 26 red balls.
+        assert_eq!(
+            odds_of_combination(69, 5).map(|n| n * 26.0),
+            Ok(292_201_338.0)
+        );
+        assert_eq!(odds_of_combination(10, 0), Ok(1.0));
+        // Past u128, still exact up to the rounding to f64.
+        assert!(close(
+            odds_of_combination(1000, 500).unwrap(),
+            2.702_882_409_454_366_5e299
+        ));
+        assert_eq!(
+            odds_of_combination(2000, 1000),
+            Err(CalcError::TooLarge {
+                operation: "odds_of_combination"
+            })
+        );
+        assert_eq!(odds_of_combination(u64::MAX, 1), Ok(u64::MAX as f64));
+        assert!(odds_of_combination(5, 6).is_err());
+    }
+
+    #[test]
+    fn test_hypergeometric() {
+        // Matching exactly 3 of 6 in a 6/49 lottery.
+        let three = hypergeometric_probability(49, 6, 6, 3).unwrap();
+        assert!(close(three, 246_820.0 / 13_983_816.0));
+        let jackpot = hypergeometric_probability(49, 6, 6, 6).unwrap();
+        assert!(close(jackpot, 1.0 / 13_983_816.0));
+        // Four aces in a five-card hand.
+        assert!(close(
+            hypergeometric_probability(52, 4, 5, 4).unwrap(),
+            48.0 / 2_598_960.0
+        ));
+        assert_eq!(hypergeometric_probability(52, 4, 5, 5), Ok(0.0));
+        // Ten cards from ten: every success is drawn.
+        assert_eq!(hypergeometric_probability(10, 3, 10, 3), Ok(1.0));
+
+        let at_least_one_ace = hypergeometric_at_least(52, 4, 5, 1).unwrap();
+        let no_ace = combinations(48, 5).unwrap() as f64 / 2_598_960.0;
+        assert!(close(at_least_one_ace, 1.0 - no_ace));
+        assert_eq!(hypergeometric_at_least(52, 4, 5, 0), Ok(1.0));
+
+        // The whole distribution sums to 1 at the largest population.
+        let n = MAX_HYPERGEOMETRIC_POPULATION;
+        let middle = hypergeometric_probability(n, n / 2, 1000, 500).unwrap();
+        assert!(middle > 0.02 && middle < 0.03, "{}", middle);
+        assert!(close(
+            hypergeometric_at_least(n, n / 2, 1000, 0).unwrap(),
+            1.0
+        ));
+        assert!(hypergeometric_probability(n + 1, 1, 1, 1).is_err());
+        assert!(hypergeometric_probability(10, 11, 1, 1).is_err());
+        assert!(hypergeometric_probability(10, 1, 11, 1).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! Complex numbers and a calculator that works over them.
+//!
+//! [`ComplexCalculator`] is the complex mode: its `sqrt` of a negative real
+//! returns an imaginary result (`sqrt(-4) = 2i`) where [`crate::Calculator`]
+//! reports [`CalcError::NegativeSqrt`].
+
+use std::fmt;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn div(&self, other: &Complex) -> Result<Complex, CalcError> {
+        let denom = other.re * other.re + other.im * other.im;
+        if denom == 0.0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        Ok(Complex {
+            re: (self.re * other.re + self.im * other.im) / denom,
+            im: (self.im * other.re - self.re * other.im) / denom,
+        })
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Complex {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// Builds `r * (cos theta + i sin theta)`; `theta` is in radians.
+    pub fn from_polar(r: f64, theta: f64) -> Complex {
+        Complex {
+            re: r * theta.cos(),
+            im: r * theta.sin(),
+        }
+    }
+
+    /// `[modulus, argument]`.
+    pub fn to_polar(&self) -> Vec<f64> {
+        vec![self.modulus(), self.argument()]
+    }
+
+    pub fn add(&self, other: &Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+
+    pub fn sub(&self, other: &Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+
+    pub fn mul(&self, other: &Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = div)]
+    pub fn div_js(&self, other: &Complex) -> Result<Complex, CalcErrorJs> {
+        self.div(other).map_err(CalcErrorJs::from)
+    }
+
+    /// `|z|`, computed without intermediate overflow.
+    pub fn modulus(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// Angle in radians in `(-pi, pi]`.
+    pub fn argument(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conjugate(&self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    /// Principal square root (non-negative real part).
+    pub fn sqrt(&self) -> Complex {
+        if self.im == 0.0 {
+            return if self.re >= 0.0 {
+                Complex::new(self.re.sqrt(), 0.0)
+            } else {
+                Complex::new(0.0, (-self.re).sqrt())
+            };
+        }
+        let m = self.modulus();
+        let re = ((m + self.re) / 2.0).sqrt();
+        let im = ((m - self.re) / 2.0).sqrt().copysign(self.im);
+        Complex::new(re, im)
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0.0
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// `3`, `2i`, `1 + 2i`, `1 - 2i`.
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.im == 0.0 {
+            write!(f, "{}", self.re)
+        } else if self.re == 0.0 {
+            write!(f, "{}i", self.im)
+        } else if self.im < 0.0 {
+            write!(f, "{} - {}i", self.re, -self.im)
+        } else {
+            write!(f, "{} + {}i", self.re, self.im)
+        }
+    }
+}
+
+/// Running-value calculator over complex numbers.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Default)]
+pub struct ComplexCalculator {
+    value: Complex,
+}
+
+impl ComplexCalculator {
+    pub fn divide(&mut self, operand: &Complex) -> Result<Complex, CalcError> {
+        self.value = self.value.div(operand)?;
+        Ok(self.value)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ComplexCalculator {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> ComplexCalculator {
+        ComplexCalculator::default()
+    }
+
+    pub fn get_value(&self) -> Complex {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: &Complex) {
+        self.value = *value;
+    }
+
+    /// Sets the value from a real number, the usual way into complex mode.
+    pub fn set_real(&mut self, value: f64) {
+        self.value = Complex::new(value, 0.0);
+    }
+
+    pub fn add(&mut self, operand: &Complex) -> Complex {
+        self.value = self.value.add(operand);
+        self.value
+    }
+
+    pub fn subtract(&mut self, operand: &Complex) -> Complex {
+        self.value = self.value.sub(operand);
+        self.value
+    }
+
+    pub fn multiply(&mut self, operand: &Complex) -> Complex {
+        self.value = self.value.mul(operand);
+        self.value
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = divide)]
+    pub fn divide_js(&mut self, operand: &Complex) -> Result<Complex, CalcErrorJs> {
+        self.divide(operand).map_err(CalcErrorJs::from)
+    }
+
+    /// Never fails: negative reals give an imaginary root.
+    pub fn sqrt(&mut self) -> Complex {
+        self.value = self.value.sqrt();
+        self.value
+    }
+
+    pub fn conjugate(&mut self) -> Complex {
+        self.value = self.value.conjugate();
+        self.value
+    }
+
+    pub fn modulus(&self) -> f64 {
+        self.value.modulus()
+    }
+
+    pub fn argument(&self) -> f64 {
+        self.value.argument()
+    }
+
+    pub fn clear(&mut self) {
+        self.value = Complex::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a.re - b.re).abs() < 1e-12 && (a.im - b.im).abs() < 1e-12
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Complex::new(1.0, 2.0);
+        let b = Complex::new(3.0, -1.0);
+        assert_eq!(a.add(&b), Complex::new(4.0, 1.0));
+        assert_eq!(a.sub(&b), Complex::new(-2.0, 3.0));
+        assert_eq!(a.mul(&b), Complex::new(5.0, 5.0));
+        assert!(close(a.mul(&b).div(&b).unwrap(), a));
+        assert_eq!(a.div(&Complex::default()), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_modulus_argument_conjugate() {
+        let z = Complex::new(3.0, 4.0);
+        assert_eq!(z.modulus(), 5.0);
+        assert_eq!(z.conjugate(), Complex::new(3.0, -4.0));
+        assert_eq!(Complex::new(0.0, 1.0).argument(), PI / 2.0);
+        assert_eq!(Complex::new(-1.0, 0.0).argument(), PI);
+    }
+
+    #[test]
+    fn test_polar_round_trip() {
+        let z = Complex::new(-2.0, 1.5);
+        let polar = z.to_polar();
+        assert!(close(Complex::from_polar(polar[0], polar[1]), z));
+        assert!(close(
+            Complex::from_polar(2.0, PI / 2.0),
+            Complex::new(0.0, 2.0)
+        ));
+    }
+
+    #[test]
+    fn test_sqrt_of_negative_is_imaginary() {
+        assert_eq!(Complex::new(-4.0, 0.0).sqrt(), Complex::new(0.0, 2.0));
+        assert_eq!(Complex::new(9.0, 0.0).sqrt(), Complex::new(3.0, 0.0));
+        let z = Complex::new(3.0, -4.0);
+        let root = z.sqrt();
+        assert!(close(root, Complex::new(2.0, -1.0)));
+        assert!(close(root.mul(&root), z));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Complex::new(0.0, 2.0).to_string(), "2i");
+        assert_eq!(Complex::new(3.0, 0.0).to_string(), "3");
+        assert_eq!(Complex::new(1.0, -2.5).to_string(), "1 - 2.5i");
+        assert_eq!(Complex::new(1.0, 2.0).to_js_string(), "1 + 2i");
+    }
+
+    #[test]
+    fn test_complex_calculator_mode() {
+        let mut calc = ComplexCalculator::new();
+        calc.set_real(-4.0);
+        assert_eq!(calc.sqrt(), Complex::new(0.0, 2.0));
+        calc.multiply(&Complex::new(0.0, 1.0));
+        assert_eq!(calc.get_value(), Complex::new(-2.0, 0.0));
+        calc.add(&Complex::new(0.0, 3.0));
+        assert_eq!(calc.conjugate(), Complex::new(-2.0, -3.0));
+        assert_eq!(
+            calc.divide(&Complex::default()),
+            Err(CalcError::DivisionByZero)
+        );
+        assert_eq!(calc.get_value(), Complex::new(-2.0, -3.0));
+        calc.clear();
+        assert_eq!(calc.modulus(), 0.0);
+    }
+}
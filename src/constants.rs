@@ -0,0 +1,195 @@
+//! Mathematical and physical constants by name.
+//!
+//! Physical values are CODATA 2018; the SI defining constants (`c`, `h`,
+//! `k`, `e_charge`, `avogadro`) are exact. Names are matched ignoring case
+//! and `_`/`-`/space, so `speed_of_light`, `Speed Of Light` and
+//! `speedoflight` are the same constant; symbols (`c`, `G`, `g`) are
+//! matched exactly because `G` and `g` differ.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+/// One entry of [`CONSTANTS`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Constant {
+    pub name: &'static str,
+    pub symbol: &'static str,
+    pub value: f64,
+    /// SI unit, empty for dimensionless numbers.
+    pub unit: &'static str,
+}
+
+const fn c(name: &'static str, symbol: &'static str, value: f64, unit: &'static str) -> Constant {
+    Constant {
+        name,
+        symbol,
+        value,
+        unit,
+    }
+}
+
+pub const CONSTANTS: &[Constant] = &[
+    c("pi", "\u{3c0}", std::f64::consts::PI, ""),
+    c("e", "e", std::f64::consts::E, ""),
+    c("tau", "\u{3c4}", std::f64::consts::TAU, ""),
+    c("phi", "\u{3c6}", 1.618_033_988_749_895, ""),
+    c("sqrt2", "\u{221a}2", std::f64::consts::SQRT_2, ""),
+    c("ln2", "ln2", std::f64::consts::LN_2, ""),
+    c("ln10", "ln10", std::f64::consts::LN_10, ""),
+    c("euler_gamma", "\u{3b3}", 0.577_215_664_901_532_9, ""),
+    c("speed_of_light", "c", 299_792_458.0, "m/s"),
+    c("planck", "h", 6.626_070_15e-34, "J s"),
+    c("reduced_planck", "\u{127}", 1.054_571_817e-34, "J s"),
+    c("boltzmann", "k", 1.380_649e-23, "J/K"),
+    c("avogadro", "NA", 6.022_140_76e23, "1/mol"),
+    c("elementary_charge", "e_charge", 1.602_176_634e-19, "C"),
+    c("gas_constant", "R", 8.314_462_618_153_24, "J/(mol K)"),
+    c("faraday", "F", 96_485.332_123_310_02, "C/mol"),
+    c("gravitational_constant", "G", 6.674_30e-11, "m^3/(kg s^2)"),
+    c("standard_gravity", "g", 9.806_65, "m/s^2"),
+    c("electron_mass", "m_e", 9.109_383_701_5e-31, "kg"),
+    c("proton_mass", "m_p", 1.672_621_923_69e-27, "kg"),
+    c("neutron_mass", "m_n", 1.674_927_498_04e-27, "kg"),
+    c("atomic_mass_unit", "u", 1.660_539_066_60e-27, "kg"),
+    c(
+        "vacuum_permittivity",
+        "\u{3b5}0",
+        8.854_187_812_8e-12,
+        "F/m",
+    ),
+    c(
+        "vacuum_permeability",
+        "\u{3bc}0",
+        1.256_637_062_12e-6,
+        "N/A^2",
+    ),
+    c("fine_structure", "\u{3b1}", 7.297_352_569_3e-3, ""),
+    c(
+        "stefan_boltzmann",
+        "\u{3c3}",
+        5.670_374_419e-8,
+        "W/(m^2 K^4)",
+    ),
+    c("rydberg", "R_inf", 10_973_731.568_160, "1/m"),
+    c("bohr_radius", "a0", 5.291_772_109_03e-11, "m"),
+    c("standard_atmosphere", "atm", 101_325.0, "Pa"),
+];
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Looks up a constant by name or symbol.
+pub fn lookup(name: &str) -> Result<&'static Constant, CalcError> {
+    let key = normalize(name);
+    CONSTANTS
+        .iter()
+        .find(|c| c.symbol == name.trim())
+        .or_else(|| CONSTANTS.iter().find(|c| normalize(c.name) == key))
+        .ok_or_else(|| CalcError::InvalidArgument(format!("Unknown constant '{}'", name)))
+}
+
+/// The value of a constant by name or symbol.
+pub fn constant(name: &str) -> Result<f64, CalcError> {
+    lookup(name).map(|c| c.value)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = constant)]
+pub fn constant_js(name: &str) -> Result<f64, CalcErrorJs> {
+    constant(name).map_err(CalcErrorJs::from)
+}
+
+/// Every constant as `[{ name, symbol, value, unit }]`, for building a picker.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = constants)]
+pub fn constants_js() -> JsValue {
+    serde_wasm_bindgen::to_value(CONSTANTS).unwrap_or(JsValue::NULL)
+}
+
+impl Calculator {
+    /// Replaces the current value with a named constant, recorded in the
+    /// history under the constant's canonical name.
+    pub fn insert_constant(&mut self, name: &str) -> Result<f64, CalcError> {
+        self.permit_name("constant")?;
+        let constant = lookup(name).map_err(|e| self.fail(e, None))?;
+        self.add_to_history(
+            Operation::Constant(constant.name.to_string()),
+            self.current_value,
+            Some(constant.value),
+            constant.value,
+        );
+        self.current_value = constant.value;
+        Ok(constant.value)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = insert_constant)]
+    pub fn insert_constant_js(&mut self, name: &str) -> Result<f64, CalcErrorJs> {
+        self.insert_constant(name).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_by_name_and_symbol() {
+        assert_eq!(constant("pi"), Ok(std::f64::consts::PI));
+        assert_eq!(constant("Avogadro"), Ok(6.022_140_76e23));
+        assert_eq!(constant("speed of light"), Ok(299_792_458.0));
+        assert_eq!(constant("c"), Ok(299_792_458.0));
+        assert_eq!(constant("G"), Ok(6.674_30e-11));
+        assert_eq!(constant("g"), Ok(9.806_65));
+        assert_eq!(constant("\u{3c6}"), constant("phi"));
+        assert!(matches!(
+            constant("unobtainium"),
+            Err(CalcError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_names_and_symbols_are_unique() {
+        for (i, a) in CONSTANTS.iter().enumerate() {
+            for b in &CONSTANTS[i + 1..] {
+                assert_ne!(normalize(a.name), normalize(b.name));
+                assert_ne!(a.symbol, b.symbol);
+            }
+        }
+        // Derived constants agree with the exact ones they come from.
+        let r = constant("NA").unwrap() * constant("k").unwrap();
+        assert!((r - constant("R").unwrap()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_insert_constant_records_history() {
+        let mut calc = Calculator::new();
+        calc.set_value(2.0);
+        assert_eq!(calc.insert_constant("PI"), Ok(std::f64::consts::PI));
+        calc.multiply(2.0);
+        assert_eq!(calc.get_value(), std::f64::consts::TAU);
+        let entry = &calc.history_range(1, 1)[0];
+        assert_eq!(entry.operation, Operation::Constant("pi".to_string()));
+        assert_eq!(entry.operand1, 2.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+
+        assert!(calc.insert_constant("nope").is_err());
+        assert_eq!(calc.history_count(), 3);
+        assert_eq!(
+            calc.apply(&Operation::Constant("e".to_string()), None),
+            Ok(std::f64::consts::E)
+        );
+    }
+}
@@ -0,0 +1,859 @@
+//! Currency helpers: till counting and exchange-rate conversion.
+//!
+//! Till amounts and denominations are converted to whole cents once, up
+//! front, so `0.1 + 0.2` style drift never leaks into a count.
+//!
+//! [`CurrencyConverter`] holds one quote per currency against a base
+//! currency, as units of that currency per one base unit (`EUR` at `0.92`
+//! with base `USD`). Any other pair is triangulated through the base. Each
+//! quote has a bid and an ask; [`CurrencyConverter::exchange`] fills at the
+//! side that costs the customer, [`CurrencyConverter::convert`] uses the mid.
+//! Every quote loaded is also kept by timestamp, so
+//! [`CurrencyConverter::average_rate`] and
+//! [`CurrencyConverter::rate_change_percent`] can look back over a period.
+//!
+//! Token amounts (18-decimal ERC-20 balances, satoshis) overflow the 53-bit
+//! integer range of an `f64`, so [`to_smallest_unit`] and
+//! [`from_smallest_unit`] work on decimal strings digit by digit and are
+//! exact at any size. From JS, pass the result to `BigInt()`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{now_ms, CalcError};
+
+// Exhaustive search for an exact breakdown is only tried up to this many cents.
+const MAX_EXACT_SEARCH_CENTS: i64 = 1_000_000;
+
+// Enough for a uint256 balance written entirely as a fraction.
+const MAX_UNIT_DECIMALS: u32 = 78;
+
+/// How many of one bill or coin.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DenominationCount {
+    pub denomination: f64,
+    pub count: u64,
+}
+
+/// Counts per denomination, largest first, plus whatever is left over when
+/// the amount cannot be made exactly from the set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Breakdown {
+    pub counts: Vec<DenominationCount>,
+    pub remainder: f64,
+}
+
+/// Converts `amount` to whole cents, rejecting sub-cent precision.
+pub fn to_cents(amount: f64) -> Result<i64, CalcError> {
+    let cents = (amount * 100.0).round();
+    if !amount.is_finite() || cents.abs() > i64::MAX as f64 / 2.0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "Invalid amount {}",
+            amount
+        )));
+    }
+    if (amount * 100.0 - cents).abs() > 1e-6 * cents.abs().max(1.0) {
+        return Err(CalcError::InvalidArgument(format!(
+            "{} has more precision than whole cents",
+            amount
+        )));
+    }
+    Ok(cents as i64)
+}
+
+/// Bills and coins in general circulation for `code` (USD, EUR, GBP).
+pub fn standard_denominations(code: &str) -> Result<Vec<f64>, CalcError> {
+    let set: &[f64] = match code.to_ascii_uppercase().as_str() {
+        "USD" => &[100.0, 50.0, 20.0, 10.0, 5.0, 1.0, 0.25, 0.10, 0.05, 0.01],
+        "EUR" => &[
+            500.0, 200.0, 100.0, 50.0, 20.0, 10.0, 5.0, 2.0, 1.0, 0.50, 0.20, 0.10, 0.05, 0.02,
+            0.01,
+        ],
+        "GBP" => &[
+            50.0, 20.0, 10.0, 5.0, 2.0, 1.0, 0.50, 0.20, 0.10, 0.05, 0.02, 0.01,
+        ],
+        _ => {
+            return Err(CalcError::InvalidArgument(format!(
+                "No standard denominations for '{}'",
+                code
+            )))
+        }
+    };
+    Ok(set.to_vec())
+}
+
+// Fewest pieces that make exactly `target`, or None.
+fn exact_counts(target: i64, denominations: &[i64]) -> Option<Vec<u64>> {
+    let size = target as usize + 1;
+    let mut best = vec![u32::MAX; size];
+    let mut last = vec![usize::MAX; size];
+    best[0] = 0;
+    for amount in 1..size {
+        for (i, &d) in denominations.iter().enumerate() {
+            let d = d as usize;
+            if d <= amount && best[amount - d] != u32::MAX && best[amount - d] + 1 < best[amount] {
+                best[amount] = best[amount - d] + 1;
+                last[amount] = i;
+            }
+        }
+    }
+    if best[target as usize] == u32::MAX {
+        return None;
+    }
+    let mut counts = vec![0u64; denominations.len()];
+    let mut amount = target as usize;
+    while amount > 0 {
+        let i = last[amount];
+        counts[i] += 1;
+        amount -= denominations[i] as usize;
+    }
+    Some(counts)
+}
+
+/// Splits `amount` into `denominations` (any order, duplicates ignored).
+/// Uses the greedy largest-first count, which is what a cashier does and is
+/// optimal for real currency sets. For custom sets where greedy leaves a
+/// remainder, an exact combination is searched for (up to 10,000.00).
+pub fn denomination_breakdown(amount: f64, denominations: &[f64]) -> Result<Breakdown, CalcError> {
+    let target = to_cents(amount)?;
+    if target < 0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "Amount must not be negative, got {}",
+            amount
+        )));
+    }
+    let mut cents = Vec::with_capacity(denominations.len());
+    for &d in denominations {
+        let c = to_cents(d)?;
+        if c <= 0 {
+            return Err(CalcError::InvalidArgument(format!(
+                "Denominations must be positive, got {}",
+                d
+            )));
+        }
+        cents.push(c);
+    }
+    if cents.is_empty() {
+        return Err(CalcError::InvalidArgument(
+            "No denominations given".to_string(),
+        ));
+    }
+    cents.sort_unstable_by(|a, b| b.cmp(a));
+    cents.dedup();
+
+    let mut left = target;
+    let mut counts: Vec<u64> = cents
+        .iter()
+        .map(|&d| {
+            let n = left / d;
+            left -= n * d;
+            n as u64
+        })
+        .collect();
+    if left != 0 && target <= MAX_EXACT_SEARCH_CENTS {
+        if let Some(exact) = exact_counts(target, &cents) {
+            counts = exact;
+            left = 0;
+        }
+    }
+    Ok(Breakdown {
+        counts: cents
+            .iter()
+            .zip(counts)
+            .map(|(&d, count)| DenominationCount {
+                denomination: d as f64 / 100.0,
+                count,
+            })
+            .collect(),
+        remainder: left as f64 / 100.0,
+    })
+}
+
+/// `{ counts: [{ denomination, count }], remainder }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = denomination_breakdown)]
+pub fn denomination_breakdown_js(
+    amount: f64,
+    denominations: &[f64],
+) -> Result<JsValue, CalcErrorJs> {
+    let breakdown = denomination_breakdown(amount, denominations)
+        .map_err(|e| CalcErrorJs::new(e, Some(amount)))?;
+    serde_wasm_bindgen::to_value(&breakdown).map_err(|e| {
+        CalcErrorJs::from(CalcError::InvalidArgument(format!(
+            "Could not serialize breakdown: {}",
+            e
+        )))
+    })
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = standard_denominations)]
+pub fn standard_denominations_js(code: &str) -> Result<Vec<f64>, CalcErrorJs> {
+    standard_denominations(code).map_err(CalcErrorJs::from)
+}
+
+// Splits a plain decimal into sign, integer digits and fraction digits,
+// with the integer part's leading zeros removed.
+pub(crate) fn split_decimal(
+    text: &str,
+    allow_fraction: bool,
+) -> Result<(bool, &str, &str), CalcError> {
+    let trimmed = text.trim();
+    let offset = text.len() - text.trim_start().len();
+    let (negative, rest) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let start = offset + (trimmed.len() - rest.len());
+    let (int, frac) = match rest.split_once('.') {
+        Some((int, frac)) if allow_fraction => (int, frac),
+        _ => (rest, ""),
+    };
+    for (i, c) in rest.char_indices() {
+        let is_point = allow_fraction && i == int.len();
+        if !(c.is_ascii_digit() || is_point) {
+            return Err(CalcError::Syntax {
+                message: format!("Invalid character '{}' in amount", c),
+                position: start + i,
+            });
+        }
+    }
+    if int.is_empty() && frac.is_empty() {
+        return Err(CalcError::Syntax {
+            message: "Expected digits".to_string(),
+            position: start,
+        });
+    }
+    Ok((negative, int.trim_start_matches('0'), frac))
+}
+
+fn check_unit_decimals(decimals: u32) -> Result<(), CalcError> {
+    if decimals > MAX_UNIT_DECIMALS {
+        return Err(CalcError::InvalidArgument(format!(
+            "Decimals must be 0-{}, got {}",
+            MAX_UNIT_DECIMALS, decimals
+        )));
+    }
+    Ok(())
+}
+
+fn signed(negative: bool, digits: String) -> String {
+    if negative && digits != "0" {
+        format!("-{}", digits)
+    } else {
+        digits
+    }
+}
+
+/// Converts a whole-unit decimal string to an integer count of smallest
+/// units: `to_smallest_unit("1.5", 18)` is `"1500000000000000000"`. Fails
+/// rather than rounds when `amount` has more than `decimals` significant
+/// fraction digits.
+pub fn to_smallest_unit(amount: &str, decimals: u32) -> Result<String, CalcError> {
+    check_unit_decimals(decimals)?;
+    let (negative, int, frac) = split_decimal(amount, true)?;
+    let places = decimals as usize;
+    let (kept, dropped) = frac.split_at(frac.len().min(places));
+    if dropped.bytes().any(|b| b != b'0') {
+        return Err(CalcError::InvalidArgument(format!(
+            "{} has more than {} decimals",
+            amount.trim(),
+            decimals
+        )));
+    }
+    let digits = format!("{}{}{}", int, kept, "0".repeat(places - kept.len()));
+    let digits = digits.trim_start_matches('0');
+    let digits = if digits.is_empty() { "0" } else { digits };
+    Ok(signed(negative, digits.to_string()))
+}
+
+/// Converts an integer count of smallest units back to a whole-unit
+/// decimal string without trailing zeros: `from_smallest_unit("150000000", 8)`
+/// is `"1.5"`.
+pub fn from_smallest_unit(units: &str, decimals: u32) -> Result<String, CalcError> {
+    check_unit_decimals(decimals)?;
+    let (negative, digits, _) = split_decimal(units, false)?;
+    let places = decimals as usize;
+    let padded = format!("{:0>width$}", digits, width = places + 1);
+    let (int, frac) = padded.split_at(padded.len() - places);
+    let frac = frac.trim_end_matches('0');
+    let text = if frac.is_empty() {
+        int.to_string()
+    } else {
+        format!("{}.{}", int, frac)
+    };
+    Ok(signed(negative, text))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = to_smallest_unit)]
+pub fn to_smallest_unit_js(amount: &str, decimals: u32) -> Result<String, CalcErrorJs> {
+    to_smallest_unit(amount, decimals).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = from_smallest_unit)]
+pub fn from_smallest_unit_js(units: &str, decimals: u32) -> Result<String, CalcErrorJs> {
+    from_smallest_unit(units, decimals).map_err(CalcErrorJs::from)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quote {
+    bid: f64,
+    ask: f64,
+    // Milliseconds since the Unix epoch.
+    loaded_at: f64,
+}
+
+pub(crate) fn currency_code(code: &str) -> Result<String, CalcError> {
+    let code = code.trim();
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(CalcError::InvalidArgument(format!(
+            "Invalid currency code '{}'",
+            code
+        )));
+    }
+    Ok(code.to_ascii_uppercase())
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct CurrencyConverter {
+    base: String,
+    quotes: HashMap<String, Quote>,
+    // Mid rates by currency, as `(loaded_at, mid)` in time order.
+    history: HashMap<String, Vec<(f64, f64)>>,
+    max_age_ms: Option<f64>,
+}
+
+impl CurrencyConverter {
+    pub fn new(base: &str) -> Result<CurrencyConverter, CalcError> {
+        Ok(CurrencyConverter {
+            base: currency_code(base)?,
+            quotes: HashMap::new(),
+            history: HashMap::new(),
+            max_age_ms: None,
+        })
+    }
+
+    /// Loads a bid/ask quote fetched at `loaded_at` (ms since the epoch).
+    /// The quote is added to the history; it only replaces the current
+    /// quote if it is at least as recent, so older rates can be backfilled.
+    /// A second quote with the same timestamp replaces the first.
+    pub fn set_quote_at(
+        &mut self,
+        code: &str,
+        bid: f64,
+        ask: f64,
+        loaded_at: f64,
+    ) -> Result<(), CalcError> {
+        let code = currency_code(code)?;
+        if code == self.base {
+            return Err(CalcError::InvalidArgument(format!(
+                "{} is the base currency",
+                code
+            )));
+        }
+        if !(bid.is_finite() && ask.is_finite() && bid > 0.0 && ask >= bid) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Invalid quote for {}: bid {} ask {}",
+                code, bid, ask
+            )));
+        }
+        if !loaded_at.is_finite() {
+            return Err(CalcError::InvalidArgument(format!(
+                "Invalid timestamp for {}: {}",
+                code, loaded_at
+            )));
+        }
+        let series = self.history.entry(code.clone()).or_default();
+        let at = series.partition_point(|&(t, _)| t < loaded_at);
+        let mid = (bid + ask) / 2.0;
+        match series.get_mut(at) {
+            Some(entry) if entry.0 == loaded_at => entry.1 = mid,
+            _ => series.insert(at, (loaded_at, mid)),
+        }
+        let quote = Quote {
+            bid,
+            ask,
+            loaded_at,
+        };
+        match self.quotes.get(&code) {
+            Some(current) if current.loaded_at > loaded_at => {}
+            _ => {
+                self.quotes.insert(code, quote);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_quote(&mut self, code: &str, bid: f64, ask: f64) -> Result<(), CalcError> {
+        self.set_quote_at(code, bid, ask, now_ms())
+    }
+
+    /// Loads a rate with no spread.
+    pub fn set_rate(&mut self, code: &str, rate: f64) -> Result<(), CalcError> {
+        self.set_quote(code, rate, rate)
+    }
+
+    // The base currency is always fresh and quoted at 1.
+    fn quote_at(&self, code: &str, now: f64) -> Result<Quote, CalcError> {
+        let code = currency_code(code)?;
+        if code == self.base {
+            return Ok(Quote {
+                bid: 1.0,
+                ask: 1.0,
+                loaded_at: now,
+            });
+        }
+        let quote = *self
+            .quotes
+            .get(&code)
+            .ok_or_else(|| CalcError::InvalidArgument(format!("No rate loaded for {}", code)))?;
+        let age = now - quote.loaded_at;
+        if let Some(limit) = self.max_age_ms {
+            if age > limit {
+                return Err(CalcError::StaleRate(format!(
+                    "Rate for {} is {} ms old, limit is {} ms",
+                    code, age, limit
+                )));
+            }
+        }
+        Ok(quote)
+    }
+
+    /// Age of the loaded rate for `code` at `now`. Fails with
+    /// [`CalcError::StaleRate`] once it is older than the age limit.
+    pub fn rate_age_at(&self, code: &str, now: f64) -> Result<f64, CalcError> {
+        self.quote_at(code, now).map(|q| now - q.loaded_at)
+    }
+
+    pub fn rate_age(&self, code: &str) -> Result<f64, CalcError> {
+        self.rate_age_at(code, now_ms())
+    }
+
+    /// `(bid, ask)` for one unit of `from` in `to`, through the base.
+    pub fn cross_quote_at(&self, from: &str, to: &str, now: f64) -> Result<(f64, f64), CalcError> {
+        let from = self.quote_at(from, now)?;
+        let to = self.quote_at(to, now)?;
+        Ok((to.bid / from.ask, to.ask / from.bid))
+    }
+
+    pub fn cross_quote(&self, from: &str, to: &str) -> Result<(f64, f64), CalcError> {
+        self.cross_quote_at(from, to, now_ms())
+    }
+
+    /// `amount` of `from` in `to` at the mid rate.
+    pub fn convert_at(
+        &self,
+        amount: f64,
+        from: &str,
+        to: &str,
+        now: f64,
+    ) -> Result<f64, CalcError> {
+        let from = self.quote_at(from, now)?;
+        let to = self.quote_at(to, now)?;
+        Ok(amount / ((from.bid + from.ask) / 2.0) * ((to.bid + to.ask) / 2.0))
+    }
+
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Result<f64, CalcError> {
+        self.convert_at(amount, from, to, now_ms())
+    }
+
+    /// What a customer receives in `to` for `amount` of `from`, paying
+    /// the spread on both legs.
+    pub fn exchange_at(
+        &self,
+        amount: f64,
+        from: &str,
+        to: &str,
+        now: f64,
+    ) -> Result<f64, CalcError> {
+        self.cross_quote_at(from, to, now)
+            .map(|(bid, _)| amount * bid)
+    }
+
+    pub fn exchange(&self, amount: f64, from: &str, to: &str) -> Result<f64, CalcError> {
+        self.exchange_at(amount, from, to, now_ms())
+    }
+
+    // The mid cross rate of `pair` ("EUR/JPY": JPY per EUR) each time
+    // either leg was quoted between `start` and `end`, inclusive. Each
+    // leg is taken at its latest quote up to that time; times before both
+    // legs have a quote are skipped.
+    fn rate_series(&self, pair: &str, start: f64, end: f64) -> Result<Vec<f64>, CalcError> {
+        let (from, to) = match pair.split_once('/') {
+            Some((from, to)) => (currency_code(from)?, currency_code(to)?),
+            None => {
+                return Err(CalcError::InvalidArgument(format!(
+                    "Invalid currency pair '{}', expected e.g. EUR/USD",
+                    pair
+                )))
+            }
+        };
+        if !(start.is_finite() && end.is_finite() && start <= end) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Invalid period: {} to {}",
+                start, end
+            )));
+        }
+        let leg = |code: &str| -> Result<Option<&[(f64, f64)]>, CalcError> {
+            if *code == self.base {
+                return Ok(None);
+            }
+            self.history
+                .get(code)
+                .map(|series| Some(series.as_slice()))
+                .ok_or_else(|| CalcError::InvalidArgument(format!("No rate loaded for {}", code)))
+        };
+        let (from_series, to_series) = (leg(&from)?, leg(&to)?);
+        let mid_at = |series: Option<&[(f64, f64)]>, t: f64| match series {
+            None => Some(1.0),
+            Some(series) => match series.partition_point(|&(loaded_at, _)| loaded_at <= t) {
+                0 => None,
+                n => Some(series[n - 1].1),
+            },
+        };
+        let mut times: Vec<f64> = from_series
+            .into_iter()
+            .chain(to_series)
+            .flatten()
+            .map(|&(t, _)| t)
+            .filter(|t| (start..=end).contains(t))
+            .collect();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+        let rates: Vec<f64> = times
+            .into_iter()
+            .filter_map(|t| Some(mid_at(to_series, t)? / mid_at(from_series, t)?))
+            .collect();
+        if rates.is_empty() {
+            return Err(CalcError::InvalidArgument(format!(
+                "No {}/{} rates between {} and {}",
+                from, to, start, end
+            )));
+        }
+        Ok(rates)
+    }
+
+    /// Mean of the mid rates of `pair` ("EUR/JPY" is JPY per EUR) quoted
+    /// between `start` and `end` (ms since the epoch, inclusive). Each
+    /// quote counts once, however long it stood.
+    pub fn average_rate(&self, pair: &str, start: f64, end: f64) -> Result<f64, CalcError> {
+        let rates = self.rate_series(pair, start, end)?;
+        Ok(rates.iter().sum::<f64>() / rates.len() as f64)
+    }
+
+    /// Percent change of the mid rate of `pair` from its first quote
+    /// between `start` and `end` to its last.
+    pub fn rate_change_percent(&self, pair: &str, start: f64, end: f64) -> Result<f64, CalcError> {
+        let rates = self.rate_series(pair, start, end)?;
+        let (first, last) = (rates[0], rates[rates.len() - 1]);
+        Ok((last - first) / first * 100.0)
+    }
+
+    /// Spread of the `from`/`to` cross as a percentage of its mid.
+    pub fn spread(&self, from: &str, to: &str) -> Result<f64, CalcError> {
+        let (bid, ask) = self.cross_quote(from, to)?;
+        Ok((ask - bid) / ((ask + bid) / 2.0) * 100.0)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl CurrencyConverter {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new_js(base: &str) -> Result<CurrencyConverter, CalcErrorJs> {
+        CurrencyConverter::new(base).map_err(CalcErrorJs::from)
+    }
+
+    pub fn base(&self) -> String {
+        self.base.clone()
+    }
+
+    /// Rates older than `ms` make conversions fail with `STALE_RATE`;
+    /// 0 removes the limit.
+    pub fn set_max_rate_age(&mut self, ms: f64) {
+        self.max_age_ms = (ms > 0.0).then_some(ms);
+    }
+
+    pub fn max_rate_age(&self) -> f64 {
+        self.max_age_ms.unwrap_or(0.0)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_quote)]
+    pub fn set_quote_js(&mut self, code: &str, bid: f64, ask: f64) -> Result<(), CalcErrorJs> {
+        self.set_quote(code, bid, ask)
+            .map_err(|e| CalcErrorJs::new(e, Some(bid)))
+    }
+
+    /// Like `set_quote`, for a rate fetched at `loaded_at` (ms since the epoch).
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_quote_at)]
+    pub fn set_quote_at_js(
+        &mut self,
+        code: &str,
+        bid: f64,
+        ask: f64,
+        loaded_at: f64,
+    ) -> Result<(), CalcErrorJs> {
+        self.set_quote_at(code, bid, ask, loaded_at)
+            .map_err(|e| CalcErrorJs::new(e, Some(bid)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_rate)]
+    pub fn set_rate_js(&mut self, code: &str, rate: f64) -> Result<(), CalcErrorJs> {
+        self.set_rate(code, rate)
+            .map_err(|e| CalcErrorJs::new(e, Some(rate)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = rate_age)]
+    pub fn rate_age_js(&self, code: &str) -> Result<f64, CalcErrorJs> {
+        self.rate_age(code).map_err(CalcErrorJs::from)
+    }
+
+    /// `[bid, ask]` for one unit of `from` in `to`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = cross_quote)]
+    pub fn cross_quote_js(&self, from: &str, to: &str) -> Result<Vec<f64>, CalcErrorJs> {
+        self.cross_quote(from, to)
+            .map(|(bid, ask)| vec![bid, ask])
+            .map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = convert)]
+    pub fn convert_js(&self, amount: f64, from: &str, to: &str) -> Result<f64, CalcErrorJs> {
+        self.convert(amount, from, to)
+            .map_err(|e| CalcErrorJs::new(e, Some(amount)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = exchange)]
+    pub fn exchange_js(&self, amount: f64, from: &str, to: &str) -> Result<f64, CalcErrorJs> {
+        self.exchange(amount, from, to)
+            .map_err(|e| CalcErrorJs::new(e, Some(amount)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = spread)]
+    pub fn spread_js(&self, from: &str, to: &str) -> Result<f64, CalcErrorJs> {
+        self.spread(from, to).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = average_rate)]
+    pub fn average_rate_js(&self, pair: &str, start: f64, end: f64) -> Result<f64, CalcErrorJs> {
+        self.average_rate(pair, start, end)
+            .map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = rate_change_percent)]
+    pub fn rate_change_percent_js(
+        &self,
+        pair: &str,
+        start: f64,
+        end: f64,
+    ) -> Result<f64, CalcErrorJs> {
+        self.rate_change_percent(pair, start, end)
+            .map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(b: &Breakdown) -> Vec<u64> {
+        b.counts.iter().map(|c| c.count).collect()
+    }
+
+    #[test]
+    fn test_usd_breakdown_is_exact() {
+        let usd = standard_denominations("usd").unwrap();
+        let b = denomination_breakdown(188.41, &usd).unwrap();
+        assert_eq!(counts(&b), vec![1, 1, 1, 1, 1, 3, 1, 1, 1, 1]);
+        assert_eq!(b.remainder, 0.0);
+        // 0.1 + 0.2 style amounts still land on whole cents.
+        let b = denomination_breakdown(0.1 + 0.2, &usd).unwrap();
+        assert_eq!(counts(&b), vec![0, 0, 0, 0, 0, 0, 1, 0, 1, 0]);
+        assert_eq!(b.remainder, 0.0);
+    }
+
+    #[test]
+    fn test_custom_set_any_order() {
+        let b = denomination_breakdown(30.0, &[10.0, 25.0]).unwrap();
+        assert_eq!(b.counts[0].denomination, 25.0);
+        // Greedy would give 25 + 5 left over; 3 x 10 is exact.
+        assert_eq!(counts(&b), vec![0, 3]);
+        assert_eq!(b.remainder, 0.0);
+    }
+
+    #[test]
+    fn test_remainder_when_not_representable() {
+        let b = denomination_breakdown(1.03, &[1.0, 0.05]).unwrap();
+        assert_eq!(counts(&b), vec![1, 0]);
+        assert_eq!(b.remainder, 0.03);
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        assert!(denomination_breakdown(-1.0, &[1.0]).is_err());
+        assert!(denomination_breakdown(1.0, &[]).is_err());
+        assert!(denomination_breakdown(1.0, &[0.0]).is_err());
+        assert!(denomination_breakdown(1.0, &[0.001]).is_err());
+        assert!(denomination_breakdown(1.005, &[1.0]).is_err());
+        assert!(standard_denominations("XYZ").is_err());
+    }
+
+    fn converter() -> CurrencyConverter {
+        let mut fx = CurrencyConverter::new("usd").unwrap();
+        fx.set_quote_at("EUR", 0.90, 0.92, 1_000.0).unwrap();
+        fx.set_quote_at("jpy", 148.0, 150.0, 1_000.0).unwrap();
+        fx
+    }
+
+    #[test]
+    fn test_triangulates_through_base() {
+        let fx = converter();
+        assert_eq!(fx.base(), "USD");
+        assert_eq!(fx.convert_at(100.0, "USD", "EUR", 1_000.0), Ok(91.0));
+        let eur_jpy = fx.convert_at(91.0, "EUR", "JPY", 1_000.0).unwrap();
+        assert!((eur_jpy - 14_900.0).abs() < 1e-9);
+        assert_eq!(fx.convert_at(5.0, "jpy", "jpy", 1_000.0), Ok(5.0));
+        assert!(matches!(
+            fx.convert_at(1.0, "USD", "GBP", 1_000.0),
+            Err(CalcError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_exchange_pays_the_spread() {
+        let fx = converter();
+        let (bid, ask) = fx.cross_quote_at("EUR", "JPY", 1_000.0).unwrap();
+        assert!((bid - 148.0 / 0.92).abs() < 1e-9);
+        assert!((ask - 150.0 / 0.90).abs() < 1e-9);
+        // A round trip through the spread always loses money.
+        let jpy = fx.exchange_at(100.0, "EUR", "JPY", 1_000.0).unwrap();
+        let back = fx.exchange_at(jpy, "JPY", "EUR", 1_000.0).unwrap();
+        assert!(back < 100.0);
+        assert_eq!(fx.exchange_at(100.0, "USD", "EUR", 1_000.0), Ok(90.0));
+    }
+
+    #[test]
+    fn test_rate_age_limit() {
+        let mut fx = converter();
+        assert_eq!(fx.rate_age_at("EUR", 61_000.0), Ok(60_000.0));
+        assert_eq!(fx.rate_age_at("USD", 61_000.0), Ok(0.0));
+        fx.set_max_rate_age(30_000.0);
+        assert_eq!(fx.rate_age_at("EUR", 31_000.0), Ok(30_000.0));
+        let err = fx.rate_age_at("EUR", 61_000.0).unwrap_err();
+        assert_eq!(err.code(), "STALE_RATE");
+        assert!(fx.convert_at(1.0, "USD", "EUR", 61_000.0).is_err());
+        fx.set_quote_at("EUR", 0.91, 0.91, 60_000.0).unwrap();
+        assert_eq!(fx.convert_at(100.0, "USD", "EUR", 61_000.0), Ok(91.0));
+        fx.set_max_rate_age(0.0);
+        assert_eq!(fx.max_rate_age(), 0.0);
+        assert!(fx.convert_at(1.0, "USD", "JPY", 1e12).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_quotes() {
+        let mut fx = converter();
+        assert!(fx.set_quote_at("USD", 1.0, 1.0, 0.0).is_err());
+        assert!(fx.set_quote_at("GBP", 0.8, 0.7, 0.0).is_err());
+        assert!(fx.set_quote_at("GBP", 0.0, 0.7, 0.0).is_err());
+        assert!(fx.set_rate("GB1", 0.8).is_err());
+        assert!(CurrencyConverter::new("").is_err());
+        fx.set_rate("GBP", 0.8).unwrap();
+        assert_eq!(fx.spread("USD", "GBP"), Ok(0.0));
+    }
+
+    #[test]
+    fn test_rate_history() {
+        let mut fx = converter();
+        fx.set_rate("GBP", 0.80).unwrap();
+        fx.set_quote_at("EUR", 0.92, 0.94, 2_000.0).unwrap();
+        fx.set_quote_at("EUR", 0.95, 0.95, 3_000.0).unwrap();
+        // Backfilled: kept in the history, but not the current quote.
+        fx.set_quote_at("EUR", 0.89, 0.89, 500.0).unwrap();
+        assert_eq!(fx.rate_age_at("EUR", 3_000.0), Ok(0.0));
+
+        assert_eq!(fx.average_rate("USD/EUR", 1_000.0, 3_000.0), Ok(0.93));
+        assert_eq!(fx.average_rate("usd/eur", 0.0, 1_000.0), Ok(0.9));
+        let change = fx.rate_change_percent("USD/EUR", 0.0, 3_000.0).unwrap();
+        assert!((change - (0.95 / 0.89 - 1.0) * 100.0).abs() < 1e-9);
+        let back = fx.rate_change_percent("EUR/USD", 1_000.0, 3_000.0).unwrap();
+        assert!((back - (0.91 / 0.95 - 1.0) * 100.0).abs() < 1e-9);
+
+        // A cross samples both legs whenever either was quoted.
+        fx.set_quote_at("JPY", 135.0, 135.0, 2_500.0).unwrap();
+        let avg = fx.average_rate("EUR/JPY", 1_000.0, 2_500.0).unwrap();
+        let expected = (149.0 / 0.91 + 149.0 / 0.93 + 135.0 / 0.93) / 3.0;
+        assert!((avg - expected).abs() < 1e-9);
+        assert_eq!(fx.rate_change_percent("EUR/EUR", 0.0, 1e12), Ok(0.0));
+
+        assert!(fx.average_rate("USD/EUR", 3_001.0, 4_000.0).is_err());
+        assert!(fx.average_rate("USD/EUR", 2_000.0, 1_000.0).is_err());
+        assert!(fx.average_rate("USDEUR", 0.0, 1.0).is_err());
+        assert!(fx.average_rate("USD/CHF", 0.0, 1.0).is_err());
+        assert!(fx.set_quote_at("EUR", 0.9, 0.9, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_smallest_unit_round_trip() {
+        let wei = to_smallest_unit("1.5", 18).unwrap();
+        assert_eq!(wei, "1500000000000000000");
+        assert_eq!(from_smallest_unit(&wei, 18).unwrap(), "1.5");
+        assert_eq!(to_smallest_unit("21000000", 8).unwrap(), "2100000000000000");
+        assert_eq!(to_smallest_unit(".00000001", 8).unwrap(), "1");
+        assert_eq!(to_smallest_unit("-0.25", 2).unwrap(), "-25");
+        assert_eq!(to_smallest_unit("-0.000", 2).unwrap(), "0");
+        assert_eq!(to_smallest_unit("1.10", 1).unwrap(), "11");
+        assert_eq!(from_smallest_unit("1", 8).unwrap(), "0.00000001");
+        assert_eq!(from_smallest_unit("-0100", 2).unwrap(), "-1");
+        assert_eq!(from_smallest_unit("0", 18).unwrap(), "0");
+        assert_eq!(from_smallest_unit("42", 0).unwrap(), "42");
+    }
+
+    #[test]
+    fn test_smallest_unit_is_exact_beyond_f64() {
+        // uint256 max, which no f64 can hold.
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        let whole = from_smallest_unit(max, 18).unwrap();
+        assert_eq!(
+            whole,
+            "115792089237316195423570985008687907853269984665640564039457.584007913129639935"
+        );
+        assert_eq!(to_smallest_unit(&whole, 18).unwrap(), max);
+    }
+
+    #[test]
+    fn test_smallest_unit_errors() {
+        assert!(matches!(
+            to_smallest_unit("0.123", 2),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            to_smallest_unit(" 1.2x", 2),
+            Err(CalcError::Syntax {
+                message: "Invalid character 'x' in amount".to_string(),
+                position: 4
+            })
+        );
+        assert!(to_smallest_unit("1.2.3", 2).is_err());
+        assert!(to_smallest_unit("", 2).is_err());
+        assert!(to_smallest_unit("1", 79).is_err());
+        assert!(from_smallest_unit("1.5", 2).is_err());
+        assert!(from_smallest_unit("-", 2).is_err());
+    }
+}
@@ -0,0 +1,487 @@
+//! Named datasets kept inside the calculator.
+//!
+//! Passing a `Float64Array` to every statistics or chart call copies it
+//! across the WASM boundary each time. [`Calculator::load_dataset`] copies
+//! it once and keeps it under a name; after that, summaries, histograms and
+//! expressions read it in place:
+//!
+//! ```text
+//! load_dataset(sales, "sales")
+//! evaluate("avg(dataset(sales)) * 1.1")
+//! evaluate("max(dataset(sales), dataset(returns))")
+//! ```
+//!
+//! In an expression, `dataset(name)` spreads the values into the arguments
+//! of the function it is passed to, like a cell range, so it works with
+//! every variadic built-in (`sum`, `avg`, `min`, `max`, `count`,
+//! `product`). The name is written bare, so it must be an identifier.
+//!
+//! Datasets also combine element by element into new datasets:
+//! [`Calculator::dataset_add`] and its siblings pair equal-length datasets
+//! like columns of a table, and [`Calculator::filter_dataset`] keeps the
+//! values a [`Predicate`] accepts, reading each as `value` and its
+//! position as `index`; the predicate can also use variables and other
+//! datasets. `dataset_values` copies a result back out, so a page can
+//! chain several steps and cross the boundary only at the end.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{unknown_identifier, AngleScope, Scope};
+use crate::predicate::Predicate;
+use crate::stats::StatCalculator;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// The most histogram bins.
+pub const MAX_BINS: usize = 1000;
+
+/// What `dataset_summary` returns.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatasetSummary {
+    pub count: usize,
+    pub sum: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev_population: f64,
+    /// `None` with a single value.
+    pub std_dev_sample: Option<f64>,
+}
+
+// Variables with the calculator's datasets, for `evaluate`.
+pub(crate) struct DatasetScope<'a> {
+    variables: &'a BTreeMap<String, f64>,
+    datasets: &'a BTreeMap<String, StatCalculator>,
+}
+
+impl Scope for DatasetScope<'_> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.variables.variable(name)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.variables.names()
+    }
+
+    fn dataset(&self, name: &str) -> Option<&[f64]> {
+        self.datasets.get(name).map(StatCalculator::values)
+    }
+
+    fn dataset_names(&self) -> Vec<String> {
+        self.datasets.keys().cloned().collect()
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+impl Calculator {
+    /// Stores a copy of `values` as the dataset `name`, replacing any
+    /// dataset of that name, and returns how many values it holds.
+    pub fn load_dataset(&mut self, values: &[f64], name: &str) -> Result<usize, CalcError> {
+        if !is_identifier(name) {
+            let err = CalcError::InvalidArgument(format!(
+                "Dataset names must be identifiers, got '{}'",
+                name
+            ));
+            return Err(self.fail(err, None));
+        }
+        let mut data = StatCalculator::default();
+        let count = data.set_data(values).map_err(|e| self.fail(e, None))?;
+        self.datasets.insert(name.to_string(), data);
+        Ok(count)
+    }
+
+    /// The dataset `name`, for any of the [`StatCalculator`] statistics.
+    pub fn dataset(&self, name: &str) -> Result<&StatCalculator, CalcError> {
+        self.datasets.get(name).ok_or_else(|| {
+            let err = unknown_identifier(name, self.datasets.keys().map(String::as_str));
+            self.fail(err, None)
+        })
+    }
+
+    /// A copy of the values of `name`.
+    pub fn dataset_values(&self, name: &str) -> Result<Vec<f64>, CalcError> {
+        self.dataset(name).map(|data| data.values().to_vec())
+    }
+
+    pub fn dataset_summary(&self, name: &str) -> Result<DatasetSummary, CalcError> {
+        let data = self.dataset(name)?;
+        let summary = || {
+            Ok(DatasetSummary {
+                count: data.count(),
+                sum: data.sum(),
+                mean: data.mean()?,
+                median: data.median()?,
+                min: data.min()?,
+                max: data.max()?,
+                std_dev_population: data.std_dev_population()?,
+                std_dev_sample: data.std_dev_sample().ok(),
+            })
+        };
+        summary().map_err(|e| self.fail(e, None))
+    }
+
+    /// How many values fall in each of `bins` equal-width bins from the
+    /// smallest value to the largest, for a histogram. The largest value
+    /// counts in the last bin; if every value is the same, all count in
+    /// the first.
+    pub fn dataset_histogram(&self, name: &str, bins: usize) -> Result<Vec<u32>, CalcError> {
+        let data = self.dataset(name)?;
+        if bins == 0 || bins > MAX_BINS {
+            let err =
+                CalcError::InvalidArgument(format!("Bins must be 1-{}, got {}", MAX_BINS, bins));
+            return Err(self.fail(err, Some(bins as f64)));
+        }
+        let mut counts = vec![0; bins];
+        let (Ok(min), Ok(max)) = (data.min(), data.max()) else {
+            return Ok(counts);
+        };
+        let width = (max - min) / bins as f64;
+        for &value in data.values() {
+            let bin = if width > 0.0 {
+                (((value - min) / width) as usize).min(bins - 1)
+            } else {
+                0
+            };
+            counts[bin] += 1;
+        }
+        Ok(counts)
+    }
+
+    // Stores `op` applied to each pair of elements of `a` and `b` as
+    // `into`.
+    fn combine_datasets(
+        &mut self,
+        a: &str,
+        b: &str,
+        into: &str,
+        op: impl Fn(f64, f64) -> Result<f64, CalcError>,
+    ) -> Result<usize, CalcError> {
+        let (left, right) = (self.dataset(a)?.values(), self.dataset(b)?.values());
+        if left.len() != right.len() {
+            let err = CalcError::InvalidArgument(format!(
+                "Datasets {} and {} differ in length ({} vs {})",
+                a,
+                b,
+                left.len(),
+                right.len()
+            ));
+            return Err(self.fail(err, None));
+        }
+        let values: Result<Vec<f64>, CalcError> =
+            left.iter().zip(right).map(|(&x, &y)| op(x, y)).collect();
+        let values = values.map_err(|e| self.fail(e, None))?;
+        self.load_dataset(&values, into)
+    }
+
+    /// Stores the element-by-element sum of `a` and `b` as `into`, which
+    /// may be either of them, and returns its length.
+    pub fn dataset_add(&mut self, a: &str, b: &str, into: &str) -> Result<usize, CalcError> {
+        self.combine_datasets(a, b, into, |x, y| Ok(x + y))
+    }
+
+    pub fn dataset_subtract(&mut self, a: &str, b: &str, into: &str) -> Result<usize, CalcError> {
+        self.combine_datasets(a, b, into, |x, y| Ok(x - y))
+    }
+
+    pub fn dataset_multiply(&mut self, a: &str, b: &str, into: &str) -> Result<usize, CalcError> {
+        self.combine_datasets(a, b, into, |x, y| Ok(x * y))
+    }
+
+    /// Fails if any element of `b` is 0.
+    pub fn dataset_divide(&mut self, a: &str, b: &str, into: &str) -> Result<usize, CalcError> {
+        self.combine_datasets(a, b, into, |x, y| {
+            if y == 0.0 {
+                Err(CalcError::DivisionByZero)
+            } else {
+                Ok(x / y)
+            }
+        })
+    }
+
+    /// Stores the values of `name` for which `predicate` holds as `into`,
+    /// in order, and returns how many there are.
+    pub fn filter_dataset(
+        &mut self,
+        name: &str,
+        predicate: &str,
+        into: &str,
+    ) -> Result<usize, CalcError> {
+        let mask = self.dataset_mask(name, predicate)?;
+        let values = self.dataset(name)?.values();
+        let kept: Vec<f64> = values
+            .iter()
+            .zip(mask)
+            .filter_map(|(&value, keep)| keep.then_some(value))
+            .collect();
+        self.load_dataset(&kept, into)
+    }
+
+    /// Whether `predicate` holds for each value of `name`, as in
+    /// `filter_dataset`; see `predicate` for values where it fails.
+    pub fn dataset_mask(&self, name: &str, predicate: &str) -> Result<Vec<bool>, CalcError> {
+        let mask = || {
+            let predicate = Predicate::parse(predicate)?;
+            let values = self.dataset(name)?.values();
+            let mut variables = BTreeMap::new();
+            for side in predicate.sides() {
+                variables.append(&mut self.variable_scope(side)?);
+            }
+            let scope = AngleScope {
+                scope: &self.dataset_scope(&variables),
+                unit: self.angle_unit,
+            };
+            predicate.test_each(values, &scope)
+        };
+        mask().map_err(|e| self.fail(e, None))
+    }
+
+    pub(crate) fn dataset_scope<'a>(
+        &'a self,
+        variables: &'a BTreeMap<String, f64>,
+    ) -> DatasetScope<'a> {
+        DatasetScope {
+            variables,
+            datasets: &self.datasets,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Copies a `Float64Array` in once; see the [module docs](self).
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = load_dataset)]
+    pub fn load_dataset_js(&mut self, values: &[f64], name: &str) -> Result<usize, CalcErrorJs> {
+        self.load_dataset(values, name).map_err(CalcErrorJs::from)
+    }
+
+    /// The values of `name` as a new `Float64Array`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_values)]
+    pub fn dataset_values_js(&self, name: &str) -> Result<Vec<f64>, CalcErrorJs> {
+        self.dataset_values(name).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_add)]
+    pub fn dataset_add_js(&mut self, a: &str, b: &str, into: &str) -> Result<usize, CalcErrorJs> {
+        self.dataset_add(a, b, into).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_subtract)]
+    pub fn dataset_subtract_js(
+        &mut self,
+        a: &str,
+        b: &str,
+        into: &str,
+    ) -> Result<usize, CalcErrorJs> {
+        self.dataset_subtract(a, b, into).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_multiply)]
+    pub fn dataset_multiply_js(
+        &mut self,
+        a: &str,
+        b: &str,
+        into: &str,
+    ) -> Result<usize, CalcErrorJs> {
+        self.dataset_multiply(a, b, into).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_divide)]
+    pub fn dataset_divide_js(
+        &mut self,
+        a: &str,
+        b: &str,
+        into: &str,
+    ) -> Result<usize, CalcErrorJs> {
+        self.dataset_divide(a, b, into).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = filter_dataset)]
+    pub fn filter_dataset_js(
+        &mut self,
+        name: &str,
+        predicate: &str,
+        into: &str,
+    ) -> Result<usize, CalcErrorJs> {
+        self.filter_dataset(name, predicate, into)
+            .map_err(CalcErrorJs::from)
+    }
+
+    /// A `Uint8Array` with 1 where `predicate` holds and 0 elsewhere, for
+    /// a conditional-formatting mask.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_mask)]
+    pub fn dataset_mask_js(&self, name: &str, predicate: &str) -> Result<Vec<u8>, CalcErrorJs> {
+        let mask = self
+            .dataset_mask(name, predicate)
+            .map_err(CalcErrorJs::from)?;
+        Ok(mask.into_iter().map(u8::from).collect())
+    }
+
+    /// Removes the dataset `name`, returning whether it existed.
+    pub fn remove_dataset(&mut self, name: &str) -> bool {
+        self.datasets.remove(name).is_some()
+    }
+
+    /// The dataset names, sorted.
+    pub fn dataset_names(&self) -> Vec<String> {
+        self.datasets.keys().cloned().collect()
+    }
+
+    /// `{ count, sum, mean, median, min, max, std_dev_population,
+    /// std_dev_sample }`; `std_dev_sample` is `null` for a single value.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_summary)]
+    pub fn dataset_summary_js(&self, name: &str) -> Result<JsValue, CalcErrorJs> {
+        let summary = self.dataset_summary(name).map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&summary).unwrap_or(JsValue::NULL))
+    }
+
+    /// A `Uint32Array` of bin counts.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = dataset_histogram)]
+    pub fn dataset_histogram_js(&self, name: &str, bins: usize) -> Result<Vec<u32>, CalcErrorJs> {
+        self.dataset_histogram(name, bins)
+            .map_err(|e| CalcErrorJs::new(e, Some(bins as f64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datasets_in_expressions() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.load_dataset(&[120.0, 80.0, 100.0], "sales"), Ok(3));
+        calc.load_dataset(&[5.0, 300.0], "returns").unwrap();
+        assert_eq!(calc.evaluate("avg(dataset(sales)) * 2"), Ok(200.0));
+        assert_eq!(
+            calc.evaluate("max(dataset(sales), dataset(returns), 1)"),
+            Ok(300.0)
+        );
+        assert_eq!(calc.evaluate("count(dataset(sales))"), Ok(3.0));
+        calc.add_function_alias("mean", "avg").unwrap();
+        assert_eq!(calc.evaluate("mean(dataset(returns))"), Ok(152.5));
+        // A dataset name does not shadow a variable of the same name.
+        calc.set_variable("sales", 1.0).unwrap();
+        assert_eq!(calc.evaluate("sum(dataset(sales)) + sales"), Ok(301.0));
+
+        assert!(matches!(
+            calc.evaluate("sum(dataset(sale))"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(calc.evaluate("dataset(sales) + 1").is_err());
+        assert!(calc.evaluate("sum(dataset(sales, returns))").is_err());
+    }
+
+    #[test]
+    fn test_dataset_statistics() {
+        let mut calc = Calculator::new();
+        calc.load_dataset(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], "scores")
+            .unwrap();
+        let summary = calc.dataset_summary("scores").unwrap();
+        assert_eq!(summary.count, 8);
+        assert_eq!(summary.mean, 5.0);
+        assert_eq!(summary.median, 4.5);
+        assert_eq!(summary.std_dev_population, 2.0);
+        assert_eq!(calc.dataset("scores").unwrap().mode(), Ok(vec![4.0]));
+        assert_eq!(
+            calc.dataset_histogram("scores", 7).unwrap(),
+            [1, 0, 3, 2, 0, 1, 1]
+        );
+        assert_eq!(calc.dataset_histogram("scores", 1).unwrap(), [8]);
+        assert!(calc.dataset_histogram("scores", 0).is_err());
+
+        calc.load_dataset(&[3.0], "one").unwrap();
+        assert_eq!(calc.dataset_summary("one").unwrap().std_dev_sample, None);
+        assert_eq!(calc.dataset_histogram("one", 3).unwrap(), [1, 0, 0]);
+        calc.load_dataset(&[], "empty").unwrap();
+        assert!(calc.dataset_summary("empty").is_err());
+        assert_eq!(calc.dataset_names(), ["empty", "one", "scores"]);
+        assert!(calc.remove_dataset("one"));
+        assert!(!calc.remove_dataset("one"));
+
+        assert!(calc.load_dataset(&[1.0, f64::NAN], "bad").is_err());
+        assert!(calc.load_dataset(&[1.0], "two words").is_err());
+        assert!(calc.load_dataset(&[1.0], "1st").is_err());
+        assert_eq!(calc.dataset_names().len(), 2);
+    }
+
+    #[test]
+    fn test_columnwise_arithmetic() {
+        let mut calc = Calculator::new();
+        calc.load_dataset(&[100.0, 200.0, 300.0], "revenue")
+            .unwrap();
+        calc.load_dataset(&[60.0, 150.0, 330.0], "cost").unwrap();
+        assert_eq!(calc.dataset_subtract("revenue", "cost", "profit"), Ok(3));
+        assert_eq!(calc.dataset_values("profit").unwrap(), [40.0, 50.0, -30.0]);
+        calc.dataset_divide("profit", "revenue", "margin").unwrap();
+        assert_eq!(calc.dataset_values("margin").unwrap(), [0.4, 0.25, -0.1]);
+        // The result can replace an operand.
+        calc.dataset_add("cost", "cost", "cost").unwrap();
+        calc.dataset_multiply("cost", "margin", "cost").unwrap();
+        assert_eq!(calc.dataset_values("cost").unwrap(), [48.0, 75.0, -66.0]);
+
+        calc.load_dataset(&[1.0, 0.0, 1.0], "zeros").unwrap();
+        assert_eq!(
+            calc.dataset_divide("revenue", "zeros", "bad"),
+            Err(CalcError::DivisionByZero)
+        );
+        calc.load_dataset(&[1.0], "short").unwrap();
+        assert!(calc.dataset_add("revenue", "short", "bad").is_err());
+        assert!(calc.dataset_add("revenue", "missing", "bad").is_err());
+        assert!(!calc.dataset_names().contains(&"bad".to_string()));
+    }
+
+    #[test]
+    fn test_filter_dataset() {
+        let mut calc = Calculator::new();
+        calc.load_dataset(&[90.0, 120.0, 105.0, 130.0], "spend")
+            .unwrap();
+        calc.set_variable("budget", 100.0).unwrap();
+        assert_eq!(
+            calc.filter_dataset("spend", "value > budget * 1.1", "over"),
+            Ok(2)
+        );
+        assert_eq!(calc.dataset_values("over").unwrap(), [120.0, 130.0]);
+        calc.filter_dataset("spend", "index % 2 == 0", "even")
+            .unwrap();
+        assert_eq!(calc.dataset_values("even").unwrap(), [90.0, 105.0]);
+        // Predicates can read other datasets.
+        calc.filter_dataset("spend", "value >= avg(dataset(spend))", "high")
+            .unwrap();
+        assert_eq!(calc.dataset_values("high").unwrap(), [120.0, 130.0]);
+        assert_eq!(calc.filter_dataset("spend", "value < 0", "none"), Ok(0));
+        assert_eq!(
+            calc.dataset_mask("spend", "value > budget"),
+            Ok(vec![false, true, true, true])
+        );
+
+        assert!(matches!(
+            calc.filter_dataset("spend", "value = 1", "bad"),
+            Err(CalcError::Syntax { .. })
+        ));
+        assert!(matches!(
+            calc.filter_dataset("spend", "valu > 1", "bad"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+    }
+}
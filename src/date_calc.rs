@@ -0,0 +1,392 @@
+//! Date arithmetic on ISO-8601 calendar dates (`YYYY-MM-DD`).
+//!
+//! Dates are proleptic Gregorian with years 1 to 9999 and no time of day
+//! or time zone, so a day is always a day. Month arithmetic clamps to the
+//! end of the month: one month after `2024-01-31` is `2024-02-29`.
+
+use std::fmt;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// ISO weekday numbers (Monday is 1) of the default weekend.
+pub const SATURDAY_SUNDAY: &[u32] = &[6, 7];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// Whole years, months and days from one date to a later one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Age {
+    pub years: u32,
+    pub months: u32,
+    pub days: u32,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Result<Date, CalcError> {
+        if !(1..=9999).contains(&year) {
+            return Err(invalid(format!("Year {} is outside 1 to 9999", year)));
+        }
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(invalid(format!(
+                "{:04}-{:02}-{:02} is not a calendar date",
+                year, month, day
+            )));
+        }
+        Ok(Date { year, month, day })
+    }
+
+    /// Parses `YYYY-MM-DD`.
+    pub fn parse(text: &str) -> Result<Date, CalcError> {
+        let malformed = || invalid(format!("Expected a YYYY-MM-DD date, got '{}'", text));
+        let bytes = text.as_bytes();
+        let digits = |range: std::ops::Range<usize>| {
+            bytes[range.clone()]
+                .iter()
+                .all(u8::is_ascii_digit)
+                .then(|| text[range].parse::<u32>().ok())
+                .flatten()
+        };
+        if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return Err(malformed());
+        }
+        match (digits(0..4), digits(5..7), digits(8..10)) {
+            (Some(year), Some(month), Some(day)) => Date::new(year as i32, month, day),
+            _ => Err(malformed()),
+        }
+    }
+
+    /// Days since 1970-01-01 (negative before it).
+    pub fn to_days(self) -> i64 {
+        // Howard Hinnant's days_from_civil, with March as the first month
+        // so the leap day falls at the end of the year.
+        let y = self.year as i64 - i64::from(self.month <= 2);
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn from_days(days: i64) -> Result<Date, CalcError> {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        let year = i32::try_from(year).map_err(|_| invalid("Date is out of range"))?;
+        Date::new(year, month, day)
+    }
+
+    /// ISO weekday: 1 for Monday through 7 for Sunday.
+    pub fn weekday(self) -> u32 {
+        // 1970-01-01 was a Thursday.
+        (self.to_days() + 3).rem_euclid(7) as u32 + 1
+    }
+
+    pub fn add_days(self, days: i64) -> Result<Date, CalcError> {
+        let total = self
+            .to_days()
+            .checked_add(days)
+            .ok_or_else(|| invalid("Date is out of range"))?;
+        Date::from_days(total)
+    }
+
+    /// Moves by whole months, clamping the day to the target month's end.
+    pub fn add_months(self, months: i64) -> Result<Date, CalcError> {
+        let index = (self.year as i64 * 12 + self.month as i64 - 1)
+            .checked_add(months)
+            .ok_or_else(|| invalid("Date is out of range"))?;
+        let year =
+            i32::try_from(index.div_euclid(12)).map_err(|_| invalid("Date is out of range"))?;
+        let month = index.rem_euclid(12) as u32 + 1;
+        if !(1..=9999).contains(&year) {
+            return Err(invalid("Date is out of range"));
+        }
+        Date::new(year, month, self.day.min(days_in_month(year, month)))
+    }
+
+    pub fn add_years(self, years: i64) -> Result<Date, CalcError> {
+        let months = years
+            .checked_mul(12)
+            .ok_or_else(|| invalid("Date is out of range"))?;
+        self.add_months(months)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Days from `start` to `end`; negative when `end` is earlier.
+pub fn days_between(start: &str, end: &str) -> Result<i64, CalcError> {
+    Ok(Date::parse(end)?.to_days() - Date::parse(start)?.to_days())
+}
+
+pub fn add_days(date: &str, days: i64) -> Result<String, CalcError> {
+    Ok(Date::parse(date)?.add_days(days)?.to_string())
+}
+
+pub fn add_months(date: &str, months: i64) -> Result<String, CalcError> {
+    Ok(Date::parse(date)?.add_months(months)?.to_string())
+}
+
+pub fn add_years(date: &str, years: i64) -> Result<String, CalcError> {
+    Ok(Date::parse(date)?.add_years(years)?.to_string())
+}
+
+/// Working days from `start` up to but not including `end`, skipping the
+/// ISO weekdays in `weekend`; negative when `end` is earlier.
+pub fn business_days_between(start: &str, end: &str, weekend: &[u32]) -> Result<i64, CalcError> {
+    if let Some(day) = weekend.iter().find(|d| !(1..=7).contains(*d)) {
+        return Err(invalid(format!(
+            "Weekend days are 1 (Monday) to 7 (Sunday), got {}",
+            day
+        )));
+    }
+    let (a, b) = (Date::parse(start)?, Date::parse(end)?);
+    let (from, to, sign) = if a <= b { (a, b, 1) } else { (b, a, -1) };
+    let span = to.to_days() - from.to_days();
+    let workdays_per_week = (1..=7).filter(|d| !weekend.contains(d)).count() as i64;
+    let mut count = span / 7 * workdays_per_week;
+    let first = from.weekday();
+    for offset in 0..span % 7 {
+        let weekday = (first - 1 + offset as u32) % 7 + 1;
+        if !weekend.contains(&weekday) {
+            count += 1;
+        }
+    }
+    Ok(sign * count)
+}
+
+/// Age on `on` of someone born on `birth`, as completed years, months and
+/// days. A February 29 birthday is reached on February 28 in other years.
+pub fn age(birth: &str, on: &str) -> Result<Age, CalcError> {
+    let (birth, on) = (Date::parse(birth)?, Date::parse(on)?);
+    if on < birth {
+        return Err(invalid(format!(
+            "{} is before the birth date {}",
+            on, birth
+        )));
+    }
+    let mut months = (on.year - birth.year) as i64 * 12 + on.month as i64 - birth.month as i64;
+    if birth.add_months(months)? > on {
+        months -= 1;
+    }
+    let anniversary = birth.add_months(months)?;
+    Ok(Age {
+        years: (months / 12) as u32,
+        months: (months % 12) as u32,
+        days: (on.to_days() - anniversary.to_days()) as u32,
+    })
+}
+
+// Day counts cross the boundary as numbers; they are far below 2^53.
+#[cfg(feature = "wasm")]
+fn whole(value: f64, what: &str) -> Result<i64, CalcError> {
+    if !value.is_finite() || value.fract() != 0.0 || value.abs() > 1e9 {
+        return Err(invalid(format!(
+            "{} must be a whole number, got {}",
+            what, value
+        )));
+    }
+    Ok(value as i64)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = days_between)]
+pub fn days_between_js(start: &str, end: &str) -> Result<f64, CalcErrorJs> {
+    days_between(start, end)
+        .map(|d| d as f64)
+        .map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = add_days)]
+pub fn add_days_js(date: &str, days: f64) -> Result<String, CalcErrorJs> {
+    whole(days, "Days")
+        .and_then(|days| add_days(date, days))
+        .map_err(|e| CalcErrorJs::new(e, Some(days)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = add_months)]
+pub fn add_months_js(date: &str, months: f64) -> Result<String, CalcErrorJs> {
+    whole(months, "Months")
+        .and_then(|months| add_months(date, months))
+        .map_err(|e| CalcErrorJs::new(e, Some(months)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = add_years)]
+pub fn add_years_js(date: &str, years: f64) -> Result<String, CalcErrorJs> {
+    whole(years, "Years")
+        .and_then(|years| add_years(date, years))
+        .map_err(|e| CalcErrorJs::new(e, Some(years)))
+}
+
+/// `weekend` is an array of ISO weekday numbers (Monday is 1); it defaults
+/// to Saturday and Sunday.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = business_days_between)]
+pub fn business_days_between_js(
+    start: &str,
+    end: &str,
+    weekend: Option<Vec<u32>>,
+) -> Result<f64, CalcErrorJs> {
+    let weekend = weekend.as_deref().unwrap_or(SATURDAY_SUNDAY);
+    business_days_between(start, end, weekend)
+        .map(|d| d as f64)
+        .map_err(CalcErrorJs::from)
+}
+
+/// `{ years, months, days }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = age)]
+pub fn age_js(birth: &str, on: &str) -> Result<JsValue, CalcErrorJs> {
+    let age = age(birth, on).map_err(CalcErrorJs::from)?;
+    serde_wasm_bindgen::to_value(&age)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Could not serialize age: {}", e))))
+}
+
+/// ISO weekday of `date`: 1 for Monday through 7 for Sunday.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = weekday)]
+pub fn weekday_js(date: &str) -> Result<u32, CalcErrorJs> {
+    Date::parse(date)
+        .map(Date::weekday)
+        .map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_day_numbers() {
+        assert_eq!(Date::parse("1970-01-01").unwrap().to_days(), 0);
+        assert_eq!(Date::parse("2000-03-01").unwrap().to_days(), 11_017);
+        assert_eq!(Date::parse("0001-01-01").unwrap().weekday(), 1);
+        assert_eq!(Date::parse("2024-06-15").unwrap().weekday(), 6);
+        for days in [-719_162, -1, 0, 59, 10_000, 2_932_896] {
+            assert_eq!(Date::from_days(days).unwrap().to_days(), days);
+        }
+        assert!(Date::parse("2023-02-29").is_err());
+        assert!(Date::parse("2024-13-01").is_err());
+        assert!(Date::parse("2024-1-01").is_err());
+        assert!(Date::parse("+024-01-01").is_err());
+        assert!(Date::parse("2024-01-01T00:00").is_err());
+    }
+
+    #[test]
+    fn test_days_and_months() {
+        assert_eq!(days_between("2024-01-01", "2025-01-01"), Ok(366));
+        assert_eq!(days_between("2024-03-10", "2024-03-01"), Ok(-9));
+        assert_eq!(add_days("2024-02-28", 2), Ok("2024-03-01".to_string()));
+        assert_eq!(add_days("2024-01-01", -1), Ok("2023-12-31".to_string()));
+        assert_eq!(add_months("2024-01-31", 1), Ok("2024-02-29".to_string()));
+        assert_eq!(add_months("2024-03-31", -13), Ok("2023-02-28".to_string()));
+        assert_eq!(add_years("2024-02-29", 1), Ok("2025-02-28".to_string()));
+        assert!(add_years("9999-01-01", 1).is_err());
+        assert!(add_days("0001-01-01", -1).is_err());
+    }
+
+    #[test]
+    fn test_business_days() {
+        // Monday 2024-06-03 to Monday 2024-06-17.
+        assert_eq!(
+            business_days_between("2024-06-03", "2024-06-17", SATURDAY_SUNDAY),
+            Ok(10)
+        );
+        assert_eq!(
+            business_days_between("2024-06-07", "2024-06-10", SATURDAY_SUNDAY),
+            Ok(1)
+        );
+        assert_eq!(
+            business_days_between("2024-06-10", "2024-06-07", SATURDAY_SUNDAY),
+            Ok(-1)
+        );
+        // Friday-Saturday weekend.
+        assert_eq!(
+            business_days_between("2024-06-06", "2024-06-09", &[5, 6]),
+            Ok(1)
+        );
+        assert_eq!(
+            business_days_between("2024-06-03", "2024-06-10", &[]),
+            Ok(7)
+        );
+        assert!(business_days_between("2024-06-03", "2024-06-10", &[0]).is_err());
+    }
+
+    #[test]
+    fn test_age() {
+        let age = |birth, on| super::age(birth, on).unwrap();
+        assert_eq!(
+            age("1990-05-15", "2024-05-14"),
+            Age {
+                years: 33,
+                months: 11,
+                days: 29
+            }
+        );
+        assert_eq!(
+            age("1990-05-15", "2024-05-15"),
+            Age {
+                years: 34,
+                months: 0,
+                days: 0
+            }
+        );
+        assert_eq!(
+            age("2000-01-31", "2000-03-01"),
+            Age {
+                years: 0,
+                months: 1,
+                days: 1
+            }
+        );
+        assert_eq!(
+            age("2004-02-29", "2005-02-28"),
+            Age {
+                years: 1,
+                months: 0,
+                days: 0
+            }
+        );
+        assert!(super::age("2024-01-02", "2024-01-01").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Decibel arithmetic.
+//!
+//! Decibels are logarithmic, so they do not add like ordinary numbers: two
+//! 60 dB machines make 63 dB, not 120 dB. [`db_add`] converts each level
+//! back to power, sums, and converts the total to decibels again.
+//!
+//! [`db_ratio`] is a power ratio in decibels. [`spl_at_distance`] and
+//! [`distance_attenuation`] follow the inverse-square law of a point
+//! source in a free field, which loses 6.02 dB per doubling of distance.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn positive(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid(format!("{} must be positive, got {}", what, value)));
+    }
+    Ok(value)
+}
+
+/// The combined level of incoherent sources at `levels` dB.
+pub fn db_add(levels: &[f64]) -> Result<f64, CalcError> {
+    if levels.is_empty() {
+        return Err(invalid("No levels to add"));
+    }
+    if let Some(level) = levels.iter().find(|l| !l.is_finite()) {
+        return Err(invalid(format!("Invalid level {}", level)));
+    }
+    // Factoring out the loudest keeps 10^(L/10) in range for any level.
+    let loudest = levels.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let sum: f64 = levels
+        .iter()
+        .map(|l| 10f64.powf((l - loudest) / 10.0))
+        .sum();
+    Ok(loudest + 10.0 * sum.log10())
+}
+
+/// `power1` relative to `power2` in dB: `10 log10(power1 / power2)`.
+pub fn db_ratio(power1: f64, power2: f64) -> Result<f64, CalcError> {
+    Ok(10.0 * (positive("Power", power1)? / positive("Power", power2)?).log10())
+}
+
+/// How much quieter a point source is at `distance` than at `reference`
+/// (the same unit), in dB; negative when `distance` is closer.
+pub fn distance_attenuation(reference: f64, distance: f64) -> Result<f64, CalcError> {
+    let reference = positive("Reference distance", reference)?;
+    Ok(20.0 * (positive("Distance", distance)? / reference).log10())
+}
+
+/// The level at `distance` of a point source measured at `level` dB from
+/// `reference`.
+pub fn spl_at_distance(level: f64, reference: f64, distance: f64) -> Result<f64, CalcError> {
+    if !level.is_finite() {
+        return Err(invalid(format!("Invalid level {}", level)));
+    }
+    Ok(level - distance_attenuation(reference, distance)?)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = db_add)]
+pub fn db_add_js(levels: &[f64]) -> Result<f64, CalcErrorJs> {
+    db_add(levels).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = db_ratio)]
+pub fn db_ratio_js(power1: f64, power2: f64) -> Result<f64, CalcErrorJs> {
+    db_ratio(power1, power2).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = distance_attenuation)]
+pub fn distance_attenuation_js(reference: f64, distance: f64) -> Result<f64, CalcErrorJs> {
+    distance_attenuation(reference, distance).map_err(|e| CalcErrorJs::new(e, Some(distance)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = spl_at_distance)]
+pub fn spl_at_distance_js(level: f64, reference: f64, distance: f64) -> Result<f64, CalcErrorJs> {
+    spl_at_distance(level, reference, distance).map_err(|e| CalcErrorJs::new(e, Some(level)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_db_add() {
+        let doubled = db_add(&[60.0, 60.0]).unwrap();
+        assert!(close(doubled, 60.0 + 10.0 * 2f64.log10()));
+        assert!((doubled - 63.0).abs() < 0.02);
+        assert!(close(db_add(&[80.0]).unwrap(), 80.0));
+        // Ten 90 dB sources are 100 dB.
+        assert!(close(db_add(&[90.0; 10]).unwrap(), 100.0));
+        // A much quieter source barely counts.
+        assert!(db_add(&[90.0, 70.0]).unwrap() - 90.0 < 0.05);
+        // Huge levels do not overflow.
+        assert!(close(db_add(&[4000.0, 4000.0]).unwrap(), doubled + 3940.0));
+        assert!(db_add(&[]).is_err());
+        assert!(db_add(&[60.0, f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn test_ratio_and_distance() {
+        assert!(close(db_ratio(100.0, 1.0).unwrap(), 20.0));
+        assert!(close(db_ratio(1.0, 2.0).unwrap(), -10.0 * 2f64.log10()));
+        assert!(db_ratio(0.0, 1.0).is_err());
+
+        assert!(close(distance_attenuation(1.0, 10.0).unwrap(), 20.0));
+        assert!(close(
+            distance_attenuation(2.0, 1.0).unwrap(),
+            -20.0 * 2f64.log10()
+        ));
+        assert!(close(
+            spl_at_distance(94.0, 1.0, 2.0).unwrap(),
+            94.0 - 20.0 * 2f64.log10()
+        ));
+        assert!(close(spl_at_distance(100.0, 10.0, 100.0).unwrap(), 80.0));
+        assert!(spl_at_distance(100.0, 0.0, 1.0).is_err());
+        assert!(spl_at_distance(f64::INFINITY, 1.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Keeping redundant entries out of the history.
+//!
+//! Two kinds of entry add nothing to a tape:
+//!
+//! - **repeats**: the same operation with the same operand and result as
+//!   the entry before it, as pressing `=`, `MS` or `C` again records;
+//! - **no-ops**: an entry that left the value (or, for memory operations,
+//!   the memory) where it was, such as adding 0 or rounding a value that is
+//!   already round. `MS` and `MR` never count, since their entries cannot
+//!   show whether anything changed.
+//!
+//! [`Calculator::set_history_dedupe`] stops either kind being recorded
+//! from then on; [`Calculator::dedupe_history`] removes both from the
+//! history already recorded. Dropping them never changes the value or
+//! memory the history leads to.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{same_f64, CalculationHistory, Calculator, Operation};
+
+/// Which redundant entries `add_to_history` leaves out. Both off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistoryDedupe {
+    pub collapse_repeats: bool,
+    pub skip_no_ops: bool,
+}
+
+fn is_repeat(previous: &CalculationHistory, entry: &CalculationHistory) -> bool {
+    // Consecutive entries chain, so the same result means this one changed
+    // nothing; `operand1` differs when the previous entry did change it.
+    previous.operation == entry.operation
+        && previous.operand2.map(f64::to_bits) == entry.operand2.map(f64::to_bits)
+        && same_f64(previous.result, entry.result)
+}
+
+fn is_no_op(entry: &CalculationHistory) -> bool {
+    !matches!(
+        entry.operation,
+        Operation::MemoryStore | Operation::MemoryRecall
+    ) && same_f64(entry.operand1, entry.result)
+}
+
+impl Calculator {
+    // Whether `entry` should be appended under the dedupe settings. A step
+    // left out records nothing, which `apply_all` allows for.
+    pub(crate) fn worth_recording(&self, entry: &CalculationHistory) -> bool {
+        let repeat = self.dedupe.collapse_repeats
+            && self
+                .history
+                .back()
+                .is_some_and(|previous| is_repeat(previous, entry));
+        let no_op = self.dedupe.skip_no_ops && is_no_op(entry);
+        !repeat && !no_op
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Chooses which redundant entries are left out of the history from now
+    /// on: repeats of the previous entry, and operations that changed
+    /// nothing. Entries already recorded are kept; see `dedupe_history`.
+    pub fn set_history_dedupe(&mut self, collapse_repeats: bool, skip_no_ops: bool) {
+        self.dedupe = HistoryDedupe {
+            collapse_repeats,
+            skip_no_ops,
+        };
+    }
+
+    /// Removes repeated and no-op entries from the recorded history, keeping
+    /// the first of each run of repeats, and returns how many were removed.
+    pub fn dedupe_history(&mut self) -> usize {
+        let before = self.history.len();
+        let mut previous: Option<CalculationHistory> = None;
+        self.history.retain(|entry| {
+            let redundant =
+                is_no_op(entry) || previous.as_ref().is_some_and(|p| is_repeat(p, entry));
+            if !redundant {
+                previous = Some(entry.clone());
+            }
+            !redundant
+        });
+        let removed = before - self.history.len();
+        if removed > 0 {
+            self.history_rewritten();
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operations(calc: &Calculator) -> Vec<Operation> {
+        calc.history_range(0, usize::MAX)
+            .into_iter()
+            .map(|e| e.operation)
+            .collect()
+    }
+
+    #[test]
+    fn test_dedupe_while_recording() {
+        let mut calc = Calculator::new();
+        calc.set_history_dedupe(true, false);
+        calc.set_value(5.0);
+        calc.set_value(5.0);
+        calc.memory_store();
+        calc.memory_store();
+        calc.add(0.0);
+        calc.add(2.0);
+        calc.add(2.0);
+        assert_eq!(
+            operations(&calc),
+            [
+                Operation::SetValue,
+                Operation::MemoryStore,
+                Operation::Add,
+                Operation::Add,
+                Operation::Add
+            ]
+        );
+
+        calc.set_history_dedupe(false, true);
+        calc.multiply(1.0);
+        calc.memory_add();
+        calc.memory_clear();
+        calc.memory_clear();
+        calc.memory_store();
+        assert_eq!(calc.history_count(), 8);
+        assert_eq!(calc.get_value(), 9.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_dedupe_history() {
+        let mut calc = Calculator::new();
+        calc.clear();
+        calc.set_value(3.0);
+        calc.subtract(0.0);
+        calc.memory_recall();
+        calc.memory_recall();
+        calc.set_value(3.0);
+        calc.round_to(2).unwrap();
+        calc.negate();
+        assert_eq!(calc.dedupe_history(), 4);
+        assert_eq!(
+            operations(&calc),
+            [
+                Operation::SetValue,
+                Operation::MemoryRecall,
+                Operation::SetValue,
+                Operation::Negate
+            ]
+        );
+        assert_eq!(calc.dedupe_history(), 0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_dedupe_in_a_batch_notifies_only_recorded_steps() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.add(2.0);
+        calc.set_history_dedupe(false, true);
+        let sink = heard.clone();
+        calc.on_history(move |entry| sink.borrow_mut().push(entry.result));
+        let steps = [(Operation::Add, Some(0.0)), (Operation::Add, Some(0.0))];
+        assert_eq!(calc.apply_all(steps), Ok(vec![3.0, 3.0]));
+        assert!(heard.borrow().is_empty());
+
+        let steps = [(Operation::Add, Some(0.0)), (Operation::Add, Some(4.0))];
+        assert_eq!(calc.apply_all(steps), Ok(vec![3.0, 7.0]));
+        assert_eq!(*heard.borrow(), [7.0]);
+        assert_eq!(calc.history_series(), [1.0, 3.0, 7.0]);
+    }
+}
@@ -0,0 +1,317 @@
+//! A self-test and benchmark that runs inside the deployed module.
+//!
+//! [`run_diagnostics`] checks that this build computes what it should and
+//! measures how fast it does so on the device it runs on:
+//!
+//! - `precision` checks compare results that IEEE 754 pins down exactly
+//!   (`0.1 + 0.2`, `sqrt(2)`, `20!`) bit for bit, and transcendental ones
+//!   against a tolerance;
+//! - `parser` checks round-trip numbers through text, localized input,
+//!   expressions and history tapes;
+//! - `throughput` times a fixed number of calls of a few common operations
+//!   with the platform's monotonic clock (`performance.now()` in the
+//!   browser).
+//!
+//! It uses its own calculators and leaves the `timing` totals alone, so it
+//! can run next to a live calculator.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{evaluate, parse_number};
+use crate::format::{format_number, FormatOptions};
+use crate::tape::TapeFormat;
+use crate::timing::{enabled, precise_now_ms, set_timing};
+use crate::{compound_interest, factorial, CalcError, Calculator};
+
+/// Calls made per throughput benchmark.
+pub const BENCH_ITERATIONS: u32 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiagnosticCheck {
+    pub name: &'static str,
+    /// `precision` or `parser`.
+    pub category: &'static str,
+    pub passed: bool,
+    /// What went wrong, for a failed check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Throughput {
+    pub name: &'static str,
+    pub iterations: u32,
+    pub total_ms: f64,
+    /// `None` when the run was too quick for the clock to measure.
+    pub ops_per_sec: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiagnosticsReport {
+    /// The crate version of this build.
+    pub version: &'static str,
+    /// Whether every check passed.
+    pub passed: bool,
+    pub checks: Vec<DiagnosticCheck>,
+    pub throughput: Vec<Throughput>,
+}
+
+type Check = fn() -> Result<(), String>;
+
+fn expect_bits(what: &str, actual: f64, expected: f64) -> Result<(), String> {
+    if actual.to_bits() == expected.to_bits() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} gave {:?}, expected {:?}",
+            what, actual, expected
+        ))
+    }
+}
+
+fn expect_close(what: &str, actual: f64, expected: f64, tolerance: f64) -> Result<(), String> {
+    if (actual - expected).abs() <= tolerance {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} gave {:?}, expected {:?}",
+            what, actual, expected
+        ))
+    }
+}
+
+fn describe(what: &str, err: CalcError) -> String {
+    format!("{} failed: {}", what, err)
+}
+
+fn check_add() -> Result<(), String> {
+    let mut calc = Calculator::new();
+    calc.set_value(0.1);
+    calc.add(0.2);
+    expect_bits("0.1 + 0.2", calc.get_value(), 0.30000000000000004)
+}
+
+fn check_sqrt() -> Result<(), String> {
+    let mut calc = Calculator::new();
+    calc.set_value(2.0);
+    let root = calc.sqrt().map_err(|e| describe("sqrt(2)", e))?;
+    expect_bits("sqrt(2)", root, std::f64::consts::SQRT_2)
+}
+
+fn check_division_by_zero() -> Result<(), String> {
+    match Calculator::new().divide(0.0) {
+        Err(CalcError::DivisionByZero) => Ok(()),
+        other => Err(format!("1 / 0 gave {:?}, expected DivisionByZero", other)),
+    }
+}
+
+fn check_factorial() -> Result<(), String> {
+    match factorial(20) {
+        Ok(2_432_902_008_176_640_000) => Ok(()),
+        other => Err(format!("20! gave {:?}", other)),
+    }
+}
+
+fn check_transcendental() -> Result<(), String> {
+    let sin = evaluate("sin(pi / 6)").map_err(|e| describe("sin(pi / 6)", e))?;
+    expect_close("sin(pi / 6)", sin, 0.5, 1e-15)?;
+    let e = evaluate("exp(1)").map_err(|e| describe("exp(1)", e))?;
+    expect_close("exp(1)", e, std::f64::consts::E, 1e-15)?;
+    expect_close(
+        "compound_interest(1000, 5, 10, 12)",
+        compound_interest(1000.0, 5.0, 10.0, 12.0),
+        1_647.009_497_690_28,
+        1e-9,
+    )
+}
+
+fn check_number_round_trip() -> Result<(), String> {
+    let values = [
+        0.1,
+        1.0 / 3.0,
+        -2.5e21,
+        1e-300,
+        f64::MIN_POSITIVE,
+        123_456.789,
+    ];
+    for value in values {
+        for text in [value.to_string(), format!("{:e}", value)] {
+            let parsed = parse_number(&text).map_err(|e| describe(&text, e))?;
+            expect_bits(&format!("parse_number({:?})", text), parsed, value)?;
+        }
+        let shown = format_number(value, &FormatOptions::default())
+            .map_err(|e| describe("format_number", e))?;
+        let parsed = parse_number(&shown).map_err(|e| describe(&shown, e))?;
+        expect_bits(&format!("format_number({:?})", value), parsed, value)?;
+    }
+    Ok(())
+}
+
+fn check_localized_input() -> Result<(), String> {
+    let mut calc = Calculator::new();
+    calc.set_input_locale("de-DE");
+    let value = calc
+        .set_value_str("1234,5")
+        .map_err(|e| describe("\"1234,5\" in de-DE", e))?;
+    expect_bits("\"1234,5\" in de-DE", value, 1234.5)
+}
+
+fn check_expression() -> Result<(), String> {
+    for (src, expected) in [
+        ("2 + 3 * 4", 14.0),
+        ("(2 + 3) * 4", 20.0),
+        ("2 ^ 3 ^ 2", 512.0),
+    ] {
+        let value = evaluate(src).map_err(|e| describe(src, e))?;
+        expect_bits(src, value, expected)?;
+    }
+    Ok(())
+}
+
+fn check_tape_round_trip() -> Result<(), String> {
+    let mut calc = Calculator::new();
+    calc.set_value(10.0);
+    calc.add(0.1);
+    calc.multiply(3.0);
+    calc.divide(7.0).map_err(|e| describe("divide", e))?;
+    let mut copy = Calculator::new();
+    copy.import_tape(&calc.export_history_csv(), TapeFormat::Csv)
+        .map_err(|e| describe("import_tape", e))?;
+    expect_bits("the re-imported tape", copy.get_value(), calc.get_value())
+}
+
+const CHECKS: &[(&str, &str, Check)] = &[
+    ("add", "precision", check_add),
+    ("sqrt", "precision", check_sqrt),
+    ("division_by_zero", "precision", check_division_by_zero),
+    ("factorial", "precision", check_factorial),
+    ("transcendental", "precision", check_transcendental),
+    ("number_round_trip", "parser", check_number_round_trip),
+    ("localized_input", "parser", check_localized_input),
+    ("expression", "parser", check_expression),
+    ("tape_round_trip", "parser", check_tape_round_trip),
+];
+
+fn bench(name: &'static str, mut call: impl FnMut(u32)) -> Throughput {
+    let start = precise_now_ms();
+    for i in 0..BENCH_ITERATIONS {
+        call(i);
+    }
+    let total_ms = precise_now_ms() - start;
+    Throughput {
+        name,
+        iterations: BENCH_ITERATIONS,
+        total_ms,
+        ops_per_sec: (total_ms > 0.0).then(|| BENCH_ITERATIONS as f64 * 1000.0 / total_ms),
+    }
+}
+
+fn benchmarks() -> Vec<Throughput> {
+    let mut calc = Calculator::new();
+    calc.set_history_limit(100);
+    let options = FormatOptions {
+        thousands: true,
+        decimals: Some(2),
+        ..FormatOptions::default()
+    };
+    vec![
+        bench("add", |i| {
+            calc.add(i as f64);
+        }),
+        bench("divide", |i| {
+            let _ = calc.divide(1.0 + i as f64);
+        }),
+        bench("sqrt", |_| {
+            calc.set_value(2.0);
+            let _ = calc.sqrt();
+        }),
+        bench("evaluate", |_| {
+            let _ = evaluate("(1 + 2) * 3 / 4 - sqrt(16)");
+        }),
+        bench("format_number", |i| {
+            let _ = format_number(1234.5678 * i as f64, &options);
+        }),
+    ]
+}
+
+/// Runs the built-in checks and benchmarks.
+pub fn run_diagnostics() -> DiagnosticsReport {
+    // Keep the benchmark calls out of the caller's timing totals.
+    let timing = enabled();
+    set_timing(false);
+    let checks: Vec<DiagnosticCheck> = CHECKS
+        .iter()
+        .map(|&(name, category, check)| {
+            let detail = check().err();
+            DiagnosticCheck {
+                name,
+                category,
+                passed: detail.is_none(),
+                detail,
+            }
+        })
+        .collect();
+    let throughput = benchmarks();
+    set_timing(timing);
+    DiagnosticsReport {
+        version: env!("CARGO_PKG_VERSION"),
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+        throughput,
+    }
+}
+
+/// `{ version, passed, checks: [{ name, category, passed, detail }],
+/// throughput: [{ name, iterations, total_ms, ops_per_sec }] }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = run_diagnostics)]
+pub fn run_diagnostics_js() -> JsValue {
+    serde_wasm_bindgen::to_value(&run_diagnostics()).unwrap_or(JsValue::NULL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timing::{reset_timings, timings};
+
+    #[test]
+    fn test_run_diagnostics() {
+        set_timing(true);
+        reset_timings();
+        let report = run_diagnostics();
+        let failed: Vec<_> = report.checks.iter().filter(|c| !c.passed).collect();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert!(report.passed);
+        assert_eq!(report.checks.len(), CHECKS.len());
+        let names: Vec<_> = report.throughput.iter().map(|t| t.name).collect();
+        assert_eq!(
+            names,
+            ["add", "divide", "sqrt", "evaluate", "format_number"]
+        );
+        assert!(report
+            .throughput
+            .iter()
+            .all(|t| t.iterations == BENCH_ITERATIONS && t.total_ms >= 0.0));
+        assert!(enabled());
+        assert!(timings().is_empty());
+        set_timing(false);
+    }
+
+    #[test]
+    fn test_failed_check_detail() {
+        assert_eq!(
+            expect_bits("x", 0.3, 0.1 + 0.2),
+            Err("x gave 0.3, expected 0.30000000000000004".to_string())
+        );
+        let json = serde_json::to_value(DiagnosticCheck {
+            name: "add",
+            category: "precision",
+            passed: true,
+            detail: None,
+        })
+        .unwrap();
+        assert!(json.get("detail").is_none());
+    }
+}
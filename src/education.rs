@@ -0,0 +1,265 @@
+//! Classroom guard rails.
+//!
+//! Education mode limits a calculator to an allow-list of operations,
+//! shows at most a set number of decimal places, and turns
+//! `clear_history` into a request that `confirm_clear_history` must
+//! follow, so a stray click cannot wipe a student's working.
+//!
+//! Operations are named as on the tape (`add`, `sqrt`, `nth_root`, ...),
+//! with `constant` and `function` covering every constant and user
+//! function, and `evaluate` covering typed expressions. Entering values,
+//! `clear` and the memory keys are always allowed. A disallowed operation
+//! fails with [`CalcError::NotAllowed`]; the infallible Rust methods leave
+//! the value unchanged instead, as they do in strict math mode.
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::unknown_identifier;
+use crate::format::MAX_ROUND_PLACES;
+use crate::tape::operation_name;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+/// The names an allow-list may contain.
+pub const GATED_OPERATIONS: &[&str] = &[
+    "add",
+    "subtract",
+    "multiply",
+    "divide",
+    "sqrt",
+    "power",
+    "percent_of",
+    "add_percent",
+    "subtract_percent",
+    "round",
+    "nth_root",
+    "modulo",
+    "reciprocal",
+    "abs",
+    "negate",
+    "constant",
+    "function",
+    "evaluate",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EducationMode {
+    /// `None` allows every operation.
+    pub allowed: Option<BTreeSet<String>>,
+    /// Most decimal places `format_value` shows.
+    pub max_decimals: Option<u32>,
+    // Set by `clear_history`, consumed by `confirm_clear_history`.
+    clear_requested: bool,
+}
+
+// `constant:pi` and `function:tip` are gated as `constant` and `function`.
+fn gate_name(operation: &Operation) -> String {
+    let name = operation_name(operation);
+    match name.split_once(':') {
+        Some((kind, _)) => kind.to_string(),
+        None => name,
+    }
+}
+
+impl Calculator {
+    /// Turns education mode on, replacing any earlier settings.
+    pub fn enable_education_mode(
+        &mut self,
+        allowed: Option<&[&str]>,
+        max_decimals: Option<u32>,
+    ) -> Result<(), CalcError> {
+        let unknown = allowed
+            .unwrap_or_default()
+            .iter()
+            .find(|name| !GATED_OPERATIONS.contains(name));
+        if let Some(name) = unknown {
+            let err = unknown_identifier(name, GATED_OPERATIONS.iter().copied());
+            return Err(self.fail(err, None));
+        }
+        if let Some(decimals) = max_decimals.filter(|&d| d > MAX_ROUND_PLACES) {
+            let err = CalcError::InvalidArgument(format!(
+                "Decimals must be 0-{}, got {}",
+                MAX_ROUND_PLACES, decimals
+            ));
+            return Err(self.fail(err, Some(decimals as f64)));
+        }
+        self.education = Some(EducationMode {
+            allowed: allowed.map(|names| names.iter().map(|n| n.to_string()).collect()),
+            max_decimals,
+            clear_requested: false,
+        });
+        Ok(())
+    }
+
+    pub fn education(&self) -> Option<&EducationMode> {
+        self.education.as_ref()
+    }
+
+    // Refuses `operation` if education mode does not allow it.
+    pub(crate) fn permit(&self, operation: &Operation) -> Result<(), CalcError> {
+        // Naming the operation allocates, so only when there is a list.
+        if self
+            .education
+            .as_ref()
+            .is_none_or(|mode| mode.allowed.is_none())
+        {
+            return Ok(());
+        }
+        self.permit_name(&gate_name(operation))
+    }
+
+    pub(crate) fn permit_name(&self, name: &str) -> Result<(), CalcError> {
+        let allowed = self
+            .education
+            .as_ref()
+            .and_then(|mode| mode.allowed.as_ref());
+        match allowed {
+            Some(allowed) if !allowed.contains(name) => Err(self.fail(
+                CalcError::NotAllowed(format!("'{}' is disabled in education mode", name)),
+                None,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // In education mode, arms the confirmation instead of clearing.
+    pub(crate) fn request_clear_history(&mut self) -> bool {
+        match &mut self.education {
+            Some(mode) => {
+                mode.clear_requested = true;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `allowed` is an array of operation names (omit it to allow all);
+    /// `max_decimals` caps the places `format_value` shows (0-9).
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = enable_education_mode)]
+    pub fn enable_education_mode_js(
+        &mut self,
+        allowed: Option<Vec<String>>,
+        max_decimals: Option<u32>,
+    ) -> Result<(), CalcErrorJs> {
+        let names: Option<Vec<&str>> = allowed
+            .as_ref()
+            .map(|names| names.iter().map(String::as_str).collect());
+        self.enable_education_mode(names.as_deref(), max_decimals)
+            .map_err(CalcErrorJs::from)
+    }
+
+    /// Leaves education mode, dropping any unconfirmed clear request.
+    pub fn disable_education_mode(&mut self) {
+        self.education = None;
+    }
+
+    pub fn education_mode(&self) -> bool {
+        self.education.is_some()
+    }
+
+    /// Clears the history if `clear_history` asked to since the last
+    /// confirmation; returns whether it did.
+    pub fn confirm_clear_history(&mut self) -> bool {
+        let requested = self
+            .education
+            .as_mut()
+            .is_some_and(|mode| std::mem::take(&mut mode.clear_requested));
+        if requested {
+            self.history.clear();
+            self.history_rewritten();
+        }
+        requested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::FormatOptions;
+
+    #[test]
+    fn test_allow_list() {
+        let mut calc = Calculator::new();
+        calc.enable_education_mode(Some(&["add", "multiply", "constant"]), None)
+            .unwrap();
+        calc.set_value(9.0);
+        assert_eq!(calc.add(1.0), 10.0);
+        assert_eq!(calc.subtract(4.0), 10.0);
+        assert_eq!(
+            calc.apply(&Operation::Subtract, Some(4.0))
+                .map_err(|e| e.code()),
+            Err("NOT_ALLOWED")
+        );
+        assert!(matches!(calc.sqrt(), Err(CalcError::NotAllowed(_))));
+        assert!(matches!(
+            calc.evaluate("sqrt(16)"),
+            Err(CalcError::NotAllowed(_))
+        ));
+        assert!(calc.insert_constant("pi").is_ok());
+        calc.memory_store();
+        assert_eq!(calc.history_count(), 4);
+        assert_eq!(calc.check_invariants(), Ok(()));
+
+        calc.disable_education_mode();
+        assert_eq!(calc.sqrt(), Ok(std::f64::consts::PI.sqrt()));
+    }
+
+    #[test]
+    fn test_enable_rejects_bad_settings() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            calc.enable_education_mode(Some(&["sqrtt"]), None),
+            Err(CalcError::UnknownIdentifier {
+                name: "sqrtt".to_string(),
+                suggestions: vec!["sqrt".to_string()],
+            })
+        );
+        assert!(calc.enable_education_mode(None, Some(10)).is_err());
+        assert!(!calc.education_mode());
+    }
+
+    #[test]
+    fn test_decimal_limit() {
+        let mut calc = Calculator::new();
+        calc.enable_education_mode(None, Some(2)).unwrap();
+        calc.set_value(2.0);
+        calc.divide(3.0).unwrap();
+        let options = FormatOptions::default();
+        assert_eq!(calc.format_value(&options).unwrap(), "0.67");
+        let four = FormatOptions {
+            decimals: Some(4),
+            ..FormatOptions::default()
+        };
+        assert_eq!(calc.format_value(&four).unwrap(), "0.67");
+        calc.set_value(0.5);
+        assert_eq!(calc.format_value(&options).unwrap(), "0.5");
+        // Only the display is limited.
+        assert_eq!(calc.get_value(), 0.5);
+    }
+
+    #[test]
+    fn test_clearing_history_needs_confirmation() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        assert!(!calc.confirm_clear_history());
+        calc.enable_education_mode(None, None).unwrap();
+        assert!(!calc.clear_history());
+        assert_eq!(calc.history_count(), 1);
+        assert!(calc.confirm_clear_history());
+        assert_eq!(calc.history_count(), 0);
+        calc.add(1.0);
+        assert!(!calc.confirm_clear_history());
+        assert_eq!(calc.history_count(), 1);
+        calc.disable_education_mode();
+        assert!(calc.clear_history());
+        assert_eq!(calc.history_count(), 0);
+    }
+}
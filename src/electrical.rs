@@ -0,0 +1,340 @@
+//! Electronics helpers: resistor color codes and E-series values.
+//!
+//! [`resistor_value`] reads the bands of a through-hole resistor (IEC
+//! 60062): 3 or 4 bands are two digits and a multiplier, 5 or 6 bands are
+//! three digits and a multiplier, followed by the tolerance and, on 6-band
+//! parts, the temperature coefficient. A 3-band resistor is ±20%.
+//! [`colors_for_value`] goes the other way.
+//!
+//! Resistors are made in the preferred values of the E-series: 12 values
+//! per decade at ±10% (E12), 24 at ±5% (E24). [`nearest_e_series`] picks
+//! the closest one by ratio, which is how the series is spaced.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::parse_number;
+use crate::format::format_si;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+const DIGIT_COLORS: [&str; 10] = [
+    "black", "brown", "red", "orange", "yellow", "green", "blue", "violet", "grey", "white",
+];
+
+const E12: [f64; 12] = [1.0, 1.2, 1.5, 1.8, 2.2, 2.7, 3.3, 3.9, 4.7, 5.6, 6.8, 8.2];
+
+const E24: [f64; 24] = [
+    1.0, 1.1, 1.2, 1.3, 1.5, 1.6, 1.8, 2.0, 2.2, 2.4, 2.7, 3.0, 3.3, 3.6, 3.9, 4.3, 4.7, 5.1, 5.6,
+    6.2, 6.8, 7.5, 8.2, 9.1,
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ResistorValue {
+    pub ohms: f64,
+    /// In percent.
+    pub tolerance: f64,
+    /// Temperature coefficient in ppm/K, for 6-band resistors.
+    pub tempco: Option<f64>,
+    /// `ohms` with an SI prefix: `"4.7 kΩ"`.
+    pub label: String,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn color_name(color: &str) -> String {
+    match color.trim().to_ascii_lowercase().as_str() {
+        "gray" => "grey".to_string(),
+        "purple" => "violet".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn digit(color: &str) -> Result<u32, CalcError> {
+    DIGIT_COLORS
+        .iter()
+        .position(|&c| c == color)
+        .map(|d| d as u32)
+        .ok_or_else(|| invalid(format!("'{}' is not a digit band", color)))
+}
+
+// The power of ten a multiplier band stands for.
+fn multiplier(color: &str) -> Result<i32, CalcError> {
+    match color {
+        "gold" => Ok(-1),
+        "silver" => Ok(-2),
+        c => digit(c)
+            .map(|d| d as i32)
+            .map_err(|_| invalid(format!("'{}' is not a multiplier band", c))),
+    }
+}
+
+fn tolerance(color: &str) -> Result<f64, CalcError> {
+    Ok(match color {
+        "brown" => 1.0,
+        "red" => 2.0,
+        "orange" => 0.05,
+        "yellow" => 0.02,
+        "green" => 0.5,
+        "blue" => 0.25,
+        "violet" => 0.1,
+        "grey" => 0.01,
+        "gold" => 5.0,
+        "silver" => 10.0,
+        c => return Err(invalid(format!("'{}' is not a tolerance band", c))),
+    })
+}
+
+fn tempco(color: &str) -> Result<f64, CalcError> {
+    Ok(match color {
+        "black" => 250.0,
+        "brown" => 100.0,
+        "red" => 50.0,
+        "orange" => 15.0,
+        "yellow" => 25.0,
+        "green" => 20.0,
+        "blue" => 10.0,
+        "violet" => 5.0,
+        "grey" => 1.0,
+        c => {
+            return Err(invalid(format!(
+                "'{}' is not a temperature coefficient band",
+                c
+            )))
+        }
+    })
+}
+
+fn ohms_label(ohms: f64) -> String {
+    let value = format_si(ohms);
+    if value.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{} Ω", value)
+    } else {
+        format!("{}Ω", value)
+    }
+}
+
+// `digits x 10^exponent` from the decimal text, so 4.7 kΩ is exactly 4700.
+fn scaled(digits: u32, exponent: i32) -> Result<f64, CalcError> {
+    parse_number(&format!("{}e{}", digits, exponent))
+}
+
+/// The resistance of a resistor with `colors` bands, read starting from
+/// the end away from the tolerance band (`["yellow", "violet", "red",
+/// "gold"]` is 4.7 kΩ ±5%). `gray` and `purple` are accepted for grey and violet.
+pub fn resistor_value<S: AsRef<str>>(colors: &[S]) -> Result<ResistorValue, CalcError> {
+    let colors: Vec<String> = colors.iter().map(|c| color_name(c.as_ref())).collect();
+    let digit_bands = match colors.len() {
+        3 | 4 => 2,
+        5 | 6 => 3,
+        n => return Err(invalid(format!("A resistor has 3 to 6 bands, got {}", n))),
+    };
+    let digits = colors[..digit_bands]
+        .iter()
+        .try_fold(0, |n, c| Ok::<u32, CalcError>(n * 10 + digit(c)?))?;
+    let ohms = scaled(digits, multiplier(&colors[digit_bands])?)?;
+    let tolerance = match colors.get(digit_bands + 1) {
+        Some(color) => tolerance(color)?,
+        None => 20.0,
+    };
+    let tempco = colors.get(digit_bands + 2).map(|c| tempco(c)).transpose()?;
+    Ok(ResistorValue {
+        ohms,
+        tolerance,
+        tempco,
+        label: ohms_label(ohms),
+    })
+}
+
+/// The digit and multiplier bands for `ohms`: two digits when that is
+/// exact, otherwise three (a 5-band resistor). Add a tolerance band to
+/// complete the code. Values needing more than three digits, or below
+/// 0.1 Ω or above the white multiplier, have no code.
+pub fn colors_for_value(ohms: f64) -> Result<Vec<String>, CalcError> {
+    if !ohms.is_finite() || ohms < 0.0 {
+        return Err(invalid(format!(
+            "Resistance must be a non-negative number of ohms, got {}",
+            ohms
+        )));
+    }
+    if ohms == 0.0 {
+        // The single black band of a zero-ohm link.
+        return Ok(vec!["black".to_string()]);
+    }
+    let text = format!("{:e}", ohms);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let mut digits = mantissa.replace('.', "");
+    if digits.len() > 3 {
+        return Err(invalid(format!(
+            "{} Ω needs more than three significant digits",
+            ohms
+        )));
+    }
+    if digits.len() == 1 {
+        digits.push('0');
+    }
+    let power = exponent + 1 - digits.len() as i32;
+    let multiplier = match power {
+        -2 => "silver",
+        -1 => "gold",
+        0..=9 => DIGIT_COLORS[power as usize],
+        _ => {
+            return Err(invalid(format!(
+                "{} Ω is out of the color-code range",
+                ohms
+            )))
+        }
+    };
+    let mut bands: Vec<String> = digits
+        .bytes()
+        .map(|b| DIGIT_COLORS[(b - b'0') as usize].to_string())
+        .collect();
+    bands.push(multiplier.to_string());
+    Ok(bands)
+}
+
+/// The E12 or E24 value (`series` 12 or 24) closest to `value` by ratio:
+/// `nearest_e_series(5000.0, 12)` is 4700.
+pub fn nearest_e_series(value: f64, series: u32) -> Result<f64, CalcError> {
+    let values: &[f64] = match series {
+        12 => &E12,
+        24 => &E24,
+        n => {
+            return Err(invalid(format!(
+                "Unknown E-series E{}, expected E12 or E24",
+                n
+            )))
+        }
+    };
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid(format!(
+            "Value must be a positive number, got {}",
+            value
+        )));
+    }
+    let decade = value.log10().floor() as i32;
+    let normalized = value / 10f64.powi(decade);
+    // 10 is the next decade's first value.
+    let nearest = values
+        .iter()
+        .chain(std::iter::once(&10.0))
+        .min_by(|a, b| {
+            let distance = |v: f64| (normalized / v).ln().abs();
+            distance(**a).total_cmp(&distance(**b))
+        })
+        .copied()
+        .unwrap_or(1.0);
+    parse_number(&format!("{}e{}", nearest, decade))
+}
+
+/// `colors` is an array of band colors; returns `{ ohms, tolerance,
+/// tempco, label }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = resistor_value)]
+pub fn resistor_value_js(colors: Vec<String>) -> Result<JsValue, CalcErrorJs> {
+    let value = resistor_value(&colors).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&value).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = colors_for_value)]
+pub fn colors_for_value_js(ohms: f64) -> Result<Vec<String>, CalcErrorJs> {
+    colors_for_value(ohms).map_err(|e| CalcErrorJs::new(e, Some(ohms)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = nearest_e_series)]
+pub fn nearest_e_series_js(value: f64, series: u32) -> Result<f64, CalcErrorJs> {
+    nearest_e_series(value, series).map_err(|e| CalcErrorJs::new(e, Some(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resistor_value() {
+        let r = resistor_value(&["yellow", "violet", "red", "gold"]).unwrap();
+        assert_eq!(r.ohms, 4700.0);
+        assert_eq!(r.tolerance, 5.0);
+        assert_eq!(r.tempco, None);
+        assert_eq!(r.label, "4.7 kΩ");
+
+        let r = resistor_value(&["Brown", "black", "black", "gold"]).unwrap();
+        assert_eq!((r.ohms, r.label.as_str()), (10.0, "10 Ω"));
+        assert_eq!(
+            resistor_value(&["red", "red", "silver"]).unwrap().ohms,
+            0.22
+        );
+        assert_eq!(
+            resistor_value(&["orange", "orange", "black"])
+                .unwrap()
+                .tolerance,
+            20.0
+        );
+        let r = resistor_value(&["brown", "black", "black", "red", "brown", "gray"]).unwrap();
+        assert_eq!((r.ohms, r.tolerance, r.tempco), (10_000.0, 1.0, Some(1.0)));
+        assert_eq!(
+            resistor_value(&["white", "purple", "white", "white", "violet"])
+                .unwrap()
+                .ohms,
+            979e9
+        );
+
+        assert!(resistor_value(&["red", "red"]).is_err());
+        assert!(resistor_value(&["gold", "red", "red", "gold"]).is_err());
+        assert!(resistor_value(&["red", "red", "red", "black"]).is_err());
+        assert!(resistor_value(&["red", "red", "pink", "gold"]).is_err());
+    }
+
+    #[test]
+    fn test_colors_for_value() {
+        assert_eq!(
+            colors_for_value(4700.0).unwrap(),
+            vec!["yellow", "violet", "red"]
+        );
+        assert_eq!(
+            colors_for_value(0.47).unwrap(),
+            vec!["yellow", "violet", "silver"]
+        );
+        assert_eq!(
+            colors_for_value(1e6).unwrap(),
+            vec!["brown", "black", "green"]
+        );
+        assert_eq!(
+            colors_for_value(1.5).unwrap(),
+            vec!["brown", "green", "gold"]
+        );
+        assert_eq!(
+            colors_for_value(4990.0).unwrap(),
+            vec!["yellow", "white", "white", "brown"]
+        );
+        assert_eq!(colors_for_value(0.0).unwrap(), vec!["black"]);
+        assert!(colors_for_value(4701.0).is_err());
+        assert!(colors_for_value(0.01).is_err());
+        assert!(colors_for_value(1e12).is_err());
+        assert!(colors_for_value(-1.0).is_err());
+
+        // Round trip through a tolerance band.
+        let mut bands = colors_for_value(33_000.0).unwrap();
+        bands.push("gold".to_string());
+        assert_eq!(resistor_value(&bands).unwrap().ohms, 33_000.0);
+    }
+
+    #[test]
+    fn test_nearest_e_series() {
+        assert_eq!(nearest_e_series(5000.0, 12), Ok(4700.0));
+        assert_eq!(nearest_e_series(5000.0, 24), Ok(5100.0));
+        assert_eq!(nearest_e_series(0.0333, 12), Ok(0.033));
+        assert_eq!(nearest_e_series(9.6, 12), Ok(10.0));
+        assert_eq!(nearest_e_series(9.4, 24), Ok(9.1));
+        assert_eq!(nearest_e_series(220.0, 24), Ok(220.0));
+        assert!(nearest_e_series(100.0, 6).is_err());
+        assert!(nearest_e_series(0.0, 12).is_err());
+    }
+}
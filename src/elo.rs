@@ -0,0 +1,118 @@
+//! Elo ratings.
+//!
+//! A player rated 400 points above another is expected to score ten times
+//! as much: [`win_probability`] is `1 / (1 + 10^((b - a) / 400))`, counting
+//! a draw as half a win. After a game each rating moves by `k` times the
+//! difference between the actual and expected score; FIDE uses a K-factor
+//! of 40 for new players, 20 for most and 10 at 2400 and above.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn rating(value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() {
+        return Err(invalid(format!("Invalid rating {}", value)));
+    }
+    Ok(value)
+}
+
+/// The expected score of a player rated `rating_a` against `rating_b`.
+pub fn win_probability(rating_a: f64, rating_b: f64) -> Result<f64, CalcError> {
+    let difference = rating(rating_b)? - rating(rating_a)?;
+    Ok(1.0 / (1.0 + 10f64.powf(difference / 400.0)))
+}
+
+/// The rating lead that gives an expected score of `p`, strictly between 0
+/// and 1.
+pub fn rating_difference_for(p: f64) -> Result<f64, CalcError> {
+    if !(p > 0.0 && p < 1.0) {
+        return Err(invalid(format!(
+            "Expected score must be between 0 and 1 exclusive, got {}",
+            p
+        )));
+    }
+    Ok(-400.0 * (1.0 / p - 1.0).log10())
+}
+
+/// How much `rating_a` changes after scoring `result` (1 a win, 0.5 a
+/// draw, 0 a loss) against `rating_b`. The opponent's rating changes by
+/// the negation.
+pub fn elo_change(rating_a: f64, rating_b: f64, result: f64, k: f64) -> Result<f64, CalcError> {
+    if !(0.0..=1.0).contains(&result) {
+        return Err(invalid(format!(
+            "Result must be between 0 (a loss) and 1 (a win), got {}",
+            result
+        )));
+    }
+    if !k.is_finite() || k <= 0.0 {
+        return Err(invalid(format!("K-factor must be positive, got {}", k)));
+    }
+    Ok(k * (result - win_probability(rating_a, rating_b)?))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = win_probability)]
+pub fn win_probability_js(rating_a: f64, rating_b: f64) -> Result<f64, CalcErrorJs> {
+    win_probability(rating_a, rating_b).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = rating_difference_for)]
+pub fn rating_difference_for_js(p: f64) -> Result<f64, CalcErrorJs> {
+    rating_difference_for(p).map_err(|e| CalcErrorJs::new(e, Some(p)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = elo_change)]
+pub fn elo_change_js(
+    rating_a: f64,
+    rating_b: f64,
+    result: f64,
+    k: f64,
+) -> Result<f64, CalcErrorJs> {
+    elo_change(rating_a, rating_b, result, k).map_err(|e| CalcErrorJs::new(e, Some(result)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_win_probability() {
+        assert_eq!(win_probability(1500.0, 1500.0), Ok(0.5));
+        assert!(close(win_probability(1900.0, 1500.0).unwrap(), 10.0 / 11.0));
+        let favourite = win_probability(1700.0, 1500.0).unwrap();
+        let underdog = win_probability(1500.0, 1700.0).unwrap();
+        assert!(close(favourite + underdog, 1.0));
+        assert!(close(rating_difference_for(favourite).unwrap(), 200.0));
+        assert_eq!(rating_difference_for(0.5), Ok(0.0));
+        assert!(rating_difference_for(1.0).is_err());
+        assert!(win_probability(f64::NAN, 1500.0).is_err());
+    }
+
+    #[test]
+    fn test_elo_change() {
+        // Equal players, K = 32: a win is worth 16.
+        assert_eq!(elo_change(1500.0, 1500.0, 1.0, 32.0), Ok(16.0));
+        assert_eq!(elo_change(1500.0, 1500.0, 0.5, 32.0), Ok(0.0));
+        // An upset wins more than a favourite's win.
+        let upset = elo_change(1500.0, 1900.0, 1.0, 20.0).unwrap();
+        assert!(close(upset, 20.0 * 10.0 / 11.0));
+        let drawn = elo_change(1900.0, 1500.0, 0.5, 20.0).unwrap();
+        assert!(close(drawn, 20.0 * (0.5 - 10.0 / 11.0)));
+        assert!(elo_change(1500.0, 1500.0, 2.0, 32.0).is_err());
+        assert!(elo_change(1500.0, 1500.0, 1.0, 0.0).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Home-energy helpers: battery runtime, running costs and solar sizing.
+//!
+//! Efficiencies and depths of discharge are percentages, like the rates
+//! elsewhere in the crate: `85` is 85%. Energy is in kWh, power in W.
+//!
+//! Solar sizing uses peak sun hours, the hours per day of 1000 W/m² sun
+//! that deliver a site's daily insolation, so a 400 W panel at 5 peak sun
+//! hours makes 2 kWh a day before losses. The system efficiency covers the
+//! inverter, wiring, temperature and soiling losses between them, commonly
+//! 75-85%.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SolarSizing {
+    /// Panel power needed to cover the daily use, in W.
+    pub array_watts: f64,
+    /// Whole panels needed for `array_watts`.
+    pub panel_count: u32,
+    /// What `panel_count` panels make a day after losses, in kWh.
+    pub daily_production_kwh: f64,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn positive(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid(format!("{} must be positive, got {}", what, value)));
+    }
+    Ok(value)
+}
+
+fn non_negative(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value < 0.0 {
+        return Err(invalid(format!(
+            "{} must not be negative, got {}",
+            what, value
+        )));
+    }
+    Ok(value)
+}
+
+// A percentage over 0 and at most 100, as a fraction.
+fn percent(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value <= 0.0 || value > 100.0 {
+        return Err(invalid(format!(
+            "{} must be over 0 and at most 100%, got {}",
+            what, value
+        )));
+    }
+    Ok(value / 100.0)
+}
+
+/// Hours a battery of `capacity_mah` runs a `load_ma` load, when
+/// `efficiency` percent of its capacity is usable.
+pub fn battery_runtime(capacity_mah: f64, load_ma: f64, efficiency: f64) -> Result<f64, CalcError> {
+    let capacity = positive("Capacity", capacity_mah)?;
+    let load = positive("Load", load_ma)?;
+    Ok(capacity * percent("Efficiency", efficiency)? / load)
+}
+
+/// What running `watts` for `hours` costs at `price_per_kwh`.
+pub fn energy_cost(watts: f64, hours: f64, price_per_kwh: f64) -> Result<f64, CalcError> {
+    let kwh = non_negative("Power", watts)? * non_negative("Hours", hours)? / 1000.0;
+    Ok(kwh * non_negative("Price", price_per_kwh)?)
+}
+
+/// The solar array that covers `daily_kwh` at `peak_sun_hours` with panels
+/// of `panel_watts` and `system_efficiency` percent.
+pub fn solar_sizing(
+    daily_kwh: f64,
+    peak_sun_hours: f64,
+    panel_watts: f64,
+    system_efficiency: f64,
+) -> Result<SolarSizing, CalcError> {
+    let daily_wh = non_negative("Daily use", daily_kwh)? * 1000.0;
+    let sun = positive("Peak sun hours", peak_sun_hours)?;
+    if sun > 24.0 {
+        return Err(invalid(format!(
+            "Peak sun hours must be at most 24, got {}",
+            sun
+        )));
+    }
+    let panel = positive("Panel power", panel_watts)?;
+    let efficiency = percent("System efficiency", system_efficiency)?;
+    let array_watts = daily_wh / (sun * efficiency);
+    let panels = (array_watts / panel).ceil();
+    if panels > u32::MAX as f64 {
+        return Err(invalid("That many panels is not a home system"));
+    }
+    Ok(SolarSizing {
+        array_watts,
+        panel_count: panels as u32,
+        daily_production_kwh: panels * panel * sun * efficiency / 1000.0,
+    })
+}
+
+/// Battery capacity in kWh that covers `daily_kwh` for `days` without
+/// sun, discharging to at most `depth_of_discharge` percent.
+pub fn battery_bank_kwh(
+    daily_kwh: f64,
+    days: f64,
+    depth_of_discharge: f64,
+) -> Result<f64, CalcError> {
+    let needed = non_negative("Daily use", daily_kwh)? * non_negative("Days", days)?;
+    Ok(needed / percent("Depth of discharge", depth_of_discharge)?)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = battery_runtime)]
+pub fn battery_runtime_js(
+    capacity_mah: f64,
+    load_ma: f64,
+    efficiency: f64,
+) -> Result<f64, CalcErrorJs> {
+    battery_runtime(capacity_mah, load_ma, efficiency).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = energy_cost)]
+pub fn energy_cost_js(watts: f64, hours: f64, price_per_kwh: f64) -> Result<f64, CalcErrorJs> {
+    energy_cost(watts, hours, price_per_kwh).map_err(CalcErrorJs::from)
+}
+
+/// `{ array_watts, panel_count, daily_production_kwh }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = solar_sizing)]
+pub fn solar_sizing_js(
+    daily_kwh: f64,
+    peak_sun_hours: f64,
+    panel_watts: f64,
+    system_efficiency: f64,
+) -> Result<JsValue, CalcErrorJs> {
+    let sizing = solar_sizing(daily_kwh, peak_sun_hours, panel_watts, system_efficiency)
+        .map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&sizing).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = battery_bank_kwh)]
+pub fn battery_bank_kwh_js(
+    daily_kwh: f64,
+    days: f64,
+    depth_of_discharge: f64,
+) -> Result<f64, CalcErrorJs> {
+    battery_bank_kwh(daily_kwh, days, depth_of_discharge).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_battery_and_cost() {
+        // A 3000 mAh cell, 85% usable, at 250 mA.
+        assert!(close(battery_runtime(3000.0, 250.0, 85.0).unwrap(), 10.2));
+        assert_eq!(battery_runtime(2000.0, 100.0, 100.0), Ok(20.0));
+        assert!(battery_runtime(2000.0, 0.0, 90.0).is_err());
+        assert!(battery_runtime(2000.0, 100.0, 0.0).is_err());
+        assert!(battery_runtime(2000.0, 100.0, 120.0).is_err());
+
+        // A 1500 W heater for 4 hours at 0.15 per kWh.
+        assert!(close(energy_cost(1500.0, 4.0, 0.15).unwrap(), 0.9));
+        assert_eq!(energy_cost(0.0, 4.0, 0.15), Ok(0.0));
+        assert!(energy_cost(-1.0, 4.0, 0.15).is_err());
+    }
+
+    #[test]
+    fn test_solar_sizing() {
+        // 30 kWh a day at 5 peak sun hours and 80%: 7.5 kW of panels.
+        let sizing = solar_sizing(30.0, 5.0, 400.0, 80.0).unwrap();
+        assert!(close(sizing.array_watts, 7500.0));
+        assert_eq!(sizing.panel_count, 19);
+        assert!(close(sizing.daily_production_kwh, 30.4));
+        assert!(sizing.daily_production_kwh >= 30.0);
+        assert_eq!(solar_sizing(0.0, 5.0, 400.0, 80.0).unwrap().panel_count, 0);
+        assert!(solar_sizing(30.0, 0.0, 400.0, 80.0).is_err());
+        assert!(solar_sizing(30.0, 25.0, 400.0, 80.0).is_err());
+        assert!(solar_sizing(30.0, 5.0, 0.0, 80.0).is_err());
+
+        // Two days of 10 kWh at 50% depth of discharge.
+        assert_eq!(battery_bank_kwh(10.0, 2.0, 50.0), Ok(40.0));
+        assert!(battery_bank_kwh(10.0, 2.0, 0.0).is_err());
+    }
+}
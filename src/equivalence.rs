@@ -0,0 +1,218 @@
+//! Checking whether two expressions mean the same thing.
+//!
+//! Grading `2*(x+1)` against `2*x+2` cannot compare text. Instead
+//! [`compare_expressions`] evaluates both sides at the same sampled values
+//! of their variables and compares the results within a tolerance. Points
+//! are drawn from a fixed seed, alternating whole numbers and fractions in
+//! -10..10, so the verdict for a pair of expressions never changes between
+//! runs. Points where either side is undefined (a square root of a
+//! negative, a division by zero) are skipped, so `sqrt(x)^2` equals `x`.
+//!
+//! Sampling can be fooled by expressions that agree at every sampled point
+//! and nowhere else, but not by any a student is likely to type. Without
+//! variables the two values are simply compared.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, Dialect, Expr};
+use crate::random::Rng;
+use crate::variables::collect_dependencies;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// Points compared before two expressions with variables count as equal.
+pub const SAMPLE_POINTS: usize = 32;
+// Fewer points than this where both sides are defined is no verdict.
+const MIN_POINTS: usize = 8;
+const MAX_DRAWS: usize = 256;
+const SEED: u64 = 0x5eed_e9a1;
+
+/// A point where the two expressions differ.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Counterexample {
+    pub point: BTreeMap<String, f64>,
+    pub left: f64,
+    pub right: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub equal: bool,
+    /// The variables of either expression, sorted.
+    pub variables: Vec<String>,
+    /// How many points were compared.
+    pub points: usize,
+    pub counterexample: Option<Counterexample>,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+/// Whether `x` and `y` agree within `tolerance`, relative to the larger of
+/// them once that exceeds 1.
+pub(crate) fn within(x: f64, y: f64, tolerance: f64) -> bool {
+    x == y || (x - y).abs() <= tolerance * x.abs().max(y.abs()).max(1.0)
+}
+
+// `expr` at `point`; `None` where it is undefined there.
+fn value_at(expr: &Expr, point: &BTreeMap<String, f64>) -> Result<Option<f64>, CalcError> {
+    match expr.eval(point) {
+        Ok(value) if value.is_finite() => Ok(Some(value)),
+        Ok(_) => Ok(None),
+        Err(e @ (CalcError::Syntax { .. } | CalcError::UnknownIdentifier { .. })) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Compares standard-dialect expressions `a` and `b` as described in the
+/// module docs. `tolerance` is relative to the larger result once that
+/// exceeds 1, and absolute below; 0 demands identical results.
+pub fn compare_expressions(a: &str, b: &str, tolerance: f64) -> Result<Comparison, CalcError> {
+    if !tolerance.is_finite() || tolerance < 0.0 {
+        return Err(invalid(format!(
+            "Tolerance must be a non-negative number, got {}",
+            tolerance
+        )));
+    }
+    let (left, right) = (parse(a, Dialect::Standard)?, parse(b, Dialect::Standard)?);
+    let mut names = BTreeSet::new();
+    collect_dependencies(&left, &mut names);
+    collect_dependencies(&right, &mut names);
+    let variables: Vec<String> = names.into_iter().collect();
+    let (wanted, draws) = if variables.is_empty() {
+        (1, 1)
+    } else {
+        (SAMPLE_POINTS, MAX_DRAWS)
+    };
+
+    let mut rng = Rng::seeded(SEED);
+    let mut points = 0;
+    for draw in 0..draws {
+        let point: BTreeMap<String, f64> = variables
+            .iter()
+            .map(|name| {
+                let value = if draw % 2 == 0 {
+                    rng.below(21) as f64 - 10.0
+                } else {
+                    rng.unit() * 20.0 - 10.0
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        let (Some(x), Some(y)) = (value_at(&left, &point)?, value_at(&right, &point)?) else {
+            continue;
+        };
+        if !within(x, y, tolerance) {
+            return Ok(Comparison {
+                equal: false,
+                variables,
+                points: points + 1,
+                counterexample: Some(Counterexample {
+                    point,
+                    left: x,
+                    right: y,
+                }),
+            });
+        }
+        points += 1;
+        if points == wanted {
+            break;
+        }
+    }
+    if points < wanted.min(MIN_POINTS) {
+        return Err(invalid(format!(
+            "The expressions are both defined at only {} of {} sampled points",
+            points, draws
+        )));
+    }
+    Ok(Comparison {
+        equal: true,
+        variables,
+        points,
+        counterexample: None,
+    })
+}
+
+/// Whether `a` and `b` are numerically equivalent; see
+/// [`compare_expressions`].
+pub fn expressions_equal(a: &str, b: &str, tolerance: f64) -> Result<bool, CalcError> {
+    compare_expressions(a, b, tolerance).map(|comparison| comparison.equal)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = expressions_equal)]
+pub fn expressions_equal_js(a: &str, b: &str, tolerance: f64) -> Result<bool, CalcErrorJs> {
+    expressions_equal(a, b, tolerance).map_err(|e| CalcErrorJs::new(e, Some(tolerance)))
+}
+
+/// `{ equal, variables, points, counterexample }`, where a counterexample
+/// is `{ point, left, right }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = compare_expressions)]
+pub fn compare_expressions_js(a: &str, b: &str, tolerance: f64) -> Result<JsValue, CalcErrorJs> {
+    let comparison =
+        compare_expressions(a, b, tolerance).map_err(|e| CalcErrorJs::new(e, Some(tolerance)))?;
+    Ok(serde_wasm_bindgen::to_value(&comparison).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equivalent_expressions() {
+        assert_eq!(expressions_equal("2*(x+1)", "2*x+2", 1e-9), Ok(true));
+        assert_eq!(
+            expressions_equal("(a+b)^2", "a^2 + 2*a*b + b^2", 1e-9),
+            Ok(true)
+        );
+        assert_eq!(
+            expressions_equal("sin(x)^2 + cos(x)^2", "1", 1e-12),
+            Ok(true)
+        );
+        // Undefined points are skipped.
+        assert_eq!(expressions_equal("sqrt(x)^2", "x", 1e-9), Ok(true));
+        assert_eq!(expressions_equal("x/x", "1", 0.0), Ok(true));
+        assert_eq!(expressions_equal("0.1 + 0.2", "0.3", 1e-12), Ok(true));
+        assert_eq!(expressions_equal("0.1 + 0.2", "0.3", 0.0), Ok(false));
+    }
+
+    #[test]
+    fn test_different_expressions() {
+        let comparison = compare_expressions("2*x + 1", "2*(x + 1)", 1e-9).unwrap();
+        assert!(!comparison.equal);
+        assert_eq!(comparison.variables, ["x"]);
+        let counterexample = comparison.counterexample.unwrap();
+        assert_eq!(counterexample.right - counterexample.left, 1.0);
+        assert_eq!(expressions_equal("sqrt(x^2)", "x", 1e-9), Ok(false));
+        assert_eq!(expressions_equal("floor(x)", "x", 1e-9), Ok(false));
+        // The verdict is reproducible.
+        assert_eq!(
+            compare_expressions("x^2", "x", 0.0).unwrap(),
+            compare_expressions("x^2", "x", 0.0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_comparison_errors() {
+        assert!(matches!(
+            expressions_equal("2 *", "2", 0.0),
+            Err(CalcError::Syntax { .. })
+        ));
+        assert!(matches!(
+            expressions_equal("foo(x)", "x", 0.0),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(expressions_equal("x", "x", -1.0).is_err());
+        // Never defined: no verdict rather than "equal".
+        assert!(expressions_equal("sqrt(-1 - x^2)", "1", 0.0).is_err());
+        assert_eq!(expressions_equal("1/0", "2", 0.0).ok(), None);
+    }
+}
@@ -0,0 +1,111 @@
+//! A log of failed operations.
+//!
+//! Every error a calculator method reports (a division by zero, an
+//! expression that does not parse, an operation education mode refuses) is
+//! appended here with what was being worked on and when, separately from
+//! the history, which only records what succeeded. When an end user says
+//! "it stopped working", `get_error_log()` shows what they tried.
+//!
+//! The log keeps the newest [`ERROR_LOG_LIMIT`] entries.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{now_ms, CalcError, Calculator};
+
+/// How many failures the log keeps before dropping the oldest.
+pub const ERROR_LOG_LIMIT: usize = 100;
+
+/// One failed operation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorLogEntry {
+    /// The stable error code, as on `CalcErrorJs`.
+    pub code: String,
+    /// The full message, including any position or suggestions.
+    pub message: String,
+    /// The input that triggered the error, when there was one.
+    pub operand: Option<f64>,
+    /// The current value when it failed; failures leave it unchanged.
+    pub value: f64,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: f64,
+}
+
+// Appended to from `Calculator::fail`, which only borrows the calculator.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ErrorLog(RefCell<VecDeque<ErrorLogEntry>>);
+
+impl ErrorLog {
+    pub(crate) fn record(&self, error: &CalcError, operand: Option<f64>, value: f64) {
+        let mut entries = self.0.borrow_mut();
+        if entries.len() == ERROR_LOG_LIMIT {
+            entries.pop_front();
+        }
+        entries.push_back(ErrorLogEntry {
+            code: error.code().to_string(),
+            message: error.to_string(),
+            operand,
+            value,
+            timestamp: now_ms(),
+        });
+    }
+}
+
+impl Calculator {
+    /// The logged failures, oldest first.
+    pub fn error_log(&self) -> Vec<ErrorLogEntry> {
+        self.error_log.0.borrow().iter().cloned().collect()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `[{ code, message, operand, value, timestamp }]`, oldest first; see
+    /// [`ErrorLogEntry`].
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_error_log)]
+    pub fn get_error_log_js(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.error_log()).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn error_log_count(&self) -> usize {
+        self.error_log.0.borrow().len()
+    }
+
+    pub fn clear_error_log(&mut self) {
+        self.error_log.0.borrow_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_log() {
+        let mut calc = Calculator::new();
+        calc.add(10.0);
+        assert!(calc.divide(0.0).is_err());
+        assert!(calc.evaluate("1 +").is_err());
+        calc.add(1.0);
+        let log = calc.error_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].code, "DIVISION_BY_ZERO");
+        assert_eq!((log[0].operand, log[0].value), (Some(0.0), 10.0));
+        assert_eq!(log[1].code, "SYNTAX_ERROR");
+        assert!(log[1].message.contains("position"));
+        assert!(log[0].timestamp > 0.0 && log[1].timestamp >= log[0].timestamp);
+        assert_eq!(calc.history_count(), 2);
+
+        calc.clear_error_log();
+        assert_eq!(calc.error_log_count(), 0);
+        for _ in 0..ERROR_LOG_LIMIT + 5 {
+            let _ = calc.divide(0.0);
+        }
+        assert_eq!(calc.error_log_count(), ERROR_LOG_LIMIT);
+    }
+}
@@ -0,0 +1,330 @@
+//! Listener hooks for calculator state changes.
+//!
+//! A calculator notifies three kinds of listener:
+//!
+//! - **change**: `(value, memory)` after every state-changing call;
+//! - **history**: each [`CalculationHistory`] entry as it is appended;
+//! - **error**: each [`CalcError`] a method reports, with its operand.
+//!
+//! Listeners live in a thread-local registry keyed by a per-calculator id
+//! rather than in the struct, so `Calculator` holds no JS handles. Ids are
+//! unique across threads, a clone starts with no id and so no listeners,
+//! and dropping a calculator removes its entry.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::hooks::{FormatHook, InputHook};
+use crate::logging::{LogLevel, LogSink};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, CalculationHistory, Calculator, Operation};
+
+type ChangeListener = Rc<dyn Fn(f64, f64)>;
+type HistoryListener = Rc<dyn Fn(&CalculationHistory)>;
+type ErrorListener = Rc<dyn Fn(&CalcError, Option<f64>)>;
+
+// Everything registered for one calculator, including the hooks of
+// `hooks` and the sink of `logging`.
+#[derive(Default, Clone)]
+pub(crate) struct Listeners {
+    change: Option<ChangeListener>,
+    history: Option<HistoryListener>,
+    error: Option<ErrorListener>,
+    pub(crate) input: Option<InputHook>,
+    pub(crate) format: Option<FormatHook>,
+    pub(crate) log: Option<(LogLevel, LogSink)>,
+}
+
+thread_local! {
+    static LISTENERS: RefCell<HashMap<u32, Listeners>> = RefCell::new(HashMap::new());
+}
+
+// Process-wide, since a calculator can move to another thread.
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A calculator's key in the registries, if it has been given one.
+/// Cloning gives none: a clone is a new calculator.
+#[derive(Debug, Default)]
+pub(crate) struct ListenerId(Option<u32>);
+
+impl ListenerId {
+    pub(crate) fn get(&self) -> Option<u32> {
+        self.0
+    }
+}
+
+impl Clone for ListenerId {
+    fn clone(&self) -> Self {
+        ListenerId(None)
+    }
+}
+
+impl Drop for Calculator {
+    fn drop(&mut self) {
+        if let Some(id) = self.listener_id.0.take() {
+            release(id);
+        }
+    }
+}
+
+// Removes `id` from every registry on this thread. Entries registered on
+// another thread stay there until that thread exits.
+fn release(id: u32) {
+    let _ = LISTENERS.try_with(|map| map.borrow_mut().remove(&id));
+}
+
+impl Calculator {
+    // This calculator's key in the thread-local registries, assigned on
+    // first use.
+    pub(crate) fn registry_id(&mut self) -> u32 {
+        *self
+            .listener_id
+            .0
+            .get_or_insert_with(|| NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    // Hands this calculator's id to `other`, which takes its place (as
+    // replay's scratch copy does once it succeeds).
+    pub(crate) fn pass_registry_id(&mut self, other: &mut Calculator) {
+        other.listener_id = std::mem::take(&mut self.listener_id);
+    }
+
+    pub(crate) fn listeners_mut(&mut self, update: impl FnOnce(&mut Listeners)) {
+        let id = self.registry_id();
+        LISTENERS.with(|map| update(map.borrow_mut().entry(id).or_default()));
+    }
+
+    // As `listeners_mut`, for removals, which need no id.
+    pub(crate) fn clear_registered(&mut self, update: impl FnOnce(&mut Listeners)) {
+        if let Some(id) = self.listener_id.get() {
+            LISTENERS.with(|map| map.borrow_mut().get_mut(&id).map(update));
+        }
+    }
+
+    // Cloned out so a listener can't observe the registry mid-borrow. A
+    // run in progress has none: it reports once it is over (see `batch`).
+    pub(crate) fn listeners(&self) -> Option<Listeners> {
+        if self.run_usage.is_some() {
+            return None;
+        }
+        let id = self.listener_id.get()?;
+        LISTENERS.with(|map| map.borrow().get(&id).cloned())
+    }
+
+    pub fn on_change(&mut self, listener: impl Fn(f64, f64) + 'static) {
+        self.listeners_mut(|l| l.change = Some(Rc::new(listener)));
+    }
+
+    pub fn on_history(&mut self, listener: impl Fn(&CalculationHistory) + 'static) {
+        self.listeners_mut(|l| l.history = Some(Rc::new(listener)));
+    }
+
+    pub fn on_error(&mut self, listener: impl Fn(&CalcError, Option<f64>) + 'static) {
+        self.listeners_mut(|l| l.error = Some(Rc::new(listener)));
+    }
+
+    // Called as `entry` is recorded, which may be before the method stores
+    // its result, so the post-call state is read from the entry.
+    pub(crate) fn notify_recorded(&self, entry: &CalculationHistory) {
+        self.log_operation(entry);
+        let Some(listeners) = self.listeners() else {
+            return;
+        };
+        if let Some(history) = listeners.history {
+            history(entry);
+        }
+        if let Some(change) = listeners.change {
+            let (value, memory) = self.state_after(&entry.operation, entry.result);
+            change(value, memory);
+        }
+    }
+
+    // For an operation that records no entry (while incognito, say), called
+    // where its entry would have been: only the change listener hears it.
+    pub(crate) fn notify_unrecorded(&self, operation: &Operation, result: f64) {
+        if let Some(change) = self.listeners().and_then(|l| l.change) {
+            let (value, memory) = self.state_after(operation, result);
+            change(value, memory);
+        }
+    }
+
+    // `(value, memory)` once `operation` stores `result`.
+    fn state_after(&self, operation: &Operation, result: f64) -> (f64, f64) {
+        if operation.affects_memory() {
+            (self.current_value, result)
+        } else {
+            (result, self.memory)
+        }
+    }
+
+    // For changes that rewrite the history rather than append to it: only
+    // the change listener hears about them.
+    pub(crate) fn notify_changed(&self) {
+        if let Some(change) = self.listeners().and_then(|l| l.change) {
+            change(self.current_value, self.memory);
+        }
+    }
+
+    /// Reports `error` to the error listener and the error log and hands it
+    /// back, so failure paths can `return Err(self.fail(..))`.
+    pub(crate) fn fail(&self, error: CalcError, operand: Option<f64>) -> CalcError {
+        // A failed run reports once, after putting back what it changed.
+        if self.run_usage.is_some() {
+            return error;
+        }
+        self.session.record_error();
+        if !self.incognito {
+            self.error_log.record(&error, operand, self.current_value);
+        }
+        self.log_error(&error);
+        if let Some(listener) = self.listeners().and_then(|l| l.error) {
+            listener(&error, operand);
+        }
+        error
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Calls `f(value, memory)` after every state-changing call.
+    #[cfg(feature = "wasm")]
+    pub fn set_on_change(&mut self, f: &js_sys::Function) {
+        let f = f.clone();
+        self.on_change(move |value, memory| {
+            let _ = f.call2(&JsValue::NULL, &value.into(), &memory.into());
+        });
+    }
+
+    /// Calls `f(entry)` with each new history entry, shaped like
+    /// `get_history()` elements.
+    #[cfg(feature = "wasm")]
+    pub fn set_on_history(&mut self, f: &js_sys::Function) {
+        let f = f.clone();
+        self.on_history(move |entry| {
+            if let Ok(entry) = serde_wasm_bindgen::to_value(entry) {
+                let _ = f.call1(&JsValue::NULL, &entry);
+            }
+        });
+    }
+
+    /// Calls `f(error)` with a `CalcErrorJs` whenever a method fails.
+    #[cfg(feature = "wasm")]
+    pub fn set_on_error(&mut self, f: &js_sys::Function) {
+        let f = f.clone();
+        self.on_error(move |error, operand| {
+            let error = CalcErrorJs::new(error.clone(), operand);
+            let _ = f.call1(&JsValue::NULL, &error.into());
+        });
+    }
+
+    /// Removes every listener from this calculator, leaving its hooks.
+    pub fn clear_listeners(&mut self) {
+        self.clear_registered(|l| {
+            l.change = None;
+            l.history = None;
+            l.error = None;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::tape::TapeFormat;
+
+    #[test]
+    fn test_change_and_history_listeners_fire() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        let mut calc = Calculator::new();
+        let sink = changes.clone();
+        calc.on_change(move |value, memory| sink.borrow_mut().push((value, memory)));
+        let sink = entries.clone();
+        calc.on_history(move |entry| sink.borrow_mut().push(entry.operation.clone()));
+
+        calc.add(5.0);
+        calc.memory_store();
+        calc.multiply(2.0);
+        assert_eq!(*changes.borrow(), vec![(5.0, 0.0), (5.0, 5.0), (10.0, 5.0)]);
+        assert_eq!(
+            *entries.borrow(),
+            vec![Operation::Add, Operation::MemoryStore, Operation::Multiply]
+        );
+    }
+
+    #[test]
+    fn test_error_listener_receives_operand() {
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let mut calc = Calculator::new();
+        let sink = errors.clone();
+        calc.on_error(move |error, operand| sink.borrow_mut().push((error.clone(), operand)));
+        let _ = calc.divide(0.0);
+        calc.set_value(-1.0);
+        let _ = calc.sqrt();
+        assert_eq!(
+            *errors.borrow(),
+            vec![
+                (CalcError::DivisionByZero, Some(0.0)),
+                (CalcError::NegativeSqrt, Some(-1.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_import_notifies_only_the_error() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut calc = Calculator::new();
+        let sink = log.clone();
+        calc.on_history(move |_| sink.borrow_mut().push("history"));
+        let sink = log.clone();
+        calc.on_error(move |_, _| sink.borrow_mut().push("error"));
+
+        assert!(calc.import_tape("10 +\n0 /\n", TapeFormat::Text).is_err());
+        assert_eq!(*log.borrow(), vec!["error"]);
+        log.borrow_mut().clear();
+        calc.import_tape("10 +\n5 +\n", TapeFormat::Text).unwrap();
+        assert_eq!(*log.borrow(), vec!["history"; 3]);
+    }
+
+    #[test]
+    fn test_listeners_are_per_calculator_and_clearable() {
+        let count = Rc::new(Cell::new(0));
+        let mut calc = Calculator::new();
+        let mut other = Calculator::new();
+        let sink = count.clone();
+        calc.on_change(move |_, _| sink.set(sink.get() + 1));
+        other.add(1.0);
+        assert_eq!(count.get(), 0);
+        calc.add(1.0);
+        let mut clone = calc.clone();
+        clone.add(1.0);
+        assert_eq!(count.get(), 1);
+        // Clearing a clone leaves the original's listeners alone.
+        clone.clear_listeners();
+        calc.add(1.0);
+        assert_eq!(count.get(), 2);
+        calc.clear_listeners();
+        calc.add(1.0);
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn test_dropping_a_calculator_releases_its_listeners() {
+        let mut calc = Calculator::new();
+        calc.on_change(|_, _| {});
+        calc.on_input(|src| Ok(src.to_string()));
+        let id = calc.registry_id();
+        assert_ne!(Calculator::new().registry_id(), id);
+        assert!(LISTENERS.with(|map| map.borrow().contains_key(&id)));
+        drop(calc);
+        assert!(!LISTENERS.with(|map| map.borrow().contains_key(&id)));
+    }
+}
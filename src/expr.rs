@@ -0,0 +1,1404 @@
+//! Expression evaluator: tokenizer, recursive-descent parser and tree walker.
+//!
+//! Two dialects share one grammar. [`Dialect::Standard`] is the usual
+//! calculator syntax (`2 * sqrt(x) + pi`). [`Dialect::Formula`] accepts
+//! spreadsheet-style input such as `=SUM(A1:A3)*B`: an optional leading `=`,
+//! case-insensitive function names with spreadsheet aliases (`AVERAGE`,
+//! `POWER`, ...), cell-like variable names (`A1`, `$B$2`) and `A1:B3` ranges
+//! inside function arguments.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{math, CalcError, Calculator};
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Standard,
+    Formula,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Ident(String),
+    /// One of `+ - * / % ^ !`.
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+}
+
+/// A token with its character span in the source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned {
+    pub token: Token,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    /// Postfix `%` in the formula dialect: `50%` is `0.5`.
+    Percent,
+    /// Postfix `!`.
+    Factorial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// Infix `%` in the standard dialect.
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    /// A rectangular cell range such as `A1:B3`; only valid as an argument.
+    Range(String, String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Function call; the name is already canonicalized for the dialect.
+    Call(String, Vec<Expr>),
+}
+
+/// The unit `sin`, `cos` and `tan` read their argument in and `asin`,
+/// `acos` and `atan` return their result in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+    /// 400 to the full turn.
+    Gradians,
+}
+
+impl AngleUnit {
+    fn full_turn(self) -> f64 {
+        match self {
+            AngleUnit::Radians => std::f64::consts::TAU,
+            AngleUnit::Degrees => 360.0,
+            AngleUnit::Gradians => 400.0,
+        }
+    }
+
+    fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Radians => angle,
+            unit => angle / unit.full_turn() * std::f64::consts::TAU,
+        }
+    }
+
+    fn radians_to_unit(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Radians => angle,
+            unit => angle / std::f64::consts::TAU * unit.full_turn(),
+        }
+    }
+
+    // `sin`, `cos` or `tan` of `angle`. In degrees and gradians, multiples
+    // of a quarter turn are exact, so `sin(180)` is 0 rather than 1.2e-16,
+    // and `tan` of an odd quarter turn is an error rather than 1.6e16.
+    pub(crate) fn trig(self, name: &str, angle: f64) -> Result<f64, CalcError> {
+        let radians = match self {
+            AngleUnit::Radians => angle,
+            unit => {
+                let reduced = angle.rem_euclid(unit.full_turn());
+                let quarter = reduced / (unit.full_turn() / 4.0);
+                if quarter.fract() == 0.0 {
+                    let q = quarter as usize % 4;
+                    return match name {
+                        "sin" => Ok([0.0, 1.0, 0.0, -1.0][q]),
+                        "cos" => Ok([1.0, 0.0, -1.0, 0.0][q]),
+                        _ if q % 2 == 1 => Err(CalcError::InvalidArgument(format!(
+                            "tan is undefined at {}",
+                            angle
+                        ))),
+                        _ => Ok(0.0),
+                    };
+                }
+                unit.to_radians(reduced)
+            }
+        };
+        Ok(match name {
+            "sin" => math::sin(radians),
+            "cos" => math::cos(radians),
+            _ => math::tan(radians),
+        })
+    }
+}
+
+/// Source of variable values during evaluation.
+pub trait Scope {
+    fn variable(&self, name: &str) -> Option<f64>;
+
+    /// The bound variable names, offered as did-you-mean suggestions.
+    fn names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The unit of trigonometric arguments and results.
+    fn angle_unit(&self) -> AngleUnit {
+        AngleUnit::Radians
+    }
+
+    /// The values of the named dataset read by `dataset(name)`.
+    fn dataset(&self, _name: &str) -> Option<&[f64]> {
+        None
+    }
+
+    /// The dataset names, offered as did-you-mean suggestions.
+    fn dataset_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `scope` with trigonometry in `unit`.
+pub struct AngleScope<'a, S: ?Sized> {
+    pub scope: &'a S,
+    pub unit: AngleUnit,
+}
+
+impl<S: Scope + ?Sized> Scope for AngleScope<'_, S> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.scope.variable(name)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.scope.names()
+    }
+
+    fn angle_unit(&self) -> AngleUnit {
+        self.unit
+    }
+
+    fn dataset(&self, name: &str) -> Option<&[f64]> {
+        self.scope.dataset(name)
+    }
+
+    fn dataset_names(&self) -> Vec<String> {
+        self.scope.dataset_names()
+    }
+}
+
+impl Scope for () {
+    fn variable(&self, _name: &str) -> Option<f64> {
+        None
+    }
+}
+
+impl Scope for BTreeMap<String, f64> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.get(name).copied()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+impl Scope for HashMap<String, f64> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.get(name).copied()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.keys().cloned().collect()
+    }
+}
+
+/// Built-in functions as `(canonical name, min args, max args)`; `None`
+/// means variadic.
+pub const FUNCTIONS: &[(&str, usize, Option<usize>)] = &[
+    ("abs", 1, Some(1)),
+    ("acos", 1, Some(1)),
+    ("asin", 1, Some(1)),
+    ("atan", 1, Some(1)),
+    ("avg", 1, None),
+    ("ceil", 1, Some(1)),
+    ("cos", 1, Some(1)),
+    ("count", 0, None),
+    ("exp", 1, Some(1)),
+    ("floor", 1, Some(1)),
+    ("ln", 1, Some(1)),
+    ("log", 1, Some(1)),
+    ("max", 1, None),
+    ("min", 1, None),
+    ("mod", 2, Some(2)),
+    ("pi", 0, Some(0)),
+    ("pow", 2, Some(2)),
+    ("product", 1, None),
+    ("round", 1, Some(2)),
+    ("sin", 1, Some(1)),
+    ("sqrt", 1, Some(1)),
+    ("sum", 1, None),
+    ("tan", 1, Some(1)),
+];
+
+/// The pseudo-function that spreads a named dataset into the arguments of
+/// the function it is passed to, like a cell range: `avg(dataset(sales))`.
+pub const DATASET: &str = "dataset";
+
+/// How deeply parentheses, unary minus, `^` and function arguments may
+/// nest. Deeper input is a syntax error rather than a stack overflow.
+pub const MAX_NESTING: usize = 100;
+
+/// How deep an expression tree may be, counting each operator in a chain
+/// as a level too, since evaluating `1 + 1 + ...` recurses once per `+`.
+pub const MAX_DEPTH: usize = 512;
+
+/// The most cells one range may cover.
+pub const MAX_RANGE_CELLS: usize = 100_000;
+
+/// Named constants available in the standard dialect.
+pub const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("e", std::f64::consts::E),
+    ("tau", std::f64::consts::TAU),
+];
+
+// Spreadsheet spellings that differ from the canonical builtin names.
+const FORMULA_ALIASES: &[(&str, &str)] = &[
+    ("average", "avg"),
+    ("power", "pow"),
+    ("int", "floor"),
+    ("log10", "log"),
+];
+
+fn syntax(message: impl Into<String>, position: usize) -> CalcError {
+    CalcError::Syntax {
+        message: message.into(),
+        position,
+    }
+}
+
+/// Splits `src` into tokens. In the formula dialect a single leading `=` is
+/// skipped and `$` markers in absolute references are dropped.
+pub fn tokenize(src: &str, dialect: Dialect) -> Result<Vec<Spanned>, CalcError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    if dialect == Dialect::Formula {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '=' {
+            i += 1;
+        }
+    }
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || c == '.' {
+            let (value, end) = scan_number(&chars, i)?;
+            tokens.push(Spanned {
+                token: Token::Number(value),
+                start,
+                end,
+            });
+            i = end;
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' || (c == '$' && dialect == Dialect::Formula) {
+            let mut name = String::new();
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || chars[i] == '_'
+                    || (chars[i] == '$' && dialect == Dialect::Formula))
+            {
+                if chars[i] != '$' {
+                    name.push(chars[i]);
+                }
+                i += 1;
+            }
+            if name.is_empty() {
+                return Err(syntax("Expected a cell reference after '$'", start));
+            }
+            tokens.push(Spanned {
+                token: Token::Ident(name),
+                start,
+                end: i,
+            });
+            continue;
+        }
+        let token = match c {
+            '+' | '-' | '*' | '/' | '%' | '^' | '!' => Token::Op(c),
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            ':' if dialect == Dialect::Formula => Token::Colon,
+            _ => return Err(syntax(format!("Unexpected character '{}'", c), start)),
+        };
+        i += 1;
+        tokens.push(Spanned {
+            token,
+            start,
+            end: i,
+        });
+    }
+    Ok(tokens)
+}
+
+// Scans `digits [. digits] [e [+-] digits]` starting at `start`.
+fn scan_number(chars: &[char], start: usize) -> Result<(f64, usize), CalcError> {
+    let mut i = start;
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < chars.len() && chars[i] == '.' {
+        i += 1;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i == start + 1 && chars[start] == '.' {
+        return Err(syntax("Expected digits around '.'", start));
+    }
+    if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+        let mut j = i + 1;
+        if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+            j += 1;
+        }
+        if j >= chars.len() || !chars[j].is_ascii_digit() {
+            return Err(syntax("Expected digits in exponent", j));
+        }
+        while j < chars.len() && chars[j].is_ascii_digit() {
+            j += 1;
+        }
+        i = j;
+    }
+    let text: String = chars[start..i].iter().collect();
+    text.parse::<f64>()
+        .map(|v| (v, i))
+        .map_err(|_| syntax(format!("Invalid number '{}'", text), start))
+}
+
+/// Parses a single numeric literal with an optional sign: `"1e-7"`, `".5"`,
+/// `"+3"`. Unlike `str::parse::<f64>` the error says what is wrong and
+/// where, and there is no `inf`/`NaN` spelling.
+pub fn parse_number(text: &str) -> Result<f64, CalcError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    let negative = match chars.get(i) {
+        Some('-') => {
+            i += 1;
+            true
+        }
+        Some('+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    match chars.get(i) {
+        Some(c) if c.is_ascii_digit() || *c == '.' => {}
+        Some(c) => return Err(syntax(format!("Unexpected character '{}'", c), i)),
+        None => return Err(syntax("Expected a number", i)),
+    }
+    let (value, mut end) = scan_number(&chars, i)?;
+    while end < chars.len() && chars[end].is_whitespace() {
+        end += 1;
+    }
+    if let Some(c) = chars.get(end) {
+        return Err(syntax(format!("Unexpected character '{}'", c), end));
+    }
+    Ok(if negative { -value } else { value })
+}
+
+/// Parses `src` into an expression tree.
+pub fn parse(src: &str, dialect: Dialect) -> Result<Expr, CalcError> {
+    parse_tokens(tokenize(src, dialect)?, src.chars().count(), dialect)
+}
+
+/// Parses tokens from [`tokenize`]. `src_len` is the length of the source
+/// in characters, where errors at the end of input are reported.
+pub fn parse_tokens(
+    tokens: Vec<Spanned>,
+    src_len: usize,
+    dialect: Dialect,
+) -> Result<Expr, CalcError> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        dialect,
+        src_len,
+        depth: 0,
+        nesting: 0,
+    };
+    let expr = parser.expression()?;
+    if let Some(t) = parser.tokens.get(parser.pos) {
+        return Err(syntax("Unexpected token", t.start));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    dialect: Dialect,
+    src_len: usize,
+    // The depth of the tree being built and of nested `unary` calls,
+    // bounded by `MAX_DEPTH` and `MAX_NESTING`.
+    depth: usize,
+    nesting: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.start)
+            .unwrap_or(self.src_len)
+    }
+
+    fn eat_op(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), CalcError> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(syntax(format!("Expected {}", what), self.position()))
+        }
+    }
+
+    // One level deeper. An error ends the parse, so callers only restore
+    // the depth on success.
+    fn descend(&mut self) -> Result<(), CalcError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(syntax(
+                format!("Expression is more than {} levels deep", MAX_DEPTH),
+                self.position(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat_op('+') {
+                BinaryOp::Add
+            } else if self.eat_op('-') {
+                BinaryOp::Sub
+            } else {
+                self.depth = depth;
+                return Ok(lhs);
+            };
+            self.descend()?;
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat_op('*') {
+                BinaryOp::Mul
+            } else if self.eat_op('/') {
+                BinaryOp::Div
+            } else if self.dialect == Dialect::Standard && self.eat_op('%') {
+                BinaryOp::Rem
+            } else {
+                self.depth = depth;
+                return Ok(lhs);
+            };
+            self.descend()?;
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    // Unary minus binds looser than `^`, so `-2^2` is -4. Every nested
+    // expression is parsed through here, so this is where nesting grows.
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        self.nesting += 1;
+        if self.nesting > MAX_NESTING {
+            return Err(syntax(
+                format!("Expression nests more than {} levels deep", MAX_NESTING),
+                self.position(),
+            ));
+        }
+        self.descend()?;
+        let expr = if self.eat_op('-') {
+            Expr::Unary(UnaryOp::Neg, Box::new(self.unary()?))
+        } else if self.eat_op('+') {
+            self.unary()?
+        } else {
+            self.power()?
+        };
+        self.depth -= 1;
+        self.nesting -= 1;
+        Ok(expr)
+    }
+
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.postfix()?;
+        if self.eat_op('^') {
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat_op('!') {
+                self.descend()?;
+                expr = Expr::Unary(UnaryOp::Factorial, Box::new(expr));
+            } else if self.dialect == Dialect::Formula && self.eat_op('%') {
+                self.descend()?;
+                expr = Expr::Unary(UnaryOp::Percent, Box::new(expr));
+            } else {
+                self.depth = depth;
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        let position = self.position();
+        let Some(token) = self.tokens.get(self.pos).map(|t| t.token.clone()) else {
+            return Err(syntax("Unexpected end of expression", position));
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(v) => Ok(Expr::Number(v)),
+            Token::LParen => {
+                let inner = self.expression()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(inner)
+            }
+            Token::Ident(name) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    let args = self.arguments()?;
+                    return Ok(Expr::Call(self.function_name(&name), args));
+                }
+                let name = self.variable_name(&name);
+                if self.peek() == Some(&Token::Colon) {
+                    self.pos += 1;
+                    let end_position = self.position();
+                    match self.tokens.get(self.pos).map(|t| t.token.clone()) {
+                        Some(Token::Ident(end)) => {
+                            self.pos += 1;
+                            return Ok(Expr::Range(name, self.variable_name(&end)));
+                        }
+                        _ => {
+                            return Err(syntax("Expected a cell reference after ':'", end_position))
+                        }
+                    }
+                }
+                Ok(Expr::Variable(name))
+            }
+            _ => Err(syntax("Unexpected token", position)),
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expr>, CalcError> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expression()?);
+            if self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                continue;
+            }
+            self.expect(Token::RParen, "',' or ')'")?;
+            return Ok(args);
+        }
+    }
+
+    fn function_name(&self, name: &str) -> String {
+        match self.dialect {
+            Dialect::Standard => name.to_string(),
+            Dialect::Formula => {
+                let lower = name.to_lowercase();
+                FORMULA_ALIASES
+                    .iter()
+                    .find(|(alias, _)| *alias == lower)
+                    .map(|(_, canonical)| canonical.to_string())
+                    .unwrap_or(lower)
+            }
+        }
+    }
+
+    fn variable_name(&self, name: &str) -> String {
+        match self.dialect {
+            Dialect::Standard => name.to_string(),
+            Dialect::Formula => name.to_uppercase(),
+        }
+    }
+}
+
+// Splits a cell reference like `AB12` into a zero-based column and row.
+fn parse_cell(name: &str) -> Option<(usize, usize)> {
+    let split = name.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = name.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    let row: usize = digits.parse().ok()?;
+    let col = letters.chars().try_fold(0usize, |acc, c| {
+        acc.checked_mul(26)?
+            .checked_add(c as usize - 'A' as usize + 1)
+    })?;
+    Some((col - 1, row))
+}
+
+fn cell_name(col: usize, row: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        letters.push((b'A' + ((n - 1) % 26) as u8) as char);
+        n = (n - 1) / 26;
+    }
+    letters.iter().rev().collect::<String>() + &row.to_string()
+}
+
+impl Expr {
+    /// Evaluates the tree, resolving variables through `scope` first and
+    /// then through the built-in [`CONSTANTS`].
+    pub fn eval<S: Scope + ?Sized>(&self, scope: &S) -> Result<f64, CalcError> {
+        match self {
+            Expr::Number(v) => Ok(*v),
+            Expr::Variable(name) => eval_variable(name, scope),
+            Expr::Range(start, end) => Err(CalcError::InvalidArgument(format!(
+                "Range {}:{} can only be used as a function argument",
+                start, end
+            ))),
+            Expr::Unary(op, inner) => {
+                let v = inner.eval(scope)?;
+                match op {
+                    UnaryOp::Neg => Ok(-v),
+                    UnaryOp::Percent => Ok(v / 100.0),
+                    UnaryOp::Factorial => {
+                        if !v.is_finite() || v < 0.0 || v.fract() != 0.0 {
+                            return Err(CalcError::InvalidInput);
+                        }
+                        crate::factorial(v as u32).map(|f| f as f64)
+                    }
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let a = lhs.eval(scope)?;
+                let b = rhs.eval(scope)?;
+                match op {
+                    BinaryOp::Add => Ok(a + b),
+                    BinaryOp::Sub => Ok(a - b),
+                    BinaryOp::Mul => Ok(a * b),
+                    BinaryOp::Div | BinaryOp::Rem if b == 0.0 => Err(CalcError::DivisionByZero),
+                    BinaryOp::Div => Ok(a / b),
+                    BinaryOp::Rem => Ok(a % b),
+                    BinaryOp::Pow => Ok(math::pow(a, b)),
+                }
+            }
+            Expr::Call(name, _) if name == DATASET => Err(CalcError::InvalidArgument(
+                "dataset(...) can only be used as a function argument".to_string(),
+            )),
+            Expr::Call(name, args) => eval_call(name, args, scope),
+        }
+    }
+}
+
+// The arms of `eval` that need more than a few locals live out of line,
+// keeping the frame it recurses with small.
+#[inline(never)]
+fn eval_variable<S: Scope + ?Sized>(name: &str, scope: &S) -> Result<f64, CalcError> {
+    scope
+        .variable(name)
+        .or_else(|| CONSTANTS.iter().find(|(c, _)| *c == name).map(|(_, v)| *v))
+        .ok_or_else(|| {
+            let names = scope.names();
+            let known = names
+                .iter()
+                .map(String::as_str)
+                .chain(CONSTANTS.iter().map(|(c, _)| *c));
+            unknown_identifier(name, known)
+        })
+}
+
+#[inline(never)]
+fn eval_call<S: Scope + ?Sized>(name: &str, args: &[Expr], scope: &S) -> Result<f64, CalcError> {
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg {
+            Expr::Range(start, end) => expand_range(start, end, scope, &mut values)?,
+            Expr::Call(f, inner) if f == DATASET => {
+                values.extend_from_slice(dataset_values(inner, scope)?)
+            }
+            other => values.push(other.eval(scope)?),
+        }
+    }
+    call_builtin(name, &values, scope.angle_unit())
+}
+
+// Pushes every bound cell in the range; unbound cells are skipped like
+// empty cells in a spreadsheet.
+fn expand_range<S: Scope + ?Sized>(
+    start: &str,
+    end: &str,
+    scope: &S,
+    out: &mut Vec<f64>,
+) -> Result<(), CalcError> {
+    let invalid = || CalcError::InvalidArgument(format!("Invalid range {}:{}", start, end));
+    let (c1, r1) = parse_cell(start).ok_or_else(invalid)?;
+    let (c2, r2) = parse_cell(end).ok_or_else(invalid)?;
+    let cells = (c1.abs_diff(c2) + 1).checked_mul(r1.abs_diff(r2) + 1);
+    if cells.is_none_or(|cells| cells > MAX_RANGE_CELLS) {
+        return Err(CalcError::InvalidArgument(format!(
+            "Range {}:{} covers more than {} cells",
+            start, end, MAX_RANGE_CELLS
+        )));
+    }
+    for col in c1.min(c2)..=c1.max(c2) {
+        for row in r1.min(r2)..=r1.max(r2) {
+            if let Some(v) = scope.variable(&cell_name(col, row)) {
+                out.push(v);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The values of `dataset(name)`, given its arguments.
+fn dataset_values<'a, S: Scope + ?Sized>(
+    args: &[Expr],
+    scope: &'a S,
+) -> Result<&'a [f64], CalcError> {
+    let [Expr::Variable(name)] = args else {
+        return Err(CalcError::InvalidArgument(
+            "dataset expects the name of one dataset".to_string(),
+        ));
+    };
+    scope.dataset(name).ok_or_else(|| {
+        let names = scope.dataset_names();
+        unknown_identifier(name, names.iter().map(String::as_str))
+    })
+}
+
+fn call_builtin(name: &str, args: &[f64], unit: AngleUnit) -> Result<f64, CalcError> {
+    let Some(&(_, min, max)) = FUNCTIONS.iter().find(|(f, _, _)| *f == name) else {
+        return Err(unknown_identifier(
+            name,
+            FUNCTIONS.iter().map(|(f, _, _)| *f),
+        ));
+    };
+    if args.len() < min || max.is_some_and(|max| args.len() > max) {
+        let expected = match max {
+            Some(max) if max == min => format!("{}", min),
+            Some(max) => format!("{} to {}", min, max),
+            None => format!("at least {}", min),
+        };
+        return Err(CalcError::InvalidArgument(format!(
+            "{} expects {} argument(s), got {}",
+            name,
+            expected,
+            args.len()
+        )));
+    }
+    let x = args.first().copied().unwrap_or(0.0);
+    let result = match name {
+        "abs" => x.abs(),
+        "acos" => unit.radians_to_unit(math::acos(x)),
+        "asin" => unit.radians_to_unit(math::asin(x)),
+        "atan" => unit.radians_to_unit(math::atan(x)),
+        "avg" => args.iter().sum::<f64>() / args.len() as f64,
+        "ceil" => x.ceil(),
+        "cos" | "sin" | "tan" => unit.trig(name, x)?,
+        "count" => args.len() as f64,
+        "exp" => math::exp(x),
+        "floor" => x.floor(),
+        "ln" => math::log(x),
+        "log" => math::log10(x),
+        "max" => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        "min" => args.iter().copied().fold(f64::INFINITY, f64::min),
+        "mod" => {
+            if args[1] == 0.0 {
+                return Err(CalcError::DivisionByZero);
+            }
+            x % args[1]
+        }
+        "pi" => std::f64::consts::PI,
+        "pow" => math::pow(x, args[1]),
+        "product" => args.iter().product(),
+        "round" => {
+            let factor = math::powi(10.0, args.get(1).copied().unwrap_or(0.0) as i32);
+            (x * factor).round() / factor
+        }
+        "sqrt" => {
+            if x < 0.0 {
+                return Err(CalcError::NegativeSqrt);
+            }
+            x.sqrt()
+        }
+        "sum" => args.iter().sum(),
+        _ => {
+            return Err(unknown_identifier(
+                name,
+                FUNCTIONS.iter().map(|(f, _, _)| *f),
+            ))
+        }
+    };
+    Ok(result)
+}
+
+// Levenshtein distance over characters, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// A [`CalcError::UnknownIdentifier`] for `name` suggesting up to three
+/// of the `known` names within two edits of it, closest first. A name is
+/// never suggested if reaching it takes as many edits as `name` has
+/// characters, so `x` does not suggest `e`.
+pub(crate) fn unknown_identifier<'a>(
+    name: &str,
+    known: impl IntoIterator<Item = &'a str>,
+) -> CalcError {
+    let limit = name.chars().count().min(3);
+    let mut close: Vec<(usize, &str)> = known
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, candidate)| distance < limit && candidate != name)
+        .collect();
+    close.sort();
+    close.dedup();
+    CalcError::UnknownIdentifier {
+        name: name.to_string(),
+        suggestions: close
+            .into_iter()
+            .take(3)
+            .map(|(_, candidate)| candidate.to_string())
+            .collect(),
+    }
+}
+
+/// Evaluates a standard-dialect expression with no variables bound.
+pub fn evaluate(src: &str) -> Result<f64, CalcError> {
+    parse(src, Dialect::Standard)?.eval(&())
+}
+
+/// A repair [`evaluate_lenient`] made to its input, in the order applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LenientFix {
+    /// A dangling operator, `,` or `(` was dropped from the end.
+    DroppedTrailing { text: String },
+    /// `count` closing parentheses were appended.
+    ClosedParentheses { count: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LenientResult {
+    pub value: f64,
+    pub fixes: Vec<LenientFix>,
+}
+
+/// Evaluates a half-typed standard-dialect expression the way a phone
+/// calculator previews it: a trailing operator is ignored and open
+/// parentheses are closed, so `2 * (3 + 4` gives 14 and `5 +` gives 5.
+/// Anything else wrong with the input is still an error.
+pub fn evaluate_lenient(src: &str) -> Result<LenientResult, CalcError> {
+    let chars: Vec<char> = src.chars().collect();
+    evaluate_lenient_tokens(tokenize(src, Dialect::Standard)?, &chars)
+}
+
+// `evaluate_lenient` on the tokens of `chars`, for callers that keep them.
+pub(crate) fn evaluate_lenient_tokens(
+    mut tokens: Vec<Spanned>,
+    chars: &[char],
+) -> Result<LenientResult, CalcError> {
+    let mut fixes = Vec::new();
+    let mut keep = tokens.len();
+    while keep > 0
+        && matches!(
+            tokens[keep - 1].token,
+            Token::Op('+' | '-' | '*' | '/' | '%' | '^') | Token::Comma | Token::LParen
+        )
+    {
+        let dropped = &tokens[keep - 1];
+        fixes.push(LenientFix::DroppedTrailing {
+            text: chars[dropped.start..dropped.end].iter().collect(),
+        });
+        keep -= 1;
+    }
+    tokens.truncate(keep);
+    let mut end = tokens.last().map_or(0, |t| t.end);
+    // Unmatched `)` are left for the parser to report.
+    let open = tokens.iter().fold(0i64, |depth, t| match t.token {
+        Token::LParen => depth + 1,
+        Token::RParen => depth - 1,
+        _ => depth,
+    });
+    if open > 0 {
+        for _ in 0..open {
+            tokens.push(Spanned {
+                token: Token::RParen,
+                start: end,
+                end: end + 1,
+            });
+            end += 1;
+        }
+        fixes.push(LenientFix::ClosedParentheses {
+            count: open as usize,
+        });
+    }
+    let value = parse_tokens(tokens, end, Dialect::Standard)?.eval(&())?;
+    Ok(LenientResult { value, fixes })
+}
+
+/// Evaluates a spreadsheet-style formula against a map of cell values.
+/// Cell names are matched case-insensitively (`b2` and `B2` are the same).
+pub fn evaluate_formula(formula: &str, cells: &BTreeMap<String, f64>) -> Result<f64, CalcError> {
+    let cells: BTreeMap<String, f64> = cells.iter().map(|(k, v)| (k.to_uppercase(), *v)).collect();
+    parse(formula, Dialect::Formula)?.eval(&cells)
+}
+
+/// Completions for the identifier being typed at the end of `input`:
+/// builtin functions, constants and `variables` starting with it (ignoring
+/// case), sorted and without duplicates. Returns nothing when `input` does
+/// not end in an identifier, e.g. after an operator or a number.
+pub fn suggest_with(input: &str, variables: &[&str]) -> Vec<String> {
+    let start = input
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(input.len(), |(i, _)| i);
+    let prefix = input[start..].to_lowercase();
+    if !prefix.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        return Vec::new();
+    }
+    let names = FUNCTIONS
+        .iter()
+        .map(|(name, _, _)| *name)
+        .chain(CONSTANTS.iter().map(|(name, _)| *name))
+        .chain(variables.iter().copied());
+    let mut matches: Vec<String> = names
+        .filter(|name| name.to_lowercase().starts_with(&prefix))
+        .map(str::to_string)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// [`suggest_with`] for expressions with no variables bound.
+pub fn suggest(input: &str) -> Vec<String> {
+    suggest_with(input, &[])
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = suggest)]
+pub fn suggest_js(input: &str) -> Vec<String> {
+    suggest(input)
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Completions as [`suggest`], plus `ans`, `mem`, the variables that
+    /// `Calculator::evaluate` binds, user functions and macros, dataset
+    /// names, word aliases and unit symbols.
+    pub fn suggest(&self, input: &str) -> Vec<String> {
+        let variables = self.variable_names();
+        let datasets = self.dataset_names();
+        let aliases = self.aliases.entries();
+        let names: Vec<&str> = ["ans", "mem"]
+            .into_iter()
+            .chain(variables.iter().map(String::as_str))
+            .chain(self.functions.keys().map(String::as_str))
+            .chain(datasets.iter().map(String::as_str))
+            .chain(aliases.iter().map(|(alias, _)| alias.as_str()))
+            .chain(crate::units::unit_symbols())
+            .collect();
+        suggest_with(input, &names)
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = evaluate)]
+pub fn evaluate_js(expr: &str) -> Result<f64, CalcErrorJs> {
+    evaluate(expr).map_err(CalcErrorJs::from)
+}
+
+/// Returns `{ value, fixes }`, where each fix is `{ kind: "dropped_trailing",
+/// text }` or `{ kind: "closed_parentheses", count }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = evaluate_lenient)]
+pub fn evaluate_lenient_js(expr: &str) -> Result<JsValue, CalcErrorJs> {
+    let result = evaluate_lenient(expr).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+}
+
+/// `cells` is a plain object mapping cell names to numbers, e.g. `{ A1: 2 }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = evaluate_formula)]
+pub fn evaluate_formula_js(formula: &str, cells: JsValue) -> Result<f64, CalcErrorJs> {
+    let cells: BTreeMap<String, f64> = if cells.is_undefined() || cells.is_null() {
+        BTreeMap::new()
+    } else {
+        serde_wasm_bindgen::from_value(cells).map_err(|e| {
+            CalcErrorJs::from(CalcError::InvalidArgument(format!(
+                "Invalid cells object: {}",
+                e
+            )))
+        })?
+    };
+    evaluate_formula(formula, &cells).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(pairs: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn test_evaluate_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), 9.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(evaluate("-2 ^ 2").unwrap(), -4.0);
+        assert_eq!(evaluate("10 - 4 - 3").unwrap(), 3.0);
+        assert_eq!(evaluate("7 % 4").unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_evaluate_functions_and_constants() {
+        assert_eq!(evaluate("sqrt(16) + abs(-2)").unwrap(), 6.0);
+        assert_eq!(evaluate("max(1, 5, 3)").unwrap(), 5.0);
+        assert_eq!(evaluate("round(2.345, 2)").unwrap(), 2.35);
+        assert_eq!(evaluate("5!").unwrap(), 120.0);
+        assert!((evaluate("2 * pi").unwrap() - std::f64::consts::TAU).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_evaluate_scientific_notation() {
+        assert_eq!(evaluate("1.5e3").unwrap(), 1500.0);
+        assert_eq!(evaluate("2E-2").unwrap(), 0.02);
+        assert_eq!(evaluate(".5").unwrap(), 0.5);
+        assert!(matches!(
+            evaluate("2 * 1e + 1"),
+            Err(CalcError::Syntax { position: 6, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("1e-7"), Ok(1e-7));
+        assert_eq!(parse_number(" .5 "), Ok(0.5));
+        assert_eq!(parse_number("+3"), Ok(3.0));
+        assert_eq!(parse_number("-2.5E+3"), Ok(-2500.0));
+        assert_eq!(parse_number("7."), Ok(7.0));
+        assert_eq!(
+            parse_number("1e"),
+            Err(CalcError::Syntax {
+                message: "Expected digits in exponent".to_string(),
+                position: 2
+            })
+        );
+        assert_eq!(
+            parse_number("--3"),
+            Err(CalcError::Syntax {
+                message: "Unexpected character '-'".to_string(),
+                position: 1
+            })
+        );
+        assert!(matches!(
+            parse_number("1.5x"),
+            Err(CalcError::Syntax { position: 3, .. })
+        ));
+        assert!(matches!(
+            parse_number("1e+"),
+            Err(CalcError::Syntax { position: 3, .. })
+        ));
+        assert!(matches!(
+            parse_number("."),
+            Err(CalcError::Syntax { position: 0, .. })
+        ));
+        assert!(matches!(
+            parse_number("  "),
+            Err(CalcError::Syntax { position: 2, .. })
+        ));
+        assert!(parse_number("inf").is_err());
+        assert!(parse_number("1 2").is_err());
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(suggest("2 * s"), vec!["sin", "sqrt", "sum"]);
+        assert_eq!(suggest("max(1, P"), vec!["pi", "pow", "product"]);
+        assert_eq!(suggest("t"), vec!["tan", "tau"]);
+        assert!(suggest("2 + ").is_empty());
+        assert!(suggest("12").is_empty());
+        assert!(suggest("zz").is_empty());
+        assert_eq!(suggest_with("x + ra", &["rate", "x"]), vec!["rate"]);
+        assert_eq!(
+            Calculator::new().suggest("a"),
+            vec!["A", "abs", "acos", "acre", "ans", "asin", "atan", "atm", "avg"]
+        );
+        assert_eq!(suggest("2 * SQ"), vec!["sqrt"]);
+    }
+
+    #[test]
+    fn test_calculator_suggest_covers_every_name_source() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.suggest("3 kW"), vec!["kW", "kWh"]);
+        assert_eq!(calc.suggest("60 mp"), vec!["MPa", "mph"]);
+        calc.define_function("tip", "x * 0.18").unwrap();
+        calc.load_dataset(&[1.0, 2.0], "temps").unwrap();
+        calc.add_function_alias("tally", "sum").unwrap();
+        assert_eq!(calc.suggest("2 * ta"), vec!["tally", "tan", "tau"]);
+        assert_eq!(
+            calc.suggest("t"),
+            vec!["t", "tally", "tan", "tau", "temps", "tip"]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_lenient() {
+        let lenient = evaluate_lenient("2 * (3 + 4").unwrap();
+        assert_eq!(lenient.value, 14.0);
+        assert_eq!(
+            lenient.fixes,
+            vec![LenientFix::ClosedParentheses { count: 1 }]
+        );
+        let lenient = evaluate_lenient("max(1, (5 *").unwrap();
+        assert_eq!(lenient.value, 5.0);
+        assert_eq!(
+            lenient.fixes,
+            vec![
+                LenientFix::DroppedTrailing {
+                    text: "*".to_string()
+                },
+                LenientFix::ClosedParentheses { count: 2 },
+            ]
+        );
+        assert_eq!(evaluate_lenient("7 % ").unwrap().value, 7.0);
+        assert_eq!(evaluate_lenient("3!").unwrap().value, 6.0);
+        assert_eq!(
+            evaluate_lenient("1 + 2").unwrap(),
+            LenientResult {
+                value: 3.0,
+                fixes: vec![]
+            }
+        );
+        assert!(evaluate_lenient("").is_err());
+        assert!(evaluate_lenient("2 + )").is_err());
+        assert!(evaluate_lenient("1 / (0").is_err());
+    }
+
+    #[test]
+    fn test_unknown_identifier_suggestions() {
+        let suggestions = |src: &str| match evaluate(src) {
+            Err(CalcError::UnknownIdentifier { suggestions, .. }) => suggestions,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(suggestions("sqr(4)"), vec!["sqrt", "sin", "sum"]);
+        assert_eq!(suggestions("SQRT(4)"), vec!["sqrt"]);
+        assert_eq!(suggestions("2 * pie"), vec!["pi", "e"]);
+        assert_eq!(suggestions("mn(1, 2)"), vec!["ln", "min"]);
+        assert!(suggestions("x + 1").is_empty());
+        assert!(suggestions("hypotenuse(3, 4)").is_empty());
+        let err = Calculator::new().evaluate("anss + 1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown identifier: anss (did you mean ans?)"
+        );
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        assert_eq!(evaluate("1 / 0"), Err(CalcError::DivisionByZero));
+        assert_eq!(evaluate("sqrt(-1)"), Err(CalcError::NegativeSqrt));
+        assert_eq!(evaluate("foo + 1"), Err(unknown_identifier("foo", [])));
+        assert_eq!(evaluate("nope(1)"), Err(unknown_identifier("nope", [])));
+        assert!(matches!(
+            evaluate("sqrt(1, 2)"),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert_eq!(evaluate("2.5!"), Err(CalcError::InvalidInput));
+    }
+
+    #[test]
+    fn test_syntax_error_positions() {
+        assert_eq!(
+            evaluate("1 + * 2"),
+            Err(CalcError::Syntax {
+                message: "Unexpected token".to_string(),
+                position: 4
+            })
+        );
+        assert_eq!(
+            evaluate("(1 + 2"),
+            Err(CalcError::Syntax {
+                message: "Expected ')'".to_string(),
+                position: 6
+            })
+        );
+        assert!(matches!(
+            evaluate("1 # 2"),
+            Err(CalcError::Syntax { position: 2, .. })
+        ));
+        assert!(matches!(
+            evaluate(""),
+            Err(CalcError::Syntax { position: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let nested = |n: usize| "(".repeat(n) + "1" + &")".repeat(n);
+        assert_eq!(evaluate(&nested(MAX_NESTING - 1)), Ok(1.0));
+        assert_eq!(
+            evaluate(&nested(100_000)),
+            Err(CalcError::Syntax {
+                message: "Expression nests more than 100 levels deep".to_string(),
+                position: MAX_NESTING
+            })
+        );
+        let too_deep = "Expression is more than 512 levels deep".to_string();
+        assert!(evaluate(&"-".repeat(100_000)).is_err());
+        assert!(evaluate(&"2^".repeat(100_000)).is_err());
+        let sum = |n: usize| "1+".repeat(n) + "1";
+        assert_eq!(evaluate(&sum(MAX_DEPTH - 1)), Ok(MAX_DEPTH as f64));
+        assert!(matches!(
+            evaluate(&sum(100_000)),
+            Err(CalcError::Syntax { message, .. }) if message == too_deep
+        ));
+        assert!(evaluate(&format!("1{}", "!".repeat(100_000))).is_err());
+        // Operands of a long chain don't add up.
+        let products = vec!["(2*3)"; 400].join("+");
+        assert_eq!(evaluate(&products), Ok(2400.0));
+    }
+
+    #[test]
+    fn test_formula_dialect_basic() {
+        let c = cells(&[("B", 2.0)]);
+        assert_eq!(evaluate_formula("=SUM(1,2,3)*B", &c).unwrap(), 12.0);
+        assert_eq!(evaluate_formula("=average(2, 4)", &c).unwrap(), 3.0);
+        assert_eq!(evaluate_formula("=POWER(B, 3)", &c).unwrap(), 8.0);
+        assert_eq!(evaluate_formula("  = 50% * 10", &c).unwrap(), 5.0);
+    }
+
+    #[test]
+    fn test_formula_dialect_cells_and_ranges() {
+        let c = cells(&[("A1", 1.0), ("A2", 2.0), ("a3", 3.0), ("B1", 10.0)]);
+        assert_eq!(evaluate_formula("=SUM(A1:A3)", &c).unwrap(), 6.0);
+        assert_eq!(evaluate_formula("=SUM(A1:B2)", &c).unwrap(), 13.0);
+        assert_eq!(evaluate_formula("=$B$1 + a1", &c).unwrap(), 11.0);
+        assert_eq!(evaluate_formula("=COUNT(A1:A9)", &c).unwrap(), 3.0);
+        for huge in ["=SUM(A1:ZZZZZZZZZZZZZZ1)", "=SUM(A1:A999999999)"] {
+            assert!(matches!(
+                evaluate_formula(huge, &c),
+                Err(CalcError::InvalidArgument(_))
+            ));
+        }
+        assert!(matches!(
+            evaluate_formula("=A1:A3", &c),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            evaluate_formula("=C1", &c),
+            Err(CalcError::UnknownIdentifier {
+                name: "C1".to_string(),
+                suggestions: vec!["A1".to_string(), "B1".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn test_standard_dialect_rejects_formula_syntax() {
+        assert!(matches!(evaluate("=1+2"), Err(CalcError::Syntax { .. })));
+        assert!(matches!(
+            evaluate("sum(A1:A2)"),
+            Err(CalcError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cell_name_round_trip() {
+        for (name, col, row) in [
+            ("A1", 0, 1),
+            ("Z9", 25, 9),
+            ("AA10", 26, 10),
+            ("AZ3", 51, 3),
+        ] {
+            assert_eq!(parse_cell(name), Some((col, row)));
+            assert_eq!(cell_name(col, row), name);
+        }
+        assert_eq!(parse_cell("A"), None);
+        assert_eq!(parse_cell("1A"), None);
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let tokens = tokenize("12 + ab", Dialect::Standard).unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!((tokens[2].start, tokens[2].end), (5, 7));
+        assert_eq!(tokens[2].token, Token::Ident("ab".to_string()));
+    }
+}
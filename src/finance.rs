@@ -0,0 +1,434 @@
+//! Time-value-of-money functions.
+//!
+//! Rates are percentages per period, as in [`crate::compound_interest`]:
+//! `5.0` means 5%. Cash-flow series start at period 0, which is not
+//! discounted (unlike spreadsheet `NPV`, which starts at period 1).
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// Amortization schedules longer than this many periods (100 years of
+/// daily payments) are refused.
+pub const MAX_SCHEDULE_PERIODS: u32 = 36_500;
+
+/// One period of a loan amortization schedule.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AmortizationRow {
+    pub period: u32,
+    pub payment: f64,
+    pub principal: f64,
+    pub interest: f64,
+    pub balance: f64,
+}
+
+/// Periods for a value to double at a fixed rate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DoublingTime {
+    /// `ln 2 / ln(1 + rate)`.
+    pub exact: f64,
+    /// The rule of 72 estimate, `72 / rate`.
+    pub rule_of_72: f64,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn check_rate(rate: f64) -> Result<f64, CalcError> {
+    if !rate.is_finite() || rate <= -100.0 {
+        return Err(invalid(format!(
+            "Rate must be greater than -100%, got {}",
+            rate
+        )));
+    }
+    Ok(rate / 100.0)
+}
+
+fn check_periods(periods: u32) -> Result<f64, CalcError> {
+    if periods == 0 {
+        return Err(invalid("Number of periods must be at least 1"));
+    }
+    Ok(periods as f64)
+}
+
+/// Level payment that repays `principal` over `periods` at `rate` per period.
+pub fn loan_payment(principal: f64, rate: f64, periods: u32) -> Result<f64, CalcError> {
+    let r = check_rate(rate)?;
+    let n = check_periods(periods)?;
+    if r == 0.0 {
+        return Ok(principal / n);
+    }
+    Ok(principal * r / (1.0 - (1.0 + r).powf(-n)))
+}
+
+/// Period-by-period split of each payment into interest and principal. The
+/// final payment absorbs rounding drift so the closing balance is exactly 0.
+/// At most [`MAX_SCHEDULE_PERIODS`] periods.
+pub fn amortization_schedule(
+    principal: f64,
+    rate: f64,
+    periods: u32,
+) -> Result<Vec<AmortizationRow>, CalcError> {
+    if periods > MAX_SCHEDULE_PERIODS {
+        return Err(invalid(format!(
+            "Schedules are limited to {} periods, got {}",
+            MAX_SCHEDULE_PERIODS, periods
+        )));
+    }
+    let payment = loan_payment(principal, rate, periods)?;
+    let r = rate / 100.0;
+    let mut balance = principal;
+    let mut rows = Vec::with_capacity(periods as usize);
+    for period in 1..=periods {
+        let interest = balance * r;
+        let (payment, principal_part) = if period == periods {
+            (balance + interest, balance)
+        } else {
+            (payment, payment - interest)
+        };
+        balance -= principal_part;
+        rows.push(AmortizationRow {
+            period,
+            payment,
+            principal: principal_part,
+            interest,
+            balance: if period == periods { 0.0 } else { balance },
+        });
+    }
+    Ok(rows)
+}
+
+/// Net present value of `cash_flows`, the first at period 0.
+pub fn npv(rate: f64, cash_flows: &[f64]) -> Result<f64, CalcError> {
+    let r = check_rate(rate)?;
+    Ok(cash_flows
+        .iter()
+        .enumerate()
+        .map(|(t, cf)| cf / (1.0 + r).powi(t as i32))
+        .sum())
+}
+
+/// Internal rate of return: the rate (in percent) at which `npv` is 0.
+/// Needs at least one sign change in `cash_flows`.
+pub fn irr(cash_flows: &[f64]) -> Result<f64, CalcError> {
+    let has_positive = cash_flows.iter().any(|&cf| cf > 0.0);
+    let has_negative = cash_flows.iter().any(|&cf| cf < 0.0);
+    if !(has_positive && has_negative) {
+        return Err(invalid("IRR needs both positive and negative cash flows"));
+    }
+    let f = |r: f64| -> f64 {
+        cash_flows
+            .iter()
+            .enumerate()
+            .map(|(t, cf)| cf / (1.0 + r).powi(t as i32))
+            .sum()
+    };
+    let df = |r: f64| -> f64 {
+        cash_flows
+            .iter()
+            .enumerate()
+            .map(|(t, cf)| -(t as f64) * cf / (1.0 + r).powi(t as i32 + 1))
+            .sum()
+    };
+
+    // Newton from 10%, falling back to bisection when it wanders off.
+    let mut r = 0.1;
+    for _ in 0..100 {
+        let (value, slope) = (f(r), df(r));
+        if slope == 0.0 || !slope.is_finite() {
+            break;
+        }
+        let next = r - value / slope;
+        if !next.is_finite() || next <= -1.0 {
+            break;
+        }
+        if (next - r).abs() < 1e-12 {
+            return Ok(next * 100.0);
+        }
+        r = next;
+    }
+
+    let mut low = -0.9999;
+    let mut high = 1.0;
+    while f(low).signum() == f(high).signum() {
+        high *= 2.0;
+        if high > 1e6 {
+            return Err(invalid("IRR did not converge"));
+        }
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if f(mid).signum() == f(low).signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok((low + high) / 2.0 * 100.0)
+}
+
+/// Value after `periods` of paying `payment` at the end of each period.
+pub fn annuity_future_value(payment: f64, rate: f64, periods: u32) -> Result<f64, CalcError> {
+    let r = check_rate(rate)?;
+    let n = check_periods(periods)?;
+    if r == 0.0 {
+        return Ok(payment * n);
+    }
+    Ok(payment * ((1.0 + r).powf(n) - 1.0) / r)
+}
+
+/// Value today of `periods` end-of-period payments of `payment`.
+pub fn annuity_present_value(payment: f64, rate: f64, periods: u32) -> Result<f64, CalcError> {
+    let r = check_rate(rate)?;
+    let n = check_periods(periods)?;
+    if r == 0.0 {
+        return Ok(payment * n);
+    }
+    Ok(payment * (1.0 - (1.0 + r).powf(-n)) / r)
+}
+
+/// Effective annual rate (percent) of a nominal annual rate compounded
+/// `compounds_per_year` times.
+pub fn effective_annual_rate(nominal_rate: f64, compounds_per_year: u32) -> Result<f64, CalcError> {
+    let n = check_periods(compounds_per_year)?;
+    let r = check_rate(nominal_rate / n)?;
+    Ok(((1.0 + r).powf(n) - 1.0) * 100.0)
+}
+
+/// How many periods a value takes to double at `rate` percent per period
+/// compounded, exactly and by the rule of 72.
+pub fn doubling_time(rate: f64) -> Result<DoublingTime, CalcError> {
+    if !(rate > 0.0 && rate.is_finite()) {
+        return Err(invalid(format!(
+            "Rate must be positive to double, got {}",
+            rate
+        )));
+    }
+    Ok(DoublingTime {
+        exact: std::f64::consts::LN_2 / (rate / 100.0).ln_1p(),
+        rule_of_72: 72.0 / rate,
+    })
+}
+
+/// The rate (percent per period) that doubles a value in `periods`
+/// periods, which need not be whole.
+pub fn rate_for_doubling(periods: f64) -> Result<f64, CalcError> {
+    if !(periods > 0.0 && periods.is_finite()) {
+        return Err(invalid(format!(
+            "Periods must be positive, got {}",
+            periods
+        )));
+    }
+    Ok((std::f64::consts::LN_2 / periods).exp_m1() * 100.0)
+}
+
+/// `{ exact, rule_of_72 }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = doubling_time)]
+pub fn doubling_time_js(rate: f64) -> Result<JsValue, CalcErrorJs> {
+    let time = doubling_time(rate).map_err(|e| CalcErrorJs::new(e, Some(rate)))?;
+    serde_wasm_bindgen::to_value(&time).map_err(|e| {
+        CalcErrorJs::from(invalid(format!("Could not serialize doubling time: {}", e)))
+    })
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = rate_for_doubling)]
+pub fn rate_for_doubling_js(periods: f64) -> Result<f64, CalcErrorJs> {
+    rate_for_doubling(periods).map_err(|e| CalcErrorJs::new(e, Some(periods)))
+}
+
+/// Basis points as a percentage: 25 bps is 0.25%.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn basis_points_to_percent(bps: f64) -> f64 {
+    bps / 100.0
+}
+
+/// A percentage in basis points: 0.25% is 25 bps.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn percent_to_basis_points(percent: f64) -> f64 {
+    percent * 100.0
+}
+
+/// `value` moved by `bps` basis points of itself: up for positive `bps`,
+/// down for negative. `apply_bps(200, 25)` is 200.5.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn apply_bps(value: f64, bps: f64) -> f64 {
+    value + value * bps / 10_000.0
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = loan_payment)]
+pub fn loan_payment_js(principal: f64, rate: f64, periods: u32) -> Result<f64, CalcErrorJs> {
+    loan_payment(principal, rate, periods).map_err(CalcErrorJs::from)
+}
+
+/// Array of `{ period, payment, principal, interest, balance }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = amortization_schedule)]
+pub fn amortization_schedule_js(
+    principal: f64,
+    rate: f64,
+    periods: u32,
+) -> Result<JsValue, CalcErrorJs> {
+    let rows = amortization_schedule(principal, rate, periods).map_err(CalcErrorJs::from)?;
+    serde_wasm_bindgen::to_value(&rows)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Could not serialize schedule: {}", e))))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = npv)]
+pub fn npv_js(rate: f64, cash_flows: &[f64]) -> Result<f64, CalcErrorJs> {
+    npv(rate, cash_flows).map_err(|e| CalcErrorJs::new(e, Some(rate)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = irr)]
+pub fn irr_js(cash_flows: &[f64]) -> Result<f64, CalcErrorJs> {
+    irr(cash_flows).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = annuity_future_value)]
+pub fn annuity_future_value_js(payment: f64, rate: f64, periods: u32) -> Result<f64, CalcErrorJs> {
+    annuity_future_value(payment, rate, periods).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = annuity_present_value)]
+pub fn annuity_present_value_js(payment: f64, rate: f64, periods: u32) -> Result<f64, CalcErrorJs> {
+    annuity_present_value(payment, rate, periods).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = effective_annual_rate)]
+pub fn effective_annual_rate_js(
+    nominal_rate: f64,
+    compounds_per_year: u32,
+) -> Result<f64, CalcErrorJs> {
+    effective_annual_rate(nominal_rate, compounds_per_year).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn test_loan_payment() {
+        // 200k over 30 years at 6%/12 monthly.
+        let payment = loan_payment(200_000.0, 0.5, 360).unwrap();
+        assert!(close(payment, 1199.10, 0.005), "{}", payment);
+        assert_eq!(loan_payment(1200.0, 0.0, 12), Ok(100.0));
+        assert!(loan_payment(1000.0, 5.0, 0).is_err());
+        assert!(loan_payment(1000.0, -100.0, 12).is_err());
+    }
+
+    #[test]
+    fn test_amortization_schedule_pays_off() {
+        let rows = amortization_schedule(1000.0, 1.0, 12).unwrap();
+        assert_eq!(rows.len(), 12);
+        assert_eq!(rows[0].period, 1);
+        assert!(close(rows[0].interest, 10.0, 1e-9));
+        assert!(close(
+            rows[0].principal + rows[0].interest,
+            rows[0].payment,
+            1e-9
+        ));
+        assert_eq!(rows[11].balance, 0.0);
+        let repaid: f64 = rows.iter().map(|r| r.principal).sum();
+        assert!(close(repaid, 1000.0, 1e-9));
+        let rows = amortization_schedule(1000.0, 0.01, MAX_SCHEDULE_PERIODS).unwrap();
+        assert_eq!(rows.len(), MAX_SCHEDULE_PERIODS as usize);
+        assert!(amortization_schedule(1000.0, 1.0, MAX_SCHEDULE_PERIODS + 1).is_err());
+        assert!(amortization_schedule(1000.0, 1.0, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_npv_and_irr() {
+        let flows = [-1000.0, 300.0, 400.0, 500.0];
+        assert!(close(npv(10.0, &flows).unwrap(), -21.036814, 1e-6));
+        assert_eq!(npv(0.0, &flows), Ok(200.0));
+        let rate = irr(&flows).unwrap();
+        assert!(close(rate, 8.896, 1e-3), "{}", rate);
+        assert!(close(npv(rate, &flows).unwrap(), 0.0, 1e-6));
+        assert!(irr(&[100.0, 200.0]).is_err());
+    }
+
+    #[test]
+    fn test_irr_bisection_fallback() {
+        // Very high return: Newton from 10% overshoots below -100%.
+        let flows = [-1.0, 0.0, 0.0, 0.0, 0.0, 1000.0];
+        let rate = irr(&flows).unwrap();
+        assert!(close(npv(rate, &flows).unwrap(), 0.0, 1e-6), "{}", rate);
+    }
+
+    #[test]
+    fn test_annuities() {
+        assert!(close(
+            annuity_future_value(100.0, 5.0, 10).unwrap(),
+            1257.789254,
+            1e-6
+        ));
+        assert!(close(
+            annuity_present_value(100.0, 5.0, 10).unwrap(),
+            772.173493,
+            1e-6
+        ));
+        assert_eq!(annuity_future_value(100.0, 0.0, 3), Ok(300.0));
+    }
+
+    #[test]
+    fn test_effective_annual_rate() {
+        assert!(close(
+            effective_annual_rate(12.0, 12).unwrap(),
+            12.682503,
+            1e-6
+        ));
+        assert!(close(effective_annual_rate(5.0, 1).unwrap(), 5.0, 1e-12));
+        assert!(effective_annual_rate(5.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_doubling_time() {
+        let time = doubling_time(8.0).unwrap();
+        assert!(close(time.exact, 9.006468, 1e-6), "{}", time.exact);
+        assert_eq!(time.rule_of_72, 9.0);
+        // For small rates the exact time approaches 69.3 / rate.
+        let tiny = doubling_time(1e-9).unwrap();
+        assert!(close(
+            tiny.exact / tiny.rule_of_72,
+            100.0 * std::f64::consts::LN_2 / 72.0,
+            1e-6
+        ));
+        assert!(doubling_time(0.0).is_err());
+        assert!(doubling_time(-5.0).is_err());
+        assert!(doubling_time(f64::NAN).is_err());
+
+        let rate = rate_for_doubling(9.0).unwrap();
+        assert!(close(rate, 8.005974, 1e-6), "{}", rate);
+        assert!(close(doubling_time(rate).unwrap().exact, 9.0, 1e-9));
+        assert_eq!(rate_for_doubling(1.0), Ok(100.0));
+        assert!(rate_for_doubling(0.0).is_err());
+    }
+
+    #[test]
+    fn test_basis_points() {
+        assert_eq!(basis_points_to_percent(25.0), 0.25);
+        assert_eq!(basis_points_to_percent(-150.0), -1.5);
+        assert_eq!(percent_to_basis_points(0.25), 25.0);
+        assert_eq!(percent_to_basis_points(-1.5), -150.0);
+        assert_eq!(apply_bps(200.0, 25.0), 200.5);
+        assert_eq!(apply_bps(200.0, -50.0), 199.0);
+        assert_eq!(apply_bps(-200.0, 25.0), -200.5);
+        assert_eq!(apply_bps(1000.0, 0.0), 1000.0);
+    }
+}
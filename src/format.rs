@@ -0,0 +1,576 @@
+//! Display formatting for calculator values.
+//!
+//! [`format_number`] covers what a display or export needs: fixed decimals
+//! or significant figures, digit grouping, scientific and engineering
+//! notation, and the decimal and group separators of a locale. Fixed
+//! decimals round half-up on the shortest decimal representation, like
+//! [`crate::rounding`], so `1.005` shows as `1.01`.
+//!
+//! SI notation is engineering notation with the exponent written as a
+//! prefix (`4.7 k`, `2.2 µ`), the way component values are labelled.
+//! [`parse_si`] reads such values back, including the electronics style
+//! that puts the prefix where the point goes (`4k7`).
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::parse_number;
+use crate::locale::locale_separators;
+use crate::rounding::{round_to, RoundingMode};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+pub(crate) const MAX_DECIMALS: usize = 20;
+const MAX_SIGNIFICANT: usize = 17;
+// `round_to` works on at most this many places.
+pub(crate) const MAX_ROUND_PLACES: u32 = 9;
+
+// Engineering prefixes by power of ten, quecto to quetta.
+const SI_PREFIXES: [(i32, char); 20] = [
+    (-30, 'q'),
+    (-27, 'r'),
+    (-24, 'y'),
+    (-21, 'z'),
+    (-18, 'a'),
+    (-15, 'f'),
+    (-12, 'p'),
+    (-9, 'n'),
+    (-6, 'µ'),
+    (-3, 'm'),
+    (3, 'k'),
+    (6, 'M'),
+    (9, 'G'),
+    (12, 'T'),
+    (15, 'P'),
+    (18, 'E'),
+    (21, 'Z'),
+    (24, 'Y'),
+    (27, 'R'),
+    (30, 'Q'),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Notation {
+    /// Positional digits: `12345.6`.
+    #[default]
+    Standard,
+    /// One digit before the point: `1.23456e+4`.
+    Scientific,
+    /// Exponent a multiple of three: `12.3456e+3`.
+    Engineering,
+    /// Engineering with an SI prefix for the exponent: `12.3456 k`. Values
+    /// beyond the prefixes (quecto to quetta) fall back to engineering.
+    Si,
+}
+
+/// Options for [`format_number`]. From JavaScript pass a plain object;
+/// missing fields take their defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Digits after the decimal point (of the mantissa, in scientific and
+    /// engineering notation). Default: as many as the value needs.
+    pub decimals: Option<usize>,
+    /// Significant figures, 1-17. Cannot be combined with `decimals`.
+    pub significant: Option<usize>,
+    pub notation: Notation,
+    /// Group integer digits in threes (default false).
+    pub thousands: bool,
+    /// BCP 47 tag such as `de-DE`, used for the separators (default `en`).
+    pub locale: Option<String>,
+    /// Overrides the locale's decimal separator.
+    pub decimal_separator: Option<String>,
+    /// Overrides the locale's group separator.
+    pub group_separator: Option<String>,
+}
+
+// Decimal digits and exponent of `magnitude` (finite, non-negative):
+// the value is 0.d1d2d3... x 10^(exp + 1), i.e. d1.d2d3... x 10^exp.
+fn scientific_digits(magnitude: f64, significant: Option<usize>) -> (String, i32) {
+    let text = match significant {
+        Some(n) => format!("{:.*e}", n - 1, magnitude),
+        None => format!("{:e}", magnitude),
+    };
+    let (mantissa, exp) = text.split_once('e').unwrap_or((&text, "0"));
+    let digits = mantissa.replace('.', "");
+    (digits, exp.parse().unwrap_or(0))
+}
+
+// Splits `digits` with the point after `point` digits, padding with zeros.
+fn place_point(digits: &str, point: i32) -> (String, String) {
+    if point <= 0 {
+        let zeros = "0".repeat(point.unsigned_abs() as usize);
+        return ("0".to_string(), format!("{}{}", zeros, digits));
+    }
+    let point = point as usize;
+    if point >= digits.len() {
+        let zeros = "0".repeat(point - digits.len());
+        return (format!("{}{}", digits, zeros), String::new());
+    }
+    (digits[..point].to_string(), digits[point..].to_string())
+}
+
+// Mantissa parts and exponent for scientific (`step` 1) or engineering
+// (`step` 3) notation.
+fn exponent_parts(magnitude: f64, options: &FormatOptions, step: i32) -> (String, String, i32) {
+    if magnitude == 0.0 {
+        let frac = "0".repeat(options.decimals.unwrap_or(0));
+        return ("0".to_string(), frac, 0);
+    }
+    let (mut digits, mut exp) = scientific_digits(magnitude, options.significant);
+    if let Some(decimals) = options.decimals {
+        // Rounding can carry into a new exponent (9.99 -> 10.0), so settle
+        // the exponent first and round once more if it moved.
+        for _ in 0..2 {
+            let lead = exp.rem_euclid(step) as usize + 1;
+            let (d, e) = scientific_digits(magnitude, Some(lead + decimals));
+            let moved = e != exp;
+            digits = d;
+            exp = e;
+            if !moved {
+                break;
+            }
+        }
+    }
+    let shown = exp - exp.rem_euclid(step);
+    let (int, frac) = place_point(&digits, exp - shown + 1);
+    (int, frac, shown)
+}
+
+fn group(int: &str, separator: &str) -> String {
+    let mut grouped = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Formats `value` for display. Non-finite values come out as JavaScript
+/// prints them (`NaN`, `Infinity`, `-Infinity`).
+pub fn format_number(value: f64, options: &FormatOptions) -> Result<String, CalcError> {
+    if options.decimals.is_some() && options.significant.is_some() {
+        return Err(CalcError::InvalidArgument(
+            "Use either decimals or significant, not both".to_string(),
+        ));
+    }
+    if options.decimals.is_some_and(|d| d > MAX_DECIMALS) {
+        return Err(CalcError::InvalidArgument(format!(
+            "Decimals must be 0-{}",
+            MAX_DECIMALS
+        )));
+    }
+    if options
+        .significant
+        .is_some_and(|n| n == 0 || n > MAX_SIGNIFICANT)
+    {
+        return Err(CalcError::InvalidArgument(format!(
+            "Significant figures must be 1-{}",
+            MAX_SIGNIFICANT
+        )));
+    }
+    if value.is_nan() {
+        return Ok("NaN".to_string());
+    }
+    if value.is_infinite() {
+        return Ok(if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string());
+    }
+
+    let magnitude = value.abs();
+    let (int, frac, exponent) = match options.notation {
+        Notation::Scientific => exponent_parts(magnitude, options, 1),
+        Notation::Engineering | Notation::Si => exponent_parts(magnitude, options, 3),
+        Notation::Standard => {
+            let text = match (options.decimals, options.significant) {
+                (Some(d), _) => {
+                    let rounded = if d as u32 <= MAX_ROUND_PLACES {
+                        round_to(magnitude, d as u32, RoundingMode::HalfUp)
+                    } else {
+                        magnitude
+                    };
+                    format!("{:.*}", d, rounded)
+                }
+                (None, Some(n)) if magnitude != 0.0 => {
+                    let (digits, exp) = scientific_digits(magnitude, Some(n));
+                    let (int, frac) = place_point(&digits, exp + 1);
+                    if frac.is_empty() {
+                        int
+                    } else {
+                        format!("{}.{}", int, frac)
+                    }
+                }
+                _ => magnitude.to_string(),
+            };
+            let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+            (int.to_string(), frac.to_string(), 0)
+        }
+    };
+
+    let (locale_decimal, locale_group) =
+        locale_separators(options.locale.as_deref().unwrap_or("en"));
+    let decimal = options
+        .decimal_separator
+        .as_deref()
+        .unwrap_or(locale_decimal);
+    let group_separator = options.group_separator.as_deref().unwrap_or(locale_group);
+
+    let zero = int.bytes().all(|b| b == b'0') && frac.bytes().all(|b| b == b'0');
+    let mut out = String::new();
+    if value < 0.0 && !zero {
+        out.push('-');
+    }
+    if options.thousands {
+        out.push_str(&group(&int, group_separator));
+    } else {
+        out.push_str(&int);
+    }
+    if !frac.is_empty() {
+        out.push_str(decimal);
+        out.push_str(&frac);
+    }
+    let prefix = match options.notation {
+        Notation::Si => si_prefix(exponent),
+        _ => None,
+    };
+    if let Some(prefix) = prefix {
+        if exponent != 0 {
+            out.push(' ');
+            out.push(prefix);
+        }
+    } else if options.notation != Notation::Standard {
+        out.push_str(&format!(
+            "e{}{}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        ));
+    }
+    Ok(out)
+}
+
+// A space for no exponent, so every exponent in range has a prefix.
+fn si_prefix(exponent: i32) -> Option<char> {
+    if exponent == 0 {
+        return Some(' ');
+    }
+    SI_PREFIXES
+        .iter()
+        .find(|&&(e, _)| e == exponent)
+        .map(|&(_, prefix)| prefix)
+}
+
+// The power of ten of a prefix; `u` and the Greek mu also mean micro.
+fn si_exponent(prefix: char) -> Option<i32> {
+    let prefix = match prefix {
+        'u' | '\u{3bc}' => 'µ',
+        c => c,
+    };
+    SI_PREFIXES
+        .iter()
+        .find(|&&(_, p)| p == prefix)
+        .map(|&(e, _)| e)
+}
+
+/// `value` in SI notation: `4700.0` is `"4.7 k"`, `0.0000022` is
+/// `"2.2 µ"`.
+pub fn format_si(value: f64) -> String {
+    let options = FormatOptions {
+        notation: Notation::Si,
+        ..Default::default()
+    };
+    // Default options are always valid.
+    format_number(value, &options).unwrap_or_else(|_| value.to_string())
+}
+
+/// Parses a number with an optional SI prefix: `"4.7k"`, `"4.7 k"`,
+/// `"2.2u"` (`u` for `µ`), or in electronics style with the prefix as the
+/// decimal point, `"4k7"`. There, `R` marks a point with no prefix
+/// (`"4R7"` is 4.7).
+pub fn parse_si(text: &str) -> Result<f64, CalcError> {
+    let trimmed = text.trim();
+    let chars: Vec<char> = trimmed.chars().collect();
+    let letter = chars
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, c)| c.is_alphabetic() || **c == 'µ');
+    let Some((at, &prefix)) = letter else {
+        return parse_number(trimmed);
+    };
+    let mantissa: String = chars[..at].iter().collect();
+    let rest: String = chars[at + 1..].iter().collect();
+    let invalid = || CalcError::InvalidArgument(format!("Invalid SI value '{}'", trimmed));
+    // `1e5` and `1E5` are exponents, not exa.
+    if matches!(prefix, 'e' | 'E') && !rest.is_empty() {
+        return parse_number(trimmed);
+    }
+    let (digits, exponent) = if rest.is_empty() {
+        let exponent = si_exponent(prefix).ok_or_else(invalid)?;
+        (mantissa.trim_end().to_string(), exponent)
+    } else {
+        // Electronics style: the prefix sits between whole digits.
+        let whole = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let unsigned = mantissa.trim_start_matches(['-', '+']);
+        if !whole(unsigned) || !whole(&rest) {
+            return Err(invalid());
+        }
+        let exponent = match prefix {
+            'R' => 0,
+            p => si_exponent(p).ok_or_else(invalid)?,
+        };
+        (format!("{}.{}", mantissa, rest), exponent)
+    };
+    parse_number(&digits)?;
+    // Scaled through the decimal text, so `4.7k` is exactly 4700.
+    parse_number(&format!("{}e{}", digits, exponent))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = format_si)]
+pub fn format_si_js(value: f64) -> String {
+    format_si(value)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = parse_si)]
+pub fn parse_si_js(text: &str) -> Result<f64, CalcErrorJs> {
+    parse_si(text).map_err(CalcErrorJs::from)
+}
+
+impl Calculator {
+    /// The current value formatted with [`format_number`], or by the
+    /// format hook (see `hooks`) if one is set and takes the value. Without
+    /// a `locale` in `options`, the calculator's input locale is used, so
+    /// output reads the way input is typed. Education mode's decimal limit
+    /// caps `decimals` and rounds the displayed value (not the value itself).
+    pub fn format_value(&self, options: &FormatOptions) -> Result<String, CalcError> {
+        let mut options = options.clone();
+        if options.locale.is_none() {
+            options.locale = self.input_locale.clone();
+        }
+        let mut value = self.current_value;
+        if let Some(limit) = self.education().and_then(|mode| mode.max_decimals) {
+            value = round_to(value, limit, RoundingMode::HalfUp);
+            options.decimals = options.decimals.map(|d| d.min(limit as usize));
+        }
+        if let Some(shown) = self.hook_format(value, &options)? {
+            return Ok(shown);
+        }
+        format_number(value, &options)
+    }
+
+    /// Rounds the current value half-up to `decimals` places (0-9),
+    /// recorded in the history.
+    pub fn round_to(&mut self, decimals: u32) -> Result<f64, CalcError> {
+        self.permit(&Operation::Round)?;
+        if decimals > MAX_ROUND_PLACES {
+            let err = CalcError::InvalidArgument(format!(
+                "Decimals must be 0-{}, got {}",
+                MAX_ROUND_PLACES, decimals
+            ));
+            return Err(self.fail(err, Some(decimals as f64)));
+        }
+        let result = round_to(self.current_value, decimals, RoundingMode::HalfUp);
+        self.add_to_history(
+            Operation::Round,
+            self.current_value,
+            Some(decimals as f64),
+            result,
+        );
+        self.current_value = result;
+        Ok(result)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `options` is an optional `{ decimals, significant, notation,
+    /// thousands, locale, decimal_separator, group_separator }`; without it
+    /// the settings' `decimals` and `notation` apply (see `apply_settings`).
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = format_value)]
+    pub fn format_value_js(&self, options: JsValue) -> Result<String, CalcErrorJs> {
+        let options: FormatOptions = if options.is_undefined() || options.is_null() {
+            self.display_options()
+        } else {
+            serde_wasm_bindgen::from_value(options).map_err(|e| {
+                CalcErrorJs::from(CalcError::InvalidArgument(format!(
+                    "Invalid format options: {}",
+                    e
+                )))
+            })?
+        };
+        self.format_value(&options).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = round_to)]
+    pub fn round_to_js(&mut self, decimals: u32) -> Result<f64, CalcErrorJs> {
+        self.round_to(decimals)
+            .map_err(|e| CalcErrorJs::new(e, Some(decimals as f64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(value: f64, options: FormatOptions) -> String {
+        format_number(value, &options).unwrap()
+    }
+
+    #[test]
+    fn test_decimals_and_significant() {
+        assert_eq!(fmt(1234.5, FormatOptions::default()), "1234.5");
+        let two = FormatOptions {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(fmt(1.005, two.clone()), "1.01");
+        assert_eq!(fmt(-0.001, two.clone()), "0.00");
+        assert_eq!(fmt(7.0, two), "7.00");
+        let sig = |n| FormatOptions {
+            significant: Some(n),
+            ..Default::default()
+        };
+        assert_eq!(fmt(1234.5, sig(2)), "1200");
+        assert_eq!(fmt(0.0012345, sig(3)), "0.00123");
+        assert_eq!(fmt(1.5, sig(3)), "1.50");
+        assert_eq!(fmt(0.0, sig(3)), "0");
+    }
+
+    #[test]
+    fn test_scientific_and_engineering() {
+        let sci = FormatOptions {
+            notation: Notation::Scientific,
+            ..Default::default()
+        };
+        assert_eq!(fmt(12345.6, sci.clone()), "1.23456e+4");
+        assert_eq!(fmt(-0.00012, sci.clone()), "-1.2e-4");
+        assert_eq!(fmt(0.0, sci.clone()), "0e+0");
+        let sci2 = FormatOptions {
+            decimals: Some(2),
+            ..sci
+        };
+        assert_eq!(fmt(9.999, sci2.clone()), "1.00e+1");
+        assert_eq!(fmt(12345.6, sci2), "1.23e+4");
+
+        let eng = FormatOptions {
+            notation: Notation::Engineering,
+            ..Default::default()
+        };
+        assert_eq!(fmt(12345.6, eng.clone()), "12.3456e+3");
+        assert_eq!(fmt(0.00047, eng.clone()), "470e-6");
+        assert_eq!(fmt(1.0, eng.clone()), "1e+0");
+        let eng1 = FormatOptions {
+            decimals: Some(1),
+            ..eng.clone()
+        };
+        assert_eq!(fmt(999.96, eng1.clone()), "1.0e+3");
+        assert_eq!(fmt(4700.0, eng1), "4.7e+3");
+        let eng_sig = FormatOptions {
+            significant: Some(2),
+            ..eng
+        };
+        assert_eq!(fmt(123456.0, eng_sig), "120e+3");
+    }
+
+    #[test]
+    fn test_si_prefixes() {
+        assert_eq!(format_si(4700.0), "4.7 k");
+        assert_eq!(format_si(0.0000022), "2.2 µ");
+        assert_eq!(format_si(-0.047), "-47 m");
+        assert_eq!(format_si(330.0), "330");
+        assert_eq!(format_si(0.0), "0");
+        assert_eq!(format_si(1e33), "1e+33");
+        let si1 = FormatOptions {
+            decimals: Some(1),
+            notation: Notation::Si,
+            ..Default::default()
+        };
+        assert_eq!(fmt(999_960.0, si1), "1.0 M");
+
+        assert_eq!(parse_si("4.7k"), Ok(4700.0));
+        assert_eq!(parse_si(" 4.7 k "), Ok(4700.0));
+        assert_eq!(parse_si("4k7"), Ok(4700.0));
+        assert_eq!(parse_si("4R7"), Ok(4.7));
+        assert_eq!(parse_si("2.2u"), Ok(2.2e-6));
+        assert_eq!(parse_si("2.2µ"), Ok(parse_si("2.2\u{3bc}").unwrap()));
+        assert_eq!(parse_si("-1M5"), Ok(-1_500_000.0));
+        assert_eq!(parse_si("3m"), Ok(0.003));
+        assert_eq!(parse_si("1e5"), Ok(1e5));
+        assert_eq!(parse_si("12"), Ok(12.0));
+        for bad in ["4.7x", "4.7k7", "k", "4k7k", "1E5k", ""] {
+            assert!(parse_si(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(parse_si(&format_si(0.00047)), Ok(0.00047));
+    }
+
+    #[test]
+    fn test_grouping_and_locales() {
+        let grouped = |locale: &str| FormatOptions {
+            decimals: Some(2),
+            thousands: true,
+            locale: Some(locale.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(fmt(1234567.891, grouped("en-US")), "1,234,567.89");
+        assert_eq!(fmt(1234567.891, grouped("de_DE")), "1.234.567,89");
+        assert_eq!(fmt(-1234.5, grouped("fr")), "-1\u{a0}234,50");
+        assert_eq!(fmt(1234.5, grouped("de-CH")), "1\u{2019}234.50");
+        assert_eq!(fmt(999.0, grouped("xx")), "999.00");
+        let custom = FormatOptions {
+            thousands: true,
+            decimal_separator: Some("\u{b7}".to_string()),
+            group_separator: Some("_".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(fmt(1234.5, custom), "1_234\u{b7}5");
+    }
+
+    #[test]
+    fn test_invalid_options_and_non_finite() {
+        let both = FormatOptions {
+            decimals: Some(2),
+            significant: Some(2),
+            ..Default::default()
+        };
+        assert!(format_number(1.0, &both).is_err());
+        let zero_sig = FormatOptions {
+            significant: Some(0),
+            ..Default::default()
+        };
+        assert!(format_number(1.0, &zero_sig).is_err());
+        assert_eq!(fmt(f64::NAN, FormatOptions::default()), "NaN");
+        assert_eq!(
+            fmt(f64::NEG_INFINITY, FormatOptions::default()),
+            "-Infinity"
+        );
+    }
+
+    #[test]
+    fn test_round_to_records_history() {
+        let mut calc = Calculator::new();
+        calc.set_value(2.0 / 3.0);
+        assert_eq!(calc.round_to(2), Ok(0.67));
+        assert_eq!(calc.get_value(), 0.67);
+        let entry = &calc.history_range(1, 1)[0];
+        assert_eq!(entry.operation, Operation::Round);
+        assert_eq!(entry.operand2, Some(2.0));
+        assert_eq!(calc.check_invariants(), Ok(()));
+        assert!(calc.round_to(10).is_err());
+        assert_eq!(calc.history_count(), 2);
+        assert_eq!(calc.apply(&Operation::Round, Some(1.0)), Ok(0.7));
+        assert_eq!(
+            calc.format_value(&FormatOptions {
+                decimals: Some(3),
+                ..Default::default()
+            }),
+            Ok("0.700".to_string())
+        );
+    }
+}
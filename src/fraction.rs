@@ -0,0 +1,601 @@
+//! Exact rational arithmetic.
+//!
+//! [`Fraction`] is always in lowest terms with a positive denominator, so
+//! `1/3 + 1/6` is exactly `1/2`. Intermediate products are computed in
+//! `i128` and only rejected if the reduced result does not fit in `i64`.
+//! From JavaScript, numerators and denominators travel as numbers and must
+//! be safe integers (|n| <= 2^53 - 1).
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
+fn overflow() -> CalcError {
+    CalcError::InvalidArgument("Fraction overflow".to_string())
+}
+
+#[cfg(feature = "wasm")]
+pub(crate) fn safe_integer(value: f64) -> Result<i64, CalcError> {
+    if value.fract() != 0.0 || value.abs() > MAX_SAFE_INTEGER {
+        return Err(CalcError::InvalidArgument(format!(
+            "Expected a safe integer, got {}",
+            value
+        )));
+    }
+    Ok(value as i64)
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fraction {
+    num: i64,
+    den: i64,
+}
+
+impl Fraction {
+    /// Builds `num/den` in lowest terms.
+    pub fn from_parts(num: i64, den: i64) -> Result<Fraction, CalcError> {
+        Fraction::reduce(num as i128, den as i128)
+    }
+
+    fn reduce(num: i128, den: i128) -> Result<Fraction, CalcError> {
+        if den == 0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        let g = gcd(num, den).max(1);
+        let sign = if den < 0 { -1 } else { 1 };
+        let num = i64::try_from(sign * num / g).map_err(|_| overflow())?;
+        let den = i64::try_from(sign * den / g).map_err(|_| overflow())?;
+        Ok(Fraction { num, den })
+    }
+
+    pub fn numer(&self) -> i64 {
+        self.num
+    }
+
+    pub fn denom(&self) -> i64 {
+        self.den
+    }
+
+    /// Parses `"3"`, `"-1/3"`, a mixed number `"2 1/3"` or a terminating
+    /// decimal `"0.75"` (converted exactly).
+    pub fn parse(text: &str) -> Result<Fraction, CalcError> {
+        let invalid = || CalcError::InvalidArgument(format!("Invalid fraction '{}'", text.trim()));
+        let int = |s: &str| s.trim().parse::<i64>().map_err(|_| invalid());
+        let text = text.trim();
+        if let Some((whole, rest)) = text.split_once(' ') {
+            let whole_part = int(whole)?;
+            let frac = match rest.trim().split_once('/') {
+                Some((n, d)) if !n.contains('-') => Fraction::from_parts(int(n)?, int(d)?)?,
+                _ => return Err(invalid()),
+            };
+            let magnitude = Fraction::from_parts(whole_part.abs(), 1)?.add(&frac)?;
+            // "-2 1/3" is -(2 + 1/3).
+            return if whole.starts_with('-') {
+                magnitude.neg()
+            } else {
+                Ok(magnitude)
+            };
+        }
+        if let Some((n, d)) = text.split_once('/') {
+            return Fraction::from_parts(int(n)?, int(d)?);
+        }
+        if let Some((i, f)) = text.split_once('.') {
+            if f.is_empty() || !f.chars().all(|c| c.is_ascii_digit()) || f.len() > 18 {
+                return Err(invalid());
+            }
+            let den = 10i128.pow(f.len() as u32);
+            let negative = i.starts_with('-');
+            let whole = if i.is_empty() || i == "-" { 0 } else { int(i)? } as i128;
+            let frac: i128 = f.parse().map_err(|_| invalid())?;
+            let num = whole * den + if negative { -frac } else { frac };
+            return Fraction::reduce(num, den);
+        }
+        Fraction::from_parts(int(text)?, 1)
+    }
+
+    /// Builds the mixed number `whole num/den`; the sign is the whole
+    /// part's (or the numerator's when the whole part is 0), so
+    /// `from_mixed(-2, 1, 3)` is `-7/3`.
+    pub fn from_mixed(whole: i64, num: i64, den: i64) -> Result<Fraction, CalcError> {
+        if den <= 0 || (whole != 0 && num < 0) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Invalid mixed number {} {}/{}",
+                whole, num, den
+            )));
+        }
+        let magnitude = (whole as i128).abs() * den as i128 + num as i128;
+        let sign = if whole < 0 { -1 } else { 1 };
+        Fraction::reduce(sign * magnitude, den as i128)
+    }
+
+    /// The whole part of the mixed form, truncated toward zero: 2 for
+    /// `7/3`, -2 for `-7/3`.
+    pub fn whole_part(&self) -> i64 {
+        self.num / self.den
+    }
+
+    /// What remains after [`Fraction::whole_part`], with the value's sign:
+    /// `1/3` for `7/3`, `-1/3` for `-7/3`.
+    pub fn fractional_part(&self) -> Fraction {
+        Fraction {
+            num: self.num % self.den,
+            den: self.den,
+        }
+        .normalized()
+    }
+
+    // `0/den` is stored as `0/1`.
+    fn normalized(self) -> Fraction {
+        if self.num == 0 {
+            Fraction::default()
+        } else {
+            self
+        }
+    }
+
+    /// Evaluates school-style arithmetic on fractions and mixed numbers,
+    /// such as `"1 3/4 + 2 1/2"` or `"3/4 × 2/3"`, exactly and with `×`/`*`
+    /// and `÷` binding tighter than `+` and `-`. Operators are separated
+    /// by spaces; a `/` with spaces around it divides, one without forms a
+    /// fraction.
+    pub fn evaluate(text: &str) -> Result<Fraction, CalcError> {
+        let invalid =
+            |what: &str| CalcError::InvalidArgument(format!("{} in '{}'", what, text.trim()));
+        let mut tokens = text.split_whitespace().peekable();
+        // A sum of terms, each a run of products and quotients.
+        let mut total = Fraction::default();
+        let mut sign = 1;
+        loop {
+            let mut term = None::<Fraction>;
+            let mut pending = None::<&str>;
+            loop {
+                let Some(token) = tokens.next() else {
+                    return Err(invalid("Expected a number"));
+                };
+                let mut operand =
+                    Fraction::parse(token).map_err(|_| invalid("Expected a number"))?;
+                // `1 3/4`: a whole number followed by a fraction.
+                let is_whole = !token.contains(['/', '.']);
+                if let Some(next) = tokens
+                    .peek()
+                    .filter(|t| is_whole && t.len() > 1 && t.contains('/'))
+                {
+                    operand = Fraction::parse(&format!("{} {}", token, next))
+                        .map_err(|_| invalid("Invalid mixed number"))?;
+                    tokens.next();
+                }
+                term = Some(match (term, pending) {
+                    (None, _) => operand,
+                    (Some(t), Some("*" | "×")) => t.mul(&operand)?,
+                    (Some(t), _) => t.div(&operand)?,
+                });
+                match tokens.peek() {
+                    Some(&op @ ("*" | "×" | "/" | "÷")) => {
+                        pending = Some(op);
+                        tokens.next();
+                    }
+                    _ => break,
+                }
+            }
+            let term = term.unwrap_or_default();
+            total = if sign > 0 {
+                total.add(&term)?
+            } else {
+                total.sub(&term)?
+            };
+            match tokens.next() {
+                None => return Ok(total),
+                Some("+") => sign = 1,
+                Some("-" | "−") => sign = -1,
+                Some(other) => return Err(invalid(&format!("Unexpected '{}'", other))),
+            }
+        }
+    }
+
+    pub fn add(&self, other: &Fraction) -> Result<Fraction, CalcError> {
+        Fraction::reduce(
+            self.num as i128 * other.den as i128 + other.num as i128 * self.den as i128,
+            self.den as i128 * other.den as i128,
+        )
+    }
+
+    pub fn sub(&self, other: &Fraction) -> Result<Fraction, CalcError> {
+        self.add(&other.neg()?)
+    }
+
+    pub fn mul(&self, other: &Fraction) -> Result<Fraction, CalcError> {
+        Fraction::reduce(
+            self.num as i128 * other.num as i128,
+            self.den as i128 * other.den as i128,
+        )
+    }
+
+    pub fn div(&self, other: &Fraction) -> Result<Fraction, CalcError> {
+        Fraction::reduce(
+            self.num as i128 * other.den as i128,
+            self.den as i128 * other.num as i128,
+        )
+    }
+
+    pub fn neg(&self) -> Result<Fraction, CalcError> {
+        Fraction::reduce(-(self.num as i128), self.den as i128)
+    }
+
+    /// The first continued-fraction convergent of `value` within
+    /// `tolerance`; a tolerance of 0 gives the exact binary value.
+    pub fn from_decimal(value: f64, tolerance: f64) -> Result<Fraction, CalcError> {
+        if !value.is_finite() || value.abs() > MAX_SAFE_INTEGER {
+            return Err(CalcError::InvalidArgument(format!(
+                "Cannot convert {} to a fraction",
+                value
+            )));
+        }
+        let tolerance = tolerance.abs();
+        let (mut h0, mut h1) = (0i128, 1i128);
+        let (mut k0, mut k1) = (1i128, 0i128);
+        let mut x = value;
+        for _ in 0..64 {
+            let a = x.floor();
+            (h0, h1) = (h1, a as i128 * h1 + h0);
+            (k0, k1) = (k1, a as i128 * k1 + k0);
+            if k1 > i64::MAX as i128 || h1.abs() > i64::MAX as i128 {
+                break;
+            }
+            let approx = h1 as f64 / k1 as f64;
+            if (approx - value).abs() <= tolerance || x == a {
+                return Fraction::reduce(h1, k1);
+            }
+            x = 1.0 / (x - a);
+            if !x.is_finite() {
+                break;
+            }
+        }
+        // Best convergent that still fits.
+        Fraction::reduce(h0, k0)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Fraction {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new(numerator: f64, denominator: f64) -> Result<Fraction, CalcErrorJs> {
+        let num = safe_integer(numerator).map_err(|e| CalcErrorJs::new(e, Some(numerator)))?;
+        let den = safe_integer(denominator).map_err(|e| CalcErrorJs::new(e, Some(denominator)))?;
+        Fraction::from_parts(num, den).map_err(|e| CalcErrorJs::new(e, Some(denominator)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = parse)]
+    pub fn parse_js(text: &str) -> Result<Fraction, CalcErrorJs> {
+        Fraction::parse(text).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = from_mixed)]
+    pub fn from_mixed_js(
+        whole: f64,
+        numerator: f64,
+        denominator: f64,
+    ) -> Result<Fraction, CalcErrorJs> {
+        let parts = [whole, numerator, denominator]
+            .map(|v| safe_integer(v).map_err(|e| CalcErrorJs::new(e, Some(v))));
+        let [whole, num, den] = parts;
+        Fraction::from_mixed(whole?, num?, den?).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = evaluate)]
+    pub fn evaluate_js(text: &str) -> Result<Fraction, CalcErrorJs> {
+        Fraction::evaluate(text).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = whole_part)]
+    pub fn whole_part_js(&self) -> f64 {
+        self.whole_part() as f64
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = fractional_part)]
+    pub fn fractional_part_js(&self) -> Fraction {
+        self.fractional_part()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = from_decimal)]
+    pub fn from_decimal_js(value: f64, tolerance: f64) -> Result<Fraction, CalcErrorJs> {
+        Fraction::from_decimal(value, tolerance).map_err(|e| CalcErrorJs::new(e, Some(value)))
+    }
+
+    pub fn numerator(&self) -> f64 {
+        self.num as f64
+    }
+
+    pub fn denominator(&self) -> f64 {
+        self.den as f64
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&self, other: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.add(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = sub)]
+    pub fn sub_js(&self, other: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.sub(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = mul)]
+    pub fn mul_js(&self, other: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.mul(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = div)]
+    pub fn div_js(&self, other: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.div(other).map_err(CalcErrorJs::from)
+    }
+
+    pub fn to_decimal(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// `"7/3"` becomes `"2 1/3"`; proper fractions and integers are unchanged.
+    pub fn to_mixed_string(&self) -> String {
+        let whole = self.num / self.den;
+        let rest = (self.num % self.den).abs();
+        if whole == 0 || rest == 0 {
+            return self.to_string();
+        }
+        format!("{} {}/{}", whole, rest, self.den)
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// `a/b`, or just `a` when the denominator is 1.
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl Default for Fraction {
+    fn default() -> Self {
+        Fraction { num: 0, den: 1 }
+    }
+}
+
+/// Running-value calculator in rational mode.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Default)]
+pub struct FractionCalculator {
+    value: Fraction,
+    // Whether `display` shows mixed numbers rather than improper fractions.
+    mixed: bool,
+}
+
+impl FractionCalculator {
+    fn update(
+        &mut self,
+        op: fn(&Fraction, &Fraction) -> Result<Fraction, CalcError>,
+        operand: &Fraction,
+    ) -> Result<Fraction, CalcError> {
+        self.value = op(&self.value, operand)?;
+        Ok(self.value)
+    }
+
+    pub fn add(&mut self, operand: &Fraction) -> Result<Fraction, CalcError> {
+        self.update(Fraction::add, operand)
+    }
+
+    pub fn subtract(&mut self, operand: &Fraction) -> Result<Fraction, CalcError> {
+        self.update(Fraction::sub, operand)
+    }
+
+    pub fn multiply(&mut self, operand: &Fraction) -> Result<Fraction, CalcError> {
+        self.update(Fraction::mul, operand)
+    }
+
+    pub fn divide(&mut self, operand: &Fraction) -> Result<Fraction, CalcError> {
+        self.update(Fraction::div, operand)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl FractionCalculator {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> FractionCalculator {
+        FractionCalculator::default()
+    }
+
+    pub fn get_value(&self) -> Fraction {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: &Fraction) {
+        self.value = *value;
+    }
+
+    /// Parses `text` as in `Fraction.parse` and makes it the value.
+    #[cfg(feature = "wasm")]
+    pub fn set_from_string(&mut self, text: &str) -> Result<Fraction, CalcErrorJs> {
+        self.value = Fraction::parse(text).map_err(CalcErrorJs::from)?;
+        Ok(self.value)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&mut self, operand: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.add(operand).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = subtract)]
+    pub fn subtract_js(&mut self, operand: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.subtract(operand).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = multiply)]
+    pub fn multiply_js(&mut self, operand: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.multiply(operand).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = divide)]
+    pub fn divide_js(&mut self, operand: &Fraction) -> Result<Fraction, CalcErrorJs> {
+        self.divide(operand).map_err(CalcErrorJs::from)
+    }
+
+    pub fn clear(&mut self) {
+        self.value = Fraction::default();
+    }
+
+    /// Chooses how `display` writes the value: `"2 1/3"` when true,
+    /// `"7/3"` (the default) when false.
+    pub fn set_mixed_output(&mut self, mixed: bool) {
+        self.mixed = mixed;
+    }
+
+    pub fn display(&self) -> String {
+        if self.mixed {
+            self.value.to_mixed_string()
+        } else {
+            self.value.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frac(text: &str) -> Fraction {
+        Fraction::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_simplification_and_sign() {
+        let f = Fraction::from_parts(6, -8).unwrap();
+        assert_eq!((f.numer(), f.denom()), (-3, 4));
+        assert_eq!(Fraction::from_parts(0, -5).unwrap(), Fraction::default());
+        assert_eq!(Fraction::from_parts(1, 0), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_exact_arithmetic() {
+        assert_eq!(frac("1/3").add(&frac("1/6")), Ok(frac("1/2")));
+        assert_eq!(frac("1/3").sub(&frac("1/2")), Ok(frac("-1/6")));
+        assert_eq!(frac("2/3").mul(&frac("3/4")), Ok(frac("1/2")));
+        assert_eq!(frac("1/2").div(&frac("1/4")), Ok(frac("2")));
+        assert_eq!(frac("1/2").div(&frac("0")), Err(CalcError::DivisionByZero));
+        let big = Fraction::from_parts(i64::MAX, 1).unwrap();
+        assert!(matches!(big.add(&big), Err(CalcError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_parse_forms() {
+        assert_eq!(frac("2 1/3"), frac("7/3"));
+        assert_eq!(frac("-2 1/3"), frac("-7/3"));
+        assert_eq!(frac("0.75"), frac("3/4"));
+        assert_eq!(frac("-1.5"), frac("-3/2"));
+        assert_eq!(frac("-0.5"), frac("-1/2"));
+        assert_eq!(frac(" 4 "), frac("8/2"));
+        assert!(Fraction::parse("1/x").is_err());
+        assert!(Fraction::parse("2 -1/3").is_err());
+    }
+
+    #[test]
+    fn test_display_and_mixed() {
+        assert_eq!(frac("7/3").to_string(), "7/3");
+        assert_eq!(frac("7/3").to_mixed_string(), "2 1/3");
+        assert_eq!(frac("-7/3").to_mixed_string(), "-2 1/3");
+        assert_eq!(frac("2/3").to_mixed_string(), "2/3");
+        assert_eq!(frac("6/3").to_js_string(), "2");
+    }
+
+    #[test]
+    fn test_mixed_numbers() {
+        assert_eq!(Fraction::from_mixed(1, 3, 4), Ok(frac("7/4")));
+        assert_eq!(Fraction::from_mixed(-2, 1, 3), Ok(frac("-7/3")));
+        assert_eq!(Fraction::from_mixed(0, -1, 2), Ok(frac("-1/2")));
+        assert!(Fraction::from_mixed(1, -1, 2).is_err());
+        assert!(Fraction::from_mixed(1, 1, 0).is_err());
+        assert_eq!(frac("-7/3").whole_part(), -2);
+        assert_eq!(frac("-7/3").fractional_part(), frac("-1/3"));
+        assert_eq!(frac("6/3").fractional_part(), Fraction::default());
+
+        let eval = |text| Fraction::evaluate(text).map(|f| f.to_mixed_string());
+        assert_eq!(eval("1 3/4 + 2 1/2"), Ok("4 1/4".to_string()));
+        assert_eq!(eval("5 - 1 1/3 × 3"), Ok("1".to_string()));
+        assert_eq!(eval("3/4 ÷ 1 1/2 - 1/2"), Ok("0".to_string()));
+        assert_eq!(eval("2 / 3 * 0.5"), Ok("1/3".to_string()));
+        assert_eq!(eval("-1 1/2 + 1/4"), Ok("-1 1/4".to_string()));
+        assert_eq!(
+            Fraction::evaluate("1/2 ÷ 0"),
+            Err(CalcError::DivisionByZero)
+        );
+        assert!(Fraction::evaluate("1/2 +").is_err());
+        assert!(Fraction::evaluate("1/2 % 3").is_err());
+        assert!(Fraction::evaluate("").is_err());
+
+        let mut calc = FractionCalculator::new();
+        calc.set_value(&frac("7/3"));
+        assert_eq!(calc.display(), "7/3");
+        calc.set_mixed_output(true);
+        assert_eq!(calc.display(), "2 1/3");
+    }
+
+    #[test]
+    fn test_decimal_conversion() {
+        let third = Fraction::from_decimal(0.3333333333, 1e-6).unwrap();
+        assert_eq!(third, frac("1/3"));
+        assert_eq!(
+            Fraction::from_decimal(std::f64::consts::PI, 2e-3),
+            Ok(frac("22/7"))
+        );
+        assert_eq!(Fraction::from_decimal(-0.125, 0.0), Ok(frac("-1/8")));
+        assert_eq!(frac("5/8").to_decimal(), 0.625);
+        assert!(Fraction::from_decimal(f64::NAN, 1e-6).is_err());
+    }
+
+    #[test]
+    fn test_fraction_calculator() {
+        let mut calc = FractionCalculator::new();
+        calc.add(&frac("1/3")).unwrap();
+        calc.add(&frac("1/3")).unwrap();
+        calc.add(&frac("1/3")).unwrap();
+        assert_eq!(calc.get_value(), frac("1"));
+        calc.divide(&frac("3")).unwrap();
+        assert_eq!(calc.get_value().to_string(), "1/3");
+        assert_eq!(calc.divide(&frac("0")), Err(CalcError::DivisionByZero));
+        assert_eq!(calc.get_value(), frac("1/3"));
+    }
+}
@@ -0,0 +1,240 @@
+//! Read-only views of a calculator.
+//!
+//! [`Calculator::freeze`] returns a [`FrozenCalculator`]: a copy of the
+//! state that can be read but not changed, for handing to a UI component
+//! or plugin that should display the calculator without being able to
+//! alter it. From Rust the view simply has no mutating methods. From
+//! JavaScript, where a plugin may call anything, the usual mutators exist
+//! on the view and throw `NOT_ALLOWED` instead of silently doing nothing.
+//!
+//! The view is a snapshot taken when `freeze` is called; later changes to
+//! the calculator do not show through, so freeze again to pass on fresh
+//! state. It has no listeners of its own and never calls the calculator's.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::format::FormatOptions;
+use crate::settings::CalculatorSettings;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, CalculationHistory, Calculator};
+
+/// A read-only copy of a calculator; see the module docs.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone)]
+pub struct FrozenCalculator {
+    calc: Calculator,
+}
+
+#[cfg(feature = "wasm")]
+fn read_only(method: &str) -> CalcErrorJs {
+    CalcErrorJs::from(CalcError::NotAllowed(format!(
+        "Calculator is read-only: {} is not allowed",
+        method
+    )))
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// A read-only snapshot of the current state.
+    pub fn freeze(&self) -> FrozenCalculator {
+        FrozenCalculator { calc: self.clone() }
+    }
+}
+
+impl FrozenCalculator {
+    /// See [`Calculator::history_range`].
+    pub fn history_range(&self, start: usize, count: usize) -> Vec<CalculationHistory> {
+        self.calc.history_range(start, count)
+    }
+
+    pub fn settings(&self) -> CalculatorSettings {
+        self.calc.settings()
+    }
+
+    /// See [`Calculator::format_value`].
+    pub fn format_value(&self, options: &FormatOptions) -> Result<String, CalcError> {
+        self.calc.format_value(options)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl FrozenCalculator {
+    pub fn get_value(&self) -> f64 {
+        self.calc.get_value()
+    }
+
+    pub fn get_memory(&self) -> f64 {
+        self.calc.get_memory()
+    }
+
+    pub fn history_count(&self) -> usize {
+        self.calc.history_count()
+    }
+
+    pub fn get_history_limit(&self) -> usize {
+        self.calc.get_history_limit()
+    }
+
+    pub fn strict_math(&self) -> bool {
+        self.calc.strict_math()
+    }
+
+    pub fn canonical_state_string(&self) -> String {
+        self.calc.canonical_state_string()
+    }
+
+    /// An editable copy, independent of both the view and the calculator
+    /// it was frozen from.
+    pub fn thaw(&self) -> Calculator {
+        self.calc.clone()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_history(&self) -> JsValue {
+        self.calc.get_history()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_settings)]
+    pub fn get_settings_js(&self) -> JsValue {
+        self.calc.get_settings_js()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = format_value)]
+    pub fn format_value_js(&self, options: JsValue) -> Result<String, CalcErrorJs> {
+        self.calc.format_value_js(options)
+    }
+
+    // The mutators a plugin written against `Calculator` would call.
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&self, _value: f64) -> Result<f64, CalcErrorJs> {
+        Err(read_only("add"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = subtract)]
+    pub fn subtract_js(&self, _value: f64) -> Result<f64, CalcErrorJs> {
+        Err(read_only("subtract"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = multiply)]
+    pub fn multiply_js(&self, _value: f64) -> Result<f64, CalcErrorJs> {
+        Err(read_only("multiply"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = divide)]
+    pub fn divide_js(&self, _value: f64) -> Result<f64, CalcErrorJs> {
+        Err(read_only("divide"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = power)]
+    pub fn power_js(&self, _exponent: f64) -> Result<f64, CalcErrorJs> {
+        Err(read_only("power"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = sqrt)]
+    pub fn sqrt_js(&self) -> Result<f64, CalcErrorJs> {
+        Err(read_only("sqrt"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = evaluate)]
+    pub fn evaluate_js(&self, _expr: &str) -> Result<f64, CalcErrorJs> {
+        Err(read_only("evaluate"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_value)]
+    pub fn set_value_js(&self, _value: f64) -> Result<(), CalcErrorJs> {
+        Err(read_only("set_value"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = clear)]
+    pub fn clear_js(&self) -> Result<(), CalcErrorJs> {
+        Err(read_only("clear"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = memory_store)]
+    pub fn memory_store_js(&self) -> Result<(), CalcErrorJs> {
+        Err(read_only("memory_store"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = memory_recall)]
+    pub fn memory_recall_js(&self) -> Result<f64, CalcErrorJs> {
+        Err(read_only("memory_recall"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = memory_clear)]
+    pub fn memory_clear_js(&self) -> Result<(), CalcErrorJs> {
+        Err(read_only("memory_clear"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = memory_add)]
+    pub fn memory_add_js(&self) -> Result<(), CalcErrorJs> {
+        Err(read_only("memory_add"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = clear_history)]
+    pub fn clear_history_js(&self) -> Result<bool, CalcErrorJs> {
+        Err(read_only("clear_history"))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = apply_settings)]
+    pub fn apply_settings_js(&self, _changes: JsValue) -> Result<JsValue, CalcErrorJs> {
+        Err(read_only("apply_settings"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_freeze_is_a_snapshot() {
+        let mut calc = Calculator::new();
+        calc.add(5.0);
+        calc.memory_store();
+        let frozen = calc.freeze();
+        calc.multiply(3.0);
+        assert_eq!((frozen.get_value(), frozen.get_memory()), (5.0, 5.0));
+        assert_eq!(frozen.history_count(), 2);
+        assert_eq!(calc.get_value(), 15.0);
+
+        let mut copy = frozen.thaw();
+        copy.clear();
+        assert_eq!(frozen.get_value(), 5.0);
+        #[cfg(feature = "wasm")]
+        assert_eq!(frozen.add_js(1.0).unwrap_err().code(), "NOT_ALLOWED");
+    }
+
+    #[test]
+    fn test_freeze_drops_listeners() {
+        let changes = Rc::new(RefCell::new(0));
+        let mut calc = Calculator::new();
+        let sink = changes.clone();
+        calc.on_change(move |_, _| *sink.borrow_mut() += 1);
+        let mut copy = calc.freeze().thaw();
+        copy.add(1.0);
+        assert_eq!(*changes.borrow(), 0);
+        calc.add(1.0);
+        assert_eq!(*changes.borrow(), 1);
+    }
+}
@@ -0,0 +1,299 @@
+//! User-defined functions and recorded macros.
+//!
+//! A function is an expression in `x`, the current value:
+//! `define_function("tip", "x * 0.18")`. A macro is a recorded run of
+//! operations: everything applied between `record_macro("vat")` and
+//! `finish_macro()` is replayed by `call_function("vat")`. Both share one
+//! namespace and are called the same way.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, AngleScope, Dialect, Expr, CONSTANTS, FUNCTIONS};
+use crate::locale::localize_input;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+/// The operations of a macro, as `(operation, operand)` pairs for
+/// [`Calculator::apply`].
+pub type MacroSteps = Vec<(Operation, Option<f64>)>;
+
+/// What a user function name is bound to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserFunction {
+    /// An expression evaluated with `x` (and `ans`) bound to the current
+    /// value and `mem` to the memory. Recorded as a single history entry.
+    Expression { source: String, expr: Expr },
+    /// Operations replayed through [`Calculator::apply`], each recorded as
+    /// it would be if called directly.
+    Macro(MacroSteps),
+}
+
+fn check_name(name: &str) -> Result<(), CalcError> {
+    let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !valid {
+        return Err(CalcError::InvalidArgument(format!(
+            "Function name '{}' must be an identifier",
+            name
+        )));
+    }
+    let reserved = ["x", "ans", "mem"].contains(&name)
+        || FUNCTIONS.iter().any(|(f, _, _)| *f == name)
+        || CONSTANTS.iter().any(|(c, _)| *c == name);
+    if reserved {
+        return Err(CalcError::InvalidArgument(format!(
+            "'{}' is a built-in name",
+            name
+        )));
+    }
+    Ok(())
+}
+
+impl Calculator {
+    /// Defines (or replaces) `name` as the expression `body` in `x`. The
+    /// body is parsed now, in the input locale, so syntax errors surface
+    /// here rather than on the first call.
+    pub fn define_function(&mut self, name: &str, body: &str) -> Result<(), CalcError> {
+        let expr = check_name(name)
+            .and_then(|_| localize_input(body, self.decimal_comma()))
+            .and_then(|body| parse(&body, Dialect::Standard))
+            .map_err(|e| self.fail(e, None))?;
+        self.functions.insert(
+            name.to_string(),
+            UserFunction::Expression {
+                source: body.to_string(),
+                expr,
+            },
+        );
+        Ok(())
+    }
+
+    /// Starts recording a macro to be saved as `name`. Until
+    /// [`Calculator::finish_macro`], every recorded operation is also
+    /// appended to the macro.
+    pub fn record_macro(&mut self, name: &str) -> Result<(), CalcError> {
+        if let Some((recording, _)) = &self.recording {
+            let err = CalcError::InvalidArgument(format!("Already recording '{}'", recording));
+            return Err(self.fail(err, None));
+        }
+        check_name(name).map_err(|e| self.fail(e, None))?;
+        self.recording = Some((name.to_string(), Vec::new()));
+        Ok(())
+    }
+
+    /// Saves the macro being recorded and returns how many operations it
+    /// holds.
+    ///
+    /// A macro may call expression functions but not itself, so a name
+    /// that some macro already calls cannot become a macro; this keeps
+    /// every call finite.
+    pub fn finish_macro(&mut self) -> Result<usize, CalcError> {
+        let Some((name, steps)) = self.recording.take() else {
+            let err = CalcError::InvalidArgument("No macro is being recorded".to_string());
+            return Err(self.fail(err, None));
+        };
+        let called = Operation::Function(name.clone());
+        let recursive = steps
+            .iter()
+            .chain(self.functions.values().flat_map(|f| match f {
+                UserFunction::Macro(steps) => steps.as_slice(),
+                UserFunction::Expression { .. } => &[],
+            }))
+            .any(|(operation, _)| *operation == called);
+        if recursive {
+            let err = CalcError::InvalidArgument(format!("Macro '{}' would call itself", name));
+            return Err(self.fail(err, None));
+        }
+        let count = steps.len();
+        self.functions.insert(name, UserFunction::Macro(steps));
+        Ok(count)
+    }
+
+    /// Applies the function or macro `name` to the current value and
+    /// returns the new value. A macro is all-or-nothing, like
+    /// [`Calculator::apply_batch`].
+    pub fn call_function(&mut self, name: &str) -> Result<f64, CalcError> {
+        if self.starts_timing() {
+            return self.timed(&format!("function:{}", name), |calc| {
+                calc.call_function(name)
+            });
+        }
+        self.permit_name("function")?;
+        let Some(function) = self.functions.get(name).cloned() else {
+            let known = self.functions.keys().map(String::as_str);
+            let err = crate::expr::unknown_identifier(name, known);
+            return Err(self.fail(err, None));
+        };
+        match function {
+            UserFunction::Expression { expr, .. } => {
+                let mut scope = BTreeMap::new();
+                scope.insert("x".to_string(), self.current_value);
+                scope.insert("ans".to_string(), self.current_value);
+                scope.insert("mem".to_string(), self.memory);
+                let scope = AngleScope {
+                    scope: &scope,
+                    unit: self.angle_unit,
+                };
+                let result = expr.eval(&scope).map_err(|e| self.fail(e, None))?;
+                self.commit(Operation::Function(name.to_string()), None, result)
+            }
+            UserFunction::Macro(steps) => {
+                self.apply_all(steps)?;
+                Ok(self.current_value)
+            }
+        }
+    }
+
+    pub fn user_function(&self, name: &str) -> Option<&UserFunction> {
+        self.functions.get(name)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = define_function)]
+    pub fn define_function_js(&mut self, name: &str, body: &str) -> Result<(), CalcErrorJs> {
+        self.define_function(name, body).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = record_macro)]
+    pub fn record_macro_js(&mut self, name: &str) -> Result<(), CalcErrorJs> {
+        self.record_macro(name).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = finish_macro)]
+    pub fn finish_macro_js(&mut self) -> Result<usize, CalcErrorJs> {
+        self.finish_macro().map_err(CalcErrorJs::from)
+    }
+
+    /// Abandons the macro being recorded, if any.
+    pub fn cancel_macro(&mut self) {
+        self.recording = None;
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = call_function)]
+    pub fn call_function_js(&mut self, name: &str) -> Result<f64, CalcErrorJs> {
+        let operand = self.current_value;
+        self.call_function(name)
+            .map_err(|e| CalcErrorJs::new(e, Some(operand)))
+    }
+
+    /// Removes a function or macro; returns whether it existed.
+    pub fn remove_function(&mut self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
+    /// Names of the defined functions and macros, sorted.
+    pub fn function_names(&self) -> Vec<String> {
+        self.functions.keys().cloned().collect()
+    }
+
+    /// The body of an expression function, or `undefined` for a macro or
+    /// an unknown name.
+    pub fn function_source(&self, name: &str) -> Option<String> {
+        match self.functions.get(name) {
+            Some(UserFunction::Expression { source, .. }) => Some(source.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression_function() {
+        let mut calc = Calculator::new();
+        calc.define_function("tip", "x * 0.18").unwrap();
+        calc.set_value(50.0);
+        assert_eq!(calc.call_function("tip"), Ok(9.0));
+        let entry = &calc.history_range(1, 1)[0];
+        assert_eq!(entry.operation, Operation::Function("tip".to_string()));
+        assert_eq!((entry.operand1, entry.result), (50.0, 9.0));
+        assert_eq!(calc.function_source("tip"), Some("x * 0.18".to_string()));
+        assert_eq!(calc.check_invariants(), Ok(()));
+        calc.memory_store();
+        calc.define_function("tip", "mem + ans").unwrap();
+        assert_eq!(calc.call_function("tip"), Ok(18.0));
+    }
+
+    #[test]
+    fn test_define_function_errors() {
+        let mut calc = Calculator::new();
+        assert!(matches!(
+            calc.define_function("sqrt", "x"),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            calc.define_function("2x", "x"),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            calc.define_function("half", "x /"),
+            Err(CalcError::Syntax { .. })
+        ));
+        calc.define_function("half", "x / y").unwrap();
+        assert!(matches!(
+            calc.call_function("half"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert_eq!(
+            calc.call_function("hlaf"),
+            Err(CalcError::UnknownIdentifier {
+                name: "hlaf".to_string(),
+                suggestions: vec!["half".to_string()]
+            })
+        );
+        assert_eq!(calc.history_count(), 0);
+    }
+
+    #[test]
+    fn test_recorded_macro() {
+        let mut calc = Calculator::new();
+        calc.define_function("double", "2 * x").unwrap();
+        calc.record_macro("vat").unwrap();
+        calc.set_value(100.0);
+        calc.add_percent(20.0);
+        calc.call_function("double").unwrap();
+        assert_eq!(calc.finish_macro(), Ok(3));
+        assert_eq!(calc.function_names(), vec!["double", "vat"]);
+        calc.clear();
+        let before = calc.history_count();
+        assert_eq!(calc.call_function("vat"), Ok(240.0));
+        assert_eq!(calc.history_count(), before + 3);
+        assert_eq!(calc.check_invariants(), Ok(()));
+        assert!(calc.finish_macro().is_err());
+    }
+
+    #[test]
+    fn test_macros_cannot_recurse() {
+        let mut calc = Calculator::new();
+        calc.define_function("inc", "x + 1").unwrap();
+        calc.record_macro("twice").unwrap();
+        assert!(calc.record_macro("other").is_err());
+        calc.call_function("inc").unwrap();
+        calc.call_function("inc").unwrap();
+        calc.finish_macro().unwrap();
+        // `twice` calls `inc`, so `inc` cannot become a macro.
+        calc.record_macro("inc").unwrap();
+        calc.call_function("twice").unwrap();
+        assert!(matches!(
+            calc.finish_macro(),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(calc.remove_function("twice"));
+        assert_eq!(
+            calc.call_function("twice").map_err(|e| e.code()),
+            Err("UNKNOWN_IDENTIFIER")
+        );
+    }
+}
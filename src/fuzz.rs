@@ -0,0 +1,321 @@
+//! Byte-driven driver for fuzzing and property-testing the calculator.
+//!
+//! [`apply_bytes`] decodes an arbitrary byte string into a sequence of
+//! calculator calls and replays them against a plain shadow model, so any
+//! input a fuzzer produces can be checked for state-machine consistency.
+//! The `#[cfg(fuzzing)]` entrypoints are what `cargo fuzz` targets call.
+
+use crate::{nth_root_of, same_f64, Calculator};
+
+/// Shadow copy of the state the calculator is expected to be in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Model {
+    value: f64,
+    memory: f64,
+    history_len: usize,
+    history_limit: Option<usize>,
+}
+
+impl Model {
+    fn record(&mut self) {
+        self.history_len += 1;
+        if let Some(limit) = self.history_limit {
+            self.history_len = self.history_len.min(limit);
+        }
+    }
+}
+
+const OPCODES: u8 = 20;
+
+fn take_f64(data: &[u8], pos: &mut usize) -> Option<f64> {
+    let bytes = data.get(*pos..*pos + 8)?;
+    *pos += 8;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Some(f64::from_le_bytes(buf))
+}
+
+/// Decodes `data` into calculator calls, applies them to `calc`, and checks
+/// the result of every step against a shadow model.
+///
+/// Each step is one opcode byte, followed by an 8-byte little-endian `f64`
+/// operand for the operations that take one. Decoding stops at the first
+/// truncated operand. Returns a description of the first divergence, or of
+/// the first [`Calculator::check_invariants`] failure.
+pub fn apply_bytes(calc: &mut Calculator, data: &[u8]) -> Result<(), String> {
+    let mut model = Model {
+        value: calc.get_value(),
+        memory: calc.get_memory(),
+        history_len: calc.history_count(),
+        history_limit: match calc.get_history_limit() {
+            0 => None,
+            n => Some(n),
+        },
+    };
+    let mut pos = 0;
+    while pos < data.len() {
+        let opcode = data[pos] % OPCODES;
+        pos += 1;
+        match opcode {
+            0..=6 | 15 | 16 => {
+                let Some(v) = take_f64(data, &mut pos) else {
+                    break;
+                };
+                match opcode {
+                    0 => {
+                        model.value += v;
+                        model.record();
+                        calc.add(v);
+                    }
+                    1 => {
+                        model.value -= v;
+                        model.record();
+                        calc.subtract(v);
+                    }
+                    2 => {
+                        model.value *= v;
+                        model.record();
+                        calc.multiply(v);
+                    }
+                    3 => {
+                        let ok = calc.divide(v).is_ok();
+                        if ok != (v != 0.0) {
+                            return Err(format!("divide({}) returned ok={}", v, ok));
+                        }
+                        if ok {
+                            model.value /= v;
+                            model.record();
+                        }
+                    }
+                    4 => {
+                        model.value = crate::math::pow(model.value, v);
+                        model.record();
+                        calc.power(v);
+                    }
+                    5 => {
+                        model.value = v;
+                        model.record();
+                        calc.set_value(v);
+                    }
+                    15 => {
+                        let x = model.value;
+                        let real = v != 0.0
+                            && v.is_finite()
+                            && (x >= 0.0 || x.is_nan() || (v.fract() == 0.0 && v % 2.0 != 0.0));
+                        let ok = calc.nth_root(v).is_ok();
+                        if ok != real {
+                            return Err(format!("nth_root({}) of {} returned ok={}", v, x, ok));
+                        }
+                        if ok {
+                            // The root itself is the calculator's own; the
+                            // model checks when it may fail and what it records.
+                            model.value = nth_root_of(x, v).map_err(|e| e.to_string())?;
+                            model.record();
+                        }
+                    }
+                    16 => {
+                        let ok = calc.modulo(v).is_ok();
+                        if ok != (v != 0.0) {
+                            return Err(format!("modulo({}) returned ok={}", v, ok));
+                        }
+                        if ok {
+                            model.value %= v;
+                            model.record();
+                        }
+                    }
+                    _ => {
+                        // Large factorial inputs are valid fuzz data too.
+                        let n = v.abs().min(u32::MAX as f64) as u32;
+                        let ok = crate::factorial(n).is_ok();
+                        if ok != (n <= 20) {
+                            return Err(format!("factorial({}) returned ok={}", n, ok));
+                        }
+                    }
+                }
+            }
+            7 => {
+                let ok = calc.sqrt().is_ok();
+                if ok != (model.value >= 0.0 || model.value.is_nan()) {
+                    return Err(format!("sqrt of {} returned ok={}", model.value, ok));
+                }
+                if ok {
+                    model.value = model.value.sqrt();
+                    model.record();
+                }
+            }
+            8 => {
+                model.value = 0.0;
+                model.record();
+                calc.clear();
+            }
+            9 => {
+                model.memory = model.value;
+                model.record();
+                calc.memory_store();
+            }
+            10 => {
+                model.value = model.memory;
+                model.record();
+                calc.memory_recall();
+            }
+            11 => {
+                model.memory = 0.0;
+                model.record();
+                calc.memory_clear();
+            }
+            12 => {
+                model.memory += model.value;
+                model.record();
+                calc.memory_add();
+            }
+            13 => {
+                model.history_len = 0;
+                calc.clear_history();
+            }
+            14 => {
+                // Limit comes from the next byte; 0 lifts the cap.
+                let Some(&limit) = data.get(pos) else {
+                    break;
+                };
+                pos += 1;
+                model.history_limit = (limit != 0).then_some(limit as usize);
+                if limit != 0 {
+                    model.history_len = model.history_len.min(limit as usize);
+                }
+                calc.set_history_limit(limit as usize);
+            }
+            17 => {
+                let ok = calc.reciprocal().is_ok();
+                if ok != (model.value != 0.0) {
+                    return Err(format!("reciprocal of {} returned ok={}", model.value, ok));
+                }
+                if ok {
+                    model.value = 1.0 / model.value;
+                    model.record();
+                }
+            }
+            18 => {
+                model.value = model.value.abs();
+                model.record();
+                calc.abs();
+            }
+            _ => {
+                model.value = -model.value;
+                model.record();
+                calc.negate();
+            }
+        }
+
+        let actual = Model {
+            value: calc.get_value(),
+            memory: calc.get_memory(),
+            history_len: calc.history_count(),
+            history_limit: model.history_limit,
+        };
+        if !same_f64(actual.value, model.value)
+            || !same_f64(actual.memory, model.memory)
+            || actual.history_len != model.history_len
+        {
+            return Err(format!(
+                "after opcode {} state is {:?}, expected {:?}",
+                opcode, actual, model
+            ));
+        }
+    }
+    calc.check_invariants()
+}
+
+/// `cargo fuzz` entrypoint: runs arbitrary bytes against a fresh calculator.
+#[cfg(fuzzing)]
+pub fn fuzz_calculator(data: &[u8]) {
+    let mut calc = Calculator::new();
+    if let Err(violation) = apply_bytes(&mut calc, data) {
+        panic!("calculator invariant violated: {}", violation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(code: u8, value: f64) -> Vec<u8> {
+        let mut bytes = vec![code];
+        bytes.extend_from_slice(&value.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_apply_bytes_empty_input() {
+        let mut calc = Calculator::new();
+        assert_eq!(apply_bytes(&mut calc, &[]), Ok(()));
+        assert_eq!(calc.get_value(), 0.0);
+    }
+
+    #[test]
+    fn test_apply_bytes_decodes_operations() {
+        let mut calc = Calculator::new();
+        let mut data = op(5, 10.0); // set_value(10)
+        data.extend(op(0, 5.0)); // add 5
+        data.push(9); // memory_store
+        data.extend(op(3, 0.0)); // divide by zero, rejected
+        data.extend(op(2, 2.0)); // multiply 2
+        assert_eq!(apply_bytes(&mut calc, &data), Ok(()));
+        assert_eq!(calc.get_value(), 30.0);
+        assert_eq!(calc.get_memory(), 15.0);
+        assert_eq!(calc.history_count(), 4);
+    }
+
+    #[test]
+    fn test_apply_bytes_decodes_roots_and_unary_operations() {
+        let mut calc = Calculator::new();
+        let mut data = op(5, -27.0); // set_value(-27)
+        data.extend(op(15, 3.0)); // cube root, -3
+        data.extend(op(15, 2.0)); // even root of a negative, rejected
+        data.push(18); // abs, 3
+        data.extend(op(16, 2.0)); // mod 2, 1
+        data.extend(op(16, 0.0)); // mod 0, rejected
+        data.push(17); // reciprocal, 1
+        data.push(19); // negate, -1
+        data.push(8); // clear
+        data.push(17); // reciprocal of 0, rejected
+        assert_eq!(apply_bytes(&mut calc, &data), Ok(()));
+        assert_eq!(calc.get_value(), 0.0);
+        assert_eq!(calc.history_count(), 7);
+    }
+
+    #[test]
+    fn test_apply_bytes_history_limit() {
+        let mut calc = Calculator::new();
+        let mut data = vec![14, 2]; // set_history_limit(2)
+        for _ in 0..4 {
+            data.extend(op(0, 1.0));
+        }
+        assert_eq!(apply_bytes(&mut calc, &data), Ok(()));
+        assert_eq!(calc.history_count(), 2);
+    }
+
+    #[test]
+    fn test_apply_bytes_stops_at_truncated_operand() {
+        let mut calc = Calculator::new();
+        let data = [0u8, 1, 2, 3];
+        assert_eq!(apply_bytes(&mut calc, &data), Ok(()));
+        assert_eq!(calc.history_count(), 0);
+    }
+
+    #[test]
+    fn test_random_sequences_hold_invariants() {
+        // Small deterministic LCG so the property test needs no extra crates.
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..500 {
+            let mut data = Vec::with_capacity(128);
+            for _ in 0..128 {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                data.push((seed >> 56) as u8);
+            }
+            let mut calc = Calculator::new();
+            assert_eq!(apply_bytes(&mut calc, &data), Ok(()), "input {:?}", data);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Checking a submitted answer against the expected one.
+//!
+//! [`check_answer`] accepts any form of the right answer: `2*x+2` for
+//! `2*(x+1)`, `0.5` for `1/2`, `50 cm` for `0.5 m`. Options tighten that.
+//! `require_simplified` rejects answers that are right but unfinished, such
+//! as `6/4`, `2+3` or `((x))`. `units` evaluates both sides as quantities
+//! (see `units`), so a missing unit or one of the wrong dimension is its
+//! own verdict; `same_unit` also demands the expected unit.
+//!
+//! A problem with the expected answer is an error, since it is the
+//! question that is wrong. A problem with the submission, even a syntax
+//! error, is a [`Verdict`] that says what is wrong with it.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::equivalence::{compare_expressions, within};
+use crate::expr::{parse, BinaryOp, Dialect, Expr, UnaryOp, CONSTANTS};
+use crate::lint::{lint_expression, LintKind};
+use crate::units::{evaluate_quantity, Quantity};
+use crate::variables::collect_dependencies;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// How strictly [`check_answer`] grades. As JSON every field is optional:
+/// `{ tolerance: 1e-6, require_simplified: true }`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnswerOptions {
+    /// Relative once the expected value exceeds 1, absolute below; see
+    /// `equivalence`. Default 1e-9.
+    pub tolerance: f64,
+    /// Whether a right answer must also be in simplest form.
+    pub require_simplified: bool,
+    /// Whether both sides are quantities with units.
+    pub units: bool,
+    /// Whether the answer must be in the expected unit, not just one of the
+    /// same dimension. Implies `units`.
+    pub same_unit: bool,
+}
+
+impl Default for AnswerOptions {
+    fn default() -> Self {
+        AnswerOptions {
+            tolerance: 1e-9,
+            require_simplified: false,
+            units: false,
+            same_unit: false,
+        }
+    }
+}
+
+/// Why an answer was marked wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerIssue {
+    /// The answer could not be evaluated.
+    Unparseable,
+    /// The answer has the wrong value.
+    Value,
+    NotSimplified,
+    MissingUnit,
+    WrongDimension,
+    /// The right amount in another unit, with `same_unit`.
+    WrongUnit,
+}
+
+/// The outcome of [`check_answer`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Verdict {
+    pub correct: bool,
+    /// Whether the answer has the expected value, whatever its form.
+    pub equivalent: bool,
+    pub issue: Option<AnswerIssue>,
+    /// Feedback for the student.
+    pub message: String,
+}
+
+impl Verdict {
+    fn correct() -> Verdict {
+        Verdict {
+            correct: true,
+            equivalent: true,
+            issue: None,
+            message: "Correct".to_string(),
+        }
+    }
+
+    fn wrong(issue: AnswerIssue, equivalent: bool, message: impl Into<String>) -> Verdict {
+        Verdict {
+            correct: false,
+            equivalent,
+            issue: Some(issue),
+            message: message.into(),
+        }
+    }
+}
+
+/// Grades `submitted` against `expected` as described in the module docs.
+pub fn check_answer(
+    expected: &str,
+    submitted: &str,
+    options: &AnswerOptions,
+) -> Result<Verdict, CalcError> {
+    let verdict = if options.units || options.same_unit {
+        check_quantity(expected, submitted, options)?
+    } else {
+        // Fails for the expected answer alone, before blaming the answer.
+        compare_expressions(expected, expected, options.tolerance)?;
+        match compare_expressions(expected, submitted, options.tolerance) {
+            Err(e) => Verdict::wrong(AnswerIssue::Unparseable, false, e.to_string()),
+            Ok(comparison) if !comparison.equal => {
+                Verdict::wrong(AnswerIssue::Value, false, "Not the expected answer")
+            }
+            Ok(_) => Verdict::correct(),
+        }
+    };
+    if verdict.correct && options.require_simplified && !is_simplified(submitted, options) {
+        return Ok(Verdict::wrong(
+            AnswerIssue::NotSimplified,
+            true,
+            "Correct, but not in simplest form",
+        ));
+    }
+    Ok(verdict)
+}
+
+fn check_quantity(
+    expected: &str,
+    submitted: &str,
+    options: &AnswerOptions,
+) -> Result<Verdict, CalcError> {
+    if !options.tolerance.is_finite() || options.tolerance < 0.0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "Tolerance must be a non-negative number, got {}",
+            options.tolerance
+        )));
+    }
+    let want = evaluate_quantity(expected)?;
+    let got = match evaluate_quantity(submitted) {
+        Ok(got) => got,
+        Err(e) => {
+            return Ok(Verdict::wrong(
+                AnswerIssue::Unparseable,
+                false,
+                e.to_string(),
+            ))
+        }
+    };
+    if got.unit().is_empty() && !want.unit().is_empty() {
+        return Ok(Verdict::wrong(
+            AnswerIssue::MissingUnit,
+            false,
+            format!("The answer needs a unit, such as {}", want.unit()),
+        ));
+    }
+    if !want.same_dimension(&got) {
+        return Ok(Verdict::wrong(
+            AnswerIssue::WrongDimension,
+            false,
+            format!("{} cannot be converted to {}", got.unit(), want.unit()),
+        ));
+    }
+    let converted = got.convert_to(&want.unit())?;
+    if !within(want.value(), converted.value(), options.tolerance) {
+        return Ok(Verdict::wrong(
+            AnswerIssue::Value,
+            false,
+            "Not the expected answer",
+        ));
+    }
+    if options.same_unit && got.unit() != want.unit() {
+        return Ok(Verdict::wrong(
+            AnswerIssue::WrongUnit,
+            true,
+            format!("Give the answer in {}", want.unit()),
+        ));
+    }
+    Ok(Verdict::correct())
+}
+
+// Whether a right answer is finished: nothing the linter would strip or
+// fold, and a constant answer is a number, a multiple of a constant or a
+// fraction in lowest terms. With units, it is a single number and unit.
+fn is_simplified(submitted: &str, options: &AnswerOptions) -> bool {
+    if options.units || options.same_unit {
+        return Quantity::parse(submitted).is_ok();
+    }
+    let (Ok(warnings), Ok(expr)) = (
+        lint_expression(submitted),
+        parse(submitted, Dialect::Standard),
+    ) else {
+        return false;
+    };
+    if warnings.iter().any(|w| {
+        matches!(
+            w.kind,
+            LintKind::RedundantParentheses | LintKind::ConstantSubexpression
+        )
+    }) {
+        return false;
+    }
+    let mut variables = BTreeSet::new();
+    collect_dependencies(&expr, &mut variables);
+    !variables.is_empty() || is_final_constant(&expr)
+}
+
+fn is_final_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) => true,
+        Expr::Variable(name) => CONSTANTS.iter().any(|(c, _)| c == name),
+        Expr::Unary(UnaryOp::Neg, inner) => is_final_constant(inner),
+        Expr::Binary(BinaryOp::Mul, lhs, rhs) => {
+            matches!(lhs.as_ref(), Expr::Number(_))
+                && matches!(rhs.as_ref(), Expr::Variable(_))
+                && is_final_constant(rhs)
+        }
+        Expr::Binary(BinaryOp::Div, lhs, rhs) => match (integer(lhs), integer(rhs)) {
+            (Some(a), Some(b)) => b > 1.0 && gcd(a.abs(), b) == 1.0,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// The whole number `expr` is written as, if it is one.
+fn integer(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Number(n) if n.fract() == 0.0 => Some(*n),
+        Expr::Unary(UnaryOp::Neg, inner) => integer(inner).map(|n| -n),
+        _ => None,
+    }
+}
+
+fn gcd(mut a: f64, mut b: f64) -> f64 {
+    while b != 0.0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// `options` is an optional `{ tolerance, require_simplified, units,
+/// same_unit }`. Returns `{ correct, equivalent, issue, message }`, where
+/// `issue` is `null` or one of `"unparseable"`, `"value"`,
+/// `"not_simplified"`, `"missing_unit"`, `"wrong_dimension"` and
+/// `"wrong_unit"`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = check_answer)]
+pub fn check_answer_js(
+    expected: &str,
+    submitted: &str,
+    options: JsValue,
+) -> Result<JsValue, CalcErrorJs> {
+    let options = if options.is_undefined() || options.is_null() {
+        AnswerOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).map_err(|e| {
+            CalcErrorJs::from(CalcError::InvalidArgument(format!(
+                "Invalid answer options: {}",
+                e
+            )))
+        })?
+    };
+    let verdict = check_answer(expected, submitted, &options).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&verdict).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(expected: &str, submitted: &str, options: &AnswerOptions) -> Verdict {
+        check_answer(expected, submitted, options).unwrap()
+    }
+
+    #[test]
+    fn test_any_equivalent_form_is_correct() {
+        let options = AnswerOptions::default();
+        assert!(check("2*(x+1)", "2*x + 2", &options).correct);
+        assert!(check("1/2", "0.5", &options).correct);
+        assert!(check("6/4", "3/2", &options).correct);
+
+        let wrong = check("2*(x+1)", "2*x + 1", &options);
+        assert_eq!(wrong.issue, Some(AnswerIssue::Value));
+        assert!(!wrong.equivalent);
+        let garbled = check("2", "2 +", &options);
+        assert_eq!(garbled.issue, Some(AnswerIssue::Unparseable));
+        // A broken question is an error, not a wrong answer.
+        assert!(check_answer("2 +", "2", &options).is_err());
+        assert!(check_answer(
+            "2",
+            "2",
+            &AnswerOptions {
+                tolerance: -1.0,
+                ..Default::default()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_require_simplified() {
+        let options = AnswerOptions {
+            require_simplified: true,
+            ..Default::default()
+        };
+        for answer in ["3/2", "1.5"] {
+            assert!(check("6/4", answer, &options).correct, "{}", answer);
+        }
+        assert!(check("-6/4", "-3/2", &options).correct);
+        for answer in ["6/4", "1 + 1/2", "(3/2)", "3/2 * 1"] {
+            let verdict = check("6/4", answer, &options);
+            assert_eq!(
+                verdict.issue,
+                Some(AnswerIssue::NotSimplified),
+                "{}",
+                answer
+            );
+            assert!(verdict.equivalent);
+        }
+        assert!(check("pi*2", "2*pi", &options).correct);
+        assert!(check("2*(x+1)", "2*x + 2", &options).correct);
+        assert_eq!(
+            check("x", "((x))", &options).issue,
+            Some(AnswerIssue::NotSimplified)
+        );
+        // The value is checked first.
+        assert_eq!(
+            check("6/4", "7/4", &options).issue,
+            Some(AnswerIssue::Value)
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        let options = AnswerOptions {
+            units: true,
+            ..Default::default()
+        };
+        assert!(check("0.5 m", "50 cm", &options).correct);
+        assert!(check("2 m * 3 m", "6 m^2", &options).correct);
+        let issue = |expected, submitted, options| check(expected, submitted, options).issue;
+        assert_eq!(
+            issue("0.5 m", "0.5", &options),
+            Some(AnswerIssue::MissingUnit)
+        );
+        assert_eq!(
+            issue("0.5 m", "0.5 s", &options),
+            Some(AnswerIssue::WrongDimension)
+        );
+        assert_eq!(issue("0.5 m", "5 cm", &options), Some(AnswerIssue::Value));
+        assert_eq!(
+            issue("0.5 m", "5 furlong", &options),
+            Some(AnswerIssue::Unparseable)
+        );
+
+        let strict = AnswerOptions {
+            same_unit: true,
+            require_simplified: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            issue("0.5 m", "50 cm", &strict),
+            Some(AnswerIssue::WrongUnit)
+        );
+        assert!(check("0.5 m", "0.5 m", &strict).correct);
+        assert_eq!(
+            issue("0.5 m", "0.25 m + 0.25 m", &strict),
+            Some(AnswerIssue::NotSimplified)
+        );
+    }
+}
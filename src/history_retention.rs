@@ -0,0 +1,140 @@
+//! Dropping history entries by age.
+//!
+//! Besides the count limit of `set_history_limit`, a calculator on a shared
+//! kiosk can keep entries for a limited time only:
+//! `expire_history_older_than(seconds)` sets the age, by entry timestamp,
+//! past which entries are dropped. There is no timer; expiry is evaluated
+//! lazily, when the age is set, whenever an entry is recorded and when
+//! [`Calculator::prune_expired_history`] is called, which a kiosk can do
+//! before showing the history or when it goes idle.
+//!
+//! Expired entries are usually the oldest, and dropping them keeps the
+//! entry numbering of `history_sync` like the count limit does. Entries
+//! restored out of timestamp order are dropped too, which starts the
+//! numbering over.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{now_ms, CalcError, Calculator};
+
+impl Calculator {
+    /// Drops entries recorded more than `seconds` ago, now and from then
+    /// on. 0 removes the age limit.
+    pub fn expire_history_older_than(&mut self, seconds: f64) -> Result<(), CalcError> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(CalcError::InvalidArgument(format!(
+                "History age must be a non-negative number of seconds, got {}",
+                seconds
+            )));
+        }
+        self.history_max_age_ms = (seconds > 0.0).then_some(seconds * 1000.0);
+        self.expire_history_at(now_ms());
+        Ok(())
+    }
+
+    // Drops entries older than the age limit as of `now` (ms since the
+    // epoch), returning how many.
+    pub(crate) fn expire_history_at(&mut self, now: f64) -> usize {
+        let Some(max_age) = self.history_max_age_ms else {
+            return 0;
+        };
+        let expired = |timestamp: f64| now - timestamp > max_age;
+        let before = self.history.len();
+        while self.history.front().is_some_and(|e| expired(e.timestamp)) {
+            self.history.pop_front();
+            self.history_evicted += 1;
+        }
+        if self.history.iter().any(|e| expired(e.timestamp)) {
+            self.history.retain(|e| !expired(e.timestamp));
+            self.history_rewritten();
+        }
+        let dropped = before - self.history.len();
+        if dropped > 0 {
+            self.log_limit("history_max_age", max_age / 1000.0, Some(dropped));
+        }
+        dropped
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = expire_history_older_than)]
+    pub fn expire_history_older_than_js(&mut self, seconds: f64) -> Result<(), CalcErrorJs> {
+        self.expire_history_older_than(seconds)
+            .map_err(|e| CalcErrorJs::new(e, Some(seconds)))
+    }
+
+    /// The age limit in seconds, or 0 when entries do not expire.
+    pub fn history_max_age_seconds(&self) -> f64 {
+        self.history_max_age_ms.map_or(0.0, |ms| ms / 1000.0)
+    }
+
+    /// Drops the entries that have expired since the last check, returning
+    /// how many.
+    pub fn prune_expired_history(&mut self) -> usize {
+        self.expire_history_at(now_ms())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CalculationHistory, Operation};
+
+    fn entry(timestamp: f64) -> CalculationHistory {
+        CalculationHistory {
+            operand1: 0.0,
+            operand2: Some(1.0),
+            operation: Operation::Add,
+            result: 1.0,
+            timestamp,
+            duration_ms: None,
+            operand_source: Default::default(),
+            author: None,
+        }
+    }
+
+    #[test]
+    fn test_expire_history_older_than() {
+        let mut calc = Calculator::new();
+        let now = now_ms();
+        calc.history
+            .extend([now - 120_000.0, now - 90_000.0, now - 10_000.0].map(entry));
+        assert_eq!(calc.history_max_age_seconds(), 0.0);
+        calc.expire_history_older_than(60.0).unwrap();
+        assert_eq!(calc.history_max_age_seconds(), 60.0);
+        assert_eq!(calc.history_count(), 1);
+        // The dropped entries count as evicted, like the count limit.
+        assert_eq!(calc.history_next_index(), 3);
+        // Lazily: entries expire when checked, not on a timer.
+        assert_eq!(calc.expire_history_at(now + 49_000.0), 0);
+        assert_eq!(calc.expire_history_at(now + 51_000.0), 1);
+        assert_eq!(calc.history_count(), 0);
+        assert!(calc.expire_history_older_than(-1.0).is_err());
+        assert!(calc.expire_history_older_than(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_expiry_on_record_and_out_of_order() {
+        let mut calc = Calculator::new();
+        calc.expire_history_older_than(60.0).unwrap();
+        let now = now_ms();
+        calc.history.push_back(entry(now - 120_000.0));
+        calc.add(2.0);
+        assert_eq!(calc.history_count(), 1);
+        // A restored entry behind a newer one still expires.
+        calc.history.push_back(entry(now - 120_000.0));
+        let version = calc.history_version();
+        assert_eq!(calc.prune_expired_history(), 1);
+        assert_ne!(calc.history_version(), version);
+        assert_eq!(calc.history_series(), [2.0]);
+        // 0 turns expiry off.
+        calc.expire_history_older_than(0.0).unwrap();
+        calc.history.push_back(entry(0.0));
+        assert_eq!(calc.prune_expired_history(), 0);
+    }
+}
@@ -0,0 +1,140 @@
+//! Totals and filters over the recorded history.
+//!
+//! Two ways of summing a history:
+//!
+//! - [`Calculator::history_sum`] and [`Calculator::history_average`] work
+//!   on the results, as [`Calculator::history_series`] lists them. Memory
+//!   operations are left out, since their result is the memory.
+//! - [`Calculator::tape_total`] reads the history as an adding-machine tape:
+//!   amounts entered with `add` or `set_value` count up, those entered with
+//!   `subtract` count down, and `clear` starts the total over. Everything else
+//!   (multiplying, percentages, memory) changes the value but enters no
+//!   amount, so it is skipped. For `set_value(100); add(20); subtract(5)` the
+//!   tape total is 115 while the results sum to 335.
+//!
+//! All of them cover the retained history only; entries evicted by the
+//! history limit or `clear_history` no longer count.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::tape::parse_operation;
+#[cfg(feature = "wasm")]
+use crate::{CalcError, CalcErrorJs};
+use crate::{CalculationHistory, Calculator, Operation};
+
+impl Calculator {
+    fn value_results(&self) -> impl Iterator<Item = f64> + '_ {
+        self.history
+            .iter()
+            .filter(|e| !e.operation.affects_memory() && e.operation != Operation::MemoryRecall)
+            .map(|e| e.result)
+    }
+
+    /// The entries recorded for `operation`, oldest first.
+    pub fn history_filtered(&self, operation: &Operation) -> Vec<CalculationHistory> {
+        self.history
+            .iter()
+            .filter(|e| e.operation == *operation)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// The sum of the results of every value operation in the history; 0
+    /// when there are none.
+    pub fn history_sum(&self) -> f64 {
+        self.value_results().sum()
+    }
+
+    /// The mean of the results `history_sum` adds up, or `None` (`undefined`)
+    /// when there are none.
+    pub fn history_average(&self) -> Option<f64> {
+        let (count, sum) = self
+            .value_results()
+            .fold((0usize, 0.0), |(count, sum), r| (count + 1, sum + r));
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    /// The adding-machine total of the amounts entered since the last
+    /// `clear`; see the module docs.
+    pub fn tape_total(&self) -> f64 {
+        let since_clear = self
+            .history
+            .iter()
+            .rposition(|e| e.operation == Operation::Clear)
+            .map_or(0, |i| i + 1);
+        self.history
+            .range(since_clear..)
+            .map(|e| match (&e.operation, e.operand2) {
+                (Operation::Add | Operation::SetValue, Some(amount)) => amount,
+                (Operation::Subtract, Some(amount)) => -amount,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    /// The entries for one operation, by name or symbol as `apply_batch`
+    /// reads them (`"add"`, `"+"`, `"memory_store"`, `"function:tip"`), in
+    /// the `get_history()` shape.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_history_filtered)]
+    pub fn get_history_filtered_js(&self, operation: &str) -> Result<JsValue, CalcErrorJs> {
+        let Some(operation) = parse_operation(operation) else {
+            let err = CalcError::InvalidArgument(format!("Unknown operation: {}", operation));
+            return Err(self.fail(err, None).into());
+        };
+        let entries = self.history_filtered(&operation);
+        Ok(serde_wasm_bindgen::to_value(&entries).unwrap_or(JsValue::NULL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_sum_and_average() {
+        let mut calc = Calculator::new();
+        assert_eq!((calc.history_sum(), calc.history_average()), (0.0, None));
+        calc.set_value(100.0);
+        calc.add(20.0);
+        calc.memory_store();
+        calc.subtract(5.0);
+        calc.memory_recall();
+        assert_eq!(calc.history_sum(), 335.0);
+        assert_eq!(calc.history_average(), Some(335.0 / 3.0));
+    }
+
+    #[test]
+    fn test_tape_total() {
+        let mut calc = Calculator::new();
+        calc.add(12.5);
+        calc.add(7.5);
+        calc.subtract(5.0);
+        calc.multiply(2.0);
+        calc.add_percent(10.0);
+        calc.memory_add();
+        assert_eq!(calc.tape_total(), 15.0);
+        calc.clear();
+        assert_eq!(calc.tape_total(), 0.0);
+        calc.set_value(40.0);
+        calc.subtract(0.25);
+        assert_eq!(calc.tape_total(), 39.75);
+    }
+
+    #[test]
+    fn test_history_filtered() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.multiply(3.0);
+        calc.add(2.0);
+        let adds = calc.history_filtered(&Operation::Add);
+        assert_eq!(adds.len(), 2);
+        assert_eq!(adds[1].operand2, Some(2.0));
+        assert!(calc.history_filtered(&Operation::Divide).is_empty());
+    }
+}
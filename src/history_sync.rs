@@ -0,0 +1,112 @@
+//! Fetching only the history entries a UI has not seen yet.
+//!
+//! `get_history` serializes every entry, which is O(n) per call; a tape
+//! view that refreshes after each operation pays that again and again once
+//! the history holds a few thousand entries. Instead, a view can keep the
+//! entries it has and ask for the rest:
+//!
+//! - Entries are numbered in the order they are recorded, and keep their
+//!   numbers as the history limit evicts older ones, so
+//!   `get_history_since(n)` returns entries `n` onwards and
+//!   [`Calculator::history_next_index`] is the `n` to ask for next time.
+//! - [`Calculator::history_version`] changes when the numbering starts
+//!   over: when recorded entries are cleared, deduplicated, replayed,
+//!   edited or otherwise rewritten. A view that sees a new version
+//!   fetches from 0 again.
+//!
+//! Appending one entry and fetching it serializes that entry alone.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CalculationHistory, Calculator};
+
+impl Calculator {
+    /// Starts the entry numbering over after entries were removed or
+    /// rewritten.
+    pub(crate) fn history_rewritten(&mut self) {
+        self.history_version = self.history_version.wrapping_add(1);
+        self.history_evicted = 0;
+    }
+
+    /// Entries numbered `index` onwards, oldest first. Entries the limit
+    /// has already evicted are skipped.
+    pub fn history_since(&self, index: usize) -> Vec<CalculationHistory> {
+        let start = index.saturating_sub(self.history_evicted);
+        self.history_range(start, self.history.len())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Changes whenever the entry numbering starts over; see
+    /// `get_history_since`.
+    pub fn history_version(&self) -> u32 {
+        self.history_version
+    }
+
+    /// The number the next recorded entry will get.
+    pub fn history_next_index(&self) -> usize {
+        self.history_evicted + self.history.len()
+    }
+
+    /// Serializes entries numbered `index` onwards; see
+    /// [`Calculator::history_since`].
+    #[cfg(feature = "wasm")]
+    pub fn get_history_since(&self, index: usize) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.history_since(index)).unwrap_or(JsValue::NULL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(entries: &[CalculationHistory]) -> Vec<f64> {
+        entries.iter().map(|e| e.result).collect()
+    }
+
+    #[test]
+    fn test_incremental_fetch() {
+        let mut calc = Calculator::new();
+        let version = calc.history_version();
+        calc.add(1.0);
+        calc.add(2.0);
+        assert_eq!(results(&calc.history_since(0)), vec![1.0, 3.0]);
+        let next = calc.history_next_index();
+        assert_eq!(next, 2);
+        calc.add(3.0);
+        assert_eq!(results(&calc.history_since(next)), vec![6.0]);
+        assert!(calc.history_since(3).is_empty());
+        assert!(calc.history_since(usize::MAX).is_empty());
+
+        // Eviction keeps the numbering.
+        calc.set_history_limit(2);
+        calc.add(4.0);
+        assert_eq!(calc.history_version(), version);
+        assert_eq!(calc.history_next_index(), 4);
+        assert_eq!(results(&calc.history_since(3)), vec![10.0]);
+        assert_eq!(results(&calc.history_since(0)), vec![6.0, 10.0]);
+    }
+
+    #[test]
+    fn test_rewrites_change_the_version() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(2);
+        calc.add(1.0);
+        calc.add(1.0);
+        calc.add(1.0);
+        let version = calc.history_version();
+        calc.replay_history(0.0).unwrap();
+        assert_ne!(calc.history_version(), version);
+        assert_eq!(calc.history_next_index(), 2);
+        assert_eq!(results(&calc.history_since(0)), vec![1.0, 2.0]);
+
+        let version = calc.history_version();
+        assert_eq!(calc.dedupe_history(), 0);
+        assert_eq!(calc.history_version(), version);
+        calc.clear_history();
+        assert_ne!(calc.history_version(), version);
+        assert_eq!(calc.history_next_index(), 0);
+    }
+}
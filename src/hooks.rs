@@ -0,0 +1,238 @@
+//! App-supplied hooks that rewrite what the calculator reads and shows.
+//!
+//! An input hook sees the raw text passed to `evaluate` before anything
+//! else does, ahead of aliases, the input locale and tokenization, and
+//! returns the text to evaluate instead. Apps use it to expand their own
+//! shorthand (`2k` to `2000`, `tip` to `* 0.18`) without forking the
+//! parser. [`Calculator::evaluate_detailed`] says whether the hook
+//! rewrote the input and what was evaluated; syntax error positions point
+//! into that rewritten text.
+//!
+//! A format hook takes over `format_value`: it gets the value and the
+//! format options and returns the display string, or nothing to fall back
+//! to the built-in formatting, so an app can show values its own way
+//! (fractions of an inch, a custom currency style) for some or all values.
+//! Education mode's decimal limit still applies to what it is given.
+//!
+//! Hooks are registered alongside the calculator's listeners, in the
+//! registry `events` describes.
+
+use std::rc::Rc;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::format::FormatOptions;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+pub(crate) type InputHook = Rc<dyn Fn(&str) -> Result<String, CalcError>>;
+pub(crate) type FormatHook = Rc<dyn Fn(f64, &FormatOptions) -> Result<Option<String>, CalcError>>;
+
+/// The result of [`Calculator::evaluate_detailed`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Evaluation {
+    pub value: f64,
+    /// Whether the input hook changed the input.
+    pub rewritten: bool,
+    /// The text that was evaluated, as the input hook returned it.
+    pub evaluated: String,
+}
+
+impl Calculator {
+    /// Rewrites the input of every `evaluate` through `hook` first. A hook
+    /// error fails the evaluation.
+    pub fn on_input(&mut self, hook: impl Fn(&str) -> Result<String, CalcError> + 'static) {
+        self.listeners_mut(|l| l.input = Some(Rc::new(hook)));
+    }
+
+    /// Formats values for `format_value` through `hook`, which returns
+    /// `None` to leave a value to the built-in formatting.
+    pub fn on_format(
+        &mut self,
+        hook: impl Fn(f64, &FormatOptions) -> Result<Option<String>, CalcError> + 'static,
+    ) {
+        self.listeners_mut(|l| l.format = Some(Rc::new(hook)));
+    }
+
+    fn input_hook(&self) -> Option<InputHook> {
+        self.listeners()?.input
+    }
+
+    // What the format hook makes of `value`, if there is one and it takes
+    // the value.
+    pub(crate) fn hook_format(
+        &self,
+        value: f64,
+        options: &FormatOptions,
+    ) -> Result<Option<String>, CalcError> {
+        match self.listeners().and_then(|l| l.format) {
+            Some(hook) => hook(value, options),
+            None => Ok(None),
+        }
+    }
+
+    /// Evaluates `src` like [`Calculator::evaluate`], also reporting what
+    /// the input hook made of it.
+    pub fn evaluate_detailed(&mut self, src: &str) -> Result<Evaluation, CalcError> {
+        if self.starts_timing() {
+            return self.timed("evaluate", |calc| calc.evaluate_detailed(src));
+        }
+        self.permit_name("evaluate")?;
+        let evaluated = match self.input_hook() {
+            Some(hook) => hook(src).map_err(|e| self.fail(e, None))?,
+            None => src.to_string(),
+        };
+        let value = self.evaluate_source(&evaluated)?;
+        Ok(Evaluation {
+            value,
+            rewritten: evaluated != src,
+            evaluated,
+        })
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn hook_threw(hook: &str, exception: JsValue) -> CalcError {
+    CalcError::InvalidArgument(format!(
+        "{} hook threw: {}",
+        hook,
+        exception
+            .as_string()
+            .unwrap_or_else(|| format!("{:?}", exception))
+    ))
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Calls `f(input)` before each evaluation and evaluates the string it
+    /// returns; `undefined` or `null` leaves the input as it was. A thrown
+    /// exception or any other return value fails the evaluation.
+    #[cfg(feature = "wasm")]
+    pub fn set_input_hook(&mut self, f: &js_sys::Function) {
+        let f = f.clone();
+        self.on_input(move |src| {
+            let returned = f
+                .call1(&JsValue::NULL, &src.into())
+                .map_err(|e| hook_threw("Input", e))?;
+            if returned.is_undefined() || returned.is_null() {
+                return Ok(src.to_string());
+            }
+            returned.as_string().ok_or_else(|| {
+                CalcError::InvalidArgument("Input hook must return a string".to_string())
+            })
+        });
+    }
+
+    /// Removes the input hook from this calculator.
+    pub fn clear_input_hook(&mut self) {
+        self.clear_registered(|l| l.input = None);
+    }
+
+    /// Calls `f(value, options)` from `format_value` and shows the string
+    /// it returns; `undefined` or `null` falls back to the built-in
+    /// formatting. `options` is shaped like `format_value`'s, with the
+    /// locale and defaults filled in. A thrown exception or any other
+    /// return value fails the call.
+    #[cfg(feature = "wasm")]
+    pub fn set_format_hook(&mut self, f: &js_sys::Function) {
+        let f = f.clone();
+        self.on_format(move |value, options| {
+            let options = serde_wasm_bindgen::to_value(options).unwrap_or(JsValue::NULL);
+            let returned = f
+                .call2(&JsValue::NULL, &value.into(), &options)
+                .map_err(|e| hook_threw("Format", e))?;
+            if returned.is_undefined() || returned.is_null() {
+                return Ok(None);
+            }
+            returned.as_string().map(Some).ok_or_else(|| {
+                CalcError::InvalidArgument("Format hook must return a string".to_string())
+            })
+        });
+    }
+
+    /// Removes the format hook from this calculator.
+    pub fn clear_format_hook(&mut self) {
+        self.clear_registered(|l| l.format = None);
+    }
+
+    /// `evaluate`, returning `{ value, rewritten, evaluated }`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = evaluate_detailed)]
+    pub fn evaluate_detailed_js(&mut self, src: &str) -> Result<JsValue, CalcErrorJs> {
+        let evaluation = self.evaluate_detailed(src).map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&evaluation).unwrap_or(JsValue::NULL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_hook_rewrites_before_parsing() {
+        let mut calc = Calculator::new();
+        calc.add_common_aliases();
+        calc.on_input(|src| Ok(src.replace('k', "*1000")));
+        // The hook runs before aliases.
+        let evaluation = calc.evaluate_detailed("2k × 3").unwrap();
+        assert_eq!(evaluation.value, 6000.0);
+        assert!(evaluation.rewritten);
+        assert_eq!(evaluation.evaluated, "2*1000 × 3");
+        assert_eq!(calc.get_value(), 6000.0);
+        assert_eq!(calc.evaluate("1 + 1"), Ok(2.0));
+        assert!(!calc.evaluate_detailed("1 + 1").unwrap().rewritten);
+        // Clones start without one.
+        assert!(calc.clone().evaluate("1k").is_err());
+
+        // Hooks are not listeners.
+        calc.clear_listeners();
+        assert_eq!(calc.evaluate("1k"), Ok(1000.0));
+        calc.clear_input_hook();
+        assert!(calc.evaluate("1k").is_err());
+    }
+
+    #[test]
+    fn test_format_hook_falls_back() {
+        let mut calc = Calculator::new();
+        calc.set_value(0.375);
+        calc.on_format(|value, options| {
+            Ok((value.fract() != 0.0 && options.decimals.is_none())
+                .then(|| format!("{}/8", value * 8.0)))
+        });
+        let options = FormatOptions::default();
+        assert_eq!(calc.format_value(&options).unwrap(), "3/8");
+        let fixed = FormatOptions {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(calc.format_value(&fixed).unwrap(), "0.38");
+        calc.set_value(2.0);
+        assert_eq!(calc.format_value(&options).unwrap(), "2");
+
+        calc.on_format(|_, _| Err(CalcError::InvalidArgument("broken".to_string())));
+        assert!(calc.format_value(&options).is_err());
+        calc.clear_format_hook();
+        assert_eq!(calc.format_value(&options).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_input_hook_errors() {
+        let mut calc = Calculator::new();
+        calc.on_input(|src| {
+            if src.contains('?') {
+                Err(CalcError::InvalidArgument("no questions".to_string()))
+            } else {
+                Ok(src.to_string())
+            }
+        });
+        assert_eq!(
+            calc.evaluate("1 ?"),
+            Err(CalcError::InvalidArgument("no questions".to_string()))
+        );
+        assert_eq!(calc.error_log_count(), 1);
+        assert!(!calc.evaluate_detailed("3").unwrap().rewritten);
+    }
+}
@@ -0,0 +1,198 @@
+//! As-you-type evaluation.
+//!
+//! [`InputSession`] holds the expression being typed and its tokens. Edits
+//! happen at the end of the text, so only the last token (which the edit
+//! may extend or cut short) and whatever follows it are lexed again; every
+//! earlier token is reused. Results are computed leniently, as
+//! [`evaluate_lenient`](crate::expr::evaluate_lenient) does, so `2 * (3 +`
+//! previews as 6.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{evaluate_lenient_tokens, tokenize, Dialect, LenientResult, Spanned};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Default)]
+pub struct InputSession {
+    text: Vec<char>,
+    // Tokens of a prefix of `text`: all of it unless `lex_error` is set.
+    tokens: Vec<Spanned>,
+    // Why the text after the last token did not lex.
+    lex_error: Option<CalcError>,
+    // Characters lexed by the most recent edit.
+    relexed: usize,
+}
+
+impl InputSession {
+    // Re-lexes after the text changed from character `edit` on. A token
+    // ending at `edit` is dropped too, since `1` followed by `2` is the
+    // single token `12`.
+    fn relex(&mut self, edit: usize) {
+        while self.tokens.last().is_some_and(|t| t.end >= edit) {
+            self.tokens.pop();
+        }
+        let resume = self.tokens.last().map_or(0, |t| t.end);
+        let rest: String = self.text[resume..].iter().collect();
+        self.relexed = self.text.len() - resume;
+        self.lex_error = match tokenize(&rest, Dialect::Standard) {
+            Ok(tokens) => {
+                self.tokens.extend(tokens.into_iter().map(|t| Spanned {
+                    start: t.start + resume,
+                    end: t.end + resume,
+                    ..t
+                }));
+                None
+            }
+            Err(CalcError::Syntax { message, position }) => Some(CalcError::Syntax {
+                message,
+                position: position + resume,
+            }),
+            Err(other) => Some(other),
+        };
+    }
+
+    /// The current text's value, or why it has none yet.
+    pub fn result(&self) -> Result<LenientResult, CalcError> {
+        if let Some(err) = &self.lex_error {
+            return Err(err.clone());
+        }
+        evaluate_lenient_tokens(self.tokens.clone(), &self.text)
+    }
+
+    pub fn tokens(&self) -> &[Spanned] {
+        &self.tokens
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl InputSession {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> InputSession {
+        InputSession::default()
+    }
+
+    /// Appends typed or pasted text.
+    pub fn push_str(&mut self, text: &str) {
+        let edit = self.text.len();
+        self.text.extend(text.chars());
+        self.relex(edit);
+    }
+
+    /// Removes the last character; returns whether there was one.
+    pub fn backspace(&mut self) -> bool {
+        if self.text.pop().is_none() {
+            return false;
+        }
+        self.relex(self.text.len());
+        true
+    }
+
+    /// Replaces the whole text, lexing it from scratch.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.tokens.clear();
+        self.relex(0);
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    /// How many characters the last edit lexed again.
+    pub fn relexed_chars(&self) -> usize {
+        self.relexed
+    }
+
+    /// The preview value; throws while the text has none yet.
+    #[cfg(feature = "wasm")]
+    pub fn value(&self) -> Result<f64, CalcErrorJs> {
+        self.result().map(|r| r.value).map_err(CalcErrorJs::from)
+    }
+
+    /// `{ value, fixes }` as returned by `evaluate_lenient`.
+    #[cfg(feature = "wasm")]
+    pub fn evaluate(&self) -> Result<JsValue, CalcErrorJs> {
+        let result = self.result().map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::evaluate_lenient;
+
+    fn type_out(session: &mut InputSession, text: &str) {
+        for c in text.chars() {
+            session.push_str(&c.to_string());
+        }
+    }
+
+    #[test]
+    fn test_keystrokes_match_whole_text() {
+        let mut session = InputSession::new();
+        let text = "max(12.5, 3) * (2 + sqrt(16";
+        for (i, c) in text.chars().enumerate() {
+            session.push_str(&c.to_string());
+            let prefix: String = text.chars().take(i + 1).collect();
+            let whole = evaluate_lenient(&prefix);
+            assert_eq!(session.result().ok(), whole.ok(), "after {:?}", prefix);
+            assert_eq!(
+                session.tokens(),
+                tokenize(&prefix, Dialect::Standard).unwrap()
+            );
+        }
+        assert_eq!(session.result().unwrap().value, 75.0);
+        while session.backspace() {
+            let text = session.text();
+            let expected = tokenize(&text, Dialect::Standard).unwrap();
+            assert_eq!(session.tokens(), expected.as_slice(), "after {:?}", text);
+        }
+        assert!(!session.backspace());
+    }
+
+    #[test]
+    fn test_only_the_suffix_is_relexed() {
+        let mut session = InputSession::new();
+        session.set_text(&"1 + ".repeat(500));
+        // The space after the last `+`, then the number being typed.
+        session.push_str("2");
+        assert_eq!(session.relexed_chars(), 2);
+        session.push_str("3");
+        assert_eq!(session.relexed_chars(), 3);
+        session.backspace();
+        assert_eq!(session.relexed_chars(), 2);
+        assert_eq!(session.result().unwrap().value, 502.0);
+    }
+
+    #[test]
+    fn test_lex_errors_recover() {
+        let mut session = InputSession::new();
+        type_out(&mut session, "2 * 1e");
+        assert_eq!(
+            session.result(),
+            Err(CalcError::Syntax {
+                message: "Expected digits in exponent".to_string(),
+                position: 6
+            })
+        );
+        type_out(&mut session, "3 #");
+        assert!(matches!(
+            session.result(),
+            Err(CalcError::Syntax { position: 8, .. })
+        ));
+        session.backspace();
+        assert_eq!(session.result().unwrap().value, 2000.0);
+        session.clear();
+        assert!(session.result().is_err());
+        assert_eq!(session.text(), "");
+    }
+}
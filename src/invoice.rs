@@ -0,0 +1,378 @@
+//! Invoices: line items with quantities, discounts and tax, totalled
+//! exactly in minor units.
+//!
+//! Each line is worked out on its own, in whole minor units (cents), with
+//! the [`Money`] rounding rules (half to even):
+//!
+//! 1. `gross` is the unit price times the quantity;
+//! 2. `discount` is `discount_percent` of `gross`;
+//! 3. `tax` is `tax_rate` percent of `gross - discount`;
+//! 4. the line's `total` is `gross - discount + tax`.
+//!
+//! The invoice totals are the sums of the line amounts, so they always add
+//! up, and `tax_by_rate` groups the tax for a VAT summary. Percentages are
+//! read as the decimals they print as, so `8.25` is exactly 8.25%.
+//!
+//! An invoice serializes to JSON (prices in minor units) for persistence;
+//! `Invoice.from_json` re-checks every item when loading it.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::currency::currency_code;
+use crate::money::{decimal, round_scaled, Money};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, MAX_SAFE_INTEGER};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LineItem {
+    pub description: String,
+    pub quantity: f64,
+    /// In minor units.
+    pub unit_price: i64,
+    /// In percent.
+    pub tax_rate: f64,
+    /// In percent of the line.
+    #[serde(default)]
+    pub discount_percent: f64,
+}
+
+/// What one line comes to, in minor units.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LineTotals {
+    pub description: String,
+    pub gross: i64,
+    pub discount: i64,
+    pub net: i64,
+    pub tax: i64,
+    pub total: i64,
+}
+
+/// The net amount and tax of the lines taxed at one rate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RateTotals {
+    pub tax_rate: f64,
+    pub net: i64,
+    pub tax: i64,
+}
+
+/// An invoice's totals, in minor units of `currency`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InvoiceTotals {
+    pub currency: String,
+    /// Before discounts.
+    pub subtotal: i64,
+    pub discount: i64,
+    pub net: i64,
+    pub tax: i64,
+    pub total: i64,
+    pub lines: Vec<LineTotals>,
+    /// One entry per tax rate, in order of first use.
+    pub tax_by_rate: Vec<RateTotals>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Invoice {
+    currency: String,
+    items: Vec<LineItem>,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+// `percent` percent of `minor`, rounded half to even.
+fn percent_of(minor: i64, percent: f64) -> Result<i64, CalcError> {
+    let (digits, scale) = decimal(&percent.to_string())?;
+    let product = (minor as i128)
+        .checked_mul(digits)
+        .ok_or(CalcError::TooLarge {
+            operation: "invoice",
+        })?;
+    let rounded = round_scaled(product, scale + 2);
+    if rounded.unsigned_abs() > MAX_SAFE_INTEGER as u128 {
+        return Err(CalcError::TooLarge {
+            operation: "invoice",
+        });
+    }
+    Ok(rounded as i64)
+}
+
+fn check_item(item: &LineItem) -> Result<(), CalcError> {
+    if !item.quantity.is_finite() || item.quantity <= 0.0 {
+        return Err(invalid(format!(
+            "Quantity must be positive, got {}",
+            item.quantity
+        )));
+    }
+    if !item.tax_rate.is_finite() || item.tax_rate < 0.0 {
+        return Err(invalid(format!(
+            "Tax rate must be zero or more, got {}",
+            item.tax_rate
+        )));
+    }
+    if !(0.0..=100.0).contains(&item.discount_percent) {
+        return Err(invalid(format!(
+            "Discount must be 0-100%, got {}",
+            item.discount_percent
+        )));
+    }
+    Ok(())
+}
+
+impl Invoice {
+    /// An empty invoice in `currency` (`"usd"`, `"EUR"`, ...).
+    pub fn new(currency: &str) -> Result<Invoice, CalcError> {
+        Ok(Invoice {
+            currency: currency_code(currency)?,
+            items: Vec::new(),
+        })
+    }
+
+    pub fn items(&self) -> &[LineItem] {
+        &self.items
+    }
+
+    /// Adds a line; `unit_price` is in major units (`19.99`), read as the
+    /// decimal it prints as. Returns the line's index.
+    pub fn add_item(
+        &mut self,
+        description: &str,
+        quantity: f64,
+        unit_price: f64,
+        tax_rate: f64,
+        discount_percent: f64,
+    ) -> Result<usize, CalcError> {
+        let item = LineItem {
+            description: description.to_string(),
+            quantity,
+            unit_price: Money::from_major(unit_price, &self.currency)?.minor(),
+            tax_rate,
+            discount_percent,
+        };
+        check_item(&item)?;
+        self.line_totals(&item)?;
+        self.items.push(item);
+        Ok(self.items.len() - 1)
+    }
+
+    fn money(&self, minor: i64) -> Result<Money, CalcError> {
+        Money::from_minor(minor, &self.currency)
+    }
+
+    fn line_totals(&self, item: &LineItem) -> Result<LineTotals, CalcError> {
+        let gross = self.money(item.unit_price)?.multiply(item.quantity)?;
+        let discount = self.money(percent_of(gross.minor(), item.discount_percent)?)?;
+        let net = gross.subtract(&discount)?;
+        let tax = self.money(percent_of(net.minor(), item.tax_rate)?)?;
+        Ok(LineTotals {
+            description: item.description.clone(),
+            gross: gross.minor(),
+            discount: discount.minor(),
+            net: net.minor(),
+            tax: tax.minor(),
+            total: net.add(&tax)?.minor(),
+        })
+    }
+
+    pub fn totals(&self) -> Result<InvoiceTotals, CalcError> {
+        let lines = self
+            .items
+            .iter()
+            .map(|item| self.line_totals(item))
+            .collect::<Result<Vec<LineTotals>, CalcError>>()?;
+        let sum = |amount: fn(&LineTotals) -> i64| {
+            lines.iter().try_fold(self.money(0)?, |acc, line| {
+                acc.add(&self.money(amount(line))?)
+            })
+        };
+        let mut tax_by_rate: Vec<RateTotals> = Vec::new();
+        for (item, line) in self.items.iter().zip(&lines) {
+            match tax_by_rate.iter_mut().find(|r| r.tax_rate == item.tax_rate) {
+                Some(rate) => {
+                    rate.net += line.net;
+                    rate.tax += line.tax;
+                }
+                None => tax_by_rate.push(RateTotals {
+                    tax_rate: item.tax_rate,
+                    net: line.net,
+                    tax: line.tax,
+                }),
+            }
+        }
+        Ok(InvoiceTotals {
+            currency: self.currency.clone(),
+            subtotal: sum(|l| l.gross)?.minor(),
+            discount: sum(|l| l.discount)?.minor(),
+            net: sum(|l| l.net)?.minor(),
+            tax: sum(|l| l.tax)?.minor(),
+            total: sum(|l| l.total)?.minor(),
+            lines,
+            tax_by_rate,
+        })
+    }
+
+    /// Loads an invoice saved with `to_json`, checking every item.
+    pub fn from_json(json: &str) -> Result<Invoice, CalcError> {
+        let mut invoice: Invoice =
+            serde_json::from_str(json).map_err(|e| invalid(format!("Invalid invoice: {}", e)))?;
+        invoice.currency = currency_code(&invoice.currency)?;
+        for (i, item) in invoice.items.iter().enumerate() {
+            check_item(item)
+                .and_then(|_| invoice.line_totals(item).map(|_| ()))
+                .map_err(|e| match e {
+                    CalcError::InvalidArgument(message) => {
+                        invalid(format!("Item {}: {}", i, message))
+                    }
+                    e => e,
+                })?;
+        }
+        Ok(invoice)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Invoice {
+    pub fn currency(&self) -> String {
+        self.currency.clone()
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Removes the line at `index`; returns whether there was one.
+    pub fn remove_item(&mut self, index: usize) -> bool {
+        if index >= self.items.len() {
+            return false;
+        }
+        self.items.remove(index);
+        true
+    }
+
+    /// `{ currency, items: [{ description, quantity, unit_price, tax_rate,
+    /// discount_percent }] }`, with `unit_price` in minor units.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new_js(currency: &str) -> Result<Invoice, CalcErrorJs> {
+        Invoice::new(currency).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add_item)]
+    pub fn add_item_js(
+        &mut self,
+        description: &str,
+        quantity: f64,
+        unit_price: f64,
+        tax_rate: f64,
+        discount_percent: f64,
+    ) -> Result<usize, CalcErrorJs> {
+        self.add_item(
+            description,
+            quantity,
+            unit_price,
+            tax_rate,
+            discount_percent,
+        )
+        .map_err(|e| CalcErrorJs::new(e, Some(unit_price)))
+    }
+
+    /// `{ currency, subtotal, discount, net, tax, total, lines: [{
+    /// description, gross, discount, net, tax, total }], tax_by_rate: [{
+    /// tax_rate, net, tax }] }`, amounts in minor units.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = totals)]
+    pub fn totals_js(&self) -> Result<JsValue, CalcErrorJs> {
+        let totals = self.totals().map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&totals).unwrap_or(JsValue::NULL))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = from_json)]
+    pub fn from_json_js(json: &str) -> Result<Invoice, CalcErrorJs> {
+        Invoice::from_json(json).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::new("usd").unwrap();
+        invoice.add_item("Widget", 3.0, 19.99, 8.25, 0.0).unwrap();
+        invoice.add_item("Gadget", 1.5, 10.0, 8.25, 10.0).unwrap();
+        invoice.add_item("Book", 2.0, 12.5, 0.0, 0.0).unwrap();
+        invoice
+    }
+
+    #[test]
+    fn test_totals() {
+        let totals = invoice().totals().unwrap();
+        let widget = &totals.lines[0];
+        // 59.97 at 8.25% is 4.947525, rounded to 4.95.
+        assert_eq!((widget.gross, widget.tax, widget.total), (5997, 495, 6492));
+        let gadget = &totals.lines[1];
+        // 15.00 less 10%, then 13.50 at 8.25% is 1.11375.
+        assert_eq!(
+            (gadget.gross, gadget.discount, gadget.net, gadget.tax),
+            (1500, 150, 1350, 111)
+        );
+        assert_eq!(totals.subtotal, 5997 + 1500 + 2500);
+        assert_eq!(totals.discount, 150);
+        assert_eq!(totals.net, totals.subtotal - totals.discount);
+        assert_eq!(totals.tax, 606);
+        assert_eq!(totals.total, totals.net + totals.tax);
+        assert_eq!(
+            totals.tax_by_rate,
+            vec![
+                RateTotals {
+                    tax_rate: 8.25,
+                    net: 7347,
+                    tax: 606
+                },
+                RateTotals {
+                    tax_rate: 0.0,
+                    net: 2500,
+                    tax: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_persistence() {
+        let invoice = invoice();
+        let json = invoice.to_json();
+        assert!(json.contains(r#""unit_price":1999"#), "{}", json);
+        assert_eq!(Invoice::from_json(&json), Ok(invoice));
+        let bad = json.replace(r#""quantity":1.5"#, r#""quantity":-1"#);
+        assert!(matches!(
+            Invoice::from_json(&bad),
+            Err(CalcError::InvalidArgument(m)) if m.starts_with("Item 1:")
+        ));
+        assert!(Invoice::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_items() {
+        let mut invoice = invoice();
+        assert!(invoice.add_item("Free", 0.0, 1.0, 0.0, 0.0).is_err());
+        assert!(invoice.add_item("Odd", 1.0, 1.0, 0.0, 150.0).is_err());
+        assert!(invoice.add_item("Huge", 1e9, 1e9, 0.0, 0.0).is_err());
+        assert_eq!(invoice.item_count(), 3);
+        assert!(invoice.remove_item(0));
+        assert!(!invoice.remove_item(5));
+        assert_eq!(invoice.items()[0].description, "Gadget");
+        assert!(Invoice::new("XYZ1").is_err());
+        assert_eq!(Invoice::new("jpy").unwrap().totals().unwrap().total, 0);
+    }
+}
@@ -0,0 +1,297 @@
+//! Keypad entry: the key-by-key state machine of a pocket calculator.
+//!
+//! A keypad UI reports keys one at a time: digits, `.`, `±`, backspace,
+//! operators and `=`. The calculator keeps the number being typed and the
+//! operator waiting for its second operand, and applies operations through
+//! the usual methods, so the history reads `set_value 2`, `add 3` for
+//! `2 + 3 =`. Each key returns the text to show.
+//!
+//! Operators apply immediately, left to right, as on a basic calculator:
+//! `2 + 3 × 4 =` is 20.
+//!
+//! - Pressing an operator straight after another replaces it: `2 + × 3 =`
+//!   is 6.
+//! - `=` with nothing typed uses the shown value as the operand: `5 × =` is
+//!   25.
+//! - Pressing `=` again repeats the last operation with the same operand:
+//!   `2 + 3 = =` is 8. Typing a number first repeats it on that number:
+//!   `2 + 3 = 10 =` is 13.
+//! - Typing after `=` starts a new calculation.
+//!
+//! A failed operation (division by zero, say) throws and resets the keypad
+//! to showing the current value.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::tape::parse_operation;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+/// Digits beyond this many in one entry are ignored, as on a keypad with a
+/// fixed-width display.
+pub const MAX_ENTRY_DIGITS: usize = 16;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Keypad {
+    // The number being typed, with `.` as the decimal mark; `None` while
+    // the current value is shown.
+    entry: Option<String>,
+    // The operator waiting for its second operand.
+    pending: Option<Operation>,
+    // The operation the last `=` applied, which `=` repeats.
+    repeat: Option<(Operation, f64)>,
+}
+
+fn keypad_operator(op: &str) -> Option<Operation> {
+    let operation = if op == "^" {
+        Operation::Power
+    } else {
+        parse_operation(op)?
+    };
+    matches!(
+        operation,
+        Operation::Add
+            | Operation::Subtract
+            | Operation::Multiply
+            | Operation::Divide
+            | Operation::Power
+    )
+    .then_some(operation)
+}
+
+impl Calculator {
+    /// Types the digit `d` (0 to 9).
+    pub fn input_digit(&mut self, d: u8) -> Result<String, CalcError> {
+        if d > 9 {
+            let err = CalcError::InvalidArgument(format!("{} is not a digit", d));
+            return Err(self.fail(err, Some(d as f64)));
+        }
+        let entry = self.keypad.entry.get_or_insert_with(|| "0".to_string());
+        if entry.chars().filter(char::is_ascii_digit).count() < MAX_ENTRY_DIGITS {
+            match entry.as_str() {
+                "0" => entry.clear(),
+                "-0" => entry.truncate(1),
+                _ => {}
+            }
+            entry.push(char::from(b'0' + d));
+        }
+        Ok(self.keypad_display())
+    }
+
+    /// Presses an operator key (`+`, `-`, `*`, `/`, `^`, or their names
+    /// such as `multiply`) or `=`.
+    pub fn press_operator(&mut self, op: &str) -> Result<String, CalcError> {
+        let result = if op == "=" {
+            self.press_equals()
+        } else {
+            match keypad_operator(op) {
+                Some(operation) => self.press_binary(operation),
+                None => {
+                    let err =
+                        CalcError::InvalidArgument(format!("Unknown keypad operator '{}'", op));
+                    return Err(self.fail(err, None));
+                }
+            }
+        };
+        if let Err(err) = result {
+            self.keypad = Keypad::default();
+            return Err(err);
+        }
+        Ok(self.keypad_display())
+    }
+
+    fn take_entry(&mut self) -> Option<f64> {
+        let entry = self.keypad.entry.take()?;
+        Some(entry.parse().unwrap_or(0.0))
+    }
+
+    fn press_binary(&mut self, operation: Operation) -> Result<(), CalcError> {
+        match (self.take_entry(), self.keypad.pending.take()) {
+            (Some(operand), Some(pending)) => {
+                self.apply(&pending, Some(operand))?;
+            }
+            (Some(operand), None) => self.set_value(operand),
+            // Nothing typed: chain from the shown value, or replace the
+            // pending operator.
+            (None, _) => {}
+        }
+        self.keypad.pending = Some(operation);
+        self.keypad.repeat = None;
+        Ok(())
+    }
+
+    fn press_equals(&mut self) -> Result<(), CalcError> {
+        let entry = self.take_entry();
+        match (self.keypad.pending.take(), self.keypad.repeat.clone()) {
+            (Some(pending), _) => {
+                let operand = entry.unwrap_or(self.current_value);
+                self.apply(&pending, Some(operand))?;
+                self.keypad.repeat = Some((pending, operand));
+            }
+            (None, Some((operation, operand))) => {
+                if let Some(value) = entry {
+                    self.set_value(value);
+                }
+                self.apply(&operation, Some(operand))?;
+            }
+            (None, None) => {
+                if let Some(value) = entry {
+                    self.set_value(value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Types the decimal point; ignored if the entry already has one.
+    pub fn input_decimal_point(&mut self) -> String {
+        let entry = self.keypad.entry.get_or_insert_with(|| "0".to_string());
+        if !entry.contains('.') {
+            entry.push('.');
+        }
+        self.keypad_display()
+    }
+
+    /// Deletes the last typed character. Does nothing while a result is
+    /// shown.
+    pub fn backspace(&mut self) -> String {
+        if let Some(entry) = &mut self.keypad.entry {
+            entry.pop();
+            if entry.is_empty() || entry == "-" {
+                *entry = "0".to_string();
+            }
+        }
+        self.keypad_display()
+    }
+
+    /// The `±` key: flips the sign of the entry, or negates the shown value
+    /// (as an operation in the history) when nothing is typed. Straight
+    /// after an operator, it starts a negative entry.
+    pub fn toggle_sign(&mut self) -> String {
+        match &mut self.keypad.entry {
+            Some(entry) => match entry.strip_prefix('-') {
+                Some(digits) => *entry = digits.to_string(),
+                None => entry.insert(0, '-'),
+            },
+            None if self.keypad.pending.is_some() => {
+                self.keypad.entry = Some("-0".to_string());
+            }
+            None => {
+                self.negate();
+            }
+        }
+        self.keypad_display()
+    }
+
+    /// The text a keypad display shows: the entry as typed, or else the
+    /// current value in the display format (see `apply_settings`).
+    pub fn keypad_display(&self) -> String {
+        match &self.keypad.entry {
+            Some(entry) if self.decimal_comma() => entry.replace('.', ","),
+            Some(entry) => entry.clone(),
+            None => self
+                .format_value(&self.display_options())
+                .unwrap_or_else(|_| self.current_value.to_string()),
+        }
+    }
+
+    /// The operator waiting for its second operand, by operation id (`add`,
+    /// `power`, ...), or `undefined`.
+    pub fn pending_operator(&self) -> Option<String> {
+        self.keypad
+            .pending
+            .as_ref()
+            .map(crate::tape::operation_name)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = input_digit)]
+    pub fn input_digit_js(&mut self, d: u8) -> Result<String, CalcErrorJs> {
+        self.input_digit(d).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = press_operator)]
+    pub fn press_operator_js(&mut self, op: &str) -> Result<String, CalcErrorJs> {
+        self.press_operator(op).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Presses each key in `keys`, returning the final display.
+    fn press(calc: &mut Calculator, keys: &str) -> Result<String, CalcError> {
+        let mut display = calc.keypad_display();
+        for key in keys.split_whitespace() {
+            display = match key {
+                "." => calc.input_decimal_point(),
+                "<" => calc.backspace(),
+                "+-" => calc.toggle_sign(),
+                digits if digits.chars().all(|c| c.is_ascii_digit()) => {
+                    for d in digits.bytes() {
+                        calc.input_digit(d - b'0')?;
+                    }
+                    calc.keypad_display()
+                }
+                op => calc.press_operator(op)?,
+            };
+        }
+        Ok(display)
+    }
+
+    #[test]
+    fn test_basic_entry() {
+        let mut calc = Calculator::new();
+        assert_eq!(press(&mut calc, "0 0 1 2 . 5 ."), Ok("12.5".to_string()));
+        assert_eq!(press(&mut calc, "< < < <"), Ok("0".to_string()));
+        assert_eq!(press(&mut calc, ". 5 +- +"), Ok("-0.5".to_string()));
+        assert_eq!(calc.pending_operator().as_deref(), Some("add"));
+        assert_eq!(press(&mut calc, "2 ="), Ok("1.5".to_string()));
+        assert_eq!(calc.history_count(), 2);
+        assert_eq!(
+            press(&mut calc, &"9 ".repeat(20)),
+            Ok("9".repeat(MAX_ENTRY_DIGITS))
+        );
+        assert!(calc.input_digit(10).is_err());
+        assert!(calc.press_operator("sqrt").is_err());
+
+        calc.set_input_locale("de-DE");
+        assert_eq!(press(&mut calc, "= 3 . 2"), Ok("3,2".to_string()));
+    }
+
+    #[test]
+    fn test_chains_and_replacement() {
+        let mut calc = Calculator::new();
+        assert_eq!(press(&mut calc, "2 + 3 *"), Ok("5".to_string()));
+        assert_eq!(press(&mut calc, "4 ="), Ok("20".to_string()));
+        assert_eq!(press(&mut calc, "2 + * 3 ="), Ok("6".to_string()));
+        assert_eq!(press(&mut calc, "5 * ="), Ok("25".to_string()));
+        // After a result, an operator continues from it.
+        assert_eq!(press(&mut calc, "- 5 ="), Ok("20".to_string()));
+        assert_eq!(press(&mut calc, "+ +- 3 ="), Ok("17".to_string()));
+        assert_eq!(press(&mut calc, "+-"), Ok("-17".to_string()));
+        assert_eq!(press(&mut calc, "2 ^ 10 ="), Ok("1024".to_string()));
+    }
+
+    #[test]
+    fn test_repeated_equals() {
+        let mut calc = Calculator::new();
+        assert_eq!(press(&mut calc, "2 + 3 = ="), Ok("8".to_string()));
+        assert_eq!(press(&mut calc, "="), Ok("11".to_string()));
+        assert_eq!(press(&mut calc, "10 ="), Ok("13".to_string()));
+        assert_eq!(press(&mut calc, "100 / 4 = = ="), Ok("1.5625".to_string()));
+        // A new operator forgets the repeat.
+        assert_eq!(press(&mut calc, "* 2 + ="), Ok("6.25".to_string()));
+
+        assert_eq!(press(&mut calc, "1 / 0 ="), Err(CalcError::DivisionByZero));
+        assert_eq!(calc.pending_operator(), None);
+        assert_eq!(press(&mut calc, "="), Ok("1".to_string()));
+    }
+}
@@ -0,0 +1,368 @@
+//! Constant-acceleration kinematics for physics homework.
+//!
+//! [`projectile`] follows a launch under standard gravity with no air
+//! resistance: range, highest point and time of flight, from ground level
+//! or a height. The launch angle is read in an [`AngleUnit`];
+//! [`Calculator::projectile`] uses the calculator's angle mode, so `45`
+//! means 45° once the mode is degrees.
+//!
+//! [`suvat_solve`] takes any three of displacement `s`, initial velocity
+//! `u`, final velocity `v`, acceleration `a` and time `t` (SI units) and
+//! finds the other two from the SUVAT equations. Where the equations give
+//! a square root, the body is taken to keep moving in one direction: the
+//! velocity found has the sign of the other velocity, or of `s` when that
+//! is zero.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::constants::lookup;
+use crate::expr::AngleUnit;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Projectile {
+    /// Horizontal distance to where it lands at ground level, in m.
+    pub range: f64,
+    /// Highest point above the ground, in m.
+    pub max_height: f64,
+    /// In s.
+    pub time_of_flight: f64,
+}
+
+/// Any three of the five SUVAT variables; the other two are `None`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Suvat {
+    pub s: Option<f64>,
+    pub u: Option<f64>,
+    pub v: Option<f64>,
+    pub a: Option<f64>,
+    pub t: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SuvatSolution {
+    pub s: f64,
+    pub u: f64,
+    pub v: f64,
+    pub a: f64,
+    pub t: f64,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn no_solution() -> CalcError {
+    invalid("These values have no unique solution")
+}
+
+/// Range, highest point and time of flight of a body launched at `speed`
+/// (m/s) and `angle` above the horizontal from `launch_height` (m).
+pub fn projectile(
+    speed: f64,
+    angle: f64,
+    launch_height: f64,
+    unit: AngleUnit,
+) -> Result<Projectile, CalcError> {
+    if !speed.is_finite() || speed < 0.0 {
+        return Err(invalid(format!(
+            "Speed must be a non-negative number, got {}",
+            speed
+        )));
+    }
+    if !launch_height.is_finite() || launch_height < 0.0 {
+        return Err(invalid(format!(
+            "Launch height must be a non-negative number, got {}",
+            launch_height
+        )));
+    }
+    if !angle.is_finite() {
+        return Err(invalid(format!("Invalid angle {}", angle)));
+    }
+    let g = lookup("standard_gravity")?.value;
+    let vx = speed * unit.trig("cos", angle)?;
+    let vy = speed * unit.trig("sin", angle)?;
+    let time_of_flight = (vy + (vy * vy + 2.0 * g * launch_height).sqrt()) / g;
+    Ok(Projectile {
+        range: vx * time_of_flight,
+        max_height: launch_height + vy.max(0.0).powi(2) / (2.0 * g),
+        time_of_flight,
+    })
+}
+
+// The root of `square` with the sign of `like`, or of `fallback` when
+// `like` is zero.
+fn signed_root(square: f64, like: f64, fallback: f64) -> Result<f64, CalcError> {
+    if square < 0.0 {
+        return Err(no_solution());
+    }
+    let sign = if like != 0.0 { like } else { fallback };
+    Ok(square.sqrt().copysign(if sign < 0.0 { -1.0 } else { 1.0 }))
+}
+
+fn nonzero(value: f64) -> Result<f64, CalcError> {
+    if value == 0.0 {
+        Err(no_solution())
+    } else {
+        Ok(value)
+    }
+}
+
+/// Solves for the two variables of `known` that are `None`.
+pub fn suvat_solve(known: &Suvat) -> Result<SuvatSolution, CalcError> {
+    let Suvat { s, u, v, a, t } = *known;
+    let given = [s, u, v, a, t].iter().filter(|x| x.is_some()).count();
+    if given != 3 {
+        return Err(invalid(format!(
+            "Give exactly three of s, u, v, a and t, got {}",
+            given
+        )));
+    }
+    if let Some(x) = [s, u, v, a, t]
+        .into_iter()
+        .flatten()
+        .find(|x| !x.is_finite())
+    {
+        return Err(invalid(format!("Invalid value {}", x)));
+    }
+    if t.is_some_and(|t| t < 0.0) {
+        return Err(invalid("Time must not be negative"));
+    }
+    let (s, u, v, a, t) = match (s, u, v, a, t) {
+        (None, Some(u), None, Some(a), Some(t)) => (u * t + a * t * t / 2.0, u, u + a * t, a, t),
+        (None, None, Some(v), Some(a), Some(t)) => (v * t - a * t * t / 2.0, v - a * t, v, a, t),
+        (None, Some(u), Some(v), None, Some(t)) => {
+            ((u + v) * t / 2.0, u, v, (v - u) / nonzero(t)?, t)
+        }
+        (None, Some(u), Some(v), Some(a), None) => {
+            let a = nonzero(a)?;
+            ((v * v - u * u) / (2.0 * a), u, v, a, (v - u) / a)
+        }
+        (Some(s), None, None, Some(a), Some(t)) => {
+            let mean = s / nonzero(t)?;
+            (s, mean - a * t / 2.0, mean + a * t / 2.0, a, t)
+        }
+        (Some(s), None, Some(v), None, Some(t)) => {
+            let t = nonzero(t)?;
+            (s, 2.0 * s / t - v, v, 2.0 * (v * t - s) / (t * t), t)
+        }
+        (Some(s), Some(u), None, None, Some(t)) => {
+            let t = nonzero(t)?;
+            (s, u, 2.0 * s / t - u, 2.0 * (s - u * t) / (t * t), t)
+        }
+        (Some(s), None, Some(v), Some(a), None) => {
+            let u = signed_root(v * v - 2.0 * a * s, v, s)?;
+            let t = if a != 0.0 {
+                (v - u) / a
+            } else {
+                s / nonzero(v)?
+            };
+            (s, u, v, a, t)
+        }
+        (Some(s), Some(u), None, Some(a), None) => {
+            let v = signed_root(u * u + 2.0 * a * s, u, s)?;
+            let t = if a != 0.0 {
+                (v - u) / a
+            } else {
+                s / nonzero(u)?
+            };
+            (s, u, v, a, t)
+        }
+        (Some(s), Some(u), Some(v), None, None) => {
+            let s = nonzero(s)?;
+            (
+                s,
+                u,
+                v,
+                (v * v - u * u) / (2.0 * s),
+                2.0 * s / nonzero(u + v)?,
+            )
+        }
+        _ => unreachable!("exactly three are given"),
+    };
+    if t < 0.0 {
+        return Err(invalid("These values need a negative time"));
+    }
+    if ![s, u, v, a, t].iter().all(|x| x.is_finite()) {
+        return Err(no_solution());
+    }
+    Ok(SuvatSolution { s, u, v, a, t })
+}
+
+impl Calculator {
+    /// [`projectile`] with `angle` in the calculator's angle mode.
+    pub fn projectile(
+        &self,
+        speed: f64,
+        angle: f64,
+        launch_height: f64,
+    ) -> Result<Projectile, CalcError> {
+        projectile(speed, angle, launch_height, self.angle_unit)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `{ range, max_height, time_of_flight }` with `angle` in the angle
+    /// mode (see `apply_settings`).
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = projectile)]
+    pub fn projectile_js(
+        &self,
+        speed: f64,
+        angle: f64,
+        launch_height: f64,
+    ) -> Result<JsValue, CalcErrorJs> {
+        let result = self
+            .projectile(speed, angle, launch_height)
+            .map_err(|e| CalcErrorJs::new(e, Some(speed)))?;
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    }
+}
+
+/// `known` is an object with three of `s`, `u`, `v`, `a` and `t`; returns
+/// all five.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = suvat_solve)]
+pub fn suvat_solve_js(known: JsValue) -> Result<JsValue, CalcErrorJs> {
+    let known: Suvat = serde_wasm_bindgen::from_value(known)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Invalid SUVAT values: {}", e))))?;
+    let solution = suvat_solve(&known).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&solution).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const G: f64 = 9.806_65;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_projectile() {
+        let p = projectile(20.0, 45.0, 0.0, AngleUnit::Degrees).unwrap();
+        assert!(close(p.range, 400.0 / G));
+        assert!(close(p.max_height, 100.0 / G));
+        assert!(close(p.time_of_flight, 2.0 * 20.0 * 0.5f64.sqrt() / G));
+
+        // Straight up lands where it started.
+        let up = projectile(10.0, 90.0, 0.0, AngleUnit::Degrees).unwrap();
+        assert_eq!(up.range, 0.0);
+        assert!(close(up.time_of_flight, 20.0 / G));
+        let radians = projectile(20.0, std::f64::consts::FRAC_PI_4, 0.0, AngleUnit::Radians);
+        assert!(close(radians.unwrap().range, p.range));
+
+        // Thrown level off a 20 m cliff.
+        let cliff = projectile(5.0, 0.0, 20.0, AngleUnit::Degrees).unwrap();
+        assert!(close(cliff.time_of_flight, (40.0 / G).sqrt()));
+        assert_eq!(cliff.max_height, 20.0);
+        assert!(projectile(-1.0, 45.0, 0.0, AngleUnit::Degrees).is_err());
+        assert!(projectile(1.0, 45.0, -1.0, AngleUnit::Degrees).is_err());
+    }
+
+    #[test]
+    fn test_projectile_uses_angle_mode() {
+        let mut calc = Calculator::new();
+        calc.update_settings(&json!({ "angle_unit": "gradians" }))
+            .unwrap();
+        let p = calc.projectile(20.0, 50.0, 0.0).unwrap();
+        assert!(close(p.range, 400.0 / G));
+    }
+
+    fn solve(
+        s: Option<f64>,
+        u: Option<f64>,
+        v: Option<f64>,
+        a: Option<f64>,
+        t: Option<f64>,
+    ) -> SuvatSolution {
+        suvat_solve(&Suvat { s, u, v, a, t }).unwrap()
+    }
+
+    #[test]
+    fn test_suvat_every_pair() {
+        // u = 2, a = 3, t = 4: v = 14, s = 32.
+        let full = SuvatSolution {
+            s: 32.0,
+            u: 2.0,
+            v: 14.0,
+            a: 3.0,
+            t: 4.0,
+        };
+        let (s, u, v, a, t) = (Some(32.0), Some(2.0), Some(14.0), Some(3.0), Some(4.0));
+        let cases = [
+            solve(None, u, None, a, t),
+            solve(None, None, v, a, t),
+            solve(None, u, v, None, t),
+            solve(None, u, v, a, None),
+            solve(s, None, None, a, t),
+            solve(s, None, v, None, t),
+            solve(s, None, v, a, None),
+            solve(s, u, None, None, t),
+            solve(s, u, None, a, None),
+            solve(s, u, v, None, None),
+        ];
+        for solution in cases {
+            for (x, y) in [
+                (solution.s, full.s),
+                (solution.u, full.u),
+                (solution.v, full.v),
+                (solution.a, full.a),
+                (solution.t, full.t),
+            ] {
+                assert!(close(x, y), "{:?}", solution);
+            }
+        }
+    }
+
+    #[test]
+    fn test_suvat_edge_cases() {
+        // Braking to a stop from 20 m/s over 40 m.
+        let stop = solve(Some(40.0), Some(20.0), Some(0.0), None, None);
+        assert_eq!((stop.a, stop.t), (-5.0, 4.0));
+        // No acceleration: constant speed.
+        let cruise = solve(Some(100.0), Some(10.0), None, Some(0.0), None);
+        assert_eq!((cruise.v, cruise.t), (10.0, 10.0));
+        // Moving the other way keeps its sign.
+        let back = solve(Some(-32.0), Some(-2.0), None, Some(-3.0), None);
+        assert!(close(back.v, -14.0));
+
+        let err = |known: Suvat| suvat_solve(&known).is_err();
+        assert!(err(Suvat::default()));
+        assert!(err(Suvat {
+            s: Some(1.0),
+            u: Some(1.0),
+            v: Some(1.0),
+            a: Some(1.0),
+            ..Suvat::default()
+        }));
+        // 10 m/s cannot reach 100 m braking at 1 m/s^2.
+        assert!(err(Suvat {
+            s: Some(100.0),
+            u: Some(10.0),
+            a: Some(-1.0),
+            ..Suvat::default()
+        }));
+        assert!(err(Suvat {
+            u: Some(1.0),
+            a: Some(1.0),
+            t: Some(-1.0),
+            ..Suvat::default()
+        }));
+        assert!(err(Suvat {
+            u: Some(5.0),
+            v: Some(1.0),
+            a: Some(1.0),
+            ..Suvat::default()
+        }));
+    }
+}
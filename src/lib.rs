@@ -1,12 +1,132 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use operand_source::OperandSource;
+
+pub mod adding_machine;
+pub mod aggregate;
+pub mod aliases;
+pub mod allocation;
+pub mod attribution;
+pub mod batch;
+pub mod bill;
+pub mod calculus;
+pub mod combinatorics;
+pub mod complex;
+pub mod constants;
+pub mod currency;
+pub mod datasets;
+pub mod date_calc;
+pub mod decibels;
+pub mod dedupe;
+pub mod diagnostics;
+pub mod education;
+pub mod electrical;
+pub mod elo;
+pub mod energy;
+pub mod equivalence;
+pub mod error_log;
+pub mod events;
+pub mod expr;
+pub mod finance;
+pub mod format;
+pub mod fraction;
+pub mod frozen;
+pub mod functions;
+pub mod fuzz;
+pub mod grading;
+pub mod history_retention;
+pub mod history_stats;
+pub mod history_sync;
+pub mod hooks;
+pub mod input;
+pub mod invoice;
+pub mod keypad;
+pub mod kinematics;
+pub mod lint;
+pub mod loans;
+pub mod locale;
+pub mod logging;
+pub mod long_division;
+pub mod manager;
+pub mod math;
+pub mod matrix;
+pub mod merge;
+pub mod money;
+pub mod operand_source;
+pub mod operation_codes;
+pub mod optics;
+pub mod payroll;
+pub mod place_value;
+pub mod predicate;
+pub mod privacy;
+pub mod probability;
+pub mod programmer;
+pub mod quiz;
+pub mod quota;
+pub mod random;
+pub mod ratio;
+pub mod read_snapshot;
+pub mod recalc;
+pub mod redact;
+pub mod repl;
+pub mod replay;
+pub mod retirement;
+pub mod rounding;
+pub mod rpc;
+pub mod settings;
+pub mod shared;
+pub mod sigfig;
+pub mod snapshot;
+pub mod solve;
+pub mod stats;
+pub mod structural;
+pub mod summary;
+pub mod sync;
+pub mod tape;
+pub mod tax;
+pub mod templates;
+pub mod thermo;
+pub mod timing;
+pub mod transfer;
+pub mod typography;
+pub mod uncertainty;
+pub mod units;
+pub mod variables;
+pub mod video;
+pub mod weather;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CalcError {
     DivisionByZero,
     NegativeSqrt,
+    /// `factorial` of more than 20.
     Overflow,
     InvalidInput,
+    /// Malformed expression text; `position` is a character offset.
+    Syntax { message: String, position: usize },
+    /// An expression referenced a variable or function that does not exist.
+    /// `suggestions` are the closest known names, best first.
+    UnknownIdentifier { name: String, suggestions: Vec<String> },
+    /// A function was called with the wrong number or kind of arguments.
+    InvalidArgument(String),
+    /// A loaded rate is older than the converter's age limit.
+    StaleRate(String),
+    /// In strict math mode, an operation would have produced NaN or an
+    /// infinity.
+    NotFinite,
+    /// Education mode, or a read-only view from `freeze`, does not allow
+    /// the operation.
+    NotAllowed(String),
+    /// A run went over its [`quota::RunQuota`]: `quota` names the limit,
+    /// `limit` is its value.
+    QuotaExceeded { quota: &'static str, limit: f64 },
+    /// A result too large for its type; `operation` names what overflowed.
+    /// Shares the `OVERFLOW` code with factorial's [`CalcError::Overflow`].
+    TooLarge { operation: &'static str },
 }
 
 impl CalcError {
@@ -16,16 +136,153 @@ impl CalcError {
             CalcError::NegativeSqrt => "Cannot take square root of negative number",
             CalcError::Overflow => "Factorial overflow: n must be <= 20",
             CalcError::InvalidInput => "Invalid input: n must be a non-negative integer <= 20",
+            CalcError::Syntax { message, .. } => message,
+            CalcError::UnknownIdentifier { .. } => "Unknown identifier",
+            CalcError::InvalidArgument(message) => message,
+            CalcError::StaleRate(message) => message,
+            CalcError::NotFinite => "Result is not a finite number",
+            CalcError::NotAllowed(message) => message,
+            CalcError::QuotaExceeded { .. } => "Quota exceeded",
+            CalcError::TooLarge { .. } => "Result is too large",
         }
     }
+
+    /// Stable machine-readable identifier, safe to match on from JS.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CalcError::DivisionByZero => "DIVISION_BY_ZERO",
+            CalcError::NegativeSqrt => "NEGATIVE_SQRT",
+            CalcError::Overflow => "OVERFLOW",
+            CalcError::InvalidInput => "INVALID_INPUT",
+            CalcError::Syntax { .. } => "SYNTAX_ERROR",
+            CalcError::UnknownIdentifier { .. } => "UNKNOWN_IDENTIFIER",
+            CalcError::InvalidArgument(_) => "INVALID_ARGUMENT",
+            CalcError::StaleRate(_) => "STALE_RATE",
+            CalcError::NotFinite => "NOT_FINITE",
+            CalcError::NotAllowed(_) => "NOT_ALLOWED",
+            CalcError::QuotaExceeded { .. } => "QUOTA_EXCEEDED",
+            CalcError::TooLarge { .. } => "OVERFLOW",
+        }
+    }
+}
+
+// Full message, including the details `as_str` leaves out.
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::Syntax { message, position } => {
+                write!(f, "{} at position {}", message, position)
+            }
+            CalcError::UnknownIdentifier { name, suggestions } if suggestions.is_empty() => {
+                write!(f, "Unknown identifier: {}", name)
+            }
+            CalcError::UnknownIdentifier { name, suggestions } => {
+                write!(f, "Unknown identifier: {} (did you mean {}?)", name, suggestions.join(" or "))
+            }
+            CalcError::QuotaExceeded { quota, limit } => {
+                write!(f, "Quota exceeded: {} is {}", quota, limit)
+            }
+            CalcError::TooLarge { operation } => write!(f, "Result of {} is too large", operation),
+            other => f.write_str(other.as_str()),
+        }
+    }
+}
+
+// So `?` can turn it into a `Box<dyn Error>` or `anyhow::Error` natively.
+impl std::error::Error for CalcError {}
+
+/// Structured error thrown to JavaScript by the fallible wrappers.
+///
+/// JS sees an object with `code`, `message` and `operand` properties, so the
+/// frontend can branch on `code` instead of matching message text.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcErrorJs {
+    error: CalcError,
+    operand: Option<f64>,
+}
+
+#[cfg(feature = "wasm")]
+impl CalcErrorJs {
+    pub fn new(error: CalcError, operand: Option<f64>) -> CalcErrorJs {
+        CalcErrorJs { error, operand }
+    }
+
+    pub fn error(&self) -> &CalcError {
+        &self.error
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl CalcErrorJs {
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> String {
+        self.error.code().to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
+
+    /// The input that triggered the error, or `undefined` when there is none.
+    #[wasm_bindgen(getter)]
+    pub fn operand(&self) -> Option<f64> {
+        self.operand
+    }
+
+    /// Character offset of a syntax error in the expression text.
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> Option<usize> {
+        match self.error {
+            CalcError::Syntax { position, .. } => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Close matches for an unknown identifier, best first, so the UI can
+    /// offer them as fixes. Empty for every other error.
+    #[wasm_bindgen(getter)]
+    pub fn suggestions(&self) -> Vec<String> {
+        match &self.error {
+            CalcError::UnknownIdentifier { suggestions, .. } => suggestions.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The exceeded limit of a run quota, such as `"max_operations"`.
+    #[wasm_bindgen(getter)]
+    pub fn quota(&self) -> Option<String> {
+        match self.error {
+            CalcError::QuotaExceeded { quota, .. } => Some(quota.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Keeps `'Error: ' + err` readable in existing string-based handlers.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        format!("{}: {}", self.error.code(), self.error)
+    }
 }
 
-// Always implement From for compilation, but only actually use it in WASM
+#[cfg(feature = "wasm")]
+impl From<CalcError> for CalcErrorJs {
+    fn from(error: CalcError) -> Self {
+        CalcErrorJs::new(error, None)
+    }
+}
+
+// Implemented on every target so the bindings compile natively, but only
+// meaningful in WASM
+#[cfg(feature = "wasm")]
 impl From<CalcError> for JsValue {
     fn from(err: CalcError) -> Self {
         #[cfg(target_arch = "wasm32")]
         {
-            JsValue::from_str(err.as_str())
+            CalcErrorJs::from(err).into()
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -43,90 +300,607 @@ pub enum Operation {
     Subtract,
     Multiply,
     Divide,
+    Sqrt,
+    Power,
+    SetValue,
+    Clear,
+    MemoryStore,
+    MemoryRecall,
+    MemoryClear,
+    MemoryAdd,
+    PercentOf,
+    AddPercent,
+    SubtractPercent,
+    /// A named constant from [`constants`] replaced the current value.
+    Constant(String),
+    /// Half-up rounding to `operand2` decimal places.
+    Round,
+    /// The `operand2`th root of `operand1`.
+    NthRoot,
+    Modulo,
+    Reciprocal,
+    Abs,
+    Negate,
+    /// A user-defined expression function (see [`functions`]) applied to
+    /// `operand1`.
+    Function(String),
+}
+
+impl Operation {
+    /// Whether the operation writes to memory rather than the current value.
+    pub fn affects_memory(&self) -> bool {
+        matches!(
+            self,
+            Operation::MemoryStore | Operation::MemoryClear | Operation::MemoryAdd
+        )
+    }
 }
 
+/// One state-changing call, as recorded in the history.
+///
+/// `operand1` is always the state the operation read (the current value, or
+/// memory for `MemoryRecall`/`MemoryClear`/`MemoryAdd`); `operand2` is the
+/// explicit argument, absent for unary and memory operations except
+/// `MemoryAdd`, where it is the value added. `timestamp` is milliseconds since
+/// the Unix epoch. `operand_source` says where `operand2` came from when it
+/// was not typed directly (see `operand_source`), and `author` who performed
+/// the operation, when one was set (see `attribution`).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CalculationHistory {
     operand1: f64,
-    operand2: f64,
+    operand2: Option<f64>,
     operation: Operation,
     result: f64,
+    timestamp: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "OperandSource::is_direct")]
+    operand_source: OperandSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone)]
 pub struct Calculator {
     current_value: f64,
     memory: f64,
-    history: Vec<CalculationHistory>,
+    history: VecDeque<CalculationHistory>,
+    // Oldest entries are evicted beyond this many; `None` is unbounded.
+    history_limit: Option<usize>,
+    // Entries older than this are dropped (see `history_retention`).
+    history_max_age_ms: Option<f64>,
+    // Bumped whenever entries are removed or rewritten other than by the
+    // limit, and how many the limit has evicted since (see `history_sync`).
+    history_version: u32,
+    history_evicted: usize,
+    // Key into the listener registry (see `events`), assigned on first use.
+    listener_id: events::ListenerId,
+    // Locale for text input and default formatting (see `locale`).
+    input_locale: Option<String>,
+    // Refuse NaN and infinite results instead of recording them.
+    strict_math: bool,
+    // Trigonometry in `evaluate` and user functions, and the default
+    // display format (see `settings`).
+    angle_unit: expr::AngleUnit,
+    display_decimals: Option<usize>,
+    notation: format::Notation,
+    // User functions and macros, and the macro being recorded (see
+    // `functions`).
+    functions: BTreeMap<String, functions::UserFunction>,
+    recording: Option<(String, functions::MacroSteps)>,
+    // Saved calculations with placeholders (see `templates`).
+    templates: BTreeMap<String, templates::Template>,
+    // Named values and live definitions (see `variables`).
+    variables: variables::Variables,
+    // The keypad entry in progress (see `keypad`).
+    keypad: keypad::Keypad,
+    // Counts for the end-of-session recap (see `summary`).
+    session: summary::SessionStats,
+    // Classroom restrictions, when enabled (see `education`).
+    education: Option<education::EducationMode>,
+    // Which redundant entries are left out of the history (see `dedupe`).
+    dedupe: dedupe::HistoryDedupe,
+    // When the operation being timed started (see `timing`).
+    op_started: Option<f64>,
+    // Where the operand of the operation being applied came from (see
+    // `operand_source`).
+    operand_source: Option<OperandSource>,
+    // Failed operations (see `error_log`).
+    error_log: error_log::ErrorLog,
+    // Limits on batches, tapes and macros, and the usage of the one
+    // running (see `quota`).
+    quota: quota::RunQuota,
+    run_usage: Option<quota::RunUsage>,
+    // What the outermost run would put back if it failed (see `batch`).
+    checkpoint: Option<batch::Checkpoint>,
+    // Alternative operator and function spellings (see `aliases`).
+    aliases: aliases::Aliases,
+    // Record no history or error log (see `privacy`).
+    incognito: bool,
+    // Whether operations are recorded at all (see `allocation`).
+    history_enabled: bool,
+    // Replica id, version vector and operation log (see `sync`).
+    sync: sync::SyncState,
+    // Who new entries are attributed to (see `attribution`).
+    author: Option<String>,
+    // Named data series for statistics and expressions (see `datasets`).
+    datasets: BTreeMap<String, stats::StatCalculator>,
+    // The entries of the last read snapshot (see `read_snapshot`).
+    snapshot_cache: std::cell::RefCell<Option<read_snapshot::SnapshotCache>>,
 }
 
 // Core implementation without WASM bindings (for tests)
 impl Calculator {
     pub fn divide(&mut self, value: f64) -> Result<f64, CalcError> {
         if value == 0.0 {
-            return Err(CalcError::DivisionByZero);
+            return Err(self.fail(CalcError::DivisionByZero, Some(value)));
         }
-        let result = self.current_value / value;
-        self.add_to_history(self.current_value, value, Operation::Divide, result);
-        self.current_value = result;
-        Ok(result)
+        self.commit(Operation::Divide, Some(value), self.current_value / value)
     }
 
     pub fn sqrt(&mut self) -> Result<f64, CalcError> {
+        self.permit(&Operation::Sqrt)?;
         if self.current_value < 0.0 {
-            return Err(CalcError::NegativeSqrt);
+            return Err(self.fail(CalcError::NegativeSqrt, Some(self.current_value)));
+        }
+        let result = self.current_value.sqrt();
+        self.add_to_history(Operation::Sqrt, self.current_value, None, result);
+        self.current_value = result;
+        Ok(self.current_value)
+    }
+
+    /// The `n`th root of the current value. Odd roots of negative numbers
+    /// are real, so `-27` with `n = 3` gives `-3`.
+    pub fn nth_root(&mut self, n: f64) -> Result<f64, CalcError> {
+        let result = nth_root_of(self.current_value, n).map_err(|e| self.fail(e, Some(n)))?;
+        self.commit(Operation::NthRoot, Some(n), result)
+    }
+
+    /// Remainder of dividing the current value by `value`, with the sign of
+    /// the current value: `-7 mod 3` is `-1`.
+    pub fn modulo(&mut self, value: f64) -> Result<f64, CalcError> {
+        if value == 0.0 {
+            return Err(self.fail(CalcError::DivisionByZero, Some(value)));
+        }
+        self.commit(Operation::Modulo, Some(value), self.current_value % value)
+    }
+
+    pub fn reciprocal(&mut self) -> Result<f64, CalcError> {
+        if self.current_value == 0.0 {
+            return Err(self.fail(CalcError::DivisionByZero, Some(self.current_value)));
+        }
+        self.commit(Operation::Reciprocal, None, 1.0 / self.current_value)
+    }
+
+    pub fn add(&mut self, value: f64) -> f64 {
+        self.arithmetic(Operation::Add, Some(value)).unwrap_or(self.current_value)
+    }
+
+    pub fn subtract(&mut self, value: f64) -> f64 {
+        self.arithmetic(Operation::Subtract, Some(value)).unwrap_or(self.current_value)
+    }
+
+    pub fn multiply(&mut self, value: f64) -> f64 {
+        self.arithmetic(Operation::Multiply, Some(value)).unwrap_or(self.current_value)
+    }
+
+    pub fn power(&mut self, exponent: f64) -> f64 {
+        self.arithmetic(Operation::Power, Some(exponent)).unwrap_or(self.current_value)
+    }
+
+    /// `percent`% of the current value: `200 × 10%` gives 20.
+    pub fn percent_of(&mut self, percent: f64) -> f64 {
+        self.arithmetic(Operation::PercentOf, Some(percent)).unwrap_or(self.current_value)
+    }
+
+    /// Marks the current value up by `percent`%: `200 + 10%` gives 220.
+    pub fn add_percent(&mut self, percent: f64) -> f64 {
+        self.arithmetic(Operation::AddPercent, Some(percent)).unwrap_or(self.current_value)
+    }
+
+    /// Discounts the current value by `percent`%: `200 - 10%` gives 180.
+    pub fn subtract_percent(&mut self, percent: f64) -> f64 {
+        self.arithmetic(Operation::SubtractPercent, Some(percent)).unwrap_or(self.current_value)
+    }
+
+    pub fn abs(&mut self) -> f64 {
+        self.arithmetic(Operation::Abs, None).unwrap_or(self.current_value)
+    }
+
+    /// Flips the sign of the current value (the `±` key).
+    pub fn negate(&mut self) -> f64 {
+        self.arithmetic(Operation::Negate, None).unwrap_or(self.current_value)
+    }
+
+    // The operations that cannot fail outside strict math mode, computed in
+    // one place so `apply` and the JS wrappers can report a refused result.
+    // The Rust methods above return the unchanged value instead.
+    fn arithmetic(&mut self, operation: Operation, operand: Option<f64>) -> Result<f64, CalcError> {
+        let a = self.current_value;
+        let b = operand.unwrap_or(f64::NAN);
+        let result = match operation {
+            Operation::Add => a + b,
+            Operation::Subtract => a - b,
+            Operation::Multiply => a * b,
+            Operation::Power => math::pow(a, b),
+            Operation::PercentOf => percentage(a, b),
+            Operation::AddPercent => a + percentage(a, b),
+            Operation::SubtractPercent => a - percentage(a, b),
+            Operation::Abs => a.abs(),
+            Operation::Negate => -a,
+            ref other => unreachable!("{:?} is not plain arithmetic", other),
+        };
+        self.commit(operation, operand, result)
+    }
+
+    // Records `operation` as taking the current value to `result`. In strict
+    // math mode a NaN or infinite result is refused and nothing changes, as
+    // is an operation education mode disallows.
+    fn commit(&mut self, operation: Operation, operand2: Option<f64>, result: f64) -> Result<f64, CalcError> {
+        self.permit(&operation)?;
+        if self.strict_math && !result.is_finite() {
+            return Err(self.fail(CalcError::NotFinite, operand2));
+        }
+        self.add_to_history(operation, self.current_value, operand2, result);
+        self.current_value = result;
+        Ok(result)
+    }
+
+    /// Sets the current value from text such as `"1e-7"` or `".5"`, so the
+    /// caller gets a positioned syntax error instead of a silent `NaN`.
+    /// With a decimal-comma input locale, `"0,5"` is one half.
+    pub fn set_value_str(&mut self, text: &str) -> Result<f64, CalcError> {
+        let value = locale::localize_input(text, self.decimal_comma())
+            .and_then(|text| expr::parse_number(&text))
+            .map_err(|e| self.fail(e, None))?;
+        self.set_value(value);
+        Ok(value)
+    }
+
+    /// Applies `operation` through the same method a direct call would use,
+    /// so it is validated and recorded identically. `operand` is required
+    /// for operations that take an argument and ignored otherwise. Returns
+    /// the current value afterwards.
+    pub fn apply(&mut self, operation: &Operation, operand: Option<f64>) -> Result<f64, CalcError> {
+        if self.starts_timing() {
+            return self.timed(&tape::operation_name(operation), |calc| calc.apply(operation, operand));
+        }
+        let needs_operand = |calc: &Calculator| {
+            operand.ok_or_else(|| {
+                calc.fail(CalcError::InvalidArgument(format!("{:?} requires an operand", operation)), None)
+            })
+        };
+        match operation {
+            Operation::Add => {
+                self.arithmetic(Operation::Add, Some(needs_operand(self)?))?;
+            }
+            Operation::Subtract => {
+                self.arithmetic(Operation::Subtract, Some(needs_operand(self)?))?;
+            }
+            Operation::Multiply => {
+                self.arithmetic(Operation::Multiply, Some(needs_operand(self)?))?;
+            }
+            Operation::Divide => {
+                self.divide(needs_operand(self)?)?;
+            }
+            Operation::Power => {
+                self.arithmetic(Operation::Power, Some(needs_operand(self)?))?;
+            }
+            Operation::SetValue => self.set_value(needs_operand(self)?),
+            Operation::Sqrt => {
+                self.sqrt()?;
+            }
+            Operation::Clear => self.clear(),
+            Operation::MemoryStore => self.memory_store(),
+            Operation::MemoryRecall => {
+                self.memory_recall();
+            }
+            Operation::MemoryClear => self.memory_clear(),
+            Operation::MemoryAdd => self.memory_add(),
+            Operation::PercentOf => {
+                self.arithmetic(Operation::PercentOf, Some(needs_operand(self)?))?;
+            }
+            Operation::AddPercent => {
+                self.arithmetic(Operation::AddPercent, Some(needs_operand(self)?))?;
+            }
+            Operation::SubtractPercent => {
+                self.arithmetic(Operation::SubtractPercent, Some(needs_operand(self)?))?;
+            }
+            Operation::Constant(name) => {
+                self.insert_constant(name)?;
+            }
+            Operation::Round => {
+                let places = needs_operand(self)?;
+                if places < 0.0 || places.fract() != 0.0 || places > u32::MAX as f64 {
+                    let err = CalcError::InvalidArgument(format!("Round needs whole decimal places, got {}", places));
+                    return Err(self.fail(err, Some(places)));
+                }
+                self.round_to(places as u32)?;
+            }
+            Operation::NthRoot => {
+                self.nth_root(needs_operand(self)?)?;
+            }
+            Operation::Modulo => {
+                self.modulo(needs_operand(self)?)?;
+            }
+            Operation::Reciprocal => {
+                self.reciprocal()?;
+            }
+            Operation::Abs | Operation::Negate => {
+                self.arithmetic(operation.clone(), None)?;
+            }
+            Operation::Function(name) => {
+                self.call_function(name)?;
+            }
         }
-        self.current_value = self.current_value.sqrt();
         Ok(self.current_value)
     }
+
+    /// Returns up to `count` entries starting at `start`, oldest first.
+    /// Indices are relative to the retained history; out-of-range requests
+    /// return fewer entries rather than failing.
+    pub fn history_range(&self, start: usize, count: usize) -> Vec<CalculationHistory> {
+        let start = start.min(self.history.len());
+        let end = start.saturating_add(count).min(self.history.len());
+        let mut entries = Vec::with_capacity(end - start);
+        entries.extend(self.history.range(start..end).cloned());
+        entries
+    }
+
+    /// Checks that value, memory and history are mutually consistent.
+    ///
+    /// Every entry must reproduce its own result from its operands, no failed
+    /// operation (e.g. a division by zero) may have left an entry behind, and
+    /// the newest value and memory entries must match the live state.
+    /// Returns a description of the first violation found. Used by the fuzz
+    /// entrypoints and property tests in [`fuzz`].
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if let Some(limit) = self.history_limit {
+            if self.history.len() > limit {
+                return Err(format!("history holds {} entries, limit is {}", self.history.len(), limit));
+            }
+        }
+        for (i, entry) in self.history.iter().enumerate() {
+            let a = entry.operand1;
+            let b = entry.operand2;
+            let expected = match (&entry.operation, b) {
+                (Operation::Add, Some(b)) => a + b,
+                (Operation::Subtract, Some(b)) => a - b,
+                (Operation::Multiply, Some(b)) => a * b,
+                (Operation::Divide, Some(b)) => {
+                    if b == 0.0 {
+                        return Err(format!("history[{}]: division by zero was recorded", i));
+                    }
+                    a / b
+                }
+                (Operation::Power, Some(b)) => math::pow(a, b),
+                (Operation::SetValue | Operation::Constant(_), Some(b)) => b,
+                (Operation::MemoryAdd, Some(b)) => a + b,
+                (Operation::PercentOf, Some(b)) => percentage(a, b),
+                (Operation::AddPercent, Some(b)) => a + percentage(a, b),
+                (Operation::SubtractPercent, Some(b)) => a - percentage(a, b),
+                (Operation::Round, Some(b)) => rounding::round_to(a, b as u32, rounding::RoundingMode::HalfUp),
+                (Operation::NthRoot, Some(b)) => match nth_root_of(a, b) {
+                    Ok(root) => root,
+                    Err(_) => return Err(format!("history[{}]: invalid root was recorded", i)),
+                },
+                (Operation::Modulo, Some(b)) => {
+                    if b == 0.0 {
+                        return Err(format!("history[{}]: modulo by zero was recorded", i));
+                    }
+                    a % b
+                }
+                (Operation::Reciprocal, None) => {
+                    if a == 0.0 {
+                        return Err(format!("history[{}]: reciprocal of zero was recorded", i));
+                    }
+                    1.0 / a
+                }
+                (Operation::Abs, None) => a.abs(),
+                // The definition may have changed since, so the recorded
+                // result is all there is to check against.
+                (Operation::Function(_), None) => entry.result,
+                (Operation::Negate, None) => -a,
+                (Operation::Sqrt, None) => {
+                    if a < 0.0 {
+                        return Err(format!("history[{}]: negative square root was recorded", i));
+                    }
+                    a.sqrt()
+                }
+                (Operation::Clear | Operation::MemoryClear, None) => 0.0,
+                (Operation::MemoryStore | Operation::MemoryRecall, None) => a,
+                (op, b) => {
+                    return Err(format!("history[{}]: {:?} recorded with operand2 {:?}", i, op, b));
+                }
+            };
+            if !same_f64(expected, entry.result) {
+                return Err(format!(
+                    "history[{}]: {:?} of {} and {:?} recorded {} but recomputes to {}",
+                    i, entry.operation, a, b, entry.result, expected
+                ));
+            }
+        }
+        let last_value = self.history.iter().rev().find(|e| !e.operation.affects_memory());
+        if let Some(entry) = last_value {
+            if !same_f64(entry.result, self.current_value) {
+                return Err(format!(
+                    "current value {} differs from last recorded result {}",
+                    self.current_value, entry.result
+                ));
+            }
+        }
+        let last_memory = self.history.iter().rev().find(|e| e.operation.affects_memory());
+        if let Some(entry) = last_memory {
+            if !same_f64(entry.result, self.memory) {
+                return Err(format!(
+                    "memory {} differs from last recorded memory result {}",
+                    self.memory, entry.result
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// IEEE equality, plus NaN equal to itself.
+pub(crate) fn same_f64(a: f64, b: f64) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+/// Milliseconds since the Unix epoch: `Date.now()` in the browser, the
+/// system clock natively and under WASI.
+pub(crate) fn now_ms() -> f64 {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown"))]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown")))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
 }
 
 // WASM bindings for JavaScript
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Calculator {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new() -> Calculator {
         Calculator {
             current_value: 0.0,
             memory: 0.0,
-            history: Vec::new(),
+            history: VecDeque::new(),
+            history_limit: None,
+            history_max_age_ms: None,
+            history_version: 0,
+            history_evicted: 0,
+            listener_id: Default::default(),
+            input_locale: None,
+            strict_math: false,
+            angle_unit: expr::AngleUnit::default(),
+            display_decimals: None,
+            notation: format::Notation::default(),
+            functions: BTreeMap::new(),
+            recording: None,
+            templates: BTreeMap::new(),
+            variables: variables::Variables::default(),
+            keypad: keypad::Keypad::default(),
+            session: summary::SessionStats::new(),
+            education: None,
+            dedupe: dedupe::HistoryDedupe::default(),
+            op_started: None,
+            operand_source: None,
+            error_log: error_log::ErrorLog::default(),
+            quota: quota::RunQuota::default(),
+            run_usage: None,
+            checkpoint: None,
+            aliases: aliases::Aliases::default(),
+            incognito: false,
+            history_enabled: true,
+            sync: sync::SyncState::default(),
+            author: None,
+            datasets: BTreeMap::new(),
+            snapshot_cache: Default::default(),
         }
     }
 
-    pub fn add(&mut self, value: f64) -> f64 {
-        let result = self.current_value + value;
-        self.add_to_history(self.current_value, value, Operation::Add, result);
-        self.current_value = result;
-        result
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&mut self, value: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::Add, Some(value)).map_err(|e| CalcErrorJs::new(e, Some(value)))
     }
 
-    pub fn subtract(&mut self, value: f64) -> f64 {
-        let result = self.current_value - value;
-        self.add_to_history(self.current_value, value, Operation::Subtract, result);
-        self.current_value = result;
-        result
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = subtract)]
+    pub fn subtract_js(&mut self, value: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::Subtract, Some(value)).map_err(|e| CalcErrorJs::new(e, Some(value)))
     }
 
-    pub fn multiply(&mut self, value: f64) -> f64 {
-        let result = self.current_value * value;
-        self.add_to_history(self.current_value, value, Operation::Multiply, result);
-        self.current_value = result;
-        result
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = multiply)]
+    pub fn multiply_js(&mut self, value: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::Multiply, Some(value)).map_err(|e| CalcErrorJs::new(e, Some(value)))
     }
 
+    #[cfg(feature = "wasm")]
     #[wasm_bindgen(js_name = divide)]
-    pub fn divide_js(&mut self, value: f64) -> Result<f64, JsValue> {
-        self.divide(value).map_err(|e| e.into())
+    pub fn divide_js(&mut self, value: f64) -> Result<f64, CalcErrorJs> {
+        self.divide(value).map_err(|e| CalcErrorJs::new(e, Some(value)))
     }
 
+    #[cfg(feature = "wasm")]
     #[wasm_bindgen(js_name = sqrt)]
-    pub fn sqrt_js(&mut self) -> Result<f64, JsValue> {
-        self.sqrt().map_err(|e| e.into())
+    pub fn sqrt_js(&mut self) -> Result<f64, CalcErrorJs> {
+        let operand = self.current_value;
+        self.sqrt().map_err(|e| CalcErrorJs::new(e, Some(operand)))
     }
 
-    pub fn power(&mut self, exponent: f64) -> f64 {
-        self.current_value = self.current_value.powf(exponent);
-        self.current_value
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = nth_root)]
+    pub fn nth_root_js(&mut self, n: f64) -> Result<f64, CalcErrorJs> {
+        self.nth_root(n).map_err(|e| CalcErrorJs::new(e, Some(n)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = modulo)]
+    pub fn modulo_js(&mut self, value: f64) -> Result<f64, CalcErrorJs> {
+        self.modulo(value).map_err(|e| CalcErrorJs::new(e, Some(value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = reciprocal)]
+    pub fn reciprocal_js(&mut self) -> Result<f64, CalcErrorJs> {
+        let operand = self.current_value;
+        self.reciprocal().map_err(|e| CalcErrorJs::new(e, Some(operand)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = abs)]
+    pub fn abs_js(&mut self) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::Abs, None).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = negate)]
+    pub fn negate_js(&mut self) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::Negate, None).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = power)]
+    pub fn power_js(&mut self, exponent: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::Power, Some(exponent)).map_err(|e| CalcErrorJs::new(e, Some(exponent)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = percent_of)]
+    pub fn percent_of_js(&mut self, percent: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::PercentOf, Some(percent)).map_err(|e| CalcErrorJs::new(e, Some(percent)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add_percent)]
+    pub fn add_percent_js(&mut self, percent: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::AddPercent, Some(percent)).map_err(|e| CalcErrorJs::new(e, Some(percent)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = subtract_percent)]
+    pub fn subtract_percent_js(&mut self, percent: f64) -> Result<f64, CalcErrorJs> {
+        self.arithmetic(Operation::SubtractPercent, Some(percent)).map_err(|e| CalcErrorJs::new(e, Some(percent)))
+    }
+
+    /// In strict math mode an operation that would produce NaN or an
+    /// infinity throws `NOT_FINITE` and leaves the value as it was.
+    pub fn set_strict_math(&mut self, strict: bool) {
+        self.strict_math = strict;
+    }
+
+    pub fn strict_math(&self) -> bool {
+        self.strict_math
     }
 
     pub fn get_value(&self) -> f64 {
@@ -134,55 +908,163 @@ impl Calculator {
     }
 
     pub fn set_value(&mut self, value: f64) {
+        self.add_to_history(Operation::SetValue, self.current_value, Some(value), value);
         self.current_value = value;
     }
 
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_value_str)]
+    pub fn set_value_str_js(&mut self, text: &str) -> Result<f64, CalcErrorJs> {
+        self.set_value_str(text).map_err(CalcErrorJs::from)
+    }
+
     pub fn clear(&mut self) {
+        self.add_to_history(Operation::Clear, self.current_value, None, 0.0);
         self.current_value = 0.0;
+        self.keypad = keypad::Keypad::default();
     }
 
     pub fn memory_store(&mut self) {
+        self.add_to_history(Operation::MemoryStore, self.current_value, None, self.current_value);
         self.memory = self.current_value;
     }
 
     pub fn memory_recall(&mut self) -> f64 {
+        self.add_to_history(Operation::MemoryRecall, self.memory, None, self.memory);
         self.current_value = self.memory;
         self.memory
     }
 
     pub fn memory_clear(&mut self) {
+        self.add_to_history(Operation::MemoryClear, self.memory, None, 0.0);
         self.memory = 0.0;
     }
 
     pub fn memory_add(&mut self) {
-        self.memory += self.current_value;
+        let result = self.memory + self.current_value;
+        self.add_to_history(Operation::MemoryAdd, self.memory, Some(self.current_value), result);
+        self.memory = result;
     }
 
     pub fn get_memory(&self) -> f64 {
         self.memory
     }
 
+    #[cfg(feature = "wasm")]
     pub fn get_history(&self) -> JsValue {
         serde_wasm_bindgen::to_value(&self.history).unwrap_or(JsValue::NULL)
     }
 
-    pub fn clear_history(&mut self) {
+    /// Clears the history and returns true. In education mode it only
+    /// asks to, returning false; `confirm_clear_history` then clears.
+    pub fn clear_history(&mut self) -> bool {
+        if self.request_clear_history() {
+            return false;
+        }
         self.history.clear();
+        self.history_rewritten();
+        true
     }
 
     pub fn history_count(&self) -> usize {
         self.history.len()
     }
+
+    /// Caps the history at `limit` entries, dropping the oldest ones first.
+    /// A limit of 0 removes the cap.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = if limit == 0 { None } else { Some(limit) };
+        self.enforce_history_limit();
+    }
+
+    /// The current cap, or 0 when the history is unbounded.
+    pub fn get_history_limit(&self) -> usize {
+        self.history_limit.unwrap_or(0)
+    }
+
+    /// Serializes a page of the history; see [`Calculator::history_range`].
+    #[cfg(feature = "wasm")]
+    pub fn get_history_range(&self, start: usize, count: usize) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.history_range(start, count)).unwrap_or(JsValue::NULL)
+    }
+
+    /// The result of every retained entry, oldest first, for charting how
+    /// the value evolved. Element `i` belongs to history entry `i`; pair it
+    /// with [`Calculator::history_timestamps`] for a time axis.
+    pub fn history_series(&self) -> Vec<f64> {
+        self.history.iter().map(|entry| entry.result).collect()
+    }
+
+    /// Timestamps matching [`Calculator::history_series`] element for element.
+    pub fn history_timestamps(&self) -> Vec<f64> {
+        self.history.iter().map(|entry| entry.timestamp).collect()
+    }
 }
 
 impl Calculator {
-    fn add_to_history(&mut self, operand1: f64, operand2: f64, operation: Operation, result: f64) {
-        self.history.push(CalculationHistory {
+    fn add_to_history(&mut self, operation: Operation, operand1: f64, operand2: Option<f64>, result: f64) {
+        if let Some((_, steps)) = &mut self.recording {
+            steps.push((operation.clone(), operand2));
+        }
+        if self.incognito {
+            self.notify_unrecorded(&operation, result);
+            return;
+        }
+        let author = self.sync_record(&operation, operand2);
+        if !self.history_enabled {
+            self.notify_unrecorded(&operation, result);
+            return;
+        }
+        let entry = CalculationHistory {
             operand1,
             operand2,
             operation,
             result,
-        });
+            timestamp: now_ms(),
+            duration_ms: self.op_started.map(|start| timing::precise_now_ms() - start),
+            operand_source: self.operand_source.clone().unwrap_or_default(),
+            author,
+        };
+        if !self.worth_recording(&entry) {
+            return;
+        }
+        // Evicting first reuses the slot, so a full history never grows.
+        let evicted = self.history_evicted;
+        if self.history_limit.is_some_and(|limit| self.history.len() >= limit) {
+            self.evict_oldest();
+        }
+        self.history.push_back(entry);
+        self.enforce_history_limit();
+        if let Some(limit) = self.history_limit.filter(|_| self.history_evicted > evicted) {
+            self.log_limit("history_limit", limit as f64, Some(self.history_evicted - evicted));
+        }
+        // A run expires old entries once it commits.
+        if self.run_usage.is_none() {
+            self.expire_history_at(now_ms());
+        }
+        if let Some(entry) = self.history.back() {
+            self.session.record(entry);
+            self.notify_recorded(entry);
+        }
+    }
+
+    fn enforce_history_limit(&mut self) {
+        if let Some(limit) = self.history_limit {
+            while self.history.len() > limit {
+                self.evict_oldest();
+            }
+        }
+    }
+
+    // Drops the oldest entry, handing it to the run in progress in case
+    // the run fails and puts it back.
+    fn evict_oldest(&mut self) {
+        if let Some(entry) = self.history.pop_front() {
+            if let Some(checkpoint) = &mut self.checkpoint {
+                checkpoint.keep_evicted(self.history_evicted, entry);
+            }
+            self.history_evicted += 1;
+        }
     }
 }
 
@@ -191,18 +1073,74 @@ impl Default for Calculator {
         Calculator {
             current_value: 0.0,
             memory: 0.0,
-            history: Vec::new(),
+            history: VecDeque::new(),
+            history_limit: None,
+            history_max_age_ms: None,
+            history_version: 0,
+            history_evicted: 0,
+            listener_id: Default::default(),
+            input_locale: None,
+            strict_math: false,
+            angle_unit: expr::AngleUnit::default(),
+            display_decimals: None,
+            notation: format::Notation::default(),
+            functions: BTreeMap::new(),
+            recording: None,
+            templates: BTreeMap::new(),
+            variables: variables::Variables::default(),
+            keypad: keypad::Keypad::default(),
+            session: summary::SessionStats::new(),
+            education: None,
+            dedupe: dedupe::HistoryDedupe::default(),
+            op_started: None,
+            operand_source: None,
+            error_log: error_log::ErrorLog::default(),
+            quota: quota::RunQuota::default(),
+            run_usage: None,
+            checkpoint: None,
+            aliases: aliases::Aliases::default(),
+            incognito: false,
+            history_enabled: true,
+            sync: sync::SyncState::default(),
+            author: None,
+            datasets: BTreeMap::new(),
+            snapshot_cache: Default::default(),
         }
     }
 }
 
 // Standalone utility functions
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn percentage(value: f64, percent: f64) -> f64 {
     value * (percent / 100.0)
 }
 
-#[wasm_bindgen]
+// Real `n`th root, shared by `nth_root` and `check_invariants` so both
+// compute it identically. Only whole odd degrees have real roots of
+// negative numbers.
+fn nth_root_of(x: f64, n: f64) -> Result<f64, CalcError> {
+    if n == 0.0 || !n.is_finite() {
+        return Err(CalcError::InvalidArgument(format!("Root degree must be finite and non-zero, got {}", n)));
+    }
+    if x >= 0.0 || x.is_nan() {
+        return Ok(if n == 2.0 {
+            x.sqrt()
+        } else if n == 3.0 {
+            math::cbrt(x)
+        } else {
+            math::pow(x, 1.0 / n)
+        });
+    }
+    if n.fract() != 0.0 {
+        return Err(CalcError::InvalidArgument(format!("Root of a negative number needs a whole degree, got {}", n)));
+    }
+    if n % 2.0 == 0.0 {
+        return Err(CalcError::InvalidArgument("Even root of a negative number".to_string()));
+    }
+    Ok(if n == 3.0 { math::cbrt(x) } else { -math::pow(-x, 1.0 / n) })
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn compound_interest(principal: f64, rate: f64, years: f64, compounds_per_year: f64) -> f64 {
     principal * (1.0 + rate / (100.0 * compounds_per_year)).powf(compounds_per_year * years)
 }
@@ -222,19 +1160,223 @@ pub fn factorial(n: u32) -> Result<u64, CalcError> {
 }
 
 // WASM wrapper for factorial
+#[cfg(feature = "wasm")]
 #[wasm_bindgen(js_name = factorial)]
-pub fn factorial_js(n: f64) -> Result<f64, JsValue> {
+pub fn factorial_js(n: f64) -> Result<f64, CalcErrorJs> {
     // f64 param sees the caller's full-precision value — no ToUint32 wrap.
     // Malformed inputs only; magnitude is the core's call so n > 20 still
     // reports Overflow rather than being mislabelled as invalid.
     if !n.is_finite() || n < 0.0 || n.fract() != 0.0 {
-        return Err(CalcError::InvalidInput.into());
+        return Err(CalcErrorJs::new(CalcError::InvalidInput, Some(n)));
     }
     // `as u32` saturates (Rust >= 1.45), so an out-of-u32-range n clamps to
     // u32::MAX and trips the core's n > 20 check — it can never wrap into the
     // valid 0..=20 window. Results for n <= 20 are exact in f64 (20! odd part
     // fits in 53 mantissa bits).
-    factorial(n as u32).map(|v| v as f64).map_err(Into::into)
+    factorial(n as u32)
+        .map(|v| v as f64)
+        .map_err(|e| CalcErrorJs::new(e, Some(n)))
+}
+
+// Largest integer a JS number holds exactly (2^53 - 1).
+pub(crate) const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+// Validates a JS number argument of the number-theory functions below and
+// of `combinatorics`.
+#[cfg(feature = "wasm")]
+pub(crate) fn whole_number(n: f64) -> Result<u64, CalcErrorJs> {
+    if !(0.0..=MAX_SAFE_INTEGER).contains(&n) || n.fract() != 0.0 {
+        let err = CalcError::InvalidArgument(format!("Expected a whole number from 0 to 2^53 - 1, got {}", n));
+        return Err(CalcErrorJs::new(err, Some(n)));
+    }
+    Ok(n as u64)
+}
+
+// `a * b mod m` without overflow.
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Primality by Miller-Rabin with the first twelve primes as witnesses,
+/// which is deterministic for every `u64`.
+pub fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = WITNESSES.iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+    let mut d = n - 1;
+    let mut r = 0;
+    while d.is_multiple_of(2) {
+        d /= 2;
+        r += 1;
+    }
+    WITNESSES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..r {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// Prime factors in ascending order, repeated by multiplicity:
+/// `prime_factors(12)` is `[2, 2, 3]`. 0 and 1 have none.
+pub fn prime_factors(mut n: u64) -> Vec<u64> {
+    if n < 2 {
+        return Vec::new();
+    }
+    if is_prime(n) {
+        return vec![n];
+    }
+    let mut factors = Vec::new();
+    // Trial division, stopping as soon as what is left is prime.
+    let mut p = 2;
+    while p <= n / p {
+        if n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+            if is_prime(n) {
+                break;
+            }
+        } else {
+            p += if p == 2 { 1 } else { 2 };
+        }
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+/// A node of a factor tree: a prime is a leaf, and a composite splits into
+/// its smallest prime factor and the cofactor.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FactorTree {
+    pub value: u64,
+    /// Empty for primes, 0 and 1; otherwise `[prime, value / prime]`.
+    pub children: Vec<FactorTree>,
+}
+
+/// The factor tree of `n`, as drawn on paper: `12` splits into `2` and
+/// `6`, and `6` into `2` and `3`. The leaves are [`prime_factors`].
+pub fn factor_tree(n: u64) -> FactorTree {
+    let mut factors = prime_factors(n);
+    if factors.len() < 2 {
+        return FactorTree { value: n, children: Vec::new() };
+    }
+    // Build from the bottom: the last two primes form the deepest node.
+    let last = factors.pop().unwrap_or(1);
+    let mut tree = FactorTree { value: last, children: Vec::new() };
+    while let Some(p) = factors.pop() {
+        let leaf = FactorTree { value: p, children: Vec::new() };
+        tree = FactorTree { value: p * tree.value, children: vec![leaf, tree] };
+    }
+    tree
+}
+
+/// Greatest common divisor; `gcd(0, 0)` is 0.
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple; 0 if either argument is 0.
+pub fn lcm(a: u64, b: u64) -> Result<u64, CalcError> {
+    if a == 0 || b == 0 {
+        return Ok(0);
+    }
+    (a / gcd(a, b)).checked_mul(b).ok_or(CalcError::TooLarge { operation: "lcm" })
+}
+
+/// The `n`th Fibonacci number, with `nth_fibonacci(0) == 0`. Values past
+/// `u64` (n > 93) are too large.
+pub fn nth_fibonacci(n: u32) -> Result<u64, CalcError> {
+    if n == 0 {
+        return Ok(0);
+    }
+    let (mut a, mut b) = (0u64, 1u64);
+    for _ in 1..n {
+        (a, b) = (b, a.checked_add(b).ok_or(CalcError::TooLarge { operation: "nth_fibonacci" })?);
+    }
+    Ok(b)
+}
+
+// The number-theory wrappers take and return JS numbers, so results past
+// 2^53 - 1 would silently round: those are reported as OVERFLOW instead.
+#[cfg(feature = "wasm")]
+fn exact(value: u64, operand: f64, operation: &'static str) -> Result<f64, CalcErrorJs> {
+    if value as f64 > MAX_SAFE_INTEGER {
+        return Err(CalcErrorJs::new(CalcError::TooLarge { operation }, Some(operand)));
+    }
+    Ok(value as f64)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = is_prime)]
+pub fn is_prime_js(n: f64) -> Result<bool, CalcErrorJs> {
+    whole_number(n).map(is_prime)
+}
+
+/// Prime factors as a `Float64Array`; see [`prime_factors`].
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = prime_factors)]
+pub fn prime_factors_js(n: f64) -> Result<Vec<f64>, CalcErrorJs> {
+    Ok(prime_factors(whole_number(n)?).into_iter().map(|p| p as f64).collect())
+}
+
+/// Nested `{ value, children }` nodes; see [`factor_tree`].
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = factor_tree)]
+pub fn factor_tree_js(n: f64) -> Result<JsValue, CalcErrorJs> {
+    let tree = factor_tree(whole_number(n)?);
+    Ok(serde_wasm_bindgen::to_value(&tree).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = gcd)]
+pub fn gcd_js(a: f64, b: f64) -> Result<f64, CalcErrorJs> {
+    Ok(gcd(whole_number(a)?, whole_number(b)?) as f64)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = lcm)]
+pub fn lcm_js(a: f64, b: f64) -> Result<f64, CalcErrorJs> {
+    let value = lcm(whole_number(a)?, whole_number(b)?).map_err(|e| CalcErrorJs::new(e, Some(b)))?;
+    exact(value, b, "lcm")
+}
+
+/// Exact up to `nth_fibonacci(78)`; larger `n` throws `OVERFLOW`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = nth_fibonacci)]
+pub fn nth_fibonacci_js(n: f64) -> Result<f64, CalcErrorJs> {
+    let index = whole_number(n)?.min(u32::MAX as u64) as u32;
+    nth_fibonacci(index)
+        .map_err(|e| CalcErrorJs::new(e, Some(n)))
+        .and_then(|value| exact(value, n, "nth_fibonacci"))
 }
 
 
@@ -382,6 +1524,86 @@ mod tests {
         assert_eq!(result, 0.25);
     }
 
+    #[test]
+    fn test_percent_operations() {
+        let mut calc = Calculator::new();
+        calc.set_value(200.0);
+        assert_eq!(calc.add_percent(10.0), 220.0);
+        assert_eq!(calc.subtract_percent(50.0), 110.0);
+        assert_eq!(calc.percent_of(10.0), 11.0);
+        assert_eq!(calc.get_value(), 11.0);
+        let history = calc.history_range(1, 3);
+        assert_eq!(history[0].operation, Operation::AddPercent);
+        assert_eq!(history[0].operand1, 200.0);
+        assert_eq!(history[0].operand2, Some(10.0));
+        assert_eq!(history[2].operation, Operation::PercentOf);
+        assert_eq!(history[2].result, 11.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+        assert_eq!(calc.apply(&Operation::AddPercent, Some(100.0)), Ok(22.0));
+    }
+
+    #[test]
+    fn test_nth_root() {
+        let mut calc = Calculator::new();
+        calc.set_value(-27.0);
+        assert_eq!(calc.nth_root(3.0), Ok(-3.0));
+        calc.set_value(-32.0);
+        assert_eq!(calc.nth_root(5.0), Ok(-2.0));
+        calc.set_value(16.0);
+        assert_eq!(calc.nth_root(4.0), Ok(2.0));
+        assert_eq!(calc.nth_root(0.5), Ok(4.0));
+        calc.set_value(-16.0);
+        assert_eq!(
+            calc.nth_root(4.0),
+            Err(CalcError::InvalidArgument("Even root of a negative number".to_string()))
+        );
+        assert!(calc.nth_root(0.5).is_err());
+        assert!(calc.nth_root(0.0).is_err());
+        assert_eq!(calc.get_value(), -16.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_modulo_reciprocal_abs_negate() {
+        let mut calc = Calculator::new();
+        calc.set_value(-7.0);
+        assert_eq!(calc.modulo(3.0), Ok(-1.0));
+        assert_eq!(calc.modulo(0.0), Err(CalcError::DivisionByZero));
+        assert_eq!(calc.abs(), 1.0);
+        assert_eq!(calc.negate(), -1.0);
+        calc.set_value(4.0);
+        assert_eq!(calc.reciprocal(), Ok(0.25));
+        calc.set_value(0.0);
+        assert_eq!(calc.reciprocal(), Err(CalcError::DivisionByZero));
+        assert_eq!(calc.history_count(), 7);
+        assert_eq!(calc.check_invariants(), Ok(()));
+        assert_eq!(calc.apply(&Operation::Negate, None), Ok(-0.0));
+        assert_eq!(calc.apply(&Operation::Modulo, Some(2.0)), Ok(-0.0));
+    }
+
+    #[test]
+    fn test_strict_math_refuses_non_finite_results() {
+        let mut calc = Calculator::new();
+        calc.set_value(1e300);
+        assert_eq!(calc.multiply(1e300), f64::INFINITY);
+        calc.set_value(1e300);
+        calc.set_strict_math(true);
+        assert!(calc.strict_math());
+        let count = calc.history_count();
+        assert_eq!(calc.multiply(1e300), 1e300);
+        assert_eq!(calc.apply(&Operation::Multiply, Some(1e300)), Err(CalcError::NotFinite));
+        assert_eq!(calc.apply(&Operation::Power, Some(2.0)), Err(CalcError::NotFinite));
+        assert_eq!(calc.divide(1e-300), Err(CalcError::NotFinite));
+        assert_eq!(calc.add(f64::NAN), 1e300);
+        #[cfg(feature = "wasm")]
+        assert_eq!(calc.multiply_js(1e300).unwrap_err().code(), "NOT_FINITE");
+        assert_eq!(calc.evaluate("ans * 1e10"), Err(CalcError::NotFinite));
+        assert_eq!(calc.get_value(), 1e300);
+        assert_eq!(calc.history_count(), count);
+        assert_eq!(calc.divide(1e100), Ok(1e200));
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
     #[test]
     fn test_clear() {
         let mut calc = Calculator::new();
@@ -447,7 +1669,7 @@ mod tests {
         calc.add(5.0);
         calc.multiply(2.0);
         
-        assert_eq!(calc.history_count(), 2);
+        assert_eq!(calc.history_count(), 3); // set_value is recorded too
     }
 
     #[test]
@@ -470,7 +1692,7 @@ mod tests {
         calc.divide(4.0).unwrap(); // 5
         
         assert_eq!(calc.get_value(), 5.0);
-        assert_eq!(calc.history_count(), 4);
+        assert_eq!(calc.history_count(), 5);
     }
 
     #[test]
@@ -525,6 +1747,78 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_is_prime() {
+        let primes: Vec<u64> = (0..30).filter(|&n| is_prime(n)).collect();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert!(is_prime(9_007_199_254_740_881));
+        assert!(is_prime(18_446_744_073_709_551_557));
+        // Strong pseudoprime to bases 2, 3, 5 and 7.
+        assert!(!is_prime(3_215_031_751));
+        assert!(!is_prime(u64::MAX));
+    }
+
+    #[test]
+    fn test_prime_factors() {
+        assert_eq!(prime_factors(0), Vec::<u64>::new());
+        assert_eq!(prime_factors(1), Vec::<u64>::new());
+        assert_eq!(prime_factors(360), vec![2, 2, 2, 3, 3, 5]);
+        assert_eq!(prime_factors(97), vec![97]);
+        assert_eq!(prime_factors(600_851_475_143), vec![71, 839, 1471, 6857]);
+        assert_eq!(prime_factors(2 * 9_007_199_254_740_881), vec![2, 9_007_199_254_740_881]);
+    }
+
+    #[test]
+    fn test_factor_tree() {
+        let leaf = |value| FactorTree { value, children: Vec::new() };
+        assert_eq!(factor_tree(7), leaf(7));
+        assert_eq!(factor_tree(1), leaf(1));
+        assert_eq!(
+            factor_tree(12),
+            FactorTree {
+                value: 12,
+                children: vec![leaf(2), FactorTree { value: 6, children: vec![leaf(2), leaf(3)] }],
+            }
+        );
+        let mut node = factor_tree(360);
+        let mut leaves = Vec::new();
+        while let [prime, rest] = node.children.as_slice() {
+            assert_eq!(prime.value * rest.value, node.value);
+            leaves.push(prime.value);
+            node = rest.clone();
+        }
+        leaves.push(node.value);
+        assert_eq!(leaves, prime_factors(360));
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(48, 18), 6);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(lcm(4, 6), Ok(12));
+        assert_eq!(lcm(0, 6), Ok(0));
+        assert_eq!(lcm(u64::MAX, u64::MAX - 1), Err(CalcError::TooLarge { operation: "lcm" }));
+    }
+
+    #[test]
+    fn test_nth_fibonacci() {
+        assert_eq!(nth_fibonacci(0), Ok(0));
+        assert_eq!(nth_fibonacci(1), Ok(1));
+        assert_eq!(nth_fibonacci(10), Ok(55));
+        assert_eq!(nth_fibonacci(93), Ok(12_200_160_415_121_876_738));
+        assert_eq!(nth_fibonacci(94), Err(CalcError::TooLarge { operation: "nth_fibonacci" }));
+        let err = nth_fibonacci(94).unwrap_err();
+        assert_eq!(err.code(), "OVERFLOW");
+        assert_eq!(err.to_string(), "Result of nth_fibonacci is too large");
+        #[cfg(feature = "wasm")]
+        assert_eq!(nth_fibonacci_js(78.0), Ok(8_944_394_323_791_464.0));
+        #[cfg(feature = "wasm")]
+        assert_eq!(nth_fibonacci_js(79.0).map_err(|e| e.code()), Err("OVERFLOW".to_string()));
+        #[cfg(feature = "wasm")]
+        assert_eq!(nth_fibonacci_js(1.5).map_err(|e| e.code()), Err("INVALID_ARGUMENT".to_string()));
+    }
+
     #[test]
     fn test_default_trait() {
         let calc = Calculator::default();
@@ -609,9 +1903,13 @@ mod tests {
     fn test_calculation_history_clone() {
         let history = CalculationHistory {
             operand1: 10.0,
-            operand2: 5.0,
+            operand2: Some(5.0),
             operation: Operation::Add,
             result: 15.0,
+            timestamp: 0.0,
+            duration_ms: None,
+            operand_source: OperandSource::Direct,
+            author: None,
         };
         let history2 = history.clone();
         
@@ -619,6 +1917,7 @@ mod tests {
         assert_eq!(history.operand2, history2.operand2);
         assert_eq!(history.operation, history2.operation);
         assert_eq!(history.result, history2.result);
+        assert_eq!(history.timestamp, history2.timestamp);
     }
 
     #[test]
@@ -669,7 +1968,7 @@ mod tests {
         calc.multiply(2.0);    // 60
         
         assert_eq!(calc.get_value(), 60.0);
-        assert_eq!(calc.history_count(), 4); // 4 operations (add, subtract, divide, multiply)
+        assert_eq!(calc.history_count(), 5); // set_value + add, subtract, divide, multiply
     }
 
     #[test]
@@ -710,7 +2009,7 @@ mod tests {
         calc.multiply(2.0);    // 200
         calc.divide(4.0).unwrap(); // 50
         
-        assert_eq!(calc.history_count(), 4);
+        assert_eq!(calc.history_count(), 5);
         
         calc.clear_history();
         assert_eq!(calc.history_count(), 0);
@@ -885,6 +2184,7 @@ mod tests {
         assert_eq!(calc.get_memory(), -25.0);
     }
 
+    #[cfg(feature = "wasm")]
     #[test]
     fn test_calc_error_from_trait() {
         // Test the From<CalcError> for JsValue implementation
@@ -988,14 +2288,19 @@ mod tests {
     fn test_calculation_history_struct() {
         let history = CalculationHistory {
             operand1: 10.0,
-            operand2: 5.0,
+            operand2: Some(5.0),
             operation: Operation::Add,
             result: 15.0,
+            timestamp: 1.0,
+            duration_ms: None,
+            operand_source: OperandSource::Direct,
+            author: None,
         };
         
         // Test all fields are accessible
         assert_eq!(history.operand1, 10.0);
-        assert_eq!(history.operand2, 5.0);
+        assert_eq!(history.operand2, Some(5.0));
+        assert_eq!(history.timestamp, 1.0);
         assert_eq!(history.result, 15.0);
         assert_eq!(history.operation, Operation::Add);
     }
@@ -1062,6 +2367,249 @@ mod tests {
         let result = calc.power(0.0);
         assert_eq!(result, 1.0);
     }
+    #[test]
+    fn test_check_invariants_after_operations() {
+        let mut calc = Calculator::new();
+        calc.set_value(10.0);
+        calc.add(5.0);
+        calc.subtract(3.0);
+        calc.multiply(f64::INFINITY);
+        calc.add(f64::NEG_INFINITY); // NaN result
+        let _ = calc.divide(0.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_unary_and_memory_operations_are_recorded() {
+        let mut calc = Calculator::new();
+        calc.set_value(16.0);
+        calc.sqrt().unwrap();
+        calc.power(3.0);
+        calc.memory_store();
+        calc.memory_add();
+        calc.memory_recall();
+        calc.memory_clear();
+        calc.clear();
+        let ops: Vec<Operation> = calc.history.iter().map(|e| e.operation.clone()).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Operation::SetValue,
+                Operation::Sqrt,
+                Operation::Power,
+                Operation::MemoryStore,
+                Operation::MemoryAdd,
+                Operation::MemoryRecall,
+                Operation::MemoryClear,
+                Operation::Clear,
+            ]
+        );
+        assert_eq!(calc.history[2].operand2, Some(3.0));
+        assert_eq!(calc.history[2].result, 64.0);
+        assert_eq!(calc.history[4].result, 128.0);
+        assert_eq!(calc.history[5].result, 128.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_failed_operations_are_not_recorded() {
+        let mut calc = Calculator::new();
+        calc.set_value(-1.0);
+        let _ = calc.sqrt();
+        let _ = calc.divide(0.0);
+        assert_eq!(calc.history_count(), 1);
+    }
+
+    #[test]
+    fn test_history_series() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(3);
+        calc.add(1.0);
+        calc.multiply(10.0);
+        calc.subtract(4.0);
+        calc.sqrt().unwrap();
+        assert_eq!(calc.history_series(), vec![10.0, 6.0, 6.0_f64.sqrt()]);
+        let timestamps = calc.history_timestamps();
+        assert_eq!(timestamps.len(), 3);
+        assert!(timestamps.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_history_entries_are_timestamped() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.add(1.0);
+        assert!(calc.history[0].timestamp > 0.0);
+        assert!(calc.history[1].timestamp >= calc.history[0].timestamp);
+    }
+
+    #[test]
+    fn test_check_invariants_detects_value_drift() {
+        let mut calc = Calculator::new();
+        calc.add(2.0);
+        calc.current_value = 7.0;
+        assert!(calc.check_invariants().is_err());
+    }
+
+    #[test]
+    fn test_check_invariants_detects_tampered_history() {
+        let mut calc = Calculator::new();
+        calc.add(2.0);
+        calc.history[0].result = 3.0;
+        assert!(calc.check_invariants().is_err());
+    }
+
+    #[test]
+    fn test_calc_error_codes() {
+        assert_eq!(CalcError::DivisionByZero.code(), "DIVISION_BY_ZERO");
+        assert_eq!(CalcError::NegativeSqrt.code(), "NEGATIVE_SQRT");
+        assert_eq!(CalcError::Overflow.code(), "OVERFLOW");
+        assert_eq!(CalcError::InvalidInput.code(), "INVALID_INPUT");
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_divide_js_structured_error() {
+        let mut calc = Calculator::new();
+        calc.set_value(1.0);
+        let err = calc.divide_js(0.0).unwrap_err();
+        assert_eq!(err.code(), "DIVISION_BY_ZERO");
+        assert_eq!(err.message(), "Division by zero");
+        assert_eq!(err.operand(), Some(0.0));
+        assert_eq!(err.error(), &CalcError::DivisionByZero);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_sqrt_js_structured_error_reports_current_value() {
+        let mut calc = Calculator::new();
+        calc.set_value(-9.0);
+        let err = calc.sqrt_js().unwrap_err();
+        assert_eq!(err.code(), "NEGATIVE_SQRT");
+        assert_eq!(err.operand(), Some(-9.0));
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_factorial_js_structured_errors() {
+        let err = factorial_js(21.0).unwrap_err();
+        assert_eq!(err.code(), "OVERFLOW");
+        assert_eq!(err.operand(), Some(21.0));
+        let err = factorial_js(2.5).unwrap_err();
+        assert_eq!(err.code(), "INVALID_INPUT");
+        assert_eq!(err.to_js_string(), "INVALID_INPUT: Invalid input: n must be a non-negative integer <= 20");
+    }
+
+    #[test]
+    fn test_calc_error_display_includes_details() {
+        let err = CalcError::Syntax { message: "Unexpected ')'".to_string(), position: 3 };
+        assert_eq!(err.as_str(), "Unexpected ')'");
+        assert_eq!(err.to_string(), "Unexpected ')' at position 3");
+        let unknown = |suggestions: &[&str]| CalcError::UnknownIdentifier {
+            name: "foo".to_string(),
+            suggestions: suggestions.iter().map(|s| s.to_string()).collect(),
+        };
+        assert_eq!(unknown(&[]).to_string(), "Unknown identifier: foo");
+        assert_eq!(unknown(&["floor", "for"]).to_string(), "Unknown identifier: foo (did you mean floor or for?)");
+        #[cfg(feature = "wasm")]
+        assert_eq!(CalcErrorJs::from(unknown(&["for"])).suggestions(), vec!["for".to_string()]);
+        assert_eq!(CalcError::DivisionByZero.to_string(), CalcError::DivisionByZero.as_str());
+        let boxed: Box<dyn std::error::Error> = CalcError::NegativeSqrt.into();
+        assert_eq!(boxed.to_string(), "Cannot take square root of negative number");
+        #[cfg(feature = "wasm")]
+        assert_eq!(CalcErrorJs::from(err).position(), Some(3));
+    }
+
+    #[test]
+    fn test_history_limit_evicts_oldest() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(3);
+        for i in 1..=5 {
+            calc.add(i as f64);
+        }
+        assert_eq!(calc.history_count(), 3);
+        assert_eq!(calc.history[0].operand2, Some(3.0));
+        assert_eq!(calc.history[2].operand2, Some(5.0));
+        assert_eq!(calc.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_lowering_history_limit_trims_immediately() {
+        let mut calc = Calculator::new();
+        for _ in 0..10 {
+            calc.add(1.0);
+        }
+        calc.set_history_limit(4);
+        assert_eq!(calc.history_count(), 4);
+        assert_eq!(calc.get_history_limit(), 4);
+        assert_eq!(calc.history[0].operand1, 6.0);
+    }
+
+    #[test]
+    fn test_history_limit_zero_is_unbounded() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(2);
+        calc.set_history_limit(0);
+        for _ in 0..5 {
+            calc.add(1.0);
+        }
+        assert_eq!(calc.history_count(), 5);
+        assert_eq!(calc.get_history_limit(), 0);
+    }
+
+    #[test]
+    fn test_history_range_pages() {
+        let mut calc = Calculator::new();
+        for i in 0..10 {
+            calc.add(i as f64);
+        }
+        let page = calc.history_range(2, 3);
+        assert_eq!(page.len(), 3);
+        assert_eq!(page[0].operand2, Some(2.0));
+        assert_eq!(page[2].operand2, Some(4.0));
+        assert_eq!(calc.history_range(8, 5).len(), 2);
+        assert!(calc.history_range(20, 5).is_empty());
+    }
+
+    #[test]
+    fn test_apply_matches_direct_calls() {
+        let mut direct = Calculator::new();
+        direct.set_value(9.0);
+        direct.sqrt().unwrap();
+        direct.power(2.0);
+        direct.memory_add();
+
+        let mut applied = Calculator::new();
+        applied.apply(&Operation::SetValue, Some(9.0)).unwrap();
+        applied.apply(&Operation::Sqrt, None).unwrap();
+        applied.apply(&Operation::Power, Some(2.0)).unwrap();
+        applied.apply(&Operation::MemoryAdd, Some(123.0)).unwrap(); // operand ignored
+
+        assert_eq!(applied.get_value(), direct.get_value());
+        assert_eq!(applied.get_memory(), direct.get_memory());
+        assert_eq!(applied.history_count(), direct.history_count());
+    }
+
+    #[test]
+    fn test_set_value_str() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.set_value_str("1e-7"), Ok(1e-7));
+        assert_eq!(calc.set_value_str("+3"), Ok(3.0));
+        assert_eq!(calc.get_value(), 3.0);
+        assert!(matches!(calc.set_value_str("--3"), Err(CalcError::Syntax { position: 1, .. })));
+        assert!(matches!(calc.set_value_str("1e"), Err(CalcError::Syntax { position: 2, .. })));
+        assert_eq!(calc.get_value(), 3.0);
+        assert_eq!(calc.history_count(), 2);
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let mut calc = Calculator::new();
+        assert!(matches!(calc.apply(&Operation::Add, None), Err(CalcError::InvalidArgument(_))));
+        assert_eq!(calc.apply(&Operation::Divide, Some(0.0)), Err(CalcError::DivisionByZero));
+        assert_eq!(calc.history_count(), 0);
+    }
+
     #[test]
     fn invalid_input_as_str() {
         assert_eq!(CalcError::InvalidInput.as_str(), "Invalid input: n must be a non-negative integer <= 20");
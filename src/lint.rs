@@ -0,0 +1,487 @@
+//! Style and precision warnings for expressions that parse fine.
+//!
+//! [`lint_expression`] reads a standard-dialect expression the way
+//! [`expr::parse`](crate::expr::parse) does and reports:
+//!
+//! - parentheses that do not change how the expression parses, such as
+//!   `(a * b) + c` or `sqrt((x))`;
+//! - operations on constants only inside an expression that is not
+//!   constant, such as the `60 * 60` in `60 * 60 * t`, which could be
+//!   written `3600`. A fully constant expression is a calculation, not a
+//!   warning. Constant trigonometry is evaluated in radians;
+//! - constant sums and differences that lose precision: subtracting
+//!   nearly equal values, which leaves mostly rounding noise, and adding a
+//!   value too small to change the other.
+//!
+//! Each warning carries the character span of the text it is about.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, tokenize, BinaryOp, Dialect, Expr, Spanned, Token, UnaryOp, CONSTANTS};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// The significant digits a subtraction may cancel before it is reported.
+pub const CANCELLATION_DIGITS: f64 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    RedundantParentheses,
+    ConstantSubexpression,
+    /// Subtracting nearly equal values.
+    Cancellation,
+    /// Adding a value too small to change the result.
+    Absorption,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintWarning {
+    pub kind: LintKind,
+    pub message: String,
+    /// Character span of the text the warning is about.
+    pub start: usize,
+    pub end: usize,
+}
+
+// Grammar levels, loosest first: a parenthesized operand at a position
+// that takes `level` or looser needs no parentheses.
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const UNARY: u8 = 3;
+const POWER: u8 = 4;
+const POSTFIX: u8 = 5;
+const PRIMARY: u8 = 6;
+
+struct Node {
+    expr: Expr,
+    start: usize,
+    end: usize,
+    /// The level of the outermost operation, looking through parentheses.
+    level: u8,
+    parenthesized: bool,
+    /// Whether the value depends on numbers and built-in constants only.
+    constant: bool,
+    /// Whether there is anything to compute, unlike `2`, `-2` or `pi`.
+    operation: bool,
+}
+
+/// Warnings about `src`, in source order. A syntax error is returned as
+/// the error [`evaluate`](crate::expr::evaluate) would give.
+pub fn lint_expression(src: &str) -> Result<Vec<LintWarning>, CalcError> {
+    parse(src, Dialect::Standard)?;
+    let chars: Vec<char> = src.chars().collect();
+    let mut linter = Linter {
+        chars: &chars,
+        tokens: tokenize(src, Dialect::Standard)?,
+        pos: 0,
+        warnings: Vec::new(),
+    };
+    let root = linter.expression()?;
+    linter.check_parentheses(&root, SUM);
+    linter.warnings.sort_by_key(|w| (w.start, w.end));
+    Ok(linter.warnings)
+}
+
+fn syntax(message: impl Into<String>, position: usize) -> CalcError {
+    CalcError::Syntax {
+        message: message.into(),
+        position,
+    }
+}
+
+// The grammar of `expr::Parser` for the standard dialect, keeping spans.
+struct Linter<'a> {
+    chars: &'a [char],
+    tokens: Vec<Spanned>,
+    pos: usize,
+    warnings: Vec<LintWarning>,
+}
+
+impl Linter<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.start)
+            .unwrap_or(self.chars.len())
+    }
+
+    fn eat_op(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_close(&mut self) -> Result<usize, CalcError> {
+        match self.tokens.get(self.pos) {
+            Some(t) if t.token == Token::RParen => {
+                self.pos += 1;
+                Ok(t.end)
+            }
+            _ => Err(syntax("Expected ')'", self.position())),
+        }
+    }
+
+    fn text(&self, node: &Node) -> String {
+        self.chars[node.start..node.end].iter().collect()
+    }
+
+    fn warn(&mut self, kind: LintKind, message: String, start: usize, end: usize) {
+        self.warnings.push(LintWarning {
+            kind,
+            message,
+            start,
+            end,
+        });
+    }
+
+    fn check_parentheses(&mut self, node: &Node, takes: u8) {
+        if node.parenthesized && node.level >= takes {
+            let message = format!("Parentheses in '{}' are not needed", self.text(node));
+            self.warn(
+                LintKind::RedundantParentheses,
+                message,
+                node.start,
+                node.end,
+            );
+        }
+    }
+
+    // Reports `operand` of a parent that is not constant itself.
+    fn check_constant(&mut self, operand: &Node) {
+        if !(operand.constant && operand.operation) {
+            return;
+        }
+        if let Ok(value) = operand.expr.eval(&()) {
+            let message = format!("'{}' is always {}", self.text(operand), value);
+            self.warn(
+                LintKind::ConstantSubexpression,
+                message,
+                operand.start,
+                operand.end,
+            );
+        }
+    }
+
+    fn check_precision(&mut self, op: BinaryOp, lhs: &Node, rhs: &Node) {
+        let (Ok(a), Ok(b)) = (lhs.expr.eval(&()), rhs.expr.eval(&())) else {
+            return;
+        };
+        let (result, subtrahend) = match op {
+            BinaryOp::Add => (a + b, -b),
+            BinaryOp::Sub => (a - b, b),
+            _ => return,
+        };
+        // Equal values cancel exactly.
+        if !result.is_finite() || result == 0.0 || a == 0.0 || b == 0.0 {
+            return;
+        }
+        let (start, end) = (lhs.start, rhs.end);
+        let text: String = self.chars[start..end].iter().collect();
+        if result == a || result == -subtrahend {
+            let (small, large) = if result == a { (rhs, lhs) } else { (lhs, rhs) };
+            let message = format!(
+                "In '{}', {} is too small to change {}",
+                text,
+                self.text(small),
+                self.text(large)
+            );
+            self.warn(LintKind::Absorption, message, start, end);
+        } else if a.signum() == subtrahend.signum() {
+            let lost = (a.abs().max(b.abs()) / result.abs()).log10();
+            if lost >= CANCELLATION_DIGITS {
+                let message = format!(
+                    "'{}' subtracts nearly equal values; about {} of the 15 significant digits are lost",
+                    text,
+                    (lost.floor() as u32).min(15)
+                );
+                self.warn(LintKind::Cancellation, message, start, end);
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: Node, rhs: Node) -> Node {
+        let (level, takes) = match op {
+            BinaryOp::Add | BinaryOp::Sub => (SUM, (SUM, PRODUCT)),
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => (PRODUCT, (PRODUCT, UNARY)),
+            BinaryOp::Pow => (POWER, (POSTFIX, UNARY)),
+        };
+        self.check_parentheses(&lhs, takes.0);
+        self.check_parentheses(&rhs, takes.1);
+        let constant = lhs.constant && rhs.constant;
+        if !constant {
+            self.check_constant(&lhs);
+            self.check_constant(&rhs);
+        }
+        if constant {
+            self.check_precision(op, &lhs, &rhs);
+        }
+        Node {
+            start: lhs.start,
+            end: rhs.end,
+            expr: Expr::Binary(op, Box::new(lhs.expr), Box::new(rhs.expr)),
+            level,
+            parenthesized: false,
+            constant,
+            operation: true,
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, operand: Node, start: usize, end: usize) -> Node {
+        let (level, takes) = match op {
+            UnaryOp::Neg => (UNARY, UNARY),
+            _ => (POSTFIX, POSTFIX),
+        };
+        self.check_parentheses(&operand, takes);
+        Node {
+            expr: Expr::Unary(op, Box::new(operand.expr)),
+            start,
+            end,
+            level,
+            parenthesized: false,
+            constant: operand.constant,
+            operation: operand.operation || op != UnaryOp::Neg,
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, CalcError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat_op('+') {
+                BinaryOp::Add
+            } else if self.eat_op('-') {
+                BinaryOp::Sub
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.term()?;
+            lhs = self.binary(op, lhs, rhs);
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, CalcError> {
+        let mut lhs = self.signed()?;
+        loop {
+            let op = if self.eat_op('*') {
+                BinaryOp::Mul
+            } else if self.eat_op('/') {
+                BinaryOp::Div
+            } else if self.eat_op('%') {
+                BinaryOp::Rem
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.signed()?;
+            lhs = self.binary(op, lhs, rhs);
+        }
+    }
+
+    fn signed(&mut self) -> Result<Node, CalcError> {
+        let start = self.position();
+        if self.eat_op('-') {
+            let operand = self.signed()?;
+            let end = operand.end;
+            return Ok(self.unary(UnaryOp::Neg, operand, start, end));
+        }
+        if self.eat_op('+') {
+            return self.signed();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Node, CalcError> {
+        let base = self.postfix()?;
+        if self.eat_op('^') {
+            let exponent = self.signed()?;
+            return Ok(self.binary(BinaryOp::Pow, base, exponent));
+        }
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> Result<Node, CalcError> {
+        let mut node = self.primary()?;
+        while self.peek() == Some(&Token::Op('!')) {
+            let end = self.tokens[self.pos].end;
+            self.pos += 1;
+            let start = node.start;
+            node = self.unary(UnaryOp::Factorial, node, start, end);
+        }
+        Ok(node)
+    }
+
+    fn primary(&mut self) -> Result<Node, CalcError> {
+        let position = self.position();
+        let Some(spanned) = self.tokens.get(self.pos).cloned() else {
+            return Err(syntax("Unexpected end of expression", position));
+        };
+        self.pos += 1;
+        let leaf = |expr, constant| Node {
+            expr,
+            start: spanned.start,
+            end: spanned.end,
+            level: PRIMARY,
+            parenthesized: false,
+            constant,
+            operation: false,
+        };
+        match spanned.token {
+            Token::Number(v) => Ok(leaf(Expr::Number(v), true)),
+            Token::LParen => {
+                let inner = self.expression()?;
+                let end = self.expect_close()?;
+                // `((a + b))`: the inner pair is the one to drop.
+                self.check_parentheses(&inner, SUM);
+                Ok(Node {
+                    start: spanned.start,
+                    end,
+                    parenthesized: true,
+                    ..inner
+                })
+            }
+            Token::Ident(name) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let mut args = Vec::new();
+                let end = if self.peek() == Some(&Token::RParen) {
+                    self.expect_close()?
+                } else {
+                    loop {
+                        let arg = self.expression()?;
+                        self.check_parentheses(&arg, SUM);
+                        args.push(arg);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.pos += 1;
+                            continue;
+                        }
+                        break self.expect_close()?;
+                    }
+                };
+                let constant = args.iter().all(|a| a.constant);
+                if !constant {
+                    for arg in &args {
+                        self.check_constant(arg);
+                    }
+                }
+                Ok(Node {
+                    expr: Expr::Call(name, args.into_iter().map(|a| a.expr).collect()),
+                    start: spanned.start,
+                    end,
+                    level: PRIMARY,
+                    parenthesized: false,
+                    constant,
+                    operation: true,
+                })
+            }
+            Token::Ident(name) => {
+                let constant = CONSTANTS.iter().any(|(c, _)| *c == name);
+                Ok(leaf(Expr::Variable(name), constant))
+            }
+            _ => Err(syntax("Unexpected token", position)),
+        }
+    }
+}
+
+/// Returns an array of `{ kind, message, start, end }`, where `kind` is
+/// `"redundant_parentheses"`, `"constant_subexpression"`, `"cancellation"`
+/// or `"absorption"`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = lint_expression)]
+pub fn lint_expression_js(expr: &str) -> Result<JsValue, CalcErrorJs> {
+    let warnings = lint_expression(expr).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&warnings).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<LintKind> {
+        lint_expression(src)
+            .unwrap()
+            .iter()
+            .map(|w| w.kind)
+            .collect()
+    }
+
+    fn texts(src: &str) -> Vec<String> {
+        lint_expression(src)
+            .unwrap()
+            .iter()
+            .map(|w| src.chars().skip(w.start).take(w.end - w.start).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_redundant_parentheses() {
+        use LintKind::RedundantParentheses as R;
+        assert_eq!(texts("(a * b) + c"), ["(a * b)"]);
+        assert_eq!(texts("(a + b) - c"), ["(a + b)"]);
+        assert_eq!(texts("sqrt((x))"), ["(x)"]);
+        assert_eq!(texts("x ^ (y ^ z)"), ["(y ^ z)"]);
+        assert_eq!(texts("((a + b)) * c"), ["(a + b)"]);
+        assert_eq!(kinds("(x)"), [R]);
+        assert_eq!(kinds("2 ^ (-x)"), [R]);
+        // Needed parentheses.
+        for src in [
+            "a - (b + c)",
+            "(a + b) * c",
+            "(-2) ^ x",
+            "(x ^ y) ^ z",
+            "a / (b * c)",
+            "(a + b)!",
+        ] {
+            assert_eq!(kinds(src), [], "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_constant_subexpressions() {
+        use LintKind::ConstantSubexpression as C;
+        let warnings = lint_expression("60 * 60 * t").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, C);
+        assert_eq!(warnings[0].message, "'60 * 60' is always 3600");
+        assert_eq!(texts("x + sqrt(16)"), ["sqrt(16)"]);
+        assert_eq!(texts("max(x, 2 * pi)"), ["2 * pi"]);
+        assert_eq!(texts("2 * pi * r"), ["2 * pi"]);
+        // Whole calculations, literals and constants are fine.
+        for src in ["60 * 60", "x * 2", "x - -2", "pi * r ^ 2"] {
+            assert_eq!(kinds(src), [], "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_precision() {
+        use LintKind::{Absorption, Cancellation};
+        assert_eq!(kinds("1.0000001 - 1"), [Cancellation]);
+        assert_eq!(kinds("0.1 + 0.2 - 0.3"), [Cancellation]);
+        assert_eq!(kinds("-5 + 5.000000001"), [Cancellation]);
+        assert_eq!(kinds("1e20 + 1"), [Absorption]);
+        let absorbed = lint_expression("1 + 1e20").unwrap();
+        assert_eq!(
+            absorbed[0].message,
+            "In '1 + 1e20', 1 is too small to change 1e20"
+        );
+        for src in ["1.5 - 1", "2 - 2", "1 + 1e-10", "x - 1.0000001"] {
+            assert_eq!(kinds(src), [], "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(matches!(
+            lint_expression("2 +"),
+            Err(CalcError::Syntax { .. })
+        ));
+        assert!(lint_expression("(1").is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Comparing loan offers on top of the amortization engine.
+//!
+//! [`compare_loans`] runs [`amortization_schedule`] for each offer and
+//! reports what it costs, so a "which loan is cheaper" UI needs one call
+//! instead of a schedule per offer. [`amortization_with_extra`] models
+//! paying more than the level payment each period and what that saves.
+//!
+//! Rates are percentages per period and payments are per period, as in
+//! [`crate::finance`]; for a monthly loan, pass the monthly rate and the
+//! number of months.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::finance::{amortization_schedule, loan_payment, AmortizationRow};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// One offer to compare.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Loan {
+    /// A label for the offer; offers without one are called `Loan 1`,
+    /// `Loan 2`, ... in input order.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub principal: f64,
+    pub rate: f64,
+    pub periods: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LoanCost {
+    pub name: String,
+    pub payment: f64,
+    pub total_interest: f64,
+    /// Principal plus interest: everything paid over the loan.
+    pub total_cost: f64,
+    /// 1 for the offer with the least interest.
+    pub rank: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LoanComparison {
+    /// The offers in input order.
+    pub loans: Vec<LoanCost>,
+    /// Offer names from least to most total interest; ties keep input
+    /// order.
+    pub ranking: Vec<String>,
+}
+
+/// A schedule with an extra payment, against the baseline without one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExtraPaymentSchedule {
+    /// The shortened schedule; each payment includes the extra.
+    pub schedule: Vec<AmortizationRow>,
+    pub total_interest: f64,
+    pub baseline_interest: f64,
+    pub interest_saved: f64,
+    /// The period of the last payment.
+    pub payoff_period: u32,
+    /// How many periods earlier than the baseline the loan is paid off.
+    pub periods_saved: u32,
+}
+
+fn for_loan(number: usize, err: CalcError) -> CalcError {
+    match err {
+        CalcError::InvalidArgument(message) => {
+            CalcError::InvalidArgument(format!("Loan {}: {}", number, message))
+        }
+        err => err,
+    }
+}
+
+/// What each of `loans` costs, ranked by total interest.
+pub fn compare_loans(loans: &[Loan]) -> Result<LoanComparison, CalcError> {
+    if loans.is_empty() {
+        return Err(CalcError::InvalidArgument(
+            "No loans to compare".to_string(),
+        ));
+    }
+    let mut costs = loans
+        .iter()
+        .enumerate()
+        .map(|(i, loan)| {
+            let rows = amortization_schedule(loan.principal, loan.rate, loan.periods)
+                .map_err(|e| for_loan(i + 1, e))?;
+            let total_interest: f64 = rows.iter().map(|row| row.interest).sum();
+            Ok(LoanCost {
+                name: loan
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Loan {}", i + 1)),
+                payment: rows[0].payment,
+                total_interest,
+                total_cost: loan.principal + total_interest,
+                rank: 0,
+            })
+        })
+        .collect::<Result<Vec<LoanCost>, CalcError>>()?;
+    let mut order: Vec<usize> = (0..costs.len()).collect();
+    order.sort_by(|&a, &b| costs[a].total_interest.total_cmp(&costs[b].total_interest));
+    for (rank, &i) in order.iter().enumerate() {
+        costs[i].rank = rank + 1;
+    }
+    let ranking = order.iter().map(|&i| costs[i].name.clone()).collect();
+    Ok(LoanComparison {
+        loans: costs,
+        ranking,
+    })
+}
+
+/// The schedule for repaying `principal` over `periods` at `rate` per
+/// period with `extra_payment` added to every level payment, until the
+/// balance is paid off. The last payment covers only what is left.
+pub fn amortization_with_extra(
+    principal: f64,
+    rate: f64,
+    periods: u32,
+    extra_payment: f64,
+) -> Result<ExtraPaymentSchedule, CalcError> {
+    if !extra_payment.is_finite() || extra_payment < 0.0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "Extra payment must be a non-negative amount, got {}",
+            extra_payment
+        )));
+    }
+    let baseline = amortization_schedule(principal, rate, periods)?;
+    let baseline_interest: f64 = baseline.iter().map(|row| row.interest).sum();
+    let payment = loan_payment(principal, rate, periods)? + extra_payment;
+    let r = rate / 100.0;
+    let mut balance = principal;
+    let mut schedule = Vec::new();
+    for period in 1..=periods {
+        let interest = balance * r;
+        // Also the baseline's last payment, which absorbs rounding drift.
+        let last = balance + interest <= payment || period == periods;
+        let (payment, principal_part) = if last {
+            (balance + interest, balance)
+        } else {
+            (payment, payment - interest)
+        };
+        balance -= principal_part;
+        schedule.push(AmortizationRow {
+            period,
+            payment,
+            principal: principal_part,
+            interest,
+            balance: if last { 0.0 } else { balance },
+        });
+        if last {
+            break;
+        }
+    }
+    let total_interest: f64 = schedule.iter().map(|row| row.interest).sum();
+    let payoff_period = schedule.len() as u32;
+    Ok(ExtraPaymentSchedule {
+        schedule,
+        total_interest,
+        baseline_interest,
+        interest_saved: baseline_interest - total_interest,
+        payoff_period,
+        periods_saved: periods - payoff_period,
+    })
+}
+
+/// `loans` is an array of `{ name?, principal, rate, periods }`; returns
+/// `{ loans: [{ name, payment, total_interest, total_cost, rank }], ranking }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = compare_loans)]
+pub fn compare_loans_js(loans: JsValue) -> Result<JsValue, CalcErrorJs> {
+    let loans: Vec<Loan> = serde_wasm_bindgen::from_value(loans).map_err(|e| {
+        CalcErrorJs::from(CalcError::InvalidArgument(format!("Invalid loans: {}", e)))
+    })?;
+    let comparison = compare_loans(&loans).map_err(CalcErrorJs::from)?;
+    serde_wasm_bindgen::to_value(&comparison).map_err(|e| {
+        CalcErrorJs::from(CalcError::InvalidArgument(format!(
+            "Could not serialize comparison: {}",
+            e
+        )))
+    })
+}
+
+/// `{ schedule: [{ period, payment, principal, interest, balance }],
+/// total_interest, baseline_interest, interest_saved, payoff_period,
+/// periods_saved }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = amortization_with_extra)]
+pub fn amortization_with_extra_js(
+    principal: f64,
+    rate: f64,
+    periods: u32,
+    extra_payment: f64,
+) -> Result<JsValue, CalcErrorJs> {
+    let result = amortization_with_extra(principal, rate, periods, extra_payment)
+        .map_err(CalcErrorJs::from)?;
+    serde_wasm_bindgen::to_value(&result).map_err(|e| {
+        CalcErrorJs::from(CalcError::InvalidArgument(format!(
+            "Could not serialize schedule: {}",
+            e
+        )))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    fn loan(name: Option<&str>, principal: f64, rate: f64, periods: u32) -> Loan {
+        Loan {
+            name: name.map(str::to_string),
+            principal,
+            rate,
+            periods,
+        }
+    }
+
+    #[test]
+    fn test_compare_loans() {
+        let comparison = compare_loans(&[
+            loan(Some("30 years"), 200_000.0, 0.5, 360),
+            loan(Some("15 years"), 200_000.0, 0.45, 180),
+            loan(None, 200_000.0, 0.0, 240),
+        ])
+        .unwrap();
+        let thirty = &comparison.loans[0];
+        assert!(
+            (thirty.payment - 1199.10).abs() < 0.005,
+            "{}",
+            thirty.payment
+        );
+        assert!((thirty.total_interest - 231_676.38).abs() < 0.01);
+        assert_eq!(thirty.total_cost, 200_000.0 + thirty.total_interest);
+        assert_eq!(comparison.loans[2].name, "Loan 3");
+        assert_eq!(comparison.loans[2].total_interest, 0.0);
+        assert_eq!(comparison.ranking, vec!["Loan 3", "15 years", "30 years"]);
+        let ranks: Vec<usize> = comparison.loans.iter().map(|l| l.rank).collect();
+        assert_eq!(ranks, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_invalid_loans() {
+        assert!(compare_loans(&[]).is_err());
+        assert_eq!(
+            compare_loans(&[loan(None, 1000.0, 1.0, 12), loan(None, 1000.0, 1.0, 0)]),
+            Err(CalcError::InvalidArgument(
+                "Loan 2: Number of periods must be at least 1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_amortization_with_extra() {
+        let result = amortization_with_extra(200_000.0, 0.5, 360, 200.0).unwrap();
+        let baseline = amortization_schedule(200_000.0, 0.5, 360).unwrap();
+        assert_eq!(result.payoff_period, result.schedule.len() as u32);
+        assert_eq!(result.periods_saved, 360 - result.payoff_period);
+        assert!(
+            (240..260).contains(&result.payoff_period),
+            "{}",
+            result.payoff_period
+        );
+        assert!(close(
+            result.schedule[0].payment,
+            baseline[0].payment + 200.0,
+            1e-9
+        ));
+        let last = result.schedule.last().unwrap();
+        assert_eq!(last.balance, 0.0);
+        assert!(last.payment <= baseline[0].payment + 200.0);
+        let repaid: f64 = result.schedule.iter().map(|row| row.principal).sum();
+        assert!(close(repaid, 200_000.0, 1e-6));
+        assert!(close(
+            result.interest_saved,
+            result.baseline_interest - result.total_interest,
+            1e-9
+        ));
+        assert!(
+            result.interest_saved > 50_000.0,
+            "{}",
+            result.interest_saved
+        );
+
+        // No extra payment is the baseline schedule.
+        let same = amortization_with_extra(1000.0, 1.0, 12, 0.0).unwrap();
+        assert_eq!(
+            same.schedule,
+            amortization_schedule(1000.0, 1.0, 12).unwrap()
+        );
+        assert_eq!(same.periods_saved, 0);
+        assert!(amortization_with_extra(1000.0, 1.0, 12, -5.0).is_err());
+        assert!(amortization_with_extra(1000.0, 1.0, 0, 5.0).is_err());
+        // Enough extra pays it off at once.
+        assert_eq!(
+            amortization_with_extra(1000.0, 1.0, 12, 2000.0)
+                .unwrap()
+                .payoff_period,
+            1
+        );
+    }
+}
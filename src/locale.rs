@@ -0,0 +1,177 @@
+//! Input locale: which character is the decimal mark.
+//!
+//! With a decimal-comma locale (`de`, `fr`, ...) numbers are typed as
+//! `0,5`, and function arguments are separated with `;` as in European
+//! spreadsheets, so `max(1,5; 2)` is `2`. Input is translated character for
+//! character, so syntax error positions still point into the original text.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, AngleScope, Dialect};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// `(decimal, group)` separators for a BCP 47 tag such as `de-DE` or
+/// `fr_FR`; unknown tags get those of `en`.
+pub fn locale_separators(locale: &str) -> (&'static str, &'static str) {
+    let tag = locale.replace('_', "-").to_ascii_lowercase();
+    if tag == "de-ch" || tag == "it-ch" {
+        return (".", "\u{2019}");
+    }
+    match tag.split('-').next().unwrap_or("") {
+        "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" | "ro" | "hr" | "sl"
+        | "sr" | "vi" => (",", "."),
+        "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" | "bg"
+        | "et" | "lt" | "lv" => (",", "\u{a0}"),
+        _ => (".", ","),
+    }
+}
+
+/// Rewrites decimal-comma input into the standard syntax: `,` becomes the
+/// decimal point and `;` the argument separator. A `.` is rejected rather
+/// than guessed at, since it could be meant as either mark.
+pub fn localize_input(text: &str, decimal_comma: bool) -> Result<String, CalcError> {
+    if !decimal_comma {
+        return Ok(text.to_string());
+    }
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            ',' => Ok('.'),
+            ';' => Ok(','),
+            '.' => Err(CalcError::Syntax {
+                message: "Use ',' as the decimal mark".to_string(),
+                position: i,
+            }),
+            c => Ok(c),
+        })
+        .collect()
+}
+
+impl Calculator {
+    pub(crate) fn decimal_comma(&self) -> bool {
+        self.input_locale
+            .as_deref()
+            .is_some_and(|l| locale_separators(l).0 == ",")
+    }
+
+    /// Evaluates a standard-dialect expression in the input locale, after
+    /// the input hook (see `hooks`) and its aliases (see `aliases`) have
+    /// rewritten it, and makes the result the current value. `ans` is the
+    /// current value, `mem` the memory, and the calculator's variables are
+    /// bound by name (see `variables`). In strict math mode a non-finite
+    /// result is refused. Education mode can disallow it as a whole.
+    pub fn evaluate(&mut self, src: &str) -> Result<f64, CalcError> {
+        self.evaluate_detailed(src)
+            .map(|evaluation| evaluation.value)
+    }
+
+    // `evaluate` of the input as the hook left it.
+    pub(crate) fn evaluate_source(&mut self, src: &str) -> Result<f64, CalcError> {
+        let value = self
+            .unalias(src, |src| {
+                localize_input(src, self.decimal_comma())
+                    .and_then(|src| parse(&src, Dialect::Standard))
+            })
+            .and_then(|expr| {
+                let mut scope = self.variable_scope(&expr)?;
+                scope.insert("ans".to_string(), self.current_value);
+                scope.insert("mem".to_string(), self.memory);
+                expr.eval(&AngleScope {
+                    scope: &self.dataset_scope(&scope),
+                    unit: self.angle_unit,
+                })
+            })
+            .and_then(|value| {
+                if self.strict_math && !value.is_finite() {
+                    Err(CalcError::NotFinite)
+                } else {
+                    Ok(value)
+                }
+            })
+            .map_err(|e| self.fail(e, None))?;
+        self.set_value(value);
+        Ok(value)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Sets the locale used by `set_value_str`, `evaluate` and, unless
+    /// overridden, `format_value`. An empty string restores the default.
+    pub fn set_input_locale(&mut self, locale: &str) {
+        let locale = locale.trim();
+        self.input_locale = (!locale.is_empty()).then(|| locale.to_string());
+    }
+
+    /// The input locale, or an empty string for the default.
+    pub fn input_locale(&self) -> String {
+        self.input_locale.clone().unwrap_or_default()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = evaluate)]
+    pub fn evaluate_js(&mut self, src: &str) -> Result<f64, CalcErrorJs> {
+        self.evaluate(src).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::FormatOptions;
+
+    #[test]
+    fn test_decimal_comma_input() {
+        let mut calc = Calculator::new();
+        calc.set_input_locale("de-DE");
+        assert_eq!(calc.input_locale(), "de-DE");
+        assert_eq!(calc.set_value_str("0,5"), Ok(0.5));
+        assert_eq!(calc.set_value_str("-1,25e2"), Ok(-125.0));
+        assert_eq!(
+            calc.set_value_str("1.5"),
+            Err(CalcError::Syntax {
+                message: "Use ',' as the decimal mark".to_string(),
+                position: 1
+            })
+        );
+        assert_eq!(calc.evaluate("max(1,5; 2) + ans"), Ok(-123.0));
+        assert!(matches!(
+            calc.evaluate("2 * (1,5"),
+            Err(CalcError::Syntax { position: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn test_default_locale_is_unchanged() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.input_locale(), "");
+        assert_eq!(calc.set_value_str("1.5"), Ok(1.5));
+        assert_eq!(calc.evaluate("max(1, 5) * ans"), Ok(7.5));
+        assert!(calc.set_value_str("1,5").is_err());
+        calc.set_input_locale("en-GB");
+        assert_eq!(calc.set_value_str("2.5"), Ok(2.5));
+        calc.set_input_locale("");
+        assert_eq!(calc.input_locale(), "");
+    }
+
+    #[test]
+    fn test_format_value_mirrors_input_locale() {
+        let mut calc = Calculator::new();
+        calc.set_input_locale("fr");
+        calc.set_value_str("1234,5").unwrap();
+        let grouped = FormatOptions {
+            decimals: Some(2),
+            thousands: true,
+            ..Default::default()
+        };
+        assert_eq!(calc.format_value(&grouped), Ok("1\u{a0}234,50".to_string()));
+        let english = FormatOptions {
+            locale: Some("en".to_string()),
+            ..grouped
+        };
+        assert_eq!(calc.format_value(&english), Ok("1,234.50".to_string()));
+    }
+}
@@ -0,0 +1,262 @@
+//! Structured log events for debugging a deployed calculator.
+//!
+//! A log sink receives a [`LogRecord`] for each internal event at or below
+//! its [`LogLevel`]:
+//!
+//! - `limit_triggered` (debug): the history limit or age limit evicted
+//!   entries, or a run exceeded its quota;
+//! - `operation_applied` (trace): an operation was recorded, with the
+//!   entry the history listener gets;
+//! - `cache_hit` (trace): `read_snapshot` reused the entries of the last
+//!   snapshot.
+//!
+//! Events are only built when a sink wants them, so a calculator without
+//! one pays nothing. The sink is registered with the calculator's
+//! listeners (see `events`), though `clear_listeners` leaves it. Batches,
+//! tapes and macros log nothing while they run; once they commit, their
+//! entries are logged, but evictions during the run are not. Incognito
+//! operations are never logged.
+
+use std::rc::Rc;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{now_ms, CalcError, CalculationHistory, Calculator};
+
+/// How much a sink hears; each level includes those before it.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LogEvent {
+    OperationApplied {
+        entry: CalculationHistory,
+    },
+    CacheHit {
+        cache: &'static str,
+        entries: usize,
+    },
+    /// `limit` (`"history_limit"`, `"history_max_age"` or a quota's name)
+    /// with its `value`, and how many entries it evicted.
+    LimitTriggered {
+        limit: &'static str,
+        value: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        evicted: Option<usize>,
+    },
+}
+
+impl LogEvent {
+    pub fn level(&self) -> LogLevel {
+        match self {
+            LogEvent::LimitTriggered { .. } => LogLevel::Debug,
+            LogEvent::OperationApplied { .. } | LogEvent::CacheHit { .. } => LogLevel::Trace,
+        }
+    }
+}
+
+/// What a sink receives: `{ level, timestamp, event: { kind, ... } }`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    /// Milliseconds since the epoch.
+    pub timestamp: f64,
+    pub event: LogEvent,
+}
+
+pub(crate) type LogSink = Rc<dyn Fn(&LogRecord)>;
+
+impl Calculator {
+    /// Sends every event at or below `level` to `sink`.
+    pub fn set_log_sink(&mut self, sink: impl Fn(&LogRecord) + 'static, level: LogLevel) {
+        self.listeners_mut(|l| l.log = Some((level, Rc::new(sink))));
+    }
+
+    /// Removes the log sink from this calculator.
+    pub fn clear_log_sink(&mut self) {
+        self.clear_registered(|l| l.log = None);
+    }
+
+    // Builds the event and hands it to the sink, if there is one at
+    // `level`.
+    pub(crate) fn log(&self, level: LogLevel, event: impl FnOnce() -> LogEvent) {
+        let sink = self
+            .listeners()
+            .and_then(|l| l.log)
+            .filter(|(wanted, _)| level <= *wanted);
+        if let Some((_, sink)) = sink {
+            let event = event();
+            debug_assert_eq!(event.level(), level);
+            sink(&LogRecord {
+                level,
+                timestamp: now_ms(),
+                event,
+            });
+        }
+    }
+
+    pub(crate) fn log_operation(&self, entry: &CalculationHistory) {
+        self.log(LogLevel::Trace, || LogEvent::OperationApplied {
+            entry: entry.clone(),
+        });
+    }
+
+    pub(crate) fn log_limit(&self, limit: &'static str, value: f64, evicted: Option<usize>) {
+        self.log(LogLevel::Debug, || LogEvent::LimitTriggered {
+            limit,
+            value,
+            evicted,
+        });
+    }
+
+    // Quota errors are the limits reported through `fail`.
+    pub(crate) fn log_error(&self, error: &CalcError) {
+        if let CalcError::QuotaExceeded { quota, limit } = error {
+            self.log_limit(quota, *limit, None);
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Calls `f(record)` for each event at or below `level`, with records
+    /// shaped like `{ level: "trace", timestamp, event: { kind:
+    /// "operation_applied", entry } }`. Exceptions `f` throws are ignored.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_log_sink)]
+    pub fn set_log_sink_js(&mut self, f: &js_sys::Function, level: LogLevel) {
+        let f = f.clone();
+        self.set_log_sink(
+            move |record| {
+                if let Ok(record) = serde_wasm_bindgen::to_value(record) {
+                    let _ = f.call1(&JsValue::NULL, &record);
+                }
+            },
+            level,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::quota::RunQuota;
+    use crate::Operation;
+
+    fn sink(calc: &mut Calculator, level: LogLevel) -> Rc<RefCell<Vec<LogRecord>>> {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let log = records.clone();
+        calc.set_log_sink(move |record| log.borrow_mut().push(record.clone()), level);
+        records
+    }
+
+    fn kinds(records: &RefCell<Vec<LogRecord>>) -> Vec<String> {
+        records
+            .borrow()
+            .iter()
+            .map(|record| {
+                let json = serde_json::to_value(record).unwrap();
+                json["event"]["kind"].as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trace_sink_hears_operations_cache_hits_and_limits() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(2);
+        let records = sink(&mut calc, LogLevel::Trace);
+        calc.add(1.0);
+        calc.add(2.0);
+        calc.add(3.0);
+        let _ = calc.read_snapshot();
+        let _ = calc.read_snapshot();
+        assert_eq!(
+            kinds(&records),
+            [
+                "operation_applied",
+                "operation_applied",
+                "limit_triggered",
+                "operation_applied",
+                "cache_hit",
+            ]
+        );
+        let records = records.borrow();
+        assert!(matches!(
+            records[2].event,
+            LogEvent::LimitTriggered {
+                limit: "history_limit",
+                value: 2.0,
+                evicted: Some(1),
+            }
+        ));
+        match &records[3].event {
+            LogEvent::OperationApplied { entry } => {
+                assert_eq!(entry.operation, Operation::Add);
+                assert_eq!(entry.result, 6.0);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let json = serde_json::to_value(&records[4]).unwrap();
+        assert_eq!(json["level"], "trace");
+        assert_eq!(json["event"]["cache"], "read_snapshot");
+        assert_eq!(json["event"]["entries"], 2);
+    }
+
+    #[test]
+    fn test_debug_sink_hears_only_limits() {
+        let mut calc = Calculator::new();
+        let records = sink(&mut calc, LogLevel::Debug);
+        calc.add(1.0);
+        calc.set_run_quota(RunQuota {
+            max_operations: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+        let steps = [(Operation::Add, Some(1.0)), (Operation::Add, Some(1.0))];
+        assert!(calc.apply_all(steps).is_err());
+        let records = records.borrow();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Debug);
+        assert!(matches!(
+            records[0].event,
+            LogEvent::LimitTriggered {
+                limit: "max_operations",
+                value: 1.0,
+                evicted: None,
+            }
+        ));
+        let json = serde_json::to_value(&records[0]).unwrap();
+        assert!(json["event"].get("evicted").is_none());
+    }
+
+    #[test]
+    fn test_sink_is_per_calculator_and_clearable() {
+        let mut calc = Calculator::new();
+        let records = sink(&mut calc, LogLevel::Trace);
+        calc.clone().add(1.0);
+        calc.add(1.0);
+        calc.set_incognito(true);
+        calc.add(1.0);
+        calc.set_incognito(false);
+        assert_eq!(records.borrow().len(), 1);
+        calc.clear_listeners();
+        calc.add(1.0);
+        assert_eq!(records.borrow().len(), 2);
+        calc.clear_log_sink();
+        calc.add(1.0);
+        assert_eq!(records.borrow().len(), 2);
+        drop(calc);
+        assert_eq!(Rc::strong_count(&records), 1);
+    }
+}
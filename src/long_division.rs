@@ -0,0 +1,220 @@
+//! Long division with the full working, for teaching tools.
+//!
+//! Each step brings down a digit (a zero once past the decimal point),
+//! divides, and records what was subtracted, as on paper. Once the
+//! integer digits are done the division continues until it terminates, a
+//! remainder repeats (marking the repetend), or `max_digits` digits after
+//! the point have been written.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::{whole_number, CalcErrorJs};
+
+/// Largest divisor accepted. Every number in the working stays below ten
+/// times the divisor, so this keeps them exact as JS numbers.
+pub const MAX_DIVISOR: u64 = 100_000_000_000_000;
+
+/// Most digits after the point the JS wrapper will write.
+pub const MAX_FRACTION_DIGITS: u32 = 10_000;
+
+/// One bring-down-and-subtract step.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DivisionStep {
+    /// The number divided in this step: the previous remainder with the
+    /// next digit brought down.
+    pub partial: u64,
+    /// The quotient digit written.
+    pub digit: u8,
+    /// `digit * divisor`, subtracted from `partial`.
+    pub product: u64,
+    pub remainder: u64,
+    /// Whether the digit is after the decimal point.
+    pub fractional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LongDivision {
+    /// The quotient with the repetend in parentheses: `"12.1(6)"`.
+    pub quotient: String,
+    pub integer_digits: String,
+    pub fraction_digits: String,
+    /// Where in `fraction_digits` the repeating block starts, if it repeats.
+    pub repetend_start: Option<usize>,
+    /// The digits stopped at `max_digits` before terminating or repeating.
+    pub truncated: bool,
+    /// The whole-number remainder, `dividend % divisor`.
+    pub remainder: u64,
+    pub steps: Vec<DivisionStep>,
+}
+
+/// Divides `dividend` by `divisor`, writing at most `max_digits` digits
+/// after the decimal point. Leading steps that would only write a zero
+/// are left out, as on paper.
+pub fn long_division(
+    dividend: u64,
+    divisor: u64,
+    max_digits: usize,
+) -> Result<LongDivision, CalcError> {
+    if divisor == 0 {
+        return Err(CalcError::DivisionByZero);
+    }
+    if divisor > MAX_DIVISOR {
+        return Err(CalcError::InvalidArgument(format!(
+            "Divisor must be at most {}, got {}",
+            MAX_DIVISOR, divisor
+        )));
+    }
+    let mut steps = Vec::new();
+    let mut step = |partial: u64, fractional: bool| {
+        let digit = partial / divisor;
+        let remainder = partial % divisor;
+        steps.push(DivisionStep {
+            partial,
+            digit: digit as u8,
+            product: digit * divisor,
+            remainder,
+            fractional,
+        });
+        (digit as u8, remainder)
+    };
+
+    let mut integer_digits = String::new();
+    let mut remainder = 0;
+    let digits = dividend.to_string();
+    for (i, brought_down) in digits.bytes().enumerate() {
+        let partial = remainder * 10 + (brought_down - b'0') as u64;
+        let last = i + 1 == digits.len();
+        if integer_digits.is_empty() && partial < divisor && !last {
+            remainder = partial;
+            continue;
+        }
+        let (digit, rest) = step(partial, false);
+        integer_digits.push((b'0' + digit) as char);
+        remainder = rest;
+    }
+    let whole_remainder = remainder;
+
+    let mut fraction_digits = String::new();
+    let mut seen = HashMap::new();
+    let mut repetend_start = None;
+    let mut truncated = false;
+    while remainder != 0 {
+        if let Some(&start) = seen.get(&remainder) {
+            repetend_start = Some(start);
+            break;
+        }
+        if fraction_digits.len() == max_digits {
+            truncated = true;
+            break;
+        }
+        seen.insert(remainder, fraction_digits.len());
+        let (digit, rest) = step(remainder * 10, true);
+        fraction_digits.push((b'0' + digit) as char);
+        remainder = rest;
+    }
+
+    let mut quotient = integer_digits.clone();
+    if !fraction_digits.is_empty() {
+        quotient.push('.');
+        match repetend_start {
+            Some(start) => {
+                quotient.push_str(&fraction_digits[..start]);
+                quotient.push('(');
+                quotient.push_str(&fraction_digits[start..]);
+                quotient.push(')');
+            }
+            None => quotient.push_str(&fraction_digits),
+        }
+    }
+    Ok(LongDivision {
+        quotient,
+        integer_digits,
+        fraction_digits,
+        repetend_start,
+        truncated,
+        remainder: whole_remainder,
+        steps,
+    })
+}
+
+/// `{ quotient, integer_digits, fraction_digits, repetend_start,
+/// truncated, remainder, steps: [{ partial, digit, product, remainder,
+/// fractional }] }`; see [`LongDivision`].
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = long_division)]
+pub fn long_division_js(
+    dividend: f64,
+    divisor: f64,
+    max_digits: u32,
+) -> Result<JsValue, CalcErrorJs> {
+    if max_digits > MAX_FRACTION_DIGITS {
+        let err = CalcError::InvalidArgument(format!(
+            "At most {} digits after the point, got {}",
+            MAX_FRACTION_DIGITS, max_digits
+        ));
+        return Err(CalcErrorJs::new(err, Some(max_digits as f64)));
+    }
+    let working = long_division(
+        whole_number(dividend)?,
+        whole_number(divisor)?,
+        max_digits as usize,
+    )
+    .map_err(|e| CalcErrorJs::new(e, Some(divisor)))?;
+    Ok(serde_wasm_bindgen::to_value(&working).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminating_division() {
+        let working = long_division(765, 4, 10).unwrap();
+        assert_eq!(working.quotient, "191.25");
+        assert_eq!(working.remainder, 1);
+        assert_eq!(working.repetend_start, None);
+        assert!(!working.truncated);
+        let partials: Vec<u64> = working.steps.iter().map(|s| s.partial).collect();
+        assert_eq!(partials, vec![7, 36, 5, 10, 20]);
+        assert_eq!(
+            working.steps[0],
+            DivisionStep {
+                partial: 7,
+                digit: 1,
+                product: 4,
+                remainder: 3,
+                fractional: false,
+            }
+        );
+        assert!(working.steps[3].fractional);
+    }
+
+    #[test]
+    fn test_repeating_division() {
+        let working = long_division(73, 6, 10).unwrap();
+        assert_eq!(working.quotient, "12.1(6)");
+        assert_eq!(working.repetend_start, Some(1));
+        assert_eq!(long_division(1, 7, 10).unwrap().quotient, "0.(142857)");
+        assert_eq!(long_division(1, 3, 10).unwrap().quotient, "0.(3)");
+        assert_eq!(long_division(0, 5, 10).unwrap().quotient, "0");
+        let pending = long_division(1, 97, 5).unwrap();
+        assert_eq!(pending.quotient, "0.01030");
+        assert!(pending.truncated);
+    }
+
+    #[test]
+    fn test_long_division_errors() {
+        assert_eq!(long_division(1, 0, 10), Err(CalcError::DivisionByZero));
+        assert!(matches!(
+            long_division(1, MAX_DIVISOR + 1, 10),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(long_division(u64::MAX, MAX_DIVISOR, 40).is_ok());
+    }
+}
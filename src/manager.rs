@@ -0,0 +1,197 @@
+//! Several named calculators under one owner, for dashboards.
+//!
+//! A [`CalculatorManager`] holds one [`Calculator`] per session name. From
+//! Rust, [`CalculatorManager::session_mut`] gives direct access; from
+//! JavaScript, where the manager cannot hand out references to its
+//! calculators, [`CalculatorManager::rpc`] forwards a JSON-RPC request to
+//! one of them (see `Calculator::rpc`).
+//!
+//! [`CalculatorManager::aggregate`] combines the current values of some or
+//! all of the sessions: `sum`, `average`, `min` or `max`.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::aggregate::{min_max_array, sum_array};
+use crate::expr::unknown_identifier;
+use crate::rpc::unroutable_response;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Default)]
+pub struct CalculatorManager {
+    sessions: BTreeMap<String, Calculator>,
+}
+
+impl CalculatorManager {
+    pub fn session(&self, name: &str) -> Option<&Calculator> {
+        self.sessions.get(name)
+    }
+
+    pub fn session_mut(&mut self, name: &str) -> Option<&mut Calculator> {
+        self.sessions.get_mut(name)
+    }
+
+    /// Adds `calc` as the session `name`, replacing any session of that
+    /// name.
+    pub fn insert_session(&mut self, name: &str, calc: Calculator) {
+        self.sessions.insert(name.to_string(), calc);
+    }
+
+    fn known_session(&self, name: &str) -> Result<&Calculator, CalcError> {
+        self.sessions
+            .get(name)
+            .ok_or_else(|| unknown_identifier(name, self.sessions.keys().map(String::as_str)))
+    }
+
+    /// Combines the current values of the sessions in `names`, or of every
+    /// session when `names` is empty. `op` is `sum`, `average`, `min` or
+    /// `max`.
+    pub fn aggregate(&self, op: &str, names: &[String]) -> Result<f64, CalcError> {
+        let values = if names.is_empty() {
+            self.sessions.values().map(Calculator::get_value).collect()
+        } else {
+            names
+                .iter()
+                .map(|name| self.known_session(name).map(Calculator::get_value))
+                .collect::<Result<Vec<f64>, CalcError>>()?
+        };
+        match op {
+            "sum" => Ok(sum_array(&values)),
+            "average" if values.is_empty() => {
+                Err(CalcError::InvalidArgument("No data".to_string()))
+            }
+            "average" => Ok(sum_array(&values) / values.len() as f64),
+            "min" => min_max_array(&values).map(|(min, _)| min),
+            "max" => min_max_array(&values).map(|(_, max)| max),
+            other => Err(CalcError::InvalidArgument(format!(
+                "Unknown aggregate '{}' (expected sum, average, min or max)",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl CalculatorManager {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> CalculatorManager {
+        CalculatorManager::default()
+    }
+
+    /// Starts a new calculator as the session `name`; returns false (and
+    /// keeps the existing one) if the name is taken.
+    pub fn create_session(&mut self, name: &str) -> bool {
+        if self.sessions.contains_key(name) {
+            return false;
+        }
+        self.sessions.insert(name.to_string(), Calculator::new());
+        true
+    }
+
+    /// Removes a session; returns whether it existed.
+    pub fn remove_session(&mut self, name: &str) -> bool {
+        self.sessions.remove(name).is_some()
+    }
+
+    /// Names of the sessions, sorted.
+    pub fn session_names(&self) -> Vec<String> {
+        self.sessions.keys().cloned().collect()
+    }
+
+    /// Runs `Calculator::rpc` on the session `name`. An unknown session
+    /// gets a JSON-RPC error response naming it.
+    pub fn rpc(&mut self, name: &str, request_json: &str) -> String {
+        match self.sessions.get_mut(name) {
+            Some(calc) => calc.rpc(request_json),
+            None => {
+                let known = self.sessions.keys().map(String::as_str);
+                unroutable_response(request_json, unknown_identifier(name, known))
+            }
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_value)]
+    pub fn get_value_js(&self, name: &str) -> Result<f64, CalcErrorJs> {
+        self.known_session(name)
+            .map(Calculator::get_value)
+            .map_err(CalcErrorJs::from)
+    }
+
+    /// `names` selects the sessions to combine; leave it empty for all.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = aggregate)]
+    pub fn aggregate_js(&self, op: &str, names: Vec<String>) -> Result<f64, CalcErrorJs> {
+        self.aggregate(op, &names).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> CalculatorManager {
+        let mut manager = CalculatorManager::new();
+        for (name, value) in [("north", 120.0), ("south", 80.0), ("west", -10.0)] {
+            assert!(manager.create_session(name));
+            manager.session_mut(name).unwrap().set_value(value);
+        }
+        manager
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let manager = manager();
+        assert_eq!(manager.aggregate("sum", &[]), Ok(190.0));
+        assert_eq!(
+            manager.aggregate("average", &names(&["north", "south"])),
+            Ok(100.0)
+        );
+        assert_eq!(manager.aggregate("min", &[]), Ok(-10.0));
+        assert_eq!(
+            manager.aggregate("max", &names(&["south", "west"])),
+            Ok(80.0)
+        );
+        assert!(manager.aggregate("median", &[]).is_err());
+        assert!(matches!(
+            manager.aggregate("sum", &names(&["nort"])),
+            Err(CalcError::UnknownIdentifier { suggestions, .. }) if suggestions == ["north"]
+        ));
+
+        let empty = CalculatorManager::new();
+        assert_eq!(empty.aggregate("sum", &[]), Ok(0.0));
+        assert!(empty.aggregate("average", &[]).is_err());
+        assert!(empty.aggregate("max", &[]).is_err());
+    }
+
+    #[test]
+    fn test_sessions() {
+        let mut manager = manager();
+        assert!(!manager.create_session("north"));
+        assert_eq!(manager.session("north").unwrap().get_value(), 120.0);
+        let response = manager.rpc(
+            "south",
+            r#"{"jsonrpc":"2.0","method":"add","params":[5],"id":1}"#,
+        );
+        assert!(response.contains(r#""result":85.0"#), "{}", response);
+        assert_eq!(manager.aggregate("sum", &[]), Ok(195.0));
+
+        let response = manager.rpc(
+            "east",
+            r#"{"jsonrpc":"2.0","method":"add","params":[5],"id":1}"#,
+        );
+        assert!(response.contains("UNKNOWN_IDENTIFIER"), "{}", response);
+        assert!(response.contains(r#""id":1"#), "{}", response);
+        assert!(manager.remove_session("west"));
+        assert_eq!(manager.session_names(), vec!["north", "south"]);
+    }
+}
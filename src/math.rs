@@ -0,0 +1,115 @@
+//! The transcendental functions behind `evaluate` and the calculator's
+//! operations.
+//!
+//! `+ - * /` and `sqrt` are correctly rounded by IEEE 754, so they agree on
+//! every platform. `sin`, `exp`, `powf` and the rest are not: Rust hands
+//! them to the platform's math library, and the last bit of a result can
+//! differ between wasm in a browser, x86-64 and ARM builds. With the
+//! `deterministic` feature these functions use the pure-Rust `libm` port
+//! of musl's implementations instead, which computes bit-identical results
+//! on every target, so calculators synchronized across devices stay in
+//! step. Without it they are the std methods.
+//!
+//! The history, `evaluate`, user functions and the calculator's power and
+//! root operations go through here; the domain modules (finance, units,
+//! ...) still use std.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Whether this build uses the deterministic implementations.
+pub const DETERMINISTIC: bool = cfg!(feature = "deterministic");
+
+macro_rules! unary {
+    ($($(#[$doc:meta])* $name:ident => $std:ident),* $(,)?) => {$(
+        $(#[$doc])*
+        #[inline]
+        pub fn $name(x: f64) -> f64 {
+            #[cfg(feature = "deterministic")]
+            {
+                libm::$name(x)
+            }
+            #[cfg(not(feature = "deterministic"))]
+            {
+                x.$std()
+            }
+        }
+    )*};
+}
+
+unary! {
+    sin => sin,
+    cos => cos,
+    tan => tan,
+    asin => asin,
+    acos => acos,
+    atan => atan,
+    exp => exp,
+    /// The natural logarithm.
+    log => ln,
+    log10 => log10,
+    cbrt => cbrt,
+}
+
+#[inline]
+pub fn pow(x: f64, y: f64) -> f64 {
+    #[cfg(feature = "deterministic")]
+    {
+        libm::pow(x, y)
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        x.powf(y)
+    }
+}
+
+/// `x` to an integer power. std's `powi` is not even consistently rounded
+/// on one platform, so the deterministic build uses [`pow`].
+#[inline]
+pub fn powi(x: f64, n: i32) -> f64 {
+    #[cfg(feature = "deterministic")]
+    {
+        libm::pow(x, n as f64)
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        x.powi(n)
+    }
+}
+
+/// Whether this build computes bit-identical results on every platform;
+/// peers in a synchronized session should all say the same.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn deterministic_math() -> bool {
+    DETERMINISTIC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_math_matches_known_values() {
+        assert_eq!(sin(0.0), 0.0);
+        assert_eq!(cos(0.0), 1.0);
+        assert_eq!(exp(0.0), 1.0);
+        assert_eq!(log(1.0), 0.0);
+        assert_eq!(log10(1000.0), 3.0);
+        assert_eq!(pow(2.0, 10.0), 1024.0);
+        assert_eq!(powi(10.0, -2), 0.01);
+        assert_eq!(cbrt(-27.0), -3.0);
+        assert!((sin(std::f64::consts::FRAC_PI_6) - 0.5).abs() < 1e-15);
+        assert!((atan(1.0) - std::f64::consts::FRAC_PI_4).abs() < 1e-15);
+        assert_eq!(deterministic_math(), cfg!(feature = "deterministic"));
+    }
+
+    // What musl's algorithms give on every platform; `exp(1)` is one ulp
+    // above the nearest double to e.
+    #[cfg(feature = "deterministic")]
+    #[test]
+    fn test_deterministic_bits() {
+        assert_eq!(exp(1.0).to_bits(), 0x4005_BF0A_8B14_576A);
+        assert_eq!(sin(1.0).to_bits(), 0x3FEA_ED54_8F09_0CEE);
+        assert_eq!(pow(10.0, 0.5).to_bits(), 0x4009_4C58_3ADA_5B53);
+    }
+}
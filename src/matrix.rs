@@ -0,0 +1,479 @@
+//! Dense matrices and vector products.
+//!
+//! [`Matrix`] is stored row-major, matching the flat `Float64Array` it is
+//! built from in JS. Determinant, inverse and [`Matrix::solve`] use
+//! Gaussian elimination with partial pivoting; a pivot that is negligible
+//! next to the largest entry makes the matrix singular.
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::{timing, CalcErrorJs};
+
+/// The largest `n` [`Matrix::identity`] builds: a million entries, 8 MB.
+pub const MAX_IDENTITY_SIZE: usize = 1000;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+fn dimension_error(message: String) -> CalcError {
+    CalcError::InvalidArgument(message)
+}
+
+fn singular() -> CalcError {
+    CalcError::InvalidArgument("Matrix is singular".to_string())
+}
+
+impl Matrix {
+    /// A `rows` x `cols` matrix from row-major `data`.
+    pub fn from_rows(rows: usize, cols: usize, data: Vec<f64>) -> Result<Matrix, CalcError> {
+        if rows == 0 || cols == 0 {
+            return Err(dimension_error(
+                "A matrix needs at least one row and column".to_string(),
+            ));
+        }
+        if rows.checked_mul(cols) != Some(data.len()) {
+            return Err(dimension_error(format!(
+                "{} values do not fill a {}x{} matrix",
+                data.len(),
+                rows,
+                cols
+            )));
+        }
+        Ok(Matrix { rows, cols, data })
+    }
+
+    fn at(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    fn same_shape(&self, other: &Matrix, what: &str) -> Result<(), CalcError> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(dimension_error(format!(
+                "Cannot {} a {}x{} and a {}x{} matrix",
+                what, self.rows, self.cols, other.rows, other.cols
+            )));
+        }
+        Ok(())
+    }
+
+    fn square(&self, what: &str) -> Result<usize, CalcError> {
+        if self.rows != self.cols {
+            return Err(dimension_error(format!(
+                "{} needs a square matrix, got {}x{}",
+                what, self.rows, self.cols
+            )));
+        }
+        Ok(self.rows)
+    }
+
+    pub fn add(&self, other: &Matrix) -> Result<Matrix, CalcError> {
+        self.same_shape(other, "add")?;
+        let data = self.data.iter().zip(&other.data).map(|(a, b)| a + b);
+        Ok(Matrix {
+            data: data.collect(),
+            ..*self
+        })
+    }
+
+    pub fn sub(&self, other: &Matrix) -> Result<Matrix, CalcError> {
+        self.same_shape(other, "subtract")?;
+        let data = self.data.iter().zip(&other.data).map(|(a, b)| a - b);
+        Ok(Matrix {
+            data: data.collect(),
+            ..*self
+        })
+    }
+
+    /// The `n` x `n` identity matrix, for `n` up to [`MAX_IDENTITY_SIZE`].
+    pub fn identity(n: usize) -> Result<Matrix, CalcError> {
+        let len = n
+            .checked_mul(n)
+            .filter(|_| n <= MAX_IDENTITY_SIZE)
+            .ok_or_else(|| {
+                dimension_error(format!(
+                    "Identity matrix size must be at most {}, got {}",
+                    MAX_IDENTITY_SIZE, n
+                ))
+            })?;
+        let mut data = vec![0.0; len];
+        for i in 0..n {
+            data[i * n + i] = 1.0;
+        }
+        Matrix::from_rows(n, n, data)
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Result<Matrix, CalcError> {
+        if self.cols != other.rows {
+            return Err(dimension_error(format!(
+                "Cannot multiply a {}x{} by a {}x{} matrix",
+                self.rows, self.cols, other.rows, other.cols
+            )));
+        }
+        let mut data = vec![0.0; self.rows * other.cols];
+        for i in 0..self.rows {
+            for k in 0..self.cols {
+                let a = self.at(i, k);
+                for j in 0..other.cols {
+                    data[i * other.cols + j] += a * other.at(k, j);
+                }
+            }
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data,
+        })
+    }
+
+    pub fn determinant(&self) -> Result<f64, CalcError> {
+        let n = self.square("Determinant")?;
+        let mut work = self.data.clone();
+        let tolerance = self.tolerance();
+        let mut det = 1.0;
+        for col in 0..n {
+            match pivot(&mut work, n, n, col, tolerance) {
+                Some(true) => det = -det,
+                Some(false) => {}
+                None => return Ok(0.0),
+            }
+            det *= work[col * n + col];
+            eliminate_below(&mut work, n, n, col);
+        }
+        Ok(det)
+    }
+
+    pub fn inverse(&self) -> Result<Matrix, CalcError> {
+        let n = self.square("Inverse")?;
+        // Reduce [A | I] to [I | A^-1].
+        let width = 2 * n;
+        let mut work = vec![0.0; n * width];
+        for i in 0..n {
+            work[i * width..i * width + n].copy_from_slice(&self.data[i * n..(i + 1) * n]);
+            work[i * width + n + i] = 1.0;
+        }
+        reduce(&mut work, n, width, self.tolerance())?;
+        let data = (0..n)
+            .flat_map(|i| work[i * width + n..(i + 1) * width].to_vec())
+            .collect();
+        Ok(Matrix {
+            rows: n,
+            cols: n,
+            data,
+        })
+    }
+
+    /// Solves `A x = b` for square, non-singular `A`.
+    pub fn solve(&self, b: &[f64]) -> Result<Vec<f64>, CalcError> {
+        let n = self.square("Solving")?;
+        if b.len() != n {
+            return Err(dimension_error(format!(
+                "Right-hand side has {} values, expected {}",
+                b.len(),
+                n
+            )));
+        }
+        let width = n + 1;
+        let mut work = vec![0.0; n * width];
+        for i in 0..n {
+            work[i * width..i * width + n].copy_from_slice(&self.data[i * n..(i + 1) * n]);
+            work[i * width + n] = b[i];
+        }
+        reduce(&mut work, n, width, self.tolerance())?;
+        Ok((0..n).map(|i| work[i * width + n]).collect())
+    }
+
+    // Pivots at or below this are treated as zero.
+    fn tolerance(&self) -> f64 {
+        let scale = self.data.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+        scale * self.rows as f64 * f64::EPSILON
+    }
+}
+
+// Swaps the row with the largest entry in `col` (from row `col` down) into
+// place. Returns whether rows were swapped, or `None` if every candidate is
+// within `tolerance` of zero.
+fn pivot(work: &mut [f64], rows: usize, width: usize, col: usize, tolerance: f64) -> Option<bool> {
+    let best = (col..rows).max_by(|&a, &b| {
+        work[a * width + col]
+            .abs()
+            .total_cmp(&work[b * width + col].abs())
+    })?;
+    if work[best * width + col].abs() <= tolerance {
+        return None;
+    }
+    if best != col {
+        for j in 0..width {
+            work.swap(best * width + j, col * width + j);
+        }
+    }
+    Some(best != col)
+}
+
+fn eliminate_below(work: &mut [f64], rows: usize, width: usize, col: usize) {
+    let p = work[col * width + col];
+    for i in col + 1..rows {
+        let factor = work[i * width + col] / p;
+        if factor != 0.0 {
+            for j in col..width {
+                work[i * width + j] -= factor * work[col * width + j];
+            }
+        }
+    }
+}
+
+// Gauss-Jordan elimination of the left `rows` x `rows` block of an
+// augmented row-major matrix to the identity.
+fn reduce(work: &mut [f64], rows: usize, width: usize, tolerance: f64) -> Result<(), CalcError> {
+    for col in 0..rows {
+        pivot(work, rows, width, col, tolerance).ok_or_else(singular)?;
+        let p = work[col * width + col];
+        for j in 0..width {
+            work[col * width + j] /= p;
+        }
+        for i in 0..rows {
+            let factor = work[i * width + col];
+            if i != col && factor != 0.0 {
+                for j in 0..width {
+                    work[i * width + j] -= factor * work[col * width + j];
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// One row per line, values separated by spaces.
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.data.chunks(self.cols).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            f.write_str(&cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Matrix {
+    /// Builds a matrix from a row-major `Float64Array` of `rows * cols`
+    /// values.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new(rows: usize, cols: usize, data: &[f64]) -> Result<Matrix, CalcErrorJs> {
+        Matrix::from_rows(rows, cols, data.to_vec()).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = identity)]
+    pub fn identity_js(n: usize) -> Result<Matrix, CalcErrorJs> {
+        Matrix::identity(n).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The values, row-major.
+    pub fn data(&self) -> Vec<f64> {
+        self.data.clone()
+    }
+
+    /// The value at `row`, `col`, or `undefined` out of range.
+    pub fn get(&self, row: usize, col: usize) -> Option<f64> {
+        (row < self.rows && col < self.cols).then(|| self.at(row, col))
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let data = (0..self.cols)
+            .flat_map(|j| (0..self.rows).map(move |i| (i, j)))
+            .map(|(i, j)| self.at(i, j))
+            .collect();
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            data,
+        }
+    }
+
+    pub fn scale(&self, factor: f64) -> Matrix {
+        Matrix {
+            data: self.data.iter().map(|v| v * factor).collect(),
+            ..*self
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&self, other: &Matrix) -> Result<Matrix, CalcErrorJs> {
+        self.add(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = sub)]
+    pub fn sub_js(&self, other: &Matrix) -> Result<Matrix, CalcErrorJs> {
+        self.sub(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = mul)]
+    pub fn mul_js(&self, other: &Matrix) -> Result<Matrix, CalcErrorJs> {
+        timing::time("matrix.mul", || self.mul(other)).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = determinant)]
+    pub fn determinant_js(&self) -> Result<f64, CalcErrorJs> {
+        timing::time("matrix.determinant", || self.determinant()).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = inverse)]
+    pub fn inverse_js(&self) -> Result<Matrix, CalcErrorJs> {
+        timing::time("matrix.inverse", || self.inverse()).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = solve)]
+    pub fn solve_js(&self, b: &[f64]) -> Result<Vec<f64>, CalcErrorJs> {
+        timing::time("matrix.solve", || self.solve(b)).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+pub fn dot(a: &[f64], b: &[f64]) -> Result<f64, CalcError> {
+    if a.len() != b.len() {
+        return Err(dimension_error(format!(
+            "Dot product of vectors of length {} and {}",
+            a.len(),
+            b.len()
+        )));
+    }
+    Ok(a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+/// Cross product of two 3-vectors.
+pub fn cross(a: &[f64], b: &[f64]) -> Result<Vec<f64>, CalcError> {
+    let (&[a1, a2, a3], &[b1, b2, b3]) = (a, b) else {
+        return Err(dimension_error(format!(
+            "Cross product needs two 3-vectors, got lengths {} and {}",
+            a.len(),
+            b.len()
+        )));
+    };
+    Ok(vec![
+        a2 * b3 - a3 * b2,
+        a3 * b1 - a1 * b3,
+        a1 * b2 - a2 * b1,
+    ])
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = dot)]
+pub fn dot_js(a: &[f64], b: &[f64]) -> Result<f64, CalcErrorJs> {
+    dot(a, b).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = cross)]
+pub fn cross_js(a: &[f64], b: &[f64]) -> Result<Vec<f64>, CalcErrorJs> {
+    cross(a, b).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(rows: usize, cols: usize, data: &[f64]) -> Matrix {
+        Matrix::from_rows(rows, cols, data.to_vec()).unwrap()
+    }
+
+    fn close(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-12)
+    }
+
+    #[test]
+    fn test_arithmetic_and_transpose() {
+        let a = m(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let b = m(3, 2, &[7.0, 8.0, 9.0, 10.0, 11.0, 12.0]);
+        assert_eq!(a.mul(&b), Ok(m(2, 2, &[58.0, 64.0, 139.0, 154.0])));
+        assert_eq!(a.transpose().data(), vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(a.add(&a), Ok(a.scale(2.0)));
+        assert_eq!(a.sub(&a).unwrap().data(), vec![0.0; 6]);
+        assert!(a.add(&b).is_err());
+        assert!(a.mul(&a).is_err());
+        assert!(Matrix::from_rows(2, 2, vec![1.0; 3]).is_err());
+        assert_eq!(a.to_string(), "1 2 3\n4 5 6");
+        assert_eq!(a.get(1, 2), Some(6.0));
+        assert_eq!(a.get(2, 0), None);
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(m(2, 2, &[3.0, 8.0, 4.0, 6.0]).determinant(), Ok(-14.0));
+        let a = m(3, 3, &[6.0, 1.0, 1.0, 4.0, -2.0, 5.0, 2.0, 8.0, 7.0]);
+        assert!((a.determinant().unwrap() - -306.0).abs() < 1e-9);
+        let singular = m(2, 2, &[1.0, 2.0, 2.0, 4.0]);
+        assert_eq!(singular.determinant(), Ok(0.0));
+        assert!(m(2, 3, &[0.0; 6]).determinant().is_err());
+    }
+
+    #[test]
+    fn test_inverse_and_solve() {
+        let a = m(3, 3, &[2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0]);
+        let inverse = a.inverse().unwrap();
+        let identity = Matrix::identity(3).unwrap();
+        assert!(close(&a.mul(&inverse).unwrap().data(), &identity.data()));
+        assert!(Matrix::identity(0).is_err());
+        assert_eq!(
+            Matrix::identity(MAX_IDENTITY_SIZE).unwrap().rows,
+            MAX_IDENTITY_SIZE
+        );
+        assert!(Matrix::identity(MAX_IDENTITY_SIZE + 1).is_err());
+        assert!(Matrix::identity(usize::MAX).is_err());
+        assert!(close(
+            &a.solve(&[8.0, -11.0, -3.0]).unwrap(),
+            &[2.0, 3.0, -1.0]
+        ));
+        let singular = m(2, 2, &[1.0, 2.0, 2.0, 4.0]);
+        assert_eq!(
+            singular.inverse(),
+            Err(CalcError::InvalidArgument("Matrix is singular".to_string()))
+        );
+        assert!(singular.solve(&[1.0, 2.0]).is_err());
+        assert!(a.solve(&[1.0]).is_err());
+    }
+
+    #[test]
+    fn test_vector_products() {
+        assert_eq!(dot(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]), Ok(32.0));
+        assert_eq!(
+            cross(&[1.0, 0.0, 0.0], &[0.0, 1.0, 0.0]),
+            Ok(vec![0.0, 0.0, 1.0])
+        );
+        assert!(dot(&[1.0], &[1.0, 2.0]).is_err());
+        assert!(cross(&[1.0, 2.0], &[3.0, 4.0]).is_err());
+    }
+}
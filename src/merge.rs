@@ -0,0 +1,150 @@
+//! Combining the tapes of two calculators.
+//!
+//! When two people work the same problem on separate calculators,
+//! [`merge_histories`] makes one tape of both: the entries are interleaved
+//! by timestamp, each tape keeping its own order, and replayed to give the
+//! combined running value. Ties are broken by operation name, operand and
+//! author,
+//! never by which calculator came first, so `merge_histories(a, b)` and
+//! `merge_histories(b, a)` give the same tape. An entry both tapes hold
+//! (same time, operation, operand and author, as after a fork or sync) is
+//! kept
+//! once, so merging a tape with itself changes nothing.
+
+use std::cmp::Ordering;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::summary::SessionStats;
+use crate::tape::operation_name;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, CalculationHistory, Calculator};
+
+// The order of two entries on the merged tape; `Equal` means they record
+// the same step.
+fn merge_order(x: &CalculationHistory, y: &CalculationHistory) -> Ordering {
+    let operand = |e: &CalculationHistory| e.operand2.map(f64::to_bits);
+    x.timestamp
+        .total_cmp(&y.timestamp)
+        .then_with(|| operation_name(&x.operation).cmp(&operation_name(&y.operation)))
+        .then_with(|| operand(x).cmp(&operand(y)))
+        .then_with(|| x.author.cmp(&y.author))
+}
+
+/// The entries of `a` and `b` interleaved, without recomputing them.
+pub fn interleave_histories(a: &Calculator, b: &Calculator) -> Vec<CalculationHistory> {
+    let (mut a, mut b) = (a.history.iter().peekable(), b.history.iter().peekable());
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    loop {
+        let next = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) => match merge_order(x, y) {
+                Ordering::Less => a.next(),
+                Ordering::Greater => b.next(),
+                Ordering::Equal => {
+                    b.next();
+                    a.next()
+                }
+            },
+            (Some(_), None) => a.next(),
+            (None, Some(_)) => b.next(),
+            (None, None) => break,
+        };
+        merged.extend(next.cloned());
+    }
+    merged
+}
+
+/// A new calculator holding the merged tape of `a` and `b`, replayed from
+/// the first entry's starting value. Settings, functions, variables and
+/// quotas come from `a`; the session summary and error log start empty, no
+/// listener is attached and it is not a sync replica. Fails if a step
+/// fails in its new place, a division now dividing by zero, say.
+pub fn merge_histories(a: &Calculator, b: &Calculator) -> Result<Calculator, CalcError> {
+    let merged = interleave_histories(a, b);
+    let mut calc = a.clone();
+    calc.recording = None;
+    calc.keypad = Default::default();
+    calc.leave_sync();
+    calc.session = SessionStats::new();
+    calc.error_log = Default::default();
+    let start = merged.first().map_or(0.0, |e| e.operand1);
+    calc.history = merged.into();
+    calc.replay_history(start)?;
+    calc.session = SessionStats::new();
+    calc.error_log = Default::default();
+    Ok(calc)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = merge_histories)]
+pub fn merge_histories_js(a: &Calculator, b: &Calculator) -> Result<Calculator, CalcErrorJs> {
+    merge_histories(a, b).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Operation::{self, *};
+
+    // A calculator that applied `steps`, stamped with the given times.
+    fn tape(steps: &[(f64, Operation, Option<f64>)]) -> Calculator {
+        let mut calc = Calculator::new();
+        for (timestamp, operation, operand) in steps {
+            calc.apply(operation, *operand).unwrap();
+            calc.history.back_mut().unwrap().timestamp = *timestamp;
+        }
+        calc
+    }
+
+    #[test]
+    fn test_merge_histories() {
+        let a = tape(&[(1000.0, Add, Some(1.0)), (3000.0, Multiply, Some(2.0))]);
+        let b = tape(&[(2000.0, Add, Some(10.0)), (4000.0, MemoryStore, None)]);
+        let merged = merge_histories(&a, &b).unwrap();
+        assert_eq!(merged.history_series(), [1.0, 11.0, 22.0, 22.0]);
+        assert_eq!(merged.get_value(), 22.0);
+        assert_eq!(merged.get_memory(), 22.0);
+        assert_eq!(
+            merged.history_timestamps(),
+            [1000.0, 2000.0, 3000.0, 4000.0]
+        );
+        assert!(merged.check_invariants().is_ok());
+        // The originals are untouched.
+        assert_eq!(a.get_value(), 2.0);
+        // Argument order does not matter.
+        let reversed = merge_histories(&b, &a).unwrap();
+        assert_eq!(reversed.history_series(), merged.history_series());
+    }
+
+    #[test]
+    fn test_merge_ties_and_shared_entries() {
+        let a = tape(&[(1000.0, Add, Some(5.0)), (2000.0, Multiply, Some(3.0))]);
+        let b = tape(&[(1000.0, Add, Some(5.0)), (2000.0, Add, Some(1.0))]);
+        // The shared first entry is kept once; at 2000 `add` sorts first.
+        let merged = merge_histories(&a, &b).unwrap();
+        assert_eq!(merged.history_series(), [5.0, 6.0, 18.0]);
+        assert_eq!(
+            merge_histories(&b, &a).unwrap().history_series(),
+            [5.0, 6.0, 18.0]
+        );
+        assert_eq!(
+            merge_histories(&a, &a).unwrap().history_series(),
+            [5.0, 15.0]
+        );
+        let empty = Calculator::new();
+        assert_eq!(merge_histories(&empty, &empty).unwrap().get_value(), 0.0);
+    }
+
+    #[test]
+    fn test_merge_failure() {
+        let a = tape(&[(1000.0, Add, Some(4.0)), (3000.0, Reciprocal, None)]);
+        let b = tape(&[(2000.0, Clear, None)]);
+        // Interleaved, the reciprocal is of 0.
+        assert_eq!(
+            merge_histories(&a, &b).err(),
+            Some(CalcError::DivisionByZero)
+        );
+    }
+}
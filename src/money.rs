@@ -0,0 +1,412 @@
+//! Exact money arithmetic in integer minor units.
+//!
+//! A [`Money`] is a whole number of the currency's minor unit (cents for
+//! USD, yen for JPY, fils for KWD) plus the currency code, so sums never
+//! drift the way `f64` totals do. Wherever a result falls between two minor
+//! units it is rounded half to even (banker's rounding), and
+//! [`Money::allocate`] hands out the leftover units so the parts always add
+//! up to the whole.
+//!
+//! Amounts are kept within ±(2^53 - 1) minor units, so `minor_units` is
+//! always exact in JS; anything larger is [`CalcError::TooLarge`].
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::currency::{currency_code, split_decimal};
+use crate::ratio::{largest_remainder, split_by_ratio};
+#[cfg(feature = "wasm")]
+use crate::{fraction::safe_integer, CalcErrorJs};
+use crate::{CalcError, MAX_SAFE_INTEGER};
+
+// ISO 4217 currencies whose minor unit is not the hundredth.
+const NO_DECIMALS: &[&str] = &[
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND",
+    "VUV", "XAF", "XOF", "XPF",
+];
+const THREE_DECIMALS: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+// An amount past ±(2^53 - 1) minor units.
+const TOO_LARGE: CalcError = CalcError::TooLarge { operation: "money" };
+
+/// Most parts one `split` will produce.
+pub const MAX_SPLIT_PARTS: u32 = 10_000;
+
+/// Decimal places of the minor unit of `currency`: 0 for JPY, 3 for KWD
+/// and 2 for everything else.
+pub fn minor_unit_decimals(currency: &str) -> u32 {
+    if NO_DECIMALS.contains(&currency) {
+        0
+    } else if THREE_DECIMALS.contains(&currency) {
+        3
+    } else {
+        2
+    }
+}
+
+// `n / d` rounded half to even, for `d > 0`.
+fn div_half_even(n: i128, d: i128) -> i128 {
+    let (q, r) = (n.div_euclid(d), n.rem_euclid(d));
+    match (2 * r).cmp(&d) {
+        std::cmp::Ordering::Less => q,
+        std::cmp::Ordering::Greater => q + 1,
+        std::cmp::Ordering::Equal => q + (q & 1),
+    }
+}
+
+// A decimal string as `digits / 10^scale`.
+pub(crate) fn decimal(text: &str) -> Result<(i128, u32), CalcError> {
+    let (negative, int, frac) = split_decimal(text, true)?;
+    let digits = format!("{}{}", int, frac);
+    let mut value: i128 = 0;
+    for b in digits.bytes() {
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add((b - b'0') as i128))
+            .ok_or(TOO_LARGE)?;
+    }
+    Ok((if negative { -value } else { value }, frac.len() as u32))
+}
+
+// `value / 10^scale` rounded half to even, to a whole number.
+pub(crate) fn round_scaled(value: i128, scale: u32) -> i128 {
+    match 10i128.checked_pow(scale) {
+        Some(d) => div_half_even(value, d),
+        // Beyond 10^38 a product of an i64 and a 17-digit factor is far
+        // below half a unit.
+        None => 0,
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor: i64,
+    currency: String,
+}
+
+impl Money {
+    fn checked(minor: i128, currency: &str) -> Result<Money, CalcError> {
+        if minor.unsigned_abs() > MAX_SAFE_INTEGER as u128 {
+            return Err(TOO_LARGE);
+        }
+        Ok(Money {
+            minor: minor as i64,
+            currency: currency.to_string(),
+        })
+    }
+
+    /// `minor` units of `currency` (a code such as `"usd"` or `"EUR"`,
+    /// case-insensitive).
+    pub fn from_minor(minor: i64, currency: &str) -> Result<Money, CalcError> {
+        Money::checked(minor as i128, &currency_code(currency)?)
+    }
+
+    /// Parses a decimal amount in major units, such as `"12.345"`, rounding
+    /// half to even to the minor unit (`"12.345"` USD is 12.34).
+    pub fn parse(amount: &str, currency: &str) -> Result<Money, CalcError> {
+        let currency = currency_code(currency)?;
+        let (value, scale) = decimal(amount)?;
+        let places = minor_unit_decimals(&currency);
+        let minor = if scale >= places {
+            round_scaled(value, scale - places)
+        } else {
+            value
+                .checked_mul(10i128.pow(places - scale))
+                .ok_or(TOO_LARGE)?
+        };
+        Money::checked(minor, &currency)
+    }
+
+    /// Converts a JS number in major units. The number is read as the
+    /// shortest decimal that prints as it, so `2.675` is 2.68 in the way it
+    /// was typed, not 2.67 as its binary value would round.
+    pub fn from_major(amount: f64, currency: &str) -> Result<Money, CalcError> {
+        if !amount.is_finite() {
+            return Err(CalcError::InvalidArgument(format!(
+                "Invalid amount {}",
+                amount
+            )));
+        }
+        Money::parse(&amount.to_string(), currency)
+    }
+
+    pub fn minor(&self) -> i64 {
+        self.minor
+    }
+
+    pub fn currency_code(&self) -> &str {
+        &self.currency
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), CalcError> {
+        if self.currency != other.currency {
+            return Err(CalcError::InvalidArgument(format!(
+                "Cannot combine {} with {}",
+                self.currency, other.currency
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn add(&self, other: &Money) -> Result<Money, CalcError> {
+        self.same_currency(other)?;
+        Money::checked(self.minor as i128 + other.minor as i128, &self.currency)
+    }
+
+    pub fn subtract(&self, other: &Money) -> Result<Money, CalcError> {
+        self.same_currency(other)?;
+        Money::checked(self.minor as i128 - other.minor as i128, &self.currency)
+    }
+
+    /// Multiplies by `factor` (a quantity, a rate such as `1.0825`) and
+    /// rounds half to even. Like [`Money::from_major`], `factor` is taken
+    /// as the decimal it prints as, so the product is exact before rounding.
+    pub fn multiply(&self, factor: f64) -> Result<Money, CalcError> {
+        if !factor.is_finite() {
+            return Err(CalcError::InvalidArgument(format!(
+                "Invalid factor {}",
+                factor
+            )));
+        }
+        let (value, scale) = decimal(&factor.to_string())?;
+        let product = value.checked_mul(self.minor as i128).ok_or(TOO_LARGE)?;
+        Money::checked(round_scaled(product, scale), &self.currency)
+    }
+
+    /// Splits the amount in proportion to `ratios`: `[1, 1, 1]` is an even
+    /// three-way split, `[70, 30]` a 70/30 one. Each part is rounded toward
+    /// zero and the minor units left over go one each to the parts that lost
+    /// the most to rounding (the earlier part on a tie), so the parts always
+    /// sum to the whole.
+    pub fn allocate(&self, ratios: &[u32]) -> Result<Vec<Money>, CalcError> {
+        let weights: Vec<u128> = ratios.iter().map(|&r| r as u128).collect();
+        let shares = largest_remainder(self.minor.unsigned_abs() as u128, &weights)?;
+        let sign = self.minor.signum() as i128;
+        shares
+            .into_iter()
+            .map(|share| Money::checked(sign * share as i128, &self.currency))
+            .collect()
+    }
+
+    /// `parts` equal shares, the first ones a minor unit larger when the
+    /// amount does not divide evenly.
+    pub fn split(&self, parts: u32) -> Result<Vec<Money>, CalcError> {
+        if parts == 0 || parts > MAX_SPLIT_PARTS {
+            return Err(CalcError::InvalidArgument(format!(
+                "Can split into 1 to {} parts, got {}",
+                MAX_SPLIT_PARTS, parts
+            )));
+        }
+        self.allocate(&vec![1; parts as usize])
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Money {
+    /// Throws `INVALID_ARGUMENT` unless `minor` is a safe integer.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = from_minor)]
+    pub fn from_minor_js(minor: f64, currency: &str) -> Result<Money, CalcErrorJs> {
+        let minor = safe_integer(minor).map_err(|e| CalcErrorJs::new(e, Some(minor)))?;
+        Money::from_minor(minor, currency).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = parse)]
+    pub fn parse_js(amount: &str, currency: &str) -> Result<Money, CalcErrorJs> {
+        Money::parse(amount, currency).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = from_major)]
+    pub fn from_major_js(amount: f64, currency: &str) -> Result<Money, CalcErrorJs> {
+        Money::from_major(amount, currency).map_err(|e| CalcErrorJs::new(e, Some(amount)))
+    }
+
+    pub fn minor_units(&self) -> f64 {
+        self.minor as f64
+    }
+
+    pub fn currency(&self) -> String {
+        self.currency.clone()
+    }
+
+    pub fn decimals(&self) -> u32 {
+        minor_unit_decimals(&self.currency)
+    }
+
+    /// The amount in major units as a decimal string, `"-12.30"`.
+    pub fn amount(&self) -> String {
+        let places = self.decimals() as usize;
+        let digits = format!("{:0>width$}", self.minor.unsigned_abs(), width = places + 1);
+        let (int, frac) = digits.split_at(digits.len() - places);
+        let sign = if self.minor < 0 { "-" } else { "" };
+        if frac.is_empty() {
+            format!("{}{}", sign, int)
+        } else {
+            format!("{}{}.{}", sign, int, frac)
+        }
+    }
+
+    /// The amount in major units as a JS number, for display or charts;
+    /// do arithmetic on the `Money` itself.
+    pub fn to_major(&self) -> f64 {
+        self.minor as f64 / 10f64.powi(self.decimals() as i32)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor < 0
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&self, other: &Money) -> Result<Money, CalcErrorJs> {
+        self.add(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = subtract)]
+    pub fn subtract_js(&self, other: &Money) -> Result<Money, CalcErrorJs> {
+        self.subtract(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = multiply)]
+    pub fn multiply_js(&self, factor: f64) -> Result<Money, CalcErrorJs> {
+        self.multiply(factor)
+            .map_err(|e| CalcErrorJs::new(e, Some(factor)))
+    }
+
+    /// `ratios` is a `Uint32Array`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = allocate)]
+    pub fn allocate_js(&self, ratios: &[u32]) -> Result<Vec<Money>, CalcErrorJs> {
+        self.allocate(ratios).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = split)]
+    pub fn split_js(&self, parts: u32) -> Result<Vec<Money>, CalcErrorJs> {
+        self.split(parts)
+            .map_err(|e| CalcErrorJs::new(e, Some(parts as f64)))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// `"12.30 USD"`.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount(), self.currency)
+    }
+}
+
+/// Splits `amount_cents`, a whole number of minor units, by `weights`
+/// (which may be decimals, such as `[0.5, 0.3, 0.2]`) with the
+/// largest-remainder method, so the parts always sum to `amount_cents`:
+/// `allocate(1000, [1, 1, 1])` is `[334, 333, 333]`. Leftover cents go to
+/// the parts that lost the most to rounding, the earlier on a tie.
+/// Weights must be non-negative and not all zero.
+pub fn allocate(amount_cents: f64, weights: &[f64]) -> Result<Vec<f64>, CalcError> {
+    if amount_cents.fract() != 0.0 || amount_cents.abs() > MAX_SAFE_INTEGER {
+        return Err(CalcError::InvalidArgument(format!(
+            "Amount must be a whole number of cents within ±(2^53 - 1), got {}",
+            amount_cents
+        )));
+    }
+    split_by_ratio(amount_cents, weights)
+}
+
+/// `weights` is a `Float64Array`; so is the result.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = allocate)]
+pub fn allocate_cents_js(amount_cents: f64, weights: &[f64]) -> Result<Vec<f64>, CalcErrorJs> {
+    allocate(amount_cents, weights).map_err(|e| CalcErrorJs::new(e, Some(amount_cents)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(amount: &str) -> Money {
+        Money::parse(amount, "usd").unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(usd("12.3").minor(), 1230);
+        assert_eq!(usd("-0.05").to_string(), "-0.05 USD");
+        assert_eq!(Money::parse("1500", "JPY").unwrap().to_string(), "1500 JPY");
+        assert_eq!(Money::parse("1.5", "KWD").unwrap().amount(), "1.500");
+        // Half to even at the cent.
+        assert_eq!(usd("0.125").minor(), 12);
+        assert_eq!(usd("0.135").minor(), 14);
+        assert_eq!(usd("-0.125").minor(), -12);
+        assert_eq!(usd("0.1251").minor(), 13);
+        assert_eq!(Money::from_major(2.675, "USD").unwrap().minor(), 268);
+        assert!(Money::parse("1.2.3", "USD").is_err());
+        assert!(Money::parse("1", "U$D").is_err());
+        assert_eq!(
+            Money::parse("1e20", "USD").map(|_| ()),
+            Err(CalcError::Syntax {
+                message: "Invalid character 'e' in amount".to_string(),
+                position: 1,
+            })
+        );
+        assert_eq!(Money::parse("100000000000000", "USD"), Err(TOO_LARGE));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let sum = (0..10).try_fold(usd("0"), |acc, _| acc.add(&usd("0.10")));
+        assert_eq!(sum, Ok(usd("1.00")));
+        assert_eq!(usd("5").subtract(&usd("7.25")), Ok(usd("-2.25")));
+        assert!(usd("1").add(&Money::parse("1", "EUR").unwrap()).is_err());
+        assert_eq!(usd("19.99").multiply(3.0), Ok(usd("59.97")));
+        assert_eq!(usd("10.00").multiply(0.0825), Ok(usd("0.82")));
+        assert_eq!(usd("0.30").multiply(0.05), Ok(usd("0.02")));
+        assert_eq!(usd("0.50").multiply(0.05), Ok(usd("0.02")));
+        assert_eq!(usd("0.01").multiply(1e-300), Ok(usd("0")));
+        assert_eq!(usd("1").multiply(1e300), Err(TOO_LARGE));
+    }
+
+    #[test]
+    fn test_allocate() {
+        let thirds = usd("100").split(3).unwrap();
+        assert_eq!(thirds, vec![usd("33.34"), usd("33.33"), usd("33.33")]);
+        let parts = usd("0.05").allocate(&[3, 7]).unwrap();
+        assert_eq!(parts, vec![usd("0.02"), usd("0.03")]);
+        let parts = usd("-10").allocate(&[1, 0, 2]).unwrap();
+        assert_eq!(parts, vec![usd("-3.33"), usd("0"), usd("-6.67")]);
+        let total = parts.iter().try_fold(usd("0"), |acc, p| acc.add(p));
+        assert_eq!(total, Ok(usd("-10")));
+        assert!(usd("1").allocate(&[0, 0]).is_err());
+        assert!(usd("1").split(0).is_err());
+        assert!(usd("1").split(MAX_SPLIT_PARTS + 1).is_err());
+    }
+
+    #[test]
+    fn test_allocate_cents() {
+        assert_eq!(
+            allocate(1000.0, &[1.0, 1.0, 1.0]),
+            Ok(vec![334.0, 333.0, 333.0])
+        );
+        assert_eq!(allocate(1.0, &[0.5, 0.3, 0.2]), Ok(vec![1.0, 0.0, 0.0]));
+        let parts = allocate(-9_999.0, &[0.25, 0.25, 0.5]).unwrap();
+        assert_eq!(parts, vec![-2_500.0, -2_500.0, -4_999.0]);
+        assert_eq!(parts.iter().sum::<f64>(), -9_999.0);
+        assert!(allocate(10.5, &[1.0, 1.0]).is_err());
+        assert!(allocate(10.0, &[0.0, 0.0]).is_err());
+        assert!(allocate(10.0, &[1.0, -1.0]).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Where each operand came from, for audit trails.
+//!
+//! Bookkeeping users need to trace every figure on a tape back to its
+//! origin. An operand passed to `add`, `divide`, ... is typed directly;
+//! [`Calculator::apply_from`] instead takes the operand from the memory,
+//! the result of an earlier history entry or a variable, and the entry it
+//! records says so:
+//!
+//! ```json
+//! { "operation": "Add", "operand2": 15, "operand_source": { "kind": "memory" }, ... }
+//! { "operation": "Multiply", "operand2": 0.2,
+//!   "operand_source": { "kind": "variable", "name": "rate" }, ... }
+//! ```
+//!
+//! Entries without `operand_source` were typed directly. History entries
+//! are referred to by the numbers `get_history_since` uses, which stay put
+//! as the history limit evicts older entries. Replaying or editing the
+//! history keeps the sources of the entries it recomputes, except that an
+//! edited operand is direct.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::replay::has_editable_operand;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OperandSource {
+    /// Typed or passed in directly.
+    #[default]
+    Direct,
+    Memory,
+    /// The result of the history entry numbered `entry`.
+    History {
+        entry: usize,
+    },
+    Variable {
+        name: String,
+    },
+}
+
+impl OperandSource {
+    pub fn is_direct(&self) -> bool {
+        *self == OperandSource::Direct
+    }
+}
+
+impl Calculator {
+    /// Applies `operation` (one that takes an operand, such as `Add` or
+    /// `SetValue`) with its operand read from `source`, and records where
+    /// it came from.
+    pub fn apply_from(
+        &mut self,
+        operation: &Operation,
+        source: &OperandSource,
+    ) -> Result<f64, CalcError> {
+        let operand = self.source_operand(operation, source)?;
+        self.operand_source = Some(source.clone());
+        let result = self.apply(operation, Some(operand));
+        self.operand_source = None;
+        result
+    }
+
+    // Reads the operand, logging the failure if there is none.
+    fn source_operand(
+        &self,
+        operation: &Operation,
+        source: &OperandSource,
+    ) -> Result<f64, CalcError> {
+        if !has_editable_operand(operation) {
+            let err = CalcError::InvalidArgument(format!("{:?} takes no operand", operation));
+            return Err(self.fail(err, None));
+        }
+        let operand = match source {
+            OperandSource::Direct => Err(CalcError::InvalidArgument(
+                "A direct operand is passed to apply, not apply_from".to_string(),
+            )),
+            OperandSource::Memory => Ok(self.memory),
+            OperandSource::History { entry } => entry
+                .checked_sub(self.history_evicted)
+                .and_then(|index| self.history.get(index))
+                .map(|e| e.result)
+                .ok_or_else(|| {
+                    CalcError::InvalidArgument(format!(
+                        "No history entry {} (entries {} to {} are retained)",
+                        entry,
+                        self.history_evicted,
+                        self.history_next_index()
+                    ))
+                }),
+            // Already logged.
+            OperandSource::Variable { name } => return self.get_variable(name),
+        };
+        operand.map_err(|e| self.fail(e, None))
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `op` is an operation name as in `apply_batch` (`"+"`, `"multiply"`,
+    /// `"set_value"`, ...) and `source` is `{ kind: "memory" }`,
+    /// `{ kind: "history", entry }` or `{ kind: "variable", name }`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = apply_from)]
+    pub fn apply_from_js(&mut self, op: &str, source: JsValue) -> Result<f64, CalcErrorJs> {
+        let operation = crate::tape::parse_operation(op).ok_or_else(|| {
+            let err = CalcError::InvalidArgument(format!("Unknown operation '{}'", op));
+            CalcErrorJs::from(self.fail(err, None))
+        })?;
+        let source: OperandSource = serde_wasm_bindgen::from_value(source).map_err(|e| {
+            let err = CalcError::InvalidArgument(format!("Invalid operand source: {}", e));
+            CalcErrorJs::from(self.fail(err, None))
+        })?;
+        self.apply_from(&operation, &source)
+            .map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(calc: &Calculator) -> Vec<OperandSource> {
+        calc.history_range(0, usize::MAX)
+            .into_iter()
+            .map(|e| e.operand_source)
+            .collect()
+    }
+
+    #[test]
+    fn test_sources_are_recorded() {
+        let mut calc = Calculator::new();
+        calc.set_value(15.0);
+        calc.memory_store();
+        calc.set_variable("rate", 0.2).unwrap();
+        assert_eq!(
+            calc.apply_from(&Operation::Add, &OperandSource::Memory),
+            Ok(30.0)
+        );
+        let rate = OperandSource::Variable {
+            name: "rate".to_string(),
+        };
+        assert_eq!(calc.apply_from(&Operation::Multiply, &rate), Ok(6.0));
+        let first = OperandSource::History { entry: 0 };
+        assert_eq!(calc.apply_from(&Operation::SetValue, &first), Ok(15.0));
+        calc.add(1.0);
+        assert_eq!(
+            sources(&calc),
+            vec![
+                OperandSource::Direct,
+                OperandSource::Direct,
+                OperandSource::Memory,
+                rate.clone(),
+                first,
+                OperandSource::Direct,
+            ]
+        );
+
+        let json = serde_json::to_value(calc.history_range(2, 2)).unwrap();
+        assert_eq!(
+            json[0]["operand_source"],
+            serde_json::json!({ "kind": "memory" })
+        );
+        assert_eq!(
+            json[1]["operand_source"],
+            serde_json::json!({ "kind": "variable", "name": "rate" })
+        );
+        let json = serde_json::to_value(calc.history_range(0, 1)).unwrap();
+        assert!(json[0].get("operand_source").is_none());
+    }
+
+    #[test]
+    fn test_invalid_sources() {
+        let mut calc = Calculator::new();
+        calc.add(2.0);
+        let missing = OperandSource::History { entry: 1 };
+        assert!(calc.apply_from(&Operation::Add, &missing).is_err());
+        assert!(calc
+            .apply_from(&Operation::Sqrt, &OperandSource::Memory)
+            .is_err());
+        assert!(calc
+            .apply_from(&Operation::Add, &OperandSource::Direct)
+            .is_err());
+        let unset = OperandSource::Variable {
+            name: "x".to_string(),
+        };
+        assert!(calc.apply_from(&Operation::Add, &unset).is_err());
+        // A failed operation leaves no source for the next one.
+        assert_eq!(
+            calc.apply_from(&Operation::Divide, &OperandSource::Memory),
+            Err(CalcError::DivisionByZero)
+        );
+        calc.add(1.0);
+        assert_eq!(sources(&calc), vec![OperandSource::Direct; 2]);
+        assert_eq!(calc.error_log().len(), 5);
+    }
+
+    #[test]
+    fn test_replay_keeps_sources() {
+        let mut calc = Calculator::new();
+        calc.set_value(4.0);
+        calc.memory_store();
+        calc.apply_from(&Operation::Add, &OperandSource::Memory)
+            .unwrap();
+        calc.replay_history(1.0).unwrap();
+        assert_eq!(sources(&calc)[2], OperandSource::Memory);
+        calc.edit_history_entry(2, 3.0).unwrap();
+        assert_eq!(sources(&calc)[2], OperandSource::Direct);
+    }
+}
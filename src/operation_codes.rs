@@ -0,0 +1,135 @@
+//! Stable identifiers for operations, for logging and analytics.
+//!
+//! Every [`Operation`] has a numeric [`Operation::code`] and a string
+//! [`Operation::id`] that are fixed once published: new operations get new
+//! codes, and a removed operation's code is never reused, so usage logged
+//! by one release of the crate reads the same in every later one. The enum's
+//! variant order and `Debug` names carry no such promise.
+//!
+//! `Constant` and `Function` each have one code for the whole kind; the
+//! constant or function name is data, not part of the identifier.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::Operation;
+
+/// One entry of [`OPERATION_CODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OperationCode {
+    pub code: u16,
+    pub id: &'static str,
+}
+
+const fn op(code: u16, id: &'static str) -> OperationCode {
+    OperationCode { code, id }
+}
+
+/// Every operation's code and id, by code.
+pub const OPERATION_CODES: &[OperationCode] = &[
+    op(1, "add"),
+    op(2, "subtract"),
+    op(3, "multiply"),
+    op(4, "divide"),
+    op(5, "sqrt"),
+    op(6, "power"),
+    op(7, "set_value"),
+    op(8, "clear"),
+    op(9, "memory_store"),
+    op(10, "memory_recall"),
+    op(11, "memory_clear"),
+    op(12, "memory_add"),
+    op(13, "percent_of"),
+    op(14, "add_percent"),
+    op(15, "subtract_percent"),
+    op(16, "constant"),
+    op(17, "round"),
+    op(18, "nth_root"),
+    op(19, "modulo"),
+    op(20, "reciprocal"),
+    op(21, "abs"),
+    op(22, "negate"),
+    op(23, "function"),
+];
+
+impl Operation {
+    /// The operation's stable numeric code; see the module docs.
+    pub fn code(&self) -> u16 {
+        self.entry().code
+    }
+
+    /// The operation's stable string id, `"add"`, `"memory_store"`,
+    /// `"constant"`: the name `apply_batch` accepts, without any
+    /// constant or function name.
+    pub fn id(&self) -> &'static str {
+        self.entry().id
+    }
+
+    fn entry(&self) -> OperationCode {
+        let code = match self {
+            Operation::Add => 1,
+            Operation::Subtract => 2,
+            Operation::Multiply => 3,
+            Operation::Divide => 4,
+            Operation::Sqrt => 5,
+            Operation::Power => 6,
+            Operation::SetValue => 7,
+            Operation::Clear => 8,
+            Operation::MemoryStore => 9,
+            Operation::MemoryRecall => 10,
+            Operation::MemoryClear => 11,
+            Operation::MemoryAdd => 12,
+            Operation::PercentOf => 13,
+            Operation::AddPercent => 14,
+            Operation::SubtractPercent => 15,
+            Operation::Constant(_) => 16,
+            Operation::Round => 17,
+            Operation::NthRoot => 18,
+            Operation::Modulo => 19,
+            Operation::Reciprocal => 20,
+            Operation::Abs => 21,
+            Operation::Negate => 22,
+            Operation::Function(_) => 23,
+        };
+        // Codes are dense and start at 1.
+        OPERATION_CODES[code as usize - 1]
+    }
+}
+
+/// The code/id table as `[{ code, id }]`, to ship alongside logged usage.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = operation_codes)]
+pub fn operation_codes_js() -> JsValue {
+    serde_wasm_bindgen::to_value(OPERATION_CODES).unwrap_or(JsValue::NULL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tape::parse_operation;
+
+    #[test]
+    fn test_operation_codes_are_stable() {
+        for (i, entry) in OPERATION_CODES.iter().enumerate() {
+            assert_eq!(entry.code as usize, i + 1);
+        }
+        assert_eq!(Operation::Add.code(), 1);
+        assert_eq!(Operation::MemoryStore.id(), "memory_store");
+        assert_eq!(Operation::Function("tip".to_string()).code(), 23);
+        assert_eq!(Operation::Constant("pi".to_string()).id(), "constant");
+    }
+
+    #[test]
+    fn test_operation_ids_round_trip() {
+        for entry in OPERATION_CODES {
+            let name = match entry.id {
+                "constant" => "constant:pi",
+                "function" => "function:f",
+                id => id,
+            };
+            let operation = parse_operation(name).unwrap();
+            assert_eq!((operation.code(), operation.id()), (entry.code, entry.id));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Thin lenses and apertures.
+//!
+//! [`thin_lens`] solves `1/f = 1/d_o + 1/d_i` from any two of the focal
+//! length, object distance and image distance, in any one length unit. The
+//! sign convention is real-is-positive: a converging lens has a positive
+//! focal length, a diverging one a negative focal length, and a virtual
+//! image (on the same side as the object) a negative image distance.
+//! Magnification is `-d_i / d_o`, negative for an inverted image.
+//!
+//! The f-number is the focal length over the aperture diameter.
+//! [`f_number_to_na`] and [`na_to_f_number`] use `N = 1 / (2 NA)`, which is
+//! exact for a lens in air obeying the Abbe sine condition and focused at
+//! infinity.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ThinLens {
+    pub focal_length: f64,
+    pub object_distance: f64,
+    pub image_distance: f64,
+    pub magnification: f64,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn nonzero(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value == 0.0 {
+        return Err(invalid(format!(
+            "{} must be a non-zero number, got {}",
+            what, value
+        )));
+    }
+    Ok(value)
+}
+
+fn positive(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid(format!("{} must be positive, got {}", what, value)));
+    }
+    Ok(value)
+}
+
+// `1 / (1/a - 1/b)`, the distance the lens equation leaves.
+fn remaining(a: f64, b: f64) -> Result<f64, CalcError> {
+    let inverse = 1.0 / a - 1.0 / b;
+    if inverse == 0.0 {
+        return Err(invalid("The image is at infinity"));
+    }
+    Ok(1.0 / inverse)
+}
+
+/// Solves the thin-lens equation for the one of `focal_length`,
+/// `object_distance` and `image_distance` that is `None`.
+pub fn thin_lens(
+    focal_length: Option<f64>,
+    object_distance: Option<f64>,
+    image_distance: Option<f64>,
+) -> Result<ThinLens, CalcError> {
+    let f = focal_length
+        .map(|f| nonzero("Focal length", f))
+        .transpose()?;
+    let d_o = object_distance
+        .map(|d| nonzero("Object distance", d))
+        .transpose()?;
+    let d_i = image_distance
+        .map(|d| nonzero("Image distance", d))
+        .transpose()?;
+    let (f, d_o, d_i) = match (f, d_o, d_i) {
+        (Some(f), Some(d_o), None) => (f, d_o, remaining(f, d_o)?),
+        (Some(f), None, Some(d_i)) => (f, remaining(f, d_i)?, d_i),
+        (None, Some(d_o), Some(d_i)) => {
+            let inverse = 1.0 / d_o + 1.0 / d_i;
+            if inverse == 0.0 {
+                return Err(invalid("The focal length is infinite"));
+            }
+            (1.0 / inverse, d_o, d_i)
+        }
+        (f, d_o, d_i) => {
+            let given = [f, d_o, d_i].iter().filter(|x| x.is_some()).count();
+            return Err(invalid(format!(
+                "Give exactly two of focal_length, object_distance and image_distance, got {}",
+                given
+            )));
+        }
+    };
+    Ok(ThinLens {
+        focal_length: f,
+        object_distance: d_o,
+        image_distance: d_i,
+        magnification: -d_i / d_o,
+    })
+}
+
+/// `-image_distance / object_distance`.
+pub fn magnification(object_distance: f64, image_distance: f64) -> Result<f64, CalcError> {
+    let d_o = nonzero("Object distance", object_distance)?;
+    if !image_distance.is_finite() {
+        return Err(invalid(format!(
+            "Invalid image distance {}",
+            image_distance
+        )));
+    }
+    Ok(-image_distance / d_o)
+}
+
+/// Focal length over aperture diameter: 50 mm at 25 mm is f/2.
+pub fn f_number(focal_length: f64, aperture_diameter: f64) -> Result<f64, CalcError> {
+    Ok(positive("Focal length", focal_length)? / positive("Aperture diameter", aperture_diameter)?)
+}
+
+/// Numerical aperture in air for an f-number of at least 0.5.
+pub fn f_number_to_na(f_number: f64) -> Result<f64, CalcError> {
+    if !f_number.is_finite() || f_number < 0.5 {
+        return Err(invalid(format!(
+            "An f-number in air must be at least 0.5, got {}",
+            f_number
+        )));
+    }
+    Ok(1.0 / (2.0 * f_number))
+}
+
+/// F-number for a numerical aperture in air, over 0 and at most 1.
+pub fn na_to_f_number(na: f64) -> Result<f64, CalcError> {
+    if !na.is_finite() || na <= 0.0 || na > 1.0 {
+        return Err(invalid(format!(
+            "A numerical aperture in air must be over 0 and at most 1, got {}",
+            na
+        )));
+    }
+    Ok(1.0 / (2.0 * na))
+}
+
+/// `{ focal_length, object_distance, image_distance, magnification }`, with
+/// the unknown left `undefined`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = thin_lens)]
+pub fn thin_lens_js(
+    focal_length: Option<f64>,
+    object_distance: Option<f64>,
+    image_distance: Option<f64>,
+) -> Result<JsValue, CalcErrorJs> {
+    let lens =
+        thin_lens(focal_length, object_distance, image_distance).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&lens).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = magnification)]
+pub fn magnification_js(object_distance: f64, image_distance: f64) -> Result<f64, CalcErrorJs> {
+    magnification(object_distance, image_distance)
+        .map_err(|e| CalcErrorJs::new(e, Some(object_distance)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = f_number)]
+pub fn f_number_js(focal_length: f64, aperture_diameter: f64) -> Result<f64, CalcErrorJs> {
+    f_number(focal_length, aperture_diameter).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = f_number_to_na)]
+pub fn f_number_to_na_js(f_number: f64) -> Result<f64, CalcErrorJs> {
+    f_number_to_na(f_number).map_err(|e| CalcErrorJs::new(e, Some(f_number)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = na_to_f_number)]
+pub fn na_to_f_number_js(na: f64) -> Result<f64, CalcErrorJs> {
+    na_to_f_number(na).map_err(|e| CalcErrorJs::new(e, Some(na)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_thin_lens() {
+        // A 50 mm lens focused at 300 mm.
+        let lens = thin_lens(Some(50.0), Some(300.0), None).unwrap();
+        assert!(close(lens.image_distance, 60.0));
+        assert!(close(lens.magnification, -0.2));
+        let back = thin_lens(None, Some(300.0), Some(60.0)).unwrap();
+        assert!(close(back.focal_length, 50.0));
+        let object = thin_lens(Some(50.0), None, Some(60.0)).unwrap();
+        assert!(close(object.object_distance, 300.0));
+
+        // Inside the focal length: a virtual, upright, magnified image.
+        let loupe = thin_lens(Some(50.0), Some(25.0), None).unwrap();
+        assert!(close(loupe.image_distance, -50.0));
+        assert!(close(loupe.magnification, 2.0));
+        // A diverging lens always makes a virtual image.
+        let diverging = thin_lens(Some(-20.0), Some(30.0), None).unwrap();
+        assert!(close(diverging.image_distance, -12.0));
+
+        assert!(thin_lens(Some(50.0), Some(50.0), None).is_err());
+        assert!(thin_lens(Some(50.0), None, None).is_err());
+        assert!(thin_lens(Some(50.0), Some(300.0), Some(60.0)).is_err());
+        assert!(thin_lens(Some(0.0), Some(300.0), None).is_err());
+        assert_eq!(magnification(300.0, 60.0), Ok(-0.2));
+        assert!(magnification(0.0, 60.0).is_err());
+    }
+
+    #[test]
+    fn test_apertures() {
+        assert_eq!(f_number(50.0, 25.0), Ok(2.0));
+        assert!(f_number(50.0, 0.0).is_err());
+        assert_eq!(f_number_to_na(2.0), Ok(0.25));
+        assert_eq!(na_to_f_number(0.25), Ok(2.0));
+        assert_eq!(f_number_to_na(0.5), Ok(1.0));
+        assert!(f_number_to_na(0.4).is_err());
+        assert!(na_to_f_number(1.2).is_err());
+        assert!(na_to_f_number(0.0).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+//! Time-clock rounding for payroll.
+//!
+//! Punches are minutes since midnight (`9:07` is 547). A
+//! [`RoundingPolicy`] rounds each one to the nearest `increment_minutes`,
+//! a half increment going up: with 15 minutes, 9:07 is 9:00 and 9:08 is
+//! 9:15 (the "7-minute rule"). Common increments are 5, 6 (tenths of an
+//! hour) and 15.
+//!
+//! A grace period forgives small overruns: a punch at most
+//! `grace_minutes` past an increment boundary is rounded back to it, so
+//! with a 10-minute grace and 15-minute increments 9:10 counts as 9:00
+//! rather than 9:15.
+//!
+//! [`weekly_hours`] applies one policy to every punch of a week's shifts
+//! and splits the total into regular hours and overtime past 40 hours.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+pub const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Weekly minutes past which hours are overtime.
+pub const OVERTIME_AFTER_MINUTES: u32 = 40 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundingPolicy {
+    /// 1 to 60.
+    pub increment_minutes: u32,
+    /// Less than `increment_minutes`; 0 for none.
+    pub grace_minutes: u32,
+}
+
+/// One worked shift, punches in minutes since midnight. A `clock_out`
+/// before `clock_in` ends the next day.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shift {
+    pub clock_in: u32,
+    pub clock_out: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundedShift {
+    pub clock_in: u32,
+    pub clock_out: u32,
+    /// The punches after rounding; `rounded_out` may exceed a day for a
+    /// shift that ends after midnight.
+    pub rounded_in: u32,
+    pub rounded_out: u32,
+    /// Paid minutes: `rounded_out - rounded_in`.
+    pub minutes: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WeeklyHours {
+    pub shifts: Vec<RoundedShift>,
+    pub total_minutes: u32,
+    pub total_hours: f64,
+    pub regular_hours: f64,
+    pub overtime_hours: f64,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+impl RoundingPolicy {
+    fn check(&self) -> Result<(), CalcError> {
+        if !(1..=60).contains(&self.increment_minutes) {
+            return Err(invalid(format!(
+                "Increment must be 1-60 minutes, got {}",
+                self.increment_minutes
+            )));
+        }
+        if self.grace_minutes >= self.increment_minutes {
+            return Err(invalid(format!(
+                "Grace period must be shorter than the {}-minute increment, got {}",
+                self.increment_minutes, self.grace_minutes
+            )));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, minutes: u32) -> u32 {
+        let increment = self.increment_minutes;
+        let past = minutes % increment;
+        let boundary = minutes - past;
+        if past <= self.grace_minutes || 2 * past < increment {
+            boundary
+        } else {
+            boundary + increment
+        }
+    }
+}
+
+fn check_punch(minutes: u32) -> Result<u32, CalcError> {
+    if minutes >= MINUTES_PER_DAY {
+        return Err(invalid(format!(
+            "Punches are minutes since midnight, 0-{}, got {}",
+            MINUTES_PER_DAY - 1,
+            minutes
+        )));
+    }
+    Ok(minutes)
+}
+
+/// Rounds one punch by `policy`.
+pub fn round_punch(minutes: u32, policy: &RoundingPolicy) -> Result<u32, CalcError> {
+    policy.check()?;
+    Ok(policy.apply(check_punch(minutes)?))
+}
+
+/// Rounds every punch of `shifts` by `policy` and totals the week.
+pub fn weekly_hours(shifts: &[Shift], policy: &RoundingPolicy) -> Result<WeeklyHours, CalcError> {
+    policy.check()?;
+    let shifts = shifts
+        .iter()
+        .map(|shift| {
+            let clock_in = check_punch(shift.clock_in)?;
+            let mut clock_out = check_punch(shift.clock_out)?;
+            if clock_out < clock_in {
+                clock_out += MINUTES_PER_DAY;
+            }
+            let rounded_in = policy.apply(clock_in);
+            let rounded_out = policy.apply(clock_out).max(rounded_in);
+            Ok(RoundedShift {
+                clock_in: shift.clock_in,
+                clock_out: shift.clock_out,
+                rounded_in,
+                rounded_out,
+                minutes: rounded_out - rounded_in,
+            })
+        })
+        .collect::<Result<Vec<RoundedShift>, CalcError>>()?;
+    let total_minutes: u32 = shifts.iter().map(|s| s.minutes).sum();
+    let regular = total_minutes.min(OVERTIME_AFTER_MINUTES);
+    Ok(WeeklyHours {
+        shifts,
+        total_minutes,
+        total_hours: total_minutes as f64 / 60.0,
+        regular_hours: regular as f64 / 60.0,
+        overtime_hours: (total_minutes - regular) as f64 / 60.0,
+    })
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = round_punch)]
+pub fn round_punch_js(
+    minutes: u32,
+    increment_minutes: u32,
+    grace_minutes: u32,
+) -> Result<u32, CalcErrorJs> {
+    let policy = RoundingPolicy {
+        increment_minutes,
+        grace_minutes,
+    };
+    round_punch(minutes, &policy).map_err(|e| CalcErrorJs::new(e, Some(minutes as f64)))
+}
+
+/// `shifts` is an array of `{ clock_in, clock_out }`; returns `{ shifts: [{
+/// clock_in, clock_out, rounded_in, rounded_out, minutes }], total_minutes,
+/// total_hours, regular_hours, overtime_hours }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = weekly_hours)]
+pub fn weekly_hours_js(
+    shifts: JsValue,
+    increment_minutes: u32,
+    grace_minutes: u32,
+) -> Result<JsValue, CalcErrorJs> {
+    let shifts: Vec<Shift> = serde_wasm_bindgen::from_value(shifts)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Invalid shifts: {}", e))))?;
+    let policy = RoundingPolicy {
+        increment_minutes,
+        grace_minutes,
+    };
+    let week = weekly_hours(&shifts, &policy).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&week).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(increment_minutes: u32, grace_minutes: u32) -> RoundingPolicy {
+        RoundingPolicy {
+            increment_minutes,
+            grace_minutes,
+        }
+    }
+
+    fn at(hours: u32, minutes: u32) -> u32 {
+        hours * 60 + minutes
+    }
+
+    #[test]
+    fn test_round_punch() {
+        let quarter = policy(15, 0);
+        assert_eq!(round_punch(at(9, 7), &quarter), Ok(at(9, 0)));
+        assert_eq!(round_punch(at(9, 8), &quarter), Ok(at(9, 15)));
+        assert_eq!(round_punch(at(23, 53), &quarter), Ok(at(24, 0)));
+        let tenths = policy(6, 0);
+        assert_eq!(round_punch(at(9, 2), &tenths), Ok(at(9, 0)));
+        assert_eq!(round_punch(at(9, 3), &tenths), Ok(at(9, 6)));
+        let five = policy(5, 0);
+        assert_eq!(round_punch(at(9, 3), &five), Ok(at(9, 5)));
+
+        // The grace period rounds back what would round up.
+        let graced = policy(15, 10);
+        assert_eq!(round_punch(at(9, 10), &graced), Ok(at(9, 0)));
+        assert_eq!(round_punch(at(9, 11), &graced), Ok(at(9, 15)));
+
+        assert!(round_punch(at(24, 0), &quarter).is_err());
+        assert!(round_punch(0, &policy(0, 0)).is_err());
+        assert!(round_punch(0, &policy(90, 0)).is_err());
+        assert!(round_punch(0, &policy(15, 15)).is_err());
+    }
+
+    #[test]
+    fn test_weekly_hours() {
+        let day = Shift {
+            clock_in: at(8, 53),
+            clock_out: at(17, 8),
+        };
+        let night = Shift {
+            clock_in: at(22, 2),
+            clock_out: at(6, 6),
+        };
+        let week = weekly_hours(&[day; 5], &policy(15, 0)).unwrap();
+        assert_eq!(week.shifts[0].rounded_in, at(9, 0));
+        assert_eq!(week.shifts[0].rounded_out, at(17, 15));
+        assert_eq!(week.total_minutes, 5 * at(8, 15));
+        assert_eq!(week.total_hours, 41.25);
+        assert_eq!(week.regular_hours, 40.0);
+        assert_eq!(week.overtime_hours, 1.25);
+
+        let week = weekly_hours(&[night], &policy(6, 0)).unwrap();
+        assert_eq!(week.shifts[0].rounded_in, at(22, 0));
+        assert_eq!(week.shifts[0].rounded_out, at(30, 6));
+        assert_eq!(week.total_hours, 8.1);
+        assert_eq!(week.overtime_hours, 0.0);
+
+        // A shift shorter than the rounding is not negative.
+        let blip = Shift {
+            clock_in: at(9, 8),
+            clock_out: at(9, 9),
+        };
+        let week = weekly_hours(&[blip], &policy(15, 0)).unwrap();
+        assert_eq!(week.total_minutes, 0);
+        assert!(weekly_hours(&[], &policy(15, 0)).is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+//! Place-value decomposition, for rendering "4 hundreds, 0 tens, 5 ones"
+//! style breakdowns.
+//!
+//! Digits are those of the shortest decimal that reads back as the same
+//! `f64`, so `0.1` decomposes as one tenth rather than the binary value's
+//! long expansion.
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+// Names of each power of 1000, up to 10^33.
+const GROUPS: [&str; 12] = [
+    "",
+    "thousand",
+    "million",
+    "billion",
+    "trillion",
+    "quadrillion",
+    "quintillion",
+    "sextillion",
+    "septillion",
+    "octillion",
+    "nonillion",
+    "decillion",
+];
+
+/// One digit and the place it occupies.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaceValue {
+    pub digit: u8,
+    /// The power of ten: 2 for hundreds, -1 for tenths.
+    pub place: i32,
+    /// `hundreds`, `ten thousands`, `tenths`, `ten-thousandths`, ...; past
+    /// the decillions, `10^40` or `10^-40`.
+    pub name: String,
+    /// `digit * 10^place`, e.g. 400 for the 4 in 405.
+    pub value: f64,
+}
+
+// "ten thousand" for 4, or `None` past the named groups.
+fn power_name(power: u32) -> Option<String> {
+    let group = GROUPS.get(power as usize / 3)?;
+    let name = match (["", "ten", "hundred"][power as usize % 3], *group) {
+        (unit, "") => unit.to_string(),
+        ("", group) => group.to_string(),
+        (unit, group) => format!("{} {}", unit, group),
+    };
+    Some(name)
+}
+
+fn place_name(place: i32) -> String {
+    if place == 0 {
+        return "ones".to_string();
+    }
+    let power = place.unsigned_abs();
+    match power_name(power) {
+        Some(name) if place > 0 => format!("{}s", name),
+        Some(name) => format!("{}ths", name.replace(' ', "-")),
+        None => format!("10^{}", place),
+    }
+}
+
+/// The digits of `|value|` from the most significant place down, zeros
+/// included: `405.2` gives hundreds 4, tens 0, ones 5, tenths 2.
+pub fn place_values(value: f64) -> Result<Vec<PlaceValue>, CalcError> {
+    if !value.is_finite() {
+        return Err(CalcError::InvalidArgument(format!(
+            "Cannot decompose {}",
+            value
+        )));
+    }
+    // `Display` for f64 never uses exponent notation.
+    let text = value.abs().to_string();
+    let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+    let int_places = (0..int.len() as i32).rev();
+    let frac_places = (1..=frac.len() as i32).map(|k| -k);
+    Ok(int
+        .bytes()
+        .chain(frac.bytes())
+        .zip(int_places.chain(frac_places))
+        .map(|(b, place)| {
+            let digit = b - b'0';
+            PlaceValue {
+                digit,
+                place,
+                name: place_name(place),
+                value: format!("{}e{}", digit, place).parse().unwrap_or(0.0),
+            }
+        })
+        .collect())
+}
+
+/// Array of `{ digit, place, name, value }`, most significant first.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = place_values)]
+pub fn place_values_js(value: f64) -> Result<JsValue, CalcErrorJs> {
+    let places = place_values(value).map_err(|e| CalcErrorJs::new(e, Some(value)))?;
+    Ok(serde_wasm_bindgen::to_value(&places).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(value: f64) -> Vec<(u8, String)> {
+        place_values(value)
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.digit, p.name))
+            .collect()
+    }
+
+    #[test]
+    fn test_place_values() {
+        let places = place_values(405.25).unwrap();
+        let names: Vec<&str> = places.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["hundreds", "tens", "ones", "tenths", "hundredths"]);
+        let values: Vec<f64> = places.iter().map(|p| p.value).collect();
+        assert_eq!(values, [400.0, 0.0, 5.0, 0.2, 0.05]);
+        assert_eq!(places[3].place, -1);
+        assert_eq!(
+            summary(-0.1),
+            [(0, "ones".to_string()), (1, "tenths".to_string())]
+        );
+        assert_eq!(summary(0.0), [(0, "ones".to_string())]);
+    }
+
+    #[test]
+    fn test_place_names() {
+        assert_eq!(place_name(4), "ten thousands");
+        assert_eq!(place_name(6), "millions");
+        assert_eq!(place_name(11), "hundred billions");
+        assert_eq!(place_name(-4), "ten-thousandths");
+        assert_eq!(place_name(-6), "millionths");
+        assert_eq!(place_name(40), "10^40");
+        assert_eq!(place_name(-40), "10^-40");
+        assert_eq!(place_values(1e21).unwrap()[0].name, "sextillions");
+        assert!(place_values(f64::NAN).is_err());
+    }
+}
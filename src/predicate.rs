@@ -0,0 +1,352 @@
+//! Conditions such as `value > 100` or `index % 2 == 0`.
+//!
+//! The expression grammar has no comparison operators, so a [`Predicate`]
+//! is an expression, optionally followed by one of `< <= > >= == !=` and
+//! a second expression. The comparison is found outside parentheses before
+//! either side is parsed, so `max(a, b) >= 2 * c` reads as expected. A
+//! predicate without a comparison holds when its value is neither 0 nor
+//! NaN. Equality is exact; compare a difference with a tolerance,
+//! `abs(value - 0.3) < 1e-9`, where rounding matters.
+//!
+//! `!=` always means "not equal", so write a factorial compared for
+//! equality with a space or parentheses, as in `(3!) == 6`.
+//!
+//! A conditional-formatting rule is one predicate tested against many
+//! values. [`predicate_mask`] parses it once and tests it against a whole
+//! `Float64Array`, each element read as `value` and its position as
+//! `index`, without leaving WASM; `Calculator::dataset_mask` does the same
+//! over a named dataset. A value where the predicate cannot be evaluated,
+//! such as a division by zero, is left unmarked rather than failing the
+//! mask, while an unknown name or a syntax error fails it.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, AngleUnit, Dialect, Expr, Scope};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn holds(self, a: f64, b: f64) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterEqual => a >= b,
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        })
+    }
+}
+
+/// A parsed condition; see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub lhs: Expr,
+    pub comparison: Option<(Comparison, Expr)>,
+}
+
+fn syntax(message: impl Into<String>, position: usize) -> CalcError {
+    CalcError::Syntax {
+        message: message.into(),
+        position,
+    }
+}
+
+// `src[offset..]` parsed, with error positions counted from the start of
+// the whole predicate.
+fn parse_side(src: &str, offset: usize) -> Result<Expr, CalcError> {
+    parse(src, Dialect::Standard).map_err(|e| match e {
+        CalcError::Syntax { message, position } => syntax(message, position + offset),
+        other => other,
+    })
+}
+
+// The comparison outside parentheses, as (operator, start, end) in
+// characters.
+fn find_comparison(chars: &[char]) -> Result<Option<(Comparison, usize, usize)>, CalcError> {
+    let mut found = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let (comparison, width) = match (chars[i], next) {
+            ('(', _) => {
+                depth += 1;
+                (None, 1)
+            }
+            (')', _) => {
+                depth = depth.saturating_sub(1);
+                (None, 1)
+            }
+            ('<', Some('=')) => (Some(Comparison::LessEqual), 2),
+            ('>', Some('=')) => (Some(Comparison::GreaterEqual), 2),
+            ('=', Some('=')) => (Some(Comparison::Equal), 2),
+            ('!', Some('=')) => (Some(Comparison::NotEqual), 2),
+            ('<', _) => (Some(Comparison::Less), 1),
+            ('>', _) => (Some(Comparison::Greater), 1),
+            ('=', _) => return Err(syntax("Use == to compare", i)),
+            _ => (None, 1),
+        };
+        if let Some(comparison) = comparison {
+            if depth > 0 {
+                return Err(syntax("Comparisons cannot be nested", i));
+            }
+            if found.is_some() {
+                return Err(syntax("Only one comparison is allowed", i));
+            }
+            found = Some((comparison, i, i + width));
+        }
+        i += width;
+    }
+    Ok(found)
+}
+
+// One element of a series as `value` and `index`, over `scope`.
+struct ElementScope<'a, S: ?Sized> {
+    value: f64,
+    index: f64,
+    scope: &'a S,
+}
+
+impl<S: Scope + ?Sized> Scope for ElementScope<'_, S> {
+    fn variable(&self, name: &str) -> Option<f64> {
+        match name {
+            "value" => Some(self.value),
+            "index" => Some(self.index),
+            _ => self.scope.variable(name),
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self.scope.names();
+        names.extend(["value".to_string(), "index".to_string()]);
+        names
+    }
+
+    fn angle_unit(&self) -> AngleUnit {
+        self.scope.angle_unit()
+    }
+
+    fn dataset(&self, name: &str) -> Option<&[f64]> {
+        self.scope.dataset(name)
+    }
+
+    fn dataset_names(&self) -> Vec<String> {
+        self.scope.dataset_names()
+    }
+}
+
+impl Predicate {
+    pub fn parse(src: &str) -> Result<Predicate, CalcError> {
+        let chars: Vec<char> = src.chars().collect();
+        let Some((comparison, start, end)) = find_comparison(&chars)? else {
+            return Ok(Predicate {
+                lhs: parse_side(src, 0)?,
+                comparison: None,
+            });
+        };
+        let lhs: String = chars[..start].iter().collect();
+        let rhs: String = chars[end..].iter().collect();
+        if lhs.trim().is_empty() {
+            return Err(syntax(format!("Nothing before '{}'", comparison), start));
+        }
+        if rhs.trim().is_empty() {
+            return Err(syntax(format!("Nothing after '{}'", comparison), end));
+        }
+        Ok(Predicate {
+            lhs: parse_side(&lhs, 0)?,
+            comparison: Some((comparison, parse_side(&rhs, end)?)),
+        })
+    }
+
+    /// Whether the condition holds with variables from `scope`.
+    pub fn test<S: Scope + ?Sized>(&self, scope: &S) -> Result<bool, CalcError> {
+        let a = self.lhs.eval(scope)?;
+        match &self.comparison {
+            Some((comparison, rhs)) => Ok(comparison.holds(a, rhs.eval(scope)?)),
+            None => Ok(a != 0.0 && !a.is_nan()),
+        }
+    }
+
+    /// Whether the condition holds for each of `values`, read as `value`
+    /// and `index` over `scope`; see the [module docs](self) for values
+    /// where it cannot be evaluated.
+    pub fn test_each<S: Scope + ?Sized>(
+        &self,
+        values: &[f64],
+        scope: &S,
+    ) -> Result<Vec<bool>, CalcError> {
+        let mut mask = Vec::with_capacity(values.len());
+        for (index, &value) in values.iter().enumerate() {
+            let element = ElementScope {
+                value,
+                index: index as f64,
+                scope,
+            };
+            mask.push(match self.test(&element) {
+                Ok(holds) => holds,
+                Err(
+                    CalcError::DivisionByZero | CalcError::NegativeSqrt | CalcError::InvalidInput,
+                ) => false,
+                Err(e) => return Err(e),
+            });
+        }
+        Ok(mask)
+    }
+
+    /// The sides, for collecting the variables the predicate reads.
+    pub fn sides(&self) -> impl Iterator<Item = &Expr> {
+        std::iter::once(&self.lhs).chain(self.comparison.iter().map(|(_, rhs)| rhs))
+    }
+}
+
+/// Whether `predicate` holds with the variables in `bindings`.
+pub fn evaluate_predicate(
+    predicate: &str,
+    bindings: &BTreeMap<String, f64>,
+) -> Result<bool, CalcError> {
+    Predicate::parse(predicate)?.test(bindings)
+}
+
+/// Whether `predicate` holds for each of `values`; see the
+/// [module docs](self).
+pub fn predicate_mask(
+    predicate: &str,
+    values: &[f64],
+    bindings: &BTreeMap<String, f64>,
+) -> Result<Vec<bool>, CalcError> {
+    Predicate::parse(predicate)?.test_each(values, bindings)
+}
+
+#[cfg(feature = "wasm")]
+fn bindings_from_js(bindings: JsValue) -> Result<BTreeMap<String, f64>, CalcErrorJs> {
+    if bindings.is_undefined() || bindings.is_null() {
+        return Ok(BTreeMap::new());
+    }
+    serde_wasm_bindgen::from_value(bindings).map_err(|e| {
+        CalcErrorJs::from(CalcError::InvalidArgument(format!(
+            "Invalid bindings: {}",
+            e
+        )))
+    })
+}
+
+/// `bindings` is an optional object of variable values, such as
+/// `{ value: 120, budget: 100 }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = evaluate_predicate)]
+pub fn evaluate_predicate_js(predicate: &str, bindings: JsValue) -> Result<bool, CalcErrorJs> {
+    evaluate_predicate(predicate, &bindings_from_js(bindings)?).map_err(CalcErrorJs::from)
+}
+
+/// A `Uint8Array` with 1 where `predicate` holds for the element of
+/// `values` and 0 elsewhere.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = predicate_mask)]
+pub fn predicate_mask_js(
+    predicate: &str,
+    values: &[f64],
+    bindings: JsValue,
+) -> Result<Vec<u8>, CalcErrorJs> {
+    let mask = predicate_mask(predicate, values, &bindings_from_js(bindings)?)
+        .map_err(CalcErrorJs::from)?;
+    Ok(mask.into_iter().map(u8::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holds(src: &str, value: f64) -> Result<bool, CalcError> {
+        let scope = BTreeMap::from([("value".to_string(), value)]);
+        Predicate::parse(src)?.test(&scope)
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(holds("value > 100", 120.0), Ok(true));
+        assert_eq!(holds("value>100", 100.0), Ok(false));
+        assert_eq!(holds("value >= 100", 100.0), Ok(true));
+        assert_eq!(holds("max(value, 3) <= 2 * (1 + 1)", 4.0), Ok(true));
+        assert_eq!(holds("value % 2 == 0", 6.0), Ok(true));
+        assert_eq!(holds("value != 6", 6.0), Ok(false));
+        assert_eq!(holds("(3!) == value", 6.0), Ok(true));
+        // Without a comparison, nonzero holds.
+        assert_eq!(holds("value - 1", 1.0), Ok(false));
+        assert_eq!(holds("value", 0.5), Ok(true));
+        assert_eq!(holds("value > 0/0", 1.0).ok(), None);
+    }
+
+    #[test]
+    fn test_predicate_errors() {
+        let position = |src| match Predicate::parse(src) {
+            Err(CalcError::Syntax { position, .. }) => Some(position),
+            _ => None,
+        };
+        assert_eq!(position("value = 1"), Some(6));
+        assert_eq!(position("1 < value < 3"), Some(10));
+        assert_eq!(position("max(value > 1)"), Some(10));
+        assert_eq!(position("> 1"), Some(0));
+        assert_eq!(position("value >"), Some(7));
+        // Positions on the right point into the whole predicate.
+        assert_eq!(position("value > 2 *"), Some(11));
+        assert!(matches!(
+            holds("price > 1", 1.0),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+    }
+
+    #[test]
+    fn test_masks() {
+        let bindings = BTreeMap::from([("budget".to_string(), 100.0)]);
+        assert_eq!(evaluate_predicate("budget >= 100", &bindings), Ok(true));
+        let spend = [90.0, 120.0, 105.0, 130.0];
+        assert_eq!(
+            predicate_mask("value > budget * 1.1", &spend, &bindings),
+            Ok(vec![false, true, false, true])
+        );
+        assert_eq!(
+            predicate_mask("index < 1", &spend, &bindings),
+            Ok(vec![true, false, false, false])
+        );
+        // Undefined at a value: unmarked, not a failed mask.
+        assert_eq!(
+            predicate_mask("sqrt(value) > 1", &[-4.0, 4.0], &bindings),
+            Ok(vec![false, true])
+        );
+        assert_eq!(
+            predicate_mask("1 / value > 0", &[0.0, 2.0], &bindings),
+            Ok(vec![false, true])
+        );
+        assert!(predicate_mask("value > limit", &spend, &bindings).is_err());
+        assert_eq!(predicate_mask("value > limit", &[], &bindings), Ok(vec![]));
+    }
+}
@@ -0,0 +1,107 @@
+//! Incognito mode, for calculators on shared devices.
+//!
+//! While incognito is on, nothing a user works on is kept: operations are
+//! not recorded in the history (memory operations included) and failures
+//! are not written to the error log. Turning it on also scrubs what was
+//! kept before: the history, the error log and the session recap's totals.
+//! The current value and memory are live state, not a log, and stay, and
+//! change listeners still hear about them. A replica stops syncing, since
+//! its sync log is a record too.
+//!
+//! The flag is part of `get_settings()` and of `canonical_state_string`,
+//! so a restored or inspected calculator says whether it was incognito.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::Calculator;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Turns incognito mode on, scrubbing the history, error log, session
+    /// totals and sync log, or off again.
+    pub fn set_incognito(&mut self, enabled: bool) {
+        if enabled && !self.incognito {
+            if !self.history.is_empty() {
+                self.history.clear();
+                self.history_rewritten();
+            }
+            self.clear_error_log();
+            self.reset_session();
+            self.leave_sync();
+        }
+        self.incognito = enabled;
+    }
+
+    pub fn is_incognito(&self) -> bool {
+        self.incognito
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incognito_records_nothing() {
+        let mut calc = Calculator::new();
+        calc.add(5.0);
+        calc.memory_store();
+        assert!(calc.divide(0.0).is_err());
+        let version = calc.history_version();
+        calc.set_incognito(true);
+        assert!(calc.is_incognito());
+        assert_eq!(calc.history_count(), 0);
+        assert_ne!(calc.history_version(), version);
+        assert_eq!(calc.error_log_count(), 0);
+        assert_eq!(calc.session_summary().total_operations, 0);
+        // Live state stays.
+        assert_eq!(calc.get_value(), 5.0);
+        assert_eq!(calc.get_memory(), 5.0);
+
+        calc.add(1.0);
+        calc.memory_add();
+        assert!(calc.divide(0.0).is_err());
+        assert_eq!(calc.get_value(), 6.0);
+        assert_eq!(calc.history_count(), 0);
+        assert_eq!(calc.error_log_count(), 0);
+        assert!(calc.settings().incognito);
+        assert!(calc.canonical_state_string().contains("incognito: true\n"));
+
+        calc.set_incognito(false);
+        calc.add(1.0);
+        assert_eq!(calc.history_count(), 1);
+    }
+
+    #[test]
+    fn test_incognito_still_notifies_changes() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        use crate::Operation;
+
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let entries = Rc::new(RefCell::new(0));
+        let mut calc = Calculator::new();
+        let sink = changes.clone();
+        calc.on_change(move |value, memory| sink.borrow_mut().push((value, memory)));
+        let sink = entries.clone();
+        calc.on_history(move |_| *sink.borrow_mut() += 1);
+        calc.set_incognito(true);
+        calc.add(5.0);
+        calc.memory_store();
+        calc.apply_all([(Operation::Add, Some(1.0))]).unwrap();
+        assert_eq!(*changes.borrow(), [(5.0, 0.0), (5.0, 5.0), (6.0, 5.0)]);
+        assert_eq!(*entries.borrow(), 0);
+    }
+
+    #[test]
+    fn test_incognito_setting() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        calc.update_settings(&serde_json::json!({ "incognito": true }))
+            .unwrap();
+        assert!(calc.is_incognito());
+        assert_eq!(calc.history_count(), 0);
+    }
+}
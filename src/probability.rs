@@ -0,0 +1,186 @@
+//! Everyday probability: "at least once", odds and expected value.
+//!
+//! Odds are odds in favour, `favor : against`, so a probability of 0.25 is
+//! odds of 1 : 3. [`probability_to_odds`] returns them as the single number
+//! `favor / against`.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::{whole_number, CalcErrorJs};
+
+/// How far the probabilities of an outcomes table may sum from 1.
+const TOTAL_TOLERANCE: f64 = 1e-9;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Outcome {
+    pub value: f64,
+    pub probability: f64,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn probability(p: f64) -> Result<f64, CalcError> {
+    if !(0.0..=1.0).contains(&p) {
+        return Err(invalid(format!(
+            "Probability must be between 0 and 1, got {}",
+            p
+        )));
+    }
+    Ok(p)
+}
+
+/// The probability that an event of probability `p` happens at least once
+/// in `trials` independent tries: `1 - (1 - p)^trials`.
+pub fn p_at_least_one(p: f64, trials: u64) -> Result<f64, CalcError> {
+    let p = probability(p)?;
+    if p == 1.0 {
+        return Ok(if trials == 0 { 0.0 } else { 1.0 });
+    }
+    // ln_1p and exp_m1 keep one-in-a-million chances from rounding away.
+    Ok(-(trials as f64 * (-p).ln_1p()).exp_m1())
+}
+
+/// Odds in favour for probability `p`, below 1.
+pub fn probability_to_odds(p: f64) -> Result<f64, CalcError> {
+    if probability(p)? == 1.0 {
+        return Err(invalid("A certain event has no finite odds"));
+    }
+    Ok(p / (1.0 - p))
+}
+
+/// The probability for odds of `favor : against`.
+pub fn odds_to_probability(favor: f64, against: f64) -> Result<f64, CalcError> {
+    if !favor.is_finite() || !against.is_finite() || favor < 0.0 || against < 0.0 {
+        return Err(invalid(format!(
+            "Odds must not be negative, got {} : {}",
+            favor, against
+        )));
+    }
+    if favor + against == 0.0 {
+        return Err(invalid("Odds of 0 : 0 are undefined"));
+    }
+    Ok(favor / (favor + against))
+}
+
+/// The probability-weighted mean of `outcomes`, whose probabilities must
+/// sum to 1.
+pub fn expected_value(outcomes: &[Outcome]) -> Result<f64, CalcError> {
+    if outcomes.is_empty() {
+        return Err(invalid("No outcomes"));
+    }
+    let mut total = 0.0;
+    let mut mean = 0.0;
+    for outcome in outcomes {
+        if !outcome.value.is_finite() {
+            return Err(invalid(format!("Invalid outcome value {}", outcome.value)));
+        }
+        total += probability(outcome.probability)?;
+        mean += outcome.value * outcome.probability;
+    }
+    if (total - 1.0).abs() > TOTAL_TOLERANCE {
+        return Err(invalid(format!(
+            "Outcome probabilities must sum to 1, got {}",
+            total
+        )));
+    }
+    Ok(mean)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = p_at_least_one)]
+pub fn p_at_least_one_js(p: f64, trials: f64) -> Result<f64, CalcErrorJs> {
+    p_at_least_one(p, whole_number(trials)?).map_err(|e| CalcErrorJs::new(e, Some(p)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = probability_to_odds)]
+pub fn probability_to_odds_js(p: f64) -> Result<f64, CalcErrorJs> {
+    probability_to_odds(p).map_err(|e| CalcErrorJs::new(e, Some(p)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = odds_to_probability)]
+pub fn odds_to_probability_js(favor: f64, against: f64) -> Result<f64, CalcErrorJs> {
+    odds_to_probability(favor, against).map_err(CalcErrorJs::from)
+}
+
+/// `outcomes` is an array of `{ value, probability }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = expected_value)]
+pub fn expected_value_js(outcomes: JsValue) -> Result<f64, CalcErrorJs> {
+    let outcomes: Vec<Outcome> = serde_wasm_bindgen::from_value(outcomes).map_err(|e| {
+        CalcErrorJs::from(CalcError::InvalidArgument(format!(
+            "Invalid outcomes: {}",
+            e
+        )))
+    })?;
+    expected_value(&outcomes).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn test_p_at_least_one() {
+        // At least one six in four rolls.
+        assert!(close(
+            p_at_least_one(1.0 / 6.0, 4).unwrap(),
+            1.0 - (5.0f64 / 6.0).powi(4)
+        ));
+        assert_eq!(p_at_least_one(0.5, 0), Ok(0.0));
+        assert_eq!(p_at_least_one(1.0, 3), Ok(1.0));
+        assert_eq!(p_at_least_one(0.0, 1000), Ok(0.0));
+        // A one-in-a-billion chance, twice.
+        let tiny = p_at_least_one(1e-9, 2).unwrap();
+        assert!((tiny - (2e-9 - 1e-18)).abs() < 1e-24);
+        assert!(p_at_least_one(1.1, 2).is_err());
+    }
+
+    #[test]
+    fn test_odds() {
+        assert!(close(probability_to_odds(0.25).unwrap(), 1.0 / 3.0));
+        assert_eq!(probability_to_odds(0.5), Ok(1.0));
+        assert_eq!(probability_to_odds(0.0), Ok(0.0));
+        assert!(probability_to_odds(1.0).is_err());
+        assert_eq!(odds_to_probability(1.0, 3.0), Ok(0.25));
+        assert_eq!(odds_to_probability(3.0, 0.0), Ok(1.0));
+        assert!(odds_to_probability(0.0, 0.0).is_err());
+        assert!(odds_to_probability(-1.0, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_expected_value() {
+        let die: Vec<Outcome> = (1..=6)
+            .map(|face| Outcome {
+                value: face as f64,
+                probability: 1.0 / 6.0,
+            })
+            .collect();
+        assert!(close(expected_value(&die).unwrap(), 3.5));
+        // A bet: lose 1 at 0.9, win 5 at 0.1.
+        let bet = [
+            Outcome {
+                value: -1.0,
+                probability: 0.9,
+            },
+            Outcome {
+                value: 5.0,
+                probability: 0.1,
+            },
+        ];
+        assert!(close(expected_value(&bet).unwrap(), -0.4));
+        assert!(expected_value(&bet[..1]).is_err());
+        assert!(expected_value(&[]).is_err());
+    }
+}
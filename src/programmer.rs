@@ -0,0 +1,494 @@
+//! Programmer mode: fixed-width integers, radix conversion and bitwise ops.
+//!
+//! [`ProgrammerCalculator`] holds a two's-complement integer of 8, 16, 32 or
+//! 64 bits. All arithmetic wraps at the word size like the OS calculators.
+//! Values cross the WASM boundary as `BigInt` (`u64`/`i64`) so 64-bit words
+//! never lose precision through `f64`.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+const WORD_SIZES: [u32; 4] = [8, 16, 32, 64];
+
+/// Parses an integer literal in `base` (2..=36), or auto-detects the base
+/// from a `0x`/`0o`/`0b` prefix when `base` is 0. A leading `-` is allowed,
+/// and `_` or spaces may separate digit groups. Returns the sign and the
+/// magnitude; syntax error positions are character offsets into `text`.
+pub fn parse_integer(text: &str, base: u32) -> Result<(bool, u64), CalcError> {
+    if base != 0 && !(2..=36).contains(&base) {
+        return Err(CalcError::InvalidArgument(format!(
+            "Unsupported base {}",
+            base
+        )));
+    }
+    let trimmed = text.trim();
+    let offset = text.chars().count() - text.trim_start().chars().count();
+    let (negative, rest) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let lower = rest.to_ascii_lowercase();
+    let (base, digits) = match (base, lower.get(..2)) {
+        (0 | 16, Some("0x")) => (16, &rest[2..]),
+        (0 | 8, Some("0o")) => (8, &rest[2..]),
+        (0 | 2, Some("0b")) => (2, &rest[2..]),
+        (0, _) => (10, rest),
+        (b, _) => (b, rest),
+    };
+    let digits_start = offset + (trimmed.chars().count() - digits.chars().count());
+    let mut magnitude: u64 = 0;
+    let mut seen = false;
+    for (i, c) in digits.chars().enumerate() {
+        if c == '_' || c == ' ' {
+            continue;
+        }
+        let Some(d) = c.to_digit(base) else {
+            return Err(CalcError::Syntax {
+                message: format!("Invalid digit '{}' for base {}", c, base),
+                position: digits_start + i,
+            });
+        };
+        seen = true;
+        magnitude = magnitude
+            .checked_mul(base as u64)
+            .and_then(|m| m.checked_add(d as u64))
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!("{} does not fit in 64 bits", trimmed))
+            })?;
+    }
+    if !seen {
+        return Err(CalcError::Syntax {
+            message: "Expected digits".to_string(),
+            position: digits_start,
+        });
+    }
+    Ok((negative, magnitude))
+}
+
+/// Formats `value` in `base` (2..=36) using lowercase digits.
+pub fn format_radix(mut value: u64, base: u32) -> String {
+    if value == 0 {
+        return "0".to_string();
+    }
+    let mut digits = Vec::new();
+    while value > 0 {
+        let d = (value % base as u64) as u32;
+        digits.push(std::char::from_digit(d, base).unwrap_or('?'));
+        value /= base as u64;
+    }
+    digits.iter().rev().collect()
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgrammerCalculator {
+    // Raw bit pattern, always masked to `word_bits`.
+    value: u64,
+    word_bits: u32,
+    signed: bool,
+}
+
+impl ProgrammerCalculator {
+    fn mask(&self) -> u64 {
+        if self.word_bits == 64 {
+            u64::MAX
+        } else {
+            (1u64 << self.word_bits) - 1
+        }
+    }
+
+    fn store(&mut self, raw: u64) -> u64 {
+        self.value = raw & self.mask();
+        self.value
+    }
+
+    fn signed_value(&self) -> i64 {
+        let shift = 64 - self.word_bits;
+        ((self.value << shift) as i64) >> shift
+    }
+
+    pub fn set_word_size(&mut self, bits: u32) -> Result<(), CalcError> {
+        if !WORD_SIZES.contains(&bits) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Word size must be 8, 16, 32 or 64 bits, got {}",
+                bits
+            )));
+        }
+        self.word_bits = bits;
+        self.store(self.value);
+        Ok(())
+    }
+
+    /// Parses `text` in `base` (see [`parse_integer`]) and stores it.
+    /// Negative input is stored as two's complement; values wider than the
+    /// current word are rejected rather than silently truncated.
+    pub fn set_value_from_string(&mut self, text: &str, base: u32) -> Result<u64, CalcError> {
+        let (negative, magnitude) = parse_integer(text, base)?;
+        let too_wide = || {
+            CalcError::InvalidArgument(format!(
+                "{} does not fit in {} bits",
+                text.trim(),
+                self.word_bits
+            ))
+        };
+        if negative {
+            if magnitude > 1u64 << (self.word_bits - 1) {
+                return Err(too_wide());
+            }
+            Ok(self.store(magnitude.wrapping_neg()))
+        } else {
+            if magnitude > self.mask() {
+                return Err(too_wide());
+            }
+            Ok(self.store(magnitude))
+        }
+    }
+
+    /// Formats the value in `base`. Base 10 in signed mode shows the signed
+    /// value; every other base shows the raw two's-complement bits.
+    pub fn to_base(&self, base: u32) -> Result<String, CalcError> {
+        if !(2..=36).contains(&base) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Unsupported base {}",
+                base
+            )));
+        }
+        if base == 10 && self.signed {
+            return Ok(self.signed_value().to_string());
+        }
+        Ok(format_radix(self.value, base))
+    }
+
+    pub fn divide(&mut self, operand: u64) -> Result<u64, CalcError> {
+        let operand = operand & self.mask();
+        if operand == 0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        let raw = if self.signed {
+            let shift = 64 - self.word_bits;
+            let divisor = ((operand << shift) as i64) >> shift;
+            self.signed_value().wrapping_div(divisor) as u64
+        } else {
+            self.value / operand
+        };
+        Ok(self.store(raw))
+    }
+
+    pub fn modulo(&mut self, operand: u64) -> Result<u64, CalcError> {
+        let operand = operand & self.mask();
+        if operand == 0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        let raw = if self.signed {
+            let shift = 64 - self.word_bits;
+            let divisor = ((operand << shift) as i64) >> shift;
+            self.signed_value().wrapping_rem(divisor) as u64
+        } else {
+            self.value % operand
+        };
+        Ok(self.store(raw))
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ProgrammerCalculator {
+    /// A 64-bit signed calculator holding 0.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> ProgrammerCalculator {
+        ProgrammerCalculator {
+            value: 0,
+            word_bits: 64,
+            signed: true,
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_word_size)]
+    pub fn set_word_size_js(&mut self, bits: u32) -> Result<(), CalcErrorJs> {
+        self.set_word_size(bits)
+            .map_err(|e| CalcErrorJs::new(e, Some(bits as f64)))
+    }
+
+    pub fn word_size(&self) -> u32 {
+        self.word_bits
+    }
+
+    pub fn set_signed(&mut self, signed: bool) {
+        self.signed = signed;
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_value_from_string)]
+    pub fn set_value_from_string_js(&mut self, text: &str, base: u32) -> Result<u64, CalcErrorJs> {
+        self.set_value_from_string(text, base)
+            .map_err(CalcErrorJs::from)
+    }
+
+    /// Stores a raw bit pattern, truncated to the word size.
+    pub fn set_bits(&mut self, bits: u64) {
+        self.store(bits);
+    }
+
+    /// The raw bit pattern.
+    pub fn get_bits(&self) -> u64 {
+        self.value
+    }
+
+    /// The value as a signed integer (sign-extended from the word size).
+    pub fn get_signed(&self) -> i64 {
+        self.signed_value()
+    }
+
+    /// The value as a JS number, honoring the signed flag. Exact up to 2^53.
+    pub fn get_value(&self) -> f64 {
+        if self.signed {
+            self.signed_value() as f64
+        } else {
+            self.value as f64
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = to_base)]
+    pub fn to_base_js(&self, base: u32) -> Result<String, CalcErrorJs> {
+        self.to_base(base)
+            .map_err(|e| CalcErrorJs::new(e, Some(base as f64)))
+    }
+
+    pub fn add(&mut self, operand: u64) -> u64 {
+        self.store(self.value.wrapping_add(operand))
+    }
+
+    pub fn subtract(&mut self, operand: u64) -> u64 {
+        self.store(self.value.wrapping_sub(operand))
+    }
+
+    pub fn multiply(&mut self, operand: u64) -> u64 {
+        self.store(self.value.wrapping_mul(operand))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = divide)]
+    pub fn divide_js(&mut self, operand: u64) -> Result<u64, CalcErrorJs> {
+        self.divide(operand)
+            .map_err(|e| CalcErrorJs::new(e, Some(operand as f64)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = modulo)]
+    pub fn modulo_js(&mut self, operand: u64) -> Result<u64, CalcErrorJs> {
+        self.modulo(operand)
+            .map_err(|e| CalcErrorJs::new(e, Some(operand as f64)))
+    }
+
+    pub fn and(&mut self, operand: u64) -> u64 {
+        self.store(self.value & operand)
+    }
+
+    pub fn or(&mut self, operand: u64) -> u64 {
+        self.store(self.value | operand)
+    }
+
+    pub fn xor(&mut self, operand: u64) -> u64 {
+        self.store(self.value ^ operand)
+    }
+
+    pub fn not(&mut self) -> u64 {
+        self.store(!self.value)
+    }
+
+    /// Two's-complement negation within the word.
+    pub fn negate(&mut self) -> u64 {
+        self.store(self.value.wrapping_neg())
+    }
+
+    pub fn shift_left(&mut self, count: u32) -> u64 {
+        let raw = if count >= self.word_bits {
+            0
+        } else {
+            self.value << count
+        };
+        self.store(raw)
+    }
+
+    /// Arithmetic shift in signed mode, logical shift otherwise.
+    pub fn shift_right(&mut self, count: u32) -> u64 {
+        let raw = if self.signed {
+            (self.signed_value() >> count.min(63)) as u64
+        } else if count >= self.word_bits {
+            0
+        } else {
+            self.value >> count
+        };
+        self.store(raw)
+    }
+
+    pub fn rotate_left(&mut self, count: u32) -> u64 {
+        let count = count % self.word_bits;
+        if count == 0 {
+            return self.value;
+        }
+        let raw = (self.value << count) | (self.value >> (self.word_bits - count));
+        self.store(raw)
+    }
+
+    pub fn rotate_right(&mut self, count: u32) -> u64 {
+        let count = count % self.word_bits;
+        self.rotate_left(self.word_bits - count)
+    }
+
+    pub fn clear(&mut self) {
+        self.value = 0;
+    }
+}
+
+impl Default for ProgrammerCalculator {
+    fn default() -> Self {
+        ProgrammerCalculator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_integer_bases_and_prefixes() {
+        assert_eq!(parse_integer("0xFF", 16), Ok((false, 255)));
+        assert_eq!(parse_integer("ff", 16), Ok((false, 255)));
+        assert_eq!(parse_integer("0b1010", 0), Ok((false, 10)));
+        assert_eq!(parse_integer("0o17", 8), Ok((false, 15)));
+        assert_eq!(parse_integer("-42", 10), Ok((true, 42)));
+        assert_eq!(parse_integer("1111_0000", 2), Ok((false, 240)));
+        assert_eq!(parse_integer("123", 0), Ok((false, 123)));
+    }
+
+    #[test]
+    fn test_parse_integer_errors() {
+        assert_eq!(
+            parse_integer("0x1G", 16),
+            Err(CalcError::Syntax {
+                message: "Invalid digit 'G' for base 16".to_string(),
+                position: 3
+            })
+        );
+        // Positions count characters, not bytes, of the whitespace too.
+        assert_eq!(
+            parse_integer("\u{3000}1é2", 10),
+            Err(CalcError::Syntax {
+                message: "Invalid digit 'é' for base 10".to_string(),
+                position: 2
+            })
+        );
+        assert_eq!(
+            parse_integer("\u{3000}ü", 0),
+            Err(CalcError::Syntax {
+                message: "Invalid digit 'ü' for base 10".to_string(),
+                position: 1
+            })
+        );
+        assert!(matches!(
+            parse_integer("", 10),
+            Err(CalcError::Syntax { .. })
+        ));
+        assert!(matches!(
+            parse_integer("1", 1),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            parse_integer("18446744073709551616", 10),
+            Err(CalcError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_value_and_to_base() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_value_from_string("0xFF", 16).unwrap();
+        assert_eq!(pc.to_base(2).unwrap(), "11111111");
+        assert_eq!(pc.to_base(8).unwrap(), "377");
+        assert_eq!(pc.to_base(10).unwrap(), "255");
+        assert_eq!(pc.to_base(16).unwrap(), "ff");
+        assert!(pc.to_base(37).is_err());
+    }
+
+    #[test]
+    fn test_word_size_wraps_and_sign_extends() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_word_size(8).unwrap();
+        pc.set_value_from_string("0xFF", 16).unwrap();
+        assert_eq!(pc.get_signed(), -1);
+        assert_eq!(pc.to_base(10).unwrap(), "-1");
+        pc.set_signed(false);
+        assert_eq!(pc.to_base(10).unwrap(), "255");
+        assert_eq!(pc.add(1), 0);
+        assert!(pc.set_value_from_string("256", 10).is_err());
+        assert!(pc.set_word_size(12).is_err());
+    }
+
+    #[test]
+    fn test_negative_input_is_twos_complement() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_word_size(16).unwrap();
+        pc.set_value_from_string("-1", 10).unwrap();
+        assert_eq!(pc.to_base(16).unwrap(), "ffff");
+        pc.set_value_from_string("-32768", 10).unwrap();
+        assert_eq!(pc.get_signed(), -32768);
+        assert!(pc.set_value_from_string("-32769", 10).is_err());
+    }
+
+    #[test]
+    fn test_shrinking_word_truncates() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_bits(0x1234);
+        pc.set_word_size(8).unwrap();
+        assert_eq!(pc.get_bits(), 0x34);
+    }
+
+    #[test]
+    fn test_bitwise_operations() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_word_size(8).unwrap();
+        pc.set_bits(0b1100_1010);
+        assert_eq!(pc.and(0b1111_0000), 0b1100_0000);
+        assert_eq!(pc.or(0b0000_0011), 0b1100_0011);
+        assert_eq!(pc.xor(0b1111_1111), 0b0011_1100);
+        assert_eq!(pc.not(), 0b1100_0011);
+        assert_eq!(pc.negate(), 0b0011_1101);
+    }
+
+    #[test]
+    fn test_shifts_and_rotates() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_word_size(8).unwrap();
+        pc.set_bits(0b1000_0001);
+        assert_eq!(pc.rotate_left(1), 0b0000_0011);
+        assert_eq!(pc.rotate_right(2), 0b1100_0000);
+        assert_eq!(pc.shift_right(2), 0b1111_0000); // arithmetic, signed
+        pc.set_signed(false);
+        assert_eq!(pc.shift_right(4), 0b0000_1111);
+        assert_eq!(pc.shift_left(6), 0b1100_0000);
+        assert_eq!(pc.shift_left(8), 0);
+    }
+
+    #[test]
+    fn test_integer_division() {
+        let mut pc = ProgrammerCalculator::new();
+        pc.set_value_from_string("-7", 10).unwrap();
+        assert_eq!(pc.divide(2).map(|_| pc.get_signed()), Ok(-3));
+        pc.set_value_from_string("-7", 10).unwrap();
+        assert_eq!(pc.modulo(2).map(|_| pc.get_signed()), Ok(-1));
+        assert_eq!(pc.divide(0), Err(CalcError::DivisionByZero));
+        pc.set_signed(false);
+        pc.set_bits(7);
+        assert_eq!(pc.divide(2), Ok(3));
+    }
+}
@@ -0,0 +1,230 @@
+//! A restricted evaluator for quizzes and exams.
+//!
+//! A calculator embedded in a test must not become a way around it: it
+//! should do the arithmetic a question allows and nothing else. A
+//! [`QuizEvaluator`] is a separate object rather than a mode of
+//! `Calculator`, so a page handed one has no variables, user functions,
+//! memory, history or hooks to reach for; there is no `ans`, no `mem` and
+//! no way to turn the restrictions off. Plain arithmetic (`+ - * / % ^ !`
+//! and parentheses) is always allowed. Built-in functions and constants are
+//! refused unless the [`QuizProfile`] lists them, and input length and
+//! parenthesis depth are capped so a submission cannot hide a disallowed
+//! step in an unreadable expression or stall the page.
+
+use serde::Deserialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{
+    parse_tokens, tokenize, unknown_identifier, AngleScope, AngleUnit, Dialect, Expr, Token,
+    CONSTANTS, FUNCTIONS,
+};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// What a quiz permits beyond plain arithmetic. As JSON every field is
+/// optional: `{ functions: ["sqrt"], constants: true }`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuizProfile {
+    /// Built-in functions that may be called, such as `sqrt`. Default none.
+    pub functions: Vec<String>,
+    /// Whether `pi`, `e` and `tau` may be used. Default false.
+    pub constants: bool,
+    /// The unit of trigonometric functions, if any are allowed.
+    pub angle_unit: AngleUnit,
+    /// The longest input, in characters. Default 200.
+    pub max_length: usize,
+    /// The deepest parenthesis nesting. Default 10.
+    pub max_depth: usize,
+}
+
+impl Default for QuizProfile {
+    fn default() -> Self {
+        QuizProfile {
+            functions: Vec::new(),
+            constants: false,
+            angle_unit: AngleUnit::Radians,
+            max_length: 200,
+            max_depth: 10,
+        }
+    }
+}
+
+fn not_allowed(message: impl Into<String>) -> CalcError {
+    CalcError::NotAllowed(message.into())
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuizEvaluator {
+    profile: QuizProfile,
+}
+
+impl QuizEvaluator {
+    /// Fails if `profile` names a function that does not exist.
+    pub fn new(profile: QuizProfile) -> Result<QuizEvaluator, CalcError> {
+        for name in &profile.functions {
+            if !FUNCTIONS.iter().any(|(f, _, _)| f == name) {
+                return Err(unknown_identifier(
+                    name,
+                    FUNCTIONS.iter().map(|(f, _, _)| *f),
+                ));
+            }
+        }
+        Ok(QuizEvaluator { profile })
+    }
+
+    pub fn profile(&self) -> &QuizProfile {
+        &self.profile
+    }
+
+    /// Evaluates `src` if it uses only what the profile allows; anything
+    /// else fails with [`CalcError::NotAllowed`].
+    pub fn evaluate(&self, src: &str) -> Result<f64, CalcError> {
+        let length = src.chars().count();
+        if length > self.profile.max_length {
+            return Err(not_allowed(format!(
+                "Answers are limited to {} characters",
+                self.profile.max_length
+            )));
+        }
+        let tokens = tokenize(src, Dialect::Standard)?;
+        let mut depth = 0usize;
+        for token in &tokens {
+            match token.token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if depth > self.profile.max_depth {
+                return Err(not_allowed(format!(
+                    "Parentheses may nest at most {} deep",
+                    self.profile.max_depth
+                )));
+            }
+        }
+        let expr = parse_tokens(tokens, length, Dialect::Standard)?;
+        self.check(&expr)?;
+        expr.eval(&AngleScope {
+            scope: &(),
+            unit: self.profile.angle_unit,
+        })
+    }
+
+    fn check(&self, expr: &Expr) -> Result<(), CalcError> {
+        match expr {
+            Expr::Number(_) => Ok(()),
+            Expr::Variable(name) if CONSTANTS.iter().any(|(c, _)| c == name) => {
+                if self.profile.constants {
+                    Ok(())
+                } else {
+                    Err(not_allowed(format!("'{}' is not allowed here", name)))
+                }
+            }
+            Expr::Variable(name) => Err(not_allowed(format!(
+                "Variables are not allowed here ('{}')",
+                name
+            ))),
+            Expr::Range(..) => Err(not_allowed("Cell ranges are not allowed here")),
+            Expr::Unary(_, inner) => self.check(inner),
+            Expr::Binary(_, lhs, rhs) => {
+                self.check(lhs)?;
+                self.check(rhs)
+            }
+            Expr::Call(name, args) => {
+                if !self.profile.functions.contains(name) {
+                    return Err(not_allowed(format!("'{}' is not allowed here", name)));
+                }
+                args.iter().try_for_each(|arg| self.check(arg))
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl QuizEvaluator {
+    /// `profile` is an optional `{ functions, constants, angle_unit,
+    /// max_length, max_depth }`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new_js(profile: JsValue) -> Result<QuizEvaluator, CalcErrorJs> {
+        let profile = if profile.is_undefined() || profile.is_null() {
+            QuizProfile::default()
+        } else {
+            serde_wasm_bindgen::from_value(profile).map_err(|e| {
+                CalcErrorJs::from(CalcError::InvalidArgument(format!(
+                    "Invalid quiz profile: {}",
+                    e
+                )))
+            })?
+        };
+        QuizEvaluator::new(profile).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = evaluate)]
+    pub fn evaluate_js(&self, src: &str) -> Result<f64, CalcErrorJs> {
+        self.evaluate(src).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiz() -> QuizEvaluator {
+        QuizEvaluator::new(QuizProfile::default()).unwrap()
+    }
+
+    #[test]
+    fn test_plain_arithmetic_only() {
+        let quiz = quiz();
+        assert_eq!(quiz.evaluate("(3 + 4) * 2^3 - 10 % 4"), Ok(54.0));
+        assert_eq!(quiz.evaluate("4!"), Ok(24.0));
+        for src in ["sqrt(16)", "pi * 2", "ans + 1", "mem", "x * 2"] {
+            assert!(
+                matches!(quiz.evaluate(src), Err(CalcError::NotAllowed(_))),
+                "{} was allowed",
+                src
+            );
+        }
+        // Syntax errors are still syntax errors.
+        assert!(matches!(
+            quiz.evaluate("2 +"),
+            Err(CalcError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_profile_allow_list_and_limits() {
+        let quiz = QuizEvaluator::new(QuizProfile {
+            functions: vec!["sqrt".to_string()],
+            constants: true,
+            max_length: 20,
+            max_depth: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(quiz.evaluate("sqrt(16) + 1"), Ok(5.0));
+        assert!(quiz.evaluate("2 * pi").is_ok());
+        assert!(quiz.evaluate("abs(-1)").is_err());
+        // Allowed functions cannot smuggle in disallowed ones.
+        assert!(quiz.evaluate("sqrt(abs(-16))").is_err());
+        assert!(quiz.evaluate("((1))").is_ok());
+        assert!(matches!(
+            quiz.evaluate("(((1)))"),
+            Err(CalcError::NotAllowed(_))
+        ));
+        assert!(quiz.evaluate(&"1+".repeat(10)).is_err());
+
+        assert!(matches!(
+            QuizEvaluator::new(QuizProfile {
+                functions: vec!["sqr".to_string()],
+                ..Default::default()
+            }),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+//! Resource limits for multi-step runs.
+//!
+//! A batch, an imported tape or a macro is a run: a list of operations
+//! applied all-or-nothing (see [`Calculator::apply_batch`]). When the list
+//! comes from someone else, a shared tape or a batch that calls a long
+//! macro many times over, it can be far longer than it looks. A
+//! [`RunQuota`] caps each run; exceeding it fails the whole run with
+//! [`CalcError::QuotaExceeded`] and leaves the calculator unchanged.
+//!
+//! - `max_operations` counts every operation applied, including those
+//!   inside the macros a run calls.
+//! - `max_duration_ms` is a wall-clock budget, checked every
+//!   [`CLOCK_CHECK_INTERVAL`] operations so the clock is not read for each
+//!   one.
+//!
+//! Runs store nothing beyond their history entries, which the history
+//! limit already bounds, so there is no separate memory quota.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::timing::precise_now_ms;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// How many operations run between checks of the wall-clock budget.
+pub const CLOCK_CHECK_INTERVAL: usize = 64;
+
+/// Limits on each run; `None` (the default) is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunQuota {
+    pub max_operations: Option<usize>,
+    pub max_duration_ms: Option<f64>,
+}
+
+// Usage of the run in progress.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RunUsage {
+    operations: usize,
+    started_ms: f64,
+}
+
+impl RunUsage {
+    pub(crate) fn start() -> RunUsage {
+        RunUsage {
+            operations: 0,
+            started_ms: precise_now_ms(),
+        }
+    }
+}
+
+impl Calculator {
+    /// Sets the limits later runs are held to.
+    pub fn set_run_quota(&mut self, quota: RunQuota) -> Result<(), CalcError> {
+        if let Some(ms) = quota.max_duration_ms.filter(|ms| ms.is_nan() || *ms <= 0.0) {
+            let err =
+                CalcError::InvalidArgument(format!("max_duration_ms must be positive, got {}", ms));
+            return Err(self.fail(err, Some(ms)));
+        }
+        self.quota = quota;
+        Ok(())
+    }
+
+    pub fn run_quota(&self) -> RunQuota {
+        self.quota
+    }
+
+    /// Counts one operation of the current run against the quota.
+    pub(crate) fn charge_run(&mut self) -> Result<(), CalcError> {
+        let quota = self.quota;
+        let Some(usage) = &mut self.run_usage else {
+            return Ok(());
+        };
+        usage.operations += 1;
+        if let Some(max) = quota.max_operations.filter(|max| usage.operations > *max) {
+            return Err(CalcError::QuotaExceeded {
+                quota: "max_operations",
+                limit: max as f64,
+            });
+        }
+        if let Some(budget) = quota.max_duration_ms {
+            let check = usage.operations % CLOCK_CHECK_INTERVAL == 0;
+            if check && precise_now_ms() - usage.started_ms > budget {
+                return Err(CalcError::QuotaExceeded {
+                    quota: "max_duration_ms",
+                    limit: budget,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `quota` is `{ max_operations, max_duration_ms }`; fields left out
+    /// (or `null`) are unlimited, and `set_run_quota({})` lifts every limit.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_run_quota)]
+    pub fn set_run_quota_js(&mut self, quota: JsValue) -> Result<(), CalcErrorJs> {
+        let quota: RunQuota = serde_wasm_bindgen::from_value(quota).map_err(|e| {
+            let err = CalcError::InvalidArgument(format!("Invalid quota: {}", e));
+            CalcErrorJs::from(self.fail(err, None))
+        })?;
+        self.set_run_quota(quota).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_run_quota)]
+    pub fn get_run_quota_js(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.quota).unwrap_or(JsValue::NULL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::BatchStep;
+
+    fn steps(count: usize) -> Vec<BatchStep> {
+        (0..count)
+            .map(|_| BatchStep {
+                op: "add".to_string(),
+                operand: Some(1.0),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_max_operations_counts_macro_steps() {
+        let mut calc = Calculator::new();
+        calc.record_macro("twice").unwrap();
+        calc.add(1.0);
+        calc.add(1.0);
+        calc.finish_macro().unwrap();
+        calc.set_value(0.0);
+
+        calc.set_run_quota(RunQuota {
+            max_operations: Some(3),
+            ..RunQuota::default()
+        })
+        .unwrap();
+        assert_eq!(calc.apply_batch(&steps(3)), Ok(vec![1.0, 2.0, 3.0]));
+        assert_eq!(calc.call_function("twice"), Ok(5.0));
+        // The call itself and the macro's two steps, twice over.
+        let mut batch = steps(0);
+        for _ in 0..2 {
+            batch.push(BatchStep {
+                op: "function:twice".to_string(),
+                operand: None,
+            });
+        }
+        let err = calc.apply_batch(&batch).unwrap_err();
+        assert_eq!(
+            err,
+            CalcError::QuotaExceeded {
+                quota: "max_operations",
+                limit: 3.0
+            }
+        );
+        assert_eq!(err.code(), "QUOTA_EXCEEDED");
+        assert_eq!(calc.get_value(), 5.0);
+        assert_eq!(calc.error_log().len(), 1);
+
+        calc.set_run_quota(RunQuota::default()).unwrap();
+        assert_eq!(calc.apply_batch(&batch), Ok(vec![7.0, 9.0]));
+    }
+
+    #[test]
+    fn test_max_duration_ms() {
+        let mut calc = Calculator::new();
+        assert!(calc
+            .set_run_quota(RunQuota {
+                max_duration_ms: Some(0.0),
+                ..RunQuota::default()
+            })
+            .is_err());
+        calc.set_run_quota(RunQuota {
+            max_duration_ms: Some(1e-9),
+            ..RunQuota::default()
+        })
+        .unwrap();
+        assert!(calc.apply_batch(&steps(CLOCK_CHECK_INTERVAL - 1)).is_ok());
+        let err = calc.apply_batch(&steps(CLOCK_CHECK_INTERVAL)).unwrap_err();
+        assert!(matches!(
+            err,
+            CalcError::QuotaExceeded {
+                quota: "max_duration_ms",
+                ..
+            }
+        ));
+        assert_eq!(calc.history_count(), CLOCK_CHECK_INTERVAL - 1);
+    }
+}
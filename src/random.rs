@@ -0,0 +1,308 @@
+//! Random numbers and dice.
+//!
+//! The exported functions share one generator per thread. It seeds itself
+//! from the clock (and `Math.random` in the browser) on first use;
+//! `set_seed` makes every draw after it reproducible, which `Math.random`
+//! cannot offer. The generator is xoshiro256**, not cryptographically
+//! secure.
+
+use std::cell::RefCell;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{now_ms, CalcError, MAX_SAFE_INTEGER};
+
+/// Most dice one `dice_roll` call will roll.
+pub const MAX_DICE: u32 = 10_000;
+
+// Draws `float` makes before settling for `min`. A draw only falls
+// outside the range by rounding, so this is never reached outside the
+// tiniest ranges.
+const MAX_FLOAT_DRAWS: usize = 64;
+
+fn invalid(message: String) -> CalcError {
+    CalcError::InvalidArgument(message)
+}
+
+/// A seedable xoshiro256** generator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// The same seed always yields the same sequence.
+    pub fn seeded(seed: u64) -> Rng {
+        // SplitMix64 spreads the seed over the state, which must not be all
+        // zero.
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Rng {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    // Seeded from whatever entropy the platform offers cheaply.
+    fn from_environment() -> Rng {
+        let mut seed = now_ms().to_bits();
+        #[cfg(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown"))]
+        {
+            seed ^= (js_sys::Math::random() * MAX_SAFE_INTEGER) as u64;
+        }
+        #[cfg(not(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown")))]
+        {
+            let local = 0u8;
+            seed ^= (&local as *const u8 as u64).rotate_left(32);
+        }
+        Rng::seeded(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Uniform in `[0, 1)`, with 53 random bits.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `0..bound`, without modulo bias. `bound` must be nonzero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // Reject the top partial copy of 0..bound in the u64 range.
+        let zone = u64::MAX - (u64::MAX - bound + 1) % bound;
+        loop {
+            let x = self.next_u64();
+            if x <= zone {
+                return x % bound;
+            }
+        }
+    }
+
+    /// A whole number from `min` to `max`, both included. Both must be
+    /// whole and within ±(2^53 - 1) so every result is exact.
+    pub fn int(&mut self, min: f64, max: f64) -> Result<f64, CalcError> {
+        for bound in [min, max] {
+            if bound.fract() != 0.0 || !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&bound) {
+                return Err(invalid(format!(
+                    "Bounds must be whole numbers within ±(2^53 - 1), got {}",
+                    bound
+                )));
+            }
+        }
+        if min > max {
+            return Err(invalid(format!("min {} is greater than max {}", min, max)));
+        }
+        let span = (max - min) as u64 + 1;
+        Ok(min + self.below(span) as f64)
+    }
+
+    /// A number in `[min, max)`; `min` itself when they are equal.
+    pub fn float(&mut self, min: f64, max: f64) -> Result<f64, CalcError> {
+        if !min.is_finite() || !max.is_finite() {
+            return Err(invalid(format!(
+                "Bounds must be finite, got {} and {}",
+                min, max
+            )));
+        }
+        if min > max {
+            return Err(invalid(format!("min {} is greater than max {}", min, max)));
+        }
+        if min == max {
+            return Ok(min);
+        }
+        for _ in 0..MAX_FLOAT_DRAWS {
+            let u = self.unit();
+            // Weighted rather than `min + (max - min) * u`, whose width
+            // overflows for bounds near ±f64::MAX.
+            let value = min * (1.0 - u) + max * u;
+            // Rounding can land on either bound when the range is tiny.
+            if (min..max).contains(&value) {
+                return Ok(value);
+            }
+        }
+        Ok(min)
+    }
+
+    /// `count` rolls of a die with `sides` faces, each from 1 to `sides`.
+    pub fn dice(&mut self, count: u32, sides: u32) -> Result<Vec<u32>, CalcError> {
+        if sides == 0 {
+            return Err(invalid("A die needs at least one side".to_string()));
+        }
+        if count > MAX_DICE {
+            return Err(invalid(format!(
+                "At most {} dice per roll, got {}",
+                MAX_DICE, count
+            )));
+        }
+        Ok((0..count)
+            .map(|_| self.below(sides as u64) as u32 + 1)
+            .collect())
+    }
+
+    /// An index into `weights`, each chosen with probability proportional
+    /// to its weight. Weights must be finite and non-negative, and not all
+    /// zero.
+    pub fn choice_index(&mut self, weights: &[f64]) -> Result<usize, CalcError> {
+        if let Some(w) = weights.iter().find(|w| !w.is_finite() || **w < 0.0) {
+            return Err(invalid(format!(
+                "Weights must be finite and non-negative, got {}",
+                w
+            )));
+        }
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 || !total.is_finite() {
+            return Err(invalid("Weights must not all be zero".to_string()));
+        }
+        let target = self.unit() * total;
+        let mut acc = 0.0;
+        for (i, w) in weights.iter().enumerate() {
+            acc += w;
+            if target < acc {
+                return Ok(i);
+            }
+        }
+        // Rounding left `target` past the sum: take the last weighted index.
+        Ok(weights.iter().rposition(|w| *w > 0.0).unwrap_or(0))
+    }
+}
+
+thread_local! {
+    static RNG: RefCell<Option<Rng>> = const { RefCell::new(None) };
+}
+
+fn with_rng<T>(draw: impl FnOnce(&mut Rng) -> T) -> T {
+    RNG.with(|rng| draw(rng.borrow_mut().get_or_insert_with(Rng::from_environment)))
+}
+
+/// Reseeds the shared generator used by the functions below.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn set_seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = Some(Rng::seeded(seed)));
+}
+
+pub fn random_int(min: f64, max: f64) -> Result<f64, CalcError> {
+    with_rng(|rng| rng.int(min, max))
+}
+
+pub fn random_float(min: f64, max: f64) -> Result<f64, CalcError> {
+    with_rng(|rng| rng.float(min, max))
+}
+
+pub fn dice_roll(count: u32, sides: u32) -> Result<Vec<u32>, CalcError> {
+    with_rng(|rng| rng.dice(count, sides))
+}
+
+pub fn random_choice_index(weights: &[f64]) -> Result<usize, CalcError> {
+    with_rng(|rng| rng.choice_index(weights))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = random_int)]
+pub fn random_int_js(min: f64, max: f64) -> Result<f64, CalcErrorJs> {
+    random_int(min, max).map_err(|e| CalcErrorJs::new(e, Some(max)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = random_float)]
+pub fn random_float_js(min: f64, max: f64) -> Result<f64, CalcErrorJs> {
+    random_float(min, max).map_err(|e| CalcErrorJs::new(e, Some(max)))
+}
+
+/// The individual rolls as a `Uint32Array`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = dice_roll)]
+pub fn dice_roll_js(count: u32, sides: u32) -> Result<Vec<u32>, CalcErrorJs> {
+    dice_roll(count, sides).map_err(|e| CalcErrorJs::new(e, Some(sides as f64)))
+}
+
+/// `weights` is a `Float64Array`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = random_choice_index)]
+pub fn random_choice_index_js(weights: &[f64]) -> Result<usize, CalcErrorJs> {
+    random_choice_index(weights).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_reproduces_sequence() {
+        set_seed(42);
+        let first: Vec<f64> = (0..5).map(|_| random_int(1.0, 100.0).unwrap()).collect();
+        set_seed(42);
+        let second: Vec<f64> = (0..5).map(|_| random_int(1.0, 100.0).unwrap()).collect();
+        assert_eq!(first, second);
+        set_seed(43);
+        let other: Vec<f64> = (0..5).map(|_| random_int(1.0, 100.0).unwrap()).collect();
+        assert_ne!(first, other);
+        // Reference output of xoshiro256** seeded through SplitMix64.
+        assert_eq!(Rng::seeded(0).next_u64(), 0x99ec_5f36_cb75_f2b4);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = Rng::seeded(7);
+        let mut seen = [false; 6];
+        for _ in 0..1000 {
+            let n = rng.int(-2.0, 3.0).unwrap();
+            assert!((-2.0..=3.0).contains(&n) && n.fract() == 0.0);
+            seen[(n + 2.0) as usize] = true;
+            let x = rng.float(1.5, 2.5).unwrap();
+            assert!((1.5..2.5).contains(&x));
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(rng.int(5.0, 5.0), Ok(5.0));
+        assert_eq!(rng.float(5.0, 5.0), Ok(5.0));
+        assert!(rng.int(1.5, 3.0).is_err());
+        assert!(rng.int(3.0, 1.0).is_err());
+        assert!(rng.float(0.0, f64::INFINITY).is_err());
+        for _ in 0..100 {
+            let x = rng.float(-1e308, 1e308).unwrap();
+            assert!(x.is_finite() && (-1e308..1e308).contains(&x));
+        }
+        let next = f64::from_bits(1.0f64.to_bits() + 1);
+        assert_eq!(rng.float(1.0, next), Ok(1.0));
+        let big = rng.int(-MAX_SAFE_INTEGER, MAX_SAFE_INTEGER).unwrap();
+        assert!(big.abs() <= MAX_SAFE_INTEGER);
+    }
+
+    #[test]
+    fn test_dice_and_choices() {
+        let mut rng = Rng::seeded(1);
+        let rolls = rng.dice(600, 6).unwrap();
+        assert_eq!(rolls.len(), 600);
+        assert!(rolls.iter().all(|r| (1..=6).contains(r)));
+        assert_eq!(rng.dice(3, 1), Ok(vec![1, 1, 1]));
+        assert!(rng.dice(1, 0).is_err());
+        assert!(rng.dice(MAX_DICE + 1, 6).is_err());
+
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            counts[rng.choice_index(&[1.0, 0.0, 3.0]).unwrap()] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!((800..1200).contains(&counts[0]), "{:?}", counts);
+        assert!(rng.choice_index(&[]).is_err());
+        assert!(rng.choice_index(&[0.0, 0.0]).is_err());
+        assert!(rng.choice_index(&[1.0, -1.0]).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Ratios: simplifying, comparing, and splitting a whole (or 100%) by one.
+//!
+//! Terms may be decimals. Each is read as the shortest decimal that prints
+//! as it (as in [`crate::money`]) and scaled to whole numbers, so
+//! `simplify_ratio(1.5, 2.5)` is exactly `3:5` and comparisons are decided
+//! on integers, never on rounded quotients.
+
+use std::cmp::Ordering;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::money::decimal;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, MAX_SAFE_INTEGER};
+
+fn invalid(message: String) -> CalcError {
+    CalcError::InvalidArgument(message)
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// The terms as integers with a common scale: `[1.5, 2.25]` is `[150, 225]`.
+fn integer_terms(terms: &[f64]) -> Result<Vec<i128>, CalcError> {
+    let too_precise = |t: f64| invalid(format!("{} has too many digits for an exact ratio", t));
+    let mut parts = Vec::with_capacity(terms.len());
+    for &t in terms {
+        if !t.is_finite() {
+            return Err(invalid(format!("Ratio terms must be finite, got {}", t)));
+        }
+        parts.push(decimal(&t.to_string()).map_err(|_| too_precise(t))?);
+    }
+    let scale = parts.iter().map(|&(_, s)| s).max().unwrap_or(0);
+    terms
+        .iter()
+        .zip(parts)
+        .map(|(&t, (value, s))| {
+            10i128
+                .checked_pow(scale - s)
+                .and_then(|f| value.checked_mul(f))
+                .ok_or_else(|| too_precise(t))
+        })
+        .collect()
+}
+
+/// Splits the whole number `amount` in proportion to `weights`, each part
+/// rounded down, with the units left over going one each to the parts with
+/// the largest remainders (the earlier part on a tie). The parts always sum
+/// to `amount`.
+pub(crate) fn largest_remainder(amount: u128, weights: &[u128]) -> Result<Vec<u128>, CalcError> {
+    let overflow = || invalid("Ratio is too large to split exactly".to_string());
+    let total = weights
+        .iter()
+        .try_fold(0u128, |sum, &w| sum.checked_add(w))
+        .ok_or_else(overflow)?;
+    if total == 0 {
+        return Err(invalid("Ratios must include a nonzero part".to_string()));
+    }
+    let products = weights
+        .iter()
+        .map(|&w| amount.checked_mul(w))
+        .collect::<Option<Vec<u128>>>()
+        .ok_or_else(overflow)?;
+    let mut shares: Vec<u128> = products.iter().map(|p| p / total).collect();
+    let left = amount - shares.iter().sum::<u128>();
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(products[i] % total));
+    for &i in order.iter().take(left as usize) {
+        shares[i] += 1;
+    }
+    Ok(shares)
+}
+
+/// `a:b` in lowest whole terms: `(6, 8)` is `(3, 4)`, `(1.5, 2.5)` is
+/// `(3, 5)`. A negative term keeps its sign.
+pub fn simplify_ratio(a: f64, b: f64) -> Result<(f64, f64), CalcError> {
+    let terms = integer_terms(&[a, b])?;
+    let divisor = gcd(terms[0].unsigned_abs(), terms[1].unsigned_abs());
+    if divisor == 0 {
+        return Err(invalid("A ratio needs a nonzero term".to_string()));
+    }
+    let (x, y) = (terms[0] / divisor as i128, terms[1] / divisor as i128);
+    if x.unsigned_abs().max(y.unsigned_abs()) > MAX_SAFE_INTEGER as u128 {
+        return Err(invalid(format!(
+            "{}:{} does not simplify to terms within 2^53",
+            a, b
+        )));
+    }
+    Ok((x as f64, y as f64))
+}
+
+/// Whether `a:b` is smaller than, equal to or larger than `c:d`, compared
+/// as the fractions `a/b` and `c/d`. `b` and `d` must be nonzero.
+pub fn compare_ratios(a: f64, b: f64, c: f64, d: f64) -> Result<Ordering, CalcError> {
+    let terms = integer_terms(&[a, b, c, d])?;
+    if terms[1] == 0 || terms[3] == 0 {
+        return Err(CalcError::DivisionByZero);
+    }
+    // a/b vs c/d is a*d vs c*b once both denominators are positive.
+    let (a, b) = (terms[0] * terms[1].signum(), terms[1].unsigned_abs());
+    let (c, d) = (terms[2] * terms[3].signum(), terms[3].unsigned_abs());
+    let side = |n: i128, m: u128| {
+        let product = n.unsigned_abs().checked_mul(m);
+        product.map(|p| (n.signum(), p))
+    };
+    let (lhs, rhs) = match (side(a, d), side(c, b)) {
+        (Some(lhs), Some(rhs)) => (lhs, rhs),
+        _ => return Err(invalid("Ratio terms are too large to compare".to_string())),
+    };
+    Ok(match lhs.0.cmp(&rhs.0) {
+        Ordering::Equal if lhs.0 < 0 => rhs.1.cmp(&lhs.1),
+        Ordering::Equal => lhs.1.cmp(&rhs.1),
+        other => other,
+    })
+}
+
+/// Splits the whole number `total` (a count of items, or of cents) in the
+/// ratio `parts`, with the parts summing exactly to `total`:
+/// `split_by_ratio(100, [1, 1, 1])` is `[34, 33, 33]`. Parts must be
+/// non-negative and not all zero.
+pub fn split_by_ratio(total: f64, parts: &[f64]) -> Result<Vec<f64>, CalcError> {
+    if total.fract() != 0.0 || total.abs() > MAX_SAFE_INTEGER {
+        return Err(invalid(format!(
+            "Total must be a whole number within ±(2^53 - 1), got {}",
+            total
+        )));
+    }
+    if let Some(p) = parts.iter().find(|p| **p < 0.0) {
+        return Err(invalid(format!(
+            "Ratio parts must not be negative, got {}",
+            p
+        )));
+    }
+    let weights: Vec<u128> = integer_terms(parts)?
+        .into_iter()
+        .map(|w| w as u128)
+        .collect();
+    let shares = largest_remainder(total.abs() as u128, &weights)?;
+    let sign = total.signum() as i128;
+    Ok(shares
+        .into_iter()
+        .map(|share| (sign * share as i128) as f64)
+        .collect())
+}
+
+/// Each part's share of the total as a percentage to two decimal places,
+/// for pie-chart labels: `[1, 1, 1]` is `[33.34, 33.33, 33.33]`. The
+/// hundredths are split as by [`split_by_ratio`], so the labels, printed
+/// to two decimals, always add up to exactly 100.00 where rounding each
+/// share separately could give 99.99 or 100.01. The shares are the nearest
+/// `f64`s to those labels; adding them up as floats can miss 100 by an ulp
+/// (`[1, 4, 7, 3, 6, 2]` sums to 100.00000000000001).
+pub fn percent_breakdown(parts: &[f64]) -> Result<Vec<f64>, CalcError> {
+    let hundredths = split_by_ratio(10_000.0, parts)?;
+    Ok(hundredths.into_iter().map(|h| h / 100.0).collect())
+}
+
+/// `[a, b]` in lowest terms.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = simplify_ratio)]
+pub fn simplify_ratio_js(a: f64, b: f64) -> Result<Vec<f64>, CalcErrorJs> {
+    let (x, y) = simplify_ratio(a, b).map_err(|e| CalcErrorJs::new(e, Some(a)))?;
+    Ok(vec![x, y])
+}
+
+/// -1, 0 or 1 as `a:b` is smaller than, equal to or larger than `c:d`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = compare_ratios)]
+pub fn compare_ratios_js(a: f64, b: f64, c: f64, d: f64) -> Result<i32, CalcErrorJs> {
+    let ordering = compare_ratios(a, b, c, d).map_err(CalcErrorJs::from)?;
+    Ok(ordering as i32)
+}
+
+/// `parts` is a `Float64Array`; so is the result.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = split_by_ratio)]
+pub fn split_by_ratio_js(total: f64, parts: &[f64]) -> Result<Vec<f64>, CalcErrorJs> {
+    split_by_ratio(total, parts).map_err(|e| CalcErrorJs::new(e, Some(total)))
+}
+
+/// `parts` is a `Float64Array`; so is the result.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = percent_breakdown)]
+pub fn percent_breakdown_js(parts: &[f64]) -> Result<Vec<f64>, CalcErrorJs> {
+    percent_breakdown(parts).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify_ratio() {
+        assert_eq!(simplify_ratio(6.0, 8.0), Ok((3.0, 4.0)));
+        assert_eq!(simplify_ratio(1.5, 2.5), Ok((3.0, 5.0)));
+        assert_eq!(simplify_ratio(0.1, 0.3), Ok((1.0, 3.0)));
+        assert_eq!(simplify_ratio(-4.0, 6.0), Ok((-2.0, 3.0)));
+        assert_eq!(simplify_ratio(0.0, 5.0), Ok((0.0, 1.0)));
+        assert!(simplify_ratio(0.0, 0.0).is_err());
+        assert!(simplify_ratio(1e-300, 1.0).is_err());
+        assert!(simplify_ratio(f64::NAN, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_compare_ratios() {
+        assert_eq!(compare_ratios(1.0, 3.0, 2.0, 6.0), Ok(Ordering::Equal));
+        // 0.1 + 0.2 style drift cannot make equal ratios differ.
+        assert_eq!(compare_ratios(0.3, 0.1, 3.0, 1.0), Ok(Ordering::Equal));
+        assert_eq!(compare_ratios(2.0, 3.0, 3.0, 4.0), Ok(Ordering::Less));
+        assert_eq!(compare_ratios(1.0, -2.0, -1.0, 3.0), Ok(Ordering::Less));
+        assert_eq!(compare_ratios(-1.0, 3.0, -1.0, 2.0), Ok(Ordering::Greater));
+        assert_eq!(
+            compare_ratios(1.0, 0.0, 1.0, 2.0),
+            Err(CalcError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_split_by_ratio() {
+        assert_eq!(
+            split_by_ratio(100.0, &[1.0, 1.0, 1.0]),
+            Ok(vec![34.0, 33.0, 33.0])
+        );
+        assert_eq!(
+            split_by_ratio(10.0, &[0.5, 0.3, 0.2]),
+            Ok(vec![5.0, 3.0, 2.0])
+        );
+        assert_eq!(
+            split_by_ratio(7.0, &[2.0, 0.0, 5.0]),
+            Ok(vec![2.0, 0.0, 5.0])
+        );
+        assert_eq!(split_by_ratio(-5.0, &[1.0, 1.0]), Ok(vec![-3.0, -2.0]));
+        let shares = split_by_ratio(1001.0, &[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(shares.iter().sum::<f64>(), 1001.0);
+        assert!(split_by_ratio(10.5, &[1.0]).is_err());
+        assert!(split_by_ratio(10.0, &[1.0, -1.0]).is_err());
+        assert!(split_by_ratio(10.0, &[0.0, 0.0]).is_err());
+        assert!(split_by_ratio(10.0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_percent_breakdown() {
+        assert_eq!(
+            percent_breakdown(&[1.0, 1.0, 1.0]),
+            Ok(vec![33.34, 33.33, 33.33])
+        );
+        // Rounded separately these would be 16.67 three times and 50, 100.01.
+        assert_eq!(
+            percent_breakdown(&[1.0, 1.0, 1.0, 3.0]),
+            Ok(vec![16.67, 16.67, 16.66, 50.0])
+        );
+        assert_eq!(percent_breakdown(&[0.0, 2.5]), Ok(vec![0.0, 100.0]));
+        let shares = percent_breakdown(&[3.0, 7.0, 11.0, 13.0, 17.0]).unwrap();
+        let hundredths: f64 = shares.iter().map(|p| (p * 100.0).round()).sum();
+        assert_eq!(hundredths, 10_000.0);
+        // The labels add to 100.00; the floats themselves may not.
+        let shares = percent_breakdown(&[1.0, 4.0, 7.0, 3.0, 6.0, 2.0]).unwrap();
+        assert_eq!(shares, [4.35, 17.39, 30.43, 13.04, 26.09, 8.7]);
+        let labels: Vec<String> = shares.iter().map(|p| format!("{:.2}", p)).collect();
+        let label_total: u32 = labels
+            .iter()
+            .map(|l| l.replace('.', "").parse::<u32>().unwrap())
+            .sum();
+        assert_eq!(label_total, 10_000);
+        assert_eq!(shares.iter().sum::<f64>(), 100.00000000000001);
+        assert!(percent_breakdown(&[0.0]).is_err());
+    }
+}
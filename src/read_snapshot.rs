@@ -0,0 +1,238 @@
+//! Cheap, immutable snapshots for reading a long tape.
+//!
+//! Rendering or exporting a tape of thousands of entries takes a while,
+//! and the user should be able to keep typing meanwhile. A [`ReadSnapshot`]
+//! from [`Calculator::read_snapshot`] is the state at that moment: the
+//! value, the memory and the history, which later operations do not
+//! change. The renderer works through the snapshot at its own pace while
+//! the calculator carries on.
+//!
+//! Snapshots share their entries. Cloning one copies a pointer, and taking
+//! another before the history changes reuses the same entries, so taking a
+//! snapshot every frame costs a copy of the history only in frames where it
+//! changed. Unlike `freeze`, a snapshot holds the history and nothing else;
+//! freeze for settings and formatting.
+//!
+//! A snapshot is `Send + Sync`. From Rust, a worker thread takes one
+//! through `SharedCalculator::read_snapshot`, holding the lock only for as
+//! long as that takes, and renders it without blocking the threads still
+//! operating. From JavaScript, a snapshot belongs to the thread that took
+//! it like any other WASM object: export it (`export_history_text`,
+//! `export_history_csv`, `get_history_range`) in chunks between frames, or
+//! post the export to a worker.
+
+use std::sync::Arc;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::logging::{LogEvent, LogLevel};
+use crate::tape::{history_csv, history_text};
+use crate::{CalculationHistory, Calculator};
+
+// The shared entries of the last snapshot, and the history they were
+// taken from as (version, evicted, length). Appends change the length,
+// evictions the evicted count and anything else the version.
+#[derive(Debug, Clone)]
+pub(crate) struct SnapshotCache {
+    key: (u32, usize, usize),
+    entries: Arc<[CalculationHistory]>,
+}
+
+/// The state at one moment; see the module docs.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct ReadSnapshot {
+    entries: Arc<[CalculationHistory]>,
+    value: f64,
+    memory: f64,
+    history_version: u32,
+    first_index: usize,
+}
+
+impl Calculator {
+    fn snapshot_entries(&self) -> Arc<[CalculationHistory]> {
+        let key = (
+            self.history_version,
+            self.history_evicted,
+            self.history.len(),
+        );
+        let mut cache = self.snapshot_cache.borrow_mut();
+        if let Some(cached) = cache.as_ref().filter(|cached| cached.key == key) {
+            let entries = cached.entries.clone();
+            // Released first, in case the sink takes a snapshot itself.
+            drop(cache);
+            self.log(LogLevel::Trace, || LogEvent::CacheHit {
+                cache: "read_snapshot",
+                entries: entries.len(),
+            });
+            return entries;
+        }
+        let entries: Arc<[CalculationHistory]> = self.history.iter().cloned().collect();
+        *cache = Some(SnapshotCache {
+            key,
+            entries: entries.clone(),
+        });
+        entries
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// The current state as a snapshot that later changes do not affect.
+    pub fn read_snapshot(&self) -> ReadSnapshot {
+        ReadSnapshot {
+            entries: self.snapshot_entries(),
+            value: self.current_value,
+            memory: self.memory,
+            history_version: self.history_version,
+            first_index: self.history_evicted,
+        }
+    }
+}
+
+impl ReadSnapshot {
+    pub fn entries(&self) -> &[CalculationHistory] {
+        &self.entries
+    }
+
+    /// Up to `count` entries from `start`, as in
+    /// [`Calculator::history_range`].
+    pub fn history_range(&self, start: usize, count: usize) -> &[CalculationHistory] {
+        let start = start.min(self.entries.len());
+        let end = start.saturating_add(count).min(self.entries.len());
+        &self.entries[start..end]
+    }
+
+    /// Whether both snapshots share their entries, as when the history did
+    /// not change between them.
+    pub fn shares_entries(&self, other: &ReadSnapshot) -> bool {
+        Arc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ReadSnapshot {
+    /// Another handle to the same snapshot.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = clone)]
+    pub fn clone_js(&self) -> ReadSnapshot {
+        self.clone()
+    }
+
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn get_memory(&self) -> f64 {
+        self.memory
+    }
+
+    pub fn history_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The calculator's `history_version` when the snapshot was taken.
+    pub fn history_version(&self) -> u32 {
+        self.history_version
+    }
+
+    /// The `history_sync` number of the first entry.
+    pub fn first_index(&self) -> usize {
+        self.first_index
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_history(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self.entries()).unwrap_or(JsValue::NULL)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_history_range(&self, start: usize, count: usize) -> JsValue {
+        serde_wasm_bindgen::to_value(self.history_range(start, count)).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn history_series(&self) -> Vec<f64> {
+        self.entries.iter().map(|entry| entry.result).collect()
+    }
+
+    /// As `Calculator::export_history_text`.
+    pub fn export_history_text(&self) -> String {
+        history_text(self.entries())
+    }
+
+    /// As `Calculator::export_history_csv`.
+    pub fn export_history_csv(&self) -> String {
+        history_csv(self.entries())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::SharedCalculator;
+    use crate::Operation;
+
+    #[test]
+    fn test_snapshot_is_unaffected_by_later_changes() {
+        let mut calc = Calculator::new();
+        calc.add(10.0);
+        calc.multiply(3.0);
+        calc.memory_store();
+        let snapshot = calc.read_snapshot();
+        let text = calc.export_history_text();
+
+        calc.add(1.0);
+        calc.clear_history();
+        calc.memory_clear();
+        assert_eq!(snapshot.get_value(), 30.0);
+        assert_eq!(snapshot.get_memory(), 30.0);
+        assert_eq!(snapshot.history_count(), 3);
+        assert_eq!(snapshot.export_history_text(), text);
+        assert!(snapshot
+            .export_history_csv()
+            .starts_with("operation,operand1,operand2,result,timestamp\nadd,0,10,10,"));
+        assert_eq!(snapshot.history_series(), [10.0, 30.0, 30.0]);
+        assert_eq!(snapshot.history_range(1, 10).len(), 2);
+        assert!(snapshot.history_range(5, 1).is_empty());
+        assert_ne!(snapshot.history_version(), calc.history_version());
+    }
+
+    #[test]
+    fn test_snapshot_read_on_another_thread() {
+        let shared = SharedCalculator::new();
+        shared.apply(&Operation::Add, Some(2.0)).unwrap();
+        let snapshot = shared.read_snapshot();
+        let reader = std::thread::spawn(move || snapshot.export_history_text());
+        shared.apply(&Operation::Add, Some(3.0)).unwrap();
+        assert_eq!(reader.join().unwrap(), "0 + 2 = 2\n");
+        assert_eq!(shared.read_snapshot().history_count(), 2);
+    }
+
+    #[test]
+    fn test_snapshots_share_unchanged_entries() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(2);
+        calc.add(1.0);
+        let first = calc.read_snapshot();
+        assert!(first.shares_entries(&first.clone()));
+        calc.set_value(5.0);
+        calc.clear_history();
+        calc.add(1.0);
+        // Same length, rewritten history: fresh entries.
+        let second = calc.read_snapshot();
+        assert!(!second.shares_entries(&first));
+        assert!(second.shares_entries(&calc.read_snapshot()));
+        assert_eq!(second.history_series(), [6.0]);
+
+        calc.add(1.0);
+        calc.add(1.0);
+        calc.add(1.0);
+        let third = calc.read_snapshot();
+        assert!(!third.shares_entries(&second));
+        assert_eq!(third.first_index(), 2);
+        assert_eq!(third.history_series(), [8.0, 9.0]);
+        // Clones carry the cache along with the identical history.
+        assert!(calc.clone().read_snapshot().shares_entries(&third));
+    }
+}
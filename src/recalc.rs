@@ -0,0 +1,183 @@
+//! Coalesced recomputation of a compiled expression.
+//!
+//! [`Recalculator`] owns no timer. Inputs can be set any number of times
+//! between frames (a slider drag fires dozens of `input` events per frame);
+//! each update only marks the expression dirty. The page's own
+//! `requestAnimationFrame` loop calls [`Recalculator::frame`], which
+//! re-evaluates at most once and reports whether the result changed:
+//!
+//! ```javascript
+//! const rc = new Recalculator("principal * rate / 12");
+//! slider.oninput = () => rc.set_input("rate", slider.valueAsNumber);
+//! function tick() {
+//!   if (rc.frame()) output.textContent = rc.result();
+//!   requestAnimationFrame(tick);
+//! }
+//! requestAnimationFrame(tick);
+//! ```
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, Dialect, Expr};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{same_f64, CalcError};
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct Recalculator {
+    expr: Expr,
+    inputs: BTreeMap<String, f64>,
+    dirty: bool,
+    changed: bool,
+    result: Result<f64, CalcError>,
+    evaluations: u32,
+}
+
+impl Recalculator {
+    /// Compiles `source` in the standard dialect. Nothing is evaluated until
+    /// the first [`frame`](Self::frame).
+    pub fn new(source: &str) -> Result<Recalculator, CalcError> {
+        Ok(Recalculator {
+            expr: parse(source, Dialect::Standard)?,
+            inputs: BTreeMap::new(),
+            dirty: true,
+            changed: false,
+            result: Ok(f64::NAN),
+            evaluations: 0,
+        })
+    }
+
+    /// The error from the latest evaluation, if it failed.
+    pub fn last_error(&self) -> Option<&CalcError> {
+        self.result.as_ref().err()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Recalculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new_js(source: &str) -> Result<Recalculator, CalcErrorJs> {
+        Recalculator::new(source).map_err(CalcErrorJs::from)
+    }
+
+    /// Records an input; cheap, and safe to call many times per frame.
+    pub fn set_input(&mut self, name: &str, value: f64) {
+        let previous = self.inputs.insert(name.to_string(), value);
+        if !previous.is_some_and(|p| same_f64(p, value)) {
+            self.dirty = true;
+        }
+    }
+
+    /// Recomputes if any input changed since the last frame. Returns whether
+    /// the result (or error) differs from the previous frame's.
+    pub fn frame(&mut self) -> bool {
+        self.changed = false;
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+        self.evaluations += 1;
+        let result = self.expr.eval(&self.inputs);
+        self.changed = match (&self.result, &result) {
+            (Ok(old), Ok(new)) => !same_f64(*old, *new) || self.evaluations == 1,
+            (Err(old), Err(new)) => old != new,
+            _ => true,
+        };
+        self.result = result;
+        self.changed
+    }
+
+    /// The latest result; NaN before the first frame or after an error.
+    pub fn result(&self) -> f64 {
+        self.result.clone().unwrap_or(f64::NAN)
+    }
+
+    /// Whether the last [`frame`](Self::frame) changed the result.
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Whether an input update is waiting for the next frame.
+    pub fn pending(&self) -> bool {
+        self.dirty
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = error)]
+    pub fn error_js(&self) -> Option<CalcErrorJs> {
+        self.last_error().cloned().map(CalcErrorJs::from)
+    }
+
+    /// How many times the expression has actually been evaluated.
+    pub fn evaluations(&self) -> u32 {
+        self.evaluations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_coalesce_into_one_evaluation() {
+        let mut rc = Recalculator::new("x * 2 + y").unwrap();
+        rc.set_input("y", 1.0);
+        for i in 0..50 {
+            rc.set_input("x", i as f64);
+        }
+        assert!(rc.pending());
+        assert!(rc.frame());
+        assert_eq!(rc.result(), 99.0);
+        assert_eq!(rc.evaluations(), 1);
+        assert!(!rc.frame());
+        assert!(!rc.changed());
+        assert_eq!(rc.evaluations(), 1);
+    }
+
+    #[test]
+    fn test_changed_flag_tracks_result_not_inputs() {
+        let mut rc = Recalculator::new("abs(x)").unwrap();
+        rc.set_input("x", 3.0);
+        assert!(rc.frame());
+        rc.set_input("x", -3.0);
+        assert!(!rc.frame());
+        assert_eq!(rc.evaluations(), 2);
+        rc.set_input("x", -3.0);
+        assert!(!rc.pending());
+    }
+
+    #[test]
+    fn test_errors_are_reported_and_recover() {
+        let mut rc = Recalculator::new("1 / x + y").unwrap();
+        assert!(rc.frame());
+        assert_eq!(
+            rc.last_error(),
+            Some(&CalcError::UnknownIdentifier {
+                name: "x".to_string(),
+                suggestions: vec![]
+            })
+        );
+        assert!(rc.result().is_nan());
+        rc.set_input("x", 0.0);
+        rc.set_input("y", 1.0);
+        assert!(rc.frame());
+        assert_eq!(rc.last_error(), Some(&CalcError::DivisionByZero));
+        rc.set_input("x", 2.0);
+        assert!(rc.frame());
+        assert_eq!(rc.result(), 1.5);
+        assert_eq!(rc.last_error(), None);
+    }
+
+    #[test]
+    fn test_compile_errors_surface_immediately() {
+        assert!(matches!(
+            Recalculator::new("1 +"),
+            Err(CalcError::Syntax { .. })
+        ));
+    }
+}
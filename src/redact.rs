@@ -0,0 +1,154 @@
+//! Calculator state with the numbers taken out, for bug reports.
+//!
+//! [`Calculator::export_state_redacted`] describes what a calculator holds
+//! without any of its figures: which operations the history records and in
+//! what order, how many of each the session ran, the settings, which errors
+//! occurred and how many variables, functions and templates exist. Values,
+//! operands, results, memory, timestamps, variable and function names and
+//! error messages (which can quote the input) are all left out; the value
+//! and memory only say whether they are zero, finite, NaN or infinite.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::settings::CalculatorSettings;
+use crate::tape::operation_name;
+use crate::{Calculator, Operation};
+
+/// What a redacted value still says about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueKind {
+    Zero,
+    Finite,
+    NaN,
+    Infinite,
+}
+
+impl ValueKind {
+    pub fn of(value: f64) -> ValueKind {
+        if value == 0.0 {
+            ValueKind::Zero
+        } else if value.is_nan() {
+            ValueKind::NaN
+        } else if value.is_infinite() {
+            ValueKind::Infinite
+        } else {
+            ValueKind::Finite
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RedactedState {
+    /// The crate version of this build.
+    pub version: &'static str,
+    pub settings: CalculatorSettings,
+    pub value: ValueKind,
+    pub memory: ValueKind,
+    /// The operation of each history entry, oldest first. User functions
+    /// are `function`, without their names.
+    pub history: Vec<String>,
+    /// 0 when entries do not expire.
+    pub history_max_age_seconds: f64,
+    /// Operations run this session by name, as in `session_summary`.
+    pub session_operations: BTreeMap<String, usize>,
+    /// The codes of the logged errors, oldest first.
+    pub error_codes: Vec<String>,
+    pub variables: usize,
+    pub functions: usize,
+    pub templates: usize,
+    pub education_mode: bool,
+}
+
+fn redacted_name(operation: &Operation) -> String {
+    match operation {
+        Operation::Function(_) => "function".to_string(),
+        other => operation_name(other),
+    }
+}
+
+impl Calculator {
+    pub fn redacted_state(&self) -> RedactedState {
+        let mut session_operations = BTreeMap::new();
+        for (name, count) in self.session_summary().operations {
+            let name = if name.starts_with("function:") {
+                "function".to_string()
+            } else {
+                name
+            };
+            *session_operations.entry(name).or_default() += count;
+        }
+        RedactedState {
+            version: env!("CARGO_PKG_VERSION"),
+            settings: self.settings(),
+            value: ValueKind::of(self.current_value),
+            memory: ValueKind::of(self.memory),
+            history: self
+                .history
+                .iter()
+                .map(|entry| redacted_name(&entry.operation))
+                .collect(),
+            history_max_age_seconds: self.history_max_age_seconds(),
+            session_operations,
+            error_codes: self.error_log().into_iter().map(|e| e.code).collect(),
+            variables: self.list_variables().len(),
+            functions: self.functions.len(),
+            templates: self.templates.len(),
+            education_mode: self.education.is_some(),
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// [`Calculator::redacted_state`] as indented JSON, ready to paste into
+    /// a bug report.
+    pub fn export_state_redacted(&self) -> String {
+        serde_json::to_string_pretty(&self.redacted_state()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted_state() {
+        let mut calc = Calculator::new();
+        calc.set_value(1234.56);
+        calc.multiply(1.07);
+        calc.memory_store();
+        calc.define_function("gratuity", "x * 0.18").unwrap();
+        calc.call_function("gratuity").unwrap();
+        calc.set_variable("salary", 98_000.0).unwrap();
+        assert!(calc.evaluate("98000 /").is_err());
+        let state = calc.redacted_state();
+        assert_eq!(
+            state.history,
+            ["set_value", "multiply", "memory_store", "function"]
+        );
+        assert_eq!(state.session_operations["function"], 1);
+        assert_eq!(state.value, ValueKind::Finite);
+        assert_eq!(state.memory, ValueKind::Finite);
+        assert_eq!(state.error_codes, ["SYNTAX_ERROR"]);
+        assert_eq!((state.variables, state.functions), (1, 1));
+
+        let json = calc.export_state_redacted();
+        for secret in ["1234", "1320", "98000", "98", "0.18", "salary", "gratuity"] {
+            assert!(!json.contains(secret), "{} leaked in {}", secret, json);
+        }
+        assert!(json.contains("\"angle_unit\": \"radians\""));
+    }
+
+    #[test]
+    fn test_value_kind() {
+        assert_eq!(ValueKind::of(-0.0), ValueKind::Zero);
+        assert_eq!(ValueKind::of(f64::NAN), ValueKind::NaN);
+        assert_eq!(ValueKind::of(f64::NEG_INFINITY), ValueKind::Infinite);
+        assert_eq!(ValueKind::of(1e-300), ValueKind::Finite);
+    }
+}
@@ -0,0 +1,232 @@
+//! Line-oriented front-end shared by the `wasm-calc` binary and its tests.
+//!
+//! Each line is either a calculator command (`add 5`, `+ 5`, `sqrt`, `ms`)
+//! applied to the running value, a REPL command (`history`, `state`, ...),
+//! or an expression evaluated with [`crate::expr`] whose result becomes
+//! the new value. Expressions can refer to the running value as `ans` and to
+//! memory as `mem`.
+
+use std::io::{self, BufRead, Write};
+
+use crate::tape::parse_operation;
+use crate::{CalcError, Calculator};
+
+pub const HELP: &str = "\
+Commands:
+  add|sub|mul|div|pow N   apply to the current value (or + - * / ^ N)
+  + N%, - N%, * N%        mark up, discount, or take N percent
+  root N, mod N           Nth root, remainder
+  sqrt, inv, abs, neg     unary operations
+  clear                   reset the current value to 0
+  ms, mr, mc, memory_add  memory store / recall / clear / add
+  value, memory           print the current value or memory
+  history, state          print the history or the canonical state
+  help, quit
+Anything else is evaluated as an expression; `ans` is the current value
+and `mem` the memory, e.g. `ans * 2 + sqrt(mem)`.";
+
+/// What a single line asked the REPL to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Nothing to print (blank line or comment).
+    Silent,
+    /// Text to print.
+    Output(String),
+    /// The user asked to leave.
+    Quit,
+}
+
+fn format_value(value: f64) -> String {
+    format!("{}", value)
+}
+
+/// Runs one line against `calc`. Lines starting with `#` are comments.
+pub fn execute(calc: &mut Calculator, line: &str) -> Result<Outcome, CalcError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(Outcome::Silent);
+    }
+    match line.to_ascii_lowercase().as_str() {
+        "quit" | "exit" => return Ok(Outcome::Quit),
+        "help" | "?" => return Ok(Outcome::Output(HELP.to_string())),
+        "value" => return Ok(Outcome::Output(format_value(calc.get_value()))),
+        "memory" => return Ok(Outcome::Output(format_value(calc.get_memory()))),
+        "state" => return Ok(Outcome::Output(calc.canonical_state_string())),
+        "history" => {
+            let lines: Vec<String> = calc
+                .history_range(0, usize::MAX)
+                .iter()
+                .enumerate()
+                .map(|(i, h)| match h.operand2 {
+                    Some(b) => format!(
+                        "{}: {:?} {} {} -> {}",
+                        i, h.operation, h.operand1, b, h.result
+                    ),
+                    None => format!("{}: {:?} {} -> {}", i, h.operation, h.operand1, h.result),
+                })
+                .collect();
+            return Ok(Outcome::Output(lines.join("\n")));
+        }
+        _ => {}
+    }
+
+    if let Some(value) = try_command(calc, line)? {
+        return Ok(Outcome::Output(format_value(value)));
+    }
+
+    let value = calc.evaluate(line)?;
+    Ok(Outcome::Output(format_value(value)))
+}
+
+/// Applies `line` as `<operation> [operand]` if it reads as one. Returns
+/// `None` when the line should be treated as an expression instead.
+fn try_command(calc: &mut Calculator, line: &str) -> Result<Option<f64>, CalcError> {
+    // "+5" and "+ 5" both apply to the running value, like a tape.
+    let symbol_end = line
+        .char_indices()
+        .next()
+        .filter(|(_, c)| matches!(c, '+' | '-' | '*' | '/' | '^'))
+        .map(|(i, c)| i + c.len_utf8());
+    let (name, rest) = match symbol_end {
+        Some(end) => line.split_at(end),
+        None => line.split_once(char::is_whitespace).unwrap_or((line, "")),
+    };
+    let operation = if name == "^" {
+        Some(crate::Operation::Power)
+    } else {
+        parse_operation(name)
+    };
+    let Some(mut operation) = operation else {
+        return Ok(None);
+    };
+    let mut rest = rest.trim();
+    // The % key: "+ 10%" marks up by 10 percent rather than adding 10.
+    if let Some(number) = rest.strip_suffix('%') {
+        let percent_operation = match operation {
+            crate::Operation::Add => crate::Operation::AddPercent,
+            crate::Operation::Subtract => crate::Operation::SubtractPercent,
+            crate::Operation::Multiply => crate::Operation::PercentOf,
+            _ => return Ok(None),
+        };
+        operation = percent_operation;
+        rest = number.trim_end();
+    }
+    let operand = if rest.is_empty() {
+        None
+    } else {
+        match rest.parse::<f64>() {
+            Ok(v) => Some(v),
+            Err(_) => return Ok(None),
+        }
+    };
+    calc.apply(&operation, operand).map(Some)
+}
+
+/// Reads lines from `input` until EOF or `quit`, writing results and
+/// errors to `output`. When `prompt` is set it is printed before each line.
+/// Returns the number of lines that failed.
+pub fn run<R: BufRead, W: Write>(
+    calc: &mut Calculator,
+    input: R,
+    mut output: W,
+    prompt: Option<&str>,
+) -> io::Result<usize> {
+    let mut failures = 0;
+    let mut lines = input.lines();
+    loop {
+        if let Some(prompt) = prompt {
+            write!(output, "{}", prompt)?;
+            output.flush()?;
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        match execute(calc, &line?) {
+            Ok(Outcome::Silent) => {}
+            Ok(Outcome::Output(text)) => writeln!(output, "{}", text)?,
+            Ok(Outcome::Quit) => break,
+            Err(e) => {
+                failures += 1;
+                writeln!(output, "error: {}", e)?;
+            }
+        }
+    }
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(calc: &mut Calculator, line: &str) -> String {
+        match execute(calc, line) {
+            Ok(Outcome::Output(text)) => text,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_commands_and_symbols() {
+        let mut calc = Calculator::new();
+        assert_eq!(output(&mut calc, "add 5"), "5");
+        assert_eq!(output(&mut calc, "* 4"), "20");
+        assert_eq!(output(&mut calc, "-5"), "15");
+        assert_eq!(output(&mut calc, "^2"), "225");
+        assert_eq!(output(&mut calc, "sqrt"), "15");
+        assert_eq!(output(&mut calc, "ms"), "15");
+        assert_eq!(output(&mut calc, "memory"), "15");
+        assert_eq!(calc.history_count(), 6);
+    }
+
+    #[test]
+    fn test_expressions_use_ans_and_mem() {
+        let mut calc = Calculator::new();
+        assert_eq!(output(&mut calc, "2 * (3 + 4)"), "14");
+        execute(&mut calc, "ms").unwrap();
+        assert_eq!(output(&mut calc, "ans + mem / 2"), "21");
+        assert_eq!(output(&mut calc, "-2 * 3"), "-6");
+        assert_eq!(calc.get_value(), -6.0);
+    }
+
+    #[test]
+    fn test_percent_key() {
+        let mut calc = Calculator::new();
+        calc.set_value(200.0);
+        assert_eq!(output(&mut calc, "+ 10%"), "220");
+        assert_eq!(output(&mut calc, "-50%"), "110");
+        assert_eq!(output(&mut calc, "* 10 %"), "11");
+        assert_eq!(output(&mut calc, "percent_of 50"), "5.5");
+        assert!(execute(&mut calc, "/ 10%").is_err());
+    }
+
+    #[test]
+    fn test_errors_and_silent_lines() {
+        let mut calc = Calculator::new();
+        assert_eq!(execute(&mut calc, "   "), Ok(Outcome::Silent));
+        assert_eq!(execute(&mut calc, "# note"), Ok(Outcome::Silent));
+        assert_eq!(execute(&mut calc, "div 0"), Err(CalcError::DivisionByZero));
+        assert!(matches!(
+            execute(&mut calc, "add"),
+            Err(CalcError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            execute(&mut calc, "1 +"),
+            Err(CalcError::Syntax { .. })
+        ));
+        assert_eq!(execute(&mut calc, "quit"), Ok(Outcome::Quit));
+    }
+
+    #[test]
+    fn test_run_reports_failures_and_stops_at_quit() {
+        let mut calc = Calculator::new();
+        let script = "add 2\n+ 3\ndiv 0\nvalue\nquit\nadd 100\n";
+        let mut out = Vec::new();
+        let failures = run(&mut calc, script.as_bytes(), &mut out, None).unwrap();
+        assert_eq!(failures, 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2\n5\nerror: Division by zero\n5\n"
+        );
+        assert_eq!(calc.get_value(), 5.0);
+    }
+}
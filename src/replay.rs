@@ -0,0 +1,309 @@
+//! Replaying and editing the recorded history.
+//!
+//! The history is a record of operations, so it can be run again:
+//! [`Calculator::replay_history`] re-executes every entry from a new
+//! starting value, and [`Calculator::edit_history_entry`] changes one
+//! entry's operand and recomputes everything after it, for "edit a previous
+//! step" tapes. Either way the entries are rewritten in place (keeping
+//! their timestamps, operand sources and authors) rather than appended, and the
+//! current value and memory end up where the recomputed history leaves
+//! them.
+//!
+//! Both are all-or-nothing: if a recomputed step fails (the edit makes a
+//! later division divide by zero, say) the calculator is left unchanged.
+//! Function entries run the function's current definition.
+//!
+//! [`Calculator::fork_at`] branches instead: it returns a new calculator
+//! holding the history up to an entry, so an alternate continuation can be
+//! explored without touching the original tape.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::summary::SessionStats;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+// Operations whose `operand2` is an explicit argument that can be edited.
+pub(crate) fn has_editable_operand(operation: &Operation) -> bool {
+    matches!(
+        operation,
+        Operation::Add
+            | Operation::Subtract
+            | Operation::Multiply
+            | Operation::Divide
+            | Operation::Power
+            | Operation::SetValue
+            | Operation::PercentOf
+            | Operation::AddPercent
+            | Operation::SubtractPercent
+            | Operation::Round
+            | Operation::NthRoot
+            | Operation::Modulo
+    )
+}
+
+impl Calculator {
+    // The memory just before `history[index]`, read back from the entries:
+    // the last memory the history shows before it, or else what the first
+    // memory entry from `index` on read. `None` when that entry stores over
+    // the memory, so the entries never show it.
+    fn shown_memory_before(&self, index: usize) -> Option<f64> {
+        let shown = self
+            .history
+            .range(..index)
+            .rev()
+            .find_map(|e| match e.operation {
+                Operation::MemoryRecall => Some(e.operand1),
+                ref op if op.affects_memory() => Some(e.result),
+                _ => None,
+            });
+        let read = || {
+            let first = self
+                .history
+                .range(index..)
+                .find(|e| e.operation.affects_memory() || e.operation == Operation::MemoryRecall);
+            match first {
+                Some(e) if e.operation == Operation::MemoryStore => None,
+                Some(e) => Some(e.operand1),
+                None => Some(self.memory),
+            }
+        };
+        shown.or_else(read)
+    }
+
+    // The memory to recompute `history[index..]` from. Where it is stored
+    // over before anything reads it, any value works.
+    fn memory_before(&self, index: usize) -> f64 {
+        self.shown_memory_before(index).unwrap_or(self.memory)
+    }
+
+    // Replaces `history[index..]` with the entries `steps` record when run
+    // from `start_value`.
+    fn recompute_from(
+        &mut self,
+        index: usize,
+        start_value: f64,
+        steps: Vec<(Operation, Option<f64>)>,
+    ) -> Result<f64, CalcError> {
+        let kept: Vec<_> = self
+            .history
+            .range(index..)
+            .map(|e| (e.timestamp, e.operand_source.clone(), e.author.clone()))
+            .collect();
+        let mut scratch = self.clone();
+        scratch.recording = None;
+        scratch.memory = self.memory_before(index);
+        scratch.history.truncate(index);
+        scratch.current_value = start_value;
+        for (operation, operand) in steps {
+            scratch
+                .apply(&operation, operand)
+                .map_err(|e| self.fail(e, operand))?;
+        }
+        for (entry, (timestamp, source, author)) in scratch.history.range_mut(index..).zip(kept) {
+            entry.timestamp = timestamp;
+            entry.operand_source = source;
+            entry.author = author;
+        }
+        // Recomputing is not new work for the session summary.
+        scratch.session = self.session.clone();
+        self.pass_registry_id(&mut scratch);
+        scratch.recording = self.recording.take();
+        *self = scratch;
+        self.history_rewritten();
+        self.notify_changed();
+        Ok(self.current_value)
+    }
+
+    /// Re-executes every recorded operation, oldest first, starting from
+    /// `start_value` instead of the value the history started from, and
+    /// returns the new current value.
+    pub fn replay_history(&mut self, start_value: f64) -> Result<f64, CalcError> {
+        let steps = self
+            .history
+            .iter()
+            .map(|e| (e.operation.clone(), e.operand2))
+            .collect();
+        self.recompute_from(0, start_value, steps)
+    }
+
+    /// Changes the operand of `history[index]` (an add, divide, set value,
+    /// round, ... entry) to `new_operand` and recomputes that entry and every
+    /// one after it. Returns the new current value.
+    pub fn edit_history_entry(&mut self, index: usize, new_operand: f64) -> Result<f64, CalcError> {
+        let Some(entry) = self.history.get(index) else {
+            let err = CalcError::InvalidArgument(format!(
+                "No history entry {} (the history holds {})",
+                index,
+                self.history.len()
+            ));
+            return Err(self.fail(err, Some(new_operand)));
+        };
+        if !has_editable_operand(&entry.operation) {
+            let err = CalcError::InvalidArgument(format!(
+                "history[{}] is {:?}, which has no operand to edit",
+                index, entry.operation
+            ));
+            return Err(self.fail(err, Some(new_operand)));
+        }
+        let start_value = entry.operand1;
+        let steps = std::iter::once((entry.operation.clone(), Some(new_operand)))
+            .chain(
+                self.history
+                    .range(index + 1..)
+                    .map(|e| (e.operation.clone(), e.operand2)),
+            )
+            .collect();
+        self.recompute_from(index, start_value, steps)?;
+        // The new operand was typed in.
+        if let Some(entry) = self.history.get_mut(index) {
+            entry.operand_source = Default::default();
+        }
+        Ok(self.current_value)
+    }
+
+    /// A new calculator in the state `history[index]` left this one in: the
+    /// history up to and including that entry, as recorded, with its result
+    /// as the current value and the memory it left (0 if the entries never
+    /// show it before it is stored over). Settings, functions, variables,
+    /// templates and quotas carry over; the session summary and error log
+    /// start empty, and no listener is attached.
+    pub fn fork_at(&self, index: usize) -> Result<Calculator, CalcError> {
+        let Some(entry) = self.history.get(index) else {
+            let err = CalcError::InvalidArgument(format!(
+                "No history entry {} (the history holds {})",
+                index,
+                self.history.len()
+            ));
+            return Err(self.fail(err, None));
+        };
+        let mut fork = self.clone();
+        fork.recording = None;
+        fork.keypad = Default::default();
+        fork.current_value = entry.result;
+        // Stored over later without being read, the memory of the time is
+        // unknown; start it clear, as in a new calculator.
+        fork.memory = self.shown_memory_before(index + 1).unwrap_or(0.0);
+        fork.history.truncate(index + 1);
+        fork.history_rewritten();
+        fork.session = SessionStats::new();
+        fork.error_log = Default::default();
+        Ok(fork)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = replay_history)]
+    pub fn replay_history_js(&mut self, start_value: f64) -> Result<f64, CalcErrorJs> {
+        self.replay_history(start_value)
+            .map_err(|e| CalcErrorJs::new(e, Some(start_value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = edit_history_entry)]
+    pub fn edit_history_entry_js(
+        &mut self,
+        index: usize,
+        new_operand: f64,
+    ) -> Result<f64, CalcErrorJs> {
+        self.edit_history_entry(index, new_operand)
+            .map_err(|e| CalcErrorJs::new(e, Some(new_operand)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = fork_at)]
+    pub fn fork_at_js(&self, index: usize) -> Result<Calculator, CalcErrorJs> {
+        self.fork_at(index).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(calc: &Calculator) -> Vec<f64> {
+        calc.history_range(0, usize::MAX)
+            .iter()
+            .map(|e| e.result)
+            .collect()
+    }
+
+    fn tape() -> Calculator {
+        let mut calc = Calculator::new();
+        calc.set_value(10.0);
+        calc.add(5.0);
+        calc.memory_store();
+        calc.multiply(2.0);
+        calc.memory_add();
+        calc.memory_recall();
+        calc.divide(3.0).unwrap();
+        calc
+    }
+
+    #[test]
+    fn test_edit_history_entry() {
+        let mut calc = tape();
+        assert_eq!(
+            results(&calc),
+            vec![10.0, 15.0, 15.0, 30.0, 45.0, 45.0, 15.0]
+        );
+        let stamp = calc.history_range(3, 1)[0].timestamp;
+        assert_eq!(calc.edit_history_entry(1, 20.0), Ok(30.0));
+        assert_eq!(
+            results(&calc),
+            vec![10.0, 30.0, 30.0, 60.0, 90.0, 90.0, 30.0]
+        );
+        assert_eq!(calc.get_memory(), 90.0);
+        assert_eq!(calc.history_range(3, 1)[0].timestamp, stamp);
+        assert_eq!(calc.check_invariants(), Ok(()));
+
+        assert_eq!(
+            calc.edit_history_entry(6, 0.0),
+            Err(CalcError::DivisionByZero)
+        );
+        assert_eq!(calc.current_value, 30.0);
+        assert!(calc.edit_history_entry(2, 1.0).is_err());
+        assert!(calc.edit_history_entry(7, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_fork_at() {
+        let calc = tape();
+        let mut fork = calc.fork_at(3).unwrap();
+        assert_eq!(results(&fork), vec![10.0, 15.0, 15.0, 30.0]);
+        assert_eq!((fork.get_value(), fork.get_memory()), (30.0, 15.0));
+        assert_eq!(fork.check_invariants(), Ok(()));
+        assert_eq!(fork.session_summary().total_operations, 0);
+        fork.subtract(10.0);
+        assert_eq!(fork.history_count(), 5);
+        assert_eq!(calc.history_count(), 7);
+        assert_eq!(calc.get_value(), 15.0);
+
+        let fork = calc.fork_at(0).unwrap();
+        assert_eq!((fork.get_value(), fork.get_memory()), (10.0, 0.0));
+        let fork = calc.fork_at(6).unwrap();
+        assert_eq!((fork.get_value(), fork.get_memory()), (15.0, 45.0));
+        assert!(calc.fork_at(7).is_err());
+    }
+
+    #[test]
+    fn test_replay_history() {
+        let mut calc = Calculator::new();
+        calc.add(5.0);
+        calc.multiply(3.0);
+        calc.memory_add();
+        calc.subtract(1.0);
+        assert_eq!(calc.replay_history(1.0), Ok(17.0));
+        assert_eq!(results(&calc), vec![6.0, 18.0, 18.0, 17.0]);
+        assert_eq!(calc.get_memory(), 18.0);
+        assert_eq!(calc.session_summary().total_operations, 4);
+        assert_eq!(calc.check_invariants(), Ok(()));
+        // The memory entry read 0, so replay starts memory there.
+        assert_eq!(calc.replay_history(0.0), Ok(14.0));
+        assert_eq!(calc.get_memory(), 15.0);
+    }
+}
@@ -0,0 +1,315 @@
+//! Year-by-year retirement projections.
+//!
+//! [`project_retirement`] follows a balance from `current_age` to
+//! `end_age`: contributions build it up until `retirement_age`, then
+//! withdrawals draw it down. Each year the balance grows at the return rate
+//! of its phase, and the contribution or withdrawal is made at the end of
+//! the year. Rates are annual percentages, like
+//! [`crate::compound_interest`].
+//!
+//! The withdrawal is given in today's money and rises with inflation, so it
+//! keeps its purchasing power; each year also reports the balance in
+//! today's money. A withdrawal the balance cannot cover takes what is left,
+//! and [`RetirementProjection::depleted_at_age`] records when that
+//! happened. The full series is returned for charting.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// Projections longer than this many years are refused.
+pub const MAX_PROJECTION_YEARS: u32 = 150;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RetirementParams {
+    pub current_age: u32,
+    pub retirement_age: u32,
+    /// The projection runs up to this age.
+    pub end_age: u32,
+    #[serde(default)]
+    pub starting_balance: f64,
+    /// Added at the end of each working year.
+    #[serde(default)]
+    pub annual_contribution: f64,
+    /// Yearly growth of the contribution, in percent (default 0).
+    #[serde(default)]
+    pub contribution_growth: f64,
+    /// Return while working, in percent per year.
+    pub return_rate: f64,
+    /// Return once retired; defaults to `return_rate`.
+    #[serde(default)]
+    pub retirement_return_rate: Option<f64>,
+    #[serde(default)]
+    pub inflation_rate: f64,
+    /// Taken at the end of each retired year, in today's money.
+    #[serde(default)]
+    pub annual_withdrawal: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Accumulation,
+    Withdrawal,
+}
+
+/// One year of a projection. Amounts are in the money of the time except
+/// `real_balance`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RetirementYear {
+    /// Age at the start of the year.
+    pub age: u32,
+    pub phase: Phase,
+    pub starting_balance: f64,
+    pub growth: f64,
+    pub contribution: f64,
+    pub withdrawal: f64,
+    pub ending_balance: f64,
+    /// `ending_balance` in today's money.
+    pub real_balance: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RetirementProjection {
+    pub years: Vec<RetirementYear>,
+    /// The balance on reaching `retirement_age`.
+    pub balance_at_retirement: f64,
+    pub final_balance: f64,
+    /// The age in whose year the balance ran out, if it did.
+    pub depleted_at_age: Option<u32>,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn check_rate(name: &str, rate: f64) -> Result<f64, CalcError> {
+    if !rate.is_finite() || rate <= -100.0 {
+        return Err(invalid(format!(
+            "{} must be greater than -100%, got {}",
+            name, rate
+        )));
+    }
+    Ok(rate / 100.0)
+}
+
+fn check_amount(name: &str, amount: f64) -> Result<f64, CalcError> {
+    if !amount.is_finite() || amount < 0.0 {
+        return Err(invalid(format!(
+            "{} must be a non-negative amount, got {}",
+            name, amount
+        )));
+    }
+    Ok(amount)
+}
+
+/// Projects `params` year by year.
+pub fn project_retirement(params: &RetirementParams) -> Result<RetirementProjection, CalcError> {
+    let RetirementParams {
+        current_age,
+        retirement_age,
+        end_age,
+        ..
+    } = *params;
+    if !(current_age <= retirement_age && retirement_age <= end_age) {
+        return Err(invalid(format!(
+            "Ages must satisfy current_age <= retirement_age <= end_age, got {}, {}, {}",
+            current_age, retirement_age, end_age
+        )));
+    }
+    if end_age - current_age > MAX_PROJECTION_YEARS {
+        return Err(invalid(format!(
+            "Projections are limited to {} years",
+            MAX_PROJECTION_YEARS
+        )));
+    }
+    let working_return = check_rate("return_rate", params.return_rate)?;
+    let retired_return = check_rate(
+        "retirement_return_rate",
+        params.retirement_return_rate.unwrap_or(params.return_rate),
+    )?;
+    let inflation = check_rate("inflation_rate", params.inflation_rate)?;
+    let contribution_growth = check_rate("contribution_growth", params.contribution_growth)?;
+    let mut balance = check_amount("starting_balance", params.starting_balance)?;
+    let contribution = check_amount("annual_contribution", params.annual_contribution)?;
+    let withdrawal = check_amount("annual_withdrawal", params.annual_withdrawal)?;
+
+    let mut years = Vec::with_capacity((end_age - current_age) as usize);
+    let mut balance_at_retirement = balance;
+    let mut depleted_at_age = None;
+    for (n, age) in (current_age..end_age).enumerate() {
+        let n = n as i32;
+        let phase = if age < retirement_age {
+            Phase::Accumulation
+        } else {
+            Phase::Withdrawal
+        };
+        let starting_balance = balance;
+        let rate = match phase {
+            Phase::Accumulation => working_return,
+            Phase::Withdrawal => retired_return,
+        };
+        let growth = starting_balance * rate;
+        balance += growth;
+        let (added, taken) = match phase {
+            Phase::Accumulation => (contribution * (1.0 + contribution_growth).powi(n), 0.0),
+            Phase::Withdrawal => {
+                // Inflated to the end of the year it is taken.
+                let wanted = withdrawal * (1.0 + inflation).powi(n + 1);
+                if wanted > balance && depleted_at_age.is_none() {
+                    depleted_at_age = Some(age);
+                }
+                (0.0, wanted.min(balance.max(0.0)))
+            }
+        };
+        balance += added - taken;
+        if age + 1 == retirement_age {
+            balance_at_retirement = balance;
+        }
+        years.push(RetirementYear {
+            age,
+            phase,
+            starting_balance,
+            growth,
+            contribution: added,
+            withdrawal: taken,
+            ending_balance: balance,
+            real_balance: balance / (1.0 + inflation).powi(n + 1),
+        });
+    }
+    Ok(RetirementProjection {
+        years,
+        balance_at_retirement,
+        final_balance: balance,
+        depleted_at_age,
+    })
+}
+
+/// `params` is `{ current_age, retirement_age, end_age, return_rate }` plus
+/// any of `starting_balance`, `annual_contribution`, `contribution_growth`,
+/// `retirement_return_rate`, `inflation_rate` and `annual_withdrawal`;
+/// returns `{ years: [{ age, phase, starting_balance, growth, contribution,
+/// withdrawal, ending_balance, real_balance }], balance_at_retirement,
+/// final_balance, depleted_at_age }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = project_retirement)]
+pub fn project_retirement_js(params: JsValue) -> Result<JsValue, CalcErrorJs> {
+    let params: RetirementParams = serde_wasm_bindgen::from_value(params)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Invalid parameters: {}", e))))?;
+    let projection = project_retirement(&params).map_err(CalcErrorJs::from)?;
+    serde_wasm_bindgen::to_value(&projection)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Could not serialize projection: {}", e))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    fn params() -> RetirementParams {
+        RetirementParams {
+            current_age: 30,
+            retirement_age: 65,
+            end_age: 90,
+            starting_balance: 10_000.0,
+            annual_contribution: 6_000.0,
+            return_rate: 6.0,
+            retirement_return_rate: Some(4.0),
+            inflation_rate: 2.0,
+            annual_withdrawal: 40_000.0,
+            ..RetirementParams::default()
+        }
+    }
+
+    #[test]
+    fn test_accumulation_matches_annuity_math() {
+        let projection = project_retirement(&RetirementParams {
+            annual_withdrawal: 0.0,
+            ..params()
+        })
+        .unwrap();
+        assert_eq!(projection.years.len(), 60);
+        assert_eq!(projection.years[0].age, 30);
+        assert_eq!(projection.years[34].phase, Phase::Accumulation);
+        assert_eq!(projection.years[35].phase, Phase::Withdrawal);
+        // 10000 compounded for 35 years plus an end-of-year annuity of 6000.
+        let growth = 1.06f64.powi(35);
+        let expected = 10_000.0 * growth + 6_000.0 * (growth - 1.0) / 0.06;
+        assert!(
+            close(projection.balance_at_retirement, expected, 1e-6),
+            "{}",
+            projection.balance_at_retirement
+        );
+        assert_eq!(
+            projection.years[35].starting_balance,
+            projection.balance_at_retirement
+        );
+        let last = projection.years.last().unwrap();
+        assert!(close(
+            last.real_balance,
+            last.ending_balance / 1.02f64.powi(60),
+            1e-6
+        ));
+        assert_eq!(projection.depleted_at_age, None);
+    }
+
+    #[test]
+    fn test_withdrawals_and_depletion() {
+        let projection = project_retirement(&params()).unwrap();
+        let first = &projection.years[35];
+        assert!(close(first.withdrawal, 40_000.0 * 1.02f64.powi(36), 1e-9));
+        assert!(close(first.growth, first.starting_balance * 0.04, 1e-9));
+
+        let projection = project_retirement(&RetirementParams {
+            annual_withdrawal: 100_000.0,
+            ..params()
+        })
+        .unwrap();
+        let age = projection.depleted_at_age.unwrap();
+        assert!((65..90).contains(&age), "{}", age);
+        assert_eq!(projection.final_balance, 0.0);
+        assert!(projection.years.iter().all(|y| y.ending_balance >= 0.0));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let bad = [
+            RetirementParams {
+                retirement_age: 25,
+                ..params()
+            },
+            RetirementParams {
+                end_age: 200,
+                ..params()
+            },
+            RetirementParams {
+                return_rate: -100.0,
+                ..params()
+            },
+            RetirementParams {
+                annual_contribution: -1.0,
+                ..params()
+            },
+        ];
+        for params in bad {
+            assert!(project_retirement(&params).is_err(), "{:?}", params);
+        }
+        let now = project_retirement(&RetirementParams {
+            current_age: 65,
+            retirement_age: 65,
+            end_age: 65,
+            ..params()
+        })
+        .unwrap();
+        assert!(now.years.is_empty());
+        assert_eq!(now.final_balance, 10_000.0);
+        assert_eq!(now.balance_at_retirement, 10_000.0);
+    }
+}
@@ -0,0 +1,82 @@
+//! Decimal rounding as selected on a business calculator's F/CUT/5/4 switch.
+//!
+//! Rounding works on the shortest decimal representation of the value, so
+//! `1.005` rounds half-up to `1.01` even though its binary value is just
+//! below it.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// `F`: floating decimal, no rounding.
+    #[default]
+    Float,
+    /// `CUT`: truncate toward zero.
+    Cut,
+    /// `5/4`: round half away from zero.
+    HalfUp,
+}
+
+/// Rounds `value` to `places` decimals (at most 9) under `mode`.
+pub fn round_to(value: f64, places: u32, mode: RoundingMode) -> f64 {
+    if mode == RoundingMode::Float || !value.is_finite() || places > 9 {
+        return value;
+    }
+    let text = value.abs().to_string();
+    let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+    if frac.len() <= places as usize {
+        return value;
+    }
+    let kept = format!("{}{}", int, &frac[..places as usize]);
+    let Ok(mut digits) = kept.parse::<u128>() else {
+        return value;
+    };
+    if mode == RoundingMode::HalfUp && frac.as_bytes()[places as usize] >= b'5' {
+        digits += 1;
+    }
+    let magnitude = digits as f64 / 10f64.powi(places as i32);
+    if value < 0.0 && magnitude != 0.0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = round_to)]
+pub fn round_to_js(value: f64, places: u32, mode: RoundingMode) -> f64 {
+    round_to(value, places, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cut_and_half_up() {
+        assert_eq!(round_to(2.345, 2, RoundingMode::Cut), 2.34);
+        assert_eq!(round_to(2.345, 2, RoundingMode::HalfUp), 2.35);
+        assert_eq!(round_to(2.344, 2, RoundingMode::HalfUp), 2.34);
+        assert_eq!(round_to(-2.345, 2, RoundingMode::HalfUp), -2.35);
+        assert_eq!(round_to(-2.349, 2, RoundingMode::Cut), -2.34);
+        assert_eq!(round_to(9.99, 1, RoundingMode::HalfUp), 10.0);
+        assert_eq!(round_to(0.5, 0, RoundingMode::HalfUp), 1.0);
+    }
+
+    #[test]
+    fn test_uses_decimal_representation() {
+        assert_eq!(round_to(1.005, 2, RoundingMode::HalfUp), 1.01);
+        assert_eq!(round_to(0.1 + 0.2, 2, RoundingMode::Cut), 0.3);
+    }
+
+    #[test]
+    fn test_float_and_short_values_unchanged() {
+        assert_eq!(round_to(2.345, 2, RoundingMode::Float), 2.345);
+        assert_eq!(round_to(2.5, 2, RoundingMode::Cut), 2.5);
+        assert_eq!(round_to(1e300, 2, RoundingMode::HalfUp), 1e300);
+        assert!(round_to(f64::NAN, 2, RoundingMode::Cut).is_nan());
+        assert_eq!(round_to(-0.001, 2, RoundingMode::Cut).to_string(), "0");
+    }
+}
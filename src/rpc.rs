@@ -0,0 +1,716 @@
+//! JSON-RPC 2.0 endpoint over the calculator, for server-side wasm hosts.
+//!
+//! [`Calculator::rpc`] takes one request object or a batch array and returns
+//! the serialized response (an empty string when every request was a
+//! notification). Methods are the calculator's public operations, batches
+//! and tapes included, plus the standalone utilities; params may be
+//! positional (`[5]`) or named (`{"value": 5}`).
+//!
+//! Calculator failures use the application error code `-32000` and carry
+//! the `CalcError` code and message in `error.data`.
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::batch::BatchStep;
+use crate::expr::{evaluate_formula, evaluate_lenient};
+use crate::operation_codes::OPERATION_CODES;
+use crate::tape::{TapeFormat, TapeLayout};
+use crate::{
+    compound_interest, constants, factorial, percentage, CalcError, Calculator, Operation,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const CALC_ERROR: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<CalcError> for RpcError {
+    fn from(err: CalcError) -> Self {
+        let mut data = json!({ "code": err.code(), "message": err.to_string() });
+        if let CalcError::UnknownIdentifier { suggestions, .. } = &err {
+            data["suggestions"] = json!(suggestions);
+        }
+        if let CalcError::QuotaExceeded { quota, .. } = &err {
+            data["quota"] = json!(quota);
+        }
+        RpcError {
+            code: CALC_ERROR,
+            message: err.to_string(),
+            data: Some(data),
+        }
+    }
+}
+
+/// Positional or named parameters of one call.
+struct Params<'a> {
+    positional: Option<&'a Vec<Value>>,
+    named: Option<&'a Map<String, Value>>,
+}
+
+impl<'a> Params<'a> {
+    fn from_value(params: Option<&'a Value>) -> Result<Params<'a>, RpcError> {
+        match params {
+            None | Some(Value::Null) => Ok(Params {
+                positional: None,
+                named: None,
+            }),
+            Some(Value::Array(items)) => Ok(Params {
+                positional: Some(items),
+                named: None,
+            }),
+            Some(Value::Object(map)) => Ok(Params {
+                positional: None,
+                named: Some(map),
+            }),
+            Some(_) => Err(RpcError::new(
+                INVALID_REQUEST,
+                "params must be an array or object",
+            )),
+        }
+    }
+
+    fn get(&self, index: usize, name: &str) -> Option<&'a Value> {
+        match (self.positional, self.named) {
+            (Some(items), _) => items.get(index),
+            (_, Some(map)) => map.get(name),
+            _ => None,
+        }
+    }
+
+    fn number(&self, index: usize, name: &str) -> Result<f64, RpcError> {
+        self.get(index, name)
+            .and_then(Value::as_f64)
+            .ok_or_else(|| {
+                RpcError::new(INVALID_PARAMS, format!("missing numeric param '{}'", name))
+            })
+    }
+
+    fn count(&self, index: usize, name: &str) -> Result<usize, RpcError> {
+        let value = self
+            .get(index, name)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| {
+                RpcError::new(
+                    INVALID_PARAMS,
+                    format!("missing non-negative integer param '{}'", name),
+                )
+            })?;
+        usize::try_from(value).map_err(|_| too_large(name))
+    }
+
+    fn places(&self, index: usize, name: &str) -> Result<u32, RpcError> {
+        u32::try_from(self.count(index, name)?).map_err(|_| too_large(name))
+    }
+
+    fn boolean(&self, index: usize, name: &str) -> Result<bool, RpcError> {
+        self.get(index, name)
+            .and_then(Value::as_bool)
+            .ok_or_else(|| {
+                RpcError::new(INVALID_PARAMS, format!("missing boolean param '{}'", name))
+            })
+    }
+
+    fn text(&self, index: usize, name: &str) -> Result<&'a str, RpcError> {
+        self.get(index, name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                RpcError::new(INVALID_PARAMS, format!("missing string param '{}'", name))
+            })
+    }
+
+    fn array(&self, index: usize, name: &str) -> Result<&'a Value, RpcError> {
+        self.get(index, name)
+            .filter(|v| v.is_array())
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing array param '{}'", name)))
+    }
+
+    fn object(&self, index: usize, name: &str) -> Result<&'a Value, RpcError> {
+        self.get(index, name)
+            .filter(|v| v.is_object())
+            .ok_or_else(|| {
+                RpcError::new(INVALID_PARAMS, format!("missing object param '{}'", name))
+            })
+    }
+}
+
+// Deserializes a structured param, such as batch steps or tape options.
+fn decode<T: DeserializeOwned>(value: &Value, name: &str) -> Result<T, RpcError> {
+    serde_json::from_value(value.clone())
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid {}: {}", name, e)))
+}
+
+fn tape_format(name: &str) -> Result<TapeFormat, RpcError> {
+    match name {
+        "text" => Ok(TapeFormat::Text),
+        "csv" => Ok(TapeFormat::Csv),
+        _ => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("unknown tape format '{}', expected 'text' or 'csv'", name),
+        )),
+    }
+}
+
+fn too_large(name: &str) -> RpcError {
+    RpcError::new(INVALID_PARAMS, format!("param '{}' is too large", name))
+}
+
+fn number(v: f64) -> Value {
+    // JSON has no NaN or Infinity; serde_json maps them to null.
+    json!(v)
+}
+
+impl Calculator {
+    // Arithmetic goes through `apply`, so strict math mode's refusals
+    // become errors rather than an unchanged value.
+    fn operation(
+        &mut self,
+        operation: Operation,
+        params: &Params,
+        name: &str,
+    ) -> Result<Value, RpcError> {
+        let operand = params.number(0, name)?;
+        Ok(number(self.apply(&operation, Some(operand))?))
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Params) -> Result<Value, RpcError> {
+        let result = match method {
+            "add" => self.operation(Operation::Add, params, "value")?,
+            "subtract" => self.operation(Operation::Subtract, params, "value")?,
+            "multiply" => self.operation(Operation::Multiply, params, "value")?,
+            "divide" => number(self.divide(params.number(0, "value")?)?),
+            "sqrt" => number(self.sqrt()?),
+            "power" => self.operation(Operation::Power, params, "exponent")?,
+            "nth_root" => number(self.nth_root(params.number(0, "n")?)?),
+            "modulo" => number(self.modulo(params.number(0, "value")?)?),
+            "reciprocal" => number(self.reciprocal()?),
+            "abs" => number(self.apply(&Operation::Abs, None)?),
+            "negate" => number(self.apply(&Operation::Negate, None)?),
+            "set_strict_math" => {
+                self.set_strict_math(params.boolean(0, "strict")?);
+                Value::Null
+            }
+            "get_settings" => serde_json::to_value(self.settings()).unwrap_or(Value::Null),
+            "apply_settings" => {
+                let settings = self.update_settings(params.object(0, "settings")?)?;
+                serde_json::to_value(settings).unwrap_or(Value::Null)
+            }
+            "round_to" => number(self.round_to(params.places(0, "decimals")?)?),
+            "percent_of" => self.operation(Operation::PercentOf, params, "percent")?,
+            "add_percent" => self.operation(Operation::AddPercent, params, "percent")?,
+            "subtract_percent" => self.operation(Operation::SubtractPercent, params, "percent")?,
+            "set_value" => {
+                self.set_value(params.number(0, "value")?);
+                Value::Null
+            }
+            "set_value_str" => number(self.set_value_str(params.text(0, "text")?)?),
+            "set_input_locale" => {
+                self.set_input_locale(params.text(0, "locale")?);
+                Value::Null
+            }
+            "get_value" => number(self.get_value()),
+            "clear" => {
+                self.clear();
+                Value::Null
+            }
+            "memory_store" => {
+                self.memory_store();
+                Value::Null
+            }
+            "memory_recall" => number(self.memory_recall()),
+            "memory_clear" => {
+                self.memory_clear();
+                Value::Null
+            }
+            "memory_add" => {
+                self.memory_add();
+                Value::Null
+            }
+            "get_memory" => number(self.get_memory()),
+            "get_history" => serde_json::to_value(&self.history).unwrap_or(Value::Null),
+            "get_history_range" => {
+                let page = self.history_range(params.count(0, "start")?, params.count(1, "count")?);
+                serde_json::to_value(page).unwrap_or(Value::Null)
+            }
+            "get_history_since" => {
+                serde_json::to_value(self.history_since(params.count(0, "index")?))
+                    .unwrap_or(Value::Null)
+            }
+            "history_version" => json!(self.history_version()),
+            "history_next_index" => json!(self.history_next_index()),
+            "history_count" => json!(self.history_count()),
+            "history_sum" => number(self.history_sum()),
+            "history_average" => self.history_average().map_or(Value::Null, number),
+            "tape_total" => number(self.tape_total()),
+            "apply_batch" => {
+                let steps: Vec<BatchStep> = decode(params.array(0, "steps")?, "steps")?;
+                self.apply_batch(&steps)?;
+                number(self.current_value)
+            }
+            "apply_batch_trace" => {
+                let steps: Vec<BatchStep> = decode(params.array(0, "steps")?, "steps")?;
+                let values = self.apply_batch(&steps)?;
+                Value::Array(values.into_iter().map(number).collect())
+            }
+            "import_tape" => {
+                let format = tape_format(params.text(1, "format")?)?;
+                json!(self.import_tape(params.text(0, "text")?, format)?)
+            }
+            "export_history_csv" => json!(self.export_history_csv()),
+            "export_history_text" => json!(self.export_history_text()),
+            "format_tape" => {
+                let layout = match params.get(1, "options") {
+                    None | Some(Value::Null) => TapeLayout::default(),
+                    Some(options) => decode(options, "options")?,
+                };
+                json!(self.format_tape(params.count(0, "width")?, &layout)?)
+            }
+            "apply_from" => {
+                let name = params.text(0, "operation")?;
+                let operation = crate::tape::parse_operation(name).ok_or_else(|| {
+                    RpcError::new(INVALID_PARAMS, format!("unknown operation '{}'", name))
+                })?;
+                let source = serde_json::from_value(params.object(1, "source")?.clone())
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid source: {}", e)))?;
+                number(self.apply_from(&operation, &source)?)
+            }
+            "get_history_filtered" => {
+                let name = params.text(0, "operation")?;
+                let operation = crate::tape::parse_operation(name).ok_or_else(|| {
+                    RpcError::new(INVALID_PARAMS, format!("unknown operation '{}'", name))
+                })?;
+                serde_json::to_value(self.history_filtered(&operation)).unwrap_or(Value::Null)
+            }
+            "clear_history" => json!(self.clear_history()),
+            "confirm_clear_history" => json!(self.confirm_clear_history()),
+            "set_history_limit" => {
+                self.set_history_limit(params.count(0, "limit")?);
+                Value::Null
+            }
+            "get_history_limit" => json!(self.get_history_limit()),
+            "set_history_dedupe" => {
+                self.set_history_dedupe(
+                    params.boolean(0, "collapse_repeats")?,
+                    params.boolean(1, "skip_no_ops")?,
+                );
+                Value::Null
+            }
+            "dedupe_history" => json!(self.dedupe_history()),
+            "get_error_log" => serde_json::to_value(self.error_log()).unwrap_or(Value::Null),
+            "error_log_count" => json!(self.error_log_count()),
+            "clear_error_log" => json!(self.clear_error_log()),
+            "operation_codes" => serde_json::to_value(OPERATION_CODES).unwrap_or(Value::Null),
+            "canonical_state_string" => json!(self.canonical_state_string()),
+            "insert_constant" => number(self.insert_constant(params.text(0, "name")?)?),
+            "define_function" => {
+                self.define_function(params.text(0, "name")?, params.text(1, "body")?)?;
+                Value::Null
+            }
+            "save_template" => {
+                let placeholders =
+                    self.save_template(params.text(0, "name")?, params.text(1, "body")?)?;
+                serde_json::to_value(placeholders).unwrap_or(Value::Null)
+            }
+            "run_template" => {
+                let values = serde_json::from_value(params.object(1, "values")?.clone())
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid values: {}", e)))?;
+                number(self.run_template(params.text(0, "name")?, &values)?)
+            }
+            "set_variable" => {
+                self.set_variable(params.text(0, "name")?, params.number(1, "value")?)?;
+                Value::Null
+            }
+            "define_live" => {
+                self.define_live(params.text(0, "name")?, params.text(1, "expression")?)?;
+                Value::Null
+            }
+            "get_variable" => number(self.get_variable(params.text(0, "name")?)?),
+            "list_variables" => serde_json::to_value(self.list_variables()).unwrap_or(Value::Null),
+            "remove_variable" => json!(self.remove_variable(params.text(0, "name")?)),
+            "call_function" => number(self.call_function(params.text(0, "name")?)?),
+            "constant" => number(constants::constant(params.text(0, "name")?)?),
+            "percentage" => number(percentage(
+                params.number(0, "value")?,
+                params.number(1, "percent")?,
+            )),
+            "compound_interest" => number(compound_interest(
+                params.number(0, "principal")?,
+                params.number(1, "rate")?,
+                params.number(2, "years")?,
+                params.number(3, "compounds_per_year")?,
+            )),
+            "factorial" => {
+                let n = params.number(0, "n")?;
+                if !n.is_finite() || n < 0.0 || n.fract() != 0.0 {
+                    return Err(CalcError::InvalidInput.into());
+                }
+                json!(factorial(n as u32)?)
+            }
+            "evaluate" => number(self.evaluate(params.text(0, "expr")?)?),
+            "evaluate_lenient" => serde_json::to_value(evaluate_lenient(params.text(0, "expr")?)?)
+                .unwrap_or(Value::Null),
+            "evaluate_formula" => {
+                let cells = decode(params.object(1, "cells")?, "cells")?;
+                number(evaluate_formula(params.text(0, "formula")?, &cells)?)
+            }
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("Method not found: {}", method),
+                ))
+            }
+        };
+        Ok(result)
+    }
+
+    // Handles one request object; `None` means it was a notification.
+    fn handle_request(&mut self, request: &Value) -> Option<Value> {
+        let Value::Object(obj) = request else {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "Invalid Request"),
+            ));
+        };
+        let id = obj.get("id").cloned();
+        let version_ok = obj.get("jsonrpc").and_then(Value::as_str) == Some("2.0");
+        let method = obj.get("method").and_then(Value::as_str);
+        let outcome = match (version_ok, method) {
+            (true, Some(method)) => Params::from_value(obj.get("params"))
+                .and_then(|params| self.dispatch(method, &params)),
+            _ => Err(RpcError::new(INVALID_REQUEST, "Invalid Request")),
+        };
+        // Malformed requests are answered even without an id, per the spec.
+        let id = match id {
+            Some(id) => id,
+            None if matches!(&outcome, Err(e) if e.code == INVALID_REQUEST) => Value::Null,
+            None => return None,
+        };
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => error_response(id, err),
+        })
+    }
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    let mut error = json!({ "code": err.code, "message": err.message });
+    if let Some(data) = err.data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+/// The response to a request that has no calculator to run on, echoing
+/// its `id` when it has one; see `CalculatorManager::rpc`.
+pub(crate) fn unroutable_response(request_json: &str, err: CalcError) -> String {
+    let id = serde_json::from_str::<Value>(request_json)
+        .ok()
+        .and_then(|request| request.get("id").cloned())
+        .unwrap_or(Value::Null);
+    error_response(id, err.into()).to_string()
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Executes a JSON-RPC 2.0 request or batch and returns the response
+    /// JSON. Requests without an `id` are notifications and produce no
+    /// response; a batch of only notifications returns an empty string.
+    pub fn rpc(&mut self, request_json: &str) -> String {
+        let request: Value = match serde_json::from_str(request_json) {
+            Ok(v) => v,
+            Err(e) => {
+                return error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+                )
+                .to_string();
+            }
+        };
+        match request {
+            Value::Array(items) if items.is_empty() => error_response(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "Invalid Request"),
+            )
+            .to_string(),
+            Value::Array(items) => {
+                let responses: Vec<Value> = items
+                    .iter()
+                    .filter_map(|item| self.handle_request(item))
+                    .collect();
+                if responses.is_empty() {
+                    String::new()
+                } else {
+                    Value::Array(responses).to_string()
+                }
+            }
+            single => self
+                .handle_request(&single)
+                .map(|response| response.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(calc: &mut Calculator, request: &str) -> Value {
+        serde_json::from_str(&calc.rpc(request)).unwrap()
+    }
+
+    #[test]
+    fn test_rpc_positional_and_named_params() {
+        let mut calc = Calculator::new();
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"add","params":[5],"id":1}"#,
+        );
+        assert_eq!(r, json!({"jsonrpc":"2.0","result":5.0,"id":1}));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"multiply","params":{"value":3},"id":"b"}"#,
+        );
+        assert_eq!(r["result"], json!(15.0));
+        assert_eq!(r["id"], json!("b"));
+        assert_eq!(calc.get_value(), 15.0);
+    }
+
+    #[test]
+    fn test_rpc_calc_error_carries_code() {
+        let mut calc = Calculator::new();
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"divide","params":[0],"id":7}"#,
+        );
+        assert_eq!(r["error"]["code"], json!(CALC_ERROR));
+        assert_eq!(r["error"]["data"]["code"], json!("DIVISION_BY_ZERO"));
+        assert_eq!(r["id"], json!(7));
+    }
+
+    #[test]
+    fn test_rpc_protocol_errors() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            call(&mut calc, "{not json")["error"]["code"],
+            json!(PARSE_ERROR)
+        );
+        assert_eq!(
+            call(&mut calc, "[]")["error"]["code"],
+            json!(INVALID_REQUEST)
+        );
+        assert_eq!(
+            call(&mut calc, r#"{"method":"add","params":[1],"id":1}"#)["error"]["code"],
+            json!(INVALID_REQUEST)
+        );
+        assert_eq!(
+            call(&mut calc, r#"{"jsonrpc":"2.0","method":"nope","id":1}"#)["error"]["code"],
+            json!(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            call(
+                &mut calc,
+                r#"{"jsonrpc":"2.0","method":"add","params":["x"],"id":1}"#
+            )["error"]["code"],
+            json!(INVALID_PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_rpc_round_to_rejects_huge_places() {
+        let mut calc = Calculator::new();
+        calc.set_value(1.2345);
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"round_to","params":[4294967298],"id":1}"#,
+        );
+        assert_eq!(r["error"]["code"], json!(INVALID_PARAMS));
+        assert_eq!(calc.get_value(), 1.2345);
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"round_to","params":[2],"id":2}"#,
+        );
+        assert_eq!(r["result"], json!(1.23));
+    }
+
+    #[test]
+    fn test_rpc_batch_and_notifications() {
+        let mut calc = Calculator::new();
+        let batch = r#"[
+            {"jsonrpc":"2.0","method":"set_value","params":[10]},
+            {"jsonrpc":"2.0","method":"add","params":[5],"id":1},
+            {"jsonrpc":"2.0","method":"memory_store"},
+            {"jsonrpc":"2.0","method":"get_memory","id":2},
+            {"jsonrpc":"2.0","method":"evaluate","params":{"expr":"2^10"},"id":3},
+            1
+        ]"#;
+        let r = call(&mut calc, batch);
+        let responses = r.as_array().unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["result"], json!(15.0));
+        assert_eq!(responses[1]["result"], json!(15.0));
+        assert_eq!(responses[2]["result"], json!(1024.0));
+        assert_eq!(responses[3]["error"]["code"], json!(INVALID_REQUEST));
+    }
+
+    #[test]
+    fn test_rpc_evaluate_uses_the_calculator() {
+        let mut calc = Calculator::new();
+        calc.set_value(4.0);
+        calc.set_variable("rate", 0.5).unwrap();
+        calc.set_input_locale("de-DE");
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"evaluate","params":["ans * rate + 0,5"],"id":1}"#,
+        );
+        assert_eq!(r["result"], json!(2.5));
+        assert_eq!(calc.get_value(), 2.5);
+
+        calc.enable_education_mode(Some(&["add"]), None).unwrap();
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"evaluate","params":["2^10"],"id":2}"#,
+        );
+        assert_eq!(r["error"]["data"]["code"], json!("NOT_ALLOWED"));
+        assert_eq!(calc.get_value(), 2.5);
+    }
+
+    #[test]
+    fn test_rpc_all_notifications_returns_empty() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            calc.rpc(r#"{"jsonrpc":"2.0","method":"add","params":[1]}"#),
+            ""
+        );
+        assert_eq!(
+            calc.rpc(r#"[{"jsonrpc":"2.0","method":"add","params":[1]}]"#),
+            ""
+        );
+        assert_eq!(calc.get_value(), 2.0);
+    }
+
+    #[test]
+    fn test_rpc_history_and_utilities() {
+        let mut calc = Calculator::new();
+        calc.add(2.0);
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"get_history","id":1}"#,
+        );
+        assert_eq!(r["result"][0]["operation"], json!("Add"));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"factorial","params":[5],"id":2}"#,
+        );
+        assert_eq!(r["result"], json!(120));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"percentage","params":[200,10],"id":3}"#,
+        );
+        assert_eq!(r["result"], json!(20.0));
+    }
+
+    #[test]
+    fn test_rpc_batches_tapes_and_formulas() {
+        let mut calc = Calculator::new();
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"apply_batch_trace",
+                "params":[[{"op":"set_value","operand":10},{"op":"+","operand":5}]],"id":1}"#,
+        );
+        assert_eq!(r["result"], json!([10.0, 15.0]));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"apply_batch",
+                "params":{"steps":[{"op":"*","operand":2},{"op":"/","operand":0}]},"id":2}"#,
+        );
+        assert_eq!(r["error"]["data"]["code"], json!("DIVISION_BY_ZERO"));
+        assert_eq!(calc.get_value(), 15.0);
+
+        let csv = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"export_history_csv","id":3}"#,
+        );
+        let mut copy = Calculator::new();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "method": "import_tape",
+            "params": [csv["result"], "csv"],
+            "id": 4,
+        });
+        let r = call(&mut copy, &request.to_string());
+        assert_eq!(r["result"], json!(3));
+        assert_eq!(copy.get_value(), 15.0);
+        let r = call(
+            &mut copy,
+            r#"{"jsonrpc":"2.0","method":"import_tape","params":["","tsv"],"id":5}"#,
+        );
+        assert_eq!(r["error"]["code"], json!(INVALID_PARAMS));
+        let r = call(
+            &mut copy,
+            r#"{"jsonrpc":"2.0","method":"export_history_text","id":6}"#,
+        );
+        assert_eq!(r["result"], json!(copy.export_history_text()));
+        let r = call(
+            &mut copy,
+            r#"{"jsonrpc":"2.0","method":"format_tape","params":[12,{"total":false}],"id":7}"#,
+        );
+        let layout = TapeLayout {
+            total: false,
+            ..TapeLayout::default()
+        };
+        assert_eq!(r["result"], json!(copy.format_tape(12, &layout).unwrap()));
+
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"evaluate_lenient","params":["(1 + 2"],"id":8}"#,
+        );
+        assert_eq!(r["result"]["value"], json!(3.0));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"evaluate_formula",
+                "params":["=SUM(A1:A2)",{"A1":1,"A2":2}],"id":9}"#,
+        );
+        assert_eq!(r["result"], json!(3.0));
+    }
+
+    #[test]
+    fn test_rpc_settings() {
+        let mut calc = Calculator::new();
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"apply_settings","params":[{"decimals":2}],"id":1}"#,
+        );
+        assert_eq!(r["result"]["decimals"], json!(2));
+        assert_eq!(r["result"]["angle_unit"], json!("radians"));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"apply_settings","params":{"settings":{"typo":1}},"id":2}"#,
+        );
+        assert_eq!(r["error"]["data"]["code"], json!("INVALID_ARGUMENT"));
+        let r = call(
+            &mut calc,
+            r#"{"jsonrpc":"2.0","method":"get_settings","id":3}"#,
+        );
+        assert_eq!(r["result"]["decimals"], json!(2));
+    }
+}
@@ -0,0 +1,197 @@
+//! All of a calculator's modes in one place.
+//!
+//! [`CalculatorSettings`] gathers the angle unit, the default display
+//! format, strict math, the history limit and incognito mode, so a UI can read them with
+//! one `get_settings()`, restore them with one `apply_settings(saved)`, and
+//! persist them as a single JSON object. The individual setters
+//! (`set_strict_math`, `set_history_limit`) still work and show up here.
+//!
+//! From JavaScript, `apply_settings` takes a partial object: fields left
+//! out keep their current values, and unknown fields are an error so a
+//! typo does not pass silently.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::AngleUnit;
+use crate::format::{FormatOptions, Notation, MAX_DECIMALS};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// What `get_settings` returns and `apply_settings` reads.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalculatorSettings {
+    /// `"radians"` (the default), `"degrees"` or `"gradians"`: the unit of
+    /// `sin`/`cos`/`tan` arguments and `asin`/`acos`/`atan` results in
+    /// `evaluate` and user functions.
+    pub angle_unit: AngleUnit,
+    /// Decimal places `format_value()` shows without options, 0-20; `None`
+    /// (`null`) shows as many as the value needs.
+    pub decimals: Option<usize>,
+    /// Notation `format_value()` uses without options.
+    pub notation: Notation,
+    pub strict_math: bool,
+    /// 0 means unbounded, as in `set_history_limit`.
+    pub history_limit: usize,
+    /// See `set_incognito`; turning it on scrubs the history.
+    pub incognito: bool,
+}
+
+impl Calculator {
+    pub fn settings(&self) -> CalculatorSettings {
+        CalculatorSettings {
+            angle_unit: self.angle_unit,
+            decimals: self.display_decimals,
+            notation: self.notation,
+            strict_math: self.strict_math,
+            history_limit: self.get_history_limit(),
+            incognito: self.incognito,
+        }
+    }
+
+    /// Replaces every setting at once. Nothing changes if `settings` is
+    /// invalid.
+    pub fn apply_settings(&mut self, settings: &CalculatorSettings) -> Result<(), CalcError> {
+        if let Some(decimals) = settings.decimals.filter(|d| *d > MAX_DECIMALS) {
+            let err = CalcError::InvalidArgument(format!(
+                "Decimals must be 0-{}, got {}",
+                MAX_DECIMALS, decimals
+            ));
+            return Err(self.fail(err, Some(decimals as f64)));
+        }
+        self.angle_unit = settings.angle_unit;
+        self.display_decimals = settings.decimals;
+        self.notation = settings.notation;
+        self.strict_math = settings.strict_math;
+        self.set_history_limit(settings.history_limit);
+        self.set_incognito(settings.incognito);
+        Ok(())
+    }
+
+    /// Applies the fields of the JSON object `changes` over the current
+    /// settings and returns the result.
+    pub fn update_settings(&mut self, changes: &Value) -> Result<CalculatorSettings, CalcError> {
+        let Value::Object(changes) = changes else {
+            let err = CalcError::InvalidArgument("Settings must be an object".to_string());
+            return Err(self.fail(err, None));
+        };
+        let mut merged = serde_json::to_value(self.settings()).unwrap_or(Value::Null);
+        if let Value::Object(fields) = &mut merged {
+            fields.extend(changes.clone());
+        }
+        let settings: CalculatorSettings = serde_json::from_value(merged).map_err(|e| {
+            self.fail(
+                CalcError::InvalidArgument(format!("Invalid settings: {}", e)),
+                None,
+            )
+        })?;
+        self.apply_settings(&settings)?;
+        Ok(settings)
+    }
+
+    /// The format `format_value()` uses without options: the settings'
+    /// decimals and notation.
+    pub fn display_options(&self) -> FormatOptions {
+        FormatOptions {
+            decimals: self.display_decimals,
+            notation: self.notation,
+            ..FormatOptions::default()
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `{ angle_unit, decimals, notation, strict_math, history_limit,
+    /// incognito }`; see
+    /// [`CalculatorSettings`].
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_settings)]
+    pub fn get_settings_js(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.settings()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Changes the settings named in `changes` (a partial `get_settings()`
+    /// object) and returns the full settings afterwards.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = apply_settings)]
+    pub fn apply_settings_js(&mut self, changes: JsValue) -> Result<JsValue, CalcErrorJs> {
+        let changes: Value = serde_wasm_bindgen::from_value(changes).map_err(|e| {
+            let err = CalcError::InvalidArgument(format!("Invalid settings: {}", e));
+            CalcErrorJs::from(self.fail(err, None))
+        })?;
+        let settings = self.update_settings(&changes)?;
+        Ok(serde_wasm_bindgen::to_value(&settings).unwrap_or(JsValue::NULL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_update_settings() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.settings(), CalculatorSettings::default());
+        let settings = calc
+            .update_settings(&json!({ "angle_unit": "degrees", "decimals": 2 }))
+            .unwrap();
+        assert_eq!(settings.angle_unit, AngleUnit::Degrees);
+        assert_eq!(settings.decimals, Some(2));
+        calc.set_history_limit(5);
+        calc.update_settings(&json!({ "notation": "scientific" }))
+            .unwrap();
+        assert_eq!(calc.settings().history_limit, 5);
+        assert_eq!(calc.settings().decimals, Some(2));
+
+        let before = calc.settings();
+        assert!(calc.update_settings(&json!({ "decimals": 21 })).is_err());
+        assert!(calc
+            .update_settings(&json!({ "angle": "degrees" }))
+            .is_err());
+        assert!(calc
+            .update_settings(&json!({ "notation": "roman" }))
+            .is_err());
+        assert!(calc.update_settings(&json!([1, 2])).is_err());
+        assert_eq!(calc.settings(), before);
+        assert_eq!(calc.session_summary().error_count, 4);
+    }
+
+    #[test]
+    fn test_settings_take_effect() {
+        let mut calc = Calculator::new();
+        calc.apply_settings(&CalculatorSettings {
+            angle_unit: AngleUnit::Degrees,
+            decimals: Some(3),
+            notation: Notation::Standard,
+            strict_math: true,
+            history_limit: 2,
+            incognito: false,
+        })
+        .unwrap();
+        assert_eq!(calc.evaluate("sin(30) + cos(180)"), Ok(-0.5));
+        assert_eq!(calc.evaluate("asin(1)"), Ok(90.0));
+        assert!(calc.evaluate("tan(90)").is_err());
+        calc.define_function("sin_of", "sin(x)").unwrap();
+        calc.set_value(270.0);
+        assert_eq!(calc.call_function("sin_of"), Ok(-1.0));
+        assert_eq!(
+            calc.format_value(&calc.display_options()).unwrap(),
+            "-1.000"
+        );
+        assert!(calc.strict_math());
+        assert_eq!(calc.history_count(), 2);
+
+        calc.update_settings(&json!({ "angle_unit": "gradians" }))
+            .unwrap();
+        assert_eq!(calc.evaluate("cos(100)"), Ok(0.0));
+        calc.update_settings(&json!({ "angle_unit": "radians" }))
+            .unwrap();
+        assert!((calc.evaluate("sin(pi / 6)").unwrap() - 0.5).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,176 @@
+//! A calculator shared between threads.
+//!
+//! [`Calculator`] is `Send` but not `Sync`: one instance belongs to one
+//! thread at a time, which is all a browser page needs. A server handling
+//! requests on a thread pool, or wasm threads sharing one session, need a
+//! handle every thread can hold. [`SharedCalculator`] is that handle: an
+//! `Arc<Mutex<Calculator>>` with a plain Rust API (no `JsValue`), where
+//! each call locks, runs to completion and unlocks, so calls from different
+//! threads never interleave within an operation.
+//!
+//! Listeners (see [`crate::events`]) live in a per-thread registry, so a
+//! listener registered on one thread is not called for operations made
+//! through the handle on another. Calculator ids are unique across
+//! threads, so a calculator moved between threads never picks up another
+//! calculator's listeners.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::batch::BatchStep;
+use crate::read_snapshot::ReadSnapshot;
+use crate::{CalcError, CalculationHistory, Calculator, Operation};
+
+/// A cloneable, `Send + Sync` handle to one calculator. Clones share the
+/// same state.
+#[derive(Clone, Default)]
+pub struct SharedCalculator {
+    inner: Arc<Mutex<Calculator>>,
+}
+
+impl SharedCalculator {
+    pub fn new() -> SharedCalculator {
+        SharedCalculator::default()
+    }
+
+    // Calculator methods validate before they write, so a panic (in a
+    // `with` closure, say) leaves a usable state; recover the lock rather
+    // than failing every later call.
+    fn lock(&self) -> MutexGuard<'_, Calculator> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs `f` with exclusive access, for anything the methods below do
+    /// not cover or several calls that must not be interleaved with other
+    /// threads' calls.
+    pub fn with<T>(&self, f: impl FnOnce(&mut Calculator) -> T) -> T {
+        f(&mut self.lock())
+    }
+
+    /// See [`Calculator::apply`].
+    pub fn apply(&self, operation: &Operation, operand: Option<f64>) -> Result<f64, CalcError> {
+        self.lock().apply(operation, operand)
+    }
+
+    /// See [`Calculator::apply_batch`]; no other thread's call lands between
+    /// the steps.
+    pub fn apply_batch(&self, steps: &[BatchStep]) -> Result<Vec<f64>, CalcError> {
+        self.lock().apply_batch(steps)
+    }
+
+    /// See [`Calculator::read_snapshot`]; the lock is held only while the
+    /// snapshot is taken.
+    pub fn read_snapshot(&self) -> ReadSnapshot {
+        self.lock().read_snapshot()
+    }
+
+    /// See [`Calculator::evaluate`].
+    pub fn evaluate(&self, src: &str) -> Result<f64, CalcError> {
+        self.lock().evaluate(src)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.lock().get_value()
+    }
+
+    pub fn memory(&self) -> f64 {
+        self.lock().get_memory()
+    }
+
+    /// A copy of the retained history, oldest first.
+    pub fn history(&self) -> Vec<CalculationHistory> {
+        self.lock().history_range(0, usize::MAX)
+    }
+
+    /// An independent copy of the current state.
+    pub fn snapshot(&self) -> Calculator {
+        self.lock().clone()
+    }
+}
+
+impl From<Calculator> for SharedCalculator {
+    fn from(calc: Calculator) -> SharedCalculator {
+        SharedCalculator {
+            inner: Arc::new(Mutex::new(calc)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_shared_across_threads() {
+        assert_send_sync::<SharedCalculator>();
+        let shared = SharedCalculator::new();
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let calc = shared.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        calc.apply(&Operation::Add, Some(1.0)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(shared.value(), 400.0);
+        assert_eq!(shared.history().len(), 400);
+        assert_eq!(shared.with(|calc| calc.check_invariants()), Ok(()));
+    }
+
+    #[test]
+    fn test_listeners_on_two_threads_stay_apart() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let main_hits = Rc::new(Cell::new(0));
+        let shared = SharedCalculator::new();
+        let sink = main_hits.clone();
+        shared.with(|calc| calc.on_change(move |_, _| sink.set(sink.get() + 1)));
+        // Registered on the worker, then used from here.
+        let (moved, worker_hits) = std::thread::spawn(|| {
+            let moved = SharedCalculator::new();
+            let hits = Rc::new(Cell::new(0));
+            let sink = hits.clone();
+            moved.with(|calc| calc.on_change(move |_, _| sink.set(sink.get() + 1)));
+            moved.apply(&Operation::Add, Some(1.0)).unwrap();
+            (moved, hits.get())
+        })
+        .join()
+        .unwrap();
+        assert_eq!(worker_hits, 1);
+
+        moved.apply(&Operation::Add, Some(1.0)).unwrap();
+        assert_eq!(main_hits.get(), 0);
+        shared.apply(&Operation::Add, Some(1.0)).unwrap();
+        assert_eq!(main_hits.get(), 1);
+        assert_ne!(
+            moved.with(|calc| calc.registry_id()),
+            shared.with(|calc| calc.registry_id())
+        );
+    }
+
+    #[test]
+    fn test_shared_methods() {
+        let mut calc = Calculator::new();
+        calc.set_value(6.0);
+        let shared = SharedCalculator::from(calc);
+        assert_eq!(shared.evaluate("ans * 7"), Ok(42.0));
+        assert_eq!(
+            shared.apply(&Operation::Divide, Some(0.0)),
+            Err(CalcError::DivisionByZero)
+        );
+        shared.with(|calc| calc.memory_store());
+        assert_eq!(shared.memory(), 42.0);
+        let copy = shared.snapshot();
+        shared.apply(&Operation::Clear, None).unwrap();
+        assert_eq!((copy.get_value(), shared.value()), (42.0, 0.0));
+    }
+}
@@ -0,0 +1,585 @@
+//! Significant-figure arithmetic, for chemistry and physics coursework.
+//!
+//! A [`SigFig`] is a value with the significant figures it was measured
+//! to, counted from how it is written: `12.30` has four, `0.0045` two,
+//! `1200` two (trailing zeros without a decimal point are not
+//! significant), `1200.` four and `1.20e3` three. Results follow the usual
+//! textbook rules:
+//!
+//! - `a × b`, `a ÷ b`, `aⁿ`: as many figures as the operand with fewest.
+//! - `a + b`, `a - b`: rounded to the decimal place of the least precise
+//!   operand, so `12.1 + 0.345` is `12.4`.
+//!
+//! Exact values (counts, defined constants, exponents) never limit a
+//! result. Results keep the full-precision value, available as `value`;
+//! only `rounded` and `to_string` apply the rules, so rounding errors do
+//! not build up over a chain of operations. Rounding is half away from
+//! zero on the first 15 significant digits, so floating-point noise such
+//! as `100.0 - 99.95 = 0.04999999999999716` still rounds as `0.05`; for the
+//! same reason a value has at most 15 figures.
+//!
+//! [`evaluate_sig_figs`] evaluates a whole expression this way, reading
+//! each number as written.
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{tokenize, Dialect, Spanned, Token, CONSTANTS};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// Digits kept before rounding, which absorbs floating-point noise.
+const MAX_FIGURES: u32 = 15;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SigFig {
+    value: f64,
+    /// `None` for an exact value.
+    figures: Option<u32>,
+    /// The power of ten of the last significant digit.
+    place: i32,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+// The power of ten of the first digit of `x`, which is not zero.
+fn decade(x: f64) -> i32 {
+    let text = format!("{:e}", x);
+    text[text.find('e').expect("exponent") + 1..]
+        .parse()
+        .expect("integer exponent")
+}
+
+// `x` rounded half away from zero to a multiple of 10^place, on its first
+// 15 significant digits.
+fn round_at(x: f64, place: i32) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        return x;
+    }
+    let text = format!("{:.*e}", MAX_FIGURES as usize - 1, x.abs());
+    let (mantissa, exponent) = text.split_once('e').expect("exponent");
+    let exponent: i32 = exponent.parse().expect("integer exponent");
+    let digits: Vec<u8> = mantissa.bytes().filter(u8::is_ascii_digit).collect();
+    let keep = exponent - place + 1;
+    let magnitude: f64 = if keep < 0 {
+        0.0
+    } else {
+        let keep = keep as usize;
+        let mut kept: u128 = 0;
+        for i in 0..keep.min(39) {
+            kept = kept * 10 + digits.get(i).map_or(0, |d| (d - b'0') as u128);
+        }
+        if digits.get(keep).is_some_and(|d| *d >= b'5') {
+            kept += 1;
+        }
+        format!("{}e{}", kept, place).parse().expect("decimal")
+    };
+    magnitude.copysign(x)
+}
+
+impl SigFig {
+    /// Reads a number as written, counting its significant figures.
+    pub fn parse(text: &str) -> Result<SigFig, CalcError> {
+        let bad = || invalid(format!("Invalid number '{}'", text));
+        let trimmed = text.trim();
+        let unsigned = trimmed.strip_prefix(['-', '+']).unwrap_or(trimmed);
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(i) => (
+                &unsigned[..i],
+                unsigned[i + 1..].parse::<i32>().map_err(|_| bad())?,
+            ),
+            None => (unsigned, 0),
+        };
+        let (int, frac) = match mantissa.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (mantissa, None),
+        };
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if !all_digits(int)
+            || !frac.is_none_or(all_digits)
+            || int.len() + frac.map_or(0, str::len) == 0
+        {
+            return Err(bad());
+        }
+        let value: f64 = trimmed.parse().map_err(|_| bad())?;
+        if !value.is_finite() {
+            return Err(bad());
+        }
+        let digits = format!("{}{}", int, frac.unwrap_or(""));
+        let significant = digits.trim_start_matches('0');
+        let (figures, place) = match frac {
+            Some(frac) => (significant.len(), exponent - frac.len() as i32),
+            None => {
+                let zeros = significant.len() - significant.trim_end_matches('0').len();
+                (significant.len() - zeros, exponent + zeros as i32)
+            }
+        };
+        Ok(SigFig {
+            value,
+            figures: Some(figures.max(1) as u32),
+            place,
+        })
+    }
+
+    /// `value` measured to `figures` significant figures, 1 to 15.
+    pub fn with_figures(value: f64, figures: u32) -> Result<SigFig, CalcError> {
+        if !(1..=MAX_FIGURES).contains(&figures) {
+            return Err(invalid(format!(
+                "Significant figures must be 1-{}, got {}",
+                MAX_FIGURES, figures
+            )));
+        }
+        if !value.is_finite() {
+            return Err(invalid(format!("Value must be finite, got {}", value)));
+        }
+        Ok(SigFig::to_figures(value, figures))
+    }
+
+    fn to_figures(value: f64, figures: u32) -> SigFig {
+        let figures = figures.min(MAX_FIGURES);
+        let place = if value == 0.0 {
+            1 - figures as i32
+        } else {
+            // 9.96 to two figures is 10, whose last figure is the units.
+            let first = decade(value) - (figures as i32 - 1);
+            decade(round_at(value, first).max(value.abs())) - (figures as i32 - 1)
+        };
+        SigFig {
+            value,
+            figures: Some(figures),
+            place,
+        }
+    }
+
+    fn at_place(value: f64, place: i32) -> SigFig {
+        let rounded = round_at(value, place);
+        let figures = if rounded == 0.0 {
+            1
+        } else {
+            (decade(rounded) - place + 1).max(1) as u32
+        };
+        SigFig {
+            value,
+            figures: Some(figures),
+            place,
+        }
+    }
+
+    fn finite(result: SigFig) -> Result<SigFig, CalcError> {
+        if !result.value.is_finite() {
+            return Err(CalcError::TooLarge {
+                operation: "sig_figs",
+            });
+        }
+        Ok(result)
+    }
+
+    fn combine(&self, other: &SigFig, value: f64) -> Result<SigFig, CalcError> {
+        // Cancellation leaves the operands' representation error in digits
+        // the result seems to have; keep the 15 the operands had.
+        let largest = self.value.abs().max(other.value.abs());
+        let value = if largest > 0.0 && value.is_finite() {
+            round_at(value, decade(largest) - (MAX_FIGURES as i32 - 1))
+        } else {
+            value
+        };
+        SigFig::finite(match (self.figures, other.figures) {
+            (None, None) => SigFig::exact(value),
+            (Some(_), None) => SigFig::at_place(value, self.place),
+            (None, Some(_)) => SigFig::at_place(value, other.place),
+            (Some(_), Some(_)) => SigFig::at_place(value, self.place.max(other.place)),
+        })
+    }
+
+    fn scaled(&self, other: &SigFig, value: f64) -> Result<SigFig, CalcError> {
+        SigFig::finite(match (self.figures, other.figures) {
+            (None, None) => SigFig::exact(value),
+            (Some(n), None) | (None, Some(n)) => SigFig::to_figures(value, n),
+            (Some(a), Some(b)) => SigFig::to_figures(value, a.min(b)),
+        })
+    }
+
+    pub fn add(&self, other: &SigFig) -> Result<SigFig, CalcError> {
+        self.combine(other, self.value + other.value)
+    }
+
+    pub fn sub(&self, other: &SigFig) -> Result<SigFig, CalcError> {
+        self.combine(other, self.value - other.value)
+    }
+
+    pub fn mul(&self, other: &SigFig) -> Result<SigFig, CalcError> {
+        self.scaled(other, self.value * other.value)
+    }
+
+    pub fn div(&self, other: &SigFig) -> Result<SigFig, CalcError> {
+        if other.value == 0.0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        self.scaled(other, self.value / other.value)
+    }
+
+    /// Raises to an exact power, keeping the figures.
+    pub fn pow(&self, exponent: f64) -> Result<SigFig, CalcError> {
+        let value = self.value.powf(exponent);
+        if value.is_nan() {
+            return Err(invalid(format!(
+                "{}^{} is not a real number",
+                self.value, exponent
+            )));
+        }
+        SigFig::finite(match self.figures {
+            None => SigFig::exact(value),
+            Some(n) => SigFig::to_figures(value, n),
+        })
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SigFig {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Result<SigFig, CalcErrorJs> {
+        SigFig::parse(text).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = with_figures)]
+    pub fn with_figures_js(value: f64, figures: u32) -> Result<SigFig, CalcErrorJs> {
+        SigFig::with_figures(value, figures).map_err(|e| CalcErrorJs::new(e, Some(value)))
+    }
+
+    /// A value known exactly, such as a count or a defined constant.
+    pub fn exact(value: f64) -> SigFig {
+        SigFig {
+            value,
+            figures: None,
+            place: 0,
+        }
+    }
+
+    /// The full-precision value.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// The value rounded to its significant figures.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn rounded(&self) -> f64 {
+        match self.figures {
+            Some(_) => round_at(self.value, self.place),
+            None => self.value,
+        }
+    }
+
+    /// The significant figures; `undefined` for an exact value.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn figures(&self) -> Option<u32> {
+        self.figures
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&self, other: &SigFig) -> Result<SigFig, CalcErrorJs> {
+        self.add(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = sub)]
+    pub fn sub_js(&self, other: &SigFig) -> Result<SigFig, CalcErrorJs> {
+        self.sub(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = mul)]
+    pub fn mul_js(&self, other: &SigFig) -> Result<SigFig, CalcErrorJs> {
+        self.mul(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = div)]
+    pub fn div_js(&self, other: &SigFig) -> Result<SigFig, CalcErrorJs> {
+        self.div(other)
+            .map_err(|e| CalcErrorJs::new(e, Some(other.value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = pow)]
+    pub fn pow_js(&self, exponent: f64) -> Result<SigFig, CalcErrorJs> {
+        self.pow(exponent)
+            .map_err(|e| CalcErrorJs::new(e, Some(exponent)))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// The rounded value written so that it reads back with the same figures:
+/// `12.40`, `1200` (two figures), `1200.` (four), `1.20e3` (three).
+impl fmt::Display for SigFig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(figures) = self.figures else {
+            return write!(f, "{}", self.value);
+        };
+        let rounded = round_at(self.value, self.place);
+        if self.place < 0 {
+            return write!(f, "{:.*}", (-self.place) as usize, rounded);
+        }
+        let text = format!("{:.0}", rounded);
+        let zeros = text.len() - text.trim_end_matches('0').len();
+        if rounded == 0.0 || zeros as i32 == self.place {
+            write!(f, "{}", text)
+        } else if self.place == 0 {
+            write!(f, "{}.", text)
+        } else {
+            write!(f, "{:.*e}", figures as usize - 1, rounded)
+        }
+    }
+}
+
+/// Evaluates `src` with significant figures, reading each number as
+/// written. Supports `+ - * / ^`, parentheses and `sqrt`; `pi`, `e`, `tau`
+/// and exponents are exact.
+pub fn evaluate_sig_figs(src: &str) -> Result<SigFig, CalcError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut parser = SigFigParser {
+        chars: &chars,
+        tokens: tokenize(src, Dialect::Standard)?,
+        pos: 0,
+    };
+    let result = parser.expression()?;
+    if let Some(t) = parser.tokens.get(parser.pos) {
+        return Err(syntax("Unexpected token", t.start));
+    }
+    Ok(result)
+}
+
+fn syntax(message: impl Into<String>, position: usize) -> CalcError {
+    CalcError::Syntax {
+        message: message.into(),
+        position,
+    }
+}
+
+// The expression grammar of `expr`, over significant-figure values.
+struct SigFigParser<'a> {
+    chars: &'a [char],
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl SigFigParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.start)
+            .unwrap_or(self.chars.len())
+    }
+
+    fn eat_op(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_close(&mut self) -> Result<(), CalcError> {
+        if self.peek() != Some(&Token::RParen) {
+            return Err(syntax("Expected ')'", self.position()));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<SigFig, CalcError> {
+        let mut lhs = self.term()?;
+        loop {
+            if self.eat_op('+') {
+                lhs = lhs.add(&self.term()?)?;
+            } else if self.eat_op('-') {
+                lhs = lhs.sub(&self.term()?)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<SigFig, CalcError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat_op('*') {
+                lhs = lhs.mul(&self.unary()?)?;
+            } else if self.eat_op('/') {
+                lhs = lhs.div(&self.unary()?)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<SigFig, CalcError> {
+        if self.eat_op('-') {
+            let inner = self.unary()?;
+            return Ok(SigFig {
+                value: -inner.value,
+                ..inner
+            });
+        }
+        if self.eat_op('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<SigFig, CalcError> {
+        let base = self.primary()?;
+        if self.eat_op('^') {
+            return base.pow(self.unary()?.value);
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<SigFig, CalcError> {
+        let position = self.position();
+        let Some(spanned) = self.tokens.get(self.pos).cloned() else {
+            return Err(syntax("Unexpected end of expression", position));
+        };
+        self.pos += 1;
+        match spanned.token {
+            Token::Number(_) => {
+                let text: String = self.chars[spanned.start..spanned.end].iter().collect();
+                SigFig::parse(&text)
+            }
+            Token::LParen => {
+                let inner = self.expression()?;
+                self.expect_close()?;
+                Ok(inner)
+            }
+            Token::Ident(name) if name == "sqrt" && self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let inner = self.expression()?;
+                self.expect_close()?;
+                inner.pow(0.5)
+            }
+            Token::Ident(name) => CONSTANTS
+                .iter()
+                .find(|(c, _)| *c == name)
+                .map(|(_, v)| SigFig::exact(*v))
+                .ok_or_else(|| {
+                    crate::expr::unknown_identifier(
+                        &name,
+                        CONSTANTS.iter().map(|(c, _)| *c).chain(["sqrt"]),
+                    )
+                }),
+            _ => Err(syntax("Unexpected token", position)),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = evaluate_sig_figs)]
+pub fn evaluate_sig_figs_js(src: &str) -> Result<SigFig, CalcErrorJs> {
+    evaluate_sig_figs(src).map_err(CalcErrorJs::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(text: &str) -> SigFig {
+        SigFig::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_counting_figures() {
+        for (text, figures) in [
+            ("12.30", 4),
+            ("0.0045", 2),
+            ("1200", 2),
+            ("1200.", 4),
+            ("1.20e3", 3),
+            ("-3.0", 2),
+            ("7", 1),
+            ("0.00", 1),
+        ] {
+            assert_eq!(s(text).figures(), Some(figures), "{}", text);
+            assert_eq!(s(text).to_string(), text, "{}", text);
+        }
+        assert_eq!(s(".5").to_string(), "0.5");
+        assert!(SigFig::parse("1.2.3").is_err());
+        assert!(SigFig::parse("abc").is_err());
+        assert!(SigFig::parse("").is_err());
+        assert_eq!(SigFig::with_figures(9.96, 2).unwrap().to_string(), "10.");
+        assert_eq!(
+            SigFig::with_figures(0.012345, 3).unwrap().to_string(),
+            "0.0123"
+        );
+        assert!(SigFig::with_figures(1.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        // Products keep the fewest figures.
+        let area = s("12.30").mul(&s("2.1")).unwrap();
+        assert_eq!(area.to_string(), "26");
+        assert_eq!(area.value(), 12.3 * 2.1);
+        assert_eq!(s("3.00").div(&s("7.0")).unwrap().to_string(), "0.43");
+        // Sums keep the least precise decimal place.
+        assert_eq!(s("12.1").add(&s("0.345")).unwrap().to_string(), "12.4");
+        assert_eq!(s("1200").add(&s("34.5")).unwrap().to_string(), "1200");
+        assert_eq!(s("100.0").sub(&s("99.95")).unwrap().to_string(), "0.1");
+        assert_eq!(s("1.23").sub(&s("1.23")).unwrap().to_string(), "0.00");
+        // Exact values do not limit the result.
+        let doubled = s("2.50").mul(&SigFig::exact(2.0)).unwrap();
+        assert_eq!(doubled.to_string(), "5.00");
+        assert_eq!(s("1.5").pow(2.0).unwrap().to_string(), "2.3");
+        assert_eq!(s("1.0").div(&s("0.0")), Err(CalcError::DivisionByZero));
+        // Rounding half up on the decimal digits.
+        assert_eq!(SigFig::with_figures(1.005, 3).unwrap().rounded(), 1.01);
+        assert_eq!(s("10.0").mul(&s("10.0")).unwrap().to_string(), "100.");
+        assert_eq!(s("12.0").mul(&s("100.")).unwrap().to_string(), "1.20e3");
+    }
+
+    #[test]
+    fn test_evaluate_sig_figs() {
+        let result = evaluate_sig_figs("12.30 * 2.1 + 0.55").unwrap();
+        assert_eq!(result.to_string(), "26");
+        assert_eq!(
+            evaluate_sig_figs("(4.184 * 250.0 * 3.2)")
+                .unwrap()
+                .to_string(),
+            "3300"
+        );
+        assert_eq!(evaluate_sig_figs("2 * pi * 1.50").unwrap().to_string(), "9");
+        assert_eq!(
+            evaluate_sig_figs("pi * 1.50^2").unwrap().to_string(),
+            "7.07"
+        );
+        assert_eq!(evaluate_sig_figs("2.00^3").unwrap().to_string(), "8.00");
+        assert_eq!(evaluate_sig_figs("sqrt(16.0)").unwrap().to_string(), "4.00");
+        assert_eq!(
+            evaluate_sig_figs("-1.50 + 0.2").unwrap().to_string(),
+            "-1.3"
+        );
+        assert!(evaluate_sig_figs("pi").unwrap().figures().is_none());
+        assert!(matches!(
+            evaluate_sig_figs("2 * x"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(evaluate_sig_figs("2 *").is_err());
+    }
+}
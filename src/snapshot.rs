@@ -0,0 +1,275 @@
+//! Stable text dumps of calculator state for golden-file tests.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::functions::UserFunction;
+use crate::tape::operation_name;
+use crate::Calculator;
+
+/// Significant digits kept when normalizing floats, so that last-bit noise
+/// from a changed evaluation order does not churn every snapshot.
+const CANONICAL_DIGITS: usize = 12;
+
+/// Formats `x` rounded to [`CANONICAL_DIGITS`] significant digits.
+///
+/// `-0.0` is folded into `0.0`, and non-finite values print as `NaN`,
+/// `inf` and `-inf`.
+pub fn canonical_f64(x: f64) -> String {
+    if x.is_nan() {
+        return "NaN".to_string();
+    }
+    if x.is_infinite() {
+        return if x > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    if x == 0.0 {
+        return "0.0".to_string();
+    }
+    let rounded: f64 = format!("{:.*e}", CANONICAL_DIGITS - 1, x)
+        .parse()
+        .unwrap_or(x);
+    format!("{:?}", rounded)
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Returns a stable, line-oriented dump of the state that decides what
+    /// the calculator computes: value, memory, settings, input locale,
+    /// variables, user functions and macros, templates, education mode,
+    /// dedupe, run quota and the history.
+    ///
+    /// Fields are always written in the same order and floats go through
+    /// [`canonical_f64`], so two calculators that behave the same produce
+    /// byte-identical strings across releases and platforms. Everything
+    /// after `history_limit` but the history is only written when it
+    /// differs from a new calculator's, so snapshots from before a field
+    /// existed still hold. Entry timestamps, listeners, sync state and
+    /// datasets are deliberately left out.
+    pub fn canonical_state_string(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("value: {}\n", canonical_f64(self.current_value)));
+        out.push_str(&format!("memory: {}\n", canonical_f64(self.memory)));
+        out.push_str(&format!("history_limit: {}\n", self.get_history_limit()));
+        if self.incognito {
+            out.push_str("incognito: true\n");
+        }
+        self.write_settings(&mut out);
+        self.write_definitions(&mut out);
+        out.push_str(&format!("history: {}\n", self.history.len()));
+        for (i, entry) in self.history.iter().enumerate() {
+            let operand2 = match entry.operand2 {
+                Some(v) => format!(" {}", canonical_f64(v)),
+                None => String::new(),
+            };
+            let author = match &entry.author {
+                Some(name) => format!(" by {}", name),
+                None => String::new(),
+            };
+            out.push_str(&format!(
+                "  {}: {:?} {}{} -> {}{}\n",
+                i,
+                entry.operation,
+                canonical_f64(entry.operand1),
+                operand2,
+                canonical_f64(entry.result),
+                author
+            ));
+        }
+        out
+    }
+}
+
+impl Calculator {
+    fn write_settings(&self, out: &mut String) {
+        let defaults = crate::settings::CalculatorSettings::default();
+        let settings = self.settings();
+        if settings.strict_math {
+            out.push_str("strict_math: true\n");
+        }
+        if settings.angle_unit != defaults.angle_unit {
+            out.push_str(&format!("angle_unit: {:?}\n", settings.angle_unit));
+        }
+        if let Some(decimals) = settings.decimals {
+            out.push_str(&format!("decimals: {}\n", decimals));
+        }
+        if settings.notation != defaults.notation {
+            out.push_str(&format!("notation: {:?}\n", settings.notation));
+        }
+        if let Some(locale) = &self.input_locale {
+            out.push_str(&format!("input_locale: {}\n", locale));
+        }
+        if let Some(mode) = &self.education {
+            let allowed = match &mode.allowed {
+                Some(names) => names.iter().cloned().collect::<Vec<_>>().join(","),
+                None => "all".to_string(),
+            };
+            let decimals = match mode.max_decimals {
+                Some(d) => d.to_string(),
+                None => "any".to_string(),
+            };
+            out.push_str(&format!(
+                "education: allowed {}, max_decimals {}\n",
+                allowed, decimals
+            ));
+        }
+        if self.dedupe.collapse_repeats || self.dedupe.skip_no_ops {
+            out.push_str(&format!(
+                "dedupe: collapse_repeats {}, skip_no_ops {}\n",
+                self.dedupe.collapse_repeats, self.dedupe.skip_no_ops
+            ));
+        }
+        let quota = self.run_quota();
+        if let Some(max) = quota.max_operations {
+            out.push_str(&format!("quota max_operations: {}\n", max));
+        }
+        if let Some(ms) = quota.max_duration_ms {
+            out.push_str(&format!("quota max_duration_ms: {}\n", canonical_f64(ms)));
+        }
+    }
+
+    // Variables, functions and templates, each sorted by name.
+    fn write_definitions(&self, out: &mut String) {
+        let variables = self.list_variables();
+        if !variables.is_empty() {
+            out.push_str(&format!("variables: {}\n", variables.len()));
+        }
+        for variable in variables {
+            match (variable.expression, variable.value) {
+                (Some(expression), _) => {
+                    out.push_str(&format!("  {} := {}\n", variable.name, expression))
+                }
+                (None, value) => out.push_str(&format!(
+                    "  {} = {}\n",
+                    variable.name,
+                    canonical_f64(value.unwrap_or(f64::NAN))
+                )),
+            }
+        }
+        if !self.functions.is_empty() {
+            out.push_str(&format!("functions: {}\n", self.functions.len()));
+        }
+        for (name, function) in &self.functions {
+            match function {
+                UserFunction::Expression { source, .. } => {
+                    out.push_str(&format!("  {}(x) = {}\n", name, source))
+                }
+                UserFunction::Macro(steps) => {
+                    let steps: Vec<String> = steps
+                        .iter()
+                        .map(|(operation, operand)| match operand {
+                            Some(v) => {
+                                format!("{} {}", operation_name(operation), canonical_f64(*v))
+                            }
+                            None => operation_name(operation),
+                        })
+                        .collect();
+                    out.push_str(&format!("  {}: macro {}\n", name, steps.join(", ")));
+                }
+            }
+        }
+        if !self.templates.is_empty() {
+            out.push_str(&format!("templates: {}\n", self.templates.len()));
+        }
+        for (name, template) in &self.templates {
+            out.push_str(&format!("  {}: {}\n", name, template.source));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_f64_normalizes_noise() {
+        assert_eq!(canonical_f64(0.1 + 0.2), "0.3");
+        assert_eq!(canonical_f64(15.0), "15.0");
+        assert_eq!(canonical_f64(-0.0), "0.0");
+        assert_eq!(canonical_f64(1e100), "1e100");
+    }
+
+    #[test]
+    fn test_canonical_f64_non_finite() {
+        assert_eq!(canonical_f64(f64::NAN), "NaN");
+        assert_eq!(canonical_f64(f64::INFINITY), "inf");
+        assert_eq!(canonical_f64(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_canonical_state_string_new_calculator() {
+        let calc = Calculator::new();
+        assert_eq!(
+            calc.canonical_state_string(),
+            "value: 0.0\nmemory: 0.0\nhistory_limit: 0\nhistory: 0\n"
+        );
+    }
+
+    #[test]
+    fn test_canonical_state_string_lists_history_in_order() {
+        let mut calc = Calculator::new();
+        calc.set_value(10.0);
+        calc.add(5.0);
+        calc.memory_store();
+        calc.divide(3.0).unwrap();
+        assert_eq!(
+            calc.canonical_state_string(),
+            "value: 5.0\nmemory: 15.0\nhistory_limit: 0\nhistory: 4\n  0: SetValue 0.0 10.0 -> 10.0\n  1: Add 10.0 5.0 -> 15.0\n  2: MemoryStore 15.0 -> 15.0\n  3: Divide 15.0 3.0 -> 5.0\n"
+        );
+    }
+
+    #[test]
+    fn test_canonical_state_string_writes_changed_configuration() {
+        use crate::expr::AngleUnit;
+        use crate::quota::RunQuota;
+
+        let mut calc = Calculator::new();
+        calc.set_strict_math(true);
+        let settings = crate::settings::CalculatorSettings {
+            angle_unit: AngleUnit::Degrees,
+            decimals: Some(2),
+            ..calc.settings()
+        };
+        calc.apply_settings(&settings).unwrap();
+        calc.set_variable("rate", 0.25).unwrap();
+        calc.define_live("double", "rate * 2").unwrap();
+        calc.define_function("tip", "x * 0.18").unwrap();
+        calc.record_macro("twice").unwrap();
+        calc.multiply(2.0);
+        calc.finish_macro().unwrap();
+        calc.save_template("area", "{w} * {h}").unwrap();
+        calc.set_input_locale("de-DE");
+        calc.enable_education_mode(Some(&["add", "multiply"]), Some(3))
+            .unwrap();
+        calc.set_history_dedupe(true, false);
+        calc.set_run_quota(RunQuota {
+            max_operations: Some(50),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            calc.canonical_state_string(),
+            "value: 0.0\nmemory: 0.0\nhistory_limit: 0\n\
+             strict_math: true\nangle_unit: Degrees\ndecimals: 2\n\
+             input_locale: de-DE\n\
+             education: allowed add,multiply, max_decimals 3\n\
+             dedupe: collapse_repeats true, skip_no_ops false\n\
+             quota max_operations: 50\n\
+             variables: 2\n  double := rate * 2\n  rate = 0.25\n\
+             functions: 2\n  tip(x) = x * 0.18\n  twice: macro multiply 2.0\n\
+             templates: 1\n  area: {w} * {h}\n\
+             history: 1\n  0: Multiply 0.0 2.0 -> 0.0\n"
+        );
+    }
+
+    #[test]
+    fn test_canonical_state_string_is_stable_across_equal_sessions() {
+        let run = || {
+            let mut calc = Calculator::new();
+            calc.set_value(0.1);
+            calc.add(0.2);
+            calc.multiply(3.0);
+            calc.canonical_state_string()
+        };
+        assert_eq!(run(), run());
+    }
+}
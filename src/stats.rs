@@ -0,0 +1,366 @@
+//! Descriptive statistics and simple linear regression over a data series.
+//!
+//! Load a whole series in one call with `set_data(Float64Array)` rather than
+//! one `push_data` per element to avoid a WASM boundary crossing per value.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+fn no_data() -> CalcError {
+    CalcError::InvalidArgument("No data".to_string())
+}
+
+fn check_finite(values: &[f64]) -> Result<(), CalcError> {
+    match values.iter().find(|v| !v.is_finite()) {
+        Some(v) => Err(CalcError::InvalidArgument(format!(
+            "Data must be finite, got {}",
+            v
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Default)]
+pub struct StatCalculator {
+    data: Vec<f64>,
+    // Regression x values; indices 0..n are used when absent.
+    xs: Option<Vec<f64>>,
+}
+
+impl StatCalculator {
+    /// Appends a value. If pairs were set, its x is its index in the series.
+    pub fn push_data(&mut self, value: f64) -> Result<usize, CalcError> {
+        check_finite(&[value])?;
+        self.data.push(value);
+        if let Some(xs) = &mut self.xs {
+            xs.push(xs.len() as f64);
+        }
+        Ok(self.data.len())
+    }
+
+    pub(crate) fn values(&self) -> &[f64] {
+        &self.data
+    }
+
+    pub fn set_data(&mut self, values: &[f64]) -> Result<usize, CalcError> {
+        check_finite(values)?;
+        self.data = values.to_vec();
+        self.xs = None;
+        Ok(self.data.len())
+    }
+
+    /// Sets `(x, y)` pairs for regression; `ys` also becomes the data series.
+    pub fn set_pairs(&mut self, xs: &[f64], ys: &[f64]) -> Result<usize, CalcError> {
+        if xs.len() != ys.len() {
+            return Err(CalcError::InvalidArgument(format!(
+                "x and y lengths differ ({} vs {})",
+                xs.len(),
+                ys.len()
+            )));
+        }
+        check_finite(xs)?;
+        check_finite(ys)?;
+        self.data = ys.to_vec();
+        self.xs = Some(xs.to_vec());
+        Ok(self.data.len())
+    }
+
+    pub fn mean(&self) -> Result<f64, CalcError> {
+        if self.data.is_empty() {
+            return Err(no_data());
+        }
+        Ok(self.sum() / self.data.len() as f64)
+    }
+
+    pub fn median(&self) -> Result<f64, CalcError> {
+        if self.data.is_empty() {
+            return Err(no_data());
+        }
+        let sorted = self.sorted();
+        let mid = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            Ok((sorted[mid - 1] + sorted[mid]) / 2.0)
+        } else {
+            Ok(sorted[mid])
+        }
+    }
+
+    /// Every value with the highest frequency, ascending.
+    pub fn mode(&self) -> Result<Vec<f64>, CalcError> {
+        if self.data.is_empty() {
+            return Err(no_data());
+        }
+        let sorted = self.sorted();
+        let mut runs: Vec<(f64, usize)> = Vec::new();
+        for v in sorted {
+            match runs.last_mut() {
+                Some((last, count)) if *last == v => *count += 1,
+                _ => runs.push((v, 1)),
+            }
+        }
+        let best = runs.iter().map(|&(_, c)| c).max().unwrap_or(0);
+        Ok(runs
+            .into_iter()
+            .filter(|&(_, c)| c == best)
+            .map(|(v, _)| v)
+            .collect())
+    }
+
+    fn sum_squared_deviations(&self) -> Result<f64, CalcError> {
+        let mean = self.mean()?;
+        Ok(self.data.iter().map(|v| (v - mean) * (v - mean)).sum())
+    }
+
+    pub fn variance_population(&self) -> Result<f64, CalcError> {
+        Ok(self.sum_squared_deviations()? / self.data.len() as f64)
+    }
+
+    pub fn variance_sample(&self) -> Result<f64, CalcError> {
+        if self.data.len() < 2 {
+            return Err(CalcError::InvalidArgument(
+                "Sample variance needs at least 2 values".to_string(),
+            ));
+        }
+        Ok(self.sum_squared_deviations()? / (self.data.len() - 1) as f64)
+    }
+
+    pub fn std_dev_population(&self) -> Result<f64, CalcError> {
+        self.variance_population().map(f64::sqrt)
+    }
+
+    pub fn std_dev_sample(&self) -> Result<f64, CalcError> {
+        self.variance_sample().map(f64::sqrt)
+    }
+
+    pub fn min(&self) -> Result<f64, CalcError> {
+        self.data
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .ok_or_else(no_data)
+    }
+
+    pub fn max(&self) -> Result<f64, CalcError> {
+        self.data
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .ok_or_else(no_data)
+    }
+
+    /// Least-squares `(slope, intercept)` of the data against the x values
+    /// from [`set_pairs`](Self::set_pairs), or against indices 0..n.
+    pub fn linear_regression(&self) -> Result<(f64, f64), CalcError> {
+        if self.data.len() < 2 {
+            return Err(CalcError::InvalidArgument(
+                "Regression needs at least 2 points".to_string(),
+            ));
+        }
+        let xs: Vec<f64> = match &self.xs {
+            Some(xs) => xs.clone(),
+            None => (0..self.data.len()).map(|i| i as f64).collect(),
+        };
+        let n = xs.len() as f64;
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = self.mean()?;
+        let mut sxy = 0.0;
+        let mut sxx = 0.0;
+        for (x, y) in xs.iter().zip(&self.data) {
+            sxy += (x - mean_x) * (y - mean_y);
+            sxx += (x - mean_x) * (x - mean_x);
+        }
+        if sxx == 0.0 {
+            return Err(CalcError::InvalidArgument(
+                "Regression needs at least 2 distinct x values".to_string(),
+            ));
+        }
+        let slope = sxy / sxx;
+        Ok((slope, mean_y - slope * mean_x))
+    }
+
+    fn sorted(&self) -> Vec<f64> {
+        let mut sorted = self.data.clone();
+        sorted.sort_by(f64::total_cmp);
+        sorted
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl StatCalculator {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> StatCalculator {
+        StatCalculator::default()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = push_data)]
+    pub fn push_data_js(&mut self, value: f64) -> Result<usize, CalcErrorJs> {
+        self.push_data(value)
+            .map_err(|e| CalcErrorJs::new(e, Some(value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_data)]
+    pub fn set_data_js(&mut self, values: &[f64]) -> Result<usize, CalcErrorJs> {
+        self.set_data(values).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_pairs)]
+    pub fn set_pairs_js(&mut self, xs: &[f64], ys: &[f64]) -> Result<usize, CalcErrorJs> {
+        self.set_pairs(xs, ys).map_err(CalcErrorJs::from)
+    }
+
+    pub fn get_data(&self) -> Vec<f64> {
+        self.data.clone()
+    }
+
+    pub fn clear_data(&mut self) {
+        self.data.clear();
+        self.xs = None;
+    }
+
+    pub fn count(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.data.iter().sum()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = mean)]
+    pub fn mean_js(&self) -> Result<f64, CalcErrorJs> {
+        self.mean().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = median)]
+    pub fn median_js(&self) -> Result<f64, CalcErrorJs> {
+        self.median().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = mode)]
+    pub fn mode_js(&self) -> Result<Vec<f64>, CalcErrorJs> {
+        self.mode().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = variance_population)]
+    pub fn variance_population_js(&self) -> Result<f64, CalcErrorJs> {
+        self.variance_population().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = variance_sample)]
+    pub fn variance_sample_js(&self) -> Result<f64, CalcErrorJs> {
+        self.variance_sample().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = std_dev_population)]
+    pub fn std_dev_population_js(&self) -> Result<f64, CalcErrorJs> {
+        self.std_dev_population().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = std_dev_sample)]
+    pub fn std_dev_sample_js(&self) -> Result<f64, CalcErrorJs> {
+        self.std_dev_sample().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = min)]
+    pub fn min_js(&self) -> Result<f64, CalcErrorJs> {
+        self.min().map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = max)]
+    pub fn max_js(&self) -> Result<f64, CalcErrorJs> {
+        self.max().map_err(CalcErrorJs::from)
+    }
+
+    /// `[slope, intercept]`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = linear_regression)]
+    pub fn linear_regression_js(&self) -> Result<Vec<f64>, CalcErrorJs> {
+        self.linear_regression()
+            .map(|(slope, intercept)| vec![slope, intercept])
+            .map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(values: &[f64]) -> StatCalculator {
+        let mut s = StatCalculator::new();
+        s.set_data(values).unwrap();
+        s
+    }
+
+    #[test]
+    fn test_central_tendency() {
+        let s = stats(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(s.count(), 8);
+        assert_eq!(s.sum(), 40.0);
+        assert_eq!(s.mean(), Ok(5.0));
+        assert_eq!(s.median(), Ok(4.5));
+        assert_eq!(s.mode(), Ok(vec![4.0]));
+        assert_eq!(s.min(), Ok(2.0));
+        assert_eq!(s.max(), Ok(9.0));
+        assert_eq!(stats(&[3.0, 1.0, 2.0]).median(), Ok(2.0));
+        assert_eq!(stats(&[1.0, 2.0, 2.0, 1.0]).mode(), Ok(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn test_dispersion() {
+        let s = stats(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(s.variance_population(), Ok(4.0));
+        assert_eq!(s.std_dev_population(), Ok(2.0));
+        assert_eq!(s.variance_sample(), Ok(32.0 / 7.0));
+        assert!(stats(&[1.0]).variance_sample().is_err());
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let s = stats(&[1.0, 3.0, 5.0, 7.0]);
+        assert_eq!(s.linear_regression(), Ok((2.0, 1.0)));
+        let mut s = StatCalculator::new();
+        s.set_pairs(&[1.0, 2.0, 4.0], &[3.0, 5.0, 9.0]).unwrap();
+        assert_eq!(s.linear_regression(), Ok((2.0, 1.0)));
+        s.set_pairs(&[1.0, 1.0], &[2.0, 3.0]).unwrap();
+        assert!(s.linear_regression().is_err());
+        assert!(s.set_pairs(&[1.0], &[1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn test_push_data_and_errors() {
+        let mut s = StatCalculator::new();
+        assert_eq!(s.mean(), Err(no_data()));
+        assert!(s.mode().is_err());
+        assert_eq!(s.push_data(1.0), Ok(1));
+        assert_eq!(s.push_data(3.0), Ok(2));
+        assert!(s.push_data(f64::NAN).is_err());
+        assert!(s.set_data(&[1.0, f64::INFINITY]).is_err());
+        assert_eq!(s.get_data(), vec![1.0, 3.0]);
+        s.clear_data();
+        assert_eq!(s.count(), 0);
+    }
+
+    #[test]
+    fn test_push_extends_pairs_by_index() {
+        let mut s = StatCalculator::new();
+        s.set_pairs(&[0.0, 1.0], &[1.0, 2.0]).unwrap();
+        s.push_data(3.0).unwrap();
+        assert_eq!(s.linear_regression(), Ok((1.0, 1.0)));
+    }
+}
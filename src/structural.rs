@@ -0,0 +1,272 @@
+//! Quick formulas for simply supported beams.
+//!
+//! A [`SimplySupportedBeam`] is a prismatic beam on a pin and a roller,
+//! described by its span, Young's modulus `E` and second moment of area
+//! `I`. It reports the support reactions, the largest bending moment and
+//! the largest deflection under a point load or a load spread evenly over
+//! the span, by Euler-Bernoulli beam theory (small deflections, elastic
+//! material, self-weight ignored unless included in the uniform load).
+//!
+//! Inputs are [`Quantity`] values in any units of the right dimension
+//! (`6 m`, `200 GPa`, `8.5e6 mm^4`, `10 kN`, `2 kN/m`); results are in SI:
+//! newtons, newton metres and metres.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::units::Quantity;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// What one load does to a beam. Positions are metres from the left
+/// support.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BeamResponse {
+    pub left_reaction: f64,
+    pub right_reaction: f64,
+    /// In N·m.
+    pub max_moment: f64,
+    pub max_moment_at: f64,
+    /// In m, in the direction of the load.
+    pub max_deflection: f64,
+    pub max_deflection_at: f64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimplySupportedBeam {
+    // SI: m, Pa and m^4.
+    span: f64,
+    modulus: f64,
+    inertia: f64,
+}
+
+// `quantity` as a number of `unit`, naming `what` if the dimension is wrong.
+fn si(what: &str, quantity: &Quantity, unit: &str) -> Result<f64, CalcError> {
+    let value = quantity
+        .convert_to(unit)
+        .map_err(|e| match e {
+            CalcError::InvalidArgument(message) => {
+                CalcError::InvalidArgument(format!("{}: {}", what, message))
+            }
+            e => e,
+        })?
+        .value();
+    if !value.is_finite() {
+        return Err(CalcError::InvalidArgument(format!(
+            "{} must be finite, got {}",
+            what, quantity
+        )));
+    }
+    Ok(value)
+}
+
+fn positive(what: &str, quantity: &Quantity, unit: &str) -> Result<f64, CalcError> {
+    let value = si(what, quantity, unit)?;
+    if value <= 0.0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "{} must be positive, got {}",
+            what, quantity
+        )));
+    }
+    Ok(value)
+}
+
+impl SimplySupportedBeam {
+    /// A beam of length `span` with Young's modulus `modulus` and second
+    /// moment of area `inertia` (a length to the fourth power).
+    pub fn new(
+        span: &Quantity,
+        modulus: &Quantity,
+        inertia: &Quantity,
+    ) -> Result<SimplySupportedBeam, CalcError> {
+        Ok(SimplySupportedBeam {
+            span: positive("Span", span, "m")?,
+            modulus: positive("Modulus", modulus, "Pa")?,
+            inertia: positive("Second moment of area", inertia, "m^4")?,
+        })
+    }
+
+    fn stiffness(&self) -> f64 {
+        self.modulus * self.inertia
+    }
+
+    /// A point `load` (a force) at `position` from the left support, or at
+    /// midspan.
+    pub fn point_load(
+        &self,
+        load: &Quantity,
+        position: Option<&Quantity>,
+    ) -> Result<BeamResponse, CalcError> {
+        let p = si("Load", load, "N")?;
+        let l = self.span;
+        let a = match position {
+            Some(position) => si("Position", position, "m")?,
+            None => l / 2.0,
+        };
+        if !(0.0..=l).contains(&a) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Position must be on the {} m span, got {} m",
+                l, a
+            )));
+        }
+        let b = l - a;
+        // The deflection peaks in the longer segment, where `x` is measured
+        // from the support at its end.
+        let short = a.min(b);
+        let x = ((l * l - short * short) / 3.0).sqrt();
+        let max_deflection = p * short * (l * l - short * short).powf(1.5)
+            / (9.0 * 3f64.sqrt() * self.stiffness() * l);
+        Ok(BeamResponse {
+            left_reaction: p * b / l,
+            right_reaction: p * a / l,
+            max_moment: p * a * b / l,
+            max_moment_at: a,
+            max_deflection,
+            max_deflection_at: if a >= b { x } else { l - x },
+        })
+    }
+
+    /// A uniform `load` per length (`2 kN/m`) over the whole span.
+    pub fn uniform_load(&self, load: &Quantity) -> Result<BeamResponse, CalcError> {
+        let w = si("Load", load, "N/m")?;
+        let l = self.span;
+        Ok(BeamResponse {
+            left_reaction: w * l / 2.0,
+            right_reaction: w * l / 2.0,
+            max_moment: w * l * l / 8.0,
+            max_moment_at: l / 2.0,
+            max_deflection: 5.0 * w * l.powi(4) / (384.0 * self.stiffness()),
+            max_deflection_at: l / 2.0,
+        })
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SimplySupportedBeam {
+    /// Each argument is a quantity as text: `new SimplySupportedBeam("6 m",
+    /// "200 GPa", "8.5e6 mm^4")`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new_js(
+        span: &str,
+        modulus: &str,
+        inertia: &str,
+    ) -> Result<SimplySupportedBeam, CalcErrorJs> {
+        let parse = |text: &str| Quantity::parse(text).map_err(CalcErrorJs::from);
+        SimplySupportedBeam::new(&parse(span)?, &parse(modulus)?, &parse(inertia)?)
+            .map_err(CalcErrorJs::from)
+    }
+
+    /// In m.
+    pub fn span(&self) -> f64 {
+        self.span
+    }
+
+    /// `{ left_reaction, right_reaction, max_moment, max_moment_at,
+    /// max_deflection, max_deflection_at }` for `load` (`"10 kN"`) at
+    /// `position` (`"2 m"`), or at midspan without one.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = point_load)]
+    pub fn point_load_js(
+        &self,
+        load: &str,
+        position: Option<String>,
+    ) -> Result<JsValue, CalcErrorJs> {
+        let load = Quantity::parse(load).map_err(CalcErrorJs::from)?;
+        let position = position
+            .as_deref()
+            .map(Quantity::parse)
+            .transpose()
+            .map_err(CalcErrorJs::from)?;
+        let response = self
+            .point_load(&load, position.as_ref())
+            .map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL))
+    }
+
+    /// Like `point_load`, for a load per length such as `"2 kN/m"`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = uniform_load)]
+    pub fn uniform_load_js(&self, load: &str) -> Result<JsValue, CalcErrorJs> {
+        let load = Quantity::parse(load).map_err(CalcErrorJs::from)?;
+        let response = self.uniform_load(&load).map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&response).unwrap_or(JsValue::NULL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(text: &str) -> Quantity {
+        Quantity::parse(text).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    // A 6 m steel beam, I = 8.5e-5 m^4.
+    fn beam() -> SimplySupportedBeam {
+        SimplySupportedBeam::new(&q("6 m"), &q("200 GPa"), &q("8.5e7 mm^4")).unwrap()
+    }
+
+    #[test]
+    fn test_point_load() {
+        let beam = beam();
+        let ei = 200e9 * 8.5e-5;
+        let mid = beam.point_load(&q("10 kN"), None).unwrap();
+        assert!(close(mid.left_reaction, 5_000.0));
+        assert!(close(mid.max_moment, 10_000.0 * 6.0 / 4.0));
+        assert!(close(mid.max_deflection, 10_000.0 * 216.0 / (48.0 * ei)));
+        assert!(close(mid.max_deflection_at, 3.0));
+
+        let off = beam.point_load(&q("10 kN"), Some(&q("2 m"))).unwrap();
+        assert!(close(off.left_reaction, 10_000.0 * 4.0 / 6.0));
+        assert!(close(off.right_reaction, 10_000.0 * 2.0 / 6.0));
+        assert!(close(off.max_moment, 10_000.0 * 2.0 * 4.0 / 6.0));
+        assert_eq!(off.max_moment_at, 2.0);
+        // Peaks in the longer, right-hand segment.
+        let x = ((36.0 - 4.0) / 3.0f64).sqrt();
+        assert!(close(off.max_deflection_at, 6.0 - x));
+        let mirrored = beam.point_load(&q("10 kN"), Some(&q("4 m"))).unwrap();
+        assert!(close(mirrored.max_deflection, off.max_deflection));
+        assert!(close(mirrored.max_deflection_at, x));
+        assert!(off.max_deflection < mid.max_deflection);
+
+        let imperial = beam.point_load(&q("10 kip"), None).unwrap();
+        assert!(close(imperial.left_reaction, 5.0 * 4_448.221_615_260_5));
+        assert!(beam.point_load(&q("10 kN"), Some(&q("7 m"))).is_err());
+        assert!(beam.point_load(&q("10 kg"), None).is_err());
+    }
+
+    #[test]
+    fn test_uniform_load() {
+        let response = beam().uniform_load(&q("2 kN/m")).unwrap();
+        assert!(close(response.left_reaction, 6_000.0));
+        assert!(close(response.right_reaction, 6_000.0));
+        assert!(close(response.max_moment, 2_000.0 * 36.0 / 8.0));
+        assert!(close(
+            response.max_deflection,
+            5.0 * 2_000.0 * 1296.0 / (384.0 * 200e9 * 8.5e-5)
+        ));
+        assert!(beam().uniform_load(&q("2 kN")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_beams() {
+        assert_eq!(
+            SimplySupportedBeam::new(&q("6 kg"), &q("200 GPa"), &q("1 m^4")),
+            Err(CalcError::InvalidArgument(
+                "Span: Cannot convert kg to m: the dimensions differ".to_string()
+            ))
+        );
+        assert!(SimplySupportedBeam::new(&q("0 m"), &q("200 GPa"), &q("1 m^4")).is_err());
+        assert!(SimplySupportedBeam::new(&q("6 m"), &q("200 GPa"), &q("1 m^3")).is_err());
+        let beam = SimplySupportedBeam::new(&q("20 ft"), &q("29000 ksi"), &q("100 in^4")).unwrap();
+        assert!(close(beam.span(), 6.096));
+    }
+}
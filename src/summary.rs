@@ -0,0 +1,157 @@
+//! End-of-session recap.
+//!
+//! [`SessionStats`] is updated as operations are recorded and errors
+//! reported, so the recap covers the whole session even after
+//! `clear_history` or history-limit eviction has dropped entries.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{now_ms, tape::operation_name, CalculationHistory, Calculator, Operation};
+
+/// Running totals for one calculator's session.
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    started_at: f64,
+    // Recorded operations by their tape name (`add`, `function:tip`, ...).
+    operations: BTreeMap<String, usize>,
+    largest: Option<f64>,
+    smallest: Option<f64>,
+    // Counted from `Calculator::fail`, which only borrows the calculator.
+    errors: Cell<usize>,
+}
+
+/// What `session_summary` returns.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionSummary {
+    /// Count of each recorded operation, keyed by name.
+    pub operations: BTreeMap<String, usize>,
+    pub total_operations: usize,
+    /// Extremes among the results of value operations (memory operations
+    /// and NaN results are left out); `None` before the first one.
+    pub largest_result: Option<f64>,
+    pub smallest_result: Option<f64>,
+    pub error_count: usize,
+    /// Milliseconds since the Unix epoch when the session began.
+    pub started_at: f64,
+    pub duration_ms: f64,
+}
+
+impl SessionStats {
+    pub(crate) fn new() -> SessionStats {
+        SessionStats {
+            started_at: now_ms(),
+            operations: BTreeMap::new(),
+            largest: None,
+            smallest: None,
+            errors: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn record(&mut self, entry: &CalculationHistory) {
+        let id = match &entry.operation {
+            Operation::Constant(_) | Operation::Function(_) => None,
+            other => Some(other.id()),
+        };
+        // Looked up by id first so a repeated operation allocates nothing.
+        match id.and_then(|id| self.operations.get_mut(id)) {
+            Some(count) => *count += 1,
+            None => {
+                *self
+                    .operations
+                    .entry(operation_name(&entry.operation))
+                    .or_default() += 1
+            }
+        }
+        if entry.operation.affects_memory() || entry.result.is_nan() {
+            return;
+        }
+        self.largest = Some(self.largest.map_or(entry.result, |m| m.max(entry.result)));
+        self.smallest = Some(self.smallest.map_or(entry.result, |m| m.min(entry.result)));
+    }
+
+    pub(crate) fn record_error(&self) {
+        self.errors.set(self.errors.get() + 1);
+    }
+}
+
+impl Calculator {
+    pub fn session_summary(&self) -> SessionSummary {
+        let stats = &self.session;
+        SessionSummary {
+            operations: stats.operations.clone(),
+            total_operations: stats.operations.values().sum(),
+            largest_result: stats.largest,
+            smallest_result: stats.smallest,
+            error_count: stats.errors.get(),
+            started_at: stats.started_at,
+            duration_ms: (now_ms() - stats.started_at).max(0.0),
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// `{ operations, total_operations, largest_result, smallest_result,
+    /// error_count, started_at, duration_ms }`; see [`SessionSummary`].
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = session_summary)]
+    pub fn session_summary_js(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.session_summary()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Starts a new session: the summary's counts, extremes and clock start
+    /// over. Value, memory and history are kept.
+    pub fn reset_session(&mut self) {
+        self.session = SessionStats::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_summary() {
+        let mut calc = Calculator::new();
+        calc.set_history_limit(2);
+        calc.add(10.0);
+        calc.multiply(3.0);
+        calc.memory_store();
+        calc.subtract(50.0);
+        calc.add(5.0);
+        assert!(calc.divide(0.0).is_err());
+        assert!(calc.evaluate("1 +").is_err());
+        let summary = calc.session_summary();
+        let counts: Vec<_> = summary
+            .operations
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("add", 2),
+                ("memory_store", 1),
+                ("multiply", 1),
+                ("subtract", 1)
+            ]
+        );
+        assert_eq!(summary.total_operations, 5);
+        assert_eq!(summary.largest_result, Some(30.0));
+        assert_eq!(summary.smallest_result, Some(-20.0));
+        assert_eq!(summary.error_count, 2);
+        assert!(summary.duration_ms >= 0.0);
+
+        calc.reset_session();
+        let summary = calc.session_summary();
+        assert_eq!(summary.total_operations, 0);
+        assert_eq!(summary.largest_result, None);
+        assert_eq!(summary.error_count, 0);
+        assert_eq!(calc.history_count(), 2);
+    }
+}
@@ -0,0 +1,394 @@
+//! Keeping calculators on several devices in step.
+//!
+//! Each synchronized calculator is a replica with its own id, given by
+//! [`Calculator::set_replica_id`]. From then on every operation it records
+//! is also appended to a sync log under that id and a per-replica sequence
+//! number, and a version vector counts how many operations of each replica
+//! it has applied. A peer sends its version vector, the calculator answers
+//! with [`Calculator::state_delta_since`] the operations the peer has not
+//! seen, and the peer replays them with [`Calculator::apply_delta`].
+//! Deltas are plain JSON, so any transport will do.
+//!
+//! Operations are replayed, not values copied: a replica that applied the
+//! same operations in the same order holds the same value (bit for bit
+//! across platforms with the `deterministic` feature, see `math`). When two
+//! replicas both ran operations the other had not seen, each applies the
+//! other's after its own and they diverge; `apply_delta` reports that as
+//! `concurrent`, for the app to reconcile. User functions are replayed by
+//! name and must be defined on every replica.
+//!
+//! Only recorded operations are synced: history edits, replays and
+//! settings are local. An incognito calculator keeps no log and cannot be
+//! a replica.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::tape::{operation_name, parse_operation};
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator, Operation};
+
+/// How many operations of each replica have been applied, by replica id.
+pub type VersionVector = BTreeMap<String, u64>;
+
+/// One logged operation: the `seq`th of replica `replica`. `op` is named as
+/// in tape export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncOp {
+    pub replica: String,
+    pub seq: u64,
+    pub op: String,
+    #[serde(default)]
+    pub operand: Option<f64>,
+    /// Who performed it, when the replica had an author set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+/// The operations one replica has and another had not seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateDelta {
+    /// The sender.
+    pub replica: String,
+    /// The sender's version vector when the delta was taken.
+    pub versions: VersionVector,
+    pub ops: Vec<SyncOp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeltaOutcome {
+    pub applied: usize,
+    /// Operations already applied here, from an overlapping delta.
+    pub skipped: usize,
+    /// Whether this replica had operations the sender had not seen, so the
+    /// two applied them in different orders.
+    pub concurrent: bool,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SyncState {
+    replica: Option<String>,
+    versions: VersionVector,
+    log: Vec<SyncOp>,
+    // The origins of the remote operations `apply_delta` is replaying, in
+    // order; each recorded operation takes the next one.
+    incoming: VecDeque<(String, u64, Option<String>)>,
+}
+
+// The part of a `SyncState` that recording operations changes, saved so a
+// failed run can be undone.
+#[derive(Debug, Clone)]
+pub(crate) struct SyncMark {
+    versions: VersionVector,
+    log_len: usize,
+}
+
+impl SyncState {
+    pub(crate) fn mark(&self) -> SyncMark {
+        SyncMark {
+            versions: self.versions.clone(),
+            log_len: self.log.len(),
+        }
+    }
+
+    pub(crate) fn rewind(&mut self, mark: SyncMark) {
+        self.versions = mark.versions;
+        self.log.truncate(mark.log_len);
+    }
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+impl Calculator {
+    /// Makes this calculator the replica `id`. The id cannot change once
+    /// set.
+    pub fn set_replica_id(&mut self, id: &str) -> Result<(), CalcError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(invalid("Replica id must not be empty"));
+        }
+        if self.incognito {
+            return Err(invalid("An incognito calculator cannot sync"));
+        }
+        match &self.sync.replica {
+            Some(current) if current != id => Err(invalid(format!(
+                "This calculator is already replica '{}'",
+                current
+            ))),
+            _ => {
+                self.sync.replica = Some(id.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn replica_id(&self) -> Option<String> {
+        self.sync.replica.clone()
+    }
+
+    pub fn version_vector(&self) -> VersionVector {
+        self.sync.versions.clone()
+    }
+
+    /// The logged operations not covered by `since`, in the order they were
+    /// applied here.
+    pub fn state_delta_since(&self, since: &VersionVector) -> Result<StateDelta, CalcError> {
+        let replica = self.replica()?;
+        let ops = self
+            .sync
+            .log
+            .iter()
+            .filter(|op| op.seq > since.get(&op.replica).copied().unwrap_or(0))
+            .cloned()
+            .collect();
+        Ok(StateDelta {
+            replica: replica.to_string(),
+            versions: self.sync.versions.clone(),
+            ops,
+        })
+    }
+
+    /// Replays the operations of `delta` this replica has not applied yet.
+    ///
+    /// All-or-nothing, like [`Calculator::apply_batch`]. A delta that skips
+    /// operations of some replica (its 3rd before its 2nd arrived) fails,
+    /// as does one naming an unknown operation.
+    pub fn apply_delta(&mut self, delta: &StateDelta) -> Result<DeltaOutcome, CalcError> {
+        self.replica()?;
+        let mut versions = self.sync.versions.clone();
+        let mut steps = Vec::new();
+        let mut origins = VecDeque::new();
+        let mut skipped = 0;
+        for op in &delta.ops {
+            let seen = versions.entry(op.replica.clone()).or_default();
+            if op.seq <= *seen {
+                skipped += 1;
+                continue;
+            }
+            if op.seq != *seen + 1 {
+                return Err(self.fail(
+                    invalid(format!(
+                        "Delta is missing operations {}..{} of replica '{}'",
+                        *seen + 1,
+                        op.seq - 1,
+                        op.replica
+                    )),
+                    None,
+                ));
+            }
+            *seen = op.seq;
+            let operation = parse_operation(&op.op).ok_or_else(|| {
+                self.fail(
+                    invalid(format!("Unknown operation '{}' in delta", op.op)),
+                    op.operand,
+                )
+            })?;
+            steps.push((operation, op.operand));
+            origins.push_back((op.replica.clone(), op.seq, op.author.clone()));
+        }
+        let applied = steps.len();
+        let concurrent = applied > 0
+            && self
+                .sync
+                .versions
+                .iter()
+                .any(|(replica, seq)| *seq > delta.versions.get(replica).copied().unwrap_or(0));
+        self.sync.incoming = origins;
+        let result = self.apply_all(steps);
+        self.sync.incoming.clear();
+        result?;
+        Ok(DeltaOutcome {
+            applied,
+            skipped,
+            concurrent,
+            value: self.current_value,
+        })
+    }
+
+    /// Stops syncing and drops the log.
+    pub(crate) fn leave_sync(&mut self) {
+        self.sync = SyncState::default();
+    }
+
+    // Appends a just-recorded operation to the log, under the origin of
+    // the remote operation being replayed or as this replica's next, and
+    // returns who performed it.
+    pub(crate) fn sync_record(
+        &mut self,
+        operation: &Operation,
+        operand: Option<f64>,
+    ) -> Option<String> {
+        let Some(own) = &self.sync.replica else {
+            return self.author.clone();
+        };
+        let (replica, seq, author) = self.sync.incoming.pop_front().unwrap_or_else(|| {
+            let seq = self.sync.versions.get(own).copied().unwrap_or(0) + 1;
+            (own.clone(), seq, self.author.clone())
+        });
+        self.sync.versions.insert(replica.clone(), seq);
+        self.sync.log.push(SyncOp {
+            replica,
+            seq,
+            op: operation_name(operation),
+            operand,
+            author: author.clone(),
+        });
+        author
+    }
+
+    fn replica(&self) -> Result<&str, CalcError> {
+        self.sync
+            .replica
+            .as_deref()
+            .ok_or_else(|| invalid("Call set_replica_id before syncing"))
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn from_json<T: serde::de::DeserializeOwned>(json: &str, what: &str) -> Result<T, CalcErrorJs> {
+    serde_json::from_str(json)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Invalid {}: {}", what, e))))
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_replica_id)]
+    pub fn set_replica_id_js(&mut self, id: &str) -> Result<(), CalcErrorJs> {
+        self.set_replica_id(id).map_err(CalcErrorJs::from)
+    }
+
+    /// The version vector as a JSON object of replica ids to counts.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = version_vector)]
+    pub fn version_vector_js(&self) -> String {
+        serde_json::to_string(&self.sync.versions).unwrap_or_default()
+    }
+
+    /// The delta for a peer with the version vector `versions` (JSON, as
+    /// from `version_vector`; empty for everything), as JSON.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = state_delta_since)]
+    pub fn state_delta_since_js(&self, versions: &str) -> Result<String, CalcErrorJs> {
+        let since = if versions.trim().is_empty() {
+            VersionVector::new()
+        } else {
+            from_json(versions, "version vector")?
+        };
+        let delta = self.state_delta_since(&since).map_err(CalcErrorJs::from)?;
+        Ok(serde_json::to_string(&delta).unwrap_or_default())
+    }
+
+    /// Applies a JSON delta from `state_delta_since`, returning
+    /// `{ applied, skipped, concurrent, value }`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = apply_delta)]
+    pub fn apply_delta_js(&mut self, delta: &str) -> Result<JsValue, CalcErrorJs> {
+        let delta: StateDelta = from_json(delta, "delta")?;
+        let outcome = self.apply_delta(&delta).map_err(CalcErrorJs::from)?;
+        Ok(serde_wasm_bindgen::to_value(&outcome).unwrap_or(JsValue::NULL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replica(id: &str) -> Calculator {
+        let mut calc = Calculator::new();
+        calc.set_replica_id(id).unwrap();
+        calc
+    }
+
+    #[test]
+    fn test_sync_two_replicas() {
+        let mut phone = replica("phone");
+        let mut laptop = replica("laptop");
+        phone.add(10.0);
+        phone.multiply(3.0);
+        phone.memory_store();
+
+        let delta = phone.state_delta_since(&laptop.version_vector()).unwrap();
+        assert_eq!(delta.ops.len(), 3);
+        assert_eq!(delta.ops[1].op, "multiply");
+        let outcome = laptop.apply_delta(&delta).unwrap();
+        assert_eq!((outcome.applied, outcome.concurrent), (3, false));
+        assert_eq!(laptop.get_value(), 30.0);
+        assert_eq!(laptop.get_memory(), 30.0);
+        assert_eq!(laptop.history_count(), 3);
+
+        // The laptop continues, and the phone catches up on that alone.
+        laptop.subtract(5.0);
+        let delta = laptop.state_delta_since(&phone.version_vector()).unwrap();
+        assert_eq!(delta.ops.len(), 1);
+        phone.apply_delta(&delta).unwrap();
+        assert_eq!(phone.get_value(), 25.0);
+        assert_eq!(phone.version_vector(), laptop.version_vector());
+        assert_eq!(phone.version_vector()["phone"], 3);
+        assert_eq!(phone.version_vector()["laptop"], 1);
+
+        // Applying a delta again changes nothing.
+        let outcome = phone.apply_delta(&delta).unwrap();
+        assert_eq!((outcome.applied, outcome.skipped), (0, 1));
+        assert_eq!(phone.get_value(), 25.0);
+    }
+
+    #[test]
+    fn test_sync_relays_and_concurrency() {
+        let mut a = replica("a");
+        let mut b = replica("b");
+        let mut c = replica("c");
+        a.add(1.0);
+        b.apply_delta(&a.state_delta_since(&b.version_vector()).unwrap())
+            .unwrap();
+        // `c` gets `a`'s operation through `b`, under `a`'s id.
+        c.apply_delta(&b.state_delta_since(&c.version_vector()).unwrap())
+            .unwrap();
+        assert_eq!(c.version_vector()["a"], 1);
+
+        a.add(2.0);
+        b.multiply(10.0);
+        let outcome = a
+            .apply_delta(&b.state_delta_since(&a.version_vector()).unwrap())
+            .unwrap();
+        assert!(outcome.concurrent);
+        assert_eq!(outcome.value, 30.0);
+    }
+
+    #[test]
+    fn test_apply_delta_errors() {
+        let mut a = replica("a");
+        let mut b = replica("b");
+        a.add(1.0);
+        a.add(2.0);
+        a.divide(0.0).unwrap_err();
+        let mut delta = a.state_delta_since(&VersionVector::new()).unwrap();
+        assert_eq!(delta.ops.len(), 2);
+        delta.ops.remove(0);
+        assert!(b.apply_delta(&delta).is_err());
+
+        let mut delta = a.state_delta_since(&VersionVector::new()).unwrap();
+        delta.ops[1].op = "launch".to_string();
+        assert!(b.apply_delta(&delta).is_err());
+        // All-or-nothing.
+        assert_eq!(b.get_value(), 0.0);
+        assert!(b.version_vector().is_empty());
+
+        let mut plain = Calculator::new();
+        assert!(plain.state_delta_since(&VersionVector::new()).is_err());
+        assert!(plain.apply_delta(&delta).is_err());
+        assert!(a.set_replica_id("b").is_err());
+        assert!(a.set_replica_id(" a ").is_ok());
+        plain.set_incognito(true);
+        assert!(plain.set_replica_id("a").is_err());
+        a.set_incognito(true);
+        assert_eq!(a.replica_id(), None);
+    }
+}
@@ -0,0 +1,804 @@
+//! Calculation tapes: importing history from other calculator apps,
+//! printing it back out as an adding-machine tape, and exporting it as CSV
+//! or plain text for saving.
+//!
+//! Two formats are understood. [`TapeFormat::Text`] is the printed
+//! adding-machine style, one entry per line:
+//!
+//! ```text
+//!     10.00 +
+//!      5.00 +
+//!      3.00 -
+//!  ---------
+//!     12.00 T
+//! ```
+//!
+//! Lines may also be written as `op amount`, as a bare amount (treated as
+//! `+`), or as a full `10 + 5 = 15` equation. Total lines (`= 12`, `12 T`,
+//! `T 12`) and separator rules carry no operation and are skipped.
+//!
+//! [`TapeFormat::Csv`] has one entry per row. A header row naming the
+//! `operation` and `operand` (or `operand2`) columns, plus an optional
+//! `operand1` column for the starting value, selects columns by name;
+//! without a header the first two columns are taken as operation, operand.
+
+use serde::Deserialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, CalculationHistory, Calculator, Operation};
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeFormat {
+    Text,
+    Csv,
+}
+
+/// One decoded tape line: the operation and its operand, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapeEntry {
+    pub operation: Operation,
+    pub operand: Option<f64>,
+}
+
+/// A decoded tape: the value it starts from and the entries to apply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tape {
+    pub start: f64,
+    pub entries: Vec<TapeEntry>,
+}
+
+// Character offset of each line start, for error positions.
+fn lines_with_offsets(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut offset = 0;
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let start = offset;
+            offset += line.chars().count() + 1;
+            (i + 1, start, line.trim_end_matches('\r'))
+        })
+        .collect()
+}
+
+fn line_error(line: usize, position: usize, message: impl std::fmt::Display) -> CalcError {
+    CalcError::Syntax {
+        message: format!("Line {}: {}", line, message),
+        position,
+    }
+}
+
+fn symbol_operation(symbol: &str) -> Option<Operation> {
+    match symbol {
+        "+" => Some(Operation::Add),
+        "-" | "\u{2212}" => Some(Operation::Subtract),
+        "*" | "\u{d7}" | "x" | "X" => Some(Operation::Multiply),
+        "/" | "\u{f7}" => Some(Operation::Divide),
+        _ => None,
+    }
+}
+
+/// Parses an operation written as a symbol or by name (`Add`, `memory_add`,
+/// `MemoryAdd` are all accepted).
+pub(crate) fn parse_operation(name: &str) -> Option<Operation> {
+    if let Some(op) = symbol_operation(name) {
+        return Some(op);
+    }
+    // `constant:pi`, as written by `export_history_csv`.
+    if let Some(constant) = name.strip_prefix("constant:") {
+        let constant = crate::constants::lookup(constant).ok()?;
+        return Some(Operation::Constant(constant.name.to_string()));
+    }
+    if let Some(function) = name.strip_prefix("function:") {
+        return Some(Operation::Function(function.to_string()));
+    }
+    let key: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    let op = match key.as_str() {
+        "add" => Operation::Add,
+        "subtract" | "sub" => Operation::Subtract,
+        "multiply" | "mul" => Operation::Multiply,
+        "divide" | "div" => Operation::Divide,
+        "sqrt" => Operation::Sqrt,
+        "power" | "pow" => Operation::Power,
+        "setvalue" | "set" => Operation::SetValue,
+        "clear" => Operation::Clear,
+        "memorystore" | "ms" => Operation::MemoryStore,
+        "memoryrecall" | "mr" => Operation::MemoryRecall,
+        "memoryclear" | "mc" => Operation::MemoryClear,
+        "memoryadd" => Operation::MemoryAdd,
+        "percentof" | "percent" => Operation::PercentOf,
+        "addpercent" => Operation::AddPercent,
+        "subtractpercent" => Operation::SubtractPercent,
+        "round" | "roundto" => Operation::Round,
+        "nthroot" | "root" => Operation::NthRoot,
+        "modulo" | "mod" => Operation::Modulo,
+        "reciprocal" | "inv" => Operation::Reciprocal,
+        "abs" => Operation::Abs,
+        "negate" | "neg" => Operation::Negate,
+        _ => return None,
+    };
+    Some(op)
+}
+
+// Amounts may carry thousands separators in text tapes: `1,234.50`.
+fn parse_amount(text: &str) -> Option<f64> {
+    let cleaned: String = text.chars().filter(|&c| c != ',').collect();
+    cleaned.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+fn is_total_marker(token: &str) -> bool {
+    matches!(token, "=" | "T" | "t" | "S" | "s")
+}
+
+/// Decodes a printed adding-machine tape.
+pub fn parse_text_tape(text: &str) -> Result<Tape, CalcError> {
+    let mut tape = Tape {
+        start: 0.0,
+        entries: Vec::new(),
+    };
+    for (line_no, offset, raw) in lines_with_offsets(text) {
+        let line = raw.trim();
+        if line.is_empty() || line.chars().all(|c| c == '-' || c == '=' || c == '_') {
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        // `10 + 5 = 15`: an equation line; the first one fixes the start.
+        if let Some(eq) = tokens.iter().position(|t| *t == "=") {
+            if eq == 3 {
+                let (a, op, b) = (tokens[0], tokens[1], tokens[2]);
+                let (Some(a), Some(op), Some(b)) =
+                    (parse_amount(a), symbol_operation(op), parse_amount(b))
+                else {
+                    return Err(line_error(
+                        line_no,
+                        offset,
+                        format!("unrecognized equation '{}'", line),
+                    ));
+                };
+                if tape.entries.is_empty() {
+                    tape.start = a;
+                }
+                tape.entries.push(TapeEntry {
+                    operation: op,
+                    operand: Some(b),
+                });
+                continue;
+            }
+        }
+
+        match tokens.as_slice() {
+            [amount] => {
+                let Some(v) = parse_amount(amount) else {
+                    return Err(line_error(
+                        line_no,
+                        offset,
+                        format!("unrecognized tape entry '{}'", line),
+                    ));
+                };
+                tape.entries.push(TapeEntry {
+                    operation: Operation::Add,
+                    operand: Some(v),
+                });
+            }
+            [a, b] if is_total_marker(a) || is_total_marker(b) => {
+                let amount = if is_total_marker(a) { b } else { a };
+                if parse_amount(amount).is_none() {
+                    return Err(line_error(
+                        line_no,
+                        offset,
+                        format!("unrecognized total '{}'", line),
+                    ));
+                }
+            }
+            [a, b] => {
+                let entry = match (
+                    parse_amount(a),
+                    symbol_operation(b),
+                    symbol_operation(a),
+                    parse_amount(b),
+                ) {
+                    (Some(v), Some(op), _, _) | (_, _, Some(op), Some(v)) => TapeEntry {
+                        operation: op,
+                        operand: Some(v),
+                    },
+                    _ => {
+                        return Err(line_error(
+                            line_no,
+                            offset,
+                            format!("unrecognized tape entry '{}'", line),
+                        ));
+                    }
+                };
+                tape.entries.push(entry);
+            }
+            _ => {
+                return Err(line_error(
+                    line_no,
+                    offset,
+                    format!("unrecognized tape entry '{}'", line),
+                ))
+            }
+        }
+    }
+    Ok(tape)
+}
+
+/// Decodes a CSV tape; see the module docs for the accepted layouts.
+pub fn parse_csv_tape(text: &str) -> Result<Tape, CalcError> {
+    let mut tape = Tape {
+        start: 0.0,
+        entries: Vec::new(),
+    };
+    let mut op_col = 0;
+    let mut operand_col = 1;
+    let mut start_col = None;
+    let mut first_row = true;
+    for (line_no, offset, raw) in lines_with_offsets(text) {
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = raw.split(',').map(|f| f.trim().trim_matches('"')).collect();
+        let header = first_row;
+        first_row = false;
+        if header && fields.iter().any(|f| f.eq_ignore_ascii_case("operation")) {
+            let find = |name: &str| fields.iter().position(|f| f.eq_ignore_ascii_case(name));
+            op_col = find("operation").unwrap_or(0);
+            let Some(col) = find("operand").or_else(|| find("operand2")) else {
+                return Err(line_error(line_no, offset, "header has no operand column"));
+            };
+            operand_col = col;
+            start_col = find("operand1");
+            continue;
+        }
+        let Some(op) = fields.get(op_col).and_then(|f| parse_operation(f)) else {
+            return Err(line_error(
+                line_no,
+                offset,
+                format!("unrecognized operation in '{}'", raw),
+            ));
+        };
+        let operand = match fields.get(operand_col).copied() {
+            None | Some("") => None,
+            Some(field) => match field.parse::<f64>() {
+                Ok(v) => Some(v),
+                Err(_) => {
+                    return Err(line_error(
+                        line_no,
+                        offset,
+                        format!("invalid operand '{}'", field),
+                    ))
+                }
+            },
+        };
+        if tape.entries.is_empty() {
+            if let Some(v) = start_col
+                .and_then(|c| fields.get(c))
+                .and_then(|f| f.parse::<f64>().ok())
+            {
+                tape.start = v;
+            }
+        }
+        tape.entries.push(TapeEntry {
+            operation: op,
+            operand,
+        });
+    }
+    Ok(tape)
+}
+
+impl Calculator {
+    /// Replays a tape from another app into this calculator's history.
+    ///
+    /// The tape's starting value is entered with `set_value`, then every
+    /// entry is applied as if typed, so imported entries are validated and
+    /// recorded exactly like live ones. The import is all-or-nothing: if any
+    /// line fails to parse or apply, the calculator is left unchanged.
+    /// Returns the number of steps applied, the starting `set_value`
+    /// included, whether or not the history kept them.
+    pub fn import_tape(&mut self, text: &str, format: TapeFormat) -> Result<usize, CalcError> {
+        let tape = match format {
+            TapeFormat::Text => parse_text_tape(text),
+            TapeFormat::Csv => parse_csv_tape(text),
+        }
+        .map_err(|e| self.fail(e, None))?;
+        let steps = std::iter::once((Operation::SetValue, Some(tape.start)))
+            .chain(tape.entries.into_iter().map(|e| (e.operation, e.operand)));
+        Ok(self.apply_all(steps)?.len())
+    }
+}
+
+/// Layout choices for [`Calculator::format_tape`]. From JavaScript pass a
+/// plain object; missing fields take their defaults.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TapeLayout {
+    /// Digits after the decimal point (default 2).
+    pub decimals: usize,
+    /// Show the running subtotal column (default true).
+    pub subtotals: bool,
+    /// End with a rule and a `T` total line (default true).
+    pub total: bool,
+    /// Group integer digits with commas (default false).
+    pub thousands: bool,
+}
+
+impl Default for TapeLayout {
+    fn default() -> Self {
+        TapeLayout {
+            decimals: 2,
+            subtotals: true,
+            total: true,
+            thousands: false,
+        }
+    }
+}
+
+const MIN_TAPE_WIDTH: usize = 12;
+
+fn tape_symbol(operation: &Operation) -> &'static str {
+    match operation {
+        Operation::Add => "+",
+        Operation::Subtract => "-",
+        Operation::Multiply => "*",
+        Operation::Divide => "/",
+        Operation::Power => "^",
+        Operation::Sqrt => "\u{221a}",
+        Operation::SetValue => "",
+        Operation::Clear => "C",
+        Operation::MemoryStore => "MS",
+        Operation::MemoryRecall => "MR",
+        Operation::MemoryClear => "MC",
+        Operation::MemoryAdd => "M+",
+        Operation::PercentOf => "%",
+        Operation::AddPercent => "+%",
+        Operation::SubtractPercent => "-%",
+        Operation::Constant(_) => "K",
+        Operation::Round => "R",
+        Operation::NthRoot => "x\u{221a}",
+        Operation::Modulo => "\u{f7}R",
+        Operation::Reciprocal => "1/",
+        Operation::Abs => "||",
+        Operation::Negate => "\u{b1}",
+        Operation::Function(_) => "F",
+    }
+}
+
+// The amount printed for an entry: the explicit argument, or for unary and
+// memory operations whatever number the key acted on.
+fn tape_amount(entry: &CalculationHistory) -> Option<f64> {
+    match entry.operation {
+        Operation::Clear | Operation::MemoryClear => None,
+        Operation::Sqrt
+        | Operation::MemoryStore
+        | Operation::Round
+        | Operation::Reciprocal
+        | Operation::Abs
+        | Operation::Negate
+        | Operation::Function(_) => Some(entry.operand1),
+        Operation::MemoryRecall => Some(entry.result),
+        _ => entry.operand2,
+    }
+}
+
+fn tape_number(value: f64, layout: &TapeLayout) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let mut text = format!("{:.*}", layout.decimals, value);
+    if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+        text.remove(0);
+    }
+    if !layout.thousands {
+        return text;
+    }
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text.as_str()),
+    };
+    let (int, frac) = rest.split_at(rest.find('.').unwrap_or(rest.len()));
+    let mut grouped = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("{}{}{}", sign, grouped, frac)
+}
+
+// Right-aligns `text` in `width` columns, or fills with '#' if it cannot fit.
+fn tape_cell(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len > width {
+        "#".repeat(width)
+    } else {
+        format!("{}{}", " ".repeat(width - len), text)
+    }
+}
+
+impl Calculator {
+    /// Prints the history as a fixed-width adding-machine tape: each line is
+    /// `width` columns of right-aligned amount, a two-column operator, and
+    /// (optionally) the running subtotal. Numbers too wide for their column
+    /// are shown as `#`. With subtotals off and only `+ - * /` entries, the
+    /// output reads back through [`TapeFormat::Text`].
+    pub fn format_tape(&self, width: usize, layout: &TapeLayout) -> Result<String, CalcError> {
+        if width < MIN_TAPE_WIDTH {
+            return Err(CalcError::InvalidArgument(format!(
+                "Tape width must be at least {} columns, got {}",
+                MIN_TAPE_WIDTH, width
+            )));
+        }
+        // amount + ' ' + op(2) [+ ' ' + subtotal]
+        let (amount_width, subtotal_width) = if layout.subtotals {
+            let columns = width - 4;
+            (columns - columns / 2, columns / 2)
+        } else {
+            (width - 3, 0)
+        };
+
+        let mut lines = Vec::new();
+        let mut running: Option<f64> = None;
+        for entry in &self.history {
+            if !entry.operation.affects_memory() {
+                running = Some(entry.result);
+            }
+            let amount = tape_amount(entry)
+                .map(|a| tape_number(a, layout))
+                .unwrap_or_default();
+            let mut line = format!(
+                "{} {:<2}",
+                tape_cell(&amount, amount_width),
+                tape_symbol(&entry.operation)
+            );
+            if layout.subtotals {
+                let subtotal = running.map(|r| tape_number(r, layout)).unwrap_or_default();
+                line.push(' ');
+                line.push_str(&tape_cell(&subtotal, subtotal_width));
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        if layout.total {
+            lines.push("-".repeat(width));
+            let total = tape_number(running.unwrap_or(self.current_value), layout);
+            lines.push(
+                format!("{} T", tape_cell(&total, width - 3))
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+// The name `parse_operation` reads back.
+pub(crate) fn operation_name(operation: &Operation) -> String {
+    match operation {
+        Operation::Constant(name) => format!("constant:{}", name),
+        Operation::Function(name) => format!("function:{}", name),
+        other => other.id().to_string(),
+    }
+}
+
+// One history entry as an equation: `10 + 5 = 15`.
+fn entry_text(entry: &CalculationHistory) -> String {
+    let (a, r) = (entry.operand1, entry.result);
+    // Only read for operations that always record an operand.
+    let b = entry.operand2.unwrap_or(f64::NAN);
+    match &entry.operation {
+        Operation::Add
+        | Operation::Subtract
+        | Operation::Multiply
+        | Operation::Divide
+        | Operation::Power => {
+            format!("{} {} {} = {}", a, tape_symbol(&entry.operation), b, r)
+        }
+        Operation::AddPercent => format!("{} + {}% = {}", a, b, r),
+        Operation::SubtractPercent => format!("{} - {}% = {}", a, b, r),
+        Operation::PercentOf => format!("{} * {}% = {}", a, b, r),
+        Operation::Sqrt => format!("\u{221a}{} = {}", a, r),
+        Operation::Round => format!("round({}, {}) = {}", a, b, r),
+        Operation::NthRoot => format!("root({}, {}) = {}", a, b, r),
+        Operation::Modulo => format!("{} mod {} = {}", a, b, r),
+        Operation::Reciprocal => format!("1/{} = {}", a, r),
+        Operation::Abs => format!("|{}| = {}", a, r),
+        Operation::Negate => format!("-({}) = {}", a, r),
+        Operation::Function(name) => format!("{}({}) = {}", name, a, r),
+        Operation::Constant(name) => format!("{} = {}", name, r),
+        Operation::SetValue => format!("= {}", r),
+        Operation::Clear => "C".to_string(),
+        Operation::MemoryStore => format!("MS {}", r),
+        Operation::MemoryRecall => format!("MR {}", r),
+        Operation::MemoryClear => "MC".to_string(),
+        Operation::MemoryAdd => format!("M+ {} = {}", b, r),
+    }
+}
+
+// `entries` as `export_history_csv` writes them.
+pub(crate) fn history_csv<'a>(entries: impl IntoIterator<Item = &'a CalculationHistory>) -> String {
+    let mut out = String::from("operation,operand1,operand2,result,timestamp\n");
+    for entry in entries {
+        let operand2 = entry.operand2.map(|v| v.to_string()).unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            operation_name(&entry.operation),
+            entry.operand1,
+            operand2,
+            entry.result,
+            entry.timestamp
+        ));
+    }
+    out
+}
+
+// `entries` as `export_history_text` writes them.
+pub(crate) fn history_text<'a>(
+    entries: impl IntoIterator<Item = &'a CalculationHistory>,
+) -> String {
+    entries
+        .into_iter()
+        .map(|entry| entry_text(entry) + "\n")
+        .collect()
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// The history as CSV with an
+    /// `operation,operand1,operand2,result,timestamp` header, one row per
+    /// entry. It reads back through `import_tape` with [`TapeFormat::Csv`].
+    pub fn export_history_csv(&self) -> String {
+        history_csv(&self.history)
+    }
+
+    /// The history as one equation per line, e.g. `10 + 5 = 15`, for
+    /// saving or printing.
+    pub fn export_history_text(&self) -> String {
+        history_text(&self.history)
+    }
+
+    /// `options` is an optional `{ decimals, subtotals, total, thousands }`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = format_tape)]
+    pub fn format_tape_js(&self, width: usize, options: JsValue) -> Result<String, CalcErrorJs> {
+        let layout: TapeLayout = if options.is_undefined() || options.is_null() {
+            TapeLayout::default()
+        } else {
+            serde_wasm_bindgen::from_value(options).map_err(|e| {
+                CalcErrorJs::from(CalcError::InvalidArgument(format!(
+                    "Invalid tape options: {}",
+                    e
+                )))
+            })?
+        };
+        self.format_tape(width, &layout)
+            .map_err(|e| CalcErrorJs::new(e, Some(width as f64)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = import_tape)]
+    pub fn import_tape_js(&mut self, text: &str, format: TapeFormat) -> Result<usize, CalcErrorJs> {
+        self.import_tape(text, format).map_err(CalcErrorJs::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Calculator {
+        let mut calc = Calculator::new();
+        calc.add(10.0);
+        calc.add(1234.5);
+        calc.subtract(4.5);
+        calc.divide(4.0).unwrap();
+        calc
+    }
+
+    #[test]
+    fn test_format_tape_columns() {
+        let calc = session();
+        let tape = calc.format_tape(24, &TapeLayout::default()).unwrap();
+        let expected = [
+            "     10.00 +       10.00",
+            "   1234.50 +     1244.50",
+            "      4.50 -     1240.00",
+            "      4.00 /      310.00",
+            "------------------------",
+            "               310.00 T",
+        ]
+        .join("\n");
+        assert_eq!(tape, expected);
+        assert!(tape.lines().all(|l| l.chars().count() <= 24));
+    }
+
+    #[test]
+    fn test_format_tape_options() {
+        let mut calc = session();
+        calc.memory_store();
+        calc.sqrt().unwrap();
+        let layout = TapeLayout {
+            decimals: 0,
+            thousands: true,
+            total: false,
+            ..TapeLayout::default()
+        };
+        let tape = calc.format_tape(20, &layout).unwrap();
+        let lines: Vec<&str> = tape.lines().collect();
+        assert_eq!(lines[1], "   1,234 +     1,244");
+        assert_eq!(lines[4], "     310 MS      310");
+        assert_eq!(lines[5], "     310 \u{221a}        18");
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn test_format_tape_overflow_and_width() {
+        let mut calc = Calculator::new();
+        calc.add(1e15);
+        let tape = calc.format_tape(12, &TapeLayout::default()).unwrap();
+        assert_eq!(tape.lines().next(), Some("#### +  ####"));
+        assert!(matches!(
+            calc.format_tape(11, &TapeLayout::default()),
+            Err(CalcError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_tape_reads_back() {
+        let calc = session();
+        let layout = TapeLayout {
+            subtotals: false,
+            ..TapeLayout::default()
+        };
+        let mut replay = Calculator::new();
+        replay
+            .import_tape(&calc.format_tape(16, &layout).unwrap(), TapeFormat::Text)
+            .unwrap();
+        assert_eq!(replay.get_value(), calc.get_value());
+    }
+
+    #[test]
+    fn test_text_tape_adding_machine_layout() {
+        let mut calc = Calculator::new();
+        let text = "   10.00 +\n    5.00 +\n    3.00 -\n ---------\n   12.00 T\n";
+        let added = calc.import_tape(text, TapeFormat::Text).unwrap();
+        assert_eq!(added, 4); // set_value + three entries
+        assert_eq!(calc.get_value(), 12.0);
+        assert_eq!(calc.check_invariants(), Ok(()));
+
+        // The count is of steps, not of entries the history kept.
+        calc.set_history_limit(2);
+        assert_eq!(calc.import_tape(text, TapeFormat::Text), Ok(4));
+        calc.set_incognito(true);
+        assert_eq!(calc.import_tape(text, TapeFormat::Text), Ok(4));
+        assert_eq!(calc.history_count(), 0);
+    }
+
+    #[test]
+    fn test_text_tape_mixed_line_styles() {
+        let tape = parse_text_tape("10 + 5 = 15\n\u{d7} 2\n1,000\n\u{f7} 4\n= 257.5").unwrap();
+        assert_eq!(tape.start, 10.0);
+        let ops: Vec<Operation> = tape.entries.iter().map(|e| e.operation.clone()).collect();
+        assert_eq!(
+            ops,
+            vec![
+                Operation::Add,
+                Operation::Multiply,
+                Operation::Add,
+                Operation::Divide
+            ]
+        );
+        assert_eq!(tape.entries[2].operand, Some(1000.0));
+    }
+
+    #[test]
+    fn test_text_tape_reports_bad_line() {
+        let err = parse_text_tape("10 +\nbanana\n").unwrap_err();
+        assert_eq!(
+            err,
+            CalcError::Syntax {
+                message: "Line 2: unrecognized tape entry 'banana'".to_string(),
+                position: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_csv_tape_with_header() {
+        let mut calc = Calculator::new();
+        let csv = "timestamp,operation,operand1,operand2,result\n\
+                   1,Add,100,50,150\n\
+                   2,Sqrt,150,,12.247\n\
+                   3,MemoryStore,12.247,,12.247\n";
+        calc.import_tape(csv, TapeFormat::Csv).unwrap();
+        assert!((calc.get_value() - 150f64.sqrt()).abs() < 1e-12);
+        assert_eq!(calc.get_memory(), calc.get_value());
+        assert_eq!(calc.history_count(), 4);
+    }
+
+    #[test]
+    fn test_csv_tape_without_header() {
+        let tape = parse_csv_tape("add,5\nmultiply,3\n").unwrap();
+        assert_eq!(tape.start, 0.0);
+        assert_eq!(tape.entries.len(), 2);
+        assert_eq!(tape.entries[1].operation, Operation::Multiply);
+    }
+
+    #[test]
+    fn test_import_is_all_or_nothing() {
+        let mut calc = Calculator::new();
+        calc.add(1.0);
+        let err = calc
+            .import_tape("5 +\n0 /\n", TapeFormat::Text)
+            .unwrap_err();
+        assert_eq!(err, CalcError::DivisionByZero);
+        assert_eq!(calc.get_value(), 1.0);
+        assert_eq!(calc.history_count(), 1);
+    }
+
+    #[test]
+    fn test_export_history_text() {
+        let mut calc = session();
+        calc.sqrt().unwrap();
+        calc.memory_store();
+        calc.add_percent(10.0);
+        calc.insert_constant("pi").unwrap();
+        calc.clear();
+        assert_eq!(
+            calc.export_history_text(),
+            [
+                "0 + 10 = 10",
+                "10 + 1234.5 = 1244.5",
+                "1244.5 - 4.5 = 1240",
+                "1240 / 4 = 310",
+                "\u{221a}310 = 17.60681686165901",
+                "MS 17.60681686165901",
+                "17.60681686165901 + 10% = 19.36749854782491",
+                "pi = 3.141592653589793",
+                "C",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(Calculator::new().export_history_text(), "");
+    }
+
+    #[test]
+    fn test_export_history_csv_reads_back() {
+        let mut calc = session();
+        calc.memory_store();
+        calc.power(2.0);
+        calc.insert_constant("e").unwrap();
+        calc.subtract_percent(50.0);
+        let csv = calc.export_history_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("operation,operand1,operand2,result,timestamp")
+        );
+        assert!(lines.next().unwrap().starts_with("add,0,10,10,"));
+        assert!(csv.contains("\nmemory_store,310,,310,"));
+        assert!(csv.contains("\nconstant:e,96100,2.718281828459045,"));
+
+        let mut copy = Calculator::new();
+        copy.import_tape(&csv, TapeFormat::Csv).unwrap();
+        assert_eq!(copy.get_value(), calc.get_value());
+        assert_eq!(copy.get_memory(), calc.get_memory());
+        assert_eq!(copy.history_count(), calc.history_count() + 1);
+    }
+
+    #[test]
+    fn test_parse_operation_names() {
+        assert_eq!(parse_operation("memory_add"), Some(Operation::MemoryAdd));
+        assert_eq!(parse_operation("SetValue"), Some(Operation::SetValue));
+        assert_eq!(parse_operation("\u{2212}"), Some(Operation::Subtract));
+        assert_eq!(parse_operation("frobnicate"), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Income tax across progressive brackets.
+//!
+//! A bracket taxes the part of the income above its `threshold`, up to the
+//! next bracket's threshold, at its `rate` percent. Income below the first
+//! threshold is untaxed.
+//!
+//! The math is exact in whole cents: amounts must be whole cents (see
+//! [`crate::currency::to_cents`]), rates are read as the decimals they
+//! print as (`22.5` is exactly 22.5%), and each bracket's tax is rounded
+//! half to even to the cent once. The total is the sum of the bracket
+//! taxes, so the breakdown always adds up.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::currency::to_cents;
+use crate::money::{decimal, round_scaled};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaxBracket {
+    /// Income above this is taxed at `rate`, up to the next threshold.
+    pub threshold: f64,
+    /// In percent.
+    pub rate: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BracketTax {
+    pub threshold: f64,
+    pub rate: f64,
+    /// The part of the income taxed in this bracket.
+    pub taxable: f64,
+    pub tax: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IncomeTax {
+    pub income: f64,
+    pub tax: f64,
+    /// The rate (percent) on the last dollar: that of the bracket the
+    /// income ends in, or 0 below the first threshold.
+    pub marginal_rate: f64,
+    /// The tax as a percentage of the income (0 for no income).
+    pub effective_rate: f64,
+    /// Every bracket, in order, including those the income does not reach.
+    pub brackets: Vec<BracketTax>,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn check_cents(what: &str, amount: f64) -> Result<i64, CalcError> {
+    let cents = to_cents(amount)?;
+    if cents < 0 {
+        return Err(invalid(format!(
+            "{} must not be negative, got {}",
+            what, amount
+        )));
+    }
+    Ok(cents)
+}
+
+// `rate` percent as `digits / 10^scale` of a whole.
+fn check_rate(rate: f64) -> Result<(i128, u32), CalcError> {
+    if !rate.is_finite() || !(0.0..=100.0).contains(&rate) {
+        return Err(invalid(format!(
+            "Bracket rates must be 0-100%, got {}",
+            rate
+        )));
+    }
+    let (digits, scale) = decimal(&rate.to_string())?;
+    Ok((digits, scale + 2))
+}
+
+fn dollars(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// Tax on `income` across `brackets`, which must have increasing
+/// thresholds.
+pub fn income_tax(income: f64, brackets: &[TaxBracket]) -> Result<IncomeTax, CalcError> {
+    let income_cents = check_cents("Income", income)?;
+    if brackets.is_empty() {
+        return Err(invalid("At least one bracket is needed"));
+    }
+    let thresholds = brackets
+        .iter()
+        .map(|b| check_cents("Bracket thresholds", b.threshold))
+        .collect::<Result<Vec<i64>, CalcError>>()?;
+    if let Some(pair) = thresholds.windows(2).find(|pair| pair[0] >= pair[1]) {
+        return Err(invalid(format!(
+            "Bracket thresholds must increase, got {} then {}",
+            dollars(pair[0]),
+            dollars(pair[1])
+        )));
+    }
+
+    let mut rows = Vec::with_capacity(brackets.len());
+    let mut total = 0i64;
+    let mut marginal_rate = 0.0;
+    for (i, bracket) in brackets.iter().enumerate() {
+        let (digits, scale) = check_rate(bracket.rate)?;
+        let top = thresholds.get(i + 1).copied().unwrap_or(i64::MAX);
+        let taxable = income_cents.min(top) - thresholds[i];
+        let taxable = taxable.max(0);
+        let tax = round_scaled(taxable as i128 * digits, scale) as i64;
+        if income_cents > thresholds[i] {
+            marginal_rate = bracket.rate;
+        }
+        total += tax;
+        rows.push(BracketTax {
+            threshold: bracket.threshold,
+            rate: bracket.rate,
+            taxable: dollars(taxable),
+            tax: dollars(tax),
+        });
+    }
+    Ok(IncomeTax {
+        income: dollars(income_cents),
+        tax: dollars(total),
+        marginal_rate,
+        effective_rate: if income_cents == 0 {
+            0.0
+        } else {
+            total as f64 / income_cents as f64 * 100.0
+        },
+        brackets: rows,
+    })
+}
+
+/// `brackets` is an array of `{ threshold, rate }`; returns `{ income,
+/// tax, marginal_rate, effective_rate, brackets: [{ threshold, rate,
+/// taxable, tax }] }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = income_tax)]
+pub fn income_tax_js(income: f64, brackets: JsValue) -> Result<JsValue, CalcErrorJs> {
+    let brackets: Vec<TaxBracket> = serde_wasm_bindgen::from_value(brackets)
+        .map_err(|e| CalcErrorJs::from(invalid(format!("Invalid brackets: {}", e))))?;
+    let tax = income_tax(income, &brackets).map_err(|e| CalcErrorJs::new(e, Some(income)))?;
+    Ok(serde_wasm_bindgen::to_value(&tax).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // US federal brackets for a single filer, 2023, up to 35%.
+    fn brackets() -> Vec<TaxBracket> {
+        [
+            (0.0, 10.0),
+            (11_000.0, 12.0),
+            (44_725.0, 22.0),
+            (95_375.0, 24.0),
+            (182_100.0, 32.0),
+            (231_250.0, 35.0),
+        ]
+        .into_iter()
+        .map(|(threshold, rate)| TaxBracket { threshold, rate })
+        .collect()
+    }
+
+    #[test]
+    fn test_income_tax() {
+        let tax = income_tax(100_000.0, &brackets()).unwrap();
+        // 1100 + 4047 + 11143 + 1110
+        assert_eq!(tax.tax, 17_400.0);
+        assert_eq!(tax.marginal_rate, 24.0);
+        assert!((tax.effective_rate - 17.4).abs() < 1e-12);
+        let taxable: Vec<f64> = tax.brackets.iter().map(|b| b.taxable).collect();
+        assert_eq!(
+            taxable,
+            vec![11_000.0, 33_725.0, 50_650.0, 4_625.0, 0.0, 0.0]
+        );
+        let sum: f64 = tax.brackets.iter().map(|b| b.tax).sum();
+        assert_eq!(sum, tax.tax);
+
+        // Exactly on a threshold is still the lower bracket.
+        assert_eq!(
+            income_tax(11_000.0, &brackets()).unwrap().marginal_rate,
+            10.0
+        );
+        let none = income_tax(0.0, &brackets()).unwrap();
+        assert_eq!(
+            (none.tax, none.marginal_rate, none.effective_rate),
+            (0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_cent_rounding() {
+        let brackets = [
+            TaxBracket {
+                threshold: 100.0,
+                rate: 12.5,
+            },
+            TaxBracket {
+                threshold: 200.0,
+                rate: 33.3,
+            },
+        ];
+        // 5 cents at 33.3% is 1.665 cents, rounded to 2.
+        let tax = income_tax(200.05, &brackets).unwrap();
+        assert_eq!(tax.brackets[0].tax, 12.5);
+        assert_eq!(tax.brackets[1].tax, 0.02);
+        assert_eq!(tax.tax, 12.52);
+        assert_eq!(income_tax(100.01, &brackets).unwrap().tax, 0.0);
+        assert_eq!(income_tax(100.03, &brackets).unwrap().tax, 0.0);
+        // 4 cents at 12.5% is half a cent, rounded to even; 12 cents is
+        // 1.5, rounded to 2.
+        assert_eq!(income_tax(100.04, &brackets).unwrap().tax, 0.0);
+        assert_eq!(income_tax(100.12, &brackets).unwrap().tax, 0.02);
+        assert_eq!(income_tax(50.0, &brackets).unwrap().marginal_rate, 0.0);
+    }
+
+    #[test]
+    fn test_invalid_brackets() {
+        assert!(income_tax(100.0, &[]).is_err());
+        assert!(income_tax(-1.0, &brackets()).is_err());
+        assert!(income_tax(100.001, &brackets()).is_err());
+        let mut unordered = brackets();
+        unordered.swap(1, 2);
+        assert!(income_tax(100.0, &unordered).is_err());
+        let mut bad_rate = brackets();
+        bad_rate[0].rate = 120.0;
+        assert!(income_tax(100.0, &bad_rate).is_err());
+    }
+}
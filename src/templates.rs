@@ -0,0 +1,336 @@
+//! Named calculations with placeholders, for form-driven calculators.
+//!
+//! A template is an expression whose inputs are written as placeholders in
+//! braces, each with an optional description after a colon:
+//!
+//! ```text
+//! {principal: Loan amount} * {rate: Monthly rate} / (1 - (1 + {rate})^-{months: Number of payments})
+//! ```
+//!
+//! `save_template` parses it once and returns the placeholders, in order of
+//! first appearance, for the app to build an input form from; a
+//! placeholder's description is taken from its first described occurrence.
+//! `run_template` evaluates it with one value per placeholder. Running a
+//! template does not change the current value or the history.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{parse, unknown_identifier, AngleScope, Dialect, Expr, CONSTANTS, FUNCTIONS};
+use crate::locale::localize_input;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// One input of a template.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Placeholder {
+    pub name: String,
+    /// Empty when no occurrence has a description.
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// The text as saved, placeholders and all.
+    pub source: String,
+    pub placeholders: Vec<Placeholder>,
+    expr: Expr,
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// Replaces each `{name: description}` with `name`, collecting the
+// placeholders as it goes.
+fn extract_placeholders(body: &str) -> Result<(String, Vec<Placeholder>), CalcError> {
+    let mut expression = String::new();
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    let mut rest = body;
+    while let Some(open) = rest.find(['{', '}']) {
+        let position = body[..body.len() - rest.len() + open].chars().count();
+        expression.push_str(&rest[..open]);
+        if rest[open..].starts_with('}') {
+            return Err(CalcError::Syntax {
+                message: "Unmatched '}'".to_string(),
+                position,
+            });
+        }
+        let after = &rest[open + 1..];
+        let close = after
+            .find(['{', '}'])
+            .filter(|&i| after[i..].starts_with('}'));
+        let Some(close) = close else {
+            return Err(CalcError::Syntax {
+                message: "Unclosed '{'".to_string(),
+                position,
+            });
+        };
+        let inner = &after[..close];
+        rest = &after[close + 1..];
+        let (name, description) = inner.split_once(':').unwrap_or((inner, ""));
+        let (name, description) = (name.trim(), description.trim());
+        if !is_identifier(name) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Placeholder '{}' must be an identifier",
+                name
+            )));
+        }
+        let builtin = FUNCTIONS.iter().any(|(f, _, _)| *f == name)
+            || CONSTANTS.iter().any(|(c, _)| *c == name);
+        if builtin {
+            return Err(CalcError::InvalidArgument(format!(
+                "'{}' is a built-in name",
+                name
+            )));
+        }
+        match placeholders.iter_mut().find(|p| p.name == name) {
+            Some(existing) if existing.description.is_empty() => {
+                existing.description = description.to_string();
+            }
+            Some(_) => {}
+            None => placeholders.push(Placeholder {
+                name: name.to_string(),
+                description: description.to_string(),
+            }),
+        }
+        // Spaced so `{a}{b}` cannot run together into one name.
+        expression.push_str(&format!(" {} ", name));
+    }
+    expression.push_str(rest);
+    Ok((expression, placeholders))
+}
+
+// The first variable in `expr` that is neither a placeholder nor a constant.
+fn unbound_variable<'a>(expr: &'a Expr, placeholders: &[Placeholder]) -> Option<&'a str> {
+    match expr {
+        Expr::Number(_) => None,
+        Expr::Variable(name) => {
+            let bound = placeholders.iter().any(|p| p.name == *name)
+                || CONSTANTS.iter().any(|(c, _)| c == name);
+            (!bound).then_some(name.as_str())
+        }
+        Expr::Range(start, _) => Some(start.as_str()),
+        Expr::Unary(_, inner) => unbound_variable(inner, placeholders),
+        Expr::Binary(_, lhs, rhs) => {
+            unbound_variable(lhs, placeholders).or_else(|| unbound_variable(rhs, placeholders))
+        }
+        Expr::Call(_, args) => args
+            .iter()
+            .find_map(|arg| unbound_variable(arg, placeholders)),
+    }
+}
+
+impl Calculator {
+    /// Saves (or replaces) the template `name` and returns its
+    /// placeholders. The body is parsed now, in the input locale, and every
+    /// name in it must be a placeholder or a constant.
+    pub fn save_template(&mut self, name: &str, body: &str) -> Result<Vec<Placeholder>, CalcError> {
+        let template = self
+            .parse_template(name, body)
+            .map_err(|e| self.fail(e, None))?;
+        let placeholders = template.placeholders.clone();
+        self.templates.insert(name.to_string(), template);
+        Ok(placeholders)
+    }
+
+    fn parse_template(&self, name: &str, body: &str) -> Result<Template, CalcError> {
+        if !is_identifier(name) {
+            return Err(CalcError::InvalidArgument(format!(
+                "Template name '{}' must be an identifier",
+                name
+            )));
+        }
+        let (expression, placeholders) = extract_placeholders(body)?;
+        let expr = localize_input(&expression, self.decimal_comma())
+            .and_then(|expression| parse(&expression, Dialect::Standard))?;
+        if let Some(unbound) = unbound_variable(&expr, &placeholders) {
+            let known = placeholders
+                .iter()
+                .map(|p| p.name.as_str())
+                .chain(CONSTANTS.iter().map(|(c, _)| *c));
+            return Err(unknown_identifier(unbound, known));
+        }
+        Ok(Template {
+            source: body.to_string(),
+            placeholders,
+            expr,
+        })
+    }
+
+    pub fn template(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    fn known_template(&self, name: &str) -> Result<&Template, CalcError> {
+        self.templates.get(name).ok_or_else(|| {
+            let err = unknown_identifier(name, self.templates.keys().map(String::as_str));
+            self.fail(err, None)
+        })
+    }
+
+    /// Evaluates the template `name` with `values`, which must give every
+    /// placeholder a value and nothing else.
+    pub fn run_template(
+        &self,
+        name: &str,
+        values: &BTreeMap<String, f64>,
+    ) -> Result<f64, CalcError> {
+        let template = self.known_template(name)?;
+        if let Some(missing) = template
+            .placeholders
+            .iter()
+            .find(|p| !values.contains_key(&p.name))
+        {
+            let err = CalcError::InvalidArgument(format!("Missing value for '{}'", missing.name));
+            return Err(self.fail(err, None));
+        }
+        if let Some(extra) = values
+            .keys()
+            .find(|k| !template.placeholders.iter().any(|p| p.name == **k))
+        {
+            let err = CalcError::InvalidArgument(format!(
+                "'{}' is not a placeholder of '{}'",
+                extra, name
+            ));
+            return Err(self.fail(err, values.get(extra).copied()));
+        }
+        let scope = AngleScope {
+            scope: values,
+            unit: self.angle_unit,
+        };
+        template.expr.eval(&scope).map_err(|e| self.fail(e, None))
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    /// Returns the placeholders as `[{ name, description }]`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = save_template)]
+    pub fn save_template_js(&mut self, name: &str, body: &str) -> Result<JsValue, CalcErrorJs> {
+        let placeholders = self.save_template(name, body)?;
+        Ok(serde_wasm_bindgen::to_value(&placeholders).unwrap_or(JsValue::NULL))
+    }
+
+    /// `[{ name, description }]` for the template `name`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = template_placeholders)]
+    pub fn template_placeholders_js(&self, name: &str) -> Result<JsValue, CalcErrorJs> {
+        let template = self.known_template(name)?;
+        Ok(serde_wasm_bindgen::to_value(&template.placeholders).unwrap_or(JsValue::NULL))
+    }
+
+    /// `values` is a plain object mapping placeholder names to numbers,
+    /// e.g. `{ principal: 200000, rate: 0.005, months: 360 }`.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = run_template)]
+    pub fn run_template_js(&self, name: &str, values: JsValue) -> Result<f64, CalcErrorJs> {
+        let values: BTreeMap<String, f64> = if values.is_undefined() || values.is_null() {
+            BTreeMap::new()
+        } else {
+            serde_wasm_bindgen::from_value(values).map_err(|e| {
+                let err = CalcError::InvalidArgument(format!("Invalid template values: {}", e));
+                CalcErrorJs::from(self.fail(err, None))
+            })?
+        };
+        self.run_template(name, &values).map_err(CalcErrorJs::from)
+    }
+
+    /// Removes a template; returns whether it existed.
+    pub fn remove_template(&mut self, name: &str) -> bool {
+        self.templates.remove(name).is_some()
+    }
+
+    /// Names of the saved templates, sorted.
+    pub fn template_names(&self) -> Vec<String> {
+        self.templates.keys().cloned().collect()
+    }
+
+    /// The text a template was saved with, or `undefined`.
+    pub fn template_source(&self, name: &str) -> Option<String> {
+        self.templates.get(name).map(|t| t.source.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MORTGAGE: &str = "{principal: Loan amount} * {rate: Monthly rate} \
+        / (1 - (1 + {rate})^-{months: Number of payments})";
+
+    fn values(pairs: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn test_save_and_run_template() {
+        let mut calc = Calculator::new();
+        let placeholders = calc.save_template("mortgage", MORTGAGE).unwrap();
+        let names: Vec<_> = placeholders.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["principal", "rate", "months"]);
+        assert_eq!(placeholders[2].description, "Number of payments");
+
+        let inputs = values(&[("principal", 200_000.0), ("rate", 0.005), ("months", 360.0)]);
+        let payment = calc.run_template("mortgage", &inputs).unwrap();
+        assert!((payment - 1199.101050).abs() < 1e-6);
+        assert_eq!(calc.history_count(), 0);
+        assert_eq!(calc.template_names(), ["mortgage"]);
+        assert_eq!(calc.template_source("mortgage").as_deref(), Some(MORTGAGE));
+
+        calc.save_template("circle", "pi * {r}^2").unwrap();
+        assert_eq!(
+            calc.template("circle").unwrap().placeholders[0].description,
+            ""
+        );
+        assert_eq!(
+            calc.run_template("circle", &values(&[("r", 2.0)])),
+            Ok(4.0 * std::f64::consts::PI)
+        );
+        assert!(calc.remove_template("circle"));
+        assert!(!calc.remove_template("circle"));
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let mut calc = Calculator::new();
+        assert!(matches!(
+            calc.save_template("t", "{a} * b"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(matches!(
+            calc.save_template("t", "{a * 2"),
+            Err(CalcError::Syntax { position: 0, .. })
+        ));
+        assert!(calc.save_template("t", "{a}}").is_err());
+        assert!(calc.save_template("t", "{2x}").is_err());
+        assert!(calc.save_template("t", "{sqrt}").is_err());
+        assert!(calc.save_template("my template", "{a}").is_err());
+        assert!(calc.template_names().is_empty());
+
+        assert!(matches!(
+            calc.save_template("area", "{width}{height}"),
+            Err(CalcError::Syntax { .. })
+        ));
+        calc.save_template("area", "{width} * {height}").unwrap();
+        assert_eq!(
+            calc.run_template("area", &values(&[("width", 3.0), ("height", 4.0)])),
+            Ok(12.0)
+        );
+        assert!(calc
+            .run_template("area", &values(&[("width", 3.0)]))
+            .is_err());
+        let extra = values(&[("width", 3.0), ("height", 4.0), ("depth", 1.0)]);
+        assert!(calc.run_template("area", &extra).is_err());
+        assert!(matches!(
+            calc.run_template("aera", &extra),
+            Err(CalcError::UnknownIdentifier { suggestions, .. }) if suggestions == ["area"]
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+//! The ideal gas law, `pV = nRT`.
+//!
+//! [`ideal_gas_solve`] takes any three of pressure, volume, amount of
+//! substance and temperature as [`Quantity`] values and solves for the
+//! fourth. Inputs may be in any units of the right dimension (`1 atm`,
+//! `22.4 L`, `25 degC`); temperatures are converted to kelvin with their
+//! offset, so `25 degC` is 298.15 K, and must be above absolute zero. The
+//! result holds all four in SI units: Pa, m^3, mol and K.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::constants::lookup;
+use crate::units::Quantity;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GasVariable {
+    Pressure,
+    Volume,
+    Amount,
+    Temperature,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IdealGas {
+    /// In Pa.
+    pub pressure: f64,
+    /// In m^3.
+    pub volume: f64,
+    /// In mol.
+    pub amount: f64,
+    /// In K.
+    pub temperature: f64,
+    /// The variable that was solved for.
+    pub solved: GasVariable,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+// `quantity` in `unit`, which must come out positive.
+fn positive(what: &str, quantity: &Quantity, unit: &str) -> Result<f64, CalcError> {
+    let value = quantity
+        .convert_to(unit)
+        .map_err(|e| match e {
+            CalcError::InvalidArgument(message) => invalid(format!("{}: {}", what, message)),
+            e => e,
+        })?
+        .value();
+    if !value.is_finite() || value <= 0.0 {
+        let message = if unit == "K" {
+            format!("{} must be above absolute zero, got {}", what, quantity)
+        } else {
+            format!("{} must be positive, got {}", what, quantity)
+        };
+        return Err(invalid(message));
+    }
+    Ok(value)
+}
+
+/// Solves `pV = nRT` for the one variable given as `None`.
+pub fn ideal_gas_solve(
+    pressure: Option<&Quantity>,
+    volume: Option<&Quantity>,
+    amount: Option<&Quantity>,
+    temperature: Option<&Quantity>,
+) -> Result<IdealGas, CalcError> {
+    let given = [pressure, volume, amount, temperature]
+        .iter()
+        .filter(|q| q.is_some())
+        .count();
+    if given != 3 {
+        return Err(invalid(format!(
+            "Give exactly three of pressure, volume, amount and temperature, got {}",
+            given
+        )));
+    }
+    let r = lookup("gas_constant")?.value;
+    let p = pressure
+        .map(|q| positive("Pressure", q, "Pa"))
+        .transpose()?;
+    let v = volume.map(|q| positive("Volume", q, "m^3")).transpose()?;
+    let n = amount.map(|q| positive("Amount", q, "mol")).transpose()?;
+    let t = temperature
+        .map(|q| positive("Temperature", q, "K"))
+        .transpose()?;
+    let (pressure, volume, amount, temperature, solved) = match (p, v, n, t) {
+        (None, Some(v), Some(n), Some(t)) => (n * r * t / v, v, n, t, GasVariable::Pressure),
+        (Some(p), None, Some(n), Some(t)) => (p, n * r * t / p, n, t, GasVariable::Volume),
+        (Some(p), Some(v), None, Some(t)) => (p, v, p * v / (r * t), t, GasVariable::Amount),
+        (Some(p), Some(v), Some(n), None) => (p, v, n, p * v / (n * r), GasVariable::Temperature),
+        _ => unreachable!("exactly three are given"),
+    };
+    Ok(IdealGas {
+        pressure,
+        volume,
+        amount,
+        temperature,
+        solved,
+    })
+}
+
+/// Each argument is a quantity as text (`"1 atm"`, `"22.4 L"`, `"1 mol"`,
+/// `"0 degC"`), with the unknown left `undefined`; returns `{ pressure,
+/// volume, amount, temperature, solved }` in SI units.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = ideal_gas_solve)]
+pub fn ideal_gas_solve_js(
+    pressure: Option<String>,
+    volume: Option<String>,
+    amount: Option<String>,
+    temperature: Option<String>,
+) -> Result<JsValue, CalcErrorJs> {
+    let parse = |text: Option<String>| {
+        text.as_deref()
+            .map(Quantity::parse)
+            .transpose()
+            .map_err(CalcErrorJs::from)
+    };
+    let (p, v, n, t) = (
+        parse(pressure)?,
+        parse(volume)?,
+        parse(amount)?,
+        parse(temperature)?,
+    );
+    let gas = ideal_gas_solve(p.as_ref(), v.as_ref(), n.as_ref(), t.as_ref())
+        .map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&gas).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(text: &str) -> Quantity {
+        Quantity::parse(text).unwrap()
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * b.abs()
+    }
+
+    #[test]
+    fn test_solves_each_variable() {
+        // One mole at STP (0 degC, 1 atm) fills about 22.414 L.
+        let gas = ideal_gas_solve(
+            Some(&q("1 atm")),
+            None,
+            Some(&q("1 mol")),
+            Some(&q("0 degC")),
+        )
+        .unwrap();
+        assert_eq!(gas.solved, GasVariable::Volume);
+        assert!(close(gas.volume, 0.022_414, 1e-4), "{}", gas.volume);
+        assert_eq!(gas.temperature, 273.15);
+
+        let p = ideal_gas_solve(
+            None,
+            Some(&q("22.414 L")),
+            Some(&q("1 mol")),
+            Some(&q("273.15 K")),
+        )
+        .unwrap();
+        assert_eq!(p.solved, GasVariable::Pressure);
+        assert!(close(p.pressure, 101_325.0, 1e-4), "{}", p.pressure);
+
+        let n = ideal_gas_solve(
+            Some(&q("100 kPa")),
+            Some(&q("1 m^3")),
+            None,
+            Some(&q("25 degC")),
+        )
+        .unwrap();
+        assert!(close(n.amount, 100_000.0 / (8.314_462_618 * 298.15), 1e-9));
+
+        let t =
+            ideal_gas_solve(Some(&q("2 bar")), Some(&q("10 L")), Some(&q("1 mol")), None).unwrap();
+        assert_eq!(t.solved, GasVariable::Temperature);
+        assert!(close(t.temperature, 2e5 * 0.01 / 8.314_462_618, 1e-9));
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let (p, v, n, t) = (q("1 atm"), q("1 L"), q("1 mol"), q("300 K"));
+        assert!(ideal_gas_solve(Some(&p), Some(&v), Some(&n), Some(&t)).is_err());
+        assert!(ideal_gas_solve(Some(&p), None, None, Some(&t)).is_err());
+        assert_eq!(
+            ideal_gas_solve(Some(&p), Some(&v), None, Some(&q("-300 degC"))),
+            Err(CalcError::InvalidArgument(
+                "Temperature must be above absolute zero, got -300 degC".to_string()
+            ))
+        );
+        assert!(ideal_gas_solve(Some(&p), Some(&v), None, Some(&q("0 K"))).is_err());
+        assert!(ideal_gas_solve(Some(&q("1 m")), Some(&v), None, Some(&t)).is_err());
+        assert!(ideal_gas_solve(Some(&q("-1 atm")), Some(&v), None, Some(&t)).is_err());
+        assert!(ideal_gas_solve(None, Some(&v), Some(&q("1 kg")), Some(&t)).is_err());
+    }
+}
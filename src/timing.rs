@@ -0,0 +1,173 @@
+//! Optional execution timing.
+//!
+//! Off by default. With `set_timing(true)`, timed calls on this thread are
+//! measured with a monotonic clock (`performance.now()` in the browser) and
+//! added to per-name totals that `timing_summary()` reports:
+//!
+//! - calculator operations made through `apply` (and so batches, tape
+//!   imports, RPC calls and macros), `evaluate` and `call_function`, under
+//!   their operation names (`"add"`, `"evaluate"`, `"function:tip"`);
+//! - the heavier standalone functions: matrix `mul`, `determinant`,
+//!   `inverse` and `solve` (as `"matrix.inverse"` and so on), `find_root`,
+//!   `derivative_at` and `integrate`.
+//!
+//! History entries recorded by a timed calculator call also carry
+//! `duration_ms`, the time from the start of that call to the entry.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::Calculator;
+
+/// Accumulated timings for one name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Timing {
+    pub count: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static TIMINGS: RefCell<BTreeMap<String, Timing>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+pub fn enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Milliseconds from an arbitrary origin, with sub-millisecond resolution
+/// where the platform allows.
+pub(crate) fn precise_now_ms() -> f64 {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown"))]
+    {
+        // `performance` exists in windows and workers alike.
+        let performance = js_sys::Reflect::get(&js_sys::global(), &"performance".into()).ok();
+        let now = performance.as_ref().and_then(|p| {
+            let now = js_sys::Reflect::get(p, &"now".into()).ok()?;
+            let now: &js_sys::Function = now.dyn_ref()?;
+            now.call0(p).ok()?.as_f64()
+        });
+        now.unwrap_or_else(js_sys::Date::now)
+    }
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32", target_os = "unknown")))]
+    {
+        thread_local! {
+            static ORIGIN: std::time::Instant = std::time::Instant::now();
+        }
+        ORIGIN.with(|origin| origin.elapsed().as_secs_f64() * 1000.0)
+    }
+}
+
+pub(crate) fn record(name: &str, elapsed_ms: f64) {
+    TIMINGS.with(|timings| {
+        let mut timings = timings.borrow_mut();
+        let timing = timings.entry(name.to_string()).or_default();
+        timing.count += 1;
+        timing.total_ms += elapsed_ms;
+        timing.max_ms = timing.max_ms.max(elapsed_ms);
+        timing.mean_ms = timing.total_ms / timing.count as f64;
+    });
+}
+
+/// Runs `f`, adding its duration to `name`'s totals when timing is on.
+pub fn time<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return f();
+    }
+    let start = precise_now_ms();
+    let result = f();
+    record(name, precise_now_ms() - start);
+    result
+}
+
+/// The totals so far, by name.
+pub fn timings() -> BTreeMap<String, Timing> {
+    TIMINGS.with(|timings| timings.borrow().clone())
+}
+
+impl Calculator {
+    // Whether this call should time itself: timing is on and no outer call
+    // on this calculator is already being timed.
+    pub(crate) fn starts_timing(&self) -> bool {
+        enabled() && self.op_started.is_none()
+    }
+
+    // Runs `run` as one timed call named `name`; entries it records get
+    // their `duration_ms` from its start.
+    pub(crate) fn timed<T>(&mut self, name: &str, run: impl FnOnce(&mut Calculator) -> T) -> T {
+        let start = precise_now_ms();
+        self.op_started = Some(start);
+        let result = run(self);
+        self.op_started = None;
+        record(name, precise_now_ms() - start);
+        result
+    }
+}
+
+/// Turns timing on or off for this thread. Totals are kept when it is
+/// turned off; see `reset_timings`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn set_timing(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled));
+}
+
+/// `{ [name]: { count, total_ms, mean_ms, max_ms } }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn timing_summary() -> JsValue {
+    serde_wasm_bindgen::to_value(&timings()).unwrap_or(JsValue::NULL)
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn reset_timings() {
+    TIMINGS.with(|timings| timings.borrow_mut().clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Operation;
+
+    #[test]
+    fn test_timing() {
+        let mut calc = Calculator::new();
+        calc.apply(&Operation::Add, Some(1.0)).unwrap();
+        assert!(timings().is_empty());
+        assert_eq!(calc.history_range(0, 1)[0].duration_ms, None);
+
+        set_timing(true);
+        calc.apply(&Operation::Add, Some(1.0)).unwrap();
+        calc.apply(&Operation::Add, Some(1.0)).unwrap();
+        calc.evaluate("ans * 2").unwrap();
+        calc.define_function("half", "x / 2").unwrap();
+        calc.apply(&Operation::Function("half".to_string()), None)
+            .unwrap();
+        #[cfg(feature = "wasm")]
+        crate::matrix::Matrix::identity(2)
+            .unwrap()
+            .inverse_js()
+            .unwrap();
+        set_timing(false);
+
+        let summary = timings();
+        assert_eq!(summary["add"].count, 2);
+        assert!(summary["add"].max_ms >= summary["add"].mean_ms);
+        assert_eq!(summary["evaluate"].count, 1);
+        // The function call inside `apply` is part of the one timed call.
+        assert_eq!(summary["function:half"].count, 1);
+        #[cfg(feature = "wasm")]
+        assert_eq!(summary["matrix.inverse"].count, 1);
+        let history = calc.history_range(1, 4);
+        assert!(history
+            .iter()
+            .all(|e| e.duration_ms.is_some_and(|d| d >= 0.0)));
+        reset_timings();
+        assert!(timings().is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! Download and upload time estimates.
+//!
+//! Link speeds are in megabits per second, decimal as ISPs quote them
+//! (1 Mbps = 1,000,000 bit/s), and sizes in bytes. Protocol overhead (TCP/IP
+//! headers, TLS framing, retransmits) takes `overhead_percent` off the link
+//! speed; 5-10% is typical for a bulk download.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransferTime {
+    pub seconds: f64,
+    /// The link speed left after overhead, in Mbps.
+    pub effective_mbps: f64,
+    /// `seconds` for display: "1 h 2 min 5 s", or "12.5 s" under a minute.
+    pub human: String,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+// Whole days, hours, minutes and seconds, leaving out the zero ones.
+fn human_duration(seconds: f64) -> String {
+    let tenths = (seconds * 10.0).round() / 10.0;
+    if tenths < 60.0 {
+        return format!("{} s", tenths);
+    }
+    let mut rest = seconds.round();
+    let mut parts = Vec::new();
+    for (size, unit) in [(86_400.0, "d"), (3_600.0, "h"), (60.0, "min"), (1.0, "s")] {
+        let count = (rest / size).floor();
+        rest -= count * size;
+        if count > 0.0 {
+            parts.push(format!("{} {}", count, unit));
+        }
+    }
+    parts.join(" ")
+}
+
+/// How long `bytes` takes over a `mbps` link losing `overhead_percent` to
+/// protocol overhead.
+pub fn transfer_time_detailed(
+    bytes: f64,
+    mbps: f64,
+    overhead_percent: f64,
+) -> Result<TransferTime, CalcError> {
+    if !bytes.is_finite() || bytes < 0.0 {
+        return Err(invalid(format!("Size must not be negative, got {}", bytes)));
+    }
+    if !mbps.is_finite() || mbps <= 0.0 {
+        return Err(invalid(format!("Speed must be positive, got {}", mbps)));
+    }
+    if !overhead_percent.is_finite() || !(0.0..100.0).contains(&overhead_percent) {
+        return Err(invalid(format!(
+            "Overhead must be at least 0 and under 100%, got {}",
+            overhead_percent
+        )));
+    }
+    let effective_mbps = mbps * (1.0 - overhead_percent / 100.0);
+    let seconds = bytes * 8.0 / (effective_mbps * 1e6);
+    Ok(TransferTime {
+        seconds,
+        effective_mbps,
+        human: human_duration(seconds),
+    })
+}
+
+/// `{ seconds, effective_mbps, human }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = transfer_time_detailed)]
+pub fn transfer_time_detailed_js(
+    bytes: f64,
+    mbps: f64,
+    overhead_percent: f64,
+) -> Result<JsValue, CalcErrorJs> {
+    let time = transfer_time_detailed(bytes, mbps, overhead_percent)
+        .map_err(|e| CalcErrorJs::new(e, Some(bytes)))?;
+    Ok(serde_wasm_bindgen::to_value(&time).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_time_detailed() {
+        // 1 GB at 100 Mbps is 80 s; 10% overhead stretches it to 88.9 s.
+        let time = transfer_time_detailed(1e9, 100.0, 0.0).unwrap();
+        assert_eq!(time.seconds, 80.0);
+        assert_eq!(time.human, "1 min 20 s");
+        let time = transfer_time_detailed(1e9, 100.0, 10.0).unwrap();
+        assert_eq!(time.effective_mbps, 90.0);
+        assert!((time.seconds - 88.888_888_9).abs() < 1e-6);
+        assert_eq!(time.human, "1 min 29 s");
+
+        // A 50 GB game over 20 Mbps.
+        let time = transfer_time_detailed(50e9, 20.0, 0.0).unwrap();
+        assert_eq!(time.human, "5 h 33 min 20 s");
+        assert_eq!(
+            transfer_time_detailed(1e12, 10.0, 0.0).unwrap().human,
+            "9 d 6 h 13 min 20 s"
+        );
+        assert_eq!(
+            transfer_time_detailed(3_600e6 / 8.0, 1.0, 0.0)
+                .unwrap()
+                .human,
+            "1 h"
+        );
+        assert_eq!(transfer_time_detailed(5e6, 8.0, 0.0).unwrap().human, "5 s");
+        assert_eq!(
+            transfer_time_detailed(1e5, 8.0, 0.0).unwrap().human,
+            "0.1 s"
+        );
+        assert_eq!(
+            transfer_time_detailed(59.97e6, 8.0, 0.0).unwrap().human,
+            "1 min"
+        );
+        assert_eq!(transfer_time_detailed(0.0, 8.0, 0.0).unwrap().human, "0 s");
+
+        assert!(transfer_time_detailed(-1.0, 8.0, 0.0).is_err());
+        assert!(transfer_time_detailed(1e6, 0.0, 0.0).is_err());
+        assert!(transfer_time_detailed(1e6, 8.0, 100.0).is_err());
+        assert!(transfer_time_detailed(1e6, 8.0, -1.0).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! CSS font-size units and modular type scales.
+//!
+//! `px` is the CSS pixel, 1/96 in, and `pt` 1/72 in, so 12pt is 16px. `em`,
+//! `rem` and `%` are relative to `base_px`: the root font size for `rem`,
+//! the parent's for `em` and `%`. Panels that model one nesting level, as
+//! most do, pass the same base for both, 16px unless the user changed it.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// The browser default root font size.
+pub const DEFAULT_BASE_PX: f64 = 16.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontUnit {
+    Px,
+    Pt,
+    Em,
+    Rem,
+    Percent,
+}
+
+impl FontUnit {
+    /// `"px"`, `"pt"`, `"em"`, `"rem"` or `"%"`.
+    pub fn parse(name: &str) -> Result<Self, CalcError> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "px" => Ok(FontUnit::Px),
+            "pt" => Ok(FontUnit::Pt),
+            "em" => Ok(FontUnit::Em),
+            "rem" => Ok(FontUnit::Rem),
+            "%" | "percent" => Ok(FontUnit::Percent),
+            _ => Err(invalid(format!(
+                "Unknown font unit {:?}, expected px, pt, em, rem or %",
+                name
+            ))),
+        }
+    }
+
+    // Pixels in one of this unit.
+    fn px(self, base_px: f64) -> f64 {
+        match self {
+            FontUnit::Px => 1.0,
+            FontUnit::Pt => 96.0 / 72.0,
+            FontUnit::Em | FontUnit::Rem => base_px,
+            FontUnit::Percent => base_px / 100.0,
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn positive(what: &str, value: f64) -> Result<f64, CalcError> {
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid(format!("{} must be positive, got {}", what, value)));
+    }
+    Ok(value)
+}
+
+/// `value` in `from` as a number of `to`, with relative units against
+/// `base_px`.
+pub fn convert_font_size(
+    value: f64,
+    from: FontUnit,
+    to: FontUnit,
+    base_px: f64,
+) -> Result<f64, CalcError> {
+    if !value.is_finite() {
+        return Err(invalid(format!("Invalid font size {}", value)));
+    }
+    let base = positive("Base font size", base_px)?;
+    Ok(value * from.px(base) / to.px(base))
+}
+
+/// `steps` sizes of a modular scale, `base * ratio^i` from `i = 0`. A ratio
+/// under 1 steps down.
+pub fn scale(base: f64, ratio: f64, steps: u32) -> Result<Vec<f64>, CalcError> {
+    let base = positive("Base size", base)?;
+    let ratio = positive("Ratio", ratio)?;
+    Ok((0..steps).map(|i| base * ratio.powi(i as i32)).collect())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = convert_font_size)]
+pub fn convert_font_size_js(
+    value: f64,
+    from: &str,
+    to: &str,
+    base_px: Option<f64>,
+) -> Result<f64, CalcErrorJs> {
+    let from = FontUnit::parse(from).map_err(CalcErrorJs::from)?;
+    let to = FontUnit::parse(to).map_err(CalcErrorJs::from)?;
+    convert_font_size(value, from, to, base_px.unwrap_or(DEFAULT_BASE_PX))
+        .map_err(|e| CalcErrorJs::new(e, Some(value)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = scale)]
+pub fn scale_js(base: f64, ratio: f64, steps: u32) -> Result<Vec<f64>, CalcErrorJs> {
+    scale(base, ratio, steps).map_err(|e| CalcErrorJs::new(e, Some(ratio)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FontUnit::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_convert_font_size() {
+        assert!(close(convert_font_size(12.0, Pt, Px, 16.0).unwrap(), 16.0));
+        assert!(close(convert_font_size(24.0, Px, Rem, 16.0).unwrap(), 1.5));
+        assert!(close(convert_font_size(1.5, Em, Px, 20.0).unwrap(), 30.0));
+        assert!(close(
+            convert_font_size(1.25, Rem, Percent, 16.0).unwrap(),
+            125.0
+        ));
+        assert!(close(
+            convert_font_size(75.0, Percent, Pt, 16.0).unwrap(),
+            9.0
+        ));
+        assert_eq!(FontUnit::parse(" REM"), Ok(Rem));
+        assert_eq!(FontUnit::parse("%"), Ok(Percent));
+        assert!(FontUnit::parse("vw").is_err());
+        assert!(convert_font_size(1.0, Em, Px, 0.0).is_err());
+        assert!(convert_font_size(f64::NAN, Em, Px, 16.0).is_err());
+    }
+
+    #[test]
+    fn test_scale() {
+        // A major third from 16px.
+        let sizes = scale(16.0, 1.25, 4).unwrap();
+        assert_eq!(sizes.len(), 4);
+        assert!(close(sizes[0], 16.0));
+        assert!(close(sizes[3], 31.25));
+        let down = scale(16.0, 0.8, 3).unwrap();
+        assert!(close(down[2], 10.24));
+        assert_eq!(scale(16.0, 1.5, 0), Ok(vec![]));
+        assert!(scale(16.0, 0.0, 3).is_err());
+        assert!(scale(-1.0, 1.5, 3).is_err());
+    }
+}
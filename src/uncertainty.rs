@@ -0,0 +1,262 @@
+//! Values with an uncertainty, for lab-report style calculations.
+//!
+//! A [`Measurement`] is `value ± uncertainty`, the uncertainty being one
+//! standard deviation. Results carry an uncertainty propagated to first
+//! order, treating the operands as independent, so errors add in
+//! quadrature:
+//!
+//! - `a ± b`: `σ = √(σa² + σb²)`
+//! - `a × b`, `a ÷ b`: relative uncertainties add in quadrature
+//! - `aⁿ` for an exact `n`: `σ = |n aⁿ⁻¹| σa`
+//!
+//! Because of the independence assumption, `x.mul(x)` underestimates the
+//! uncertainty of `x²`; use `x.pow(2)` for powers of one quantity.
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Measurement {
+    value: f64,
+    uncertainty: f64,
+}
+
+impl Measurement {
+    /// `value ± uncertainty`; the uncertainty must be finite and not
+    /// negative.
+    pub fn with_uncertainty(value: f64, uncertainty: f64) -> Result<Measurement, CalcError> {
+        if !value.is_finite() {
+            return Err(CalcError::InvalidArgument(format!(
+                "Value must be finite, got {}",
+                value
+            )));
+        }
+        if !uncertainty.is_finite() || uncertainty < 0.0 {
+            return Err(CalcError::InvalidArgument(format!(
+                "Uncertainty must be finite and >= 0, got {}",
+                uncertainty
+            )));
+        }
+        Ok(Measurement { value, uncertainty })
+    }
+
+    pub fn div(&self, other: &Measurement) -> Result<Measurement, CalcError> {
+        if other.value == 0.0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        let value = self.value / other.value;
+        let uncertainty = (self.uncertainty / other.value)
+            .hypot(self.value * other.uncertainty / (other.value * other.value));
+        Ok(Measurement { value, uncertainty })
+    }
+
+    /// Raises the measurement to an exact power.
+    pub fn pow(&self, exponent: f64) -> Result<Measurement, CalcError> {
+        let value = self.value.powf(exponent);
+        let uncertainty = (exponent * self.value.powf(exponent - 1.0) * self.uncertainty).abs();
+        if !value.is_finite() {
+            if self.value == 0.0 {
+                return Err(CalcError::DivisionByZero);
+            }
+            return Err(CalcError::InvalidArgument(format!(
+                "{}^{} is not a real number",
+                self.value, exponent
+            )));
+        }
+        // d/da of a^n is infinite at a = 0 for 0 < n < 1: the uncertainty
+        // is unbounded unless there is none to propagate.
+        let uncertainty = if self.uncertainty == 0.0 {
+            0.0
+        } else {
+            uncertainty
+        };
+        if !uncertainty.is_finite() {
+            return Err(CalcError::InvalidArgument(format!(
+                "Uncertainty of {}^{} is unbounded",
+                self.value, exponent
+            )));
+        }
+        Ok(Measurement { value, uncertainty })
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Measurement {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: f64, uncertainty: f64) -> Result<Measurement, CalcErrorJs> {
+        Measurement::with_uncertainty(value, uncertainty)
+            .map_err(|e| CalcErrorJs::new(e, Some(uncertainty)))
+    }
+
+    /// A value known exactly, such as a count or a defined constant.
+    pub fn exact(value: f64) -> Measurement {
+        Measurement {
+            value,
+            uncertainty: 0.0,
+        }
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn uncertainty(&self) -> f64 {
+        self.uncertainty
+    }
+
+    /// `uncertainty / |value|`; infinite for an uncertain zero.
+    pub fn relative_uncertainty(&self) -> f64 {
+        if self.uncertainty == 0.0 {
+            return 0.0;
+        }
+        self.uncertainty / self.value.abs()
+    }
+
+    /// `value - uncertainty`.
+    pub fn low(&self) -> f64 {
+        self.value - self.uncertainty
+    }
+
+    /// `value + uncertainty`.
+    pub fn high(&self) -> f64 {
+        self.value + self.uncertainty
+    }
+
+    pub fn add(&self, other: &Measurement) -> Measurement {
+        Measurement {
+            value: self.value + other.value,
+            uncertainty: self.uncertainty.hypot(other.uncertainty),
+        }
+    }
+
+    pub fn sub(&self, other: &Measurement) -> Measurement {
+        Measurement {
+            value: self.value - other.value,
+            uncertainty: self.uncertainty.hypot(other.uncertainty),
+        }
+    }
+
+    pub fn mul(&self, other: &Measurement) -> Measurement {
+        Measurement {
+            value: self.value * other.value,
+            uncertainty: (self.uncertainty * other.value).hypot(self.value * other.uncertainty),
+        }
+    }
+
+    /// Multiplies by an exact factor, as in a unit conversion.
+    pub fn scale(&self, factor: f64) -> Measurement {
+        Measurement {
+            value: self.value * factor,
+            uncertainty: self.uncertainty * factor.abs(),
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = div)]
+    pub fn div_js(&self, other: &Measurement) -> Result<Measurement, CalcErrorJs> {
+        self.div(other)
+            .map_err(|e| CalcErrorJs::new(e, Some(other.value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = pow)]
+    pub fn pow_js(&self, exponent: f64) -> Result<Measurement, CalcErrorJs> {
+        self.pow(exponent)
+            .map_err(|e| CalcErrorJs::new(e, Some(exponent)))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// `9.81 ± 0.02`, or just the value when it is exact.
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.uncertainty == 0.0 {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} \u{b1} {}", self.value, self.uncertainty)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(value: f64, uncertainty: f64) -> Measurement {
+        Measurement::with_uncertainty(value, uncertainty).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn test_propagation() {
+        let sum = m(10.0, 0.3).add(&m(5.0, 0.4));
+        assert_eq!((sum.value(), sum.uncertainty()), (15.0, 0.5));
+        let diff = m(10.0, 0.3).sub(&m(5.0, 0.4));
+        assert_eq!((diff.value(), diff.uncertainty()), (5.0, 0.5));
+
+        // 3% and 4% relative give 5%.
+        let product = m(10.0, 0.3).mul(&m(2.0, 0.08));
+        assert_eq!(product.value(), 20.0);
+        assert!(close(product.uncertainty(), 1.0));
+        let quotient = m(10.0, 0.3).div(&m(2.0, 0.08)).unwrap();
+        assert_eq!(quotient.value(), 5.0);
+        assert!(close(quotient.relative_uncertainty(), 0.05));
+
+        let square = m(3.0, 0.1).pow(2.0).unwrap();
+        assert_eq!(square.value(), 9.0);
+        assert!(close(square.uncertainty(), 0.6));
+        let root = m(4.0, 0.4).pow(0.5).unwrap();
+        assert!(close(root.uncertainty(), 0.1));
+
+        let scaled = m(2.5, 0.1).scale(-100.0);
+        assert_eq!((scaled.value(), scaled.uncertainty()), (-250.0, 10.0));
+        assert_eq!(
+            Measurement::exact(3.0).mul(&m(2.0, 0.1)).uncertainty(),
+            0.30000000000000004
+        );
+    }
+
+    #[test]
+    fn test_invalid_measurements() {
+        assert!(Measurement::with_uncertainty(1.0, -0.1).is_err());
+        assert!(Measurement::with_uncertainty(f64::NAN, 0.1).is_err());
+        assert_eq!(
+            m(1.0, 0.1).div(&Measurement::exact(0.0)),
+            Err(CalcError::DivisionByZero)
+        );
+        assert!(m(-4.0, 0.1).pow(0.5).is_err());
+        assert!(m(0.0, 0.1).pow(0.5).is_err());
+        assert_eq!(
+            Measurement::exact(0.0).pow(0.5),
+            Ok(Measurement::exact(0.0))
+        );
+        assert_eq!(m(0.0, 0.1).pow(-1.0), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(m(9.81, 0.02).to_string(), "9.81 \u{b1} 0.02");
+        assert_eq!(Measurement::exact(3.0).to_string(), "3");
+        assert_eq!(
+            (m(9.81, 0.02).low(), m(9.81, 0.02).high()),
+            (9.790000000000001, 9.83)
+        );
+    }
+}
@@ -0,0 +1,1063 @@
+//! Quantities with units, and arithmetic that checks dimensions.
+//!
+//! A [`Quantity`] is a value in a unit written the usual way: `m`, `km/h`,
+//! `kg*m/s^2`, `J/(kg*K)`, `m²`. Arithmetic keeps track of the units, so
+//! adding metres to seconds is an error, `m` times `m` is `m^2` and `km`
+//! divided by `h` is a speed. Results keep the units they were computed in
+//! (`3 km * 2 km` is `6 km^2`); [`Quantity::convert_to`] changes unit
+//! between any two of the same dimension.
+//!
+//! `degC` (`°C`) and `degF` (`°F`) on their own are absolute temperatures,
+//! measured from an offset zero, so they follow their own rules:
+//!
+//! - Converting between them and `K` applies the offset: 0 °C is 273.15 K.
+//! - Subtracting two absolute temperatures gives a difference, in `K` for
+//!   Celsius and in `degR` (Rankine, Fahrenheit-sized degrees) for
+//!   Fahrenheit. Adding a difference to an absolute temperature gives an
+//!   absolute temperature. Adding two absolute temperatures is an error.
+//! - Multiplying, dividing or scaling an absolute temperature is an error;
+//!   convert it to `K` first.
+//!
+//! Inside a compound unit (`degC/min`) they are plain degree sizes.
+//!
+//! [`evaluate_quantity`] evaluates whole expressions over quantities, such
+//! as `2 kg * 9.81 m/s^2` or `(120 km) / (90 min) + 5 m/s`. A number followed
+//! by units takes them as its unit, up to the next operator that is not
+//! part of the unit, so `10 m / 2 s` is `(10 m) / (2 s)`. The result is
+//! [simplified](Quantity::simplify); convert it to name it, as in
+//! `.convert_to("N")`.
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{tokenize, unknown_identifier, Dialect, Spanned, Token, CONSTANTS};
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+/// Exponents of length, mass, time, current, temperature, amount and
+/// luminous intensity.
+type Dimension = [i8; 7];
+
+const NONE: Dimension = [0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const AREA: Dimension = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOUS: Dimension = [0, 0, 0, 0, 0, 0, 1];
+
+const fn u(symbol: &'static str, factor: f64, dimension: Dimension) -> UnitDef {
+    UnitDef {
+        symbol,
+        factor,
+        offset: 0.0,
+        dimension,
+    }
+}
+
+/// One known unit: `factor` SI units per unit, plus `offset` for
+/// absolute temperatures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct UnitDef {
+    symbol: &'static str,
+    factor: f64,
+    offset: f64,
+    dimension: Dimension,
+}
+
+const FAHRENHEIT_DEGREE: f64 = 5.0 / 9.0;
+
+const UNITS: &[UnitDef] = &[
+    u("m", 1.0, LENGTH),
+    u("km", 1e3, LENGTH),
+    u("cm", 1e-2, LENGTH),
+    u("mm", 1e-3, LENGTH),
+    u("in", 0.0254, LENGTH),
+    u("ft", 0.3048, LENGTH),
+    u("yd", 0.9144, LENGTH),
+    u("mi", 1609.344, LENGTH),
+    u("nmi", 1852.0, LENGTH),
+    u("ha", 1e4, AREA),
+    u("acre", 4_046.856_422_4, AREA),
+    u("L", 1e-3, VOLUME),
+    u("mL", 1e-6, VOLUME),
+    u("kg", 1.0, MASS),
+    u("g", 1e-3, MASS),
+    u("mg", 1e-6, MASS),
+    u("t", 1e3, MASS),
+    u("lb", 0.453_592_37, MASS),
+    u("oz", 0.028_349_523_125, MASS),
+    u("s", 1.0, TIME),
+    u("ms", 1e-3, TIME),
+    u("min", 60.0, TIME),
+    u("h", 3600.0, TIME),
+    u("day", 86_400.0, TIME),
+    u("Hz", 1.0, FREQUENCY),
+    u("mph", 0.447_04, SPEED),
+    u("kn", 1852.0 / 3600.0, SPEED),
+    u("N", 1.0, FORCE),
+    u("kN", 1e3, FORCE),
+    u("lbf", 4.448_221_615_260_5, FORCE),
+    u("kip", 4_448.221_615_260_5, FORCE),
+    u("J", 1.0, ENERGY),
+    u("kJ", 1e3, ENERGY),
+    u("cal", 4.184, ENERGY),
+    u("kcal", 4184.0, ENERGY),
+    u("kWh", 3.6e6, ENERGY),
+    u("W", 1.0, POWER),
+    u("kW", 1e3, POWER),
+    u("Pa", 1.0, PRESSURE),
+    u("kPa", 1e3, PRESSURE),
+    u("MPa", 1e6, PRESSURE),
+    u("GPa", 1e9, PRESSURE),
+    u("bar", 1e5, PRESSURE),
+    u("atm", 101_325.0, PRESSURE),
+    u("psi", 6_894.757_293_168, PRESSURE),
+    u("ksi", 6_894_757.293_168, PRESSURE),
+    u("A", 1.0, CURRENT),
+    u("C", 1.0, CHARGE),
+    u("V", 1.0, VOLTAGE),
+    u("K", 1.0, TEMPERATURE),
+    u("degR", FAHRENHEIT_DEGREE, TEMPERATURE),
+    UnitDef {
+        symbol: "degC",
+        factor: 1.0,
+        offset: 273.15,
+        dimension: TEMPERATURE,
+    },
+    UnitDef {
+        symbol: "degF",
+        factor: FAHRENHEIT_DEGREE,
+        offset: 459.67 * FAHRENHEIT_DEGREE,
+        dimension: TEMPERATURE,
+    },
+    u("mol", 1.0, AMOUNT),
+    u("cd", 1.0, LUMINOUS),
+];
+
+fn lookup(symbol: &str) -> Result<&'static UnitDef, CalcError> {
+    let symbol = match symbol {
+        "°C" => "degC",
+        "°F" => "degF",
+        "°R" => "degR",
+        other => other,
+    };
+    UNITS
+        .iter()
+        .find(|u| u.symbol == symbol)
+        .ok_or_else(|| unknown_identifier(symbol, UNITS.iter().map(|u| u.symbol)))
+}
+
+/// Every unit symbol, in table order.
+pub(crate) fn unit_symbols<'a>() -> impl Iterator<Item = &'a str> {
+    UNITS.iter().map(|u| u.symbol)
+}
+
+fn invalid_unit(text: &str) -> CalcError {
+    CalcError::InvalidArgument(format!("Invalid unit '{}'", text))
+}
+
+/// A product of unit symbols raised to powers, in order of appearance,
+/// such as `kg^1 m^1 s^-2`.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Unit {
+    terms: Vec<(&'static UnitDef, i32)>,
+}
+
+impl Unit {
+    fn parse(text: &str) -> Result<Unit, CalcError> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut parser = UnitParser {
+            chars: &chars,
+            pos: 0,
+        };
+        let unit = parser.product()?;
+        if parser.pos < chars.len() {
+            return Err(invalid_unit(text));
+        }
+        Ok(unit)
+    }
+
+    fn push(&mut self, def: &'static UnitDef, exponent: i32) {
+        match self.terms.iter_mut().find(|(d, _)| d.symbol == def.symbol) {
+            Some((_, e)) => *e += exponent,
+            None => self.terms.push((def, exponent)),
+        }
+        self.terms.retain(|(_, e)| *e != 0);
+    }
+
+    fn times(&self, other: &Unit, sign: i32) -> Unit {
+        let mut unit = self.clone();
+        for (def, exponent) in &other.terms {
+            unit.push(def, exponent * sign);
+        }
+        unit
+    }
+
+    fn factor(&self) -> f64 {
+        self.terms
+            .iter()
+            .map(|(def, e)| def.factor.powi(*e))
+            .product()
+    }
+
+    fn dimension(&self) -> Dimension {
+        let mut dimension = NONE;
+        for (def, e) in &self.terms {
+            for (d, base) in dimension.iter_mut().zip(def.dimension) {
+                *d += base * *e as i8;
+            }
+        }
+        dimension
+    }
+
+    /// The offset of an absolute temperature, when this is one.
+    fn offset(&self) -> Option<f64> {
+        match self.terms.as_slice() {
+            [(def, 1)] if def.offset != 0.0 => Some(def.offset),
+            _ => None,
+        }
+    }
+}
+
+/// `m/s`, `kg*m/s^2`, `J/(kg*K)`, `1/s`.
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let power = |def: &UnitDef, e: i32| match e {
+            1 => def.symbol.to_string(),
+            e => format!("{}^{}", def.symbol, e),
+        };
+        let numerator: Vec<String> = self
+            .terms
+            .iter()
+            .filter(|(_, e)| *e > 0)
+            .map(|(def, e)| power(def, *e))
+            .collect();
+        let denominator: Vec<String> = self
+            .terms
+            .iter()
+            .filter(|(_, e)| *e < 0)
+            .map(|(def, e)| power(def, -e))
+            .collect();
+        match (numerator.is_empty(), denominator.len()) {
+            (_, 0) => write!(f, "{}", numerator.join("*")),
+            (true, _) => write!(f, "1/{}", group(&denominator)),
+            (false, _) => write!(f, "{}/{}", numerator.join("*"), group(&denominator)),
+        }
+    }
+}
+
+fn group(terms: &[String]) -> String {
+    if terms.len() == 1 {
+        terms[0].clone()
+    } else {
+        format!("({})", terms.join("*"))
+    }
+}
+
+struct UnitParser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl UnitParser<'_> {
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.pos) == Some(&' ') {
+            self.pos += 1;
+        }
+    }
+
+    // factor (('*' | '/' | '·') factor)*, left to right: `J/kg*K` is J*K/kg.
+    fn product(&mut self) -> Result<Unit, CalcError> {
+        let mut unit = self.power()?;
+        loop {
+            self.skip_spaces();
+            let sign = match self.chars.get(self.pos) {
+                Some('*' | '·') => 1,
+                Some('/') => -1,
+                _ => return Ok(unit),
+            };
+            self.pos += 1;
+            unit = unit.times(&self.power()?, sign);
+        }
+    }
+
+    // atom ('^' integer | '²' | '³')?
+    fn power(&mut self) -> Result<Unit, CalcError> {
+        let atom = self.atom()?;
+        let exponent = match self.chars.get(self.pos) {
+            Some('²') => {
+                self.pos += 1;
+                2
+            }
+            Some('³') => {
+                self.pos += 1;
+                3
+            }
+            Some('^') => {
+                self.pos += 1;
+                let start = self.pos;
+                if self.chars.get(self.pos) == Some(&'-') {
+                    self.pos += 1;
+                }
+                while self.chars.get(self.pos).is_some_and(char::is_ascii_digit) {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[start..self.pos].iter().collect();
+                digits.parse().map_err(|_| invalid_unit(&digits))?
+            }
+            _ => 1,
+        };
+        let mut unit = Unit::default();
+        for (def, e) in atom.terms {
+            unit.push(def, e * exponent);
+        }
+        Ok(unit)
+    }
+
+    // symbol | '1' | '(' product ')'
+    fn atom(&mut self) -> Result<Unit, CalcError> {
+        self.skip_spaces();
+        match self.chars.get(self.pos) {
+            Some('(') => {
+                self.pos += 1;
+                let unit = self.product()?;
+                self.skip_spaces();
+                if self.chars.get(self.pos) != Some(&')') {
+                    return Err(invalid_unit(&self.chars.iter().collect::<String>()));
+                }
+                self.pos += 1;
+                Ok(unit)
+            }
+            Some('1') => {
+                self.pos += 1;
+                Ok(Unit::default())
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_alphabetic() || *c == '°')
+                {
+                    self.pos += 1;
+                }
+                let symbol: String = self.chars[start..self.pos].iter().collect();
+                if symbol.is_empty() {
+                    return Err(invalid_unit(&self.chars.iter().collect::<String>()));
+                }
+                let mut unit = Unit::default();
+                unit.push(lookup(&symbol)?, 1);
+                Ok(unit)
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    value: f64,
+    unit: Unit,
+}
+
+fn dimension_mismatch(verb: &str, a: &Quantity, b: &Quantity) -> CalcError {
+    CalcError::InvalidArgument(format!(
+        "Cannot {} {} and {}: the dimensions differ",
+        verb, a.unit, b.unit
+    ))
+}
+
+fn absolute_temperature(verb: &str) -> CalcError {
+    CalcError::InvalidArgument(format!(
+        "Cannot {} an absolute temperature; convert it to K first",
+        verb
+    ))
+}
+
+impl Quantity {
+    /// `value` in `unit`, such as `Quantity::new(9.81, "m/s^2")`. An empty
+    /// unit is a plain number.
+    pub fn new(value: f64, unit: &str) -> Result<Quantity, CalcError> {
+        let unit = if unit.trim().is_empty() {
+            Unit::default()
+        } else {
+            Unit::parse(unit)?
+        };
+        Ok(Quantity { value, unit })
+    }
+
+    /// Reads `"9.81 m/s^2"`: a number, a space, then the unit. Without a
+    /// space the unit starts at the first letter, as in `"5km"`.
+    pub fn parse(text: &str) -> Result<Quantity, CalcError> {
+        let text = text.trim();
+        let split = text
+            .find(char::is_whitespace)
+            .or_else(|| text.find(|c: char| c.is_alphabetic() || c == '°' || c == '('))
+            .unwrap_or(text.len());
+        let value = crate::expr::parse_number(text[..split].trim())?;
+        Quantity::new(value, &text[split..])
+    }
+
+    fn si_value(&self) -> f64 {
+        self.value * self.unit.factor() + self.unit.offset().unwrap_or(0.0)
+    }
+
+    // `si` (an absolute value, or a difference when `delta`) in `unit`.
+    fn from_si(si: f64, unit: Unit, delta: bool) -> Quantity {
+        let offset = if delta {
+            0.0
+        } else {
+            unit.offset().unwrap_or(0.0)
+        };
+        Quantity {
+            value: (si - offset) / unit.factor(),
+            unit,
+        }
+    }
+
+    // The degree a difference of two temperatures in this unit is measured in.
+    fn difference_unit(&self) -> Unit {
+        let symbol = match self.unit.terms.first() {
+            Some((def, _)) if def.symbol == "degF" => "degR",
+            _ => "K",
+        };
+        let mut unit = Unit::default();
+        unit.push(lookup(symbol).expect("built-in unit"), 1);
+        unit
+    }
+
+    fn combine(&self, other: &Quantity, sign: f64) -> Result<Quantity, CalcError> {
+        let verb = if sign > 0.0 { "add" } else { "subtract" };
+        if self.unit.dimension() != other.unit.dimension() {
+            return Err(dimension_mismatch(verb, self, other));
+        }
+        let delta = other.value * other.unit.factor();
+        match (self.unit.offset().is_some(), other.unit.offset().is_some()) {
+            (true, true) if sign > 0.0 => Err(CalcError::InvalidArgument(
+                "Cannot add two absolute temperatures".to_string(),
+            )),
+            (true, true) => {
+                let difference = self.si_value() - other.si_value();
+                Ok(Quantity::from_si(difference, self.difference_unit(), true))
+            }
+            (true, false) => Ok(Quantity::from_si(
+                self.si_value() + sign * delta,
+                self.unit.clone(),
+                false,
+            )),
+            (false, true) if sign > 0.0 => other.combine(self, 1.0),
+            (false, true) => Err(CalcError::InvalidArgument(
+                "Cannot subtract an absolute temperature from a difference".to_string(),
+            )),
+            (false, false) => Ok(Quantity {
+                value: self.value + sign * delta / self.unit.factor(),
+                unit: self.unit.clone(),
+            }),
+        }
+    }
+
+    pub fn add(&self, other: &Quantity) -> Result<Quantity, CalcError> {
+        self.combine(other, 1.0)
+    }
+
+    pub fn sub(&self, other: &Quantity) -> Result<Quantity, CalcError> {
+        self.combine(other, -1.0)
+    }
+
+    pub fn mul(&self, other: &Quantity) -> Result<Quantity, CalcError> {
+        if self.unit.offset().is_some() || other.unit.offset().is_some() {
+            return Err(absolute_temperature("multiply"));
+        }
+        Ok(Quantity {
+            value: self.value * other.value,
+            unit: self.unit.times(&other.unit, 1),
+        })
+    }
+
+    pub fn div(&self, other: &Quantity) -> Result<Quantity, CalcError> {
+        if self.unit.offset().is_some() || other.unit.offset().is_some() {
+            return Err(absolute_temperature("divide"));
+        }
+        if other.value == 0.0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        Ok(Quantity {
+            value: self.value / other.value,
+            unit: self.unit.times(&other.unit, -1),
+        })
+    }
+
+    /// Multiplies by a plain number.
+    pub fn scale(&self, factor: f64) -> Result<Quantity, CalcError> {
+        if self.unit.offset().is_some() {
+            return Err(absolute_temperature("scale"));
+        }
+        Ok(Quantity {
+            value: self.value * factor,
+            unit: self.unit.clone(),
+        })
+    }
+
+    /// The same quantity in `unit`, which must have the same dimension.
+    pub fn convert_to(&self, unit: &str) -> Result<Quantity, CalcError> {
+        let target = Quantity::new(1.0, unit)?;
+        if target.unit.dimension() != self.unit.dimension() {
+            return Err(CalcError::InvalidArgument(format!(
+                "Cannot convert {} to {}: the dimensions differ",
+                self.unit, target.unit
+            )));
+        }
+        Ok(Quantity::from_si(self.si_value(), target.unit, false))
+    }
+
+    /// Rewrites each unit in the unit of the first term with the same
+    /// dimension, so that `2400 km*min/h` becomes `40 km` and `3 m*s/min`
+    /// becomes `0.05 m`. Units of different dimensions are left alone.
+    pub fn simplify(&self) -> Quantity {
+        if self.unit.terms.len() < 2 {
+            return self.clone();
+        }
+        let mut value = self.value;
+        let mut unit = Unit::default();
+        for &(def, exponent) in &self.unit.terms {
+            let like = unit
+                .terms
+                .iter()
+                .find(|(d, _)| d.dimension == def.dimension)
+                .map(|(d, _)| *d);
+            match like {
+                Some(like) => {
+                    value *= (def.factor / like.factor).powi(exponent);
+                    unit.push(like, exponent);
+                }
+                None => unit.push(def, exponent),
+            }
+        }
+        Quantity { value, unit }
+    }
+
+    /// Raises to a plain-number `exponent`. With units, every unit's
+    /// exponent must come out whole: `(4 m^2)^0.5` is `2 m` but `(4 m)^0.5`
+    /// is an error.
+    pub fn pow(&self, exponent: f64) -> Result<Quantity, CalcError> {
+        if self.unit.offset().is_some() {
+            return Err(absolute_temperature("raise"));
+        }
+        let mut unit = Unit::default();
+        for &(def, e) in &self.unit.terms {
+            let raised = e as f64 * exponent;
+            if raised.fract() != 0.0 || raised.abs() > i32::MAX as f64 {
+                return Err(CalcError::InvalidArgument(format!(
+                    "Cannot raise {} to the power {}: the unit would have a fractional exponent",
+                    self.unit, exponent
+                )));
+            }
+            unit.push(def, raised as i32);
+        }
+        Ok(Quantity {
+            value: self.value.powf(exponent),
+            unit,
+        })
+    }
+}
+
+fn syntax(message: impl Into<String>, position: usize) -> CalcError {
+    CalcError::Syntax {
+        message: message.into(),
+        position,
+    }
+}
+
+/// Evaluates `src` over quantities; see the [module docs](self).
+///
+/// Supports `+ - * / ^`, parentheses, `sqrt`, `abs` and the constants `pi`,
+/// `e` and `tau`. Temperatures are spelled `degC` and `degF` here.
+pub fn evaluate_quantity(src: &str) -> Result<Quantity, CalcError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut parser = QuantityParser {
+        chars: &chars,
+        tokens: tokenize(src, Dialect::Standard)?,
+        pos: 0,
+    };
+    let quantity = parser.expression()?;
+    if let Some(t) = parser.tokens.get(parser.pos) {
+        return Err(syntax("Unexpected token", t.start));
+    }
+    Ok(quantity.simplify())
+}
+
+fn unknown_unit(name: &str) -> CalcError {
+    let known = UNITS.iter().map(|u| u.symbol);
+    unknown_identifier(name, known.chain(CONSTANTS.iter().map(|(c, _)| *c)))
+}
+
+// The expression grammar of `expr`, over quantities and with units after
+// numbers.
+struct QuantityParser<'a> {
+    chars: &'a [char],
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl QuantityParser<'_> {
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|t| &t.token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.start)
+            .unwrap_or(self.chars.len())
+    }
+
+    fn eat_op(&mut self, op: char) -> bool {
+        if self.peek_at(0) == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<Quantity, CalcError> {
+        let mut lhs = self.term()?;
+        loop {
+            if self.eat_op('+') {
+                lhs = lhs.add(&self.term()?)?;
+            } else if self.eat_op('-') {
+                lhs = lhs.sub(&self.term()?)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Quantity, CalcError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat_op('*') {
+                lhs = lhs.mul(&self.unary()?)?;
+            } else if self.eat_op('/') {
+                lhs = lhs.div(&self.unary()?)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // As in `expr`, `-2^2` is -4. Negating keeps an absolute temperature
+    // absolute, so `-40 degC` reads as written.
+    fn unary(&mut self) -> Result<Quantity, CalcError> {
+        if self.eat_op('-') {
+            let inner = self.unary()?;
+            return Ok(Quantity {
+                value: -inner.value,
+                unit: inner.unit,
+            });
+        }
+        if self.eat_op('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Quantity, CalcError> {
+        let base = self.primary()?;
+        if !self.eat_op('^') {
+            return Ok(base);
+        }
+        let position = self.position();
+        let exponent = self.unary()?;
+        if exponent.unit.dimension() != NONE {
+            return Err(syntax(
+                format!("An exponent must be a plain number, got {}", exponent),
+                position,
+            ));
+        }
+        base.pow(exponent.si_value())
+    }
+
+    fn primary(&mut self) -> Result<Quantity, CalcError> {
+        let position = self.position();
+        let Some(token) = self.peek_at(0).cloned() else {
+            return Err(syntax("Unexpected end of expression", position));
+        };
+        match token {
+            Token::Number(value) => {
+                self.pos += 1;
+                let unit = match self.unit_suffix()? {
+                    Some(unit) => unit,
+                    None => match self.peek_at(0) {
+                        Some(Token::Ident(name)) => return Err(unknown_unit(name)),
+                        _ => Unit::default(),
+                    },
+                };
+                Ok(Quantity { value, unit })
+            }
+            Token::LParen => {
+                self.pos += 1;
+                let inner = self.expression()?;
+                if self.peek_at(0) != Some(&Token::RParen) {
+                    return Err(syntax("Expected ')'", self.position()));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Ident(name) if self.peek_at(1) == Some(&Token::LParen) => {
+                self.pos += 2;
+                let argument = self.expression()?;
+                if self.peek_at(0) != Some(&Token::RParen) {
+                    return Err(syntax("Expected ')'", self.position()));
+                }
+                self.pos += 1;
+                match name.as_str() {
+                    "sqrt" => argument.pow(0.5),
+                    "abs" => Ok(Quantity {
+                        value: argument.value.abs(),
+                        unit: argument.unit,
+                    }),
+                    _ => Err(unknown_identifier(&name, ["abs", "sqrt"])),
+                }
+            }
+            Token::Ident(name) => {
+                if let Some((_, value)) = CONSTANTS.iter().find(|(c, _)| *c == name) {
+                    self.pos += 1;
+                    return Ok(Quantity {
+                        value: *value,
+                        unit: Unit::default(),
+                    });
+                }
+                match self.unit_suffix()? {
+                    Some(unit) => Ok(Quantity { value: 1.0, unit }),
+                    None => Err(unknown_unit(&name)),
+                }
+            }
+            _ => Err(syntax("Unexpected token", position)),
+        }
+    }
+
+    fn is_unit(&self, offset: usize) -> bool {
+        matches!(self.peek_at(offset), Some(Token::Ident(name)) if Unit::parse(name).is_ok())
+    }
+
+    // Tokens from `offset` on that spell a unit atom: a unit symbol, or a
+    // parenthesized product of them such as `(kg*K)`. Returns how many.
+    fn unit_atom(&self, offset: usize) -> Option<usize> {
+        if self.is_unit(offset) {
+            return Some(1);
+        }
+        if self.peek_at(offset) != Some(&Token::LParen) {
+            return None;
+        }
+        let mut len = 1;
+        loop {
+            len += self.unit_atom(offset + len)?;
+            len += self.unit_exponent(offset + len);
+            match self.peek_at(offset + len) {
+                Some(Token::Op('*' | '/')) => len += 1,
+                Some(Token::RParen) => return Some(len + 1),
+                _ => return None,
+            }
+        }
+    }
+
+    // The length of a `^2` or `^-1` at `offset`, or 0.
+    fn unit_exponent(&self, offset: usize) -> usize {
+        if self.peek_at(offset) != Some(&Token::Op('^')) {
+            return 0;
+        }
+        match (self.peek_at(offset + 1), self.peek_at(offset + 2)) {
+            (Some(Token::Number(n)), _) if n.fract() == 0.0 => 2,
+            (Some(Token::Op('-')), Some(Token::Number(n))) if n.fract() == 0.0 => 3,
+            _ => 0,
+        }
+    }
+
+    // The unit written at the current token, if one is, consuming it.
+    fn unit_suffix(&mut self) -> Result<Option<Unit>, CalcError> {
+        let Some(mut len) = self.unit_atom(0) else {
+            return Ok(None);
+        };
+        len += self.unit_exponent(len);
+        while matches!(self.peek_at(len), Some(Token::Op('*' | '/'))) {
+            let Some(atom) = self.unit_atom(len + 1) else {
+                break;
+            };
+            len += 1 + atom;
+            len += self.unit_exponent(len);
+        }
+        let start = self.tokens[self.pos].start;
+        let end = self.tokens[self.pos + len - 1].end;
+        let text: String = self.chars[start..end].iter().collect();
+        self.pos += len;
+        Unit::parse(&text).map(Some)
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Quantity {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(constructor)]
+    pub fn new_js(value: f64, unit: &str) -> Result<Quantity, CalcErrorJs> {
+        Quantity::new(value, unit).map_err(|e| CalcErrorJs::new(e, Some(value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = parse)]
+    pub fn parse_js(text: &str) -> Result<Quantity, CalcErrorJs> {
+        Quantity::parse(text).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// The unit as `to_string` writes it; empty for a plain number.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn unit(&self) -> String {
+        self.unit.to_string()
+    }
+
+    /// Whether `other` can be added to or converted into this quantity.
+    pub fn same_dimension(&self, other: &Quantity) -> bool {
+        self.unit.dimension() == other.unit.dimension()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = add)]
+    pub fn add_js(&self, other: &Quantity) -> Result<Quantity, CalcErrorJs> {
+        self.add(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = sub)]
+    pub fn sub_js(&self, other: &Quantity) -> Result<Quantity, CalcErrorJs> {
+        self.sub(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = mul)]
+    pub fn mul_js(&self, other: &Quantity) -> Result<Quantity, CalcErrorJs> {
+        self.mul(other).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = div)]
+    pub fn div_js(&self, other: &Quantity) -> Result<Quantity, CalcErrorJs> {
+        self.div(other)
+            .map_err(|e| CalcErrorJs::new(e, Some(other.value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = scale)]
+    pub fn scale_js(&self, factor: f64) -> Result<Quantity, CalcErrorJs> {
+        self.scale(factor)
+            .map_err(|e| CalcErrorJs::new(e, Some(factor)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = convert_to)]
+    pub fn convert_to_js(&self, unit: &str) -> Result<Quantity, CalcErrorJs> {
+        self.convert_to(unit).map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = simplify)]
+    pub fn simplify_js(&self) -> Quantity {
+        self.simplify()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = pow)]
+    pub fn pow_js(&self, exponent: f64) -> Result<Quantity, CalcErrorJs> {
+        self.pow(exponent)
+            .map_err(|e| CalcErrorJs::new(e, Some(exponent)))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toString))]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = evaluate_quantity)]
+pub fn evaluate_quantity_js(src: &str) -> Result<Quantity, CalcErrorJs> {
+    evaluate_quantity(src).map_err(CalcErrorJs::from)
+}
+
+/// `3 m/s`, or just the value for a plain number.
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.unit.terms.is_empty() {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.unit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(text: &str) -> Quantity {
+        Quantity::parse(text).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_dimension_checked_arithmetic() {
+        assert_eq!(q("3 m").add(&q("50 cm")).unwrap().to_string(), "3.5 m");
+        assert!(q("3 m").add(&q("2 s")).is_err());
+        assert_eq!(q("3 km").mul(&q("2 km")).unwrap().to_string(), "6 km^2");
+        let speed = q("120 km").div(&q("1.5 h")).unwrap();
+        assert_eq!(speed.to_string(), "80 km/h");
+        assert!(close(
+            speed.convert_to("m/s").unwrap().value(),
+            22.222222222
+        ));
+        assert!(close(
+            speed.convert_to("mph").unwrap().value(),
+            49.709695379
+        ));
+        assert!(speed.convert_to("kg").is_err());
+
+        let force = q("2 kg").mul(&q("9.81 m/s^2")).unwrap();
+        assert_eq!(force.unit(), "kg*m/s^2");
+        assert!(close(force.convert_to("N").unwrap().value(), 19.62));
+        let distance = q("80 km/h").mul(&q("30 min")).unwrap();
+        assert_eq!(distance.unit(), "km*min/h");
+        assert!(close(distance.convert_to("km").unwrap().value(), 40.0));
+        assert_eq!(q("5 m/s").mul(&q("2 s")).unwrap().to_string(), "10 m");
+        assert_eq!(q("6 m").div(&q("3 m")).unwrap().to_string(), "2");
+        assert!(q("1 m").div(&q("0 s")).is_err());
+    }
+
+    #[test]
+    fn test_unit_parsing() {
+        assert_eq!(q("4.2 J/(kg*K)").unit(), "J/(kg*K)");
+        assert_eq!(q("1 J/kg*K").unit(), "J*K/kg");
+        assert_eq!(q("3 m²").unit(), "m^2");
+        assert_eq!(q("2 1/s").unit(), "1/s");
+        assert_eq!(q("2 s^-1").unit(), "1/s");
+        assert!(close(
+            q("1 ft^2").convert_to("m^2").unwrap().value(),
+            0.09290304
+        ));
+        assert!(close(q("1 kWh").convert_to("kJ").unwrap().value(), 3600.0));
+        assert!(matches!(
+            Quantity::parse("3 kmh"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(Quantity::parse("3 m^").is_err());
+        assert!(Quantity::parse("3 (m").is_err());
+        assert!(Quantity::parse("m").is_err());
+        assert_eq!(q("5km").to_string(), "5 km");
+    }
+
+    #[test]
+    fn test_temperatures() {
+        assert!(close(q("25 degC").convert_to("K").unwrap().value(), 298.15));
+        assert!(close(
+            q("212 °F").convert_to("degC").unwrap().value(),
+            100.0
+        ));
+        assert!(close(
+            q("-40 degC").convert_to("degF").unwrap().value(),
+            -40.0
+        ));
+
+        let difference = q("30 degC").sub(&q("20 degC")).unwrap();
+        assert_eq!(difference.unit(), "K");
+        assert!(close(difference.value(), 10.0));
+        assert_eq!(q("70 degF").sub(&q("50 degF")).unwrap().unit(), "degR");
+        assert!(close(q("20 degC").add(&q("5 K")).unwrap().value(), 25.0));
+        assert!(close(q("20 degC").add(&q("9 degR")).unwrap().value(), 25.0));
+        assert!(close(q("5 K").add(&q("20 degC")).unwrap().value(), 25.0));
+        assert!(q("20 degC").add(&q("20 degC")).is_err());
+        assert!(q("20 degC").mul(&q("2 s")).is_err());
+        assert!(q("20 degC").scale(2.0).is_err());
+        // A temperature rate is a plain degree size.
+        let rate = q("2 degC/min").mul(&q("30 min")).unwrap();
+        assert_eq!(rate.unit(), "degC");
+    }
+
+    #[test]
+    fn test_simplify_and_pow() {
+        let distance = q("80 km/h").mul(&q("30 min")).unwrap().simplify();
+        assert_eq!(distance.unit(), "km");
+        assert!(close(distance.value(), 40.0));
+        let mixed = q("3 m*s/min").simplify();
+        assert_eq!(mixed.unit(), "m");
+        assert!(close(mixed.value(), 0.05));
+        assert_eq!(q("2 kg*m/s^2").simplify(), q("2 kg*m/s^2"));
+        assert_eq!(q("25 degC").simplify(), q("25 degC"));
+
+        assert_eq!(q("3 m").pow(2.0), Ok(q("9 m^2")));
+        assert_eq!(q("4 m^2").pow(0.5), Ok(q("2 m")));
+        assert_eq!(q("2 s").pow(-1.0), Ok(q("0.5 1/s")));
+        assert!(q("4 m").pow(0.5).is_err());
+        assert!(q("4 degC").pow(2.0).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_quantity() {
+        let force = evaluate_quantity("2 kg * 9.81 m/s^2").unwrap();
+        assert_eq!(force.unit(), "kg*m/s^2");
+        assert!(close(force.convert_to("N").unwrap().value(), 19.62));
+        assert_eq!(evaluate_quantity("10 m / 2 s").unwrap(), q("5 m/s"));
+        let speed = evaluate_quantity("120 km / 90 min + 5 m/s").unwrap();
+        assert_eq!(speed.unit(), "km/min");
+        assert!(close(speed.convert_to("km/h").unwrap().value(), 98.0));
+        assert_eq!(evaluate_quantity("80 km/h * 30 min").unwrap(), q("40 km"));
+        let heat = evaluate_quantity("4.2 J/(kg*K) * 2 kg * 10 K").unwrap();
+        assert_eq!(heat, q("84 J"));
+        assert_eq!(evaluate_quantity("(3 m)^2").unwrap(), q("9 m^2"));
+        assert_eq!(evaluate_quantity("sqrt(16 m^2)").unwrap(), q("4 m"));
+        assert_eq!(evaluate_quantity("2 * pi").unwrap().unit(), "");
+        assert_eq!(evaluate_quantity("6 m / 3 m").unwrap(), q("2"));
+        assert_eq!(evaluate_quantity("kg*m/s^2").unwrap(), q("1 kg*m/s^2"));
+        assert_eq!(evaluate_quantity("3 m²").unwrap(), q("3 m^2"));
+        assert_eq!(evaluate_quantity("-40 degC").unwrap(), q("-40 degC"));
+        assert!(close(
+            evaluate_quantity("30 degC - 20 degC").unwrap().value(),
+            10.0
+        ));
+
+        assert!(evaluate_quantity("3 m + 2 s").is_err());
+        assert!(evaluate_quantity("2 m ^ 1 m").is_err());
+        assert!(matches!(
+            evaluate_quantity("3 kmh"),
+            Err(CalcError::UnknownIdentifier { .. })
+        ));
+        assert!(matches!(
+            evaluate_quantity("(2 m"),
+            Err(CalcError::Syntax { .. })
+        ));
+        assert!(matches!(
+            evaluate_quantity("2 m 3"),
+            Err(CalcError::Syntax { position: 4, .. })
+        ));
+    }
+}
@@ -0,0 +1,459 @@
+//! Named variables, including live ones defined by an expression.
+//!
+//! `set_variable("price", 4.5)` binds a value. `define_live("total",
+//! "price * qty")` binds an expression over other variables instead. A
+//! live variable is worked out again every time it is read, so it always
+//! reflects the current values of its dependencies, however many live
+//! variables lie in between. Until all of its inputs are set it has no
+//! value, and reading it reports the first one missing.
+//!
+//! A definition that would depend on itself, directly or through other
+//! live variables, is refused with the cycle spelled out:
+//! `Circular definition: a -> b -> a`.
+//!
+//! `Calculator::evaluate` reads every variable alongside `ans` and `mem`,
+//! so `evaluate("total * 2")` works once `total` has a value.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::expr::{
+    parse, unknown_identifier, AngleScope, Dialect, Expr, CONSTANTS, DATASET, FUNCTIONS,
+};
+use serde::Serialize;
+
+use crate::locale::localize_input;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+use crate::{CalcError, Calculator};
+
+/// A variable bound to an expression over other variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveVariable {
+    pub source: String,
+    expr: Expr,
+    /// The variables the expression reads, sorted.
+    pub dependencies: Vec<String>,
+}
+
+/// One row of [`Calculator::list_variables`]. `value` is `None` for a live
+/// variable that cannot be evaluated yet, and `expression` is `None` for a
+/// plain one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariableEntry {
+    pub name: String,
+    pub value: Option<f64>,
+    pub expression: Option<String>,
+}
+
+/// The variables of a calculator: plain values and live definitions,
+/// never both under one name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Variables {
+    values: BTreeMap<String, f64>,
+    live: BTreeMap<String, LiveVariable>,
+}
+
+fn check_name(name: &str) -> Result<(), CalcError> {
+    let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !valid {
+        return Err(CalcError::InvalidArgument(format!(
+            "Variable name '{}' must be an identifier",
+            name
+        )));
+    }
+    let reserved = ["ans", "mem"].contains(&name)
+        || FUNCTIONS.iter().any(|(f, _, _)| *f == name)
+        || CONSTANTS.iter().any(|(c, _)| *c == name);
+    if reserved {
+        return Err(CalcError::InvalidArgument(format!(
+            "'{}' is a built-in name",
+            name
+        )));
+    }
+    Ok(())
+}
+
+// The variables `expr` reads, other than constants.
+pub(crate) fn collect_dependencies(expr: &Expr, out: &mut BTreeSet<String>) {
+    match expr {
+        Expr::Number(_) | Expr::Range(..) => {}
+        Expr::Variable(name) => {
+            if !CONSTANTS.iter().any(|(c, _)| c == name) {
+                out.insert(name.clone());
+            }
+        }
+        Expr::Unary(_, inner) => collect_dependencies(inner, out),
+        Expr::Binary(_, lhs, rhs) => {
+            collect_dependencies(lhs, out);
+            collect_dependencies(rhs, out);
+        }
+        // A dataset name is not a variable.
+        Expr::Call(name, _) if name == DATASET => {}
+        Expr::Call(_, args) => args.iter().for_each(|arg| collect_dependencies(arg, out)),
+    }
+}
+
+impl Variables {
+    // Fills `path` with a chain of live dependencies from `from` to
+    // `target`, if there is one.
+    fn path_to(
+        &self,
+        from: &str,
+        target: &str,
+        seen: &mut BTreeSet<String>,
+        path: &mut Vec<String>,
+    ) -> bool {
+        path.push(from.to_string());
+        if from == target {
+            return true;
+        }
+        if seen.insert(from.to_string()) {
+            if let Some(live) = self.live.get(from) {
+                for dependency in &live.dependencies {
+                    if self.path_to(dependency, target, seen, path) {
+                        return true;
+                    }
+                }
+            }
+        }
+        path.pop();
+        false
+    }
+
+    // Live variables, each after the live variables it depends on.
+    fn live_order(&self) -> Vec<&str> {
+        fn visit<'a>(
+            variables: &'a Variables,
+            name: &'a str,
+            seen: &mut BTreeSet<&'a str>,
+            order: &mut Vec<&'a str>,
+        ) {
+            let Some(live) = variables.live.get(name) else {
+                return;
+            };
+            if !seen.insert(name) {
+                return;
+            }
+            for dependency in &live.dependencies {
+                visit(variables, dependency, seen, order);
+            }
+            order.push(name);
+        }
+        let mut seen = BTreeSet::new();
+        let mut order = Vec::new();
+        for name in self.live.keys() {
+            visit(self, name, &mut seen, &mut order);
+        }
+        order
+    }
+
+    fn names(&self) -> impl Iterator<Item = &str> {
+        self.values
+            .keys()
+            .chain(self.live.keys())
+            .map(String::as_str)
+    }
+}
+
+impl Calculator {
+    /// Binds `name` to `value`, replacing any live definition of it.
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalcError> {
+        check_name(name).map_err(|e| self.fail(e, Some(value)))?;
+        self.variables.live.remove(name);
+        self.variables.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Binds `name` to `expression`, parsed now in the input locale. The
+    /// variables it reads need not be set yet.
+    pub fn define_live(&mut self, name: &str, expression: &str) -> Result<(), CalcError> {
+        let live = self
+            .parse_live(name, expression)
+            .map_err(|e| self.fail(e, None))?;
+        self.variables.values.remove(name);
+        self.variables.live.insert(name.to_string(), live);
+        Ok(())
+    }
+
+    fn parse_live(&self, name: &str, expression: &str) -> Result<LiveVariable, CalcError> {
+        check_name(name)?;
+        let expr = localize_input(expression, self.decimal_comma())
+            .and_then(|expression| parse(&expression, Dialect::Standard))?;
+        let mut dependencies = BTreeSet::new();
+        collect_dependencies(&expr, &mut dependencies);
+        for dependency in &dependencies {
+            let mut path = vec![name.to_string()];
+            if self
+                .variables
+                .path_to(dependency, name, &mut BTreeSet::new(), &mut path)
+            {
+                return Err(CalcError::InvalidArgument(format!(
+                    "Circular definition: {}",
+                    path.join(" -> ")
+                )));
+            }
+        }
+        Ok(LiveVariable {
+            source: expression.to_string(),
+            expr,
+            dependencies: dependencies.into_iter().collect(),
+        })
+    }
+
+    pub fn live_variable(&self, name: &str) -> Option<&LiveVariable> {
+        self.variables.live.get(name)
+    }
+
+    /// The value of every variable, working out live ones in dependency
+    /// order. A live variable that cannot be evaluated maps to the error,
+    /// which its own dependents share.
+    pub(crate) fn resolve_variables(&self) -> BTreeMap<String, Result<f64, CalcError>> {
+        let mut values = self.variables.values.clone();
+        let mut errors: BTreeMap<String, CalcError> = BTreeMap::new();
+        for name in self.variables.live_order() {
+            let live = &self.variables.live[name];
+            let failed = live
+                .dependencies
+                .iter()
+                .find_map(|dependency| errors.get(dependency).cloned());
+            let result = match failed {
+                Some(err) => Err(err),
+                None => live.expr.eval(&AngleScope {
+                    scope: &values,
+                    unit: self.angle_unit,
+                }),
+            };
+            match result {
+                Ok(value) => {
+                    values.insert(name.to_string(), value);
+                }
+                Err(err) => {
+                    errors.insert(name.to_string(), err);
+                }
+            }
+        }
+        let mut resolved: BTreeMap<_, _> = values.into_iter().map(|(k, v)| (k, Ok(v))).collect();
+        resolved.extend(errors.into_iter().map(|(k, e)| (k, Err(e))));
+        resolved
+    }
+
+    /// The variables `expr` reads, with their values, as a scope for
+    /// evaluating it. Fails with the error of the first live variable it
+    /// reads that cannot be evaluated.
+    pub(crate) fn variable_scope(&self, expr: &Expr) -> Result<BTreeMap<String, f64>, CalcError> {
+        let mut read = BTreeSet::new();
+        collect_dependencies(expr, &mut read);
+        let mut scope = BTreeMap::new();
+        for (name, result) in self.resolve_variables() {
+            match result {
+                Ok(value) => {
+                    scope.insert(name, value);
+                }
+                Err(err) if read.contains(&name) => return Err(err),
+                Err(_) => {}
+            }
+        }
+        Ok(scope)
+    }
+
+    /// Every variable, sorted by name.
+    pub fn list_variables(&self) -> Vec<VariableEntry> {
+        self.resolve_variables()
+            .into_iter()
+            .map(|(name, result)| VariableEntry {
+                expression: self.live_source(&name),
+                value: result.ok(),
+                name,
+            })
+            .collect()
+    }
+
+    /// The value of `name`, evaluated now if it is live.
+    pub fn get_variable(&self, name: &str) -> Result<f64, CalcError> {
+        let result = match self.resolve_variables().remove(name) {
+            Some(result) => result,
+            None => Err(unknown_identifier(name, self.variables.names())),
+        };
+        result.map_err(|e| self.fail(e, None))
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Calculator {
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = set_variable)]
+    pub fn set_variable_js(&mut self, name: &str, value: f64) -> Result<(), CalcErrorJs> {
+        self.set_variable(name, value)
+            .map_err(|e| CalcErrorJs::new(e, Some(value)))
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = define_live)]
+    pub fn define_live_js(&mut self, name: &str, expression: &str) -> Result<(), CalcErrorJs> {
+        self.define_live(name, expression)
+            .map_err(CalcErrorJs::from)
+    }
+
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = get_variable)]
+    pub fn get_variable_js(&self, name: &str) -> Result<f64, CalcErrorJs> {
+        self.get_variable(name).map_err(CalcErrorJs::from)
+    }
+
+    /// `[{ name, value, expression }]`; `value` is `null` for a live
+    /// variable that cannot be evaluated yet and `expression` is `null` for
+    /// a plain one.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = list_variables)]
+    pub fn list_variables_js(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.list_variables()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Removes a variable, plain or live; returns whether it existed.
+    pub fn remove_variable(&mut self, name: &str) -> bool {
+        let value = self.variables.values.remove(name);
+        let live = self.variables.live.remove(name);
+        value.is_some() || live.is_some()
+    }
+
+    /// Names of the variables, sorted.
+    pub fn variable_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.variables.names().map(str::to_string).collect();
+        names.sort();
+        names
+    }
+
+    /// The expression a live variable was defined with, or `undefined`.
+    pub fn live_source(&self, name: &str) -> Option<String> {
+        self.variables.live.get(name).map(|l| l.source.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_variables_follow_dependencies() {
+        let mut calc = Calculator::new();
+        calc.define_live("total", "subtotal * (1 + tax)").unwrap();
+        calc.define_live("subtotal", "price * qty").unwrap();
+        assert_eq!(
+            calc.get_variable("total"),
+            Err(CalcError::UnknownIdentifier {
+                name: "price".to_string(),
+                suggestions: vec![],
+            })
+        );
+        calc.set_variable("price", 4.0).unwrap();
+        calc.set_variable("qty", 3.0).unwrap();
+        calc.set_variable("tax", 0.25).unwrap();
+        assert_eq!(calc.get_variable("subtotal"), Ok(12.0));
+        assert_eq!(calc.get_variable("total"), Ok(15.0));
+        calc.set_variable("qty", 5.0).unwrap();
+        assert_eq!(calc.get_variable("total"), Ok(25.0));
+
+        assert_eq!(
+            calc.live_variable("subtotal").unwrap().dependencies,
+            vec!["price", "qty"]
+        );
+        assert_eq!(
+            calc.live_source("total").as_deref(),
+            Some("subtotal * (1 + tax)")
+        );
+        // A plain value replaces the definition.
+        calc.set_variable("subtotal", 2.0).unwrap();
+        assert_eq!(calc.live_source("subtotal"), None);
+        assert_eq!(calc.get_variable("total"), Ok(2.5));
+        assert!(matches!(
+            calc.get_variable("totl"),
+            Err(CalcError::UnknownIdentifier { suggestions, .. }) if suggestions == ["total"]
+        ));
+    }
+
+    #[test]
+    fn test_variables_in_expressions() {
+        let mut calc = Calculator::new();
+        calc.set_variable("x", 5.0).unwrap();
+        calc.set_variable("rate", 0.2).unwrap();
+        calc.define_live("net", "gross * (1 - rate)").unwrap();
+        assert_eq!(calc.evaluate("x * 2"), Ok(10.0));
+        assert_eq!(calc.evaluate("ans + x"), Ok(15.0));
+        assert_eq!(
+            calc.evaluate("net + 1"),
+            Err(CalcError::UnknownIdentifier {
+                name: "gross".to_string(),
+                suggestions: vec![],
+            })
+        );
+        // Only the variables an expression reads need a value.
+        assert_eq!(calc.evaluate("ans / x"), Ok(3.0));
+        calc.set_variable("gross", 100.0).unwrap();
+        assert_eq!(calc.evaluate("net + ans"), Ok(83.0));
+        assert_eq!(calc.suggest("ra"), vec!["rate"]);
+
+        assert_eq!(
+            calc.list_variables(),
+            vec![
+                VariableEntry {
+                    name: "gross".to_string(),
+                    value: Some(100.0),
+                    expression: None,
+                },
+                VariableEntry {
+                    name: "net".to_string(),
+                    value: Some(80.0),
+                    expression: Some("gross * (1 - rate)".to_string()),
+                },
+                VariableEntry {
+                    name: "rate".to_string(),
+                    value: Some(0.2),
+                    expression: None,
+                },
+                VariableEntry {
+                    name: "x".to_string(),
+                    value: Some(5.0),
+                    expression: None,
+                },
+            ]
+        );
+        assert!(calc.remove_variable("gross"));
+        assert!(!calc.remove_variable("gross"));
+        assert_eq!(calc.list_variables()[0].value, None);
+        assert_eq!(calc.variable_names(), vec!["net", "rate", "x"]);
+    }
+
+    #[test]
+    fn test_cycles_are_refused() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            calc.define_live("a", "a + 1"),
+            Err(CalcError::InvalidArgument(
+                "Circular definition: a -> a".to_string()
+            ))
+        );
+        calc.define_live("a", "b + 1").unwrap();
+        calc.define_live("b", "c * 2").unwrap();
+        assert_eq!(
+            calc.define_live("c", "a - pi"),
+            Err(CalcError::InvalidArgument(
+                "Circular definition: c -> a -> b -> c".to_string()
+            ))
+        );
+        calc.define_live("c", "pi").unwrap();
+        assert!(
+            (calc.get_variable("a").unwrap() - (2.0 * std::f64::consts::PI + 1.0)).abs() < 1e-12
+        );
+        // Redefining a variable drops what it used to depend on.
+        calc.define_live("b", "3").unwrap();
+        calc.define_live("c", "a").unwrap();
+        assert_eq!(calc.get_variable("c"), Ok(4.0));
+        assert_eq!(calc.error_log().len(), 2);
+        assert!(calc.set_variable("ans", 1.0).is_err());
+        assert!(calc.define_live("sqrt", "2").is_err());
+    }
+}
@@ -0,0 +1,380 @@
+//! Screen and video calculators: aspect ratios, bitrates and timecode.
+//!
+//! [`estimate_bitrate`] uses bits per pixel per frame, the usual rule of
+//! thumb for H.264: about 0.05 for streaming, 0.1 for good quality and 0.15
+//! for near-transparent encodes. H.265 and AV1 need roughly half as much.
+//!
+//! Timecode is SMPTE `HH:MM:SS:FF`, counted at the nominal rate (30 for
+//! 29.97 fps). Drop-frame timecode, written `HH:MM:SS;FF`, skips frame
+//! numbers 0 and 1 (0 to 3 at 59.94) at the start of every minute except
+//! each tenth, so that it keeps to the clock at 29.97 and 59.94 fps.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::{CalcErrorJs, MAX_SAFE_INTEGER};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl VideoQuality {
+    pub fn parse(name: &str) -> Result<Self, CalcError> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(VideoQuality::Low),
+            "medium" => Ok(VideoQuality::Medium),
+            "high" => Ok(VideoQuality::High),
+            _ => Err(invalid(format!(
+                "Unknown quality {:?}, expected low, medium or high",
+                name
+            ))),
+        }
+    }
+
+    fn bits_per_pixel(self) -> f64 {
+        match self {
+            VideoQuality::Low => 0.05,
+            VideoQuality::Medium => 0.1,
+            VideoQuality::High => 0.15,
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// `width`:`height` in lowest terms, as `"16:9"`.
+pub fn aspect_ratio(width: u32, height: u32) -> Result<String, CalcError> {
+    if width == 0 || height == 0 {
+        return Err(invalid(format!(
+            "Resolution must not be zero, got {}x{}",
+            width, height
+        )));
+    }
+    let divisor = gcd(width, height);
+    Ok(format!("{}:{}", width / divisor, height / divisor))
+}
+
+fn parse_ratio(ratio: &str) -> Result<(f64, f64), CalcError> {
+    let bad = || invalid(format!("Invalid aspect ratio {:?}, expected W:H", ratio));
+    let (w, h) = ratio.split_once(':').ok_or_else(bad)?;
+    let w: f64 = w.trim().parse().map_err(|_| bad())?;
+    let h: f64 = h.trim().parse().map_err(|_| bad())?;
+    if !w.is_finite() || !h.is_finite() || w <= 0.0 || h <= 0.0 {
+        return Err(bad());
+    }
+    Ok((w, h))
+}
+
+/// The resolution at `ratio` (`"16:9"`, `"2.39:1"`) with the one of `width`
+/// and `height` given, the other rounded to the nearest pixel.
+pub fn resolution_for_aspect(
+    ratio: &str,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<Resolution, CalcError> {
+    let (w, h) = parse_ratio(ratio)?;
+    let (width, height) = match (width, height) {
+        (Some(width), None) => (width, dimension(width as f64 * h / w)?),
+        (None, Some(height)) => (dimension(height as f64 * w / h)?, height),
+        _ => return Err(invalid("Give exactly one of width and height")),
+    };
+    aspect_ratio(width, height)?;
+    Ok(Resolution { width, height })
+}
+
+// A computed side, to the nearest pixel.
+fn dimension(pixels: f64) -> Result<u32, CalcError> {
+    let pixels = pixels.round();
+    if !(1.0..=u32::MAX as f64).contains(&pixels) {
+        return Err(invalid(format!(
+            "The other side would be {} pixels",
+            pixels
+        )));
+    }
+    Ok(pixels as u32)
+}
+
+/// A bitrate in kbit/s for `width`x`height` at `fps` and `quality`.
+pub fn estimate_bitrate(
+    width: u32,
+    height: u32,
+    fps: f64,
+    quality: VideoQuality,
+) -> Result<f64, CalcError> {
+    aspect_ratio(width, height)?;
+    let fps = frame_rate(fps)?;
+    Ok(width as f64 * height as f64 * fps * quality.bits_per_pixel() / 1000.0)
+}
+
+fn frame_rate(fps: f64) -> Result<f64, CalcError> {
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err(invalid(format!("Frame rate must be positive, got {}", fps)));
+    }
+    Ok(fps)
+}
+
+// The whole frames per timecode second, and the frame numbers dropped per
+// minute if `drop_frame`.
+fn timebase(fps: f64, drop_frame: bool) -> Result<(u64, u64), CalcError> {
+    let nominal = frame_rate(fps)?.round().max(1.0) as u64;
+    if !drop_frame {
+        return Ok((nominal, 0));
+    }
+    match nominal {
+        30 | 60 if fps.fract() != 0.0 => Ok((nominal, nominal / 15)),
+        _ => Err(invalid(format!(
+            "Drop-frame timecode is only defined at 29.97 and 59.94 fps, got {}",
+            fps
+        ))),
+    }
+}
+
+/// Frame `frames` (from zero) as timecode at `fps`.
+pub fn frames_to_timecode(frames: u64, fps: f64, drop_frame: bool) -> Result<String, CalcError> {
+    let (base, dropped) = timebase(fps, drop_frame)?;
+    let mut frames = frames;
+    if dropped > 0 {
+        let per_minute = base * 60 - dropped;
+        let per_ten_minutes = base * 600 - dropped * 9;
+        let tens = frames / per_ten_minutes;
+        let rest = frames % per_ten_minutes;
+        let mut skipped = dropped * 9 * tens;
+        if rest > dropped {
+            skipped += dropped * ((rest - dropped) / per_minute);
+        }
+        frames = frames.checked_add(skipped).ok_or_else(|| {
+            invalid(format!(
+                "Frame {} is beyond the last drop-frame timecode",
+                frames
+            ))
+        })?;
+    }
+    let ff = frames % base;
+    let seconds = frames / base;
+    Ok(format!(
+        "{:02}:{:02}:{:02}{}{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        if drop_frame { ';' } else { ':' },
+        ff
+    ))
+}
+
+/// The frame number of `timecode` at `fps`; a `;` or `.` before the frames
+/// marks it drop-frame.
+pub fn timecode_to_frames(timecode: &str, fps: f64) -> Result<u64, CalcError> {
+    let bad = || {
+        invalid(format!(
+            "Invalid timecode {:?}, expected HH:MM:SS:FF",
+            timecode
+        ))
+    };
+    let text = timecode.trim();
+    let drop_frame = text.contains([';', '.']);
+    let fields: Vec<u64> = text
+        .split([':', ';', '.'])
+        .map(|field| {
+            if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(bad());
+            }
+            // u32 keeps the frame arithmetic below from overflowing.
+            field.parse::<u32>().map(u64::from).map_err(|_| bad())
+        })
+        .collect::<Result<_, _>>()?;
+    let [hh, mm, ss, ff] = fields[..] else {
+        return Err(bad());
+    };
+    let (base, dropped) = timebase(fps, drop_frame)?;
+    if mm >= 60 || ss >= 60 || ff >= base {
+        return Err(invalid(format!(
+            "Timecode {:?} is out of range at {} fps",
+            timecode, fps
+        )));
+    }
+    if dropped > 0 && ss == 0 && ff < dropped && mm % 10 != 0 {
+        return Err(invalid(format!(
+            "Timecode {:?} is a dropped frame number",
+            timecode
+        )));
+    }
+    let minutes = hh * 60 + mm;
+    let frames = (minutes * 60 + ss) * base + ff;
+    Ok(frames - dropped * (minutes - minutes / 10))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = aspect_ratio)]
+pub fn aspect_ratio_js(width: u32, height: u32) -> Result<String, CalcErrorJs> {
+    aspect_ratio(width, height).map_err(CalcErrorJs::from)
+}
+
+/// `{ width, height }`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = resolution_for_aspect)]
+pub fn resolution_for_aspect_js(
+    ratio: &str,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<JsValue, CalcErrorJs> {
+    let resolution = resolution_for_aspect(ratio, width, height).map_err(CalcErrorJs::from)?;
+    Ok(serde_wasm_bindgen::to_value(&resolution).unwrap_or(JsValue::NULL))
+}
+
+/// `quality` is `"low"`, `"medium"` or `"high"`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = estimate_bitrate)]
+pub fn estimate_bitrate_js(
+    width: u32,
+    height: u32,
+    fps: f64,
+    quality: &str,
+) -> Result<f64, CalcErrorJs> {
+    let quality = VideoQuality::parse(quality).map_err(CalcErrorJs::from)?;
+    estimate_bitrate(width, height, fps, quality).map_err(|e| CalcErrorJs::new(e, Some(fps)))
+}
+
+/// `frames` is a JS number; frame counts stay exact far beyond any video.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = frames_to_timecode)]
+pub fn frames_to_timecode_js(
+    frames: f64,
+    fps: f64,
+    drop_frame: bool,
+) -> Result<String, CalcErrorJs> {
+    if !frames.is_finite() || frames < 0.0 || frames.fract() != 0.0 || frames > MAX_SAFE_INTEGER {
+        return Err(CalcErrorJs::new(
+            invalid(format!(
+                "Frame count must be a whole number up to 2^53 - 1, got {}",
+                frames
+            )),
+            Some(frames),
+        ));
+    }
+    frames_to_timecode(frames as u64, fps, drop_frame).map_err(|e| CalcErrorJs::new(e, Some(fps)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = timecode_to_frames)]
+pub fn timecode_to_frames_js(timecode: &str, fps: f64) -> Result<f64, CalcErrorJs> {
+    timecode_to_frames(timecode, fps)
+        .map(|frames| frames as f64)
+        .map_err(|e| CalcErrorJs::new(e, Some(fps)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aspect_ratio() {
+        assert_eq!(aspect_ratio(1920, 1080).unwrap(), "16:9");
+        assert_eq!(aspect_ratio(1280, 1024).unwrap(), "5:4");
+        assert_eq!(aspect_ratio(3440, 1440).unwrap(), "43:18");
+        assert!(aspect_ratio(0, 1080).is_err());
+
+        let uhd = resolution_for_aspect("16:9", Some(3840), None).unwrap();
+        assert_eq!(
+            uhd,
+            Resolution {
+                width: 3840,
+                height: 2160
+            }
+        );
+        let scope = resolution_for_aspect("2.39:1", None, Some(858)).unwrap();
+        assert_eq!(scope.width, 2051);
+        assert!(resolution_for_aspect("16:9", Some(1920), Some(1080)).is_err());
+        assert!(resolution_for_aspect("16:9", None, None).is_err());
+        assert!(resolution_for_aspect("16x9", Some(1920), None).is_err());
+        assert!(resolution_for_aspect("1000:1", Some(1), None).is_err());
+    }
+
+    #[test]
+    fn test_estimate_bitrate() {
+        // 1080p30 at 0.1 bits per pixel is about 6.2 Mbit/s.
+        let kbps = estimate_bitrate(1920, 1080, 30.0, VideoQuality::Medium).unwrap();
+        assert!((kbps - 6220.8).abs() < 1e-9);
+        let low = estimate_bitrate(1920, 1080, 30.0, VideoQuality::Low).unwrap();
+        assert!((low * 2.0 - kbps).abs() < 1e-9);
+        assert_eq!(VideoQuality::parse(" High"), Ok(VideoQuality::High));
+        assert!(VideoQuality::parse("ultra").is_err());
+        assert!(estimate_bitrate(1920, 1080, 0.0, VideoQuality::High).is_err());
+    }
+
+    #[test]
+    fn test_timecode() {
+        assert_eq!(frames_to_timecode(0, 25.0, false).unwrap(), "00:00:00:00");
+        assert_eq!(
+            frames_to_timecode(90_000, 25.0, false).unwrap(),
+            "01:00:00:00"
+        );
+        assert_eq!(timecode_to_frames("01:00:00:00", 25.0), Ok(90_000));
+        assert_eq!(timecode_to_frames("00:00:01:23", 24.0), Ok(47));
+        // 23.976 counts at 24 without dropping.
+        assert_eq!(
+            frames_to_timecode(1439, 23.976, false).unwrap(),
+            "00:00:59:23"
+        );
+
+        // Drop-frame at 29.97: minute 1 starts at frame 2.
+        assert_eq!(
+            frames_to_timecode(1799, 29.97, true).unwrap(),
+            "00:00:59;29"
+        );
+        assert_eq!(
+            frames_to_timecode(1800, 29.97, true).unwrap(),
+            "00:01:00;02"
+        );
+        assert_eq!(
+            frames_to_timecode(17_982, 29.97, true).unwrap(),
+            "00:10:00;00"
+        );
+        // An hour of 29.97 fps is 107,892 frames.
+        assert_eq!(
+            frames_to_timecode(107_892, 29.97, true).unwrap(),
+            "01:00:00;00"
+        );
+        assert_eq!(
+            frames_to_timecode(3600, 59.94, true).unwrap(),
+            "00:01:00;04"
+        );
+        for frames in [0, 1, 1799, 1800, 1801, 17_981, 17_982, 123_456] {
+            let tc = frames_to_timecode(frames, 29.97, true).unwrap();
+            assert_eq!(timecode_to_frames(&tc, 29.97), Ok(frames), "{}", tc);
+        }
+        assert_eq!(timecode_to_frames("00:01:00.02", 29.97), Ok(1800));
+
+        assert!(timecode_to_frames("00:01:00;00", 29.97).is_err());
+        assert!(timecode_to_frames("00:00:00:25", 25.0).is_err());
+        assert!(timecode_to_frames("00:00:00", 25.0).is_err());
+        assert!(timecode_to_frames("99999999999:00:00:00", 25.0).is_err());
+        assert!(timecode_to_frames("00:00:-1:00", 25.0).is_err());
+        assert!(frames_to_timecode(0, 25.0, true).is_err());
+        assert!(frames_to_timecode(u64::MAX, 29.97, true).is_err());
+        assert!(frames_to_timecode(u64::MAX, 29.97, false).is_ok());
+        assert!(frames_to_timecode(0, 30.0, true).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Wind chill, heat index and dew point.
+//!
+//! Temperatures and wind speeds are [`Quantity`] values in any unit
+//! (`-5 degC`, `23 degF`, `20 km/h`, `10 mph`), and each result comes back
+//! in the unit of the temperature given.
+//!
+//! - Wind chill is the US National Weather Service / Environment Canada
+//!   formula (2001). It is only defined at or below 50 °F (10 °C) with wind
+//!   of at least 3 mph (4.8 km/h); outside that the air temperature is
+//!   returned, as weather services report it.
+//! - Heat index is the NWS algorithm: Steadman's simple formula for mild
+//!   conditions and the Rothfusz regression with its low- and high-humidity
+//!   adjustments from 80 °F up.
+//! - Dew point is the Magnus formula with the Sonntag (1990) constants,
+//!   within about 0.35 °C from -45 °C to 60 °C.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::units::Quantity;
+use crate::CalcError;
+#[cfg(feature = "wasm")]
+use crate::CalcErrorJs;
+
+const MAGNUS_A: f64 = 17.62;
+const MAGNUS_B: f64 = 243.12;
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidArgument(message.into())
+}
+
+// `quantity` as a number of `unit`, naming `what` if the dimension is wrong.
+fn value_in(what: &str, quantity: &Quantity, unit: &str) -> Result<f64, CalcError> {
+    let value = quantity
+        .convert_to(unit)
+        .map_err(|e| match e {
+            CalcError::InvalidArgument(message) => invalid(format!("{}: {}", what, message)),
+            e => e,
+        })?
+        .value();
+    if !value.is_finite() {
+        return Err(invalid(format!(
+            "{} must be finite, got {}",
+            what, quantity
+        )));
+    }
+    Ok(value)
+}
+
+fn temperature_in(temperature: &Quantity, unit: &str) -> Result<f64, CalcError> {
+    let kelvin = value_in("Temperature", temperature, "K")?;
+    if kelvin <= 0.0 {
+        return Err(invalid(format!(
+            "Temperature must be above absolute zero, got {}",
+            temperature
+        )));
+    }
+    value_in("Temperature", temperature, unit)
+}
+
+fn relative_humidity(percent: f64) -> Result<f64, CalcError> {
+    if !percent.is_finite() || percent <= 0.0 || percent > 100.0 {
+        return Err(invalid(format!(
+            "Relative humidity must be over 0 and at most 100%, got {}",
+            percent
+        )));
+    }
+    Ok(percent)
+}
+
+// `value` in `unit`, converted to the unit of `like`.
+fn like(value: f64, unit: &str, like: &Quantity) -> Result<Quantity, CalcError> {
+    Quantity::new(value, unit)?.convert_to(&like.unit())
+}
+
+/// How cold `temperature` feels in wind at `wind_speed`.
+pub fn wind_chill(temperature: &Quantity, wind_speed: &Quantity) -> Result<Quantity, CalcError> {
+    let t = temperature_in(temperature, "degF")?;
+    let v = value_in("Wind speed", wind_speed, "mph")?;
+    if v < 0.0 {
+        return Err(invalid(format!(
+            "Wind speed must not be negative, got {}",
+            wind_speed
+        )));
+    }
+    if t > 50.0 || v < 3.0 {
+        return Ok(temperature.clone());
+    }
+    let v16 = v.powf(0.16);
+    like(
+        35.74 + 0.6215 * t - 35.75 * v16 + 0.4275 * t * v16,
+        "degF",
+        temperature,
+    )
+}
+
+/// How hot `temperature` feels at `humidity` percent relative humidity.
+pub fn heat_index(temperature: &Quantity, humidity: f64) -> Result<Quantity, CalcError> {
+    let t = temperature_in(temperature, "degF")?;
+    let rh = relative_humidity(humidity)?;
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let index = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+            - 0.224_755_41 * t * rh
+            - 6.837_83e-3 * t * t
+            - 5.481_717e-2 * rh * rh
+            + 1.228_74e-3 * t * t * rh
+            + 8.5282e-4 * t * rh * rh
+            - 1.99e-6 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            hi += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+        }
+        hi
+    };
+    like(index, "degF", temperature)
+}
+
+/// The temperature at which air at `temperature` and `humidity` percent
+/// relative humidity would be saturated.
+pub fn dew_point(temperature: &Quantity, humidity: f64) -> Result<Quantity, CalcError> {
+    let t = temperature_in(temperature, "degC")?;
+    let rh = relative_humidity(humidity)?;
+    let gamma = (rh / 100.0).ln() + MAGNUS_A * t / (MAGNUS_B + t);
+    like(MAGNUS_B * gamma / (MAGNUS_A - gamma), "degC", temperature)
+}
+
+/// Both arguments are quantities as text: `wind_chill("-10 degC", "30
+/// km/h")`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = wind_chill)]
+pub fn wind_chill_js(temperature: &str, wind_speed: &str) -> Result<Quantity, CalcErrorJs> {
+    let temperature = Quantity::parse(temperature).map_err(CalcErrorJs::from)?;
+    let wind_speed = Quantity::parse(wind_speed).map_err(CalcErrorJs::from)?;
+    wind_chill(&temperature, &wind_speed).map_err(CalcErrorJs::from)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = heat_index)]
+pub fn heat_index_js(temperature: &str, humidity: f64) -> Result<Quantity, CalcErrorJs> {
+    let temperature = Quantity::parse(temperature).map_err(CalcErrorJs::from)?;
+    heat_index(&temperature, humidity).map_err(|e| CalcErrorJs::new(e, Some(humidity)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = dew_point)]
+pub fn dew_point_js(temperature: &str, humidity: f64) -> Result<Quantity, CalcErrorJs> {
+    let temperature = Quantity::parse(temperature).map_err(CalcErrorJs::from)?;
+    dew_point(&temperature, humidity).map_err(|e| CalcErrorJs::new(e, Some(humidity)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(text: &str) -> Quantity {
+        Quantity::parse(text).unwrap()
+    }
+
+    fn near(quantity: &Quantity, unit: &str, expected: f64, tolerance: f64) -> bool {
+        quantity.unit() == unit && (quantity.value() - expected).abs() <= tolerance
+    }
+
+    #[test]
+    fn test_wind_chill() {
+        // NWS chart: 0 degF in a 15 mph wind feels like -19 degF.
+        let chill = wind_chill(&q("0 degF"), &q("15 mph")).unwrap();
+        assert!(near(&chill, "degF", -19.0, 0.5), "{}", chill);
+        // Environment Canada: -20 degC at 30 km/h feels like -33 degC.
+        let chill = wind_chill(&q("-20 degC"), &q("30 km/h")).unwrap();
+        assert!(near(&chill, "degC", -32.6, 0.1), "{}", chill);
+        // Outside the formula's range, the air temperature.
+        assert_eq!(wind_chill(&q("15 degC"), &q("30 km/h")), Ok(q("15 degC")));
+        assert_eq!(wind_chill(&q("0 degF"), &q("2 mph")), Ok(q("0 degF")));
+        assert!(wind_chill(&q("0 degF"), &q("-5 mph")).is_err());
+        assert!(wind_chill(&q("0 degF"), &q("5 kg")).is_err());
+        assert!(wind_chill(&q("-500 degC"), &q("5 mph")).is_err());
+    }
+
+    #[test]
+    fn test_heat_index() {
+        // NWS table: 90 degF at 60% feels like 100 degF.
+        let hi = heat_index(&q("90 degF"), 60.0).unwrap();
+        assert!(near(&hi, "degF", 100.0, 0.5), "{}", hi);
+        let hi = heat_index(&q("32 degC"), 70.0).unwrap();
+        assert!(near(&hi, "degC", 40.6, 0.5), "{}", hi);
+        // Mild conditions use the simple formula.
+        let mild = heat_index(&q("70 degF"), 50.0).unwrap();
+        assert!(near(&mild, "degF", 69.1, 0.1), "{}", mild);
+        // The dry adjustment lowers it.
+        let dry = heat_index(&q("100 degF"), 10.0).unwrap();
+        assert!(near(&dry, "degF", 94.1, 0.1), "{}", dry);
+        assert!(heat_index(&q("90 degF"), 0.0).is_err());
+        assert!(heat_index(&q("90 degF"), 101.0).is_err());
+    }
+
+    #[test]
+    fn test_dew_point() {
+        let dew = dew_point(&q("25 degC"), 60.0).unwrap();
+        assert!(near(&dew, "degC", 16.7, 0.1), "{}", dew);
+        // Saturated air is at its dew point.
+        let saturated = dew_point(&q("293.15 K"), 100.0).unwrap();
+        assert!(near(&saturated, "K", 293.15, 1e-9), "{}", saturated);
+        let dew = dew_point(&q("77 degF"), 60.0).unwrap();
+        assert!(near(&dew, "degF", 62.1, 0.2), "{}", dew);
+        assert!(dew_point(&q("25 degC"), -1.0).is_err());
+    }
+}
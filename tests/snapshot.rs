@@ -0,0 +1,50 @@
+//! Golden-file tests over `Calculator::canonical_state_string()`.
+//! Run with: cargo test --test snapshot
+//! Set UPDATE_GOLDEN=1 to rewrite the files in tests/golden/ after an
+//! intentional behavior change, then review the diff before committing.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::fs;
+use std::path::PathBuf;
+
+use wasm_calc::Calculator;
+
+fn assert_golden(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).expect("failed to write golden file");
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden file {}: {}", path.display(), e));
+    assert_eq!(actual, expected, "state snapshot {} changed", name);
+}
+
+#[test]
+fn golden_basic_session() {
+    let mut calc = Calculator::new();
+    calc.set_value(100.0);
+    calc.add(50.0);
+    calc.subtract(30.0);
+    calc.divide(4.0).unwrap();
+    calc.multiply(2.0);
+    calc.memory_store();
+    calc.sqrt().unwrap();
+    assert_golden("basic_session.txt", &calc.canonical_state_string());
+}
+
+#[test]
+fn golden_error_recovery() {
+    let mut calc = Calculator::new();
+    calc.set_value(10.0);
+    let _ = calc.divide(0.0);
+    calc.set_value(-4.0);
+    let _ = calc.sqrt();
+    calc.add(0.1);
+    calc.add(0.2);
+    calc.memory_add();
+    assert_golden("error_recovery.txt", &calc.canonical_state_string());
+}
@@ -1,12 +1,13 @@
 //! Wasm-level tests for the #[wasm_bindgen]-exported surface.
 //! Run with: wasm-pack test --node
 //! These exercise the _js wrappers (exported as divide/sqrt/factorial),
-//! get_history serialization, and the real wasm32 From<CalcError> for JsValue.
+//! get_history serialization, and the structured CalcErrorJs they throw.
 
-#![cfg(target_arch = "wasm32")]
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
 
 use wasm_bindgen::JsValue;
 use wasm_bindgen_test::*;
+use wasm_calc::expr::{evaluate_formula_js, evaluate_js};
 use wasm_calc::{factorial_js, CalcError, Calculator};
 
 const EPSILON: f64 = 1e-10;
@@ -26,8 +27,9 @@ fn divide_js_by_zero_err_message() {
     let mut c = Calculator::new();
     c.set_value(1.0);
     let err = c.divide_js(0.0).expect_err("divide by zero must error");
-    // Exercises the real wasm32 From<CalcError> for JsValue conversion
-    assert_eq!(err.as_string().as_deref(), Some(CalcError::DivisionByZero.as_str()));
+    assert_eq!(err.code(), "DIVISION_BY_ZERO");
+    assert_eq!(err.message(), CalcError::DivisionByZero.as_str());
+    assert_eq!(err.operand(), Some(0.0));
 }
 
 // ---- sqrt (sqrt_js) ----
@@ -45,7 +47,9 @@ fn sqrt_js_negative_err_message() {
     let mut c = Calculator::new();
     c.set_value(-4.0);
     let err = c.sqrt_js().expect_err("negative sqrt must error");
-    assert_eq!(err.as_string().as_deref(), Some(CalcError::NegativeSqrt.as_str()));
+    assert_eq!(err.code(), "NEGATIVE_SQRT");
+    assert_eq!(err.message(), CalcError::NegativeSqrt.as_str());
+    assert_eq!(err.operand(), Some(-4.0));
 }
 
 // ---- factorial (factorial_js) ----
@@ -59,7 +63,16 @@ fn factorial_js_ok() {
 #[wasm_bindgen_test]
 fn factorial_js_overflow_err_message() {
     let err = factorial_js(21.0).expect_err("factorial(21) must overflow");
-    assert_eq!(err.as_string().as_deref(), Some(CalcError::Overflow.as_str()));
+    assert_eq!(err.message(), CalcError::Overflow.as_str());
+}
+
+#[wasm_bindgen_test]
+fn calc_error_into_js_value_is_structured_object() {
+    // The generic From<CalcError> for JsValue path yields the same class.
+    let v: JsValue = CalcError::DivisionByZero.into();
+    assert!(v.is_object());
+    let code = js_sys::Reflect::get(&v, &JsValue::from_str("code")).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("DIVISION_BY_ZERO"));
 }
 
 // ---- get_history (serde_wasm_bindgen call site) ----
@@ -96,7 +109,7 @@ fn factorial_js_rejects_u32_wrap_window() {
     // It is a well-formed integer, just far too large: the saturating cast
     // clamps it to u32::MAX so the core reports Overflow, not InvalidInput.
     let err = factorial_js(4294967301.0).expect_err("2^32+5 must be rejected");
-    assert_eq!(err.as_string().as_deref(), Some(CalcError::Overflow.as_str()));
+    assert_eq!(err.code(), CalcError::Overflow.code());
 }
 
 #[wasm_bindgen_test]
@@ -104,12 +117,7 @@ fn factorial_js_rejects_fractional_and_negative() {
     // Malformed inputs report InvalidInput, never a misleading "overflow".
     for bad in [3.5_f64, -1.0, f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
         let err = factorial_js(bad).expect_err("malformed input must be rejected");
-        assert_eq!(
-            err.as_string().as_deref(),
-            Some(CalcError::InvalidInput.as_str()),
-            "wrong error for {}",
-            bad
-        );
+        assert_eq!(err.code(), CalcError::InvalidInput.code(), "wrong error for {}", bad);
     }
 }
 
@@ -119,3 +127,41 @@ fn factorial_js_returns_number_semantics() {
     let v = factorial_js(20.0).expect("factorial(20) should succeed");
     assert_eq!(v, 2_432_902_008_176_640_000.0);
 }
+
+// ---- expression evaluator ----
+
+#[wasm_bindgen_test]
+fn evaluate_js_syntax_error_has_position() {
+    let err = evaluate_js("1 + * 2").expect_err("malformed expression must error");
+    assert_eq!(err.code(), "SYNTAX_ERROR");
+    assert_eq!(err.position(), Some(4));
+}
+
+#[wasm_bindgen_test]
+fn evaluate_formula_js_reads_cells_object() {
+    let cells = js_sys::Object::new();
+    js_sys::Reflect::set(&cells, &JsValue::from_str("B"), &JsValue::from_f64(2.0)).unwrap();
+    let v = evaluate_formula_js("=SUM(1,2,3)*B", cells.into()).expect("formula should evaluate");
+    assert_eq!(v, 12.0);
+}
+
+// ---- listeners ----
+
+#[wasm_bindgen_test]
+fn set_on_change_and_on_error_call_js_functions() {
+    let log = js_sys::Array::new();
+    let push = js_sys::Function::new_with_args("log, x", "log.push(x)");
+    let on_change = push.bind1(&JsValue::NULL, &log);
+    let on_error = push.bind1(&JsValue::NULL, &log);
+
+    let mut calc = Calculator::new();
+    calc.set_on_change(&on_change);
+    calc.set_on_error(&on_error);
+    calc.add(5.0);
+    let _ = calc.divide_js(0.0);
+
+    assert_eq!(log.length(), 2);
+    assert_eq!(log.get(0).as_f64(), Some(5.0));
+    let code = js_sys::Reflect::get(&log.get(1), &JsValue::from_str("code")).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("DIVISION_BY_ZERO"));
+}